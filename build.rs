@@ -5,6 +5,7 @@ fn main() {
     }
 
     linker_be_nice();
+    badge_config();
     println!("cargo:rustc-link-arg=-Tdefmt.x");
     println!("cargo:rustc-link-arg=-Tlinkall.x");
 }
@@ -51,3 +52,154 @@ fn linker_be_nice() {
         std::env::current_exe().unwrap().display()
     );
 }
+
+// ── badge.toml → typed consts ───────────────────────────────────────────────
+
+/// Read `badge.toml` (or the file named by `BADGE_CONFIG`) and emit
+/// `$OUT_DIR/badge_config.rs`, which `src/config.rs` includes.
+///
+/// Missing keys fall back to the defaults below; a missing file is not an error.
+fn badge_config() {
+    let path = std::env::var("BADGE_CONFIG").map_or_else(
+        |_| std::path::Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("badge.toml"),
+        std::path::PathBuf::from,
+    );
+    println!("cargo:rerun-if-env-changed=BADGE_CONFIG");
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    let table: toml::Table = match std::fs::read_to_string(&path) {
+        Ok(text) => text
+            .parse()
+            .unwrap_or_else(|e| panic!("{}: invalid TOML: {e}", path.display())),
+        Err(_) => toml::Table::new(),
+    };
+
+    let section = |name: &str| {
+        table
+            .get(name)
+            .and_then(toml::Value::as_table)
+            .cloned()
+            .unwrap_or_default()
+    };
+    let badge = section("badge");
+    let theme = section("theme");
+    let leds = section("leds");
+    let apps = section("apps");
+    let ota = section("ota");
+    let leaderboard = section("leaderboard");
+    let conference = section("conference");
+
+    let string = |t: &toml::Table, key: &str, default: &str| -> String {
+        match t.get(key) {
+            Some(toml::Value::String(s)) => s.clone(),
+            Some(other) => panic!("badge.toml: `{key}` must be a string, got {other}"),
+            None => default.to_owned(),
+        }
+    };
+
+    let name = string(&badge, "name", "Anonymous Alpaca");
+    let contact = string(&badge, "contact", "");
+    let background = match string(&theme, "background", "1020A0").as_str() {
+        "rainbow" => "Background::Rainbow".to_owned(),
+        "retrofuture" => "Background::Retrofuture".to_owned(),
+        "hearts" => "Background::Hearts".to_owned(),
+        hex => format!("Background::Solid({})", srgb(hex, "theme.background")),
+    };
+    let foreground = srgb(&string(&theme, "foreground", "FFFFFF"), "theme.foreground");
+    let led_effect = match string(&leds, "effect", "off").as_str() {
+        "off" => "LedEffect::Off".to_owned(),
+        "heartbeat" => "LedEffect::Heartbeat".to_owned(),
+        "rainbow" => "LedEffect::Rainbow".to_owned(),
+        hex => format!("LedEffect::Solid({})", srgb(hex, "leds.effect")),
+    };
+    let brightness = match leds.get("brightness") {
+        Some(toml::Value::Integer(b)) => u8::try_from(*b)
+            .unwrap_or_else(|_| panic!("badge.toml: `leds.brightness` must be 0..=255, got {b}")),
+        Some(other) => panic!("badge.toml: `leds.brightness` must be an integer, got {other}"),
+        None => 255,
+    };
+    let enabled: Vec<String> = match apps.get("enabled") {
+        Some(toml::Value::Array(list)) => list
+            .iter()
+            .map(|v| match v {
+                toml::Value::String(s) => format!("{s:?}"),
+                other => panic!("badge.toml: `apps.enabled` entries must be strings, got {other}"),
+            })
+            .collect(),
+        Some(other) => panic!("badge.toml: `apps.enabled` must be an array, got {other}"),
+        None => Vec::new(),
+    };
+    let ota_public_key = match ota.get("public_key") {
+        Some(toml::Value::String(hex)) => format!("Some({})", key(hex, "ota.public_key")),
+        Some(other) => panic!("badge.toml: `ota.public_key` must be a string, got {other}"),
+        None => "None".to_owned(),
+    };
+    let conference_key = match conference.get("public_key") {
+        Some(toml::Value::String(hex)) => format!("Some({})", key(hex, "conference.public_key")),
+        Some(other) => panic!("badge.toml: `conference.public_key` must be a string, got {other}"),
+        None => "None".to_owned(),
+    };
+    let leaderboard_url = match leaderboard.get("url") {
+        Some(toml::Value::String(url)) => format!("Some({:?})", url.trim_end_matches('/')),
+        Some(other) => panic!("badge.toml: `leaderboard.url` must be a string, got {other}"),
+        None => "None".to_owned(),
+    };
+
+    let out = format!(
+        "/// Badge owner's name (`badge.name`).\n\
+         pub const NAME: &str = {name:?};\n\
+         /// How to reach the owner, shared on contact cards (`badge.contact`).\n\
+         pub const CONTACT: &str = {contact:?};\n\
+         /// Display colour theme (`[theme]`).\n\
+         pub const THEME: Theme = Theme {{ background: {background}, foreground: {foreground} }};\n\
+         /// Idle LED effect (`leds.effect`).\n\
+         pub const LED_EFFECT: LedEffect = {led_effect};\n\
+         /// Default LED brightness, 0–255 (`leds.brightness`).\n\
+         pub const LED_BRIGHTNESS: u8 = {brightness};\n\
+         /// Apps to include in launchers and menus (`apps.enabled`).\n\
+         pub const ENABLED_APPS: &[&str] = &[{}];\n\
+         /// Ed25519 key firmware updates must be signed with (`ota.public_key`).\n\
+         pub const OTA_PUBLIC_KEY: Option<[u8; 32]> = {ota_public_key};\n\
+         /// High-score server (`leaderboard.url`).\n\
+         pub const LEADERBOARD_URL: Option<&str> = {leaderboard_url};\n\
+         /// Ed25519 key the organisers sign announcements with (`conference.public_key`).\n\
+         pub const CONFERENCE_KEY: Option<[u8; 32]> = {conference_key};\n",
+        enabled.join(", ")
+    );
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("badge_config.rs"), out).unwrap();
+}
+
+/// Validate a 6-char hex RGB string and render it as an `Srgb::new(..)` expression.
+fn srgb(hex: &str, key: &str) -> String {
+    let byte = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|s| u8::from_str_radix(s, 16).ok())
+            .unwrap_or_else(|| {
+                panic!("badge.toml: `{key}` must be a 6-char hex RGB string, got {hex:?}")
+            })
+    };
+    if hex.len() != 6 {
+        panic!("badge.toml: `{key}` must be a 6-char hex RGB string, got {hex:?}");
+    }
+    format!("Srgb::new({}, {}, {})", byte(0), byte(2), byte(4))
+}
+
+/// Validate a 64-char hex Ed25519 public key and render it as a byte array.
+fn key(hex: &str, key: &str) -> String {
+    let bytes: Option<Vec<String>> = (hex.len() == 64)
+        .then(|| {
+            (0..32)
+                .map(|i| {
+                    hex.get(i * 2..i * 2 + 2)
+                        .and_then(|s| u8::from_str_radix(s, 16).ok())
+                        .map(|b| format!("{b:#04x}"))
+                })
+                .collect()
+        })
+        .flatten();
+    let bytes = bytes.unwrap_or_else(|| {
+        panic!("badge.toml: `{key}` must be a 64-char hex Ed25519 public key, got {hex:?}")
+    });
+    format!("[{}]", bytes.join(", "))
+}
@@ -0,0 +1,146 @@
+//! Conference announcements that can't be spoofed: they're broadcast over
+//! ESP-NOW in signed envelopes, and badges show only the ones signed with
+//! `conference.public_key` from `badge.toml`.
+//!
+//! - Build with `ANNOUNCE_SEED=<64 hex chars>` to be the organisers' badge:
+//!   A broadcasts the next announcement, and the public key to put in
+//!   `badge.toml` is logged at boot
+//! - Without `conference.public_key` set, announcements from any key are
+//!   shown, marked unverified
+//!
+//! Needs the `signed` and `esp-now` features: `cargo run --release --features signed,esp-now --example announce`
+
+#![no_std]
+#![no_main]
+
+use defmt::{info, warn};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_time::Duration;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use signed::{ANNOUNCEMENTS, KeyPair, Seed, SignedError, Signer, Verifier};
+use wifi::Wifi;
+
+extern crate alloc;
+use alloc::format;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+/// First bytes of every announcement frame.
+const MAGIC: [u8; 2] = *b"DA";
+const BROADCAST: [u8; 6] = [0xff; 6];
+/// Most an ESP-NOW frame carries.
+const FRAME_LEN: usize = 250;
+const SEED: Option<&str> = option_env!("ANNOUNCE_SEED");
+const NEWS: [&str; 4] = [
+    "Doors are open",
+    "CTF starts in 15 min",
+    "Talk moved to Track 2",
+    "Party at the main hall",
+];
+
+fn seed(hex: &str) -> Option<[u8; 32]> {
+    let mut seed = [0; 32];
+    for (index, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(index * 2..index * 2 + 2)?, 16).ok()?;
+    }
+    (hex.len() == 64).then_some(seed)
+}
+
+fn show(display: &mut Display<'static>, text: &str, note: &str, color: Rgb565) {
+    let _ = display.clear(Rgb565::BLACK);
+    let style = MonoTextStyle::new(&FONT_10X20, color);
+    let _ = Text::with_alignment(text, Point::new(160, 80), style, Alignment::Center).draw(display);
+    let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+    let _ = Text::with_alignment(note, Point::new(160, 150), small, Alignment::Center).draw(display);
+}
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let mut backlight: Backlight = resources.backlight.into();
+    backlight.on();
+    let mut buttons: Buttons = resources.buttons.into();
+    let mut motor: Vibration = resources.vibra.into();
+
+    let (mut wifi, _device, mut esp_now) = Wifi::with_esp_now(resources.wifi).unwrap();
+    wifi.start().await.unwrap();
+
+    let mut signer = SEED.and_then(seed).map(|seed| {
+        let key = KeyPair::from_seed(Seed::new(seed));
+        info!("organisers' public key: {=[u8]:02x}", *key.pk);
+        Signer::new(key, ANNOUNCEMENTS)
+    });
+    let trusted = Verifier::organisers(ANNOUNCEMENTS);
+    let verified = trusted.is_some();
+    let mut verifier = trusted.unwrap_or(Verifier::new(ANNOUNCEMENTS));
+    let role = if signer.is_some() { "A: announce" } else { "Listening" };
+    show(display, "No news yet", role, Rgb565::WHITE);
+
+    let mut next = 0;
+    let mut rejected = 0u32;
+    loop {
+        match select(esp_now.receive_async(), buttons.a.wait_for_press()).await {
+            Either::First(received) => {
+                let Some(envelope) = received.data().strip_prefix(&MAGIC) else {
+                    continue;
+                };
+                match verifier.open(envelope) {
+                    Ok(message) => {
+                        let text = core::str::from_utf8(message.payload).unwrap_or("?");
+                        info!("announcement: {}", message);
+                        let note = if verified {
+                            format!("from the organisers - {rejected} fakes dropped")
+                        } else {
+                            format!("unverified: {:02x?}", &message.signer[..4])
+                        };
+                        let color = if verified { Rgb565::GREEN } else { Rgb565::YELLOW };
+                        show(display, text, &note, color);
+                        motor.pulse(Duration::from_millis(100)).await;
+                    }
+                    Err(SignedError::Replayed) => {}
+                    Err(err) => {
+                        rejected += 1;
+                        warn!("dropped announcement: {}", err);
+                    }
+                }
+            }
+            Either::Second(()) => {
+                let Some(signer) = &mut signer else {
+                    continue;
+                };
+                let mut frame = [0; FRAME_LEN];
+                frame[..2].copy_from_slice(&MAGIC);
+                let news = NEWS[next % NEWS.len()];
+                next += 1;
+                let Ok(envelope) = signer.sign(news.as_bytes(), &mut frame[2..]) else {
+                    continue;
+                };
+                let len = 2 + envelope.len();
+                if let Err(err) = esp_now.send_async(&BROADCAST, &frame[..len]).await {
+                    warn!("send failed: {}", err);
+                }
+                show(display, news, "sent", Rgb565::CYAN);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Shows the images in the `assets` partition, read in place from flash
+//! instead of built into the app with `include_bytes!`.
+//!
+//! - Left/right steps through the assets; BMP files are drawn centred,
+//!   anything else is listed with its kind and size
+//!
+//! Needs the `assets` feature, the partition table in `partitions.csv` and a
+//! bundle packed with the script in the `assets` module docs:
+//! ```sh
+//! python pack.py assets.bin examples/assets/image.bmp logo=examples/assets/skrolli.bmp
+//! espflash write-bin 0x3a0000 assets.bin
+//! cargo run --release --features assets --example assets -- --partition-table partitions.csv
+//! ```
+
+#![no_std]
+#![no_main]
+
+use assets::{Asset, AssetKind, Assets};
+use defmt::{info, warn};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embedded_graphics::{
+    image::Image,
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use tinybmp::Bmp;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+fn draw(display: &mut Display<'_>, asset: &Asset, index: usize, count: usize) {
+    display.clear(Rgb565::BLACK).unwrap();
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let caption = alloc::format!(
+        "{}/{} {} ({:?}, {} bytes)",
+        index + 1,
+        count,
+        asset.name,
+        asset.kind,
+        asset.data.len()
+    );
+
+    if asset.kind == AssetKind::Bmp {
+        match Bmp::<Rgb565>::from_slice(asset.data) {
+            Ok(bmp) => {
+                let size = bmp.size();
+                let top_left = Point::new((320 - size.width as i32) / 2, (170 - size.height as i32) / 2);
+                Image::new(&bmp, top_left).draw(display).unwrap();
+            }
+            Err(err) => warn!("{}: not a BMP file: {}", asset.name, defmt::Debug2Format(&err)),
+        }
+    }
+    Text::with_alignment(&caption, Point::new(160, 165), style, Alignment::Center)
+        .draw(display)
+        .unwrap();
+}
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut display: Display = resources.display.into();
+    let mut backlight: Backlight = resources.backlight.into();
+    let mut buttons: Buttons = resources.buttons.into();
+    backlight.on();
+
+    let mut flash: FlashStorage = resources.flash.into();
+    let assets = Assets::new(&mut flash).expect("no asset bundle flashed");
+    for asset in assets.iter() {
+        info!("{} {} {} bytes", asset.name, asset.kind, asset.data.len());
+    }
+    if assets.is_empty() {
+        Text::with_alignment(
+            "The asset bundle is empty",
+            Point::new(160, 85),
+            MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE),
+            Alignment::Center,
+        )
+        .draw(&mut display)
+        .unwrap();
+    }
+
+    let count = assets.len().max(1);
+    let mut index = 0;
+    let mut events = buttons.events();
+    loop {
+        if let Some(asset) = assets.iter().nth(index) {
+            draw(&mut display, &asset, index, assets.len());
+        }
+        index = loop {
+            match events.next().await {
+                ButtonEvent::Pressed(Button::Right) => break (index + 1) % count,
+                ButtonEvent::Pressed(Button::Left) => break (index + count - 1) % count,
+                _ => {}
+            }
+        };
+    }
+}
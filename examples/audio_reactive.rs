@@ -0,0 +1,134 @@
+//! One microphone task feeding two consumers: the LED bars show the five
+//! spectrum bands, bass at the bottom, and the screen draws the same bands
+//! as columns with the background flashing on every beat.
+
+#![no_std]
+#![no_main]
+
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use esp_backtrace as _;
+use esp_hal::{dma::DmaDescriptor, timer::timg::TimerGroup};
+use esp_println as _;
+use microphone::{
+    DEFAULT_SAMPLE_RATE, MicProcessing, MicStream, Microphone, STREAM_RING_WORDS,
+    reactive::{AudioReactive, REACTIVE_BANDS},
+};
+use palette::Srgb;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const CHUNK: usize = 256;
+
+/// Colour of each band at full level, bass first.
+const BAND_COLORS: [Srgb<u8>; REACTIVE_BANDS] = [
+    Srgb::new(60, 0, 0),
+    Srgb::new(60, 30, 0),
+    Srgb::new(40, 60, 0),
+    Srgb::new(0, 40, 60),
+    Srgb::new(30, 0, 60),
+];
+
+/// How long the screen stays lit after a beat.
+const FLASH: Duration = Duration::from_millis(80);
+
+static AUDIO: AudioReactive = AudioReactive::new();
+
+#[embassy_executor::task]
+async fn audio_task(mut stream: MicStream<'static, 'static, CHUNK>) {
+    AUDIO.run(&mut stream).await
+}
+
+#[embassy_executor::task]
+async fn led_task(leds: &'static mut Leds<'static>) {
+    let mut frames = AUDIO.watch().unwrap();
+    loop {
+        let frame = frames.changed().await;
+        let mut colors = BAND_COLORS;
+        for (color, level) in colors.iter_mut().zip(frame.bands) {
+            *color = Srgb::new(
+                (f32::from(color.red) * level) as u8,
+                (f32::from(color.green) * level) as u8,
+                (f32::from(color.blue) * level) as u8,
+            );
+        }
+        leds.set_both_bars(&colors);
+        leds.update().await;
+    }
+}
+
+#[embassy_executor::task]
+async fn display_task(display: &'static mut Display<'static>) {
+    let mut frames = AUDIO.watch().unwrap();
+    let mut beats = AUDIO.beats().unwrap();
+    let column = u32::from(DISPLAY_WIDTH) / REACTIVE_BANDS as u32;
+    let height = u32::from(DISPLAY_HEIGHT);
+    let mut lit_until = embassy_time::Instant::now();
+    loop {
+        let frame = frames.changed().await;
+        // Time the flash from when the beat was heard, not when it got here.
+        if let Some((_, time)) = beats.try_next() {
+            lit_until = time.at + FLASH;
+        }
+        let background = if embassy_time::Instant::now() < lit_until {
+            Rgb565::CSS_DARK_SLATE_GRAY
+        } else {
+            Rgb565::BLACK
+        };
+        for (i, (level, color)) in frame.bands.iter().zip(BAND_COLORS).enumerate() {
+            let bar = (level * height as f32) as u32;
+            let x = (i as u32 * column) as i32;
+            let color = Rgb565::new(color.red >> 1, color.green, color.blue >> 1);
+            let _ = Rectangle::new(Point::new(x, 0), Size::new(column, height - bar))
+                .into_styled(PrimitiveStyle::with_fill(background))
+                .draw(display);
+            let _ = Rectangle::new(Point::new(x, (height - bar) as i32), Size::new(column, bar))
+                .into_styled(PrimitiveStyle::with_fill(color))
+                .draw(display);
+        }
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
+
+    let descriptors = mk_static!([DmaDescriptor; 8], [DmaDescriptor::EMPTY; 8]);
+    let mic = mk_static!(
+        Microphone<'static>,
+        Microphone::new(resources.mic, DEFAULT_SAMPLE_RATE, descriptors)
+    );
+    let ring = mk_static!([u32; STREAM_RING_WORDS], [0; STREAM_RING_WORDS]);
+    let stream = mic
+        .stream::<CHUNK>(ring)
+        .unwrap()
+        .with_processing(MicProcessing::new().with_dc_block());
+
+    spawner.must_spawn(audio_task(stream));
+    spawner.must_spawn(led_task(leds));
+    spawner.must_spawn(display_task(display));
+
+    loop {
+        Timer::after(Duration::from_secs(600)).await;
+    }
+}
@@ -1,4 +1,4 @@
-//! Toggles the display backlight on and off every second.
+//! Fades the display backlight in and out, then toggles it on and off.
 
 #![no_std]
 #![no_main]
@@ -21,15 +21,28 @@ esp_bootloader_esp_idf::esp_app_desc!();
 
 #[embassy_executor::task]
 async fn backlight_task(backlight: &'static mut Backlight) {
-    info!("Backlight task started — toggling every second");
+    info!("Backlight task started");
 
     loop {
+        info!("Fading out");
+        backlight.fade_out(Duration::from_secs(2)).await;
+        info!("Fading in");
+        backlight.fade_in(Duration::from_secs(2)).await;
+
+        for level in [32, 96, 160, 255] {
+            info!("Brightness: {}", level);
+            backlight.set_brightness(level);
+            Timer::after(Duration::from_millis(500)).await;
+        }
+
         backlight.toggle();
         info!(
             "Backlight: {}",
             if backlight.is_on() { "ON" } else { "OFF" }
         );
         Timer::after(Duration::from_secs(1)).await;
+        backlight.toggle();
+        Timer::after(Duration::from_secs(1)).await;
     }
 }
 
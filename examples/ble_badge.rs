@@ -0,0 +1,92 @@
+//! Serves the badge GATT service over Bluetooth LE. Connect with a phone
+//! (e.g. nRF Connect) to read the name from `badge.toml` and write a message,
+//! which appears on the screen with a buzz. The handle is the badge ID
+//! name, such as `brave-otter-42`, unless built with `BADGE_HANDLE=...`.
+//!
+//! Needs the `ble` feature: `cargo run --release --features ble --example ble_badge`
+
+#![no_std]
+#![no_main]
+
+use ble::{BadgeBle, BleEvent};
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::Duration;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+static BLE: BadgeBle = BadgeBle::new();
+
+#[embassy_executor::task]
+async fn ble_task(bt: BtResources<'static>) {
+    BLE.run(bt, config::NAME, option_env!("BADGE_HANDLE").unwrap_or(""))
+        .await
+}
+
+fn show(display: &mut Display<'static>, status: &str, message: &str) {
+    let _ = display.clear(Rgb565::BLACK);
+    let style = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let _ = Text::new(config::NAME, Point::new(8, 24), style).draw(display);
+    let dim = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_GRAY);
+    let _ = Text::new(status, Point::new(8, 60), dim).draw(display);
+    let _ = Rectangle::new(Point::new(0, 80), Size::new(u32::from(DISPLAY_WIDTH), 2))
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::CSS_DARK_CYAN))
+        .draw(display);
+    let yellow = MonoTextStyle::new(&FONT_10X20, Rgb565::YELLOW);
+    // 30 characters fit on a line.
+    for (line, chunk) in message.as_bytes().chunks(30).enumerate() {
+        if let Ok(text) = core::str::from_utf8(chunk) {
+            let _ = Text::new(text, Point::new(8, 110 + 24 * line as i32), yellow).draw(display);
+        }
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+    let mut motor: Vibration = resources.vibra.into();
+
+    let mut events = BLE.subscribe().unwrap();
+    BLE.set_app("ble_badge");
+    spawner.must_spawn(ble_task(resources.bt));
+
+    let mut status = "Advertising";
+    let mut message = ble::Text::<{ ble::MESSAGE_LEN }>::new();
+    loop {
+        show(display, status, &message);
+        match events.next().await {
+            BleEvent::Connected => status = "Connected",
+            BleEvent::Disconnected => status = "Advertising",
+            BleEvent::Message(text) => {
+                info!("message: {=str}", text.as_str());
+                message = text;
+                motor.pulse(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! "Who's around me": broadcasts this badge's handle and mood colour as a
+//! BLE beacon and lists the badges heard nearby, loudest first, with their
+//! mood and signal strength. Press A to change mood. The handle is the badge
+//! ID name, such as `brave-otter-42`, unless built with `BADGE_HANDLE=...`.
+//!
+//! Needs the `ble` feature: `cargo run --release --features ble --example ble_nearby`
+
+#![no_std]
+#![no_main]
+
+use ble::beacon::Beacon;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use palette::Srgb;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const MOODS: [Srgb<u8>; 6] = [
+    Srgb::new(255, 255, 255),
+    Srgb::new(255, 0, 0),
+    Srgb::new(255, 160, 0),
+    Srgb::new(0, 255, 0),
+    Srgb::new(0, 128, 255),
+    Srgb::new(200, 0, 255),
+];
+
+/// Rows that fit under the title.
+const ROWS: usize = 6;
+
+static BEACON: Beacon = Beacon::new();
+
+#[embassy_executor::task]
+async fn beacon_task(bt: BtResources<'static>) {
+    BEACON
+        .run(bt, option_env!("BADGE_HANDLE").unwrap_or(""))
+        .await
+}
+
+fn to_rgb565(color: Srgb<u8>) -> Rgb565 {
+    Rgb565::new(color.red >> 3, color.green >> 2, color.blue >> 3)
+}
+
+fn draw(display: &mut Display<'static>, mood: Srgb<u8>) {
+    let _ = display.clear(Rgb565::BLACK);
+    let white = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let gray = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_GRAY);
+    let _ = Rectangle::new(Point::new(0, 0), Size::new(u32::from(DISPLAY_WIDTH), 4))
+        .into_styled(PrimitiveStyle::with_fill(to_rgb565(mood)))
+        .draw(display);
+    let nearby = BEACON.nearby();
+    let count = nearby.iter().flatten().count();
+    let mut title = ble::Text::<24>::new();
+    let _ = core::fmt::write(&mut title, format_args!("{count} badges nearby"));
+    let _ = Text::new(&title, Point::new(8, 24), white).draw(display);
+
+    for (row, badge) in nearby.iter().flatten().take(ROWS).enumerate() {
+        let y = 46 + 22 * row as i32;
+        let _ = Rectangle::new(Point::new(8, y - 14), Size::new(14, 14))
+            .into_styled(PrimitiveStyle::with_fill(to_rgb565(badge.mood)))
+            .draw(display);
+        let _ = Text::new(&badge.handle, Point::new(30, y), white).draw(display);
+        let mut rssi = ble::Text::<8>::new();
+        let _ = core::fmt::write(&mut rssi, format_args!("{}", badge.rssi));
+        let _ = Text::new(&rssi, Point::new(260, y), gray).draw(display);
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+    let mut buttons: Buttons = resources.buttons.into();
+
+    spawner.must_spawn(beacon_task(resources.bt));
+
+    let mut mood = 0;
+    BEACON.set_mood(MOODS[mood]);
+    loop {
+        draw(display, MOODS[mood]);
+        let press = Buttons::debounce_press(&mut buttons.a);
+        if let Either::Second(()) = select(Timer::after(Duration::from_secs(1)), press).await {
+            mood = (mood + 1) % MOODS.len();
+            BEACON.set_mood(MOODS[mood]);
+        }
+    }
+}
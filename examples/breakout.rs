@@ -4,11 +4,14 @@
 //! - Ball bounces off walls, paddle, and bricks
 //! - LEDs flash when a brick is destroyed
 //! - Press A to launch the ball / restart after game over
+//! - The ten best scores are kept in flash and shown after each game
+//!
+//! Needs the `settings` feature: `cargo run --release --features settings --example breakout`
 
 #![no_std]
 #![no_main]
 
-use defmt::info;
+use defmt::{error, info};
 #[allow(clippy::wildcard_imports)]
 use disobey2026badge::*;
 use embassy_executor::Spawner;
@@ -23,7 +26,12 @@ use embedded_graphics::{
 use esp_backtrace as _;
 use esp_hal::timer::timg::TimerGroup;
 use esp_println as _;
+use games::{
+    GameId,
+    high_scores::{self, HighScores},
+};
 use palette::Srgb;
+use storage::{FlashStore, Settings};
 
 extern crate alloc;
 
@@ -57,6 +65,8 @@ const LED_FLASH_TICKS: u8 = 6;
 // Game tick rate
 const TICK_MS: u64 = 20;
 
+const BREAKOUT: GameId = u16::from_le_bytes(*b"BO");
+
 const BRICK_COLORS: [Rgb565; BRICK_ROWS] = [
     Rgb565::RED,
     Rgb565::CSS_ORANGE,
@@ -441,27 +451,27 @@ fn update_leds(leds: &mut Leds, game: &Game) {
     } else {
         // Show remaining bricks as a bar graph on LEDs
         let remaining = game.bricks_remaining();
-        let total = (BRICK_ROWS * BRICK_COLS) as u16;
-        let lit = ((remaining as u32 * 5 + total as u32 - 1) / total as u32) as usize;
-
-        let mut left = [Srgb::new(0u8, 0, 0); BAR_COUNT];
-        let mut right = [Srgb::new(0u8, 0, 0); BAR_COUNT];
-        for i in 0..lit.min(BAR_COUNT) {
-            let color = Srgb::new(0, 4, 2);
-            left[i] = color;
-            right[i] = color;
-        }
-        leds.set_left_bar(&left);
-        leds.set_right_bar(&right);
+        let total = (BRICK_ROWS * BRICK_COLS) as u32;
+        leds.bar_meter(remaining.into(), total, &[Srgb::new(0, 4, 2); BAR_COUNT]);
     }
 }
 
+/// Frames from the game loop; the writer task sends them so ticks never
+/// wait on the LED strip.
+static LED_QUEUE: LedQueue = LedQueue::new();
+
+#[embassy_executor::task]
+async fn led_writer_task(writer: LedWriter<'static>) {
+    writer.run().await
+}
+
 #[embassy_executor::task]
 async fn game_task(
     display: &'static mut Display<'static>,
     backlight: &'static mut Backlight,
     leds: &'static mut Leds<'static>,
     buttons: &'static mut Buttons,
+    settings: &'static Settings<FlashStore<'static>>,
 ) {
     info!("Breakout game task started");
     backlight.on();
@@ -489,14 +499,15 @@ async fn game_task(
         let tick = Duration::from_millis(TICK_MS);
 
         loop {
-            // Poll held buttons directly each tick
-            if buttons.left.is_low() {
+            // Sample all buttons once per tick
+            let input = buttons.snapshot();
+            if input.is_pressed(Button::Left) {
                 game.paddle_x = (game.paddle_x - PADDLE_SPEED).max(0);
                 if !game.launched {
                     game.ball_x = game.paddle_x + PADDLE_W / 2;
                 }
             }
-            if buttons.right.is_low() {
+            if input.is_pressed(Button::Right) {
                 game.paddle_x = (game.paddle_x + PADDLE_SPEED).min(W - PADDLE_W);
                 if !game.launched {
                     game.ball_x = game.paddle_x + PADDLE_W / 2;
@@ -504,7 +515,7 @@ async fn game_task(
             }
 
             // Check A for launch
-            if !game.launched && buttons.a.is_low() {
+            if !game.launched && input.is_pressed(Button::A) {
                 game.launched = true;
             }
 
@@ -547,6 +558,19 @@ async fn game_task(
                     }
                 }
 
+                // Keep the score and show the best ones
+                let score = u32::from(game.score);
+                let mut store = settings.lock().await;
+                let mut scores = HighScores::new(&mut *store);
+                match scores.submit(BREAKOUT, config::NAME, score) {
+                    Ok(Some(rank)) => info!("High score, #{}", rank),
+                    Ok(None) => {}
+                    Err(err) => error!("Saving score: {}", err),
+                }
+                let top = scores.top(BREAKOUT, 5).unwrap_or(high_scores::Table::new(BREAKOUT));
+                drop(store);
+                high_scores::render("Breakout", Some(score), top.iter(), display).unwrap();
+
                 // Wait for restart
                 Buttons::debounce_press(&mut buttons.a).await;
                 break; // Restart outer loop
@@ -569,10 +593,15 @@ async fn main(spawner: Spawner) -> ! {
 
     let display = mk_static!(Display<'static>, resources.display.into());
     let backlight = mk_static!(Backlight, resources.backlight.into());
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
     let buttons = mk_static!(Buttons, resources.buttons.into());
+    let store = FlashStore::new(resources.flash.into()).expect("no kv partition");
+    let settings = mk_static!(Settings<FlashStore<'static>>, Settings::new(store));
 
-    spawner.must_spawn(game_task(display, backlight, leds, buttons));
+    if let Some(writer) = leds.queued(&LED_QUEUE) {
+        spawner.must_spawn(led_writer_task(writer));
+    }
+    spawner.must_spawn(game_task(display, backlight, leds, buttons, settings));
 
     loop {
         Timer::after(Duration::from_secs(600)).await;
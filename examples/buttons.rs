@@ -16,20 +16,21 @@ extern crate alloc;
 esp_bootloader_esp_idf::esp_app_desc!();
 
 #[embassy_executor::task]
-async fn button_task(buttons: &'static mut Buttons) {
+async fn button_task(buttons: &'static mut Buttons, boot: &'static mut BootButton) {
     info!("Button task started — press any button");
 
     loop {
         let pressed = embassy_futures::select::select_array([
-            Buttons::debounce_press(&mut buttons.up),
-            Buttons::debounce_press(&mut buttons.down),
-            Buttons::debounce_press(&mut buttons.left),
-            Buttons::debounce_press(&mut buttons.right),
-            Buttons::debounce_press(&mut buttons.stick),
-            Buttons::debounce_press(&mut buttons.a),
-            Buttons::debounce_press(&mut buttons.b),
-            Buttons::debounce_press(&mut buttons.start),
-            Buttons::debounce_press(&mut buttons.select),
+            buttons.up.wait_for_click(),
+            buttons.down.wait_for_click(),
+            buttons.left.wait_for_click(),
+            buttons.right.wait_for_click(),
+            buttons.stick.wait_for_click(),
+            buttons.a.wait_for_click(),
+            buttons.b.wait_for_click(),
+            buttons.start.wait_for_click(),
+            buttons.select.wait_for_click(),
+            boot.wait_for_click(),
         ])
         .await;
 
@@ -43,6 +44,7 @@ async fn button_task(buttons: &'static mut Buttons) {
             6 => "B",
             7 => "START",
             8 => "SELECT",
+            9 => "BOOT",
             _ => "???",
         };
 
@@ -61,7 +63,8 @@ async fn main(spawner: Spawner) -> ! {
     esp_rtos::start(timg0.timer0);
 
     let buttons = mk_static!(Buttons, resources.buttons.into());
-    spawner.must_spawn(button_task(buttons));
+    let boot = mk_static!(BootButton, resources.boot.into());
+    spawner.must_spawn(button_task(buttons, boot));
 
     loop {
         embassy_time::Timer::after(embassy_time::Duration::from_secs(600)).await;
@@ -0,0 +1,73 @@
+//! Global button combos: hold Start+Select for 1 s to reset the badge,
+//! press A+B together for a short buzz.
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+static INPUT: ButtonMonitor = ButtonMonitor::new();
+
+#[embassy_executor::task]
+async fn input_task(buttons: &'static mut Buttons) {
+    INPUT.run(buttons).await
+}
+
+#[embassy_executor::task]
+async fn chord_task(vibra: &'static mut Vibration) {
+    let mut detector = ChordDetector::<2>::new();
+    let reset = detector
+        .register(
+            Chord::new(ButtonSet::of(&[Button::Start, Button::Select]))
+                .with_hold(Duration::from_secs(1)),
+        )
+        .unwrap();
+    let buzz = detector
+        .register(Chord::new(ButtonSet::of(&[Button::A, Button::B])))
+        .unwrap();
+
+    let mut chords = INPUT.chords(detector).unwrap();
+    info!("Hold START+SELECT to reset, press A+B to buzz");
+    loop {
+        let id = chords.next().await;
+        if id == reset {
+            info!("Resetting");
+            Timer::after(Duration::from_millis(100)).await;
+            esp_hal::system::software_reset();
+        } else if id == buzz {
+            info!("Buzz");
+            vibra.pulse(Duration::from_millis(80)).await;
+        }
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let buttons = mk_static!(Buttons, resources.buttons.into());
+    let vibra = mk_static!(Vibration, resources.vibra.into());
+    spawner.must_spawn(input_task(buttons));
+    spawner.must_spawn(chord_task(vibra));
+
+    loop {
+        Timer::after(Duration::from_secs(600)).await;
+    }
+}
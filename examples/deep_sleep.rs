@@ -0,0 +1,67 @@
+//! Goes to deep sleep after 10 s and wakes again when Start is pressed,
+//! reporting which button woke it.
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::{rtc_cntl::Rtc, timer::timg::TimerGroup};
+use esp_println as _;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut rtc = Rtc::new(peripherals.LPWR);
+    let mut buttons: Buttons = resources.buttons.into();
+    let mut backlight: Backlight = resources.backlight.into();
+    let mut display: Display = resources.display.into();
+
+    let woken_by = buttons.woken_by();
+    let message = if woken_by.contains(Button::Start) {
+        "Woken by START"
+    } else {
+        "Fresh boot"
+    };
+    info!("{} ({})", message, woken_by);
+
+    display.clear(Rgb565::new(4, 8, 16)).unwrap();
+    let style = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    Text::with_alignment(message, Point::new(160, 75), style, Alignment::Center)
+        .draw(&mut display)
+        .unwrap();
+    Text::with_alignment("Sleeping in 10 s", Point::new(160, 105), style, Alignment::Center)
+        .draw(&mut display)
+        .unwrap();
+    backlight.on();
+
+    Timer::after(Duration::from_secs(10)).await;
+    // Don't wake straight back up if Start happens to be held.
+    buttons.start.wait_for_release().await;
+
+    info!("Going to sleep, press START to wake");
+    backlight.off();
+    let wake = buttons.wake_on(ButtonSet::of(&[Button::Start])).unwrap();
+    rtc.sleep_deep(&[&wake]);
+}
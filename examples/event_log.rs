@@ -0,0 +1,108 @@
+//! Keeps a history of button presses in the `events` flash partition and
+//! shows the newest, across reboots.
+//!
+//! - Every boot and every press of A, B or Start is logged with the uptime
+//! - Select erases the log
+//!
+//! Needs the `event-log` feature and the partition table in `partitions.csv`:
+//! `cargo run --release --features event-log --example event_log -- --partition-table partitions.csv`
+
+#![no_std]
+#![no_main]
+
+use alloc::{collections::VecDeque, string::String};
+
+use defmt::{error, info};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::Instant;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::Text,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use event_log::EventLog;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+/// Records that fit on the screen.
+const ROWS: usize = 13;
+
+fn draw(display: &mut Display<'_>, log: &mut EventLog<'_>) {
+    let mut newest = VecDeque::with_capacity(ROWS);
+    for record in log.iter() {
+        match record {
+            Ok(record) => {
+                if newest.len() == ROWS {
+                    newest.pop_front();
+                }
+                let text = String::from_utf8_lossy(record.bytes());
+                newest.push_back(alloc::format!("#{:<5} {}", record.seq(), text));
+            }
+            Err(err) => error!("reading the log: {}", err),
+        }
+    }
+
+    display.clear(Rgb565::BLACK).unwrap();
+    let row = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+    if newest.is_empty() {
+        Text::new("The log is empty", Point::new(8, 14), row).draw(display).unwrap();
+    }
+    for (index, line) in newest.iter().enumerate() {
+        Text::new(line, Point::new(8, 14 + 11 * index as i32), row).draw(display).unwrap();
+    }
+    Text::new("A/B/Start: log a press   Select: erase", Point::new(8, 163), small)
+        .draw(display)
+        .unwrap();
+}
+
+fn append(log: &mut EventLog<'_>, event: &str) {
+    let record = alloc::format!("{:>7} ms {}", Instant::now().as_millis(), event);
+    match log.append(record.as_bytes()) {
+        Ok(seq) => info!("#{} {=str}", seq, record),
+        Err(err) => error!("appending: {}", err),
+    }
+}
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut display: Display = resources.display.into();
+    let mut backlight: Backlight = resources.backlight.into();
+    let mut buttons: Buttons = resources.buttons.into();
+    backlight.on();
+
+    let mut log = EventLog::new(resources.flash.into()).expect("no events partition");
+    append(&mut log, "boot");
+
+    let mut events = buttons.events();
+    loop {
+        draw(&mut display, &mut log);
+        match events.next().await {
+            ButtonEvent::Pressed(button @ (Button::A | Button::B | Button::Start)) => {
+                append(&mut log, &alloc::format!("{:?} pressed", button));
+            }
+            ButtonEvent::Released(Button::Select) => {
+                if let Err(err) = log.clear() {
+                    error!("erasing the log: {}", err);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Files in the `files` flash partition: lists them with their sizes and
+//! the space left.
+//!
+//! - Up/down selects a file
+//! - A saves a note with the uptime as a new file
+//! - B deletes the selected file
+//!
+//! Needs the `fs` feature and the partition table in `partitions.csv`:
+//! `cargo run --release --features fs --example files -- --partition-table partitions.csv`
+
+#![no_std]
+#![no_main]
+
+use alloc::{string::String, vec::Vec};
+
+use defmt::{error, info};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::Instant;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::Text,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use fs::Fs;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+/// Files that fit on the screen.
+const ROWS: usize = 10;
+
+fn draw(display: &mut Display<'_>, fs: &Fs<'_>, names: &[String], selected: usize) {
+    display.clear(Rgb565::BLACK).unwrap();
+    let title = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_ORANGE);
+    let normal = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let highlight = MonoTextStyle::new(&FONT_6X10, Rgb565::YELLOW);
+    let header = alloc::format!("{} files, {} KB free", names.len(), fs.free().unwrap_or(0) / 1024);
+    Text::new(&header, Point::new(8, 20), title).draw(display).unwrap();
+
+    let first = selected.saturating_sub(ROWS - 1);
+    for (row, name) in names.iter().enumerate().skip(first).take(ROWS) {
+        let style = if row == selected { highlight } else { normal };
+        let line = alloc::format!(
+            "{} {:<32} {:>7}",
+            if row == selected { '>' } else { ' ' },
+            name,
+            fs.size(name).unwrap_or(0)
+        );
+        let y = 40 + 12 * (row - first) as i32;
+        Text::new(&line, Point::new(8, y), style).draw(display).unwrap();
+    }
+    if names.is_empty() {
+        Text::new("No files yet: press A to save one", Point::new(8, 40), normal)
+            .draw(display)
+            .unwrap();
+    }
+}
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut display: Display = resources.display.into();
+    let mut backlight: Backlight = resources.backlight.into();
+    let mut buttons: Buttons = resources.buttons.into();
+    backlight.on();
+
+    let mut fs = Fs::new(resources.flash.into()).expect("no files partition");
+    let mut selected = 0;
+
+    let mut events = buttons.events();
+    loop {
+        let mut names: Vec<String> = match fs.files() {
+            Ok(files) => files.into_iter().map(|file| file.name).collect(),
+            Err(err) => {
+                error!("listing files failed: {}", err);
+                Vec::new()
+            }
+        };
+        names.sort_unstable();
+        selected = selected.min(names.len().saturating_sub(1));
+        draw(&mut display, &fs, &names, selected);
+
+        let result = match events.next().await {
+            ButtonEvent::Pressed(Button::Up) | ButtonEvent::Repeat(Button::Up) => {
+                selected = selected.saturating_sub(1);
+                Ok(())
+            }
+            ButtonEvent::Pressed(Button::Down) | ButtonEvent::Repeat(Button::Down) => {
+                selected += 1;
+                Ok(())
+            }
+            ButtonEvent::Released(Button::A) => {
+                let secs = Instant::now().as_secs();
+                let name = alloc::format!("notes/{secs}.txt");
+                info!("saving {}", name.as_str());
+                fs.write(&name, alloc::format!("Saved {secs} s after boot\n").as_bytes())
+            }
+            ButtonEvent::Released(Button::B) => match names.get(selected) {
+                Some(name) => fs.remove(name),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            error!("fs: {}", err);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Two badges find each other over ESP-NOW and share a screen.
+//!
+//! - The lobby lists nearby badges running this example; hold A on both to pair
+//! - Once paired, the D-pad moves your dot and the other badge's dot moves too
+//! - B buzzes the other badge; Start leaves and goes back to the lobby
+//!
+//! Needs the `esp-now` feature: `cargo run --release --features esp-now --example game_link`
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Ticker};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use gamelink::{EspNow, GameId, GameLink, LinkEvent};
+use wifi::Wifi;
+
+extern crate alloc;
+use alloc::format;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const GAME: GameId = u16::from_le_bytes(*b"GL");
+const DOT: u32 = 10;
+const SPEED: i32 = 3;
+/// Reliable message: buzz the other badge.
+const BUZZ: u8 = 1;
+/// Unreliable message: where my dot is, as two little-endian `i16`s.
+const POSITION: u8 = 2;
+
+static LINK: GameLink = GameLink::new(GAME);
+
+#[embassy_executor::task]
+async fn link_task(esp_now: EspNow<'static>) {
+    LINK.run(esp_now).await
+}
+
+fn text(display: &mut Display<'static>, text: &str, y: i32, color: Rgb565) {
+    let style = MonoTextStyle::new(&FONT_10X20, color);
+    let _ = Text::new(text, Point::new(8, y), style).draw(display);
+}
+
+fn dot(display: &mut Display<'static>, at: Point, color: Rgb565) {
+    let _ = Rectangle::new(at, Size::new(DOT, DOT))
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(display);
+}
+
+/// Lobby until paired; returns whether this badge is the host.
+async fn lobby(display: &mut Display<'static>, buttons: &mut Buttons) -> bool {
+    let mut ticker = Ticker::every(Duration::from_millis(100));
+    let mut frame = 0u32;
+    loop {
+        LINK.set_pairing(buttons.a.is_low());
+        while let Some(event) = LINK.try_next() {
+            if let LinkEvent::Linked { peer, host } = event {
+                info!("paired with {}", peer);
+                LINK.set_pairing(false);
+                return host;
+            }
+        }
+        if frame.is_multiple_of(5) {
+            let _ = display.clear(Rgb565::BLACK);
+            text(display, "Hold A on both badges", 24, Rgb565::WHITE);
+            for (row, peer) in LINK.lobby().iter().flatten().take(5).enumerate() {
+                let line = format!("{} {} dBm", peer.id, peer.rssi);
+                let color = if peer.pairing { Rgb565::GREEN } else { Rgb565::CSS_GRAY };
+                text(display, &line, 56 + 22 * row as i32, color);
+            }
+        }
+        frame += 1;
+        ticker.next().await;
+    }
+}
+
+/// Play until either side leaves.
+async fn play(display: &mut Display<'static>, buttons: &mut Buttons, motor: &mut Vibration, host: bool) {
+    let _ = display.clear(Rgb565::BLACK);
+    let (mut me, mut them) = if host {
+        (Point::new(60, 80), Point::new(250, 80))
+    } else {
+        (Point::new(250, 80), Point::new(60, 80))
+    };
+    let mut ticker = Ticker::every(Duration::from_millis(33));
+    let mut held = (false, false);
+    loop {
+        let before = (me, them);
+        if buttons.left.is_low() {
+            me.x -= SPEED;
+        }
+        if buttons.right.is_low() {
+            me.x += SPEED;
+        }
+        if buttons.up.is_low() {
+            me.y -= SPEED;
+        }
+        if buttons.down.is_low() {
+            me.y += SPEED;
+        }
+        me.x = me.x.clamp(0, i32::from(DISPLAY_WIDTH) - DOT as i32);
+        me.y = me.y.clamp(0, i32::from(DISPLAY_HEIGHT) - DOT as i32);
+        let [x0, x1] = (me.x as i16).to_le_bytes();
+        let [y0, y1] = (me.y as i16).to_le_bytes();
+        let _ = LINK.post(&[POSITION, x0, x1, y0, y1]);
+
+        let pressed = (buttons.b.is_low(), buttons.start.is_low());
+        if pressed.0 && !held.0 && LINK.send(&[BUZZ]).await.is_err() {
+            info!("buzz not delivered");
+        }
+        if pressed.1 && !held.1 {
+            LINK.leave().await;
+        }
+        held = pressed;
+
+        while let Some(event) = LINK.try_next() {
+            match event {
+                LinkEvent::Message(message) => match *message {
+                    [POSITION, x0, x1, y0, y1] => {
+                        them = Point::new(
+                            i16::from_le_bytes([x0, x1]).into(),
+                            i16::from_le_bytes([y0, y1]).into(),
+                        );
+                    }
+                    [BUZZ] => motor.pulse(Duration::from_millis(80)).await,
+                    _ => {}
+                },
+                LinkEvent::Unlinked => return,
+                LinkEvent::Linked { .. } => {}
+            }
+        }
+
+        dot(display, before.1, Rgb565::BLACK);
+        dot(display, before.0, Rgb565::BLACK);
+        dot(display, them, Rgb565::CSS_ORANGE);
+        dot(display, me, Rgb565::CSS_DEEP_SKY_BLUE);
+        ticker.next().await;
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+    let mut buttons: Buttons = resources.buttons.into();
+    let mut motor: Vibration = resources.vibra.into();
+
+    let (wifi, _device, esp_now) = Wifi::with_esp_now(resources.wifi).unwrap();
+    let wifi = mk_static!(Wifi, wifi);
+    wifi.start().await.unwrap();
+    spawner.must_spawn(link_task(esp_now));
+
+    loop {
+        let host = lobby(display, &mut buttons).await;
+        play(display, &mut buttons, &mut motor, host).await;
+    }
+}
@@ -0,0 +1,86 @@
+//! A clock that drops to glance mode when left alone.
+//!
+//! After 5 s without input the backlight dims; after 15 s it goes off and
+//! only a 40-pixel band at the right edge stays driven, showing the time
+//! and a crawling ticker. Press any button to bring the full clock back.
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+static ACTIVITY: Activity = Activity::new();
+
+#[embassy_executor::task]
+async fn dimmer_task(backlight: &'static mut Backlight, display: &'static SharedDisplay<'static>) {
+    let mut ticker = GlanceTicker::new("DISOBEY 2026  HACK THE PLANET");
+    IdleDimmer::new()
+        .with_dim_after(Duration::from_secs(5))
+        .with_off_after(Duration::from_secs(15))
+        .run_glance(backlight, display, &mut ticker, &ACTIVITY)
+        .await
+}
+
+#[embassy_executor::task]
+async fn button_task(buttons: &'static mut Buttons) {
+    let mut events = buttons.events();
+    loop {
+        events.next().await;
+        info!("Activity");
+        ACTIVITY.signal(());
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display: Display<'static> = resources.display.into();
+    let display = mk_static!(SharedDisplay<'static>, SharedDisplay::new(display));
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    let buttons = mk_static!(Buttons, resources.buttons.into());
+    backlight.on();
+    spawner.must_spawn(dimmer_task(backlight, display));
+    spawner.must_spawn(button_task(buttons));
+
+    let style = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    loop {
+        // The ticker draws over its band, so redraw everything each time.
+        {
+            let mut display = display.lock().await;
+            if display.power_mode() == PowerMode::Full {
+                let secs = clock::now().map_or(Instant::now().as_secs(), u64::from);
+                let (h, m, s) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+                let time = alloc::format!("{h:02}:{m:02}:{s:02}");
+                display.clear(Rgb565::new(4, 8, 16)).unwrap();
+                Text::with_alignment(&time, Point::new(160, 90), style, Alignment::Center)
+                    .draw(&mut *display)
+                    .unwrap();
+            }
+        }
+        Timer::after(Duration::from_secs(1)).await;
+    }
+}
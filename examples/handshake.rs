@@ -0,0 +1,194 @@
+//! Swap contact cards by holding two badges together.
+//!
+//! - Hold the badges close and A on both: they swap the name and contact
+//!   handle from `badge.toml`, and buzz
+//! - Left and right browse the contacts met; B forgets the one shown
+//!
+//! Contacts are kept in a RAM store here, so they last until reboot; any
+//! flash-backed `storage::Store` keeps them for good.
+//!
+//! Needs the `esp-now` feature: `cargo run --release --features esp-now --example handshake`
+
+#![no_std]
+#![no_main]
+
+use contacts::{
+    Card,
+    Contact,
+    handshake::{Handshake, NEAR_RSSI},
+};
+use defmt::{info, warn};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::{Duration, Ticker};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use gamelink::EspNow;
+use storage::{Key, Store};
+use wifi::Wifi;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+static HANDSHAKE: Handshake = Handshake::new();
+static MET: Channel<CriticalSectionRawMutex, Contact, 2> = Channel::new();
+
+/// Keeps values in RAM; stands in for a flash-backed store.
+struct RamStore {
+    slots: [Option<(Key, [u8; 96], usize)>; 33],
+}
+
+#[derive(Debug)]
+struct Full;
+
+impl Store for RamStore {
+    type Error = Full;
+
+    fn read(&mut self, key: Key, buf: &mut [u8]) -> Result<Option<usize>, Full> {
+        let Some((_, value, len)) = self.slots.iter().flatten().find(|slot| slot.0 == key) else {
+            return Ok(None);
+        };
+        buf.get_mut(..*len).ok_or(Full)?.copy_from_slice(&value[..*len]);
+        Ok(Some(*len))
+    }
+
+    fn write(&mut self, key: Key, value: &[u8]) -> Result<(), Full> {
+        let mut stored = [0; 96];
+        stored.get_mut(..value.len()).ok_or(Full)?.copy_from_slice(value);
+        let slot = match self.slots.iter().position(|slot| slot.is_some_and(|s| s.0 == key)) {
+            Some(index) => index,
+            None => self.slots.iter().position(Option::is_none).ok_or(Full)?,
+        };
+        self.slots[slot] = Some((key, stored, value.len()));
+        Ok(())
+    }
+
+    fn remove(&mut self, key: Key) -> Result<(), Full> {
+        for slot in &mut self.slots {
+            if slot.is_some_and(|s| s.0 == key) {
+                *slot = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[embassy_executor::task]
+async fn link_task(esp_now: EspNow<'static>) {
+    HANDSHAKE.run(esp_now).await
+}
+
+#[embassy_executor::task]
+async fn exchange_task() {
+    let card = Card::from_config();
+    loop {
+        match HANDSHAKE.exchange(&card).await {
+            Ok(contact) => MET.send(contact).await,
+            Err(err) => warn!("handshake: {}", err),
+        }
+    }
+}
+
+fn draw(display: &mut Display<'static>, store: &mut RamStore, shown: usize, nearby: bool) {
+    let total = contacts::count(store).unwrap_or(0);
+    match contacts::get(store, shown) {
+        Ok(Some(contact)) => {
+            let _ = contacts::render(&contact, shown, total, display);
+        }
+        _ => {
+            let _ = display.clear(Rgb565::BLACK);
+            let style = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_GRAY);
+            let _ = Text::with_alignment("No contacts yet", Point::new(160, 80), style, Alignment::Center)
+                .draw(display);
+        }
+    }
+    if nearby {
+        let style = MonoTextStyle::new(&FONT_10X20, Rgb565::GREEN);
+        let _ = Text::with_alignment("Hold A to swap cards", Point::new(160, 24), style, Alignment::Center)
+            .draw(display);
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+    let buttons: Buttons = resources.buttons.into();
+    let mut motor: Vibration = resources.vibra.into();
+    let store = mk_static!(RamStore, RamStore { slots: [None; 33] });
+
+    let (wifi, _device, esp_now) = Wifi::with_esp_now(resources.wifi).unwrap();
+    let wifi = mk_static!(Wifi, wifi);
+    wifi.start().await.unwrap();
+    spawner.must_spawn(link_task(esp_now));
+    spawner.must_spawn(exchange_task());
+    info!("Hold A with another badge within {} dBm", NEAR_RSSI);
+
+    let mut ticker = Ticker::every(Duration::from_millis(50));
+    let mut shown = 0;
+    let mut nearby = false;
+    let mut held = (false, false, false);
+    draw(display, store, shown, nearby);
+    loop {
+        HANDSHAKE.set_confirm(buttons.a.is_low());
+        let mut changed = false;
+
+        if let Ok(contact) = MET.try_receive() {
+            match contacts::add(store, &contact) {
+                Ok(Some(index)) => {
+                    info!("met {=str} ({=str})", contact.card.name(), contact.card.handle());
+                    shown = index;
+                    changed = true;
+                    motor.pulse(Duration::from_millis(120)).await;
+                }
+                Ok(None) => warn!("contact list is full"),
+                Err(err) => warn!("store: {}", defmt::Debug2Format(&err)),
+            }
+        }
+
+        let total = contacts::count(store).unwrap_or(0);
+        let pressed = (buttons.left.is_low(), buttons.right.is_low(), buttons.b.is_low());
+        if pressed.0 && !held.0 && shown > 0 {
+            shown -= 1;
+            changed = true;
+        }
+        if pressed.1 && !held.1 && shown + 1 < total {
+            shown += 1;
+            changed = true;
+        }
+        if pressed.2 && !held.2 && shown < total {
+            let _ = contacts::remove(store, shown);
+            shown = shown.min(total.saturating_sub(2));
+            changed = true;
+        }
+        held = pressed;
+
+        let near = HANDSHAKE.nearby().is_some();
+        if near != nearby {
+            nearby = near;
+            changed = true;
+        }
+        if changed {
+            draw(display, store, shown, nearby);
+        }
+        ticker.next().await;
+    }
+}
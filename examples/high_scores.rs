@@ -0,0 +1,173 @@
+//! Snake with a shared leaderboard: each game's score is signed, sent to
+//! the server at `leaderboard.url` in `badge.toml`, and the game-over
+//! screen shows the top five. Build with `WIFI_SSID=... WIFI_PASSWORD=...`
+//! set.
+//!
+//! - D-pad steers; A starts the next game
+//!
+//! The signing key is kept in a RAM store here, so the badge gets a new one
+//! each boot; any flash-backed `storage::Store` keeps it for good.
+//!
+//! Needs the `leaderboard` feature: `cargo run --release --features leaderboard --example high_scores`
+
+#![no_std]
+#![no_main]
+
+use defmt::{error, info, warn};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Ticker};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use games::{
+    Direction,
+    snake::{self, Snake},
+};
+use http::{HttpClient, Runner, SOCKETS, StackResources};
+use leaderboard::{Leaderboard, Score};
+use storage::{Key, Store};
+use wifi::{Wifi, WifiDevice};
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const SSID: Option<&str> = option_env!("WIFI_SSID");
+const PASSWORD: Option<&str> = option_env!("WIFI_PASSWORD");
+const GAME: &str = "snake";
+const STEP: Duration = Duration::from_millis(150);
+
+/// Keeps one value in RAM; stands in for a flash-backed store.
+#[derive(Default)]
+struct RamStore {
+    slot: Option<(Key, [u8; 32], usize)>,
+}
+
+#[derive(Debug)]
+struct Full;
+
+impl Store for RamStore {
+    type Error = Full;
+
+    fn read(&mut self, key: Key, buf: &mut [u8]) -> Result<Option<usize>, Full> {
+        let Some((_, value, len)) = self.slot.filter(|slot| slot.0 == key) else {
+            return Ok(None);
+        };
+        buf.get_mut(..len).ok_or(Full)?.copy_from_slice(&value[..len]);
+        Ok(Some(len))
+    }
+
+    fn write(&mut self, key: Key, value: &[u8]) -> Result<(), Full> {
+        let mut stored = [0; 32];
+        stored.get_mut(..value.len()).ok_or(Full)?.copy_from_slice(value);
+        self.slot = Some((key, stored, value.len()));
+        Ok(())
+    }
+
+    fn remove(&mut self, key: Key) -> Result<(), Full> {
+        if self.slot.is_some_and(|slot| slot.0 == key) {
+            self.slot = None;
+        }
+        Ok(())
+    }
+}
+
+#[embassy_executor::task]
+async fn wifi_task(mut wifi: Wifi, ssid: &'static str, password: &'static str) {
+    wifi.stay_connected(ssid, password).await
+}
+
+#[embassy_executor::task]
+async fn net_task(mut runner: Runner<'static, WifiDevice<'static>>) {
+    runner.run().await
+}
+
+fn message(display: &mut Display<'static>, text: &str, color: Rgb565) {
+    let _ = display.clear(Rgb565::BLACK);
+    let style = MonoTextStyle::new(&FONT_10X20, color);
+    let _ = Text::with_alignment(text, Point::new(160, 85), style, Alignment::Center).draw(display);
+}
+
+async fn play(display: &mut Display<'static>, buttons: &mut Buttons) -> u16 {
+    let mut game = Snake::new(rng().next_u32());
+    let mut ticker = Ticker::every(STEP);
+    loop {
+        for (button, direction) in [
+            (&buttons.up, Direction::Up),
+            (&buttons.down, Direction::Down),
+            (&buttons.left, Direction::Left),
+            (&buttons.right, Direction::Right),
+        ] {
+            if button.is_low() {
+                game.steer(direction);
+            }
+        }
+        let event = game.tick();
+        let _ = snake::render(&game, display);
+        if event == snake::Event::Died {
+            return game.score();
+        }
+        ticker.next().await;
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let mut backlight: Backlight = resources.backlight.into();
+    backlight.on();
+    let mut buttons: Buttons = resources.buttons.into();
+
+    let (Some(ssid), Some(password), Some(board)) = (SSID, PASSWORD, Leaderboard::from_config())
+    else {
+        error!("Build with WIFI_SSID, WIFI_PASSWORD and leaderboard.url set");
+        message(display, "Set Wi-Fi and leaderboard.url", Rgb565::RED);
+        core::future::pending().await
+    };
+
+    let (wifi, device) = Wifi::new(resources.wifi).unwrap();
+    spawner.must_spawn(wifi_task(wifi, ssid, password));
+    let net = mk_static!(StackResources<SOCKETS>, StackResources::new());
+    let (stack, runner) = http::stack(device, net);
+    spawner.must_spawn(net_task(runner));
+    let mut client = HttpClient::new(stack);
+
+    let mut store = RamStore::default();
+    let key = leaderboard::load_key(&mut store).unwrap();
+    let mut buf = [0; 512];
+    loop {
+        let points = play(display, &mut buttons).await;
+        let score = Score::new(GAME, points.into()).sign(&key);
+        info!("game over: {}", points);
+
+        stack.wait_config_up().await;
+        if let Err(err) = board.submit(&mut client, &score).await {
+            warn!("submit: {}", err);
+        }
+        match board.top(&mut client, GAME, 5, &mut buf).await {
+            Ok(top) => {
+                let _ = leaderboard::render(GAME, Some(points.into()), top, display);
+            }
+            Err(err) => {
+                warn!("top: {}", err);
+                let _ = leaderboard::render(GAME, Some(points.into()), [], display);
+            }
+        }
+        buttons.a.wait_for_press().await;
+    }
+}
@@ -0,0 +1,100 @@
+//! Dims the backlight after 5 s without input and turns it off after 15 s.
+//! Press any button to bring it back.
+//!
+//! Start toggles battery-saver mode through the power budget: the backlight
+//! and LEDs dim, and the dimmer kicks in sooner, without this example
+//! telling either of them.
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::Duration;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use palette::Srgb;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+static ACTIVITY: Activity = Activity::new();
+
+#[embassy_executor::task]
+async fn dimmer_task(backlight: &'static mut Backlight) {
+    IdleDimmer::new()
+        .with_dim_after(Duration::from_secs(5))
+        .with_off_after(Duration::from_secs(15))
+        .run(backlight, &ACTIVITY)
+        .await
+}
+
+#[embassy_executor::task]
+async fn button_task(buttons: &'static mut Buttons) {
+    let mut events = buttons.events();
+    let mut saver = false;
+    loop {
+        if let ButtonEvent::Pressed(Button::Start) = events.next().await {
+            saver = !saver;
+            power::BUDGET.set_mode(if saver { power::Mode::Saver } else { power::Mode::Normal });
+        }
+        info!("Activity");
+        ACTIVITY.signal(());
+    }
+}
+
+fn draw(display: &mut Display<'_>, saver: bool) {
+    display.clear(Rgb565::new(4, 8, 16)).unwrap();
+    let style = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    Text::with_alignment("Leave me alone...", Point::new(160, 80), style, Alignment::Center)
+        .draw(display)
+        .unwrap();
+    let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+    let mode = if saver {
+        "Battery saver on - Start turns it off"
+    } else {
+        "Start turns on battery saver"
+    };
+    Text::with_alignment(mode, Point::new(160, 120), small, Alignment::Center)
+        .draw(display)
+        .unwrap();
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut display: Display = resources.display.into();
+    let mut leds: Leds = resources.leds.try_into().expect("LED setup failed");
+    let mut budget = power::BUDGET.register().expect("no power budget slot");
+    leds.follow(power::BUDGET.register().expect("no power budget slot"));
+
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    let buttons = mk_static!(Buttons, resources.buttons.into());
+    spawner.must_spawn(dimmer_task(backlight));
+    spawner.must_spawn(button_task(buttons));
+
+    loop {
+        let saver = budget.allowance() == power::Mode::Saver.allowance();
+        draw(&mut display, saver);
+        leds.fill(Srgb::new(255, 120, 0));
+        leds.update().await;
+        budget.changed().await;
+    }
+}
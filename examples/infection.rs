@@ -0,0 +1,120 @@
+//! A "virus" that spreads across every badge in the room.
+//!
+//! - A makes this badge patient zero: it starts spreading its own strain
+//! - A badge that catches a strain turns red, buzzes and shows who it caught
+//!   it from and how many badges away patient zero is
+//! - Infected badges pass the strain on, up to 8 hops from patient zero
+//!
+//! Needs the `gossip` feature: `cargo run --release --features gossip --example infection`
+
+#![no_std]
+#![no_main]
+
+use defmt::{info, warn};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_time::Duration;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use gossip::{EspNow, GameId, Gossip, Token};
+use palette::Srgb;
+use wifi::Wifi;
+
+extern crate alloc;
+use alloc::format;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const VIRUS: GameId = u16::from_le_bytes(*b"VX");
+/// How far each strain goes from patient zero.
+const TTL: u8 = 8;
+
+static GOSSIP: Gossip = Gossip::new(VIRUS);
+
+#[embassy_executor::task]
+async fn gossip_task(esp_now: EspNow<'static>) {
+    GOSSIP.run(esp_now, gossip::generate_key()).await
+}
+
+fn show(display: &mut Display<'static>, lines: &[&str], color: Rgb565) {
+    let _ = display.clear(Rgb565::BLACK);
+    let style = MonoTextStyle::new(&FONT_10X20, color);
+    for (row, line) in lines.iter().enumerate() {
+        let y = 60 + 28 * row as i32;
+        let _ = Text::with_alignment(line, Point::new(160, y), style, Alignment::Center).draw(display);
+    }
+}
+
+fn show_infected(display: &mut Display<'static>, token: &Token) {
+    let strain = core::str::from_utf8(token.data()).unwrap_or("?");
+    let from = format!("from {}", token.from);
+    let hops = format!("{} hops from patient zero", token.hops);
+    show(display, &["INFECTED", strain, &from, &hops], Rgb565::RED);
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+    let mut buttons: Buttons = resources.buttons.into();
+    let mut motor: Vibration = resources.vibra.into();
+    let mut leds: Leds = resources.leds.try_into().expect("LED setup failed");
+
+    let (wifi, _device, esp_now) = Wifi::with_esp_now(resources.wifi).unwrap();
+    let wifi = mk_static!(Wifi, wifi);
+    wifi.start().await.unwrap();
+    spawner.must_spawn(gossip_task(esp_now));
+
+    show(display, &["Healthy", "A: become patient zero"], Rgb565::GREEN);
+    leds.fill(Srgb::new(0, 20, 0));
+    leds.update().await;
+
+    let mut infected = false;
+    loop {
+        match select(GOSSIP.next(), buttons.a.wait_for_press()).await {
+            Either::First(token) => {
+                info!("caught {}", token);
+                if !infected {
+                    infected = true;
+                    show_infected(display, &token);
+                    leds.fill(Srgb::new(40, 0, 0));
+                    leds.update().await;
+                    motor.pulse(Duration::from_millis(300)).await;
+                }
+            }
+            Either::Second(()) if !infected => {
+                let [_, animal] = id().words();
+                let strain = format!("{animal} flu");
+                match GOSSIP.spread(strain.as_bytes(), TTL) {
+                    Ok(()) => {
+                        infected = true;
+                        show(display, &["PATIENT ZERO", &strain], Rgb565::CSS_ORANGE);
+                        leds.fill(Srgb::new(40, 10, 0));
+                        leds.update().await;
+                    }
+                    Err(err) => warn!("spread: {}", err),
+                }
+            }
+            Either::Second(()) => {}
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! One task reads the buttons and broadcasts events through an `InputHub`;
+//! a logger, the idle dimmer and a haptics task each subscribe on their own.
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+static INPUT: InputHub = InputHub::new();
+static ACTIVITY: Activity = Activity::new();
+
+#[embassy_executor::task]
+async fn input_task(buttons: &'static mut Buttons) {
+    INPUT.run(buttons, EventConfig::default()).await
+}
+
+#[embassy_executor::task]
+async fn log_task() {
+    let mut events = INPUT.subscribe().unwrap();
+    loop {
+        info!("{}", events.next().await);
+    }
+}
+
+#[embassy_executor::task]
+async fn activity_task() {
+    let mut events = INPUT.subscribe().unwrap();
+    loop {
+        events.next().await;
+        ACTIVITY.signal(());
+    }
+}
+
+#[embassy_executor::task]
+async fn haptics_task(vibra: &'static mut Vibration) {
+    let mut events = INPUT.subscribe().unwrap();
+    loop {
+        match events.next().await {
+            ButtonEvent::Pressed(Button::A) => vibra.effect(HapticEffect::Click).await,
+            ButtonEvent::LongPress(..) => vibra.effect(HapticEffect::Heavy).await,
+            _ => {}
+        }
+    }
+}
+
+#[embassy_executor::task]
+async fn dimmer_task(backlight: &'static mut Backlight) {
+    IdleDimmer::new().run(backlight, &ACTIVITY).await
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let buttons = mk_static!(Buttons, resources.buttons.into());
+    let vibra = mk_static!(Vibration, resources.vibra.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    // Subscribers first, so none misses the first events.
+    spawner.must_spawn(log_task());
+    spawner.must_spawn(activity_task());
+    spawner.must_spawn(haptics_task(vibra));
+    spawner.must_spawn(dimmer_task(backlight));
+    spawner.must_spawn(input_task(buttons));
+
+    loop {
+        Timer::after(Duration::from_secs(600)).await;
+    }
+}
@@ -0,0 +1,80 @@
+//! Builds a small LED idle animation, round-trips it through the shareable
+//! blob format and plays it back within brightness/rate limits.
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use disobey2026badge::led_anim::{Animation, Keyframe, Limits, Pattern, encode};
+use embassy_executor::Spawner;
+use embassy_time::{
+    Duration,
+    Timer,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use palette::Srgb;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const FRAMES: [Keyframe; 4] = [
+    Keyframe {
+        duration_ms: 600,
+        fade: true,
+        pattern: Pattern::Gradient {
+            bottom: Srgb::new(255, 0, 64),
+            top: Srgb::new(0, 64, 255),
+        },
+    },
+    Keyframe {
+        duration_ms: 600,
+        fade: true,
+        pattern: Pattern::Gradient {
+            bottom: Srgb::new(0, 64, 255),
+            top: Srgb::new(255, 0, 64),
+        },
+    },
+    Keyframe {
+        duration_ms: 80,
+        fade: false,
+        pattern: Pattern::Fill(Srgb::new(255, 255, 255)),
+    },
+    Keyframe {
+        duration_ms: 400,
+        fade: true,
+        pattern: Pattern::Fill(Srgb::new(0, 0, 0)),
+    },
+];
+
+#[embassy_executor::task]
+async fn led_task(leds: &'static mut Leds<'static>) {
+    let mut blob = [0u8; 256];
+    let len = encode(&FRAMES, true, &mut blob).unwrap();
+    info!("Animation blob: {} bytes", len);
+
+    let animation = Animation::parse(&blob[..len]).unwrap();
+    animation.play(leds, Limits::default()).await;
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
+    spawner.must_spawn(led_task(leds));
+
+    loop {
+        Timer::after(Duration::from_secs(600)).await;
+    }
+}
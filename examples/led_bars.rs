@@ -3,6 +3,7 @@
 //! 1. Sets both bars to the same gradient — they should look symmetrical.
 //! 2. Sets each bar independently with different colors.
 //! 3. Scrolls a single lit LED up both bars in sync.
+//! 4. Sweeps a band diagonally across the badge using LED positions.
 
 #![no_std]
 #![no_main]
@@ -64,6 +65,19 @@ async fn led_task(leds: &'static mut Leds<'static>) {
                 Timer::after(Duration::from_millis(150)).await;
             }
         }
+
+        // ── Phase 4: diagonal sweep by position ─────────────────────────
+        info!("Phase 4: diagonal sweep");
+        for step in 0..=40 {
+            // From below the bottom-left corner to past the top-right one
+            let t = step as f32 / 40.0 * 1.4 - 0.2;
+            leds.fill_by_position(|p| {
+                let along = ((p.x + 1.0) / 2.0 + p.y) / 2.0;
+                if (along - t).abs() < 0.15 { Srgb::new(0, 20, 20) } else { OFF }
+            });
+            leds.update().await;
+            Timer::after(Duration::from_millis(40)).await;
+        }
     }
 }
 
@@ -77,7 +91,7 @@ async fn main(spawner: Spawner) -> ! {
     let timg0 = TimerGroup::new(peripherals.TIMG0);
     esp_rtos::start(timg0.timer0);
 
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
     spawner.must_spawn(led_task(leds));
 
     loop {
@@ -59,7 +59,7 @@ async fn main(spawner: Spawner) -> ! {
     let timg0 = TimerGroup::new(peripherals.TIMG0);
     esp_rtos::start(timg0.timer0);
 
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
     spawner.must_spawn(led_task(leds));
 
     loop {
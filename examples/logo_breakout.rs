@@ -599,7 +599,7 @@ async fn main(spawner: Spawner) -> ! {
 
     let display = mk_static!(Display<'static>, resources.display.into());
     let backlight = mk_static!(Backlight, resources.backlight.into());
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
     let buttons = mk_static!(Buttons, resources.buttons.into());
 
     spawner.must_spawn(game_task(display, backlight, leds, buttons));
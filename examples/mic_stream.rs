@@ -0,0 +1,65 @@
+//! Streams the microphone to a computer over the USB serial port, with the
+//! DC bias removed. Record it with the decoder in the
+//! `microphone::host` docs, e.g. `python3 mic.py /dev/ttyACM0 out.wav`.
+
+#![no_std]
+#![no_main]
+
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use esp_backtrace as _;
+use esp_hal::{dma::DmaDescriptor, timer::timg::TimerGroup};
+use esp_println as _;
+use microphone::{
+    DEFAULT_SAMPLE_RATE, MicProcessing, Microphone, STREAM_RING_WORDS, host::HostAudio,
+};
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+/// Samples per frame: 16 ms at 16 kHz.
+const CHUNK: usize = 256;
+
+#[embassy_executor::task]
+async fn stream_task(
+    mic: &'static mut Microphone<'static>,
+    ring: &'static mut [u32; STREAM_RING_WORDS],
+    host: &'static mut HostAudio<'static>,
+) {
+    let mut stream = mic
+        .stream::<CHUNK>(ring)
+        .unwrap()
+        .with_processing(MicProcessing::new().with_dc_block());
+    host.run(&mut stream).await
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let descriptors = mk_static!([DmaDescriptor; 8], [DmaDescriptor::EMPTY; 8]);
+    let mic = mk_static!(
+        Microphone<'static>,
+        Microphone::new(resources.mic, DEFAULT_SAMPLE_RATE, descriptors)
+    );
+    let ring = mk_static!([u32; STREAM_RING_WORDS], [0; STREAM_RING_WORDS]);
+    let host = mk_static!(
+        HostAudio<'static>,
+        HostAudio::new(resources.usb, DEFAULT_SAMPLE_RATE)
+    );
+
+    spawner.must_spawn(stream_task(mic, ring, host));
+
+    loop {
+        Timer::after(Duration::from_secs(600)).await;
+    }
+}
@@ -1,5 +1,6 @@
-//! VU meter: reads the I2S microphone as fast as possible and displays
-//! peak amplitude on both LED bars (green → yellow → red).
+//! VU meter: streams the I2S microphone and displays peak amplitude on both LED bars (green → yellow → red).
+//! Swap `AudioDisplay::Level` for `AudioDisplay::Spectrum` to see five
+//! frequency bands instead, low at the bottom.
 
 #![no_std]
 #![no_main]
@@ -11,64 +12,21 @@ use embassy_time::{Duration, Timer};
 use esp_backtrace as _;
 use esp_hal::{dma::DmaDescriptor, timer::timg::TimerGroup};
 use esp_println as _;
-use palette::Srgb;
 
 extern crate alloc;
 
 esp_bootloader_esp_idf::esp_app_desc!();
 
-/// VU meter colors from bottom (quiet) to top (loud).
-const VU_COLORS: [Srgb<u8>; BAR_COUNT] = [
-    Srgb::new(0, 20, 0),  // green
-    Srgb::new(0, 20, 0),  // green
-    Srgb::new(20, 20, 0), // yellow
-    Srgb::new(20, 10, 0), // orange
-    Srgb::new(20, 0, 0),  // red
-];
-
-const OFF: Srgb<u8> = Srgb::new(0, 0, 0);
-
-/// Maximum expected amplitude from the mic (tuning knob — adjust to taste).
-const MAX_AMPLITUDE: u16 = 4000;
-
-/// Map a peak amplitude (0..MAX_AMPLITUDE) to a LED bar pattern.
-fn amplitude_to_bar(peak: u16) -> [Srgb<u8>; BAR_COUNT] {
-    let level = if peak >= MAX_AMPLITUDE {
-        BAR_COUNT
-    } else {
-        (peak as usize * BAR_COUNT) / MAX_AMPLITUDE as usize
-    };
-
-    let mut bar = [OFF; BAR_COUNT];
-    for (i, led) in bar.iter_mut().enumerate() {
-        if i < level {
-            *led = VU_COLORS[i];
-        }
-    }
-    bar
-}
+/// How long the loudest recent level stays marked.
+const PEAK_HOLD: Duration = Duration::from_millis(800);
 
 #[embassy_executor::task]
 async fn vu_task(
-    mic: &'static mut microphone::Microphone<'static>,
+    mut stream: microphone::MicStream<'static, 'static, { led_audio::BLOCK_SIZE }>,
     leds: &'static mut Leds<'static>,
 ) {
-    let mut buf = [0i16; 512];
-
-    loop {
-        match mic.rx.read_words(&mut buf) {
-            Ok(()) => {
-                let peak = buf.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
-                let bar = amplitude_to_bar(peak);
-                leds.set_both_bars(&bar);
-                leds.update().await;
-            }
-            Err(_) => {
-                // On error, briefly pause to avoid tight-looping
-                Timer::after(Duration::from_millis(10)).await;
-            }
-        }
-    }
+    let meter = led_audio::AudioMeter::new(led_audio::AudioDisplay::Level).with_peak_hold(PEAK_HOLD);
+    led_audio::run(&mut stream, leds, meter).await
 }
 
 #[esp_rtos::main]
@@ -88,9 +46,12 @@ async fn main(spawner: Spawner) -> ! {
         microphone::Microphone::new(resources.mic, microphone::DEFAULT_SAMPLE_RATE, descriptors)
     );
 
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let ring = mk_static!([u32; microphone::STREAM_RING_WORDS], [0; microphone::STREAM_RING_WORDS]);
+    let stream = mic.stream(ring).unwrap();
+
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
 
-    spawner.must_spawn(vu_task(mic, leds));
+    spawner.must_spawn(vu_task(stream, leds));
 
     loop {
         Timer::after(Duration::from_secs(600)).await;
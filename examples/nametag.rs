@@ -1,18 +1,29 @@
 //! Name tag example — displays a name scaled to fill the screen.
 //!
-//! Set the name at compile time via the `NAME` environment variable.
-//! Optionally set `BG` and `FG` as 6-char hex RGB colors (or `BG="rainbow"`
-//! for an animated hue-cycling background, `BG="retrofuture"` for an
-//! animated synthwave road with a setting sun, or `BG="hearts"` for
-//! floating hearts), and `LEDS=heartbeat`, `LEDS=rainbow`, or `LEDS="FF8800"`
-//! (6-char hex RGB) for LED effects.
+//! The name, colors and LED effect come from `badge.toml` at compile time
+//! (see [`disobey2026badge::config`]). `theme.background` and
+//! `theme.foreground` are 6-char hex RGB colors, or the background can be
+//! `"rainbow"` for an animated hue-cycling background, `"retrofuture"` for an
+//! animated synthwave road with a setting sun, or `"hearts"` for floating
+//! hearts. `leds.effect` is `"heartbeat"`, `"rainbow"`, or a 6-char hex RGB color.
+//! With the `settings` feature, an LED profile saved in the `kv` partition
+//! takes its place.
+//!
+//! ```toml
+//! [badge]
+//! name = "Hacker"
+//!
+//! [theme]
+//! background = "000000"
+//! foreground = "00FF00"
+//!
+//! [leds]
+//! effect = "00FF00"
+//! ```
 //!
 //! ```sh
-//! NAME="User" BG="rainbow" FG="E0E0E0" LEDS="heartbeat" cargo run --release --example nametag
-//! NAME="Admin" BG="retrofuture" FG="E0E0E0" LEDS="rainbow" cargo run --release --example nametag
-//! NAME="Speaker" cargo run --release --example nametag
-//! NAME="Love" BG="hearts" FG="FFE0E0" LEDS="heartbeat" cargo run --release --example nametag
-//! NAME="Hacker" BG="000000" FG="00FF00" LEDS="00FF00" cargo run --release --example nametag
+//! cargo run --release --example nametag
+//! BADGE_CONFIG=speaker.toml cargo run --release --example nametag
 //! ```
 
 #![no_std]
@@ -21,6 +32,7 @@
 use defmt::info;
 #[allow(clippy::wildcard_imports)]
 use disobey2026badge::*;
+use disobey2026badge::config::Background;
 use embassy_executor::Spawner;
 use embassy_time::{Duration, Timer};
 use embedded_graphics::{
@@ -37,110 +49,21 @@ extern crate alloc;
 
 esp_bootloader_esp_idf::esp_app_desc!();
 
-const NAME: Option<&str> = option_env!("NAME");
-const DEFAULT_NAME: Option<&str> = Some("Anonymous Alpaca");
-const LEDS: Option<&str> = option_env!("LEDS");
-const BG_STR: Option<&str> = option_env!("BG");
-const FG_STR: Option<&str> = option_env!("FG");
 const W: u32 = 320;
 const H: u32 = 170;
 
-/// Parse a hex color string like "FF8800" into Rgb565 at const time.
-/// Returns None if the string is not exactly 6 hex chars.
-const fn parse_hex_rgb565(s: &str) -> Option<Rgb565> {
-    let b = s.as_bytes();
-    if b.len() != 6 {
-        return None;
-    }
-    let Some(r) = hex_byte(b[0], b[1]) else { return None };
-    let Some(g) = hex_byte(b[2], b[3]) else { return None };
-    let Some(b) = hex_byte(b[4], b[5]) else { return None };
+/// Convert an 8-bit sRGB colour from `badge.toml` to Rgb565.
+const fn srgb_to_rgb565(c: Srgb<u8>) -> Rgb565 {
     // Rgb565: 5 bits red, 6 bits green, 5 bits blue
-    Some(Rgb565::new(r >> 3, g >> 2, b >> 3))
-}
-/// Parse a hex color string like "FF8800" into Srgb<u8> at const time.
-/// Returns None if the string is not exactly 6 hex chars.
-const fn parse_hex_srgb(s: &str) -> Option<Srgb<u8>> {
-    let b = s.as_bytes();
-    if b.len() != 6 {
-        return None;
-    }
-    let Some(r) = hex_byte(b[0], b[1]) else { return None };
-    let Some(g) = hex_byte(b[2], b[3]) else { return None };
-    let Some(b) = hex_byte(b[4], b[5]) else { return None };
-    Some(Srgb::new(r, g, b))
-}
-/// Parsed LED hex color (if LEDS is a 6-char hex string).
-const LED_COLOR: Option<Srgb<u8>> = match LEDS {
-    Some(s) => parse_hex_srgb(s),
-    None => None,
-};
-
-const fn hex_digit(c: u8) -> Option<u8> {
-    match c {
-        b'0'..=b'9' => Some(c - b'0'),
-        b'a'..=b'f' => Some(c - b'a' + 10),
-        b'A'..=b'F' => Some(c - b'A' + 10),
-        _ => None,
-    }
-}
-
-const fn hex_byte(hi: u8, lo: u8) -> Option<u8> {
-    let Some(h) = hex_digit(hi) else { return None };
-    let Some(l) = hex_digit(lo) else { return None };
-    Some(h << 4 | l)
+    Rgb565::new(c.red >> 3, c.green >> 2, c.blue >> 3)
 }
 
-/// Const-compatible string equality check.
-const fn str_eq(a: &str, b: &str) -> bool {
-    let a = a.as_bytes();
-    let b = b.as_bytes();
-    if a.len() != b.len() {
-        return false;
-    }
-    let mut i = 0;
-    while i < a.len() {
-        if a[i] != b[i] {
-            return false;
-        }
-        i += 1;
-    }
-    true
-}
-
-const BG_RAINBOW: bool = match BG_STR {
-    Some(s) => str_eq(s, "rainbow"),
-    None => false,
+const BG_COLOR: Rgb565 = match config::THEME.background {
+    Background::Solid(c) => srgb_to_rgb565(c),
+    Background::Rainbow | Background::Retrofuture | Background::Hearts => Rgb565::BLACK,
 };
 
-const BG_RETROFUTURE: bool = match BG_STR {
-    Some(s) => str_eq(s, "retrofuture"),
-    None => false,
-};
-
-const BG_HEARTS: bool = match BG_STR {
-    Some(s) => str_eq(s, "hearts"),
-    None => false,
-};
-
-const BG_COLOR: Rgb565 = match BG_STR {
-    Some(s) if BG_RAINBOW => Rgb565::BLACK,
-    Some(s) if BG_RETROFUTURE => Rgb565::BLACK,
-    Some(s) if BG_HEARTS => Rgb565::BLACK,
-    Some(s) => match parse_hex_rgb565(s) {
-        Some(c) => c,
-        None => panic!("BG must be a 6-char hex RGB string, \"rainbow\", \"retrofuture\", or \"hearts\""),
-    },
-    None => Rgb565::new(2, 8, 20),
-};
-
-const FG_COLOR: Rgb565 = match FG_STR {
-    Some(s) => match parse_hex_rgb565(s) {
-        Some(c) => c,
-        None => panic!("FG must be a 6-char hex RGB string, e.g. \"FFFFFF\""),
-    },
-    None => Rgb565::WHITE,
-};
+const FG_COLOR: Rgb565 = srgb_to_rgb565(config::THEME.foreground);
 
 // 5×7 bitmap font — each character is 5 columns, 7 rows, stored as 7 bytes
 // where bits 4..0 represent the columns (bit 4 = leftmost).
@@ -220,7 +143,7 @@ struct NameLayout {
 
 impl NameLayout {
     fn compute() -> Self {
-        let char_count = NAME.or(DEFAULT_NAME).unwrap().chars().count() as u32;
+        let char_count = config::NAME.chars().count() as u32;
         if char_count == 0 {
             return Self { scale: 1, offset_x: 0, offset_y: 0, char_count: 0 };
         }
@@ -281,7 +204,7 @@ impl NameLayout {
 
         // Look up the character
         
-        if let Some(ch) = NAME.or(DEFAULT_NAME).unwrap().chars().nth(char_idx as usize) {
+        if let Some(ch) = config::NAME.chars().nth(char_idx as usize) {
             if let Some(rows) = glyph(ch) {
                 return (rows[glyph_row as usize] >> (GLYPH_W - 1 - glyph_col)) & 1 == 1;
             }
@@ -640,7 +563,7 @@ async fn display_task(
     display: &'static mut disobey2026badge::Display<'static>,
     backlight: &'static mut Backlight,
 ) {
-    info!("Name tag: {}", NAME);
+    info!("Name tag: {}", config::NAME);
     backlight.on();
 
     let layout = NameLayout::compute();
@@ -648,103 +571,48 @@ async fn display_task(
     // Allocate a shared framebuffer on the heap (320×170 pixels × 2 bytes = 108,800 bytes).
     let mut buf = alloc::vec![Rgb565::BLACK; (W * H) as usize];
 
-    if BG_RAINBOW {
-        let mut hue = 0u16;
-        loop {
-            let bg = hue_to_rgb565(hue as f32, 0.4);
-            draw_frame(display, bg, &layout, &mut buf);
-            hue = (hue + 2) % 360;
-            Timer::after(Duration::from_millis(50)).await;
-        }
-    } else if BG_RETROFUTURE {
-        let mut frame = 0u32;
-        loop {
-            draw_retrofuture_frame(display, frame, &layout, &mut buf);
-            frame = frame.wrapping_add(1);
-            Timer::after(Duration::from_millis(50)).await;
+    match config::THEME.background {
+        Background::Rainbow => {
+            let mut hue = 0u16;
+            loop {
+                let bg = hue_to_rgb565(hue as f32, 0.4);
+                draw_frame(display, bg, &layout, &mut buf);
+                hue = (hue + 2) % 360;
+                Timer::after(Duration::from_millis(50)).await;
+            }
         }
-    } else if BG_HEARTS {
-        let mut frame = 0u32;
-        loop {
-            draw_hearts_frame(display, frame, &layout, &mut buf);
-            frame = frame.wrapping_add(1);
-            Timer::after(Duration::from_millis(50)).await;
+        Background::Retrofuture => {
+            let mut frame = 0u32;
+            loop {
+                draw_retrofuture_frame(display, frame, &layout, &mut buf);
+                frame = frame.wrapping_add(1);
+                Timer::after(Duration::from_millis(50)).await;
+            }
         }
-    } else {
-        draw_frame(display, BG_COLOR, &layout, &mut buf);
-        loop {
-            Timer::after(Duration::from_secs(600)).await;
+        Background::Hearts => {
+            let mut frame = 0u32;
+            loop {
+                draw_hearts_frame(display, frame, &layout, &mut buf);
+                frame = frame.wrapping_add(1);
+                Timer::after(Duration::from_millis(50)).await;
+            }
         }
-    }
-}
-
-
-#[embassy_executor::task]
-async fn heartbeat_task(leds: &'static mut Leds<'static>) {
-    info!("Heartbeat LED task started");
-    let off = Srgb::new(0u8, 0, 0);
-
-    loop {
-        // Double-beat pattern like a real heartbeat: lub-dub ... pause
-        for &(brightness, ms) in &[
-            // First beat (lub)
-            (30u8, 80u64),
-            (10, 100),
-            // Second beat (dub)
-            (30, 80),
-            (5, 120),
-            // Pause
-            (0, 600),
-        ] {
-            let color = Srgb::new(brightness, 0, 0);
-            leds.fill(color);
-            leds.update().await;
-            Timer::after(Duration::from_millis(ms)).await;
+        Background::Solid(_) => {
+            draw_frame(display, BG_COLOR, &layout, &mut buf);
+            loop {
+                Timer::after(Duration::from_secs(600)).await;
+            }
         }
-        leds.fill(off);
-        leds.update().await;
     }
 }
 
-#[embassy_executor::task]
-async fn rainbow_task(leds: &'static mut Leds<'static>) {
-    info!("Rainbow LED task started");
 
-    let mut offset = 0u16;
-    loop {
-        for i in 0..leds.len() {
-            let hue = ((offset + i as u16 * 25) % 360) as f32;
-            // Simple HSV→RGB with S=1, V=0.08 (dim)
-            let c = 0.08_f32;
-            let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
-            let (r, g, b) = match (hue as u16) / 60 {
-                0 => (c, x, 0.0),
-                1 => (x, c, 0.0),
-                2 => (0.0, c, x),
-                3 => (0.0, x, c),
-                4 => (x, 0.0, c),
-                _ => (c, 0.0, x),
-            };
-            leds.set(i, Srgb::new((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8));
-        }
-        leds.update().await;
-        offset = (offset + 3) % 360;
-        Timer::after(Duration::from_millis(50)).await;
-    }
-}
 #[embassy_executor::task]
-async fn static_color_task(leds: &'static mut Leds<'static>, color: Srgb<u8>) {
-    info!("Static LED color task started");
-    leds.fill(color);
-    leds.update().await;
-    // Nothing else to do — LEDs stay on.
-    loop {
-        Timer::after(Duration::from_secs(600)).await;
-    }
+async fn led_task(leds: &'static mut Leds<'static>, profile: LedProfile) {
+    info!("LED task started: {}", profile.name());
+    profile.run(leds).await
 }
 
-
-
 #[esp_rtos::main]
 async fn main(spawner: Spawner) -> ! {
     let peripherals = disobey2026badge::init();
@@ -759,21 +627,16 @@ async fn main(spawner: Spawner) -> ! {
     let backlight = mk_static!(Backlight, resources.backlight.into());
     spawner.must_spawn(display_task(display, backlight));
 
-    match LEDS {
-        Some("heartbeat") => {
-            let leds = mk_static!(Leds<'static>, resources.leds.into());
-            spawner.must_spawn(heartbeat_task(leds));
-        }
-        Some("rainbow") => {
-            let leds = mk_static!(Leds<'static>, resources.leds.into());
-            spawner.must_spawn(rainbow_task(leds));
-        }
-        _ if LED_COLOR.is_some() => {
-            let leds = mk_static!(Leds<'static>, resources.leds.into());
-            spawner.must_spawn(static_color_task(leds, LED_COLOR.unwrap()));
-        }
-        _ => {}
-    }
+    // The profile a settings app saved, or the effect and brightness from badge.toml
+    #[cfg(feature = "settings")]
+    let profile = match storage::FlashStore::new(resources.flash.into()) {
+        Ok(mut store) => LedProfile::restore(&mut store),
+        Err(_) => LedProfile::from_config(),
+    };
+    #[cfg(not(feature = "settings"))]
+    let profile = LedProfile::from_config();
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
+    spawner.must_spawn(led_task(leds, profile));
 
     loop {
         Timer::after(Duration::from_secs(600)).await;
@@ -0,0 +1,160 @@
+//! Name tag you edit from a phone. The badge opens a Wi-Fi network named
+//! after its ID (e.g. `badge-brave-otter-42`); join it and the sign-in page
+//! that pops up sets the name, colors and LED effect. The name starts out
+//! as the one in `badge.toml`.
+//!
+//! Edits are kept in a RAM store here, so they last until reboot; any
+//! flash-backed `storage::Store` keeps them for good.
+//!
+//! Needs the `portal` feature: `cargo run --release --features portal --example nametag_portal`
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write as _;
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use disobey2026badge::config::Background;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use palette::Srgb;
+use portal::{PORTAL_URL, Portal};
+use storage::{Key, Store};
+use wifi::Wifi;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+static PORTAL: Portal = Portal::new();
+static LEDS_CHANGED: Signal<CriticalSectionRawMutex, LedProfile> = Signal::new();
+
+/// Keeps a few values in RAM; stands in for a flash-backed store.
+#[derive(Default)]
+struct RamStore {
+    slots: [Option<(Key, [u8; 64], usize)>; 4],
+}
+
+#[derive(Debug)]
+struct Full;
+
+impl Store for RamStore {
+    type Error = Full;
+
+    fn read(&mut self, key: Key, buf: &mut [u8]) -> Result<Option<usize>, Full> {
+        let Some((_, value, len)) = self.slots.iter().flatten().find(|slot| slot.0 == key) else {
+            return Ok(None);
+        };
+        buf.get_mut(..*len).ok_or(Full)?.copy_from_slice(&value[..*len]);
+        Ok(Some(*len))
+    }
+
+    fn write(&mut self, key: Key, value: &[u8]) -> Result<(), Full> {
+        let mut stored = [0; 64];
+        stored.get_mut(..value.len()).ok_or(Full)?.copy_from_slice(value);
+        let slot = match self.slots.iter().position(|slot| slot.is_some_and(|s| s.0 == key)) {
+            Some(index) => index,
+            None => self.slots.iter().position(Option::is_none).ok_or(Full)?,
+        };
+        self.slots[slot] = Some((key, stored, value.len()));
+        Ok(())
+    }
+
+    fn remove(&mut self, key: Key) -> Result<(), Full> {
+        for slot in &mut self.slots {
+            if slot.is_some_and(|s| s.0 == key) {
+                *slot = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn rgb565(c: Srgb<u8>) -> Rgb565 {
+    Rgb565::new(c.red >> 3, c.green >> 2, c.blue >> 3)
+}
+
+fn draw(display: &mut Display<'static>, nametag: &Nametag, ssid: &str) {
+    let background = match nametag.theme.background {
+        Background::Solid(color) => rgb565(color),
+        // The animated backgrounds are the `nametag` example's; keep it simple.
+        Background::Rainbow | Background::Retrofuture | Background::Hearts => Rgb565::BLACK,
+    };
+    let _ = display.clear(background);
+    let name = MonoTextStyle::new(&FONT_10X20, rgb565(nametag.theme.foreground));
+    let _ = Text::with_alignment(nametag.name(), Point::new(160, 80), name, Alignment::Center)
+        .draw(display);
+    let hint = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_GRAY);
+    let _ = Text::with_alignment(ssid, Point::new(160, 140), hint, Alignment::Center).draw(display);
+    let _ = Text::with_alignment(PORTAL_URL, Point::new(160, 162), hint, Alignment::Center)
+        .draw(display);
+}
+
+#[embassy_executor::task]
+async fn portal_task(
+    wifi: Wifi,
+    device: wifi::WifiDevice<'static>,
+    store: &'static mut RamStore,
+    ssid: &'static str,
+) {
+    PORTAL.run(wifi, device, store, ssid).await
+}
+
+#[embassy_executor::task]
+async fn led_task(leds: &'static mut Leds<'static>, mut profile: LedProfile) {
+    loop {
+        // `run` never returns, so only a change ends the select.
+        let Either::Second(changed) = select(profile.run(leds), LEDS_CHANGED.wait()).await;
+        info!("LEDs: {}", changed.name());
+        profile = changed;
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let store = mk_static!(RamStore, RamStore::default());
+    let mut nametag = Nametag::restore(store);
+    let profile = LedProfile::restore(store);
+
+    let ssid = mk_static!(alloc::string::String, alloc::string::String::new());
+    let _ = write!(ssid, "badge-{}", id());
+    info!("Join {=str} to edit the name tag", ssid.as_str());
+
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
+    spawner.must_spawn(led_task(leds, profile));
+
+    let (wifi, device) = Wifi::access_point(resources.wifi).unwrap();
+    spawner.must_spawn(portal_task(wifi, device, store, ssid.as_str()));
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let mut backlight: Backlight = resources.backlight.into();
+    backlight.on();
+    loop {
+        draw(display, &nametag, ssid);
+        let edit = PORTAL.changed().await;
+        info!("Name tag: {}", edit.nametag.name());
+        nametag = edit.nametag;
+        LEDS_CHANGED.signal(edit.leds);
+    }
+}
@@ -0,0 +1,84 @@
+//! Firmware updates that spread from badge to badge. Shows the running
+//! version, offers it to badges nearby, and installs and reboots into any
+//! newer signed version another badge offers.
+//!
+//! Set `ota.public_key` in `badge.toml` and flash `partitions.csv` first;
+//! the `ota` module docs show how to sign an image. Flash one badge with a
+//! signed image over USB, start this on the others, and watch it spread.
+//!
+//! Needs the `ota` and `esp-now` features:
+//! `cargo run --release --features ota,esp-now --example ota_relay`
+
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::Text,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use ota::{Ota, relay::relay};
+use wifi::Wifi;
+
+extern crate alloc;
+use alloc::format;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+fn text(display: &mut Display<'static>, text: &str, y: i32, color: Rgb565) {
+    let style = MonoTextStyle::new(&FONT_10X20, color);
+    let _ = Text::new(text, Point::new(8, y), style).draw(display);
+}
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let mut backlight: Backlight = resources.backlight.into();
+    backlight.on();
+    let _ = display.clear(Rgb565::BLACK);
+
+    let Some(public_key) = config::OTA_PUBLIC_KEY else {
+        error!("Set ota.public_key in badge.toml");
+        text(display, "No ota.public_key", 30, Rgb565::RED);
+        text(display, "in badge.toml", 52, Rgb565::RED);
+        core::future::pending().await
+    };
+
+    let mut flash: FlashStorage = resources.flash.into();
+    let mut ota = Ota::new(&mut flash, public_key);
+    // Got this far, so the firmware works: don't roll it back.
+    if let Err(err) = ota.mark_valid() {
+        error!("Can't mark the firmware valid: {}", err);
+    }
+    let version = ota.running_version().unwrap_or_default();
+    info!("Running version {}", version);
+    text(display, &format!("Version {version}"), 30, Rgb565::WHITE);
+    text(display, "Listening for updates", 52, Rgb565::CSS_GRAY);
+
+    let (wifi, _device, mut esp_now) = Wifi::with_esp_now(resources.wifi).unwrap();
+    let wifi = mk_static!(Wifi, wifi);
+    wifi.start().await.unwrap();
+
+    let installed = relay(&mut esp_now, &mut ota).await;
+    info!("Installed version {}, rebooting", installed);
+    text(display, &format!("Got version {installed}"), 96, Rgb565::GREEN);
+    text(display, "Rebooting", 118, Rgb565::GREEN);
+    ota::reboot()
+}
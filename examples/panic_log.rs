@@ -0,0 +1,99 @@
+//! Shows the panic saved before the last reset, if any.
+//!
+//! - A panics on purpose; the badge resets and shows it
+//! - B forgets the saved panic
+//!
+//! The panic log replaces `esp-backtrace`'s panic handler, so build without
+//! default features, with the partition table in `partitions.csv`:
+//! `cargo run --release --no-default-features --features panic-log --example panic_log -- --partition-table partitions.csv`
+
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::Text,
+};
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use panic_log::PanicLog;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+fn draw(display: &mut Display<'_>, panic: Option<&PanicLog>) {
+    display.clear(Rgb565::BLACK).unwrap();
+    let title = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+    let Some(panic) = panic else {
+        Text::new("No crash saved", Point::new(8, 24), title).draw(display).unwrap();
+        Text::new("A: panic now", Point::new(8, 160), small).draw(display).unwrap();
+        return;
+    };
+    let title = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_ORANGE_RED);
+    Text::new("Previous app crashed", Point::new(8, 24), title)
+        .draw(display)
+        .unwrap();
+    // Wrap the message to the screen width, 50 characters of FONT_6X10.
+    let body = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let chars: alloc::vec::Vec<char> = panic.message().chars().collect();
+    let rows = chars
+        .split(|&c| c == '\n')
+        .flat_map(|line| line.chunks(50))
+        .take(10);
+    for (row, line) in rows.enumerate() {
+        let line: alloc::string::String = line.iter().collect();
+        Text::new(&line, Point::new(8, 44 + 11 * row as i32), body)
+            .draw(display)
+            .unwrap();
+    }
+    let pc = alloc::format!("pc {:#010x}   A: panic again   B: forget", panic.pc().unwrap_or(0));
+    Text::new(&pc, Point::new(8, 160), small).draw(display).unwrap();
+}
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut display: Display = resources.display.into();
+    let mut backlight: Backlight = resources.backlight.into();
+    let mut buttons: Buttons = resources.buttons.into();
+    backlight.on();
+
+    let mut flash: FlashStorage = resources.flash.into();
+    let mut panic = panic_log::last_panic(&mut flash).expect("no panic partition");
+    if let Some(panic) = &panic {
+        info!("previous run panicked: {}", panic);
+    }
+
+    let mut events = buttons.events();
+    loop {
+        draw(&mut display, panic.as_ref());
+        match events.next().await {
+            ButtonEvent::Released(Button::A) => {
+                let items: &[u8] = core::hint::black_box(&[1, 2, 3]);
+                info!("item {}", items[core::hint::black_box(7)]);
+            }
+            ButtonEvent::Released(Button::B) => {
+                if let Err(err) = panic_log::clear(&mut flash) {
+                    error!("clearing the panic log: {}", err);
+                }
+                panic = None;
+            }
+            _ => {}
+        }
+    }
+}
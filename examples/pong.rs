@@ -0,0 +1,109 @@
+//! Pong against the CPU, built on the `games::pong` logic module.
+//!
+//! - Up/down to move your paddle (left)
+//! - First to 7 wins; press A to play again
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+use disobey2026badge::games::pong::{self, Move, Pong};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Ticker};
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const W: i32 = 320;
+const H: i32 = 170;
+
+// ── Framebuffer ─────────────────────────────────────────────────────────────
+
+/// Minimal DrawTarget backed by a heap pixel buffer, so frames don't flicker.
+struct Fb(Vec<Rgb565>);
+
+impl DrawTarget for Fb {
+    type Color = Rgb565;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(Point { x, y }, color) in pixels {
+            if x >= 0 && x < W && y >= 0 && y < H {
+                self.0[(y * W + x) as usize] = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Fb {
+    fn size(&self) -> Size {
+        Size::new(W as u32, H as u32)
+    }
+}
+
+// ── Game loop ───────────────────────────────────────────────────────────────
+
+#[embassy_executor::task]
+async fn game_task(display: &'static mut Display<'static>, buttons: &'static mut Buttons) {
+    let mut fb = Fb(alloc::vec![Rgb565::BLACK; (W * H) as usize]);
+    let area = Rectangle::new(Point::zero(), Size::new(W as u32, H as u32));
+
+    loop {
+        let mut game = Pong::new(rng().next_u32());
+        let mut ticker = Ticker::every(Duration::from_millis(16));
+
+        while !game.is_over() {
+            let input = if buttons.up.is_low() {
+                Move::Up
+            } else if buttons.down.is_low() {
+                Move::Down
+            } else {
+                Move::Stay
+            };
+            let event = game.tick(input);
+            if event != pong::Event::None {
+                info!("{}", event);
+            }
+
+            pong::render(&game, &mut fb).unwrap();
+            display.fill_contiguous(&area, fb.0.iter().copied()).unwrap();
+            ticker.next().await;
+        }
+
+        Buttons::debounce_press(&mut buttons.a).await;
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 160 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let buttons = mk_static!(Buttons, resources.buttons.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+
+    spawner.must_spawn(game_task(display, buttons));
+
+    loop {
+        embassy_time::Timer::after(Duration::from_secs(600)).await;
+    }
+}
@@ -0,0 +1,127 @@
+//! Lights up as other badges running this example come closer.
+//!
+//! - LEDs glow blue for a badge in the room, brighter within arm's reach,
+//!   and pink with a buzz when two badges touch
+//! - The screen lists the badges heard, their zone and a rough distance
+//!
+//! Needs the `esp-now` feature: `cargo run --release --features esp-now --example proximity`
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Instant, Ticker};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::Text,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use gamelink::{EspNow, GameId, GameLink};
+use palette::Srgb;
+use proximity::{Calibration, Tracker, Zone};
+use wifi::Wifi;
+
+extern crate alloc;
+use alloc::format;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+/// Only the lobby is used: badges announce themselves and never pair.
+const GAME: GameId = u16::from_le_bytes(*b"PX");
+
+static LINK: GameLink = GameLink::new(GAME);
+
+#[embassy_executor::task]
+async fn link_task(esp_now: EspNow<'static>) {
+    LINK.run(esp_now).await
+}
+
+const fn glow(zone: Zone) -> Srgb<u8> {
+    match zone {
+        Zone::Far => Srgb::new(0, 0, 0),
+        Zone::Near => Srgb::new(0, 0, 12),
+        Zone::Close => Srgb::new(0, 20, 60),
+        Zone::Touching => Srgb::new(80, 0, 40),
+    }
+}
+
+fn draw(display: &mut Display<'static>, tracker: &Tracker<BadgeId, 8>) {
+    let _ = display.clear(Rgb565::BLACK);
+    let title = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let _ = Text::new("Badges nearby", Point::new(8, 22), title).draw(display);
+    for (row, (id, proximity)) in tracker.iter().take(6).enumerate() {
+        let color = match proximity.zone() {
+            Zone::Far => Rgb565::CSS_GRAY,
+            Zone::Near => Rgb565::CSS_DEEP_SKY_BLUE,
+            Zone::Close => Rgb565::CYAN,
+            Zone::Touching => Rgb565::CSS_HOT_PINK,
+        };
+        let line = format!(
+            "{id} {:?} {:.1}m",
+            proximity.zone(),
+            proximity.distance().unwrap_or(0.0)
+        );
+        let style = MonoTextStyle::new(&FONT_10X20, color);
+        let _ = Text::new(&line, Point::new(8, 48 + 22 * row as i32), style).draw(display);
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+    let mut motor: Vibration = resources.vibra.into();
+    let mut leds: Leds = resources.leds.try_into().expect("LED setup failed");
+
+    let (wifi, _device, esp_now) = Wifi::with_esp_now(resources.wifi).unwrap();
+    let wifi = mk_static!(Wifi, wifi);
+    wifi.start().await.unwrap();
+    spawner.must_spawn(link_task(esp_now));
+
+    let mut tracker: Tracker<BadgeId, 8> = Tracker::new(Calibration::ESP_NOW);
+    let mut ticker = Ticker::every(Duration::from_millis(100));
+    let mut frame = 0u32;
+    let mut lit = Zone::Far;
+    loop {
+        for peer in LINK.lobby().into_iter().flatten() {
+            if let Some(change) = tracker.update(peer.id, peer.rssi, peer.seen) {
+                info!("{}: {} -> {}", change.id, change.from, change.to);
+            }
+        }
+        while let Some(change) = tracker.expire(Instant::now()) {
+            info!("{} went away", change.id);
+        }
+
+        let closest = tracker.closest().map_or(Zone::Far, |(_, proximity)| proximity.zone());
+        if closest != lit {
+            leds.fill(glow(closest));
+            leds.update().await;
+            if closest == Zone::Touching {
+                motor.pulse(Duration::from_millis(150)).await;
+            }
+            lit = closest;
+        }
+        if frame.is_multiple_of(5) {
+            draw(display, &tracker);
+        }
+        frame += 1;
+        ticker.next().await;
+    }
+}
@@ -0,0 +1,108 @@
+//! Wireless monitor: joins Wi-Fi, shows its address, and draws the frames a
+//! laptop streams to it. Build with `WIFI_SSID=... WIFI_PASSWORD=...` set;
+//! the `remote_display` module docs have the protocol and a Python sender.
+//!
+//! - LEDs glow green while a sender is connected
+//!
+//! Needs the `remote-display` feature: `cargo run --release --features remote-display --example remote_display`
+
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Ticker};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use http::{Runner, SOCKETS, StackResources};
+use palette::Srgb;
+use remote_display::{PORT, RemoteDisplay};
+use wifi::{Wifi, WifiDevice};
+
+extern crate alloc;
+use alloc::format;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const SSID: Option<&str> = option_env!("WIFI_SSID");
+const PASSWORD: Option<&str> = option_env!("WIFI_PASSWORD");
+
+static SCREEN: RemoteDisplay = RemoteDisplay::new();
+
+#[embassy_executor::task]
+async fn wifi_task(mut wifi: Wifi, ssid: &'static str, password: &'static str) {
+    wifi.stay_connected(ssid, password).await
+}
+
+#[embassy_executor::task]
+async fn net_task(mut runner: Runner<'static, WifiDevice<'static>>) {
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn led_task(mut leds: Leds<'static>) {
+    let mut ticker = Ticker::every(Duration::from_millis(200));
+    let mut lit = None;
+    loop {
+        let connected = SCREEN.is_connected();
+        if lit != Some(connected) {
+            leds.fill(if connected { Srgb::new(0, 24, 0) } else { Srgb::new(0, 0, 0) });
+            leds.update().await;
+            lit = Some(connected);
+        }
+        ticker.next().await;
+    }
+}
+
+fn message(display: &mut Display<'static>, text: &str, color: Rgb565) {
+    let _ = display.clear(Rgb565::BLACK);
+    let style = MonoTextStyle::new(&FONT_10X20, color);
+    let _ = Text::with_alignment(text, Point::new(160, 85), style, Alignment::Center).draw(display);
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let mut backlight: Backlight = resources.backlight.into();
+    backlight.on();
+    spawner.must_spawn(led_task(resources.leds.try_into().expect("LED setup failed")));
+
+    let (Some(ssid), Some(password)) = (SSID, PASSWORD) else {
+        error!("Build with WIFI_SSID and WIFI_PASSWORD set");
+        message(display, "Set WIFI_SSID", Rgb565::RED);
+        core::future::pending().await
+    };
+
+    message(display, "Joining Wi-Fi...", Rgb565::WHITE);
+    let (wifi, device) = Wifi::new(resources.wifi).unwrap();
+    spawner.must_spawn(wifi_task(wifi, ssid, password));
+    let net = mk_static!(StackResources<SOCKETS>, StackResources::new());
+    let (stack, runner) = http::stack(device, net);
+    spawner.must_spawn(net_task(runner));
+
+    stack.wait_config_up().await;
+    if let Some(config) = stack.config_v4() {
+        let address = config.address.address();
+        info!("streaming to {}:{}", address, PORT);
+        message(display, &format!("{address}:{PORT}"), Rgb565::GREEN);
+    }
+    SCREEN.run(stack, display).await
+}
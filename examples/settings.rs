@@ -0,0 +1,152 @@
+//! Settings kept in flash across reboots: the screen counts boots and the
+//! minutes the badge has been on, and remembers the brightness and colour.
+//!
+//! - Up/down changes the brightness, left/right the colour; both are saved
+//!   when the button is released
+//! - Start reclaims flash ahead of time, B forgets the counters
+//!
+//! The uptime is saved by a second task through the same `Settings`.
+//!
+//! Needs the `settings` feature: `cargo run --release --features settings --example settings`
+
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Ticker};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use storage::{AppSettings, FlashStore, Settings};
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+type Store = Settings<FlashStore<'static>>;
+
+/// This example's namespace; other apps pick their own.
+const APP: u8 = 0x10;
+const BOOTS: u8 = 0;
+const MINUTES: u8 = 1;
+const COLOR: u8 = 2;
+
+const COLORS: [Rgb565; 6] = [
+    Rgb565::WHITE,
+    Rgb565::CSS_ORANGE,
+    Rgb565::CSS_HOT_PINK,
+    Rgb565::CYAN,
+    Rgb565::GREEN,
+    Rgb565::YELLOW,
+];
+
+/// Adds a minute to the stored uptime every minute.
+#[embassy_executor::task]
+async fn uptime_task(settings: &'static Store) {
+    let app = settings.app(APP);
+    let mut ticker = Ticker::every(Duration::from_secs(60));
+    loop {
+        ticker.next().await;
+        let minutes = app.get_or(MINUTES, 0u32).await.unwrap_or(0);
+        if let Err(err) = app.set(MINUTES, minutes + 1).await {
+            error!("saving uptime: {}", err);
+        }
+    }
+}
+
+async fn draw(display: &mut Display<'_>, app: &AppSettings<'_, FlashStore<'static>>, backlight: &Backlight) {
+    let boots = app.get_or(BOOTS, 0u32).await.unwrap_or(0);
+    let minutes = app.get_or(MINUTES, 0u32).await.unwrap_or(0);
+    let color = app.get_or(COLOR, 0u8).await.unwrap_or(0);
+    let color = COLORS[usize::from(color) % COLORS.len()];
+
+    display.clear(Rgb565::BLACK).unwrap();
+    let big = MonoTextStyle::new(&FONT_10X20, color);
+    let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+    let lines = [
+        alloc::format!("Boot #{boots}"),
+        alloc::format!("{minutes} minutes on"),
+        alloc::format!("Brightness {}", backlight.brightness()),
+    ];
+    for (index, line) in lines.iter().enumerate() {
+        Text::with_alignment(line, Point::new(160, 50 + 30 * index as i32), big, Alignment::Center)
+            .draw(display)
+            .unwrap();
+    }
+    Text::with_alignment(
+        "up/down brightness  left/right colour  B reset",
+        Point::new(160, 160),
+        small,
+        Alignment::Center,
+    )
+    .draw(display)
+    .unwrap();
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut display: Display = resources.display.into();
+    let mut backlight: Backlight = resources.backlight.into();
+    let mut buttons: Buttons = resources.buttons.into();
+
+    let store = FlashStore::new(resources.flash.into()).expect("no kv partition");
+    let settings = mk_static!(Store, Settings::new(store));
+    let app = settings.app(APP);
+
+    backlight.on();
+    backlight.restore(&mut *settings.lock().await).unwrap();
+    let boots = app.get_or(BOOTS, 0u32).await.unwrap() + 1;
+    app.set(BOOTS, boots).await.unwrap();
+    info!("boot {}", boots);
+
+    spawner.must_spawn(uptime_task(settings));
+
+    let mut events = buttons.events();
+    loop {
+        draw(&mut display, &app, &backlight).await;
+        let result = match events.next().await {
+            ButtonEvent::Pressed(Button::Up) | ButtonEvent::Repeat(Button::Up) => {
+                backlight.set_brightness(backlight.brightness().saturating_add(16));
+                Ok(())
+            }
+            ButtonEvent::Pressed(Button::Down) | ButtonEvent::Repeat(Button::Down) => {
+                backlight.set_brightness(backlight.brightness().saturating_sub(16).max(16));
+                Ok(())
+            }
+            ButtonEvent::Released(Button::Up | Button::Down) => backlight.save(&mut *settings.lock().await),
+            ButtonEvent::Released(Button::Left) => {
+                let color = app.get_or(COLOR, 0u8).await.unwrap_or(0);
+                app.set(COLOR, (color + COLORS.len() as u8 - 1) % COLORS.len() as u8).await
+            }
+            ButtonEvent::Released(Button::Right) => {
+                let color = app.get_or(COLOR, 0u8).await.unwrap_or(0);
+                app.set(COLOR, (color + 1) % COLORS.len() as u8).await
+            }
+            ButtonEvent::Released(Button::Start) => settings.lock().await.compact(),
+            ButtonEvent::Released(Button::B) => {
+                app.remove(BOOTS).await.and(app.remove(MINUTES).await)
+            }
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            error!("settings: {}", err);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Two tasks draw on the display at the same time through a `SharedDisplay`.
+//!
+//! A status-bar task owns the top strip and shows the uptime; an app task
+//! bounces a ball in the area below. Each locks only its own region, so
+//! neither can draw over the other.
+
+#![no_std]
+#![no_main]
+
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Instant, Ticker, Timer};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const W: i32 = 320;
+const H: i32 = 170;
+const BAR_H: i32 = 12;
+
+const STATUS_AREA: Rectangle = Rectangle::new(Point::zero(), Size::new(W as u32, BAR_H as u32));
+const APP_AREA: Rectangle = Rectangle::new(
+    Point::new(0, BAR_H),
+    Size::new(W as u32, (H - BAR_H) as u32),
+);
+
+/// Redraws the uptime once a second.
+#[embassy_executor::task]
+async fn status_task(display: &'static SharedDisplay<'static>) {
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let mut buf = [0u8; 16];
+
+    loop {
+        let secs = Instant::now().as_secs();
+        let text = format_uptime(secs, &mut buf);
+        {
+            let mut bar = display.region(STATUS_AREA).await;
+            bar.clear(Rgb565::new(4, 8, 16)).unwrap();
+            Text::with_baseline("DISOBEY 2026", Point::new(4, 1), style, Baseline::Top)
+                .draw(&mut bar)
+                .unwrap();
+            Text::with_baseline(text, Point::new(W - 4 - 6 * text.len() as i32, 1), style, Baseline::Top)
+                .draw(&mut bar)
+                .unwrap();
+        }
+        Timer::after(Duration::from_secs(1)).await;
+    }
+}
+
+/// Bounces a ball. Drawing at y = 0 lands just under the status bar.
+#[embassy_executor::task]
+async fn app_task(display: &'static SharedDisplay<'static>) {
+    let (w, h) = (APP_AREA.size.width as i32, APP_AREA.size.height as i32);
+    let r = 10;
+    let (mut x, mut y, mut dx, mut dy) = (40, 40, 3, 2);
+    let mut ticker = Ticker::every(Duration::from_millis(30));
+
+    display.region(APP_AREA).await.clear(Rgb565::BLACK).unwrap();
+
+    loop {
+        let old = Circle::new(Point::new(x - r, y - r), (r * 2) as u32);
+        x += dx;
+        y += dy;
+        if x - r <= 0 || x + r >= w {
+            dx = -dx;
+        }
+        if y - r <= 0 || y + r >= h {
+            dy = -dy;
+        }
+        {
+            let mut app = display.region(APP_AREA).await;
+            old.into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+                .draw(&mut app)
+                .unwrap();
+            Circle::new(Point::new(x - r, y - r), (r * 2) as u32)
+                .into_styled(PrimitiveStyle::with_fill(Rgb565::CSS_ORANGE))
+                .draw(&mut app)
+                .unwrap();
+        }
+        ticker.next().await;
+    }
+}
+
+fn format_uptime(secs: u64, buf: &mut [u8; 16]) -> &str {
+    let (h, m, s) = (secs / 3600 % 100, secs / 60 % 60, secs % 60);
+    let digits = [h / 10, h % 10, m / 10, m % 10, s / 10, s % 10];
+    let mut i = 0;
+    for (n, d) in digits.iter().enumerate() {
+        if n > 0 && n % 2 == 0 {
+            buf[i] = b':';
+            i += 1;
+        }
+        buf[i] = b'0' + *d as u8;
+        i += 1;
+    }
+    core::str::from_utf8(&buf[..i]).unwrap()
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display: Display<'static> = resources.display.into();
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+
+    let shared = mk_static!(SharedDisplay<'static>, SharedDisplay::new(display));
+    spawner.must_spawn(status_task(shared));
+    spawner.must_spawn(app_task(shared));
+
+    loop {
+        Timer::after(Duration::from_secs(600)).await;
+    }
+}
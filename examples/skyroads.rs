@@ -2,6 +2,7 @@
 //!
 //! Stay on the platforms! Gaps are deadly, blocks destroy you on contact.
 //! - Left/Right to steer between lanes
+//! - Or whistle: low notes steer left, high notes right
 //! - A to jump over gaps and low blocks
 //! - Can't jump inside tunnels
 //! - LEDs react to speed and state
@@ -10,7 +11,7 @@
 #![no_main]
 
 use core::cell::UnsafeCell;
-use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, Ordering};
 
 use defmt::info;
 #[allow(clippy::wildcard_imports)]
@@ -23,8 +24,12 @@ use embedded_graphics::{
     primitives::Rectangle,
 };
 use esp_backtrace as _;
-use esp_hal::timer::timg::TimerGroup;
+use esp_hal::{dma::DmaDescriptor, timer::timg::TimerGroup};
 use esp_println as _;
+use microphone::{
+    DEFAULT_SAMPLE_RATE, MicProcessing, Microphone, STREAM_RING_WORDS,
+    dsp::{Fft, WhistleControl},
+};
 use palette::Srgb;
 
 extern crate alloc;
@@ -122,6 +127,8 @@ static INPUT_LEFT: AtomicBool = AtomicBool::new(false);
 static INPUT_RIGHT: AtomicBool = AtomicBool::new(false);
 static INPUT_JUMP: AtomicBool = AtomicBool::new(false);
 static INPUT_START: AtomicBool = AtomicBool::new(false);
+/// Lane position (×FP) being whistled for, or -1 when nobody is whistling.
+static INPUT_WHISTLE: AtomicI32 = AtomicI32::new(-1);
 
 // ── Simple RNG ──────────────────────────────────────────────────────────────
 
@@ -203,7 +210,7 @@ impl Game {
             alive: true,
             fall_timer: 0,
             crash_timer: 0,
-            rng: Rng::new(0xDEAD_BEEF),
+            rng: Rng::new(rng().next_u32()),
             frame: 0,
         };
         g.generate_up_to(GRID_DEPTH as u32);
@@ -385,6 +392,12 @@ impl Game {
         if right {
             self.ship_lane_fp += LANE_MOVE_SPEED;
         }
+        // Whistling steers towards a lane, unless a button is held
+        let whistle = INPUT_WHISTLE.load(Ordering::Relaxed);
+        if whistle >= 0 && !left && !right {
+            self.ship_lane_fp +=
+                (whistle - self.ship_lane_fp).clamp(-LANE_MOVE_SPEED, LANE_MOVE_SPEED);
+        }
         self.ship_lane_fp = self.ship_lane_fp.clamp(0, (NUM_LANES - 1) * FP);
 
         // Check if in tunnel
@@ -682,6 +695,29 @@ async fn input_task(buttons: &'static mut Buttons) {
     }
 }
 
+#[embassy_executor::task]
+async fn whistle_task(
+    mic: &'static mut Microphone<'static>,
+    ring: &'static mut [u32; STREAM_RING_WORDS],
+) {
+    info!("Whistle task started");
+    let mut stream = mic
+        .stream::<256>(ring)
+        .unwrap()
+        .with_processing(MicProcessing::new().with_dc_block());
+    let fft = Fft::<256>::new();
+    // 500 Hz is the left edge, 2 kHz the right
+    let mut whistle =
+        WhistleControl::new(500.0, 2000.0).with_range(0.0, ((NUM_LANES - 1) * FP) as f32);
+    loop {
+        let Ok(chunk) = stream.next().await else {
+            continue;
+        };
+        let lane = whistle.update(&fft.process(chunk), DEFAULT_SAMPLE_RATE);
+        INPUT_WHISTLE.store(lane.map_or(-1, |lane| lane as i32), Ordering::Relaxed);
+    }
+}
+
 #[embassy_executor::task]
 async fn display_blit_task(display: &'static mut Display<'static>) {
     info!("Display blit task running on core 1");
@@ -878,7 +914,14 @@ async fn main(spawner: Spawner) -> ! {
     esp_rtos::start(timg0.timer0);
 
     let buttons = mk_static!(Buttons, resources.buttons.into());
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
+
+    let descriptors = mk_static!([DmaDescriptor; 8], [DmaDescriptor::EMPTY; 8]);
+    let mic = mk_static!(
+        Microphone<'static>,
+        Microphone::new(resources.mic, DEFAULT_SAMPLE_RATE, descriptors)
+    );
+    let ring = mk_static!([u32; STREAM_RING_WORDS], [0; STREAM_RING_WORDS]);
 
     use esp_hal::interrupt::software::SoftwareInterruptControl;
     let sw_ints = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
@@ -909,6 +952,7 @@ async fn main(spawner: Spawner) -> ! {
 
     spawner.must_spawn(input_task(buttons));
     spawner.must_spawn(game_task(leds));
+    spawner.must_spawn(whistle_task(mic, ring));
 
     loop {
         Timer::after(Duration::from_secs(600)).await;
@@ -104,7 +104,7 @@ impl Game {
             food: Pos { x: 0, y: 0 },
             score: 0,
             game_over: false,
-            rng: Rng::new(0xDEADBEEF),
+            rng: Rng::new(rng().next_u32()),
         };
 
         // Initialize snake in the middle
@@ -331,21 +331,8 @@ fn update_leds(leds: &mut Leds, game: &Game) {
     if game.game_over {
         leds.fill(Srgb::new(20, 0, 0));
     } else {
-        // Show score as LED bar graph
-        let lit = (game.score as usize).min(BAR_COUNT);
-        let mut left = [Srgb::new(0u8, 0, 0); BAR_COUNT];
-        let mut right = [Srgb::new(0u8, 0, 0); BAR_COUNT];
-
-        for i in 0..lit {
-            let color = Srgb::new(0, 10, 0);
-            if i < BAR_COUNT / 2 {
-                left[i] = color;
-            } else {
-                right[i - BAR_COUNT / 2] = color;
-            }
-        }
-        leds.set_left_bar(&left);
-        leds.set_right_bar(&right);
+        // Show score as LED bar graph, one LED per point
+        leds.bar_meter(game.score.into(), BAR_COUNT as u32, &[Srgb::new(0, 10, 0); BAR_COUNT]);
     }
 }
 
@@ -426,7 +413,7 @@ async fn main(spawner: Spawner) -> ! {
 
     let display = mk_static!(Display<'static>, resources.display.into());
     let backlight = mk_static!(Backlight, resources.backlight.into());
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
     let buttons = mk_static!(Buttons, resources.buttons.into());
 
     spawner.must_spawn(game_task(display, backlight, leds, buttons));
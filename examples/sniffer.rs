@@ -0,0 +1,142 @@
+//! Packets in the air: hops across the Wi-Fi channels and draws how busy
+//! each one is, and raises the alarm on deauthentication frames.
+//!
+//! - Bars show frames a second per channel, the current one highlighted
+//! - A deauth or disassociation flashes the LEDs red with a buzz and is
+//!   counted on screen
+//!
+//! Needs the `sniffer` feature: `cargo run --release --features sniffer --example sniffer`
+
+#![no_std]
+#![no_main]
+
+use defmt::{info, warn};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant, Ticker};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use palette::Srgb;
+use wifi::{
+    Wifi,
+    sniffer::{CHANNELS, Management},
+};
+
+extern crate alloc;
+use alloc::format;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const DWELL: Duration = Duration::from_millis(200);
+const REDRAW: Duration = Duration::from_millis(500);
+const ALARM: Duration = Duration::from_millis(300);
+
+fn draw(display: &mut Display<'static>, rates: &[u32; 13], current: Option<u8>, deauths: u32) {
+    let _ = display.clear(Rgb565::BLACK);
+    let title = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let _ = Text::new("Packets in the air", Point::new(8, 20), title).draw(display);
+    let alarm = if deauths > 0 { Rgb565::RED } else { Rgb565::CSS_GRAY };
+    let line = format!("Deauths: {deauths}");
+    let _ = Text::new(&line, Point::new(200, 20), MonoTextStyle::new(&FONT_6X10, alarm)).draw(display);
+
+    let label = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+    let peak = rates.iter().copied().max().unwrap_or(0).max(20);
+    for (index, &rate) in rates.iter().enumerate() {
+        let channel = CHANNELS[index];
+        let x = 8 + 24 * index as i32;
+        let height = rate * 110 / peak;
+        let color = if current == Some(channel) { Rgb565::CYAN } else { Rgb565::CSS_DEEP_SKY_BLUE };
+        let _ = Rectangle::new(Point::new(x, 150 - height as i32), Size::new(18, height))
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(display);
+        let _ = Text::new(&format!("{channel}"), Point::new(x + 3, 165), label).draw(display);
+    }
+}
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let mut backlight: Backlight = resources.backlight.into();
+    backlight.on();
+    let mut motor: Vibration = resources.vibra.into();
+    let mut leds: Leds = resources.leds.try_into().expect("LED setup failed");
+
+    let (mut wifi, mut sniffer) = Wifi::sniffer(resources.wifi).unwrap();
+    wifi.start().await.unwrap();
+    sniffer.start().unwrap();
+    sniffer.hop(&CHANNELS, DWELL);
+
+    // Frames per channel since the last redraw, and the rate shown.
+    let mut counts = [0u32; 13];
+    let mut rates = [0u32; 13];
+    let mut deauths = 0;
+    let mut alarm_until = None;
+    let mut dropped = 0;
+    let mut ticker = Ticker::every(REDRAW);
+    loop {
+        match select(sniffer.next(), ticker.next()).await {
+            Either::First(packet) => {
+                if let Some(count) = counts.get_mut(usize::from(packet.channel).wrapping_sub(1)) {
+                    *count += 1;
+                }
+                if matches!(
+                    packet.management(),
+                    Some(Management::Deauthentication | Management::Disassociation)
+                ) {
+                    deauths += 1;
+                    warn!(
+                        "{} from {=[u8]:02x} on {}, reason {}",
+                        packet.management(),
+                        packet.addr2().unwrap_or_default(),
+                        packet.channel,
+                        packet.reason()
+                    );
+                    if alarm_until.is_none() {
+                        leds.fill(Srgb::new(80, 0, 0));
+                        leds.update().await;
+                        motor.pulse(Duration::from_millis(80)).await;
+                    }
+                    alarm_until = Some(Instant::now() + ALARM);
+                }
+            }
+            Either::Second(()) => {
+                // Each channel gets 1/13 of the time, so scale up to a full second.
+                let scale = (Duration::from_secs(1).as_millis() * CHANNELS.len() as u64
+                    / REDRAW.as_millis()) as u32;
+                for (rate, count) in rates.iter_mut().zip(&mut counts) {
+                    *rate = *count * scale;
+                    *count = 0;
+                }
+                draw(display, &rates, sniffer.channel(), deauths);
+                if alarm_until.is_some_and(|until| Instant::now() >= until) {
+                    leds.fill(Srgb::new(0, 0, 0));
+                    leds.update().await;
+                    alarm_until = None;
+                }
+                if sniffer.dropped() > dropped {
+                    dropped = sniffer.dropped();
+                    info!("{} frames dropped so far", dropped);
+                }
+            }
+        }
+    }
+}
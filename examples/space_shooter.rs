@@ -15,11 +15,11 @@
 #![no_std]
 #![no_main]
 
-use core::sync::atomic::{AtomicBool, Ordering};
 
 use defmt::info;
 #[allow(clippy::wildcard_imports)]
 use disobey2026badge::*;
+use disobey2026badge::ui::{Frame, FrameStyle};
 use embassy_executor::Spawner;
 use embassy_sync::channel::Channel;
 use embassy_time::{Duration, Instant, Timer};
@@ -63,11 +63,8 @@ const MAX_ENEMIES: usize = 8;
 const ENEMY_HP: u8 = 3;
 const FIRE_COOLDOWN: u8 = 12;
 
-// ── Input atomics ───────────────────────────────────────────────────────────
-static INPUT_UP: AtomicBool = AtomicBool::new(false);
-static INPUT_DOWN: AtomicBool = AtomicBool::new(false);
-static INPUT_FIRE: AtomicBool = AtomicBool::new(false);
-static INPUT_START: AtomicBool = AtomicBool::new(false);
+// ── Input ───────────────────────────────────────────────────────────────────
+static INPUT: ButtonMonitor = ButtonMonitor::new();
 
 // ── Simple RNG ──────────────────────────────────────────────────────────────
 struct Rng(u32);
@@ -198,22 +195,22 @@ impl Game {
             bullets: [Bullet::DEAD; MAX_BULLETS],
             enemies: [Enemy::DEAD; MAX_ENEMIES],
             score: 0, tick: 0, scroll_offset: 0,
-            alive: true, rng: Rng::new(0xDEAD_BEEF), enemy_spawn_timer: 0,
+            alive: true, rng: Rng::new(rng().next_u32()), enemy_spawn_timer: 0,
         }
     }
 
     fn update(&mut self) {
         self.tick += 1;
 
-        if INPUT_UP.load(Ordering::Relaxed) {
+        if INPUT.is_pressed(Button::Up) {
             self.player.y = (self.player.y - PLAYER_SPEED).max(Player::H / 2);
         }
-        if INPUT_DOWN.load(Ordering::Relaxed) {
+        if INPUT.is_pressed(Button::Down) {
             self.player.y = (self.player.y + PLAYER_SPEED).min(GAME_H - Player::H / 2 - 1);
         }
 
         if self.player.fire_cooldown > 0 { self.player.fire_cooldown -= 1; }
-        if INPUT_FIRE.load(Ordering::Relaxed) && self.player.fire_cooldown == 0 {
+        if INPUT.is_pressed(Button::A) && self.player.fire_cooldown == 0 {
             let w = self.player.weapon();
             for i in 0..w.count as usize {
                 if let Some(slot) = self.bullets.iter_mut().find(|b| !b.alive) {
@@ -590,20 +587,13 @@ async fn led_task(leds: &'static mut Leds<'static>) {
         match event {
             LedEvent::EnemyKill => {
                 // Bright flash fading to black over ~160ms
-                for i in (0..=8).rev() {
-                    let brightness = i * 5; // MAX flashbang: * 30
-                    leds.fill(Srgb::new(brightness, brightness, brightness));
-                    leds.update().await;
-                    Timer::after(Duration::from_millis(20)).await;
-                }
+                leds.fill(Srgb::new(40, 40, 40)); // MAX flashbang: 240
+                leds.update().await;
+                leds.fade_to(&[Srgb::new(0, 0, 0); LED_COUNT], Duration::from_millis(160)).await;
             }
             LedEvent::Score(score) => {
-                let lit = ((score as usize).min(BAR_COUNT * 5)) / 5;
-                let mut bar = [Srgb::new(0u8, 0, 0); BAR_COUNT];
-                for i in 0..lit.min(BAR_COUNT) {
-                    bar[i] = Srgb::new(0, 4, 2);
-                }
-                leds.set_both_bars(&bar);
+                // One LED per 5 points
+                leds.bar_meter(score / 5, BAR_COUNT as u32, &[Srgb::new(0, 4, 2); BAR_COUNT]);
                 leds.update().await;
             }
             LedEvent::GameOver => {
@@ -623,13 +613,7 @@ async fn led_task(leds: &'static mut Leds<'static>) {
 #[embassy_executor::task]
 async fn input_task(buttons: &'static mut Buttons) {
     info!("Input task started");
-    loop {
-        INPUT_UP.store(buttons.up.is_low(), Ordering::Relaxed);
-        INPUT_DOWN.store(buttons.down.is_low(), Ordering::Relaxed);
-        INPUT_FIRE.store(buttons.a.is_low(), Ordering::Relaxed);
-        INPUT_START.store(buttons.start.is_low(), Ordering::Relaxed);
-        Timer::after(Duration::from_millis(10)).await;
-    }
+    INPUT.run(buttons).await
 }
 
 #[embassy_executor::task]
@@ -743,9 +727,10 @@ async fn game_task(
         display.set_vertical_scroll_offset(HUD_RIGHT).unwrap();
         draw_rect_fb(display, GAME_X, 0, GAME_W, GAME_H, Rgb565::BLACK);
 
-        // Box
-        draw_rect_fb(display, GAME_X + 50, 40, 172, 90, Rgb565::new(12, 0, 0));
-        draw_rect_fb(display, GAME_X + 52, 42, 168, 86, Rgb565::new(4, 0, 0));
+        FrameStyle::new(Rgb565::new(12, 0, 0), Rgb565::new(4, 0, 0))
+            .with_border_width(2)
+            .draw_frame(Rectangle::new(Point::new(GAME_X + 50, 40), Size::new(172, 90)), display)
+            .unwrap();
 
         let style = MonoTextStyle::new(&FONT_10X20, Rgb565::RED);
         Text::new("GAME OVER", Point::new(GAME_X + 86, 75), style)
@@ -767,7 +752,7 @@ async fn game_task(
         LED_CHANNEL.try_send(LedEvent::GameOver).ok();
 
         loop {
-            if INPUT_START.load(Ordering::Relaxed) { break; }
+            if INPUT.is_pressed(Button::Start) { break; }
             Timer::after(Duration::from_millis(50)).await;
         }
         Timer::after(Duration::from_millis(200)).await;
@@ -787,7 +772,7 @@ async fn main(spawner: Spawner) -> ! {
     let buttons = mk_static!(Buttons, resources.buttons.into());
     let display = mk_static!(Display<'static>, resources.display.into());
     let backlight = mk_static!(Backlight, resources.backlight.into());
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
 
     spawner.must_spawn(input_task(buttons));
     spawner.must_spawn(led_task(leds));
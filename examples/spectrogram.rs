@@ -0,0 +1,70 @@
+//! Scrolling spectrogram of the microphone across the whole screen: time
+//! runs right to left, pitch bottom to top, loudness from black through
+//! purple and orange to white.
+
+#![no_std]
+#![no_main]
+
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use esp_backtrace as _;
+use esp_hal::{dma::DmaDescriptor, timer::timg::TimerGroup};
+use esp_println as _;
+use microphone::{
+    DEFAULT_SAMPLE_RATE, MicProcessing, Microphone, STREAM_RING_WORDS, spectrogram,
+};
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+/// FFT size: 128 frequency rows, 62.5 Hz apart.
+const CHUNK: usize = 256;
+
+#[embassy_executor::task]
+async fn spectrogram_task(
+    mic: &'static mut Microphone<'static>,
+    ring: &'static mut [u32; STREAM_RING_WORDS],
+    display: &'static mut Display<'static>,
+) {
+    let mut stream = mic
+        .stream::<CHUNK>(ring)
+        .unwrap()
+        .with_processing(MicProcessing::new().with_dc_block());
+    let area = Rectangle::new(
+        Point::zero(),
+        Size::new(u32::from(DISPLAY_WIDTH), u32::from(DISPLAY_HEIGHT)),
+    );
+    spectrogram::draw_spectrogram(display, area, &mut stream).await
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let backlight = mk_static!(Backlight, resources.backlight.into());
+    backlight.on();
+
+    let descriptors = mk_static!([DmaDescriptor; 8], [DmaDescriptor::EMPTY; 8]);
+    let mic = mk_static!(
+        Microphone<'static>,
+        Microphone::new(resources.mic, DEFAULT_SAMPLE_RATE, descriptors)
+    );
+    let ring = mk_static!([u32; STREAM_RING_WORDS], [0; STREAM_RING_WORDS]);
+
+    spawner.must_spawn(spectrogram_task(mic, ring, display));
+
+    loop {
+        Timer::after(Duration::from_secs(600)).await;
+    }
+}
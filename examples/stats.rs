@@ -0,0 +1,131 @@
+//! A "badge stats" screen: boots, button presses, time on and the time
+//! spent in each app, kept in flash across reboots.
+//!
+//! - Left/right switches between three pretend apps; the time counts
+//!   towards the one shown
+//! - The counters are saved every five minutes, and on Start
+//!
+//! Needs the `settings` feature: `cargo run --release --features settings --example stats`
+
+#![no_std]
+#![no_main]
+
+use defmt::{error, info};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X10, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use stats::{AppId, Stats};
+use storage::FlashStore;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const APPS: [(AppId, &str); 3] = [
+    (u16::from_le_bytes(*b"SN"), "Snake"),
+    (u16::from_le_bytes(*b"PO"), "Pong"),
+    (u16::from_le_bytes(*b"NT"), "Nametag"),
+];
+
+const SAVE_EVERY: Duration = Duration::from_secs(5 * 60);
+
+fn name(app: AppId) -> &'static str {
+    APPS.iter()
+        .find(|&&(id, _)| id == app)
+        .map_or("?", |&(_, name)| name)
+}
+
+fn hms(time: Duration) -> alloc::string::String {
+    let secs = time.as_secs();
+    alloc::format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn draw(display: &mut Display<'_>, stats: &Stats) {
+    display.clear(Rgb565::BLACK).unwrap();
+    let big = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_GOLD);
+    let row = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+
+    let current = stats.app().map_or("-", name);
+    Text::with_alignment(&alloc::format!("In {current}"), Point::new(160, 22), big, Alignment::Center)
+        .draw(display)
+        .unwrap();
+    let totals = [
+        alloc::format!("Boot #{}", stats.boots()),
+        alloc::format!("{} button presses", stats.button_presses()),
+        alloc::format!("{} on in total", hms(stats.uptime())),
+    ];
+    for (index, line) in totals.iter().enumerate() {
+        Text::new(line, Point::new(12, 50 + 12 * index as i32), row).draw(display).unwrap();
+    }
+    for (index, &(app, _)) in APPS.iter().enumerate() {
+        let line = alloc::format!("{:<8} {}", name(app), hms(stats.app_time(app)));
+        Text::new(&line, Point::new(12, 100 + 12 * index as i32), row).draw(display).unwrap();
+    }
+    Text::with_alignment("left/right app  Start save", Point::new(160, 160), small, Alignment::Center)
+        .draw(display)
+        .unwrap();
+}
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut display: Display = resources.display.into();
+    let mut backlight: Backlight = resources.backlight.into();
+    let mut buttons: Buttons = resources.buttons.into();
+    backlight.on();
+
+    let mut store = FlashStore::new(resources.flash.into()).expect("no kv partition");
+    let mut stats = Stats::boot(&mut store).unwrap();
+    info!("boot #{}, {} presses so far", stats.boots(), stats.button_presses());
+    for (app, time) in stats.apps() {
+        info!("{=str}: {} s", name(app), time.as_secs());
+    }
+
+    let mut selected = 0;
+    stats.enter(APPS[selected].0);
+    let mut saved = Instant::now();
+    let mut events = buttons.events();
+    loop {
+        draw(&mut display, &stats);
+        let save = match select(events.next(), Timer::after(Duration::from_secs(1))).await {
+            Either::First(ButtonEvent::Pressed(Button::Right)) => {
+                selected = (selected + 1) % APPS.len();
+                stats.enter(APPS[selected].0);
+                false
+            }
+            Either::First(ButtonEvent::Pressed(Button::Left)) => {
+                selected = (selected + APPS.len() - 1) % APPS.len();
+                stats.enter(APPS[selected].0);
+                false
+            }
+            Either::First(ButtonEvent::Released(Button::Start)) => true,
+            _ => saved.elapsed() >= SAVE_EVERY,
+        };
+        if save {
+            match stats.save(&mut store) {
+                Ok(()) => info!("saved, {} s on", stats.uptime().as_secs()),
+                Err(err) => error!("saving stats: {}", err),
+            }
+            saved = Instant::now();
+        }
+    }
+}
@@ -28,8 +28,12 @@ use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use defmt::info;
 #[allow(clippy::wildcard_imports)]
 use disobey2026badge::*;
+use disobey2026badge::{
+    led_anim::{Easing, Pattern},
+    led_layers::{DEFAULT_FRAME_INTERVAL, Keyframe},
+};
 use embassy_executor::Spawner;
-use embassy_sync::channel::Channel;
+use embassy_futures::join::join;
 use embassy_time::{Duration, Timer};
 use embedded_graphics::{
     mono_font::{MonoTextStyle, ascii::FONT_6X10},
@@ -70,8 +74,8 @@ const LEVEL_X: i32 = BOARD_X + BOARD_PX_W + 10;
 const LEVEL_Y: i32 = BOARD_Y + 110;
 
 const TICK_MS: u64 = 16; // ~60fps frame tick
-const DAS_DELAY: u8 = 10; // frames before auto-repeat starts
-const ARR_RATE: u8 = 2; // frames between auto-repeat moves
+const DAS_DELAY: Duration = Duration::from_millis(10 * TICK_MS); // before auto-repeat starts
+const ARR_RATE: Duration = Duration::from_millis(2 * TICK_MS); // between auto-repeat moves
 const LOCK_DELAY_FRAMES: u8 = 30; // 0.5s at 60fps
 const MAX_LOCK_RESETS: u8 = 15;
 
@@ -86,40 +90,80 @@ static INPUT_SELECT: AtomicBool = AtomicBool::new(false);
 static INPUT_START: AtomicBool = AtomicBool::new(false);
 
 // Edge detection: set to 1 by input task, consumed (set to 0) by game
+static EDGE_LEFT: AtomicU8 = AtomicU8::new(0);
+static EDGE_RIGHT: AtomicU8 = AtomicU8::new(0);
 static EDGE_UP: AtomicU8 = AtomicU8::new(0);
 static EDGE_A: AtomicU8 = AtomicU8::new(0);
 static EDGE_B: AtomicU8 = AtomicU8::new(0);
 static EDGE_SELECT: AtomicU8 = AtomicU8::new(0);
 static EDGE_START: AtomicU8 = AtomicU8::new(0);
 
-// ── LED events ──────────────────────────────────────────────────────────────
-#[derive(Clone, Copy)]
-enum LedEvent {
-    LineClear(u8),
-    TSpin,
-    GameOver,
-    LevelUp,
+// ── LED animations ──────────────────────────────────────────────────────────
+static LED_ANIMATOR: LedAnimator = LedAnimator::new().with_haptics(&HAPTICS);
+static LED_NOTIFIER: LedNotifier = LedNotifier::new();
+
+// A more important effect interrupts a less important one.
+const PRIORITY_LINE_CLEAR: u8 = 0;
+const PRIORITY_LEVEL_UP: u8 = 1;
+const PRIORITY_T_SPIN: u8 = 2;
+const PRIORITY_GAME_OVER: u8 = 3;
+
+/// Line clears are only worth showing right away.
+const LINE_CLEAR_MAX_WAIT: Duration = Duration::from_millis(300);
+
+fn notify(keyframes: &'static [Keyframe], priority: u8) {
+    LED_NOTIFIER.notify(LedNotification::new(LedAnimation::new(keyframes), priority));
 }
 
-static LED_CHANNEL: Channel<
-    embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
-    LedEvent,
-    4,
-> = Channel::new();
+const LED_OFF: Srgb<u8> = Srgb::new(0, 0, 0);
 
-// ── Vibration events ────────────────────────────────────────────────────────
-#[derive(Clone, Copy)]
-enum VibraEvent {
-    Drop,
-    LineClear,
-    Tetris,
+const fn fill(color: Srgb<u8>, ms: u64, easing: Easing) -> Keyframe {
+    Keyframe::new(Pattern::Fill(color), Duration::from_millis(ms)).with_easing(easing)
+}
+
+/// Flash `color` with a buzz and fade out.
+const fn line_clear(color: Srgb<u8>, buzz: &'static [HapticSegment]) -> [Keyframe; 2] {
+    [
+        fill(color, 30, Easing::Step).with_haptic(HapticPattern::new(buzz)),
+        fill(LED_OFF, 150, Easing::Linear),
+    ]
+}
+
+static LINE_CLEAR_1: [Keyframe; 2] = line_clear(Srgb::new(0, 0, 15), &LINE_CLEAR_BUZZ);
+static LINE_CLEAR_2: [Keyframe; 2] = line_clear(Srgb::new(0, 20, 0), &LINE_CLEAR_BUZZ);
+static LINE_CLEAR_3: [Keyframe; 2] = line_clear(Srgb::new(0, 30, 30), &LINE_CLEAR_BUZZ);
+// Tetris = bright white
+static LINE_CLEAR_4: [Keyframe; 2] = line_clear(Srgb::new(40, 40, 40), &TETRIS_BUZZ);
+
+const fn blink(color: Srgb<u8>, ms: u64) -> [Keyframe; 2] {
+    [fill(color, ms, Easing::Step), fill(LED_OFF, ms, Easing::Step)]
+}
+
+static T_SPIN: [[Keyframe; 2]; 3] = [blink(Srgb::new(30, 0, 30), 80); 3];
+static GAME_OVER: [[Keyframe; 2]; 4] = [blink(Srgb::new(20, 0, 0), 250); 4];
+
+/// One lit LED per bar, at `height` from the bottom.
+const fn level_up(height: usize) -> Keyframe {
+    // Right bar runs bottom-to-top, left bar top-to-bottom.
+    let mut colors = [LED_OFF; LED_COUNT];
+    colors[height] = Srgb::new(0, 30, 10);
+    colors[LED_COUNT - 1 - height] = Srgb::new(0, 30, 10);
+    Keyframe::new(Pattern::Each(colors), Duration::from_millis(40)).with_easing(Easing::Step)
 }
 
-static VIBRA_CHANNEL: Channel<
-    embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
-    VibraEvent,
-    4,
-> = Channel::new();
+static LEVEL_UP: [Keyframe; BAR_COUNT] =
+    [level_up(0), level_up(1), level_up(2), level_up(3), level_up(4)];
+
+// ── Vibration patterns ──────────────────────────────────────────────────────
+static HAPTICS: HapticQueue = HapticQueue::new();
+
+static DROP_BUZZ: [HapticSegment; 1] = [HapticSegment::hold(255, Duration::from_millis(20))];
+static LINE_CLEAR_BUZZ: [HapticSegment; 1] = [HapticSegment::hold(255, Duration::from_millis(40))];
+static TETRIS_BUZZ: [HapticSegment; 3] = [
+    HapticSegment::hold(255, Duration::from_millis(60)),
+    HapticSegment::pause(Duration::from_millis(40)),
+    HapticSegment::hold(255, Duration::from_millis(60)),
+];
 
 // ── Piece definitions (SRS) ─────────────────────────────────────────────────
 // Each piece has 4 rotation states, each state is 4 (x,y) offsets from pivot.
@@ -539,17 +583,12 @@ struct Game {
     lock_resets: u8,
     on_ground: bool,
     last_was_rotation: bool,
-    // DAS (delayed auto shift)
-    das_left: u8,
-    das_right: u8,
-    prev_left: bool,
-    prev_right: bool,
     prev_down: bool,
 }
 
 impl Game {
     fn new() -> Self {
-        let mut bag = Bag::new(0xCAFE_BABE);
+        let mut bag = Bag::new(rng().next_u32());
         let kind = bag.next();
         Self {
             board: empty_board(),
@@ -569,10 +608,6 @@ impl Game {
             lock_resets: 0,
             on_ground: false,
             last_was_rotation: false,
-            das_left: 0,
-            das_right: 0,
-            prev_left: false,
-            prev_right: false,
             prev_down: false,
         }
     }
@@ -660,7 +695,7 @@ impl Game {
         }
         self.score += hard_drop_score(dropped);
         self.lock_piece_and_clear();
-        VIBRA_CHANNEL.try_send(VibraEvent::Drop).ok();
+        HAPTICS.request(HapticPattern::new(&DROP_BUZZ), 0);
     }
 
     fn hold_piece(&mut self) {
@@ -698,7 +733,7 @@ impl Game {
             let new_level = (self.lines_total / 10 + 1).min(30) as u8;
             if new_level > self.level {
                 self.level = new_level;
-                LED_CHANNEL.try_send(LedEvent::LevelUp).ok();
+                notify(&LEVEL_UP, PRIORITY_LEVEL_UP);
             }
 
             if is_difficult {
@@ -708,15 +743,18 @@ impl Game {
             }
 
             if t_spin {
-                LED_CHANNEL.try_send(LedEvent::TSpin).ok();
-            }
-            LED_CHANNEL.try_send(LedEvent::LineClear(lines)).ok();
-
-            if lines == 4 {
-                VIBRA_CHANNEL.try_send(VibraEvent::Tetris).ok();
-            } else {
-                VIBRA_CHANNEL.try_send(VibraEvent::LineClear).ok();
+                notify(T_SPIN.as_flattened(), PRIORITY_T_SPIN);
             }
+            let line_clear: &'static [Keyframe] = match lines {
+                4 => &LINE_CLEAR_4,
+                3 => &LINE_CLEAR_3,
+                2 => &LINE_CLEAR_2,
+                _ => &LINE_CLEAR_1,
+            };
+            LED_NOTIFIER.notify(
+                LedNotification::new(LedAnimation::new(line_clear), PRIORITY_LINE_CLEAR)
+                    .with_max_wait(LINE_CLEAR_MAX_WAIT),
+            );
         } else {
             self.combo = 0;
         }
@@ -755,41 +793,15 @@ impl Game {
             return;
         }
 
-        // DAS horizontal movement
-        let left = INPUT_LEFT.load(Ordering::Relaxed);
-        let right = INPUT_RIGHT.load(Ordering::Relaxed);
-
-        if left && !self.prev_left {
+        // Horizontal movement; held Left/Right auto-repeat (DAS) via the
+        // input task's event stream
+        if EDGE_LEFT.swap(0, Ordering::Relaxed) > 0 {
             self.try_move(-1, 0);
-            self.das_left = 0;
-        } else if left {
-            self.das_left += 1;
-            if self.das_left >= DAS_DELAY {
-                if (self.das_left - DAS_DELAY) % ARR_RATE == 0 {
-                    self.try_move(-1, 0);
-                }
-            }
-        } else {
-            self.das_left = 0;
         }
-
-        if right && !self.prev_right {
+        if EDGE_RIGHT.swap(0, Ordering::Relaxed) > 0 {
             self.try_move(1, 0);
-            self.das_right = 0;
-        } else if right {
-            self.das_right += 1;
-            if self.das_right >= DAS_DELAY {
-                if (self.das_right - DAS_DELAY) % ARR_RATE == 0 {
-                    self.try_move(1, 0);
-                }
-            }
-        } else {
-            self.das_right = 0;
         }
 
-        self.prev_left = left;
-        self.prev_right = right;
-
         // Soft drop
         let down = INPUT_DOWN.load(Ordering::Relaxed);
         if down && !self.prev_down {
@@ -1171,133 +1183,52 @@ fn draw_pause(display: &mut Display) {
 #[embassy_executor::task]
 async fn input_task(buttons: &'static mut Buttons) {
     info!("Tetris input task started");
-    let mut prev_up = false;
-    let mut prev_a = false;
-    let mut prev_b = false;
-    let mut prev_select = false;
-    let mut prev_start = false;
+    // Debounced press/release events; no hand-rolled edge tracking needed.
+    // Left/Right also repeat while held for delayed auto-shift.
+    let config = EventConfig::default()
+        .with_repeat(DAS_DELAY, ARR_RATE)
+        .with_repeat_buttons(ButtonSet::of(&[Button::Left, Button::Right]))
+        .with_click_buttons(ButtonSet::EMPTY);
+    let mut events = buttons.events_with(config);
 
     loop {
-        let up = buttons.up.is_low();
-        let a = buttons.a.is_low();
-        let b = buttons.b.is_low();
-        let select = buttons.select.is_high(); // select is pull-down, active high
-        let start = buttons.start.is_low();
-
-        INPUT_LEFT.store(buttons.left.is_low(), Ordering::Relaxed);
-        INPUT_RIGHT.store(buttons.right.is_low(), Ordering::Relaxed);
-        INPUT_DOWN.store(buttons.down.is_low(), Ordering::Relaxed);
-        INPUT_UP.store(up, Ordering::Relaxed);
-        INPUT_A.store(a, Ordering::Relaxed);
-        INPUT_B.store(b, Ordering::Relaxed);
-        INPUT_SELECT.store(select, Ordering::Relaxed);
-        INPUT_START.store(start, Ordering::Relaxed);
-
-        // Edge detection (rising edge = just pressed)
-        if up && !prev_up {
-            EDGE_UP.store(1, Ordering::Relaxed);
-        }
-        if a && !prev_a {
-            EDGE_A.store(1, Ordering::Relaxed);
-        }
-        if b && !prev_b {
-            EDGE_B.store(1, Ordering::Relaxed);
-        }
-        if select && !prev_select {
-            EDGE_SELECT.store(1, Ordering::Relaxed);
-        }
-        if start && !prev_start {
-            EDGE_START.store(1, Ordering::Relaxed);
+        let (button, pressed) = match events.next().await {
+            ButtonEvent::Pressed(button) | ButtonEvent::Repeat(button) => (button, true),
+            ButtonEvent::Released(button) => (button, false),
+            _ => continue,
+        };
+        let (held, edge) = match button {
+            Button::Left => (&INPUT_LEFT, Some(&EDGE_LEFT)),
+            Button::Right => (&INPUT_RIGHT, Some(&EDGE_RIGHT)),
+            Button::Down => (&INPUT_DOWN, None),
+            Button::Up => (&INPUT_UP, Some(&EDGE_UP)),
+            Button::A => (&INPUT_A, Some(&EDGE_A)),
+            Button::B => (&INPUT_B, Some(&EDGE_B)),
+            Button::Select => (&INPUT_SELECT, Some(&EDGE_SELECT)),
+            Button::Start => (&INPUT_START, Some(&EDGE_START)),
+            Button::Stick => continue,
+        };
+        held.store(pressed, Ordering::Relaxed);
+        if let Some(edge) = edge.filter(|_| pressed) {
+            edge.store(1, Ordering::Relaxed);
         }
-
-        prev_up = up;
-        prev_a = a;
-        prev_b = b;
-        prev_select = select;
-        prev_start = start;
-
-        Timer::after(Duration::from_millis(8)).await;
     }
 }
 
 #[embassy_executor::task]
 async fn led_task(leds: &'static mut Leds<'static>) {
     info!("Tetris LED task started");
-    loop {
-        let event = LED_CHANNEL.receive().await;
-        match event {
-            LedEvent::LineClear(lines) => {
-                let color = match lines {
-                    4 => Srgb::new(40u8, 40, 40), // Tetris = bright white
-                    3 => Srgb::new(0, 30, 30),
-                    2 => Srgb::new(0, 20, 0),
-                    _ => Srgb::new(0, 0, 15),
-                };
-                for i in (0..=5).rev() {
-                    let b = i as u8;
-                    leds.fill(Srgb::new(
-                        (color.red as u16 * b as u16 / 5) as u8,
-                        (color.green as u16 * b as u16 / 5) as u8,
-                        (color.blue as u16 * b as u16 / 5) as u8,
-                    ));
-                    leds.update().await;
-                    Timer::after(Duration::from_millis(30)).await;
-                }
-            }
-            LedEvent::TSpin => {
-                for _ in 0..3 {
-                    leds.fill(Srgb::new(30, 0, 30));
-                    leds.update().await;
-                    Timer::after(Duration::from_millis(80)).await;
-                    leds.clear();
-                    leds.update().await;
-                    Timer::after(Duration::from_millis(80)).await;
-                }
-            }
-            LedEvent::GameOver => {
-                for _ in 0..4 {
-                    leds.fill(Srgb::new(20, 0, 0));
-                    leds.update().await;
-                    Timer::after(Duration::from_millis(250)).await;
-                    leds.clear();
-                    leds.update().await;
-                    Timer::after(Duration::from_millis(250)).await;
-                }
-            }
-            LedEvent::LevelUp => {
-                for i in 0..BAR_COUNT {
-                    let mut bar = [Srgb::new(0u8, 0, 0); BAR_COUNT];
-                    bar[i] = Srgb::new(0, 30, 10);
-                    leds.set_both_bars(&bar);
-                    leds.update().await;
-                    Timer::after(Duration::from_millis(40)).await;
-                }
-                leds.clear();
-                leds.update().await;
-            }
-        }
-    }
+    join(
+        LED_ANIMATOR.run(leds, DEFAULT_FRAME_INTERVAL),
+        LED_NOTIFIER.run(&LED_ANIMATOR, 0),
+    )
+    .await;
 }
 
 #[embassy_executor::task]
 async fn vibra_task(vibra: &'static mut Vibration) {
     info!("Tetris vibration task started");
-    loop {
-        let event = VIBRA_CHANNEL.receive().await;
-        match event {
-            VibraEvent::Drop => {
-                vibra.pulse(Duration::from_millis(20)).await;
-            }
-            VibraEvent::LineClear => {
-                vibra.pulse(Duration::from_millis(40)).await;
-            }
-            VibraEvent::Tetris => {
-                vibra.pulse(Duration::from_millis(60)).await;
-                Timer::after(Duration::from_millis(40)).await;
-                vibra.pulse(Duration::from_millis(60)).await;
-            }
-        }
-    }
+    HAPTICS.run(vibra).await
 }
 
 #[embassy_executor::task]
@@ -1386,7 +1317,7 @@ async fn game_task(
             if game.game_over {
                 Timer::after(Duration::from_millis(300)).await;
                 draw_game_over(display, game.score, game.level);
-                LED_CHANNEL.try_send(LedEvent::GameOver).ok();
+                notify(GAME_OVER.as_flattened(), PRIORITY_GAME_OVER);
 
                 // Wait for restart
                 loop {
@@ -1415,7 +1346,7 @@ async fn main(spawner: Spawner) -> ! {
 
     let display = mk_static!(Display<'static>, resources.display.into());
     let backlight = mk_static!(Backlight, resources.backlight.into());
-    let leds = mk_static!(Leds<'static>, resources.leds.into());
+    let leds = mk_static!(Leds<'static>, resources.leds.try_into().expect("LED setup failed"));
     let buttons = mk_static!(Buttons, resources.buttons.into());
     let vibra = mk_static!(Vibration, resources.vibra.into());
 
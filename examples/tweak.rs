@@ -0,0 +1,102 @@
+//! Live-tune a bouncing ball with the tweaker overlay.
+//!
+//! Up/down pick a parameter, A/B adjust it, left/right take big steps (or
+//! pick a colour channel). Start logs the current values via defmt, Select
+//! hides the overlay.
+
+#![no_std]
+#![no_main]
+
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use disobey2026badge::tweak::{Param, Tweaker};
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Ticker};
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const W: f32 = 320.0;
+const H: f32 = 170.0;
+
+const GRAVITY: usize = 0;
+const BOUNCE: usize = 1;
+const SPEED: usize = 2;
+const RADIUS: usize = 3;
+const BALL_COLOR: usize = 4;
+
+#[esp_rtos::main]
+async fn main(_spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let mut display: Display = resources.display.into();
+    let mut backlight: Backlight = resources.backlight.into();
+    let buttons: Buttons = resources.buttons.into();
+    backlight.on();
+
+    let mut tweaker = Tweaker::new([
+        Param::float("gravity", 0.3, 0.0..=2.0, 0.05),
+        Param::float("bounce", 0.85, 0.0..=1.0, 0.01),
+        Param::float("speed", 2.0, 0.0..=8.0, 0.1),
+        Param::int("radius", 8, 2..=30, 1),
+        Param::color("color", Rgb565::CSS_ORANGE),
+    ]);
+
+    display.clear(Rgb565::BLACK).unwrap();
+
+    let (mut x, mut y) = (W / 2.0, 20.0);
+    let (mut vx, mut vy) = (tweaker.float(SPEED), 0.0);
+    let mut last = Circle::new(Point::zero(), 0);
+    let mut ticker = Ticker::every(Duration::from_millis(20));
+
+    loop {
+        let was_visible = tweaker.is_visible();
+        tweaker.poll(&buttons);
+        if was_visible && !tweaker.is_visible() {
+            display
+                .fill_solid(&tweaker.area(), Rgb565::BLACK)
+                .unwrap();
+        }
+
+        let r = tweaker.int(RADIUS) as f32;
+        vx = vx.signum() * tweaker.float(SPEED);
+        vy += tweaker.float(GRAVITY);
+        x += vx;
+        y += vy;
+        if x < r || x > W - r {
+            vx = -vx;
+            x = x.clamp(r, W - r);
+        }
+        if y > H - r {
+            vy = -vy * tweaker.float(BOUNCE);
+            y = H - r;
+        }
+
+        let ball = Circle::with_center(Point::new(x as i32, y as i32), (r * 2.0) as u32);
+        last.into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+            .draw(&mut display)
+            .unwrap();
+        ball.into_styled(PrimitiveStyle::with_fill(tweaker.color(BALL_COLOR)))
+            .draw(&mut display)
+            .unwrap();
+        last = ball;
+
+        tweaker.draw(&mut display).unwrap();
+        ticker.next().await;
+    }
+}
@@ -1,4 +1,5 @@
-//! Pulses the vibration motor in a heartbeat pattern.
+//! Plays a heartbeat pattern on the vibration motor: a strong beat, a
+//! softer one, then a rest.
 
 #![no_std]
 #![no_main]
@@ -19,16 +20,17 @@ extern crate alloc;
 
 esp_bootloader_esp_idf::esp_app_desc!();
 
+static HEARTBEAT: [HapticSegment; 4] = [
+    HapticSegment::hold(255, Duration::from_millis(80)),
+    HapticSegment::pause(Duration::from_millis(120)),
+    HapticSegment::ramp(200, 60, Duration::from_millis(100)),
+    HapticSegment::pause(Duration::from_secs(1)),
+];
+
 #[embassy_executor::task]
 async fn vibration_task(motor: &'static mut Vibration) {
     info!("Vibration task started — heartbeat pattern");
-
-    loop {
-        motor.pulse(Duration::from_millis(80)).await;
-        Timer::after(Duration::from_millis(120)).await;
-        motor.pulse(Duration::from_millis(80)).await;
-        Timer::after(Duration::from_secs(1)).await;
-    }
+    motor.play(HapticPattern::new(&HEARTBEAT).with_loop(true)).await;
 }
 
 #[esp_rtos::main]
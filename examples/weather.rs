@@ -0,0 +1,108 @@
+//! Weather widget: joins Wi-Fi and shows the weather from wttr.in, updated
+//! every 10 minutes. Build with `WIFI_SSID=... WIFI_PASSWORD=...` set, and
+//! optionally `WEATHER_CITY=...` (Helsinki by default).
+//!
+//! Needs the `http` feature: `cargo run --release --features http --example weather`
+
+#![no_std]
+#![no_main]
+
+use defmt::{error, info, warn};
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_10X20},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use http::{HttpClient, Runner, SOCKETS, StackResources};
+use wifi::{Wifi, WifiDevice};
+
+extern crate alloc;
+use alloc::format;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+const SSID: Option<&str> = option_env!("WIFI_SSID");
+const PASSWORD: Option<&str> = option_env!("WIFI_PASSWORD");
+const CITY: &str = match option_env!("WEATHER_CITY") {
+    Some(city) => city,
+    None => "Helsinki",
+};
+const UPDATE_INTERVAL: Duration = Duration::from_secs(600);
+
+#[embassy_executor::task]
+async fn wifi_task(mut wifi: Wifi, ssid: &'static str, password: &'static str) {
+    wifi.stay_connected(ssid, password).await
+}
+
+#[embassy_executor::task]
+async fn net_task(mut runner: Runner<'static, WifiDevice<'static>>) {
+    runner.run().await
+}
+
+fn show(display: &mut Display<'static>, line: &str, color: Rgb565) {
+    let _ = display.clear(Rgb565::BLACK);
+    let style = MonoTextStyle::new(&FONT_10X20, color);
+    // Up to two lines of 30 characters.
+    let (first, second) = match line.char_indices().nth(30) {
+        Some((split, _)) => line.split_at(split),
+        None => (line, ""),
+    };
+    for (text, y) in [(first, 75), (second, 100)] {
+        let _ = Text::with_alignment(text.trim(), Point::new(160, y), style, Alignment::Center)
+            .draw(display);
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let mut backlight: Backlight = resources.backlight.into();
+    backlight.on();
+
+    let (Some(ssid), Some(password)) = (SSID, PASSWORD) else {
+        error!("Build with WIFI_SSID and WIFI_PASSWORD set");
+        show(display, "Set WIFI_SSID and WIFI_PASSWORD", Rgb565::RED);
+        core::future::pending().await
+    };
+    show(display, &format!("Joining {ssid}"), Rgb565::CSS_GRAY);
+
+    let (wifi, device) = Wifi::new(resources.wifi).unwrap();
+    spawner.must_spawn(wifi_task(wifi, ssid, password));
+    let net = mk_static!(StackResources<SOCKETS>, StackResources::new());
+    let (stack, runner) = http::stack(device, net);
+    spawner.must_spawn(net_task(runner));
+    stack.wait_config_up().await;
+
+    // `%l: %t %C`: place, temperature and conditions on one line.
+    let url = format!("http://wttr.in/{CITY}?format=%l:+%t+%C");
+    let mut client = HttpClient::new(stack);
+    let mut buf = [0; 1024];
+    loop {
+        match client.get(&url, &mut buf).await {
+            Ok(response) if response.is_success() => {
+                info!("{=str}", response.text());
+                show(display, response.text().trim(), Rgb565::WHITE);
+            }
+            Ok(response) => warn!("wttr.in: status {}", response.status),
+            Err(err) => warn!("wttr.in: {}", err),
+        }
+        Timer::after(UPDATE_INTERVAL).await;
+    }
+}
@@ -0,0 +1,82 @@
+//! Lists nearby Wi-Fi networks every 10 seconds, on screen with how crowded
+//! each channel is. Build with `WIFI_SSID=... WIFI_PASSWORD=...` set to
+//! join one instead and log its signal strength.
+//!
+//! Needs the `wifi` feature: `cargo run --release --features wifi --example wifi_scan`
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+#[allow(clippy::wildcard_imports)]
+use disobey2026badge::*;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use esp_backtrace as _;
+use esp_hal::timer::timg::TimerGroup;
+use esp_println as _;
+use wifi::{Wifi, survey};
+
+extern crate alloc;
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+#[embassy_executor::task]
+async fn wifi_task(mut wifi: Wifi, display: &'static mut Display<'static>) {
+    if let (Some(ssid), Some(password)) = (option_env!("WIFI_SSID"), option_env!("WIFI_PASSWORD")) {
+        loop {
+            if !wifi.is_connected()
+                && let Err(err) = wifi.connect(ssid, password).await
+            {
+                info!("joining {=str} failed: {}", ssid, err);
+            }
+            if let Some(rssi) = wifi.rssi() {
+                info!("{=str}: {} dBm", ssid, rssi);
+            }
+            Timer::after(Duration::from_secs(10)).await;
+        }
+    }
+    loop {
+        match wifi.survey().await {
+            Ok((found, survey)) => {
+                info!("{} networks:", found.len());
+                for ap in &found {
+                    info!(
+                        "  {=str} ch {} {} dBm {=str}",
+                        ap.ssid.as_str(),
+                        ap.channel,
+                        ap.signal_strength,
+                        survey::security(ap.auth_method)
+                    );
+                }
+                info!("quietest channel: {}", survey.quietest().channel);
+                let _ = survey::render(&found, &survey, display);
+            }
+            Err(err) => info!("scan failed: {}", err),
+        }
+        Timer::after(Duration::from_secs(10)).await;
+    }
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    let peripherals = disobey2026badge::init();
+    let resources = split_resources!(peripherals);
+
+    esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+    esp_alloc::heap_allocator!(size: 64 * 1024);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    let display = mk_static!(Display<'static>, resources.display.into());
+    let mut backlight: Backlight = resources.backlight.into();
+    backlight.on();
+
+    let (wifi, _device) = Wifi::new(resources.wifi).unwrap();
+    spawner.must_spawn(wifi_task(wifi, display));
+
+    loop {
+        Timer::after(Duration::from_secs(600)).await;
+    }
+}
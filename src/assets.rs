@@ -0,0 +1,312 @@
+//! Artwork and other read-only data in their own flash partition, behind
+//! the `assets` feature, so it can change without rebuilding firmware and
+//! doesn't take up room in every app image.
+//!
+//! ```rust,ignore
+//! let mut flash: FlashStorage = resources.flash.into();
+//! let assets = Assets::new(&mut flash)?;
+//!
+//! let logo = Bmp::<Rgb565>::from_slice(assets.get("logo").unwrap())?;
+//! Image::new(&logo, Point::zero()).draw(display)?;
+//!
+//! let ship: Sprite<16, 12> = assets.sprite("ship").unwrap();
+//! ```
+//!
+//! The partition is the one labelled [`PARTITION`], like in
+//! `partitions.csv` in the repository root. It's mapped into the address
+//! space the way the app's own constants are, so [`Assets::get`] returns a
+//! `&'static [u8]` read straight from flash through the cache: nothing is
+//! copied into RAM, and the flash can still be handed on to
+//! `storage::FlashStore` or `fs::Fs`.
+//! The partition is read-only while the badge runs.
+//!
+//! ## Bundles
+//!
+//! The partition holds one bundle, all fields little-endian:
+//!
+//! | Bytes | Field                                                  |
+//! |-------|--------------------------------------------------------|
+//! | 4     | `b"BDAS"`                                              |
+//! | 2     | number of assets                                       |
+//! | 2     | reserved, 0                                            |
+//! | 4     | bundle length, with this header                        |
+//! | 4     | CRC-32 of the rest of the bundle                       |
+//! | 32 ×n | per asset: name (20 bytes, zero-padded), [`AssetKind`] byte, 3 reserved bytes, offset from the start of the bundle and length, `u32` each |
+//! | …     | the assets                                             |
+//!
+//! Build one from files with this script, naming each asset after its
+//! file or with `name=path`, and flash it over USB at the partition's
+//! offset:
+//!
+//! ```text
+//! # python pack.py assets.bin logo=examples/assets/skrolli.bmp ship.rgb565
+//! # espflash write-bin 0x3a0000 assets.bin
+//! import os, struct, sys, zlib
+//!
+//! KINDS = {".bmp": 1, ".rgb565": 2}
+//! assets = []
+//! for arg in sys.argv[2:]:
+//!     name, _, path = arg.rpartition("=")
+//!     stem, ext = os.path.splitext(os.path.basename(path))
+//!     assets.append(((name or stem).encode(), KINDS.get(ext, 0), open(path, "rb").read()))
+//!
+//! directory, data = b"", b""
+//! for name, kind, blob in assets:
+//!     assert len(name) <= 20, name
+//!     offset = 16 + 32 * len(assets) + len(data)
+//!     directory += struct.pack("<20sB3xII", name, kind, offset, len(blob))
+//!     data += blob + bytes(-len(blob) % 4)
+//! body = directory + data
+//! header = struct.pack("<4sHHII", b"BDAS", len(assets), 0, 16 + len(body), zlib.crc32(body))
+//! open(sys.argv[1], "wb").write(header + body)
+//! ```
+
+use core::cell::Cell;
+
+use defmt::info;
+use embassy_sync::blocking_mutex::{
+    Mutex,
+    raw::CriticalSectionRawMutex,
+};
+use esp_bootloader_esp_idf::partitions::{
+    self,
+    PARTITION_TABLE_MAX_LEN,
+};
+use esp_hal::rom::crc::crc32_le;
+
+use crate::{
+    FlashStorage,
+    sprite::Sprite,
+};
+
+/// Label of the partition assets are kept in.
+pub const PARTITION: &str = "assets";
+/// Longest asset name, in bytes.
+pub const MAX_NAME_LEN: usize = 20;
+
+const MAGIC: [u8; 4] = *b"BDAS";
+const HEADER_LEN: usize = 16;
+const ENTRY_LEN: usize = 32;
+
+/// Start of the data bus's window onto flash and PSRAM.
+const DROM_ORIGIN: u32 = 0x3c00_0000;
+/// The MMU table, shared by the instruction and data buses.
+const MMU_TABLE: *const u32 = 0x600c_5000 as *const u32;
+const MMU_ENTRIES: usize = 512;
+const MMU_PAGE: u32 = 0x1_0000;
+const MMU_INVALID: u32 = 1 << 14;
+const MMU_ACCESS_FLASH: u32 = 0;
+
+unsafe extern "C" {
+    fn Cache_Suspend_DCache() -> u32;
+    fn Cache_Resume_DCache(autoload: u32);
+    fn cache_dbus_mmu_set(
+        ext_ram: u32,
+        vaddr: u32,
+        paddr: u32,
+        psize: u32,
+        num: u32,
+        fixed: u32,
+    ) -> i32;
+}
+
+/// The mapped partition, once [`Assets::new`] has mapped it.
+static MAPPED: Mutex<CriticalSectionRawMutex, Cell<Option<&'static [u8]>>> =
+    Mutex::new(Cell::new(None));
+
+/// Errors from [`Assets::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum AssetError {
+    /// The partition table has no partition labelled [`PARTITION`].
+    Partition(partitions::Error),
+    /// The MMU has no free pages left to map the partition into.
+    NoRoom,
+    /// The partition is erased: no bundle has been flashed.
+    Empty,
+    /// The bundle's header or directory is invalid, or its CRC doesn't
+    /// match, as after flashing was cut short.
+    Corrupt,
+}
+
+impl From<partitions::Error> for AssetError {
+    fn from(err: partitions::Error) -> Self {
+        Self::Partition(err)
+    }
+}
+
+/// What an asset holds, as the packer guessed from its file extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum AssetKind {
+    /// Anything else; also kinds newer than this firmware.
+    Raw,
+    /// A BMP file, for `tinybmp`.
+    Bmp,
+    /// Raw big-endian Rgb565 pixels, as a [`Sprite`] takes.
+    Rgb565,
+}
+
+impl AssetKind {
+    const fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => Self::Bmp,
+            2 => Self::Rgb565,
+            _ => Self::Raw,
+        }
+    }
+}
+
+/// One asset in the bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Asset {
+    pub name: &'static str,
+    pub kind: AssetKind,
+    pub data: &'static [u8],
+}
+
+/// The asset bundle in the [`PARTITION`] partition. See the
+/// [module docs](self).
+#[derive(Clone, Copy, Debug)]
+pub struct Assets {
+    bundle: &'static [u8],
+}
+
+impl Assets {
+    /// Find the [`PARTITION`] partition, map it and check the bundle in it.
+    ///
+    /// The flash is only used to read the partition table; the mapping
+    /// lasts until reset, and later calls return the same one.
+    pub fn new(flash: &mut FlashStorage<'_>) -> Result<Self, AssetError> {
+        let partition = MAPPED.lock(|mapped| {
+            if let Some(partition) = mapped.get() {
+                return Ok(partition);
+            }
+            let mut table = [0; PARTITION_TABLE_MAX_LEN];
+            let table = partitions::read_partition_table(flash, &mut table)?;
+            let entry = table
+                .iter()
+                .find(|entry| entry.label_as_str() == PARTITION)
+                .ok_or(partitions::Error::Invalid)?;
+            let partition = map(entry.offset(), entry.len())?;
+            mapped.set(Some(partition));
+            Ok::<_, AssetError>(partition)
+        })?;
+        let assets = Self::parse(partition)?;
+        info!("assets: {} in {} bytes", assets.len(), assets.bundle.len());
+        Ok(assets)
+    }
+
+    /// Number of assets in the bundle.
+    pub fn len(&self) -> usize {
+        usize::from(u16::from_le_bytes([self.bundle[4], self.bundle[5]]))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every asset, in the order they were packed.
+    pub fn iter(&self) -> impl Iterator<Item = Asset> + '_ {
+        let bundle = self.bundle;
+        bundle[HEADER_LEN..HEADER_LEN + ENTRY_LEN * self.len()]
+            .chunks_exact(ENTRY_LEN)
+            .map(move |entry| {
+                let name = &entry[..MAX_NAME_LEN];
+                let name_len = name
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .unwrap_or(MAX_NAME_LEN);
+                let (offset, len) = (word(entry, 24) as usize, word(entry, 28) as usize);
+                Asset {
+                    name: core::str::from_utf8(&name[..name_len]).unwrap_or_default(),
+                    kind: AssetKind::from_byte(entry[MAX_NAME_LEN]),
+                    data: &bundle[offset..offset + len],
+                }
+            })
+    }
+
+    /// The asset called `name`.
+    pub fn find(&self, name: &str) -> Option<Asset> {
+        self.iter().find(|asset| asset.name == name)
+    }
+
+    /// The bytes of the asset called `name`.
+    pub fn get(&self, name: &str) -> Option<&'static [u8]> {
+        self.find(name).map(|asset| asset.data)
+    }
+
+    /// The asset called `name` as a sprite, if it's [`AssetKind::Rgb565`]
+    /// and the size matches.
+    pub fn sprite<const W: usize, const H: usize>(&self, name: &str) -> Option<Sprite<W, H>> {
+        self.find(name)
+            .filter(|asset| asset.kind == AssetKind::Rgb565)
+            .filter(|asset| asset.data.len() == Sprite::<W, H>::BYTES)
+            .map(|asset| Sprite::from_raw(asset.data))
+    }
+
+    /// Check the bundle at the start of `partition`, so [`Assets::iter`]
+    /// can take its directory as read.
+    fn parse(partition: &'static [u8]) -> Result<Self, AssetError> {
+        let header = partition.get(..HEADER_LEN).ok_or(AssetError::Corrupt)?;
+        if header[..4] == [0xff; 4] {
+            return Err(AssetError::Empty);
+        }
+        if header[..4] != MAGIC {
+            return Err(AssetError::Corrupt);
+        }
+        let count = usize::from(u16::from_le_bytes([header[4], header[5]]));
+        let bundle = partition
+            .get(..word(header, 8) as usize)
+            .filter(|bundle| bundle.len() >= HEADER_LEN + ENTRY_LEN * count)
+            .ok_or(AssetError::Corrupt)?;
+        if crc32_le(0, &bundle[HEADER_LEN..]) != word(header, 12) {
+            return Err(AssetError::Corrupt);
+        }
+        let in_bounds = bundle[HEADER_LEN..HEADER_LEN + ENTRY_LEN * count]
+            .chunks_exact(ENTRY_LEN)
+            .all(|entry| {
+                word(entry, 24)
+                    .checked_add(word(entry, 28))
+                    .is_some_and(|end| end as usize <= bundle.len())
+            });
+        if !in_bounds {
+            return Err(AssetError::Corrupt);
+        }
+        Ok(Self { bundle })
+    }
+}
+
+/// The little-endian `u32` at `at` in `bytes`.
+fn word(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Map `len` bytes of flash from `offset` into the data bus, after the
+/// pages already in use, the way `esp-hal` maps PSRAM.
+fn map(offset: u32, len: u32) -> Result<&'static [u8], AssetError> {
+    let first = offset / MMU_PAGE;
+    let pages = (offset + len).div_ceil(MMU_PAGE) - first;
+    // The bootloader keeps the last entry for its own flash reads.
+    let used = (0..MMU_ENTRIES - 1)
+        .rev()
+        // SAFETY: `i` is within the MMU table.
+        .find(|&i| unsafe { MMU_TABLE.add(i).read_volatile() } != MMU_INVALID)
+        .map_or(0, |i| i + 1) as u32;
+    if used + pages > MMU_ENTRIES as u32 - 1 {
+        return Err(AssetError::NoRoom);
+    }
+    let vaddr = DROM_ORIGIN + used * MMU_PAGE;
+    // SAFETY: the pages mapped were unused, so nothing else reads them.
+    let result = unsafe {
+        let autoload = Cache_Suspend_DCache();
+        let result = cache_dbus_mmu_set(MMU_ACCESS_FLASH, vaddr, first * MMU_PAGE, 64, pages, 0);
+        Cache_Resume_DCache(autoload);
+        result
+    };
+    if result != 0 {
+        return Err(AssetError::NoRoom);
+    }
+    let start = vaddr + offset % MMU_PAGE;
+    // SAFETY: the range was just mapped to flash, which stays mapped and
+    // unchanged until reset.
+    Ok(unsafe { core::slice::from_raw_parts(start as *const u8, len as usize) })
+}
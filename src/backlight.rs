@@ -1,41 +1,244 @@
-//! Display backlight control.
+//! Display backlight control with PWM dimming.
 
-use esp_hal::gpio::{
-    Level,
-    Output,
-    OutputConfig,
+mod idle;
+
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use esp_hal::{
+    gpio::DriveMode,
+    ledc::{
+        LSGlobalClkSource,
+        Ledc,
+        LowSpeed,
+        channel::{
+            self,
+            Channel,
+            ChannelHW as _,
+            ChannelIFace as _,
+        },
+        timer::{
+            self,
+            TimerIFace as _,
+        },
+    },
+    time::Rate,
+};
+pub use idle::{
+    Activity,
+    DEFAULT_DIM_AFTER,
+    DEFAULT_OFF_AFTER,
+    IdleDimmer,
+};
+
+use crate::{
+    BacklightResources,
+    mk_static,
+    power::Allowance,
+    storage::{
+        self,
+        Store,
+    },
+};
+
+/// PWM frequency, well above audible and flicker range.
+const PWM_FREQUENCY: Rate = Rate::from_khz(24);
+
+/// PWM resolution; 11 bits is the most the 80 MHz clock allows at 24 kHz and
+/// leaves enough steps for the dim end of the gamma curve.
+const DUTY_BITS: u32 = 11;
+const DUTY_MAX: u32 = 1 << DUTY_BITS;
+
+/// Duty cycle for each brightness level, following the CIE 1931 lightness
+/// curve so equal steps in level look like equal steps in brightness.
+const CIE_DUTY: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let lightness = i as f32 * 100.0 / 255.0;
+        let luminance = if lightness <= 8.0 {
+            lightness / 903.3
+        } else {
+            let t = (lightness + 16.0) / 116.0;
+            t * t * t
+        };
+        table[i] = (luminance * DUTY_MAX as f32 + 0.5) as u16;
+        i += 1;
+    }
+    table
 };
 
-use crate::BacklightResources;
+/// Interval between brightness updates during a fade.
+const FADE_STEP: Duration = Duration::from_millis(10);
 
 /// Controls the display backlight LED.
+///
+/// Brightness is 0–255 on a perceptual (CIE lightness) scale, driven by an
+/// LEDC PWM channel: level 128 looks about half as bright as 255.
 pub struct Backlight {
-    pin: Output<'static>,
+    channel: Channel<'static, LowSpeed>,
+    level: u8,
+    /// Level restored by [`on`](Self::on) after [`off`](Self::off).
+    last_on: u8,
+    /// Cap from the [power budget](crate::power).
+    max: u8,
 }
 
 impl From<BacklightResources<'static>> for Backlight {
     fn from(res: BacklightResources<'static>) -> Self {
+        let mut ledc = Ledc::new(res.ledc);
+        ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+        let timer = mk_static!(
+            timer::Timer<'static, LowSpeed>,
+            ledc.timer(timer::Number::Timer0)
+        );
+        timer
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty11Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: PWM_FREQUENCY,
+            })
+            .unwrap();
+
+        let mut channel = ledc.channel(channel::Number::Channel0, res.led);
         // Default to backlight ON
+        channel
+            .configure(channel::config::Config {
+                timer,
+                duty_pct: 100,
+                drive_mode: DriveMode::PushPull,
+            })
+            .unwrap();
+
         Self {
-            pin: Output::new(res.led, Level::High, OutputConfig::default()),
+            channel,
+            level: u8::MAX,
+            last_on: u8::MAX,
+            max: u8::MAX,
         }
     }
 }
 
 impl Backlight {
     pub fn on(&mut self) {
-        self.pin.set_high();
+        self.set_brightness(self.last_on);
     }
 
     pub fn off(&mut self) {
-        self.pin.set_low();
+        self.set_brightness(0);
     }
 
     pub fn toggle(&mut self) {
-        self.pin.toggle();
+        if self.is_on() {
+            self.off();
+        } else {
+            self.on();
+        }
     }
 
     pub fn is_on(&self) -> bool {
-        self.pin.is_set_high()
+        self.level > 0
+    }
+
+    /// Current brightness, 0 (off) to 255 (full).
+    pub const fn brightness(&self) -> u8 {
+        self.level
+    }
+
+    /// Set the brightness immediately, 0 (off) to 255 (full).
+    ///
+    /// The panel is driven at no more than [`max_brightness`](Self::max_brightness).
+    pub fn set_brightness(&mut self, level: u8) {
+        let driven = level.min(self.max);
+        self.channel
+            .set_duty_hw(u32::from(CIE_DUTY[usize::from(driven)]));
+        self.level = level;
+        if level > 0 {
+            self.last_on = level;
+        }
+    }
+
+    /// Upper limit applied to every brightness, e.g. from
+    /// [`Allowance::backlight_brightness`].
+    pub const fn max_brightness(&self) -> u8 {
+        self.max
+    }
+
+    /// Change the cap and re-apply the current brightness under it.
+    pub fn set_max_brightness(&mut self, max: u8) {
+        self.max = max;
+        self.set_brightness(self.level);
+    }
+
+    /// Follow a [power budget](crate::power) allowance: cap the brightness
+    /// at its [`backlight_brightness`](Allowance::backlight_brightness).
+    /// [`IdleDimmer`] does this itself.
+    pub fn apply(&mut self, allowance: &Allowance) {
+        self.set_max_brightness(allowance.backlight_brightness);
+    }
+
+    /// Persist the brightness (or the level [`on`](Self::on) would restore,
+    /// if currently off) so [`restore`](Self::restore) can pick it up after
+    /// a reboot.
+    pub fn save<S: Store + ?Sized>(&self, store: &mut S) -> Result<(), S::Error> {
+        store.write(storage::BACKLIGHT_BRIGHTNESS, &[self.last_on])
+    }
+
+    /// Apply the brightness stored by [`save`](Self::save), if any.
+    ///
+    /// Returns the restored level. Nothing stored leaves the backlight as is.
+    pub fn restore<S: Store + ?Sized>(&mut self, store: &mut S) -> Result<Option<u8>, S::Error> {
+        let mut buf = [0];
+        let level = match store.read(storage::BACKLIGHT_BRIGHTNESS, &mut buf)? {
+            Some(1) if buf[0] > 0 => buf[0],
+            _ => return Ok(None),
+        };
+        self.set_brightness(level);
+        Ok(Some(level))
+    }
+
+    /// Smoothly move to `level` over `duration`.
+    ///
+    /// Brightness is interpolated in software along the perceptual scale, so
+    /// fades look even. Cancelling the future leaves the backlight at
+    /// whatever level it had reached.
+    pub async fn fade_to(&mut self, level: u8, duration: Duration) {
+        let from = i32::from(self.level);
+        let to = i32::from(level);
+        // Fading out passes through dim levels; `on` should restore the start.
+        let resume = if self.level > 0 {
+            self.level
+        } else {
+            self.last_on
+        };
+        let start = Instant::now();
+        let total = duration.as_ticks().max(1);
+
+        loop {
+            let elapsed = start.elapsed().as_ticks();
+            if elapsed >= total {
+                break;
+            }
+            let t = (elapsed * 1024 / total) as i32;
+            self.set_brightness((from + (to - from) * t / 1024) as u8);
+            Timer::after(FADE_STEP).await;
+        }
+        self.set_brightness(level);
+        if level == 0 {
+            self.last_on = resume;
+        }
+    }
+
+    /// Fade to the last non-zero brightness.
+    pub async fn fade_in(&mut self, duration: Duration) {
+        self.fade_to(self.last_on, duration).await;
+    }
+
+    /// Fade to black, remembering the current brightness for [`fade_in`](Self::fade_in).
+    pub async fn fade_out(&mut self, duration: Duration) {
+        self.fade_to(0, duration).await;
     }
 }
@@ -0,0 +1,234 @@
+//! Dim and then switch off the backlight when the badge is left alone.
+
+use defmt::warn;
+use embassy_futures::select::{
+    Either,
+    Either3,
+    select,
+    select3,
+};
+use embassy_sync::{
+    blocking_mutex::raw::{
+        CriticalSectionRawMutex,
+        RawMutex,
+    },
+    signal::Signal,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+
+use super::Backlight;
+use crate::{
+    display::{
+        GlanceTicker,
+        PowerMode,
+        SharedDisplay,
+    },
+    power::{
+        self,
+        IdleDisplay,
+        Subsystem,
+    },
+};
+
+/// Signalled by the app whenever the user does something (e.g. presses a button).
+pub type Activity = Signal<CriticalSectionRawMutex, ()>;
+
+/// Default time without input before dimming.
+pub const DEFAULT_DIM_AFTER: Duration = Duration::from_secs(30);
+/// Default time without input before the backlight goes off.
+pub const DEFAULT_OFF_AFTER: Duration = Duration::from_secs(120);
+
+const FADE: Duration = Duration::from_millis(500);
+
+/// Watches an [`Activity`] signal and dims, then turns off, the backlight
+/// after configurable timeouts. The next activity fades it back in.
+///
+/// ```rust,ignore
+/// static ACTIVITY: Activity = Activity::new();
+///
+/// #[embassy_executor::task]
+/// async fn dimmer_task(backlight: &'static mut Backlight) {
+///     IdleDimmer::new().run(backlight, &ACTIVITY).await
+/// }
+///
+/// // elsewhere, on every button press:
+/// ACTIVITY.signal(());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct IdleDimmer {
+    dim_after: Duration,
+    off_after: Duration,
+    dim_level: u8,
+}
+
+impl Default for IdleDimmer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdleDimmer {
+    /// Dim to 1/8 brightness after [`DEFAULT_DIM_AFTER`], off after [`DEFAULT_OFF_AFTER`].
+    pub const fn new() -> Self {
+        Self {
+            dim_after: DEFAULT_DIM_AFTER,
+            off_after: DEFAULT_OFF_AFTER,
+            dim_level: 32,
+        }
+    }
+
+    /// Idle time before dimming.
+    #[must_use]
+    pub const fn with_dim_after(mut self, timeout: Duration) -> Self {
+        self.dim_after = timeout;
+        self
+    }
+
+    /// Idle time before switching off, counted from the last activity.
+    #[must_use]
+    pub const fn with_off_after(mut self, timeout: Duration) -> Self {
+        self.off_after = timeout;
+        self
+    }
+
+    /// Brightness used while dimmed.
+    #[must_use]
+    pub const fn with_dim_level(mut self, level: u8) -> Self {
+        self.dim_level = level;
+        self
+    }
+
+    /// Run forever, controlling `backlight` based on `activity`.
+    ///
+    /// The brightness the backlight has when this starts (or when activity
+    /// resumes) is what gets restored.
+    ///
+    /// The dimmer follows the [power budget](crate::power): the backlight
+    /// is capped at the allowance's brightness, and dims after the
+    /// allowance's [`dim_after`](power::Allowance::dim_after) if that comes
+    /// sooner than [`with_dim_after`](Self::with_dim_after).
+    pub async fn run(&self, backlight: &mut Backlight, activity: &Activity) -> ! {
+        self.cycle::<CriticalSectionRawMutex>(backlight, activity, None)
+            .await
+    }
+
+    /// Like [`run`](Self::run), but rather than going dark the badge drops
+    /// to glance mode: the backlight goes off and only `ticker`'s band of
+    /// the panel is driven, showing the time and its text, until the next
+    /// activity switches the whole panel back on. While the allowance's
+    /// [`idle_display`](power::Allowance::idle_display) is [`IdleDisplay::Dark`],
+    /// as in battery-saver mode, it goes dark instead.
+    ///
+    /// The ticker draws over whatever was in its band, so apps should
+    /// redraw once [`Display::power_mode`](crate::Display::power_mode) is
+    /// [`PowerMode::Full`] again.
+    pub async fn run_glance<M: RawMutex>(
+        &self,
+        backlight: &mut Backlight,
+        display: &SharedDisplay<'_, M>,
+        ticker: &mut GlanceTicker<'_>,
+        activity: &Activity,
+    ) -> ! {
+        self.cycle(backlight, activity, Some((display, ticker)))
+            .await
+    }
+
+    async fn cycle<M: RawMutex>(
+        &self,
+        backlight: &mut Backlight,
+        activity: &Activity,
+        mut glance: Option<(&SharedDisplay<'_, M>, &mut GlanceTicker<'_>)>,
+    ) -> ! {
+        // Without a slot, changes still apply each time the badge wakes.
+        let mut budget = power::BUDGET.register();
+        if budget.is_none() {
+            warn!("idle dimmer: no power budget slot left");
+        }
+        let off_delay = self
+            .off_after
+            .checked_sub(self.dim_after)
+            .unwrap_or_default();
+        loop {
+            let allowance = power::BUDGET.allowance();
+            backlight.apply(&allowance);
+            let awake = backlight.brightness();
+            let dim_after = self.dim_after.min(allowance.dim_after);
+
+            if idle_for(activity, dim_after, backlight, &mut budget).await {
+                backlight.fade_to(self.dim_level.min(awake), FADE).await;
+                if idle_for(activity, off_delay, backlight, &mut budget).await {
+                    backlight.fade_out(FADE).await;
+                    match &mut glance {
+                        Some((display, ticker))
+                            if power::BUDGET.allowance().idle_display == IdleDisplay::Glance =>
+                        {
+                            glance_until_active(display, ticker, activity).await;
+                        }
+                        _ => activity.wait().await,
+                    }
+                }
+            }
+
+            if backlight.brightness() != awake {
+                backlight.fade_to(awake, FADE / 2).await;
+            }
+        }
+    }
+}
+
+/// Keep `ticker` going in glance mode until there's activity, then switch
+/// the panel back to [`PowerMode::Full`].
+async fn glance_until_active<M: RawMutex>(
+    display: &SharedDisplay<'_, M>,
+    ticker: &mut GlanceTicker<'_>,
+    activity: &Activity,
+) {
+    let entered = {
+        let mut display = display.lock().await;
+        ticker
+            .draw(&mut *display)
+            .and_then(|()| display.set_power_mode(ticker.mode()))
+    };
+    if let Err(err) = entered {
+        warn!("glance mode failed: {}", err);
+        activity.wait().await;
+        return;
+    }
+    while let Either::Second(()) = select(activity.wait(), Timer::after(GlanceTicker::STEP)).await {
+        if let Err(err) = ticker.draw(&mut *display.lock().await) {
+            warn!("glance ticker failed: {}", err);
+        }
+    }
+    if let Err(err) = display.lock().await.set_power_mode(PowerMode::Full) {
+        warn!("leaving glance mode failed: {}", err);
+    }
+}
+
+/// `true` if `timeout` passed without activity; `false` if activity came first.
+///
+/// Budget changes on the way are applied to `backlight`.
+async fn idle_for(
+    activity: &Activity,
+    timeout: Duration,
+    backlight: &mut Backlight,
+    budget: &mut Option<Subsystem<'_>>,
+) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let changed = async {
+            match budget {
+                Some(budget) => budget.changed().await,
+                None => core::future::pending().await,
+            }
+        };
+        match select3(activity.wait(), Timer::at(deadline), changed).await {
+            Either3::First(()) => return false,
+            Either3::Second(()) => return true,
+            Either3::Third(allowance) => backlight.apply(&allowance),
+        }
+    }
+}
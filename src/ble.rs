@@ -0,0 +1,379 @@
+//! Bluetooth LE peripheral with a "Disobey badge" GATT service, behind the
+//! `ble` feature.
+//!
+//! Phones (nRF Connect, a web page using Web Bluetooth, …) find the badge by
+//! its service UUID and can read who it belongs to and what it is running,
+//! and write it a short message:
+//!
+//! | Characteristic | UUID | Access |
+//! |---|---|---|
+//! | Service | [`BADGE_SERVICE_UUID`] | |
+//! | Name | [`NAME_UUID`] | read |
+//! | Handle | [`HANDLE_UUID`] | read |
+//! | Current app | [`APP_UUID`] | read, notify |
+//! | Message | [`MESSAGE_UUID`] | read, write |
+//! | Badge ID | [`ID_UUID`] | read |
+//!
+//! The badge ID is the 6-byte [`BadgeId`](crate::BadgeId) MAC; everything
+//! else is a UTF-8 string. One task runs the radio, any number of
+//! tasks receive its [`BleEvent`]s:
+//!
+//! ```rust,ignore
+//! static BLE: BadgeBle = BadgeBle::new();
+//!
+//! #[embassy_executor::task]
+//! async fn ble_task(bt: BtResources<'static>) {
+//!     BLE.run(bt, config::NAME, "@hacker").await
+//! }
+//!
+//! // in any other task
+//! BLE.set_app("snake");
+//! let mut events = BLE.subscribe().unwrap();
+//! loop {
+//!     if let BleEvent::Message(text) = events.next().await {
+//!         info!("phone says {}", text.as_str());
+//!     }
+//! }
+//! ```
+//!
+//! As with `wifi`, the radio needs the heap and `esp_rtos::start` set up
+//! before [`BadgeBle::run`], on the same core and outside a critical section.
+//!
+//! For "who's around me" without connections, see [`beacon`] instead.
+
+pub mod beacon;
+
+use core::fmt::Write as _;
+
+use embassy_futures::{
+    join::join,
+    select::{
+        Either,
+        select,
+    },
+};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    pubsub::{
+        PubSubChannel,
+        Subscriber,
+        WaitResult,
+    },
+    watch::Watch,
+};
+use embassy_time::{
+    Duration,
+    Timer,
+};
+use esp_radio::ble::controller::BleConnector;
+use trouble_host::prelude::*;
+
+use crate::{
+    BtResources,
+    radio,
+};
+
+/// The badge service, as it appears in advertisements.
+pub const BADGE_SERVICE_UUID: u128 = 0xd15b0e26_2026_4bad_9e00_000000000000;
+/// Owner's name characteristic.
+pub const NAME_UUID: u128 = 0xd15b0e26_2026_4bad_9e00_000000000001;
+/// Owner's handle characteristic.
+pub const HANDLE_UUID: u128 = 0xd15b0e26_2026_4bad_9e00_000000000002;
+/// Name of the app on screen, notified when it changes.
+pub const APP_UUID: u128 = 0xd15b0e26_2026_4bad_9e00_000000000003;
+/// Message from a phone, see [`BleEvent::Message`].
+pub const MESSAGE_UUID: u128 = 0xd15b0e26_2026_4bad_9e00_000000000004;
+/// The badge's [`BadgeId`](crate::BadgeId), for telling badges apart.
+pub const ID_UUID: u128 = 0xd15b0e26_2026_4bad_9e00_000000000005;
+
+/// Longest name or handle served; longer ones are cut.
+pub const TEXT_LEN: usize = 32;
+/// Longest message a phone can write.
+pub const MESSAGE_LEN: usize = 64;
+/// Events buffered per subscriber before the slowest one starts missing
+/// some.
+pub const BLE_CAPACITY: usize = 8;
+/// How many tasks can [`subscribe`](BadgeBle::subscribe) at once.
+pub const MAX_BLE_SUBSCRIBERS: usize = 4;
+/// How long [`BadgeBle::run`] waits before advertising again after an error.
+pub const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest advertised name: what's left of a 31-byte scan response.
+const ADVERTISED_NAME_LEN: usize = 29;
+const CONNECTIONS_MAX: usize = 1;
+/// Signalling and ATT.
+const L2CAP_CHANNELS_MAX: usize = 2;
+/// HCI commands the controller takes before answering.
+const CONTROLLER_SLOTS: usize = 20;
+
+type BleController = ExternalController<BleConnector<'static>, CONTROLLER_SLOTS>;
+type BleHostResources = HostResources<DefaultPacketPool, CONNECTIONS_MAX, L2CAP_CHANNELS_MAX>;
+
+/// A UTF-8 string of at most `N` bytes, as served over GATT.
+pub type Text<const N: usize> = HeaplessString<N>;
+
+/// What happened on the radio.
+#[derive(Clone, Debug, PartialEq, defmt::Format)]
+pub enum BleEvent {
+    /// A phone connected. Only one can be at a time; the badge stops
+    /// advertising until it leaves.
+    Connected,
+    Disconnected,
+    /// A phone wrote the message characteristic.
+    Message(Text<MESSAGE_LEN>),
+}
+
+#[gatt_server]
+struct BadgeServer {
+    badge: BadgeService,
+}
+
+#[gatt_service(uuid = BADGE_SERVICE_UUID)]
+struct BadgeService {
+    #[characteristic(uuid = NAME_UUID, read)]
+    owner_name: Text<TEXT_LEN>,
+    // Not `handle`: the macro gives the service a field of that name.
+    #[characteristic(uuid = HANDLE_UUID, read)]
+    owner_handle: Text<TEXT_LEN>,
+    #[characteristic(uuid = APP_UUID, read, notify)]
+    app: Text<TEXT_LEN>,
+    #[characteristic(uuid = MESSAGE_UUID, read, write)]
+    message: Text<MESSAGE_LEN>,
+    #[characteristic(uuid = ID_UUID, read)]
+    id: [u8; 6],
+}
+
+/// The badge as a BLE peripheral, shared between the task running the radio
+/// and the tasks using it. See the [module docs](self).
+pub struct BadgeBle {
+    events: PubSubChannel<CriticalSectionRawMutex, BleEvent, BLE_CAPACITY, MAX_BLE_SUBSCRIBERS, 1>,
+    app: Watch<CriticalSectionRawMutex, Text<TEXT_LEN>, 1>,
+}
+
+impl Default for BadgeBle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BadgeBle {
+    pub const fn new() -> Self {
+        Self {
+            events: PubSubChannel::new(),
+            app: Watch::new(),
+        }
+    }
+
+    /// Start the radio and serve the badge service forever, advertising
+    /// whenever no phone is connected. `name` is also the advertised device
+    /// name. An empty `handle` serves the [`BadgeId`](crate::BadgeId) name,
+    /// e.g. `brave-otter-42`.
+    ///
+    /// If the radio fails to start the error is logged and this never
+    /// returns; connection errors are logged and advertising restarts after
+    /// [`RETRY_DELAY`].
+    pub async fn run(&self, res: BtResources<'static>, name: &str, handle: &str) -> ! {
+        let controller = start(res).await;
+        let mut resources = BleHostResources::new();
+        let stack = trouble_host::new(controller, &mut resources);
+        let Host {
+            mut peripheral,
+            mut runner,
+            ..
+        } = stack.build();
+
+        let name = truncate::<TEXT_LEN>(name);
+        let advertised = truncate::<ADVERTISED_NAME_LEN>(&name);
+        let server = match BadgeServer::new_with_config(GapConfig::Peripheral(PeripheralConfig {
+            name: &advertised,
+            appearance: &appearance::UNKNOWN,
+        })) {
+            Ok(server) => server,
+            Err(err) => {
+                defmt::error!("ble: building the GATT table failed: {}", err);
+                core::future::pending().await
+            }
+        };
+        let badge = &server.badge;
+        let _ = badge.owner_name.set(&server, &name);
+        let _ = badge.owner_handle.set(&server, &handle_or_id(handle));
+        let _ = badge.id.set(&server, &crate::id().mac());
+
+        let host = async {
+            loop {
+                if let Err(err) = runner.run().await {
+                    defmt::error!("ble: host stopped: {}", err);
+                    Timer::after(RETRY_DELAY).await;
+                }
+            }
+        };
+        let serve = async {
+            loop {
+                if let Err(err) = self.serve(&mut peripheral, &server, &advertised).await {
+                    defmt::warn!("ble: {}", err);
+                    Timer::after(RETRY_DELAY).await;
+                }
+            }
+        };
+        match join(host, serve).await {}
+    }
+
+    /// Advertise, then handle one connection until it drops.
+    async fn serve<C: Controller>(
+        &self,
+        peripheral: &mut Peripheral<'_, C, DefaultPacketPool>,
+        server: &BadgeServer<'_>,
+        name: &str,
+    ) -> Result<(), BleHostError<C::Error>> {
+        let badge = &server.badge;
+        let mut apps = self.app.receiver();
+        if let Some(app) = self.app.try_get() {
+            let _ = badge.app.set(server, &app);
+        }
+
+        let service = BADGE_SERVICE_UUID.to_le_bytes();
+        let mut adv_data = [0; 31];
+        let adv_len = AdStructure::encode_slice(
+            &[
+                AdStructure::Flags(LE_GENERAL_DISCOVERABLE | BR_EDR_NOT_SUPPORTED),
+                AdStructure::ServiceUuids128(&[service]),
+            ],
+            &mut adv_data,
+        )?;
+        let mut scan_data = [0; 31];
+        let scan_len = AdStructure::encode_slice(
+            &[AdStructure::CompleteLocalName(name.as_bytes())],
+            &mut scan_data,
+        )?;
+
+        let advertiser = peripheral
+            .advertise(
+                &AdvertisementParameters::default(),
+                Advertisement::ConnectableScannableUndirected {
+                    adv_data: &adv_data[..adv_len],
+                    scan_data: &scan_data[..scan_len],
+                },
+            )
+            .await?;
+        let conn = advertiser.accept().await?.with_attribute_server(server)?;
+        let publisher = self.events.immediate_publisher();
+        publisher.publish_immediate(BleEvent::Connected);
+
+        loop {
+            let changed = async {
+                match apps.as_mut() {
+                    Some(apps) => apps.changed().await,
+                    None => core::future::pending().await,
+                }
+            };
+            match select(conn.next(), changed).await {
+                Either::First(GattConnectionEvent::Disconnected { reason }) => {
+                    defmt::info!("ble: disconnected: {}", reason);
+                    publisher.publish_immediate(BleEvent::Disconnected);
+                    return Ok(());
+                }
+                Either::First(GattConnectionEvent::Gatt {
+                    event: GattEvent::Write(event),
+                }) if event.handle() == badge.message.handle => match event.value(&badge.message) {
+                    Ok(text) => {
+                        event.accept()?.send().await;
+                        publisher.publish_immediate(BleEvent::Message(text));
+                    }
+                    Err(_) => event.reject(AttErrorCode::VALUE_NOT_ALLOWED)?.send().await,
+                },
+                // Reads and everything else are answered from the table
+                // when the event drops.
+                Either::First(_) => {}
+                Either::Second(app) => {
+                    if let Err(err) = badge.app.notify(&conn, &app).await {
+                        defmt::warn!("ble: app notification failed: {}", err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Tell phones which app is on screen. Cut to [`TEXT_LEN`] bytes.
+    pub fn set_app(&self, app: &str) {
+        self.app.sender().send(truncate(app));
+    }
+
+    /// Start receiving events, or `None` if [`MAX_BLE_SUBSCRIBERS`] already
+    /// are. Only events published after this call are seen.
+    pub fn subscribe(&self) -> Option<BleSubscriber<'_>> {
+        self.events
+            .subscriber()
+            .ok()
+            .map(|subscriber| BleSubscriber { subscriber })
+    }
+}
+
+/// A task's view of a [`BadgeBle`]. Dropping it frees the slot.
+pub struct BleSubscriber<'a> {
+    subscriber:
+        Subscriber<'a, CriticalSectionRawMutex, BleEvent, BLE_CAPACITY, MAX_BLE_SUBSCRIBERS, 1>,
+}
+
+impl BleSubscriber<'_> {
+    /// Wait for the next event.
+    pub async fn next(&mut self) -> BleEvent {
+        loop {
+            match self.subscriber.next_message().await {
+                WaitResult::Message(event) => return event,
+                WaitResult::Lagged(missed) => {
+                    defmt::warn!("ble: subscriber missed {} events", missed)
+                }
+            }
+        }
+    }
+
+    /// The next event if one is already waiting.
+    pub fn try_next(&mut self) -> Option<BleEvent> {
+        loop {
+            match self.subscriber.try_next_message()? {
+                WaitResult::Message(event) => return Some(event),
+                WaitResult::Lagged(missed) => {
+                    defmt::warn!("ble: subscriber missed {} events", missed)
+                }
+            }
+        }
+    }
+}
+
+/// Start the radio and its HCI transport, or log why not and never return.
+async fn start(res: BtResources<'static>) -> BleController {
+    let connector = match radio::controller() {
+        Ok(controller) => BleConnector::new(controller, res.bt, Default::default()),
+        Err(err) => {
+            defmt::error!("ble: radio failed to start: {}", err);
+            core::future::pending().await
+        }
+    };
+    let Ok(connector) = connector else {
+        defmt::error!("ble: bad controller config");
+        core::future::pending().await
+    };
+    ExternalController::new(connector)
+}
+
+/// `handle`, or the badge ID's name if it's empty.
+fn handle_or_id<const N: usize>(handle: &str) -> Text<N> {
+    if !handle.is_empty() {
+        return truncate(handle);
+    }
+    let mut text = Text::new();
+    // The longest ID name is 20 bytes; anything shorter is cut.
+    let _ = write!(text, "{}", crate::id());
+    text
+}
+
+/// `text` cut to at most `N` bytes on a character boundary.
+fn truncate<const N: usize>(text: &str) -> Text<N> {
+    let mut out = Text::new();
+    for c in text.chars() {
+        if out.push(c).is_err() {
+            break;
+        }
+    }
+    out
+}
@@ -0,0 +1,279 @@
+//! Connectionless badge beacons: broadcast a handle and mood colour, and
+//! list the badges nearby with their signal strength.
+//!
+//! Every badge advertises, non-connectable, its handle as the local name and
+//! manufacturer data `"DB" r g b` under company ID `0xFFFF` (reserved for
+//! testing), while passively scanning for the same from others. Nothing is
+//! ever connected, so any number of badges see each other at once.
+//!
+//! ```rust,ignore
+//! static BEACON: Beacon = Beacon::new();
+//!
+//! #[embassy_executor::task]
+//! async fn beacon_task(bt: BtResources<'static>) {
+//!     BEACON.run(bt, "@hacker").await
+//! }
+//!
+//! // in the UI task
+//! BEACON.set_mood(Srgb::new(255, 0, 128));
+//! for badge in BEACON.nearby().iter().flatten() {
+//!     info!("{} {} dBm", badge.handle.as_str(), badge.rssi);
+//! }
+//! ```
+//!
+//! The beacon and [`BadgeBle`](super::BadgeBle) both take the radio; run one
+//! or the other.
+
+use core::cell::RefCell;
+
+use embassy_futures::join::join3;
+use embassy_sync::{
+    blocking_mutex::{
+        Mutex,
+        raw::CriticalSectionRawMutex,
+    },
+    watch::Watch,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use palette::Srgb;
+use trouble_host::prelude::*;
+
+use super::{
+    BleHostResources,
+    RETRY_DELAY,
+    Text,
+    handle_or_id,
+    start,
+    truncate,
+};
+use crate::BtResources;
+
+/// Longest handle a beacon carries; longer ones are cut.
+pub const BEACON_HANDLE_LEN: usize = 20;
+/// How many nearby badges are remembered.
+pub const MAX_NEARBY: usize = 16;
+/// A badge not heard from for this long is no longer nearby.
+pub const NEARBY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time between advertisements.
+pub const BEACON_INTERVAL: Duration = Duration::from_millis(500);
+/// Mood advertised until [`Beacon::set_mood`] is called.
+pub const DEFAULT_MOOD: Srgb<u8> = Srgb::new(255, 255, 255);
+
+/// Bluetooth SIG company ID reserved for testing, free for everyone to use.
+const COMPANY_ID: u16 = 0xffff;
+/// First bytes of the manufacturer data, telling badges from other
+/// `0xffff` devices.
+const MAGIC: [u8; 2] = *b"DB";
+
+/// A badge heard nearby.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sighting {
+    /// Bluetooth address the beacon came from.
+    pub address: [u8; 6],
+    pub handle: Text<BEACON_HANDLE_LEN>,
+    pub mood: Srgb<u8>,
+    /// Signal strength in dBm; closer badges are louder.
+    pub rssi: i8,
+    /// When it was last heard.
+    pub seen: Instant,
+}
+
+impl Sighting {
+    fn expired(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.seen) > NEARBY_TIMEOUT
+    }
+}
+
+/// This badge's beacon and the ones it hears. See the [module docs](self).
+pub struct Beacon {
+    mood: Watch<CriticalSectionRawMutex, Srgb<u8>, 1>,
+    nearby: Mutex<CriticalSectionRawMutex, RefCell<[Option<Sighting>; MAX_NEARBY]>>,
+}
+
+impl Default for Beacon {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Beacon {
+    pub const fn new() -> Self {
+        Self {
+            mood: Watch::new(),
+            nearby: Mutex::new(RefCell::new([const { None }; MAX_NEARBY])),
+        }
+    }
+
+    /// Start the radio, then advertise `handle` and the mood and listen for
+    /// other badges forever. An empty `handle` advertises the
+    /// [`BadgeId`](crate::BadgeId) name instead.
+    ///
+    /// Like [`BadgeBle::run`](super::BadgeBle::run), a radio that fails to
+    /// start is logged and this never returns; other errors are retried
+    /// after [`RETRY_DELAY`].
+    pub async fn run(&self, res: BtResources<'static>, handle: &str) -> ! {
+        let Some(mut moods) = self.mood.receiver() else {
+            defmt::error!("ble: beacon already running");
+            core::future::pending().await
+        };
+        let controller = start(res).await;
+        let mut resources = BleHostResources::new();
+        let stack = trouble_host::new(controller, &mut resources);
+        let Host {
+            mut peripheral,
+            central,
+            mut runner,
+            ..
+        } = stack.build();
+        let handle = handle_or_id::<BEACON_HANDLE_LEN>(handle);
+
+        let host = async {
+            loop {
+                if let Err(err) = runner.run_with_handler(&Reports(self)).await {
+                    defmt::error!("ble: host stopped: {}", err);
+                    Timer::after(RETRY_DELAY).await;
+                }
+            }
+        };
+        let advertise = async {
+            let mut mood = self.mood.try_get().unwrap_or(DEFAULT_MOOD);
+            loop {
+                match advertise(&mut peripheral, &handle, mood).await {
+                    // Keep advertising until the mood changes.
+                    Ok(_advertiser) => mood = moods.changed().await,
+                    Err(err) => {
+                        defmt::warn!("ble: beacon: {}", err);
+                        Timer::after(RETRY_DELAY).await;
+                    }
+                }
+            }
+        };
+        let scan = async {
+            let mut scanner = Scanner::new(central);
+            let config = ScanConfig {
+                active: false,
+                ..Default::default()
+            };
+            loop {
+                match scanner.scan(&config).await {
+                    Ok(_session) => core::future::pending::<()>().await,
+                    Err(err) => {
+                        defmt::warn!("ble: scan: {}", err);
+                        Timer::after(RETRY_DELAY).await;
+                    }
+                }
+            }
+        };
+        match join3(host, advertise, scan).await {}
+    }
+
+    /// Change the colour this badge advertises.
+    pub fn set_mood(&self, mood: Srgb<u8>) {
+        self.mood.sender().send(mood);
+    }
+
+    /// Badges heard within [`NEARBY_TIMEOUT`], strongest signal first.
+    pub fn nearby(&self) -> [Option<Sighting>; MAX_NEARBY] {
+        let now = Instant::now();
+        let mut nearby = self.nearby.lock(|nearby| nearby.borrow().clone());
+        for slot in &mut nearby {
+            if slot.as_ref().is_some_and(|sighting| sighting.expired(now)) {
+                *slot = None;
+            }
+        }
+        // `None` sorts before `Some`, so reverse for strongest first, empty
+        // slots last.
+        nearby.sort_unstable_by_key(|slot| core::cmp::Reverse(slot.as_ref().map(|s| s.rssi)));
+        nearby
+    }
+
+    /// Remember `sighting`, replacing the same badge's last one, or the
+    /// oldest if all slots are taken.
+    fn seen(&self, sighting: Sighting) {
+        self.nearby.lock(|nearby| {
+            let mut nearby = nearby.borrow_mut();
+            let slot = match nearby
+                .iter()
+                .position(|slot| slot.as_ref().is_some_and(|s| s.address == sighting.address))
+            {
+                Some(index) => index,
+                None => nearby
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, slot)| slot.as_ref().map(|s| s.seen))
+                    .map_or(0, |(index, _)| index),
+            };
+            nearby[slot] = Some(sighting);
+        });
+    }
+}
+
+/// Start one non-connectable advertisement; it runs until dropped.
+async fn advertise<'d, C: Controller>(
+    peripheral: &mut Peripheral<'d, C, DefaultPacketPool>,
+    handle: &str,
+    mood: Srgb<u8>,
+) -> Result<Advertiser<'d, C, DefaultPacketPool>, BleHostError<C::Error>> {
+    let payload = [MAGIC[0], MAGIC[1], mood.red, mood.green, mood.blue];
+    let mut adv_data = [0; 31];
+    let len = AdStructure::encode_slice(
+        &[
+            AdStructure::ManufacturerSpecificData {
+                company_identifier: COMPANY_ID,
+                payload: &payload,
+            },
+            AdStructure::CompleteLocalName(handle.as_bytes()),
+        ],
+        &mut adv_data,
+    )?;
+    let params = AdvertisementParameters {
+        interval_min: BEACON_INTERVAL,
+        interval_max: BEACON_INTERVAL,
+        ..Default::default()
+    };
+    peripheral
+        .advertise(
+            &params,
+            Advertisement::NonconnectableNonscannableUndirected {
+                adv_data: &adv_data[..len],
+            },
+        )
+        .await
+}
+
+/// Pick badge beacons out of scan results.
+struct Reports<'a>(&'a Beacon);
+
+impl EventHandler for Reports<'_> {
+    fn on_adv_reports(&self, reports: LeAdvReportsIter) {
+        for report in reports.flatten() {
+            let mut mood = None;
+            let mut handle = Text::new();
+            for structure in AdStructure::decode(report.data).flatten() {
+                match structure {
+                    AdStructure::ManufacturerSpecificData {
+                        company_identifier: COMPANY_ID,
+                        payload: [m0, m1, red, green, blue, ..],
+                    } if [*m0, *m1] == MAGIC => mood = Some(Srgb::new(*red, *green, *blue)),
+                    AdStructure::CompleteLocalName(name) => {
+                        handle = truncate(core::str::from_utf8(name).unwrap_or_default());
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(mood) = mood {
+                self.0.seen(Sighting {
+                    address: report.addr.into_inner(),
+                    handle,
+                    mood,
+                    rssi: report.rssi,
+                    seen: Instant::now(),
+                });
+            }
+        }
+    }
+}
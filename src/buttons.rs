@@ -2,54 +2,259 @@
 //!
 //! The badge has a D-pad (up/down/left/right), A, B, Start, Select,
 //! and a joystick click button.
+//!
+//! All async waits are driven by GPIO edge interrupts, so a task blocked on
+//! a button sleeps until it changes. Polling the pins directly still works
+//! for code that prefers it.
+
+mod boot;
+mod chord;
+mod click;
+mod events;
+mod hub;
+#[cfg(feature = "input-injection")]
+pub mod inject;
+mod input;
+mod monitor;
+mod remap;
+mod wake;
 
+pub use boot::BootButton;
+pub use chord::{
+    Chord,
+    ChordDetector,
+};
+pub use click::{
+    ClickDetector,
+    ClickKind,
+    DEFAULT_CLICK_WINDOW,
+};
+use embassy_futures::select::{
+    Either,
+    select,
+    select_array,
+};
 use embassy_time::{
     Duration,
+    Instant,
     Timer,
 };
 use esp_hal::gpio::{
+    AnyPin,
     Input,
     InputConfig,
+    Pin,
+};
+pub use events::{
+    Button,
+    ButtonEvent,
+    ButtonSet,
+    ButtonState,
+    DEFAULT_LONG_PRESS,
+    EventConfig,
+    EventTracker,
+    MIN_REPEAT_INTERVAL,
+};
+pub use hub::{
+    InputHub,
+    InputSubscriber,
+};
+pub use input::ButtonInput;
+pub use monitor::{
+    ButtonMonitor,
+    Chords,
+};
+pub use remap::{
+    Action,
+    Keymap,
+};
+pub use wake::{
+    ButtonWake,
+    WakeError,
 };
 
-use crate::ButtonResources;
+use crate::{
+    ButtonResources,
+    stats,
+};
 
 /// All nine badge buttons, ready for polling or async edge detection.
+///
+/// Every async wait and event stream is debounced with the same settle time,
+/// [`DEFAULT_DEBOUNCE`] unless changed with [`with_debounce`](Self::with_debounce).
+///
+/// ```rust,ignore
+/// buttons.a.wait_for_press().await;
+/// if buttons.select.is_pressed() { /* … */ }
+/// ```
 pub struct Buttons {
-    pub up: Input<'static>,
-    pub down: Input<'static>,
-    pub left: Input<'static>,
-    pub right: Input<'static>,
-    pub stick: Input<'static>,
-    pub a: Input<'static>,
-    pub b: Input<'static>,
-    pub start: Input<'static>,
-    pub select: Input<'static>,
+    pub up: ButtonInput,
+    pub down: ButtonInput,
+    pub left: ButtonInput,
+    pub right: ButtonInput,
+    pub stick: ButtonInput,
+    pub a: ButtonInput,
+    pub b: ButtonInput,
+    pub start: ButtonInput,
+    pub select: ButtonInput,
+    last_snapshot: ButtonSet,
+    /// GPIO numbers, in [`Button::ALL`] order.
+    pins: [u8; 9],
 }
 
-const DEBOUNCE_MS: u64 = 20;
+/// Settle time applied to every button unless changed with
+/// [`Buttons::set_debounce`].
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(20);
 
 impl From<ButtonResources<'static>> for Buttons {
     fn from(res: ButtonResources<'static>) -> Self {
         let pull_up = InputConfig::default().with_pull(esp_hal::gpio::Pull::Up);
+        let low = |pin: AnyPin<'static>| ButtonInput::active_low(Input::new(pin, pull_up));
+        let pins = [
+            res.up.number(),
+            res.down.number(),
+            res.left.number(),
+            res.right.number(),
+            res.stick.number(),
+            res.a.number(),
+            res.b.number(),
+            res.start.number(),
+            res.select.number(),
+        ];
         Self {
-            up: Input::new(res.up, pull_up),
-            down: Input::new(res.down, pull_up),
-            left: Input::new(res.left, pull_up),
-            right: Input::new(res.right, pull_up),
-            stick: Input::new(res.stick, pull_up),
-            a: Input::new(res.a, pull_up),
-            b: Input::new(res.b, pull_up),
-            start: Input::new(res.start, pull_up),
-            select: Input::new(
+            up: low(res.up.into()),
+            down: low(res.down.into()),
+            left: low(res.left.into()),
+            right: low(res.right.into()),
+            stick: low(res.stick.into()),
+            a: low(res.a.into()),
+            b: low(res.b.into()),
+            start: low(res.start.into()),
+            // Select is pulled down and reads high when pressed.
+            select: ButtonInput::active_high(Input::new(
                 res.select,
                 InputConfig::default().with_pull(esp_hal::gpio::Pull::Down),
-            ),
+            )),
+            last_snapshot: ButtonSet::EMPTY,
+            pins,
         }
     }
 }
 
 impl Buttons {
+    /// Use `debounce` as the settle time for all nine buttons.
+    #[must_use]
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.set_debounce(debounce);
+        self
+    }
+
+    /// Change the settle time for all nine buttons.
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        for input in self.all_mut() {
+            input.set_debounce(debounce);
+        }
+    }
+
+    /// The longest settle time of any button.
+    ///
+    /// Waits that watch several buttons at once use this, so each one
+    /// is given at least its own interval.
+    pub fn debounce(&self) -> Duration {
+        Button::ALL
+            .into_iter()
+            .map(|b| self.get(b).debounce())
+            .max()
+            .unwrap_or(DEFAULT_DEBOUNCE)
+    }
+
+    /// Sample all nine buttons together.
+    ///
+    /// The pins are read back-to-back inside a critical section, so no
+    /// interrupt or task switch can land between them. The `just_*` queries
+    /// on the result compare against the previous call.
+    ///
+    /// ```rust,ignore
+    /// loop {
+    ///     let input = buttons.snapshot();
+    ///     if input.is_pressed(Button::Left) { paddle.left(); }
+    ///     if input.just_pressed(Button::A) { launch(); }
+    ///     Timer::after(tick).await;
+    /// }
+    /// ```
+    pub fn snapshot(&mut self) -> ButtonState {
+        let held = critical_section::with(|_| {
+            Button::ALL
+                .into_iter()
+                .filter(|&b| self.get(b).is_pressed())
+                .collect()
+        });
+        let previous = core::mem::replace(&mut self.last_snapshot, held);
+        ButtonState::new(held, previous)
+    }
+
+    /// The input for `button`.
+    pub const fn get(&self, button: Button) -> &ButtonInput {
+        match button {
+            Button::Up => &self.up,
+            Button::Down => &self.down,
+            Button::Left => &self.left,
+            Button::Right => &self.right,
+            Button::Stick => &self.stick,
+            Button::A => &self.a,
+            Button::B => &self.b,
+            Button::Start => &self.start,
+            Button::Select => &self.select,
+        }
+    }
+
+    /// All inputs, in [`Button::ALL`] order.
+    pub const fn all_mut(&mut self) -> [&mut ButtonInput; 9] {
+        [
+            &mut self.up,
+            &mut self.down,
+            &mut self.left,
+            &mut self.right,
+            &mut self.stick,
+            &mut self.a,
+            &mut self.b,
+            &mut self.start,
+            &mut self.select,
+        ]
+    }
+
+    /// A debounced stream of [`ButtonEvent`]s with default timing.
+    ///
+    /// ```rust,ignore
+    /// let mut events = buttons.events();
+    /// loop {
+    ///     match events.next().await {
+    ///         ButtonEvent::Pressed(Button::A) => fire(),
+    ///         ButtonEvent::Repeat(Button::Left) => move_left(),
+    ///         ButtonEvent::Click(Button::B, ClickKind::Double) => undo(),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn events(&mut self) -> ButtonEvents<'_> {
+        self.events_with(EventConfig::default())
+    }
+
+    /// A debounced stream of [`ButtonEvent`]s with custom long-press and
+    /// repeat timing.
+    pub fn events_with(&mut self, config: EventConfig) -> ButtonEvents<'_> {
+        let mut tracker = EventTracker::new(config);
+        // Buttons already held when the stream starts don't produce a press.
+        let now = Instant::now();
+        for button in Button::ALL {
+            tracker.update(button, self.get(button).is_pressed(), now);
+        }
+        ButtonEvents {
+            buttons: self,
+            tracker,
+        }
+    }
+
     /// Wait for a full press-and-release cycle with debouncing.
     pub async fn debounce_press_and_release(button: &mut Input<'_>) {
         Self::debounce_press(button).await;
@@ -57,10 +262,14 @@ impl Buttons {
     }
 
     /// Wait for a debounced button press (falling edge, active low).
+    ///
+    /// A raw [`Input`] carries no settings, so this always uses
+    /// [`DEFAULT_DEBOUNCE`]; [`ButtonInput::wait_for_press`] honours
+    /// [`set_debounce`](Self::set_debounce).
     pub async fn debounce_press(button: &mut Input<'_>) {
         loop {
             button.wait_for_falling_edge().await;
-            Timer::after(Duration::from_millis(DEBOUNCE_MS)).await;
+            Timer::after(DEFAULT_DEBOUNCE).await;
             if button.is_low() {
                 return;
             }
@@ -71,10 +280,75 @@ impl Buttons {
     pub async fn debounce_release(button: &mut Input<'_>) {
         loop {
             button.wait_for_rising_edge().await;
-            Timer::after(Duration::from_millis(DEBOUNCE_MS)).await;
+            Timer::after(DEFAULT_DEBOUNCE).await;
             if button.is_high() {
                 return;
             }
         }
     }
+
+    /// Wait for a single, double or triple click; the same as
+    /// [`ButtonInput::wait_for_clicks`].
+    pub async fn wait_for_clicks(button: &mut ButtonInput, window: Duration) -> ClickKind {
+        button.wait_for_clicks(window).await
+    }
+}
+
+/// Stream of [`ButtonEvent`]s from [`Buttons::events`].
+pub struct ButtonEvents<'a> {
+    buttons: &'a mut Buttons,
+    tracker: EventTracker,
+}
+
+impl ButtonEvents<'_> {
+    /// Wait for the next event.
+    pub async fn next(&mut self) -> ButtonEvent {
+        loop {
+            let now = Instant::now();
+            #[cfg(feature = "input-injection")]
+            let injected = inject::held();
+            for button in Button::ALL {
+                let pressed = self.buttons.get(button).is_pressed();
+                #[cfg(feature = "input-injection")]
+                let pressed = pressed || injected.contains(button);
+                if let Some(event) = self.tracker.update(button, pressed, now) {
+                    if let ButtonEvent::Pressed(_) = event {
+                        stats::count_press();
+                    }
+                    return event;
+                }
+            }
+            if let Some(event) = self.tracker.due(now) {
+                return event;
+            }
+
+            // Resolves to whether the wake-up needs debouncing.
+            let edge = async {
+                select_array(self.buttons.all_mut().map(|b| b.wait_for_any_edge())).await;
+                true
+            };
+            #[cfg(feature = "input-injection")]
+            let edge = async {
+                match select(edge, inject::changed()).await {
+                    Either::First(settle) => settle,
+                    Either::Second(()) => false,
+                }
+            };
+            let settle = match self.tracker.deadline() {
+                Some(deadline) => {
+                    matches!(select(edge, Timer::at(deadline)).await, Either::First(true))
+                }
+                None => edge.await,
+            };
+            if settle {
+                // Let the contacts settle before sampling.
+                Timer::after(self.buttons.debounce()).await;
+            }
+        }
+    }
+
+    /// The underlying timing state, e.g. to check what's held.
+    pub const fn tracker(&self) -> &EventTracker {
+        &self.tracker
+    }
 }
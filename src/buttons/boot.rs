@@ -0,0 +1,71 @@
+//! The BOOT button on GPIO0, usable as an extra key once the chip is running.
+
+use core::ops::{
+    Deref,
+    DerefMut,
+};
+
+use esp_hal::gpio::{
+    Input,
+    InputConfig,
+    Pull,
+};
+
+use super::ButtonInput;
+use crate::BootResources;
+
+/// The BOOT button, separate from the nine [`Buttons`](super::Buttons).
+///
+/// Holding it during reset enters the ROM bootloader; after boot it's an
+/// ordinary active-low button, e.g. for a "menu" key. Dereferences to a
+/// [`ButtonInput`], so the same debounced `wait_for_*` methods apply.
+///
+/// ```rust,ignore
+/// let mut boot: BootButton = resources.boot.into();
+/// if boot.wait_for_long_press(Duration::from_secs(1)).await {
+///     open_settings();
+/// }
+/// ```
+///
+/// It isn't a [`Button`](super::Button), so it never shows up in
+/// [`Buttons::events`](super::Buttons::events), a
+/// [`ButtonSet`](super::ButtonSet) or an [`InputHub`](super::InputHub).
+/// Those cover the front panel, and every app matches on `Button`; a
+/// tenth variant would break all of those matches for a key most apps
+/// don't use, and would tie GPIO0 to [`Buttons`](super::Buttons) for
+/// firmware that wants the pin for something else. Wait on it alongside
+/// the event stream instead:
+///
+/// ```rust,ignore
+/// match select(events.next(), boot.wait_for_click()).await {
+///     Either::First(event) => handle(event),
+///     Either::Second(()) => open_menu(),
+/// }
+/// ```
+pub struct BootButton {
+    input: ButtonInput,
+}
+
+impl From<BootResources<'static>> for BootButton {
+    fn from(res: BootResources<'static>) -> Self {
+        // GPIO0 is a strapping pin with its own pull-up; enable ours too.
+        let config = InputConfig::default().with_pull(Pull::Up);
+        Self {
+            input: ButtonInput::active_low(Input::new(res.pin, config)),
+        }
+    }
+}
+
+impl Deref for BootButton {
+    type Target = ButtonInput;
+
+    fn deref(&self) -> &Self::Target {
+        &self.input
+    }
+}
+
+impl DerefMut for BootButton {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.input
+    }
+}
@@ -0,0 +1,121 @@
+//! Button combinations that fire once when held together.
+
+use embassy_time::{
+    Duration,
+    Instant,
+};
+
+use super::ButtonSet;
+
+/// Buttons that must all be held, optionally for a while, to trigger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Chord {
+    pub buttons: ButtonSet,
+    pub hold: Duration,
+}
+
+impl Chord {
+    /// Fires as soon as every one of `buttons` is held.
+    pub const fn new(buttons: ButtonSet) -> Self {
+        Self {
+            buttons,
+            hold: Duration::from_ticks(0),
+        }
+    }
+
+    /// Only fire once the buttons have been held together for `hold`.
+    #[must_use]
+    pub const fn with_hold(mut self, hold: Duration) -> Self {
+        self.hold = hold;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    chord: Chord,
+    since: Option<Instant>,
+    fired: bool,
+}
+
+/// Watches the held [`ButtonSet`] for up to `N` registered [`Chord`]s.
+///
+/// Each chord fires once per hold; letting go of any of its buttons re-arms
+/// it. Feed it with [`update`](Self::update) whenever the held set changes
+/// and again at [`deadline`](Self::deadline).
+///
+/// ```rust,ignore
+/// let mut chords = ChordDetector::<2>::new();
+/// let reset = chords
+///     .register(Chord::new(ButtonSet::of(&[Button::Start, Button::Select]))
+///         .with_hold(Duration::from_secs(1)))
+///     .unwrap();
+/// let screenshot = chords.register(Chord::new(ButtonSet::of(&[Button::A, Button::B]))).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ChordDetector<const N: usize> {
+    slots: [Option<Slot>; N],
+}
+
+impl<const N: usize> Default for ChordDetector<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ChordDetector<N> {
+    pub const fn new() -> Self {
+        Self { slots: [None; N] }
+    }
+
+    /// Add a chord, returning the id [`update`](Self::update) reports it
+    /// by, or `None` if all `N` slots are taken.
+    pub fn register(&mut self, chord: Chord) -> Option<usize> {
+        let id = self.slots.iter().position(Option::is_none)?;
+        self.slots[id] = Some(Slot {
+            chord,
+            since: None,
+            fired: false,
+        });
+        Some(id)
+    }
+
+    /// Remove the chord registered as `id`.
+    pub fn unregister(&mut self, id: usize) {
+        if let Some(slot) = self.slots.get_mut(id) {
+            *slot = None;
+        }
+    }
+
+    /// Record the buttons `held` at `now` and return the id of a chord that
+    /// just fired.
+    ///
+    /// Several chords can fire at once; call again until it returns `None`.
+    pub fn update(&mut self, held: ButtonSet, now: Instant) -> Option<usize> {
+        let mut fired = None;
+        for (id, slot) in self.slots.iter_mut().enumerate() {
+            let Some(slot) = slot else { continue };
+            if !held.contains_all(slot.chord.buttons) {
+                slot.since = None;
+                slot.fired = false;
+                continue;
+            }
+            let since = *slot.since.get_or_insert(now);
+            if fired.is_none() && !slot.fired && now >= since + slot.chord.hold {
+                slot.fired = true;
+                fired = Some(id);
+            }
+        }
+        fired
+    }
+
+    /// When the next held-but-not-yet-fired chord is due.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|s| !s.fired)
+            .filter_map(|s| s.since.map(|t| t + s.chord.hold))
+            .min()
+    }
+}
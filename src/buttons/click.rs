@@ -0,0 +1,83 @@
+//! Single/double/triple click recognition.
+
+use embassy_time::{
+    Duration,
+    Instant,
+};
+
+/// Default time allowed between releases for them to count as one gesture.
+pub const DEFAULT_CLICK_WINDOW: Duration = Duration::from_millis(300);
+
+/// How many times a button was clicked in quick succession.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ClickKind {
+    Single,
+    Double,
+    Triple,
+}
+
+/// Groups press-and-release cycles into [`ClickKind`]s.
+///
+/// Feed it every release with [`on_release`](Self::on_release) and call
+/// [`poll`](Self::poll) once [`deadline`](Self::deadline) has passed. A third
+/// click is reported immediately, since nothing longer is recognised.
+#[derive(Clone, Copy, Debug)]
+pub struct ClickDetector {
+    window: Duration,
+    count: u8,
+    last_release: Option<Instant>,
+}
+
+impl Default for ClickDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_CLICK_WINDOW)
+    }
+}
+
+impl ClickDetector {
+    /// Create a detector with the given multi-click window.
+    pub const fn new(window: Duration) -> Self {
+        Self {
+            window,
+            count: 0,
+            last_release: None,
+        }
+    }
+
+    /// Record a release at `now`. Returns [`ClickKind::Triple`] straight away
+    /// on the third click; shorter sequences are reported by [`poll`](Self::poll).
+    pub fn on_release(&mut self, now: Instant) -> Option<ClickKind> {
+        self.count += 1;
+        self.last_release = Some(now);
+        if self.count >= 3 {
+            self.reset();
+            return Some(ClickKind::Triple);
+        }
+        None
+    }
+
+    /// When the pending sequence will be finalised, if one is in progress.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.last_release.map(|t| t + self.window)
+    }
+
+    /// Emit the pending sequence if its window has expired by `now`.
+    pub fn poll(&mut self, now: Instant) -> Option<ClickKind> {
+        let deadline = self.deadline()?;
+        if now < deadline {
+            return None;
+        }
+        let kind = match self.count {
+            1 => ClickKind::Single,
+            _ => ClickKind::Double,
+        };
+        self.reset();
+        Some(kind)
+    }
+
+    /// Drop any partially recognised sequence.
+    pub const fn reset(&mut self) {
+        self.count = 0;
+        self.last_release = None;
+    }
+}
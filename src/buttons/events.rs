@@ -0,0 +1,393 @@
+//! Button identities, events and the timing logic behind long-press, repeat
+//! and multi-click.
+
+use embassy_time::{
+    Duration,
+    Instant,
+};
+
+use super::{
+    ClickDetector,
+    ClickKind,
+    DEFAULT_CLICK_WINDOW,
+};
+
+/// One of the nine badge buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, defmt::Format)]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    Stick,
+    A,
+    B,
+    Start,
+    Select,
+}
+
+impl Button {
+    /// Every button, in [`index`](Self::index) order.
+    pub const ALL: [Self; 9] = [
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::Stick,
+        Self::A,
+        Self::B,
+        Self::Start,
+        Self::Select,
+    ];
+
+    /// Position in [`ALL`](Self::ALL), handy for per-button arrays.
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A set of buttons, e.g. those currently held.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, defmt::Format)]
+pub struct ButtonSet(u16);
+
+impl ButtonSet {
+    pub const EMPTY: Self = Self(0);
+    pub const ALL: Self = Self((1 << Button::ALL.len()) - 1);
+
+    /// A set of exactly `buttons`.
+    pub const fn of(buttons: &[Button]) -> Self {
+        let mut set = Self::EMPTY;
+        let mut i = 0;
+        while i < buttons.len() {
+            set = set.with(buttons[i]);
+            i += 1;
+        }
+        set
+    }
+
+    #[must_use]
+    pub const fn with(self, button: Button) -> Self {
+        Self(self.0 | 1 << button.index())
+    }
+
+    #[must_use]
+    pub const fn without(self, button: Button) -> Self {
+        Self(self.0 & !(1 << button.index()))
+    }
+
+    pub const fn contains(self, button: Button) -> bool {
+        self.0 & 1 << button.index() != 0
+    }
+
+    /// Whether every button in `other` is also in `self`.
+    pub const fn contains_all(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// The raw bitmask, bit `n` being [`Button::ALL`]`[n]`.
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Button> {
+        Button::ALL.into_iter().filter(move |&b| self.contains(b))
+    }
+}
+
+impl FromIterator<Button> for ButtonSet {
+    fn from_iter<I: IntoIterator<Item = Button>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::EMPTY, Self::with)
+    }
+}
+
+/// All buttons sampled at one instant, compared with the sample before.
+///
+/// Returned by [`Buttons::snapshot`](super::Buttons::snapshot); take one per
+/// game tick and read everything from it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct ButtonState {
+    held: ButtonSet,
+    previous: ButtonSet,
+}
+
+impl ButtonState {
+    /// A state where `held` are down now and `previous` were last time.
+    pub const fn new(held: ButtonSet, previous: ButtonSet) -> Self {
+        Self { held, previous }
+    }
+
+    /// Every button held in this sample.
+    pub const fn held(self) -> ButtonSet {
+        self.held
+    }
+
+    pub const fn is_pressed(self, button: Button) -> bool {
+        self.held.contains(button)
+    }
+
+    /// Held now but not in the previous sample.
+    pub const fn just_pressed(self, button: Button) -> bool {
+        self.held.contains(button) && !self.previous.contains(button)
+    }
+
+    /// Held in the previous sample but not now.
+    pub const fn just_released(self, button: Button) -> bool {
+        !self.held.contains(button) && self.previous.contains(button)
+    }
+}
+
+/// Something a button did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ButtonEvent {
+    Pressed(Button),
+    Released(Button),
+    /// Held past one of the [`EventConfig::long_press`] stages; carries how
+    /// long the button has been held so far.
+    ///
+    /// Sent once per stage per press, so holding through 0.5 s, 1 s and 2 s
+    /// stages gives three of these, e.g. to fill a "hold to delete" bar.
+    LongPress(Button, Duration),
+    /// Still held; sent after [`EventConfig::repeat_delay`] and then every
+    /// [`EventConfig::repeat_interval`].
+    Repeat(Button),
+    /// Released after one, two or three short presses in a row.
+    ///
+    /// Sent [`EventConfig::click_window`] after the last release (at once
+    /// for a triple), alongside the usual press and release events. Presses
+    /// that reached [`LongPress`](Self::LongPress) don't count.
+    Click(Button, ClickKind),
+}
+
+/// Default [`EventConfig::long_press`] stages.
+pub const DEFAULT_LONG_PRESS: &[Duration] = &[
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+];
+
+/// Shortest [`EventConfig::repeat_interval`]; shorter ones are raised to
+/// it, since a zero interval would repeat without ever waiting.
+pub const MIN_REPEAT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Timing for long-press, auto-repeat and multi-click events.
+///
+/// ```rust,ignore
+/// // Tetris-style delayed auto-shift on the horizontal directions only.
+/// let config = EventConfig::default()
+///     .with_repeat(Duration::from_millis(170), Duration::from_millis(33))
+///     .with_repeat_buttons(ButtonSet::of(&[Button::Left, Button::Right]));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct EventConfig {
+    /// Hold times that each send a [`ButtonEvent::LongPress`], ascending.
+    pub long_press: &'static [Duration],
+    /// How long a button is held before the first [`ButtonEvent::Repeat`].
+    pub repeat_delay: Duration,
+    /// Raised to [`MIN_REPEAT_INTERVAL`] if shorter.
+    pub repeat_interval: Duration,
+    /// Which buttons auto-repeat; the rest only press, release and
+    /// long-press.
+    pub repeat_buttons: ButtonSet,
+    /// Longest gap between releases for them to count as one multi-click.
+    pub click_window: Duration,
+    /// Which buttons report [`ButtonEvent::Click`].
+    pub click_buttons: ButtonSet,
+}
+
+impl Default for EventConfig {
+    fn default() -> Self {
+        Self {
+            long_press: DEFAULT_LONG_PRESS,
+            repeat_delay: Duration::from_millis(300),
+            repeat_interval: Duration::from_millis(80),
+            repeat_buttons: ButtonSet::ALL,
+            click_window: DEFAULT_CLICK_WINDOW,
+            click_buttons: ButtonSet::ALL,
+        }
+    }
+}
+
+impl EventConfig {
+    /// Send a [`ButtonEvent::LongPress`] at each of `stages` (ascending);
+    /// an empty slice turns long-presses off.
+    #[must_use]
+    pub const fn with_long_press(mut self, stages: &'static [Duration]) -> Self {
+        self.long_press = stages;
+        self
+    }
+
+    /// Start repeating after `delay`, then repeat every `interval`, at
+    /// least [`MIN_REPEAT_INTERVAL`].
+    #[must_use]
+    pub const fn with_repeat(mut self, delay: Duration, interval: Duration) -> Self {
+        self.repeat_delay = delay;
+        self.repeat_interval = if interval.as_ticks() < MIN_REPEAT_INTERVAL.as_ticks() {
+            MIN_REPEAT_INTERVAL
+        } else {
+            interval
+        };
+        self
+    }
+
+    /// Only auto-repeat `buttons`.
+    #[must_use]
+    pub const fn with_repeat_buttons(mut self, buttons: ButtonSet) -> Self {
+        self.repeat_buttons = buttons;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_click_window(mut self, window: Duration) -> Self {
+        self.click_window = window;
+        self
+    }
+
+    /// Only report clicks for `buttons`.
+    #[must_use]
+    pub const fn with_click_buttons(mut self, buttons: ButtonSet) -> Self {
+        self.click_buttons = buttons;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct State {
+    pressed_at: Option<Instant>,
+    /// Long-press stages already sent this press.
+    stage: usize,
+    repeat_at: Option<Instant>,
+}
+
+impl State {
+    fn long_at(&self, stages: &[Duration]) -> Option<Instant> {
+        Some(self.pressed_at? + *stages.get(self.stage)?)
+    }
+}
+
+/// Turns debounced button levels into [`ButtonEvent`]s.
+///
+/// Report level changes with [`update`](Self::update) and poll
+/// [`due`](Self::due) once [`deadline`](Self::deadline) has passed.
+#[derive(Clone, Copy, Debug)]
+pub struct EventTracker {
+    config: EventConfig,
+    states: [State; 9],
+    clicks: [ClickDetector; 9],
+    /// Triple clicks, which complete on release and wait here for [`due`](Self::due).
+    ready: [Option<ClickKind>; 9],
+}
+
+impl EventTracker {
+    pub fn new(config: EventConfig) -> Self {
+        Self {
+            config,
+            states: [State::default(); 9],
+            clicks: [ClickDetector::new(config.click_window); 9],
+            ready: [None; 9],
+        }
+    }
+
+    pub const fn config(&self) -> EventConfig {
+        self.config
+    }
+
+    /// Whether `button` is currently considered held.
+    pub const fn is_pressed(&self, button: Button) -> bool {
+        self.states[button.index()].pressed_at.is_some()
+    }
+
+    /// How long `button` has been held at `now`, e.g. for a progress bar
+    /// between [`ButtonEvent::LongPress`] stages.
+    pub fn held_for(&self, button: Button, now: Instant) -> Option<Duration> {
+        let since = self.states[button.index()].pressed_at?;
+        Some(now.saturating_duration_since(since))
+    }
+
+    /// Record that `button` is now `pressed` (or not).
+    ///
+    /// Returns the matching event if that's a change.
+    pub fn update(&mut self, button: Button, pressed: bool, now: Instant) -> Option<ButtonEvent> {
+        let state = &mut self.states[button.index()];
+        if state.pressed_at.is_some() == pressed {
+            return None;
+        }
+        if !pressed && self.config.click_buttons.contains(button) {
+            let clicks = &mut self.clicks[button.index()];
+            if state.stage > 0 {
+                // Held long enough for a long-press: not a click.
+                clicks.reset();
+            } else if let Some(kind) = clicks.on_release(now) {
+                self.ready[button.index()] = Some(kind);
+            }
+        }
+        *state = if pressed {
+            State {
+                pressed_at: Some(now),
+                stage: 0,
+                repeat_at: self
+                    .config
+                    .repeat_buttons
+                    .contains(button)
+                    .then(|| now + self.config.repeat_delay),
+            }
+        } else {
+            State::default()
+        };
+        Some(if pressed {
+            ButtonEvent::Pressed(button)
+        } else {
+            ButtonEvent::Released(button)
+        })
+    }
+
+    /// The earliest time a long-press, repeat or click event is due.
+    pub fn deadline(&self) -> Option<Instant> {
+        if self.ready.iter().any(Option::is_some) {
+            return Some(Instant::MIN);
+        }
+        self.states
+            .iter()
+            .flat_map(|s| [s.long_at(self.config.long_press), s.repeat_at])
+            .chain(self.clicks.iter().map(ClickDetector::deadline))
+            .flatten()
+            .min()
+    }
+
+    /// Take one long-press, repeat or click event that is due at `now`.
+    pub fn due(&mut self, now: Instant) -> Option<ButtonEvent> {
+        for (button, ready) in Button::ALL.into_iter().zip(&mut self.ready) {
+            if let Some(kind) = ready.take() {
+                return Some(ButtonEvent::Click(button, kind));
+            }
+        }
+        for (button, clicks) in Button::ALL.into_iter().zip(&mut self.clicks) {
+            if let Some(kind) = clicks.poll(now) {
+                return Some(ButtonEvent::Click(button, kind));
+            }
+        }
+        for (button, state) in Button::ALL.into_iter().zip(&mut self.states) {
+            if let Some(since) = state.pressed_at
+                && state
+                    .long_at(self.config.long_press)
+                    .is_some_and(|t| t <= now)
+            {
+                state.stage += 1;
+                let held = now.saturating_duration_since(since);
+                return Some(ButtonEvent::LongPress(button, held));
+            }
+            if let Some(t) = state.repeat_at.filter(|&t| t <= now) {
+                state.repeat_at = Some(t + self.config.repeat_interval.max(MIN_REPEAT_INTERVAL));
+                return Some(ButtonEvent::Repeat(button));
+            }
+        }
+        None
+    }
+}
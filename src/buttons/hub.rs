@@ -0,0 +1,108 @@
+//! Broadcasting one button event stream to many tasks.
+
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    pubsub::{
+        PubSubChannel,
+        Subscriber,
+        WaitResult,
+    },
+};
+
+use super::{
+    ButtonEvent,
+    Buttons,
+    EventConfig,
+};
+
+/// Events buffered per subscriber before the slowest one starts missing some.
+pub const HUB_CAPACITY: usize = 16;
+/// How many tasks can [`subscribe`](InputHub::subscribe) at once.
+pub const MAX_SUBSCRIBERS: usize = 6;
+
+/// One task reads the buttons, any number of tasks receive the events.
+///
+/// ```rust,ignore
+/// static INPUT: InputHub = InputHub::new();
+///
+/// #[embassy_executor::task]
+/// async fn input_task(buttons: &'static mut Buttons) {
+///     INPUT.run(buttons, EventConfig::default()).await
+/// }
+///
+/// // in any other task
+/// let mut events = INPUT.subscribe().unwrap();
+/// loop {
+///     if let ButtonEvent::Pressed(Button::A) = events.next().await { fire(); }
+/// }
+/// ```
+pub struct InputHub {
+    channel: PubSubChannel<CriticalSectionRawMutex, ButtonEvent, HUB_CAPACITY, MAX_SUBSCRIBERS, 1>,
+}
+
+impl Default for InputHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputHub {
+    pub const fn new() -> Self {
+        Self {
+            channel: PubSubChannel::new(),
+        }
+    }
+
+    /// Read `buttons` forever and publish every event.
+    ///
+    /// Never waits for subscribers: one that falls more than
+    /// [`HUB_CAPACITY`] events behind skips the oldest.
+    pub async fn run(&self, buttons: &mut Buttons, config: EventConfig) -> ! {
+        let publisher = self.channel.immediate_publisher();
+        let mut events = buttons.events_with(config);
+        loop {
+            publisher.publish_immediate(events.next().await);
+        }
+    }
+
+    /// Start receiving events, or `None` if [`MAX_SUBSCRIBERS`] already
+    /// are. Only events published after this call are seen.
+    pub fn subscribe(&self) -> Option<InputSubscriber<'_>> {
+        self.channel
+            .subscriber()
+            .ok()
+            .map(|subscriber| InputSubscriber { subscriber })
+    }
+}
+
+/// A task's view of an [`InputHub`]. Dropping it frees the slot.
+pub struct InputSubscriber<'a> {
+    subscriber:
+        Subscriber<'a, CriticalSectionRawMutex, ButtonEvent, HUB_CAPACITY, MAX_SUBSCRIBERS, 1>,
+}
+
+impl InputSubscriber<'_> {
+    /// Wait for the next event.
+    pub async fn next(&mut self) -> ButtonEvent {
+        loop {
+            match self.subscriber.next_message().await {
+                WaitResult::Message(event) => return event,
+                WaitResult::Lagged(missed) => {
+                    defmt::warn!("input: subscriber missed {} events", missed)
+                }
+            }
+        }
+    }
+
+    /// The next event if one is waiting.
+    pub fn try_next(&mut self) -> Option<ButtonEvent> {
+        loop {
+            match self.subscriber.try_next_message()? {
+                WaitResult::Message(event) => return Some(event),
+                WaitResult::Lagged(missed) => {
+                    defmt::warn!("input: subscriber missed {} events", missed)
+                }
+            }
+        }
+    }
+}
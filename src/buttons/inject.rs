@@ -0,0 +1,74 @@
+//! Synthetic button presses for on-device tests and remote control.
+//!
+//! Enabled by the `input-injection` feature. Injected buttons read as held
+//! to [`Buttons::events`](super::Buttons::events) on top of the real pins,
+//! so long-press, repeat and click detection all apply to them too.
+//!
+//! ```rust,ignore
+//! // From a test or serial-console task:
+//! inject::click(Button::A, Duration::from_millis(50)).await;
+//! inject::press(Button::Left);
+//! Timer::after(Duration::from_millis(500)).await; // long enough to repeat
+//! inject::release(Button::Left);
+//! ```
+
+use core::cell::Cell;
+
+use embassy_sync::{
+    blocking_mutex::{
+        Mutex,
+        raw::CriticalSectionRawMutex,
+    },
+    signal::Signal,
+};
+use embassy_time::{
+    Duration,
+    Timer,
+};
+
+use super::{
+    Button,
+    ButtonSet,
+};
+
+static HELD: Mutex<CriticalSectionRawMutex, Cell<ButtonSet>> =
+    Mutex::new(Cell::new(ButtonSet::EMPTY));
+static CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Hold `button` down until [`release`](release)d.
+pub fn press(button: Button) {
+    update(|held| held.with(button));
+}
+
+pub fn release(button: Button) {
+    update(|held| held.without(button));
+}
+
+/// Release every injected button.
+pub fn release_all() {
+    update(|_| ButtonSet::EMPTY);
+}
+
+/// Press `button`, hold it for `hold`, then release it.
+pub async fn click(button: Button, hold: Duration) {
+    press(button);
+    Timer::after(hold).await;
+    release(button);
+}
+
+/// Buttons currently held by injection.
+pub fn held() -> ButtonSet {
+    HELD.lock(Cell::get)
+}
+
+/// Wait until the injected set changes.
+///
+/// Only one waiter is woken, so only one event stream should be running.
+pub(super) async fn changed() {
+    CHANGED.wait().await;
+}
+
+fn update(f: impl FnOnce(ButtonSet) -> ButtonSet) {
+    HELD.lock(|held| held.set(f(held.get())));
+    CHANGED.signal(());
+}
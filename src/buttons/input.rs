@@ -0,0 +1,167 @@
+//! A single button that knows which level means "pressed".
+
+use core::ops::{
+    Deref,
+    DerefMut,
+};
+
+use embassy_futures::select::{
+    Either,
+    select,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use esp_hal::gpio::Input;
+
+use super::{
+    ClickDetector,
+    ClickKind,
+    DEFAULT_DEBOUNCE,
+};
+
+/// One badge button.
+///
+/// Dereferences to the underlying [`Input`], so raw level reads keep working,
+/// but prefer [`is_pressed`](Self::is_pressed) and the async `wait_for_*`
+/// methods: they account for the Select button being wired active-high while
+/// the rest are active-low. Waiting is edge-interrupt driven, so a task
+/// blocked on a button costs nothing until it changes.
+pub struct ButtonInput {
+    input: Input<'static>,
+    active_high: bool,
+    debounce: Duration,
+}
+
+impl ButtonInput {
+    /// A button pulled up and shorted to ground when pressed.
+    pub const fn active_low(input: Input<'static>) -> Self {
+        Self {
+            input,
+            active_high: false,
+            debounce: DEFAULT_DEBOUNCE,
+        }
+    }
+
+    /// A button pulled down and connected to 3V3 when pressed.
+    pub const fn active_high(input: Input<'static>) -> Self {
+        Self {
+            input,
+            active_high: true,
+            debounce: DEFAULT_DEBOUNCE,
+        }
+    }
+
+    /// Use `debounce` as the settle time instead of [`DEFAULT_DEBOUNCE`].
+    #[must_use]
+    pub const fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// How long a level must hold before a press or release counts.
+    pub const fn debounce(&self) -> Duration {
+        self.debounce
+    }
+
+    pub const fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// Whether the button is held down right now (not debounced).
+    pub fn is_pressed(&self) -> bool {
+        self.input.is_high() == self.active_high
+    }
+
+    /// Wait for a debounced press. Returns at once if already held.
+    pub async fn wait_for_press(&mut self) {
+        self.wait_for_state(true).await;
+    }
+
+    /// Wait for a debounced release. Returns at once if not held.
+    pub async fn wait_for_release(&mut self) {
+        self.wait_for_state(false).await;
+    }
+
+    /// Wait for a full press-and-release cycle.
+    ///
+    /// If the button is already held, this first waits for it to be let go,
+    /// so a press that started earlier doesn't count.
+    pub async fn wait_for_click(&mut self) {
+        self.wait_for_release().await;
+        self.wait_for_press().await;
+        self.wait_for_release().await;
+    }
+
+    /// Wait for a single, double or triple click.
+    ///
+    /// Clicks whose releases follow each other within `window` (see
+    /// [`DEFAULT_CLICK_WINDOW`](super::DEFAULT_CLICK_WINDOW)) are grouped
+    /// into one [`ClickKind`].
+    pub async fn wait_for_clicks(&mut self, window: Duration) -> ClickKind {
+        let mut detector = ClickDetector::new(window);
+        self.wait_for_release().await;
+        loop {
+            if let Some(deadline) = detector.deadline() {
+                if let Either::Second(()) = select(self.wait_for_press(), Timer::at(deadline)).await
+                {
+                    if let Some(kind) = detector.poll(Instant::now()) {
+                        return kind;
+                    }
+                    continue;
+                }
+            } else {
+                self.wait_for_press().await;
+            }
+            self.wait_for_release().await;
+            if let Some(kind) = detector.on_release(Instant::now()) {
+                return kind;
+            }
+        }
+    }
+
+    /// Wait for a press and report whether it was held for at least `hold`.
+    ///
+    /// Returns `true` as soon as `hold` has passed, without waiting for the
+    /// release, or `false` when released earlier.
+    pub async fn wait_for_long_press(&mut self, hold: Duration) -> bool {
+        self.wait_for_release().await;
+        self.wait_for_press().await;
+        match select(self.wait_for_release(), Timer::after(hold)).await {
+            Either::First(()) => false,
+            Either::Second(()) => true,
+        }
+    }
+
+    async fn wait_for_state(&mut self, pressed: bool) {
+        loop {
+            if self.is_pressed() == pressed {
+                Timer::after(self.debounce).await;
+                if self.is_pressed() == pressed {
+                    return;
+                }
+            }
+            if pressed == self.active_high {
+                self.input.wait_for_high().await;
+            } else {
+                self.input.wait_for_low().await;
+            }
+        }
+    }
+}
+
+impl Deref for ButtonInput {
+    type Target = Input<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.input
+    }
+}
+
+impl DerefMut for ButtonInput {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.input
+    }
+}
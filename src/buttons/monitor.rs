@@ -0,0 +1,149 @@
+//! Interrupt-driven tracking of which buttons are held.
+
+use embassy_futures::select::{
+    select,
+    select_array,
+};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    watch::{
+        Receiver,
+        Watch,
+    },
+};
+use embassy_time::{
+    Instant,
+    Timer,
+};
+
+use super::{
+    Button,
+    ButtonSet,
+    Buttons,
+    ChordDetector,
+};
+
+/// How many tasks can [`watch`](ButtonMonitor::watch) a monitor at once.
+pub const MAX_WATCHERS: usize = 4;
+
+/// Keeps a debounced [`ButtonSet`] of held buttons up to date from GPIO
+/// edge interrupts, instead of every app polling the pins.
+///
+/// Run [`run`](Self::run) in its own task; it sleeps until a pin changes.
+/// Game loops can then read [`held`](Self::held) each frame for free, and
+/// other tasks can [`watch`](Self::watch) for changes. Reading the pins
+/// directly through [`Buttons`] still works as a fallback.
+///
+/// ```rust,ignore
+/// static INPUT: ButtonMonitor = ButtonMonitor::new();
+///
+/// #[embassy_executor::task]
+/// async fn input_task(buttons: &'static mut Buttons) {
+///     INPUT.run(buttons).await
+/// }
+///
+/// // in the game loop
+/// if INPUT.is_pressed(Button::A) { fire(); }
+/// ```
+pub struct ButtonMonitor {
+    watch: Watch<CriticalSectionRawMutex, ButtonSet, MAX_WATCHERS>,
+}
+
+impl Default for ButtonMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ButtonMonitor {
+    pub const fn new() -> Self {
+        Self {
+            watch: Watch::new_with(ButtonSet::EMPTY),
+        }
+    }
+
+    /// Track `buttons` forever.
+    pub async fn run(&self, buttons: &mut Buttons) -> ! {
+        let sender = self.watch.sender();
+        loop {
+            let held = Button::ALL
+                .into_iter()
+                .filter(|&b| buttons.get(b).is_pressed())
+                .collect();
+            sender.send_if_modified(|current| {
+                let changed = *current != Some(held);
+                *current = Some(held);
+                changed
+            });
+
+            select_array(buttons.all_mut().map(|b| b.wait_for_any_edge())).await;
+            // Let the contacts settle before sampling.
+            Timer::after(buttons.debounce()).await;
+        }
+    }
+
+    /// The buttons held as of the last edge.
+    pub fn held(&self) -> ButtonSet {
+        self.watch.try_get().unwrap_or_default()
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.held().contains(button)
+    }
+
+    /// A receiver whose `changed().await` wakes on every change, or `None`
+    /// if [`MAX_WATCHERS`] are already registered.
+    pub fn watch(&self) -> Option<Receiver<'_, CriticalSectionRawMutex, ButtonSet, MAX_WATCHERS>> {
+        self.watch.receiver()
+    }
+
+    /// Wait for the chords in `detector` to fire, using one watcher slot.
+    ///
+    /// `None` if [`MAX_WATCHERS`] are already registered.
+    ///
+    /// ```rust,ignore
+    /// let mut chords = INPUT.chords(detector).unwrap();
+    /// loop {
+    ///     match chords.next().await {
+    ///         id if id == reset => esp_hal::system::software_reset(),
+    ///         _ => take_screenshot(),
+    ///     }
+    /// }
+    /// ```
+    pub fn chords<const N: usize>(&self, detector: ChordDetector<N>) -> Option<Chords<'_, N>> {
+        Some(Chords {
+            receiver: self.watch()?,
+            detector,
+        })
+    }
+}
+
+/// Chord events from [`ButtonMonitor::chords`].
+pub struct Chords<'a, const N: usize> {
+    receiver: Receiver<'a, CriticalSectionRawMutex, ButtonSet, MAX_WATCHERS>,
+    detector: ChordDetector<N>,
+}
+
+impl<const N: usize> Chords<'_, N> {
+    /// Wait for the next chord to fire and return its id.
+    pub async fn next(&mut self) -> usize {
+        loop {
+            let held = self.receiver.try_get().unwrap_or_default();
+            if let Some(id) = self.detector.update(held, Instant::now()) {
+                return id;
+            }
+            match self.detector.deadline() {
+                Some(deadline) => {
+                    select(self.receiver.changed(), Timer::at(deadline)).await;
+                }
+                None => {
+                    self.receiver.changed().await;
+                }
+            }
+        }
+    }
+
+    pub const fn detector(&mut self) -> &mut ChordDetector<N> {
+        &mut self.detector
+    }
+}
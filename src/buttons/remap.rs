@@ -0,0 +1,162 @@
+//! Logical actions and a user-adjustable mapping onto physical buttons.
+
+use super::{
+    Button,
+    ButtonState,
+};
+use crate::storage::{
+    self,
+    Store,
+};
+
+/// What a button means to an app, independent of which one the user presses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, defmt::Format)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Joystick click by default.
+    Stick,
+    /// A by default.
+    Confirm,
+    /// B by default.
+    Cancel,
+    /// Start by default.
+    Menu,
+    /// Select by default.
+    Select,
+}
+
+impl Action {
+    /// Every action, in [`index`](Self::index) order.
+    pub const ALL: [Self; 9] = [
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::Stick,
+        Self::Confirm,
+        Self::Cancel,
+        Self::Menu,
+        Self::Select,
+    ];
+
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// D-pad actions in clockwise order, for [`Keymap::rotate_dpad`].
+const DPAD: [Action; 4] = [Action::Up, Action::Right, Action::Down, Action::Left];
+
+/// A one-to-one mapping between [`Action`]s and physical [`Button`]s.
+///
+/// Every action has exactly one button and vice versa, so rebinding one
+/// action hands its old button to whichever action it displaced.
+///
+/// ```rust,ignore
+/// let keymap = Keymap::load(&mut store)?.unwrap_or_default();
+/// let input = buttons.snapshot();
+/// if keymap.just_pressed(input, Action::Confirm) { select_item(); }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, defmt::Format)]
+pub struct Keymap {
+    /// Button for each action, in [`Action::ALL`] order.
+    buttons: [Button; 9],
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Keymap {
+    /// The printed layout: A confirms, B cancels, Start opens the menu.
+    pub const DEFAULT: Self = Self {
+        buttons: Button::ALL,
+    };
+
+    /// The button that performs `action`.
+    pub const fn button(&self, action: Action) -> Button {
+        self.buttons[action.index()]
+    }
+
+    /// The action `button` performs.
+    pub fn action(&self, button: Button) -> Action {
+        let index = self.buttons.iter().position(|&b| b == button).unwrap_or(0);
+        Action::ALL[index]
+    }
+
+    /// Bind `action` to `button`; the action `button` had moves to the
+    /// button `action` had.
+    #[must_use]
+    pub fn with(mut self, action: Action, button: Button) -> Self {
+        let other = self.action(button);
+        self.buttons.swap(action.index(), other.index());
+        self
+    }
+
+    /// Exchange the buttons of two actions, e.g. Confirm and Cancel.
+    #[must_use]
+    pub const fn swap(mut self, a: Action, b: Action) -> Self {
+        let button = self.buttons[a.index()];
+        self.buttons[a.index()] = self.buttons[b.index()];
+        self.buttons[b.index()] = button;
+        self
+    }
+
+    /// Remap the D-pad for a badge turned `quarter_turns` × 90° clockwise,
+    /// so pressing towards the top of the screen is still [`Action::Up`].
+    #[must_use]
+    pub const fn rotate_dpad(mut self, quarter_turns: u8) -> Self {
+        let old = self.buttons;
+        let turns = quarter_turns as usize % 4;
+        let mut i = 0;
+        while i < DPAD.len() {
+            let from = DPAD[(i + DPAD.len() - turns) % DPAD.len()];
+            self.buttons[DPAD[i].index()] = old[from.index()];
+            i += 1;
+        }
+        self
+    }
+
+    pub const fn is_pressed(&self, state: ButtonState, action: Action) -> bool {
+        state.is_pressed(self.button(action))
+    }
+
+    pub const fn just_pressed(&self, state: ButtonState, action: Action) -> bool {
+        state.just_pressed(self.button(action))
+    }
+
+    pub const fn just_released(&self, state: ButtonState, action: Action) -> bool {
+        state.just_released(self.button(action))
+    }
+
+    /// Persist the mapping so [`load`](Self::load) can pick it up after a
+    /// reboot.
+    pub fn save<S: Store + ?Sized>(&self, store: &mut S) -> Result<(), S::Error> {
+        store.write(storage::KEYMAP, &self.buttons.map(|b| b.index() as u8))
+    }
+
+    /// The mapping stored by [`save`](Self::save), if any.
+    ///
+    /// Anything that isn't a valid one-to-one mapping reads as `None`.
+    pub fn load<S: Store + ?Sized>(store: &mut S) -> Result<Option<Self>, S::Error> {
+        let mut buf = [0; 9];
+        if store.read(storage::KEYMAP, &mut buf)? != Some(buf.len()) {
+            return Ok(None);
+        }
+        let mut seen = 0u16;
+        for &i in &buf {
+            if usize::from(i) >= Button::ALL.len() || seen & 1 << i != 0 {
+                return Ok(None);
+            }
+            seen |= 1 << i;
+        }
+        Ok(Some(Self {
+            buttons: buf.map(|i| Button::ALL[usize::from(i)]),
+        }))
+    }
+}
@@ -0,0 +1,158 @@
+//! Waking the chip from light or deep sleep with buttons (EXT1).
+
+use esp_hal::{
+    gpio::{
+        AnyPin,
+        RtcFunction,
+        RtcPin,
+        RtcPinWithResistors,
+    },
+    peripherals::LPWR,
+    rtc_cntl::{
+        Rtc,
+        sleep::{
+            RtcSleepConfig,
+            WakeSource,
+            WakeTriggers,
+        },
+    },
+};
+
+use super::{
+    Button,
+    ButtonSet,
+    Buttons,
+};
+
+/// Highest GPIO routed to the RTC domain on the ESP32-S3. On this chip the
+/// RTC number of GPIO0–21 equals the GPIO number.
+const MAX_RTC_GPIO: u8 = 21;
+
+/// Why [`Buttons::wake_on`] refused a set of buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum WakeError {
+    /// The button's pin can't wake the chip: B and Select sit outside the
+    /// RTC domain (and Select is active-high besides).
+    Unsupported(Button),
+    /// No buttons were given.
+    Empty,
+}
+
+/// An EXT1 wake source that fires when any of its buttons is pressed.
+///
+/// Pass it to [`Rtc::sleep_light`] or [`Rtc::sleep_deep`]. While it exists
+/// the pins are handed to the RTC domain, so [`Buttons`] stays borrowed;
+/// dropping it gives them back.
+pub struct ButtonWake<'a> {
+    buttons: &'a mut Buttons,
+    set: ButtonSet,
+}
+
+impl ButtonWake<'_> {
+    /// The buttons that will wake the chip.
+    pub const fn buttons(&self) -> ButtonSet {
+        self.set
+    }
+
+    fn pins(&self) -> impl Iterator<Item = AnyPin<'static>> + '_ {
+        self.set.iter().map(|b| {
+            // SAFETY: `Buttons` owns these pins and is borrowed for our
+            // lifetime, so nothing else drives them meanwhile.
+            unsafe { AnyPin::steal(self.buttons.pins[b.index()]) }
+        })
+    }
+}
+
+impl WakeSource for ButtonWake<'_> {
+    fn apply(
+        &self,
+        _rtc: &Rtc<'_>,
+        triggers: &mut WakeTriggers,
+        sleep_config: &mut RtcSleepConfig,
+    ) {
+        // Keep RTC peripherals (and with them the pull-ups) powered.
+        sleep_config.set_rtc_peri_pd_en(false);
+        triggers.set_ext1(true);
+
+        let mut bits = 0u32;
+        for pin in self.pins() {
+            pin.rtc_set_config(true, true, RtcFunction::Rtc);
+            // The digital pull-up doesn't apply once the pad is muxed to RTC.
+            pin.rtcio_pullup(true);
+            bits |= 1 << pin.rtc_number();
+        }
+
+        let rtc_cntl = LPWR::regs();
+        rtc_cntl
+            .ext_wakeup1()
+            .modify(|_, w| w.ext_wakeup1_status_clr().set_bit());
+        rtc_cntl
+            .ext_wakeup1()
+            .modify(|_, w| unsafe { w.ext_wakeup1_sel().bits(bits) });
+        // Level 0: wake when any selected pin reads low, i.e. is pressed.
+        rtc_cntl
+            .ext_wakeup_conf()
+            .modify(|_, w| w.ext_wakeup1_lv().clear_bit());
+    }
+}
+
+impl Drop for ButtonWake<'_> {
+    fn drop(&mut self) {
+        for pin in self.pins() {
+            pin.rtcio_pullup(false);
+            pin.rtc_set_config(true, false, RtcFunction::Rtc);
+        }
+    }
+}
+
+impl Buttons {
+    /// Buttons whose pins can wake the chip.
+    pub fn wake_capable(&self) -> ButtonSet {
+        Button::ALL
+            .into_iter()
+            .filter(|&b| b != Button::Select && self.pins[b.index()] <= MAX_RTC_GPIO)
+            .collect()
+    }
+
+    /// Configure `buttons` as a wake source for the next sleep.
+    ///
+    /// ```rust,ignore
+    /// let mut rtc = Rtc::new(peripherals.LPWR);
+    /// let wake = buttons.wake_on(ButtonSet::of(&[Button::Start]))?;
+    /// rtc.sleep_deep(&[&wake]);
+    /// ```
+    pub fn wake_on(&mut self, buttons: ButtonSet) -> Result<ButtonWake<'_>, WakeError> {
+        if buttons.is_empty() {
+            return Err(WakeError::Empty);
+        }
+        let capable = self.wake_capable();
+        if let Some(button) = buttons.iter().find(|&b| !capable.contains(b)) {
+            return Err(WakeError::Unsupported(button));
+        }
+        Ok(ButtonWake {
+            buttons: self,
+            set: buttons,
+        })
+    }
+
+    /// Which buttons woke the chip from its last sleep.
+    ///
+    /// Empty if the last wake (or boot) wasn't caused by a button.
+    pub fn woken_by(&self) -> ButtonSet {
+        if !matches!(
+            esp_hal::rtc_cntl::wakeup_cause(),
+            esp_hal::system::SleepSource::Ext1
+        ) {
+            return ButtonSet::EMPTY;
+        }
+        let status = LPWR::regs()
+            .ext_wakeup1_status()
+            .read()
+            .ext_wakeup1_status()
+            .bits();
+        self.wake_capable()
+            .iter()
+            .filter(|b| status & 1 << self.pins[b.index()] != 0)
+            .collect()
+    }
+}
@@ -0,0 +1,76 @@
+//! Wall-clock time, once something has told the badge what time it is.
+//!
+//! The badge has no battery-backed clock. It knows the time only after
+//! [`set`] is called with one from somewhere else (a server, a phone,
+//! another badge) and forgets it on reset; until then [`now`] is `None`.
+//!
+//! ```rust,ignore
+//! clock::set(1_770_984_000);
+//! if let Some(now) = clock::now() {
+//!     let time = Utc::from_unix(now);
+//!     info!("{}:{}", time.hour, time.minute);
+//! }
+//! ```
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::{
+    Mutex,
+    raw::CriticalSectionRawMutex,
+};
+use embassy_time::Instant;
+
+/// Unix time at boot, once known.
+static BOOT: Mutex<CriticalSectionRawMutex, Cell<Option<u64>>> = Mutex::new(Cell::new(None));
+
+/// Set the time to `unix`, in seconds since 1970-01-01 UTC.
+pub fn set(unix: u32) {
+    let boot = u64::from(unix).saturating_sub(Instant::now().as_secs());
+    BOOT.lock(|cell| cell.set(Some(boot)));
+}
+
+/// Seconds since 1970-01-01 UTC, if [`set`] has been called since boot.
+pub fn now() -> Option<u32> {
+    let boot = BOOT.lock(Cell::get)?;
+    u32::try_from(boot + Instant::now().as_secs()).ok()
+}
+
+/// A Unix time split into calendar fields, in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Utc {
+    pub year: u16,
+    /// 1 to 12.
+    pub month: u8,
+    /// 1 to 31.
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Utc {
+    pub const fn from_unix(unix: u32) -> Self {
+        let days = unix / 86_400;
+        let secs = unix % 86_400;
+        // Howard Hinnant's days-to-civil, with eras of 400 years starting
+        // on 0000-03-01.
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+}
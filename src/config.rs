@@ -0,0 +1,73 @@
+//! Compile-time badge configuration from `badge.toml`.
+//!
+//! The build script reads `badge.toml` from the crate root (or the path in
+//! the `BADGE_CONFIG` environment variable) and turns it into the typed
+//! constants below. Any key that is left out falls back to its default.
+//!
+//! ```toml
+//! [badge]
+//! name = "Anonymous Alpaca"
+//! contact = "@alpaca@infosec.exchange"   # shared on contact cards
+//!
+//! [theme]
+//! background = "1020A0"   # 6-char hex RGB, "rainbow", "retrofuture" or "hearts"
+//! foreground = "FFFFFF"
+//!
+//! [leds]
+//! effect = "off"          # "off", "heartbeat", "rainbow" or 6-char hex RGB
+//! brightness = 255
+//!
+//! [apps]
+//! enabled = ["nametag", "tetris"]
+//!
+//! [ota]
+//! public_key = "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c"
+//!
+//! [leaderboard]
+//! url = "https://scores.example.org"
+//!
+//! [conference]
+//! public_key = "8f3a6c1e2b7d4f9051a6e3c8d2b7f40e19c5a83d6e2f7b104c9d8a5e3f6b2c71"
+//! ```
+
+use palette::Srgb;
+
+/// Nametag background style.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Background {
+    /// A single solid colour.
+    Solid(Srgb<u8>),
+    /// Animated hue-cycling background.
+    Rainbow,
+    /// Animated synthwave road with a setting sun.
+    Retrofuture,
+    /// Floating hearts.
+    Hearts,
+}
+
+/// Colour theme shared by the display-facing apps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub background: Background,
+    pub foreground: Srgb<u8>,
+}
+
+/// Idle LED effect selected at build time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedEffect {
+    Off,
+    Heartbeat,
+    Rainbow,
+    /// All LEDs lit with one static colour.
+    Solid(Srgb<u8>),
+}
+
+/// Returns `true` if `app` is listed in `[apps] enabled`.
+///
+/// An empty list means every app is enabled.
+#[must_use]
+pub fn app_enabled(app: &str) -> bool {
+    ENABLED_APPS.is_empty() || ENABLED_APPS.contains(&app)
+}
+
+include!(concat!(env!("OUT_DIR"), "/badge_config.rs"));
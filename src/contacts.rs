@@ -0,0 +1,381 @@
+//! Contact cards swapped between badges, and the list they're kept in.
+//!
+//! A [`Card`] is what a badge hands out: the owner's name, how to reach
+//! them and a colour. Cards are swapped with a handshake (the
+//! [`handshake`] module, with the `esp-now` feature) and each one received
+//! is kept in a [`Store`] as a [`Contact`], with who sent it and when:
+//!
+//! ```rust,ignore
+//! let contact = HANDSHAKE.exchange(&Card::from_config()).await?;
+//! contacts::add(&mut store, &contact)?;
+//! for index in 0..contacts::count(&mut store)? {
+//!     if let Some(contact) = contacts::get(&mut store, index)? {
+//!         info!("{}: {}", contact.card.name(), contact.card.handle());
+//!     }
+//! }
+//! ```
+//!
+//! [`render`] draws a contact as a full screen, for apps that list them.
+//!
+//! ## Storage format
+//!
+//! The number of contacts is a little-endian `u16` under
+//! [`storage::CONTACTS`]; contact `i` is under [`storage::CONTACT_LIST`]
+//! `+ i`, oldest first. Each is the sender's MAC, the time met as a
+//! little-endian `u32` of Unix seconds (0 if the badge didn't know the
+//! time), then the card: colour as three bytes, then the name and the
+//! handle, each as a length byte and UTF-8.
+
+#[cfg(feature = "esp-now")]
+pub mod handshake;
+
+use core::fmt::{
+    self,
+    Write as _,
+};
+
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyle,
+        ascii::FONT_6X10,
+        iso_8859_1::FONT_10X20,
+    },
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        Point,
+        RgbColor,
+        WebColors,
+    },
+    text::{
+        Alignment,
+        Text,
+    },
+};
+use palette::Srgb;
+
+use crate::{
+    BadgeId,
+    Nametag,
+    clock::Utc,
+    config,
+    display::{
+        DISPLAY_HEIGHT,
+        DISPLAY_WIDTH,
+    },
+    storage::{
+        self,
+        Key,
+        Store,
+    },
+};
+
+/// Longest name or handle on a card, in bytes.
+pub const CARD_TEXT_LEN: usize = 32;
+/// Longest encoded [`Card`].
+pub const CARD_LEN: usize = 3 + 2 * (1 + CARD_TEXT_LEN);
+/// Most contacts kept; [`add`] refuses new ones beyond this.
+pub const MAX_CONTACTS: usize = 128;
+
+/// MAC and time met, ahead of the card.
+const RECORD_HEADER_LEN: usize = 10;
+
+/// What a badge hands out in a handshake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Card {
+    name: [u8; CARD_TEXT_LEN],
+    name_len: u8,
+    handle: [u8; CARD_TEXT_LEN],
+    handle_len: u8,
+    /// Accent colour the card is shown in.
+    pub color: Srgb<u8>,
+}
+
+impl Card {
+    /// `name` and `handle` are cut to [`CARD_TEXT_LEN`] bytes at a
+    /// character boundary.
+    pub fn new(name: &str, handle: &str, color: Srgb<u8>) -> Self {
+        let mut card = Self {
+            name: [0; CARD_TEXT_LEN],
+            name_len: 0,
+            handle: [0; CARD_TEXT_LEN],
+            handle_len: 0,
+            color,
+        };
+        card.name_len = fit(name, &mut card.name);
+        card.handle_len = fit(handle, &mut card.handle);
+        card
+    }
+
+    /// `badge.name` and `badge.contact` from `badge.toml`, in the theme's
+    /// foreground colour.
+    pub fn from_config() -> Self {
+        Self::new(config::NAME, config::CONTACT, config::THEME.foreground)
+    }
+
+    /// The name and colour on the nametag, which may have been edited since
+    /// the build, with `badge.contact` from `badge.toml`.
+    pub fn from_nametag(nametag: &Nametag) -> Self {
+        Self::new(nametag.name(), config::CONTACT, nametag.theme.foreground)
+    }
+
+    pub fn name(&self) -> &str {
+        // Only ever filled from a `&str` or checked on decode.
+        core::str::from_utf8(&self.name[..usize::from(self.name_len)]).unwrap_or_default()
+    }
+
+    /// How to reach the owner: a handle, an address, anything short.
+    pub fn handle(&self) -> &str {
+        core::str::from_utf8(&self.handle[..usize::from(self.handle_len)]).unwrap_or_default()
+    }
+
+    /// Write the card into `buf` as described in the
+    /// [module docs](self#storage-format), returning the bytes used.
+    pub fn encode<'b>(&self, buf: &'b mut [u8; CARD_LEN]) -> &'b [u8] {
+        let name = &self.name[..usize::from(self.name_len)];
+        let handle = &self.handle[..usize::from(self.handle_len)];
+        buf[..3].copy_from_slice(&[self.color.red, self.color.green, self.color.blue]);
+        let mut len = 3;
+        for text in [name, handle] {
+            buf[len] = text.len() as u8;
+            buf[len + 1..len + 1 + text.len()].copy_from_slice(text);
+            len += 1 + text.len();
+        }
+        &buf[..len]
+    }
+
+    /// A card from [`encode`](Self::encode), and the bytes after it.
+    /// Anything that doesn't decode is `None`.
+    pub fn decode(bytes: &[u8]) -> Option<(Self, &[u8])> {
+        let ([red, green, blue], rest) = bytes.split_first_chunk::<3>()?;
+        let (name, rest) = text(rest)?;
+        let (handle, rest) = text(rest)?;
+        Some((
+            Self::new(name, handle, Srgb::new(*red, *green, *blue)),
+            rest,
+        ))
+    }
+}
+
+/// A card received in a handshake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Contact {
+    /// The badge it came from.
+    pub id: BadgeId,
+    pub card: Card,
+    /// When the cards were swapped, in Unix seconds; 0 if the badge didn't
+    /// know the time.
+    pub met: u32,
+}
+
+impl Contact {
+    /// When the cards were swapped, if the badge knew the time.
+    pub const fn met_at(&self) -> Option<Utc> {
+        match self.met {
+            0 => None,
+            met => Some(Utc::from_unix(met)),
+        }
+    }
+}
+
+/// Number of contacts in `store`.
+pub fn count<S: Store + ?Sized>(store: &mut S) -> Result<usize, S::Error> {
+    let mut buf = [0; 2];
+    Ok(match store.read(storage::CONTACTS, &mut buf)? {
+        Some(2) => usize::from(u16::from_le_bytes(buf)).min(MAX_CONTACTS),
+        _ => 0,
+    })
+}
+
+/// Contact `index`, oldest first. Anything that doesn't decode reads as
+/// `None`.
+pub fn get<S: Store + ?Sized>(store: &mut S, index: usize) -> Result<Option<Contact>, S::Error> {
+    if index >= MAX_CONTACTS {
+        return Ok(None);
+    }
+    let mut buf = [0; RECORD_HEADER_LEN + CARD_LEN];
+    let Some(len) = store.read(key(index), &mut buf)? else {
+        return Ok(None);
+    };
+    let Some((header, card)) = buf[..len].split_first_chunk::<RECORD_HEADER_LEN>() else {
+        return Ok(None);
+    };
+    let Some((card, _)) = Card::decode(card) else {
+        return Ok(None);
+    };
+    let [a, b, c, d, e, f, m0, m1, m2, m3] = *header;
+    Ok(Some(Contact {
+        id: BadgeId::from_mac([a, b, c, d, e, f]),
+        card,
+        met: u32::from_le_bytes([m0, m1, m2, m3]),
+    }))
+}
+
+/// Keep `contact`, replacing the one from the same badge if there is one.
+/// Returns its index, or `None` if there are already [`MAX_CONTACTS`].
+pub fn add<S: Store + ?Sized>(store: &mut S, contact: &Contact) -> Result<Option<usize>, S::Error> {
+    let count = count(store)?;
+    let mut index = count;
+    for i in 0..count {
+        if get(store, i)?.is_some_and(|known| known.id == contact.id) {
+            index = i;
+            break;
+        }
+    }
+    if index == MAX_CONTACTS {
+        return Ok(None);
+    }
+    let mut buf = [0; RECORD_HEADER_LEN + CARD_LEN];
+    buf[..6].copy_from_slice(&contact.id.mac());
+    buf[6..RECORD_HEADER_LEN].copy_from_slice(&contact.met.to_le_bytes());
+    let mut card = [0; CARD_LEN];
+    let card = contact.card.encode(&mut card);
+    buf[RECORD_HEADER_LEN..][..card.len()].copy_from_slice(card);
+    store.write(key(index), &buf[..RECORD_HEADER_LEN + card.len()])?;
+    if index == count {
+        store.write(storage::CONTACTS, &(count as u16 + 1).to_le_bytes())?;
+    }
+    Ok(Some(index))
+}
+
+/// Forget contact `index`; the ones after it move down by one.
+pub fn remove<S: Store + ?Sized>(store: &mut S, index: usize) -> Result<(), S::Error> {
+    let count = count(store)?;
+    if index >= count {
+        return Ok(());
+    }
+    let mut buf = [0; RECORD_HEADER_LEN + CARD_LEN];
+    for i in index + 1..count {
+        if let Some(len) = store.read(key(i), &mut buf)? {
+            store.write(key(i - 1), &buf[..len])?;
+        }
+    }
+    store.remove(key(count - 1))?;
+    store.write(storage::CONTACTS, &(count as u16 - 1).to_le_bytes())
+}
+
+/// Draw `contact` as a full screen: the name large in the card's colour,
+/// the handle, the badge it came from and when. `index` of `total` goes
+/// in the corner.
+pub fn render<D>(
+    contact: &Contact,
+    index: usize,
+    total: usize,
+    target: &mut D,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    target.clear(Rgb565::BLACK)?;
+    let center = i32::from(DISPLAY_WIDTH) / 2;
+    let color = contact.card.color;
+    let accent = Rgb565::new(color.red >> 3, color.green >> 2, color.blue >> 3);
+    let large = MonoTextStyle::new(&FONT_10X20, accent);
+    let medium = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+
+    Text::with_alignment(
+        contact.card.name(),
+        Point::new(center, 60),
+        large,
+        Alignment::Center,
+    )
+    .draw(target)?;
+    Text::with_alignment(
+        contact.card.handle(),
+        Point::new(center, 90),
+        medium,
+        Alignment::Center,
+    )
+    .draw(target)?;
+
+    let mut line = Line::new();
+    let _ = write!(line, "{}", contact.id);
+    Text::with_alignment(
+        line.as_str(),
+        Point::new(center, 120),
+        small,
+        Alignment::Center,
+    )
+    .draw(target)?;
+    let mut line = Line::new();
+    let _ = match contact.met_at() {
+        Some(t) => write!(
+            line,
+            "met {}-{:02}-{:02} {:02}:{:02} UTC",
+            t.year, t.month, t.day, t.hour, t.minute
+        ),
+        None => write!(line, "met at an unknown time"),
+    };
+    Text::with_alignment(
+        line.as_str(),
+        Point::new(center, 134),
+        small,
+        Alignment::Center,
+    )
+    .draw(target)?;
+    let mut line = Line::new();
+    let _ = write!(line, "{}/{}", index + 1, total);
+    Text::with_alignment(
+        line.as_str(),
+        Point::new(i32::from(DISPLAY_WIDTH) - 4, i32::from(DISPLAY_HEIGHT) - 6),
+        small,
+        Alignment::Right,
+    )
+    .draw(target)?;
+    Ok(())
+}
+
+/// A line of text formatted on the stack; what doesn't fit is dropped.
+struct Line {
+    buf: [u8; 48],
+    len: usize,
+}
+
+impl Line {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 48],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for Line {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(self.buf.len() - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+fn key(index: usize) -> Key {
+    storage::CONTACT_LIST + index as Key
+}
+
+/// Copy as much of `text` as fits `buf`, cut at a character boundary, and
+/// return its length.
+fn fit(text: &str, buf: &mut [u8; CARD_TEXT_LEN]) -> u8 {
+    let mut len = text.len().min(CARD_TEXT_LEN);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    buf[..len].copy_from_slice(&text.as_bytes()[..len]);
+    len as u8
+}
+
+/// A length-prefixed UTF-8 string, and the bytes after it.
+fn text(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let (&len, rest) = bytes.split_first()?;
+    let (text, rest) = rest.split_at_checked(usize::from(len))?;
+    Some((core::str::from_utf8(text).ok()?, rest))
+}
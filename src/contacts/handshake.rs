@@ -0,0 +1,170 @@
+//! Swapping contact cards by holding two badges together.
+//!
+//! Both owners hold their badges close and keep a button held; the badges
+//! pair over the [game link](crate::gamelink), each sends its [`Card`], and
+//! [`Handshake::exchange`] returns the other's as a [`Contact`]:
+//!
+//! ```rust,ignore
+//! static HANDSHAKE: Handshake = Handshake::new();
+//!
+//! #[embassy_executor::task]
+//! async fn handshake_task(esp_now: EspNow<'static>) {
+//!     HANDSHAKE.run(esp_now).await
+//! }
+//!
+//! // UI task, every frame
+//! HANDSHAKE.set_confirm(buttons.a.is_low());
+//!
+//! // exchange task
+//! loop {
+//!     match HANDSHAKE.exchange(&Card::from_config()).await {
+//!         Ok(contact) => contacts::add(&mut store, &contact)?,
+//!         Err(err) => warn!("handshake: {}", err),
+//!     }
+//! }
+//! ```
+//!
+//! Holding the button is the consent: nothing is sent until both owners
+//! do, and only to a badge closer than [`NEAR_RSSI`]. Cards also carry the
+//! sender's [`clock`](crate::clock), so a badge that doesn't know the time
+//! learns it from one that does.
+
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+
+use embassy_futures::join::join;
+use embassy_time::{
+    Duration,
+    with_timeout,
+};
+
+use super::{
+    CARD_LEN,
+    Card,
+    Contact,
+};
+use crate::{
+    clock,
+    gamelink::{
+        EspNow,
+        GameId,
+        GameLink,
+        LinkEvent,
+        LobbyPeer,
+    },
+};
+
+/// Game link ID of the handshake.
+pub const HANDSHAKE_GAME: GameId = u16::from_le_bytes(*b"HS");
+/// Weakest signal, in dBm, from a badge that counts as held close.
+pub const NEAR_RSSI: i8 = -50;
+/// How long after pairing the other card must arrive.
+pub const EXCHANGE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Why [`Handshake::exchange`] came back without a card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum HandshakeError {
+    /// The other badge paired but left before its card arrived.
+    Left,
+    /// No card within [`EXCHANGE_TIMEOUT`] of pairing.
+    Timeout,
+}
+
+/// The handshake between this badge and one held next to it. See the
+/// [module docs](self).
+pub struct Handshake {
+    link: GameLink,
+    /// Cleared after an exchange until the button is let go, so holding it
+    /// doesn't swap cards over and over.
+    armed: AtomicBool,
+}
+
+impl Handshake {
+    pub const fn new() -> Self {
+        Self {
+            link: GameLink::new(HANDSHAKE_GAME),
+            armed: AtomicBool::new(true),
+        }
+    }
+
+    /// Run the radio side forever.
+    pub async fn run(&self, esp_now: EspNow<'static>) -> ! {
+        self.link.run(esp_now).await
+    }
+
+    /// The closest badge ready to shake hands, if it's within [`NEAR_RSSI`].
+    pub fn nearby(&self) -> Option<LobbyPeer> {
+        self.link.lobby()[0].filter(|peer| peer.rssi >= NEAR_RSSI)
+    }
+
+    /// Call with whether the owner holds the confirm button. The badge only
+    /// offers to pair while it's held and a badge is [`nearby`](Self::nearby),
+    /// and once per press.
+    pub fn set_confirm(&self, held: bool) {
+        if !held {
+            self.armed.store(true, Ordering::Relaxed);
+        }
+        let armed = self.armed.load(Ordering::Relaxed);
+        self.link
+            .set_pairing(held && armed && self.nearby().is_some());
+    }
+
+    /// Wait until both owners confirm, then send `card` and return the
+    /// other badge's.
+    pub async fn exchange(&self, card: &Card) -> Result<Contact, HandshakeError> {
+        let peer = loop {
+            if let LinkEvent::Linked { peer, .. } = self.link.next().await {
+                break peer;
+            }
+        };
+        let mut frame = [0; CARD_LEN + 4];
+        let mut encoded = [0; CARD_LEN];
+        let encoded = card.encode(&mut encoded);
+        frame[..encoded.len()].copy_from_slice(encoded);
+        let time = clock::now().unwrap_or(0);
+        frame[encoded.len()..][..4].copy_from_slice(&time.to_le_bytes());
+        let frame = &frame[..encoded.len() + 4];
+
+        let receive = async {
+            loop {
+                match self.link.next().await {
+                    LinkEvent::Message(message) => {
+                        if let Some((card, &[t0, t1, t2, t3])) = Card::decode(&message) {
+                            break Ok((card, u32::from_le_bytes([t0, t1, t2, t3])));
+                        }
+                    }
+                    LinkEvent::Unlinked => break Err(HandshakeError::Left),
+                    LinkEvent::Linked { .. } => {}
+                }
+            }
+        };
+        // Whether the other side got ours is its business; leaving early only
+        // cuts its retries short once it has sent its own.
+        let exchanged = with_timeout(EXCHANGE_TIMEOUT, join(self.link.send(frame), receive)).await;
+        self.armed.store(false, Ordering::Relaxed);
+        self.link.set_pairing(false);
+        self.link.leave().await;
+        let (sent, received) = exchanged.map_err(|_| HandshakeError::Timeout)?;
+        if let Err(err) = sent {
+            defmt::debug!("handshake: card not acknowledged: {}", err);
+        }
+        let (card, time) = received?;
+        if clock::now().is_none() && time != 0 {
+            clock::set(time);
+        }
+        defmt::info!("handshake: met {}", peer);
+        Ok(Contact {
+            id: peer,
+            card,
+            met: clock::now().unwrap_or(0),
+        })
+    }
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
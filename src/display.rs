@@ -1,9 +1,24 @@
 //! ST7789 display driver — 320×170 LCD over SPI with DMA.
 
+mod glance;
+mod interface;
+mod shared;
+
+use embedded_graphics::{
+    Pixel,
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        OriginDimensions,
+        Point,
+        Size,
+    },
+    primitives::Rectangle,
+};
 use embedded_hal_bus::spi::ExclusiveDevice;
 use esp_hal::{
-    Async,
     dma::{
+        DmaBufError,
         DmaRxBuf,
         DmaTxBuf,
     },
@@ -13,26 +28,185 @@ use esp_hal::{
         Output,
         OutputConfig,
     },
-    spi::master::Spi,
-    time::Rate,
+    spi::master::{
+        ConfigError,
+        Spi,
+    },
 };
+use mipidsi::interface::Interface as _;
 
+pub use self::{
+    glance::GlanceTicker,
+    interface::PanelInterface,
+    shared::{
+        Region,
+        SharedDisplay,
+    },
+};
 use crate::DisplayResources;
 
-type SpiInterface<'a> = mipidsi::interface::SpiInterface<
-    'a,
-    ExclusiveDevice<esp_hal::spi::master::SpiDmaBus<'a, Async>, Output<'a>, esp_hal::delay::Delay>,
-    Output<'a>,
->;
+/// The underlying `mipidsi` driver wrapped by [`Display`].
+pub type RawDisplay<'a> = mipidsi::Display<PanelInterface<'a>, mipidsi::models::ST7789, Output<'a>>;
+
+/// Size of the pixel batch buffer handed to `mipidsi`.
+const BUFFER_SIZE: usize = 32000;
+
+/// Screen width in pixels (landscape).
+pub const DISPLAY_WIDTH: u16 = 320;
+/// Screen height in pixels (landscape).
+pub const DISPLAY_HEIGHT: u16 = 170;
+
+/// Width of the band kept alive in [`PowerMode::Glance`].
+pub const GLANCE_BAND_WIDTH: u16 = 40;
+
+// ST7789 commands not wrapped by `mipidsi`.
+const RDDID: u8 = 0x04;
+const RDDST: u8 = 0x09;
+const PTLON: u8 = 0x12;
+const NORON: u8 = 0x13;
+const DISPOFF: u8 = 0x28;
+const DISPON: u8 = 0x29;
+const PTLAR: u8 = 0x30;
+const IDMOFF: u8 = 0x38;
+const IDMON: u8 = 0x39;
+
+/// Panel power states, from brightest to darkest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum PowerMode {
+    /// Whole panel active in full colour.
+    Full,
+    /// Only screen columns `start..=end` are driven, in 8-colour idle mode.
+    ///
+    /// The rest of the panel shows black. The ST7789 partial area runs along
+    /// its native rows, which the badge's rotation maps onto screen X, so
+    /// the band is a vertical strip. Draw into it using only fully saturated
+    /// primaries (black, white, red, green, blue, cyan, magenta, yellow).
+    Glance { start: u16, end: u16 },
+    /// Panel switched off and asleep.
+    Off,
+}
+
+impl PowerMode {
+    /// A [`GLANCE_BAND_WIDTH`]-wide glance band starting at screen column
+    /// `start`, moved left as far as it takes to fit on the screen.
+    pub const fn glance(start: u16) -> Self {
+        let start = if start > DISPLAY_WIDTH - GLANCE_BAND_WIDTH {
+            DISPLAY_WIDTH - GLANCE_BAND_WIDTH
+        } else {
+            start
+        };
+        Self::Glance {
+            start,
+            end: start + GLANCE_BAND_WIDTH - 1,
+        }
+    }
+
+    /// The visible area in this mode, or `None` when the panel is off.
+    pub fn visible_area(self) -> Option<Rectangle> {
+        match self {
+            Self::Full => Some(Rectangle::new(
+                Point::zero(),
+                Size::new(u32::from(DISPLAY_WIDTH), u32::from(DISPLAY_HEIGHT)),
+            )),
+            Self::Glance { start, end } => Some(Rectangle::new(
+                Point::new(i32::from(start), 0),
+                Size::new(
+                    u32::from(end.saturating_sub(start) + 1),
+                    u32::from(DISPLAY_HEIGHT),
+                ),
+            )),
+            Self::Off => None,
+        }
+    }
+}
+
+/// Display status word returned by [`Display::read_status`] (RDDST).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct PanelStatus(pub u32);
+
+impl PanelStatus {
+    /// Display is on (DISPON).
+    pub const DISPLAY_ON: u32 = 1 << 10;
+    /// Normal display mode (NORON).
+    pub const NORMAL_MODE: u32 = 1 << 16;
+    /// Out of sleep (SLPOUT).
+    pub const SLEEP_OUT: u32 = 1 << 17;
+    /// Partial mode (PTLON).
+    pub const PARTIAL_MODE: u32 = 1 << 18;
+    /// Idle / 8-colour mode (IDMON).
+    pub const IDLE_MODE: u32 = 1 << 19;
+
+    /// Whether every bit in `mask` is set.
+    pub const fn contains(self, mask: u32) -> bool {
+        self.0 & mask == mask
+    }
+}
+
+/// Identification bytes returned by [`Display::read_id`] (RDDID).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct PanelId {
+    pub manufacturer: u8,
+    pub version: u8,
+    pub module: u8,
+}
+
+impl PanelId {
+    /// Factory default ID of an unprogrammed ST7789.
+    pub const ST7789: Self = Self {
+        manufacturer: 0x85,
+        version: 0x85,
+        module: 0x52,
+    };
+}
+
+/// Errors from display initialisation and drawing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum DisplayError {
+    /// DMA buffers could not be set up.
+    Dma(DmaBufError),
+    /// The SPI bus rejected its configuration.
+    Config(ConfigError),
+    /// The panel did not accept the initialisation sequence.
+    Init,
+    /// An SPI transfer to the panel failed.
+    Spi(esp_hal::spi::Error),
+    /// The controller did not answer a read, or answered inconsistently.
+    NoResponse,
+    /// A [`PowerMode::Glance`] band that ends before it starts or past the
+    /// right edge of the screen.
+    InvalidBand,
+}
+
+impl From<esp_hal::spi::Error> for DisplayError {
+    fn from(err: esp_hal::spi::Error) -> Self {
+        Self::Spi(err)
+    }
+}
 
 /// The badge's ST7789 display, ready to draw on with `embedded-graphics`.
-pub type Display<'a> = mipidsi::Display<SpiInterface<'a>, mipidsi::models::ST7789, Output<'a>>;
+///
+/// All drawing goes through [`DrawTarget`] with [`DisplayError`] as the error
+/// type, so firmware can handle bus failures instead of panicking.
+pub struct Display<'a> {
+    inner: RawDisplay<'a>,
+    power: PowerMode,
+}
 
 impl<'a> From<DisplayResources<'a>> for Display<'a> {
     fn from(res: DisplayResources<'a>) -> Self {
-        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(32000);
-        let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer).unwrap();
-        let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap();
+        Self::try_new(res).unwrap()
+    }
+}
+
+impl<'a> Display<'a> {
+    /// Initialise the panel, returning an error instead of panicking.
+    ///
+    /// To retry after a failure, pass `resources.display.reborrow()` so the
+    /// pins and peripherals are still available for the next attempt.
+    pub fn try_new(res: DisplayResources<'a>) -> Result<Self, DisplayError> {
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(BUFFER_SIZE);
+        let dma_rx_buf = DmaRxBuf::new(rx_descriptors, rx_buffer).map_err(DisplayError::Dma)?;
+        let dma_tx_buf = DmaTxBuf::new(tx_descriptors, tx_buffer).map_err(DisplayError::Dma)?;
 
         let mut delay = esp_hal::delay::Delay::new();
 
@@ -42,9 +216,9 @@ impl<'a> From<DisplayResources<'a>> for Display<'a> {
 
         let spi = Spi::new(
             res.spi,
-            esp_hal::spi::master::Config::default().with_frequency(Rate::from_mhz(80)),
+            esp_hal::spi::master::Config::default().with_frequency(interface::WRITE_FREQUENCY),
         )
-        .unwrap()
+        .map_err(DisplayError::Config)?
         .with_sck(res.sck)
         .with_mosi(res.mosi)
         .with_miso(res.miso)
@@ -53,12 +227,11 @@ impl<'a> From<DisplayResources<'a>> for Display<'a> {
         .into_async();
 
         let cs = Output::new(res.cs, Level::High, OutputConfig::default());
-        let spi_device = ExclusiveDevice::new(spi, cs, delay).unwrap();
+        let Ok(spi_device) = ExclusiveDevice::new(spi, cs, delay);
 
-        let buffer = crate::mk_static!([u8; 32000], [0_u8; 32000]);
-        let di = mipidsi::interface::SpiInterface::new(spi_device, dc, buffer);
+        let di = PanelInterface::new(spi_device, dc, interface_buffer());
 
-        mipidsi::Builder::new(mipidsi::models::ST7789, di)
+        let inner = mipidsi::Builder::new(mipidsi::models::ST7789, di)
             .reset_pin(rst)
             .display_size(170, 320)
             .invert_colors(mipidsi::options::ColorInversion::Inverted)
@@ -67,6 +240,204 @@ impl<'a> From<DisplayResources<'a>> for Display<'a> {
             )
             .display_offset(35, 0)
             .init(&mut delay)
-            .unwrap()
+            .map_err(|_| DisplayError::Init)?;
+
+        Ok(Self {
+            inner,
+            power: PowerMode::Full,
+        })
+    }
+
+    /// Write a single pixel.
+    pub fn set_pixel(&mut self, x: u16, y: u16, color: Rgb565) -> Result<(), DisplayError> {
+        Ok(self.inner.set_pixel(x, y, color)?)
     }
+
+    /// Stream pixels into the inclusive window `(sx, sy)..=(ex, ey)`.
+    pub fn set_pixels<T>(
+        &mut self,
+        sx: u16,
+        sy: u16,
+        ex: u16,
+        ey: u16,
+        colors: T,
+    ) -> Result<(), DisplayError>
+    where
+        T: IntoIterator<Item = Rgb565>,
+    {
+        Ok(self.inner.set_pixels(sx, sy, ex, ey, colors)?)
+    }
+
+    /// Define the hardware scroll region (in the panel's native 320-row axis).
+    pub fn set_vertical_scroll_region(
+        &mut self,
+        top_fixed_area: u16,
+        bottom_fixed_area: u16,
+    ) -> Result<(), DisplayError> {
+        Ok(self
+            .inner
+            .set_vertical_scroll_region(top_fixed_area, bottom_fixed_area)?)
+    }
+
+    /// Set the hardware scroll offset within the scroll region.
+    pub fn set_vertical_scroll_offset(&mut self, offset: u16) -> Result<(), DisplayError> {
+        Ok(self.inner.set_vertical_scroll_offset(offset)?)
+    }
+
+    /// The current [`PowerMode`].
+    pub const fn power_mode(&self) -> PowerMode {
+        self.power
+    }
+
+    /// Switch between full, glance and off.
+    ///
+    /// Waking from [`PowerMode::Off`] blocks for the panel's 120 ms sleep-out time.
+    /// Framebuffer contents survive every transition. A glance band must
+    /// have `start <= end < DISPLAY_WIDTH`; anything else is refused with
+    /// [`DisplayError::InvalidBand`] before the panel is touched.
+    pub fn set_power_mode(&mut self, mode: PowerMode) -> Result<(), DisplayError> {
+        if let PowerMode::Glance { start, end } = mode
+            && !(start <= end && end < DISPLAY_WIDTH)
+        {
+            return Err(DisplayError::InvalidBand);
+        }
+        if mode == self.power {
+            return Ok(());
+        }
+        let mut delay = esp_hal::delay::Delay::new();
+        if self.power == PowerMode::Off {
+            self.inner.wake(&mut delay)?;
+            self.command(DISPON, &[])?;
+        }
+        match mode {
+            PowerMode::Full => {
+                self.command(NORON, &[])?;
+                self.command(IDMOFF, &[])?;
+            }
+            PowerMode::Glance { start, end } => {
+                let [s0, s1] = start.to_be_bytes();
+                let [e0, e1] = end.to_be_bytes();
+                self.command(PTLAR, &[s0, s1, e0, e1])?;
+                self.command(PTLON, &[])?;
+                self.command(IDMON, &[])?;
+            }
+            PowerMode::Off => {
+                self.command(DISPOFF, &[])?;
+                self.inner.sleep(&mut delay)?;
+            }
+        }
+        self.power = mode;
+        Ok(())
+    }
+
+    /// Read the controller's three ID bytes.
+    ///
+    /// An ST7789 that has not been OTP-programmed answers [`PanelId::ST7789`].
+    pub fn read_id(&mut self) -> Result<PanelId, DisplayError> {
+        // SAFETY: reads do not change any panel state.
+        let [manufacturer, version, module] =
+            unsafe { self.inner.dcs() }.read_shifted::<3>(RDDID)?;
+        Ok(PanelId {
+            manufacturer,
+            version,
+            module,
+        })
+    }
+
+    /// Read the 32-bit display status word.
+    pub fn read_status(&mut self) -> Result<PanelStatus, DisplayError> {
+        // SAFETY: reads do not change any panel state.
+        let bytes = unsafe { self.inner.dcs() }.read_shifted::<4>(RDDST)?;
+        Ok(PanelStatus(u32::from_be_bytes(bytes)))
+    }
+
+    /// Check that the controller answers over MISO and agrees with the
+    /// driver's idea of its power state.
+    ///
+    /// Returns the panel ID on success. A floating or disconnected MISO reads
+    /// back all-zero or all-one bytes and fails with
+    /// [`DisplayError::NoResponse`]. In [`PowerMode::Off`] only the ID is
+    /// checked.
+    pub fn self_test(&mut self) -> Result<PanelId, DisplayError> {
+        let id = self.read_id()?;
+        let raw = [id.manufacturer, id.version, id.module];
+        if raw == [0x00; 3] || raw == [0xFF; 3] {
+            return Err(DisplayError::NoResponse);
+        }
+
+        let status = self.read_status()?;
+        let expected = match self.power {
+            PowerMode::Full => {
+                PanelStatus::SLEEP_OUT | PanelStatus::DISPLAY_ON | PanelStatus::NORMAL_MODE
+            }
+            PowerMode::Glance { .. } => {
+                PanelStatus::SLEEP_OUT
+                    | PanelStatus::DISPLAY_ON
+                    | PanelStatus::PARTIAL_MODE
+                    | PanelStatus::IDLE_MODE
+            }
+            PowerMode::Off => 0,
+        };
+        if !status.contains(expected) {
+            return Err(DisplayError::NoResponse);
+        }
+        Ok(id)
+    }
+
+    /// Send a raw command that leaves `mipidsi`'s view of the panel intact.
+    fn command(&mut self, command: u8, args: &[u8]) -> Result<(), DisplayError> {
+        // SAFETY: callers only send power/partial-mode commands, which do not
+        // touch the address mode, pixel format or scroll state mipidsi tracks.
+        Ok(unsafe { self.inner.dcs() }.send_command(command, args)?)
+    }
+
+    /// Access the underlying `mipidsi` driver.
+    pub const fn raw(&mut self) -> &mut RawDisplay<'a> {
+        &mut self.inner
+    }
+}
+
+impl DrawTarget for Display<'_> {
+    type Color = Rgb565;
+    type Error = DisplayError;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        Ok(self.inner.draw_iter(pixels)?)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        Ok(self.inner.fill_contiguous(area, colors)?)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        Ok(self.inner.fill_solid(area, color)?)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        Ok(self.inner.clear(color)?)
+    }
+}
+
+impl OriginDimensions for Display<'_> {
+    fn size(&self) -> Size {
+        self.inner.size()
+    }
+}
+
+/// The pixel batch buffer.
+///
+/// A plain static rather than [`mk_static!`](crate::mk_static) so that
+/// [`Display::try_new`] can be called again after a failed attempt.
+fn interface_buffer() -> &'static mut [u8; BUFFER_SIZE] {
+    static mut BUFFER: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+    let buffer = &raw mut BUFFER;
+    // SAFETY: only `Display::try_new` takes this buffer, and a `Display` owns
+    // SPI2 for as long as it holds it, so two live references cannot exist.
+    unsafe { &mut *buffer }
 }
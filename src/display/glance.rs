@@ -0,0 +1,152 @@
+//! A clock and scrolling ticker for the [`PowerMode::Glance`] band.
+
+use embassy_time::{
+    Duration,
+    Instant,
+};
+use embedded_graphics::{
+    Drawable,
+    draw_target::DrawTargetExt as _,
+    mono_font::{
+        MonoTextStyle,
+        ascii::FONT_10X20,
+    },
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        Point,
+        RgbColor,
+        Size,
+    },
+    primitives::Rectangle,
+    text::{
+        Baseline,
+        Text,
+    },
+};
+
+use super::{
+    DISPLAY_HEIGHT,
+    GLANCE_BAND_WIDTH,
+    PowerMode,
+};
+use crate::clock::{
+    self,
+    Utc,
+};
+
+/// Height of a line of [`FONT_10X20`].
+const LINE_HEIGHT: i32 = 20;
+/// Where the ticker starts, under the clock and a gap.
+const TICKER_TOP: i32 = 2 * LINE_HEIGHT + 8;
+/// Pixels the ticker moves up each [`GlanceTicker::STEP`].
+const SCROLL: u32 = 2;
+
+/// The time, hours over minutes, and a line of text crawling up under it,
+/// drawn in the [`GLANCE_BAND_WIDTH`]-wide band of [`PowerMode::glance`].
+///
+/// The time is [`clock::now`] in UTC, or the time since boot until the
+/// clock is set. Everything is drawn in the saturated primaries the band's
+/// 8-colour mode shows.
+///
+/// ```rust,ignore
+/// let mut ticker = GlanceTicker::new("Disobey 2026").with_band(280);
+/// display.set_power_mode(ticker.mode())?;
+/// loop {
+///     ticker.draw(&mut display)?;
+///     Timer::after(GlanceTicker::STEP).await;
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct GlanceTicker<'t> {
+    text: &'t str,
+    start: u16,
+    offset: u32,
+}
+
+impl<'t> GlanceTicker<'t> {
+    /// How often to [`draw`](Self::draw) for a smooth crawl.
+    pub const STEP: Duration = Duration::from_millis(100);
+
+    /// A ticker showing `text`, in a band at the right edge of the screen.
+    pub const fn new(text: &'t str) -> Self {
+        Self {
+            text,
+            start: u16::MAX,
+            offset: 0,
+        }
+    }
+
+    /// Put the band at screen column `start` instead, moved left as far as
+    /// it takes to fit.
+    #[must_use]
+    pub const fn with_band(mut self, start: u16) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// The glance mode that shows this ticker's band.
+    pub const fn mode(&self) -> PowerMode {
+        PowerMode::glance(self.start)
+    }
+
+    /// The band's bounds in screen coordinates.
+    pub fn area(&self) -> Rectangle {
+        self.mode().visible_area().unwrap_or(Rectangle::zero())
+    }
+
+    /// Draw the band and move the ticker on a step.
+    pub fn draw<D>(&mut self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let area = self.area();
+        let mut band = target.cropped(&area);
+        band.clear(Rgb565::BLACK)?;
+
+        let (hour, minute) = match clock::now() {
+            Some(now) => {
+                let time = Utc::from_unix(now);
+                (u32::from(time.hour), u32::from(time.minute))
+            }
+            None => {
+                let minutes = Instant::now().as_secs() / 60;
+                ((minutes / 60 % 100) as u32, (minutes % 60) as u32)
+            }
+        };
+        let clock = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+        for (row, value) in [hour, minute].into_iter().enumerate() {
+            let digits = [b'0' + (value / 10) as u8, b'0' + (value % 10) as u8];
+            let digits = core::str::from_utf8(&digits).unwrap_or("");
+            let at = Point::new(10, row as i32 * LINE_HEIGHT);
+            Text::with_baseline(digits, at, clock, Baseline::Top).draw(&mut band)?;
+        }
+
+        let len = self.text.chars().count() as u32;
+        if len == 0 {
+            return Ok(());
+        }
+        let height = i32::from(DISPLAY_HEIGHT) - TICKER_TOP;
+        let lines = Rectangle::new(
+            Point::new(0, TICKER_TOP),
+            Size::new(u32::from(GLANCE_BAND_WIDTH), height as u32),
+        );
+        let mut lines = band.clipped(&lines);
+        let style = MonoTextStyle::new(&FONT_10X20, Rgb565::YELLOW);
+        // One character a line, entering at the bottom and leaving at the
+        // top, then round again after a blank screenful.
+        let cycle = len * LINE_HEIGHT as u32 + height as u32;
+        let scrolled = (self.offset % cycle) as i32;
+        let mut buf = [0; 4];
+        for (i, c) in self.text.chars().enumerate() {
+            let y = TICKER_TOP + height + i as i32 * LINE_HEIGHT - scrolled;
+            if y + LINE_HEIGHT <= TICKER_TOP || y >= TICKER_TOP + height {
+                continue;
+            }
+            let c = c.encode_utf8(&mut buf);
+            Text::with_baseline(c, Point::new(15, y), style, Baseline::Top).draw(&mut lines)?;
+        }
+        self.offset = self.offset.wrapping_add(SCROLL) % cycle;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+//! SPI interface for the ST7789 that can also read registers back over MISO.
+//!
+//! Mirrors `mipidsi::interface::SpiInterface` for writes, but keeps the SPI
+//! device reachable so [`Display::read_id`](super::Display::read_id) and
+//! friends can issue read commands.
+
+use core::convert::Infallible;
+
+use embedded_hal::spi::{
+    Operation,
+    SpiDevice,
+};
+use embedded_hal_bus::spi::{
+    DeviceError,
+    ExclusiveDevice,
+};
+use esp_hal::{
+    Async,
+    delay::Delay,
+    gpio::Output,
+    spi::master::{
+        Config,
+        SpiDmaBus,
+    },
+    time::Rate,
+};
+use mipidsi::interface::Interface;
+
+use super::DisplayError;
+
+/// SPI clock used for pixel and command writes.
+pub(super) const WRITE_FREQUENCY: Rate = Rate::from_mhz(80);
+
+/// SPI clock used for register reads — the ST7789 read cycle is ≥150 ns.
+const READ_FREQUENCY: Rate = Rate::from_mhz(6);
+
+type Device<'a> = ExclusiveDevice<SpiDmaBus<'a, Async>, Output<'a>, Delay>;
+
+/// Command/pixel interface with register read support.
+pub struct PanelInterface<'a> {
+    spi: Device<'a>,
+    dc: Output<'a>,
+    buffer: &'a mut [u8],
+}
+
+fn bus_error(err: DeviceError<esp_hal::spi::Error, Infallible>) -> esp_hal::spi::Error {
+    match err {
+        DeviceError::Spi(e) => e,
+        DeviceError::Cs(never) => match never {},
+    }
+}
+
+impl<'a> PanelInterface<'a> {
+    pub(super) const fn new(spi: Device<'a>, dc: Output<'a>, buffer: &'a mut [u8]) -> Self {
+        Self { spi, dc, buffer }
+    }
+
+    /// Send `command` and read `buf.len()` bytes of its response.
+    ///
+    /// The ST7789 inserts one dummy clock before multi-byte read data, so the
+    /// caller reads one extra byte and shifts it out (see [`read_shifted`](Self::read_shifted)).
+    fn read(&mut self, command: u8, buf: &mut [u8]) -> Result<(), DisplayError> {
+        let slow = Config::default().with_frequency(READ_FREQUENCY);
+        let fast = Config::default().with_frequency(WRITE_FREQUENCY);
+
+        self.spi
+            .bus_mut()
+            .apply_config(&slow)
+            .map_err(DisplayError::Config)?;
+        self.dc.set_low();
+        let result = self
+            .spi
+            .transaction(&mut [Operation::Write(&[command]), Operation::Read(buf)])
+            .map_err(bus_error);
+        self.dc.set_high();
+        self.spi
+            .bus_mut()
+            .apply_config(&fast)
+            .map_err(DisplayError::Config)?;
+
+        Ok(result?)
+    }
+
+    /// Read an `N`-byte response that follows a dummy clock cycle.
+    pub(super) fn read_shifted<const N: usize>(
+        &mut self,
+        command: u8,
+    ) -> Result<[u8; N], DisplayError> {
+        let mut raw = [0_u8; 8];
+        self.read(command, &mut raw[..=N])?;
+        let bits = u64::from_be_bytes(raw) << 1;
+        let mut out = [0_u8; N];
+        out.copy_from_slice(&bits.to_be_bytes()[..N]);
+        Ok(out)
+    }
+}
+
+impl Interface for PanelInterface<'_> {
+    type Word = u8;
+    type Error = esp_hal::spi::Error;
+
+    fn send_command(&mut self, command: u8, args: &[u8]) -> Result<(), Self::Error> {
+        self.dc.set_low();
+        self.spi.write(&[command]).map_err(bus_error)?;
+        self.dc.set_high();
+        self.spi.write(args).map_err(bus_error)
+    }
+
+    fn send_pixels<const N: usize>(
+        &mut self,
+        pixels: impl IntoIterator<Item = [Self::Word; N]>,
+    ) -> Result<(), Self::Error> {
+        let mut pixels = pixels.into_iter();
+        loop {
+            let mut len = 0;
+            for chunk in self.buffer.chunks_exact_mut(N) {
+                let Some(pixel) = pixels.next() else {
+                    break;
+                };
+                chunk.copy_from_slice(&pixel);
+                len += N;
+            }
+            if len == 0 {
+                return Ok(());
+            }
+            self.spi.write(&self.buffer[..len]).map_err(bus_error)?;
+        }
+    }
+
+    fn send_repeated_pixel<const N: usize>(
+        &mut self,
+        pixel: [Self::Word; N],
+        count: u32,
+    ) -> Result<(), Self::Error> {
+        let per_batch = (self.buffer.len() / N) as u32;
+        let filled = per_batch.min(count) as usize * N;
+        for chunk in self.buffer[..filled].chunks_exact_mut(N) {
+            chunk.copy_from_slice(&pixel);
+        }
+        let mut remaining = count;
+        while remaining > 0 {
+            let batch = remaining.min(per_batch);
+            self.spi
+                .write(&self.buffer[..batch as usize * N])
+                .map_err(bus_error)?;
+            remaining -= batch;
+        }
+        Ok(())
+    }
+}
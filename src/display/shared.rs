@@ -0,0 +1,128 @@
+//! A [`Display`] that several tasks can draw on.
+
+use core::ops::DerefMut;
+
+use embassy_sync::{
+    blocking_mutex::raw::{
+        CriticalSectionRawMutex,
+        RawMutex,
+    },
+    mutex::{
+        Mutex,
+        MutexGuard,
+    },
+};
+use embedded_graphics::{
+    Pixel,
+    draw_target::DrawTargetExt as _,
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        OriginDimensions,
+        Size,
+    },
+    primitives::Rectangle,
+};
+
+use super::{
+    Display,
+    DisplayError,
+};
+
+/// A [`Display`] behind an async mutex, so a status-bar task and an app task
+/// can both draw without handing a single `&'static mut` around.
+///
+/// Each task locks the display, draws, and drops the guard. Use
+/// [`region`](Self::region) to give a task its own rectangle: drawing is
+/// translated to the region's origin and clipped to its bounds, so a status
+/// bar can't scribble over the app and vice versa.
+///
+/// # Performance
+///
+/// - Whoever holds the lock blocks every other drawer. Lock per frame (or
+///   per widget), not for a whole animation, and never hold a guard across
+///   a `Timer` await.
+/// - SPI transfers are blocking, so a full-screen redraw (~10 ms at 80 MHz)
+///   stalls the other task for that long. Keep the status bar's region small
+///   and redraw it only when its contents change.
+/// - Clipping is done per pixel for [`DrawTarget::draw_iter`]; rectangle
+///   fills are clipped once and stay as fast as on a bare [`Display`].
+pub struct SharedDisplay<'a, M: RawMutex = CriticalSectionRawMutex> {
+    display: Mutex<M, Display<'a>>,
+}
+
+impl<'a, M: RawMutex> SharedDisplay<'a, M> {
+    pub const fn new(display: Display<'a>) -> Self {
+        Self {
+            display: Mutex::new(display),
+        }
+    }
+
+    /// Lock the whole display.
+    pub async fn lock(&self) -> MutexGuard<'_, M, Display<'a>> {
+        self.display.lock().await
+    }
+
+    /// Lock the display, limiting drawing to `area`.
+    ///
+    /// Coordinates passed to the returned [`Region`] are relative to the top
+    /// left of `area`.
+    pub async fn region(&self, area: Rectangle) -> Region<'_, 'a, M> {
+        Region {
+            guard: self.display.lock().await,
+            area,
+        }
+    }
+}
+
+/// Exclusive, clipped access to part of a [`SharedDisplay`].
+pub struct Region<'g, 'a, M: RawMutex> {
+    guard: MutexGuard<'g, M, Display<'a>>,
+    area: Rectangle,
+}
+
+impl<M: RawMutex> Region<'_, '_, M> {
+    /// The region's bounds in screen coordinates.
+    pub const fn area(&self) -> Rectangle {
+        self.area
+    }
+}
+
+impl<M: RawMutex> DrawTarget for Region<'_, '_, M> {
+    type Color = Rgb565;
+    type Error = DisplayError;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.guard.deref_mut().cropped(&self.area).draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.guard
+            .deref_mut()
+            .cropped(&self.area)
+            .fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.guard
+            .deref_mut()
+            .cropped(&self.area)
+            .fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.guard.deref_mut().fill_solid(&self.area, color)
+    }
+}
+
+impl<M: RawMutex> OriginDimensions for Region<'_, '_, M> {
+    fn size(&self) -> Size {
+        self.area.size
+    }
+}
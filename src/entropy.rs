@@ -0,0 +1,66 @@
+//! Random seeds that differ from badge to badge and boot to boot.
+//!
+//! The low bits of microphone samples are mostly thermal noise; they're
+//! hashed into a small pool along with the SoC random number generator and
+//! the time, and [`rng`] draws a fresh generator from it.
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::{
+    Mutex,
+    raw::CriticalSectionRawMutex,
+};
+use embassy_time::Instant;
+
+use crate::games::Rng;
+
+/// Low bits of each sample taken as noise.
+const NOISE_BITS: u32 = 4;
+
+static POOL: Mutex<CriticalSectionRawMutex, Cell<u64>> =
+    Mutex::new(Cell::new(0x243f_6a88_85a3_08d3));
+
+/// Stir the low bits of `samples` into the pool.
+///
+/// [`MicStream`](crate::microphone::MicStream) does this with every chunk;
+/// [`Microphone::gather_entropy`](crate::Microphone::gather_entropy) grabs a
+/// block for firmware that doesn't otherwise listen.
+pub fn add_entropy(samples: &[i16]) {
+    let mask = (1 << NOISE_BITS) - 1;
+    let per_word = (u64::BITS / NOISE_BITS) as usize;
+    let mut hash = 0;
+    for chunk in samples.chunks(per_word) {
+        let word = chunk
+            .iter()
+            .fold(0u64, |word, &s| (word << NOISE_BITS) | (s as u64 & mask));
+        hash = mix(hash ^ word);
+    }
+    POOL.lock(|pool| pool.set(mix(pool.get() ^ hash)));
+}
+
+/// A generator seeded from the pool, the SoC RNG and the time, for
+/// shuffling pieces, placing food and the like. Each call gives a different
+/// one.
+///
+/// ```rust,ignore
+/// let mut game = Snake::new(rng().next_u32());
+/// ```
+pub fn rng() -> Rng {
+    let hardware = esp_hal::rng::Rng::new();
+    let fresh = (u64::from(hardware.random()) << 32 | u64::from(hardware.random()))
+        ^ Instant::now().as_ticks().rotate_left(29);
+    let seed = POOL.lock(|pool| {
+        let seed = mix(pool.get() ^ fresh);
+        // Move the pool on so the next call can't repeat this seed.
+        pool.set(mix(seed ^ 0x9e37_79b9_7f4a_7c15));
+        seed
+    });
+    Rng::new((seed ^ (seed >> 32)) as u32)
+}
+
+/// SplitMix64's finaliser: every input bit affects every output bit.
+pub(crate) const fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
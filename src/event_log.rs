@@ -0,0 +1,404 @@
+//! An append-only log of small records in its own flash partition, behind
+//! the `event-log` feature, for histories that must survive a reset: the
+//! badges met, infection-game events and the like.
+//!
+//! ```rust,ignore
+//! let mut log = EventLog::new(resources.flash.into())?;
+//! log.append(b"met brave-otter-42")?;
+//!
+//! for record in log.iter() {
+//!     let record = record?;
+//!     info!("#{} {=[u8]:a}", record.seq(), record.bytes());
+//! }
+//! ```
+//!
+//! The partition is the one labelled [`PARTITION`], like in
+//! `partitions.csv` in the repository root. It's a ring of 4 KB sectors,
+//! filled one after the other. When the last free one fills up, the oldest
+//! sector is erased to make room, dropping its records, so the log always
+//! holds as many of the newest records as fit, and every sector is erased
+//! equally often: once per trip round the ring.
+//!
+//! Each record is written with a CRC and a sequence number that counts up
+//! for the life of the log. A reset mid-append loses that record only;
+//! [`EventLog::iter`] skips it. Appending blocks while the flash works,
+//! and the append that moves on to the next sector also erases it, which
+//! takes about 50 ms.
+//!
+//! ## Format
+//!
+//! Each sector starts with `b"BEVT"` and the sequence number of its first
+//! record, then holds records back to back, each 4-byte aligned: its
+//! length as a `u16` and again inverted, its sequence number, the CRC-32
+//! of those and the record, then the record itself. All little-endian.
+
+use defmt::info;
+use embedded_storage::{
+    ReadStorage,
+    nor_flash::NorFlash,
+};
+use esp_bootloader_esp_idf::partitions::{
+    self,
+    PARTITION_TABLE_MAX_LEN,
+};
+use esp_hal::rom::crc::crc32_le;
+use esp_storage::FlashStorageError;
+
+use crate::FlashStorage;
+
+/// Label of the partition the log is kept in.
+pub const PARTITION: &str = "events";
+/// Longest record, in bytes.
+pub const MAX_RECORD_LEN: usize = 256;
+
+const SECTOR: u32 = FlashStorage::SECTOR_SIZE;
+const MAGIC: [u8; 4] = *b"BEVT";
+/// Magic and the sequence number of the sector's first record.
+const SECTOR_HEADER_LEN: u32 = 8;
+/// Length, inverted length, sequence number and CRC.
+const RECORD_HEADER_LEN: usize = 12;
+
+/// Errors from [`EventLog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum EventLogError {
+    /// The partition table has no partition labelled [`PARTITION`], or it's
+    /// shorter than two sectors.
+    Partition(partitions::Error),
+    Flash(FlashStorageError),
+    /// The record is longer than [`MAX_RECORD_LEN`].
+    TooLong,
+}
+
+impl From<partitions::Error> for EventLogError {
+    fn from(err: partitions::Error) -> Self {
+        Self::Partition(err)
+    }
+}
+
+impl From<FlashStorageError> for EventLogError {
+    fn from(err: FlashStorageError) -> Self {
+        Self::Flash(err)
+    }
+}
+
+/// One record read back by [`EventLog::iter`].
+#[derive(Clone, Debug)]
+pub struct Record {
+    seq: u32,
+    bytes: [u8; MAX_RECORD_LEN],
+    len: usize,
+}
+
+impl Record {
+    /// Sequence number, one more than the record appended before it.
+    pub const fn seq(&self) -> u32 {
+        self.seq
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl defmt::Format for Record {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        defmt::write!(fmt, "#{} {=[u8]}", self.seq, self.bytes());
+    }
+}
+
+/// What a record header says is at a position in a sector.
+enum Slot {
+    /// Erased: the sector's records end here.
+    Free,
+    /// Not a record header, as after a reset mid-append: nothing more can
+    /// be read from the sector.
+    Garbage,
+    Record {
+        len: usize,
+    },
+}
+
+impl Slot {
+    fn parse(bytes: &[u8; RECORD_HEADER_LEN]) -> Self {
+        if bytes[..4] == [0xff; 4] {
+            return Self::Free;
+        }
+        let [l0, l1, n0, n1, ..] = *bytes;
+        let len = u16::from_le_bytes([l0, l1]);
+        if len != !u16::from_le_bytes([n0, n1]) || usize::from(len) > MAX_RECORD_LEN {
+            return Self::Garbage;
+        }
+        Self::Record {
+            len: usize::from(len),
+        }
+    }
+}
+
+/// Bytes a record of `len` bytes takes in a sector.
+const fn span(len: usize) -> u32 {
+    (RECORD_HEADER_LEN + len.next_multiple_of(4)) as u32
+}
+
+/// The log in the [`PARTITION`] partition. See the [module docs](self).
+pub struct EventLog<'d> {
+    flash: FlashStorage<'d>,
+    /// Start of the partition.
+    offset: u32,
+    sectors: u32,
+    /// The sector being appended to.
+    active: u32,
+    /// Where in it the next record goes.
+    position: u32,
+    /// Sequence number of the next record.
+    seq: u32,
+}
+
+impl<'d> EventLog<'d> {
+    /// Find the [`PARTITION`] partition and where the log in it ends.
+    ///
+    /// An erased partition comes up empty; sectors that hold anything
+    /// else are erased as the log reaches them.
+    pub fn new(mut flash: FlashStorage<'d>) -> Result<Self, EventLogError> {
+        let mut table = [0; PARTITION_TABLE_MAX_LEN];
+        let table = partitions::read_partition_table(&mut flash, &mut table)?;
+        let partition = table
+            .iter()
+            .find(|partition| partition.label_as_str() == PARTITION)
+            .ok_or(partitions::Error::Invalid)?;
+        let (offset, len) = (partition.offset(), partition.len());
+        if len < 2 * SECTOR {
+            return Err(partitions::Error::Invalid.into());
+        }
+        let sectors = len / SECTOR;
+        // With no sector started, the first append starts sector 0.
+        let mut log = Self {
+            flash,
+            offset,
+            sectors,
+            active: sectors - 1,
+            position: SECTOR,
+            seq: 0,
+        };
+        log.mount()?;
+        info!(
+            "event_log: {} sectors, next record #{}",
+            log.sectors, log.seq
+        );
+        Ok(log)
+    }
+
+    /// Add `record` after the newest, erasing the oldest sector if there's
+    /// no room left, and return its sequence number.
+    pub fn append(&mut self, record: &[u8]) -> Result<u32, EventLogError> {
+        if record.len() > MAX_RECORD_LEN {
+            return Err(EventLogError::TooLong);
+        }
+        let span = span(record.len());
+        if self.position + span > SECTOR {
+            self.advance()?;
+        }
+
+        let mut buf = [0; RECORD_HEADER_LEN + MAX_RECORD_LEN];
+        let len = record.len() as u16;
+        buf[..2].copy_from_slice(&len.to_le_bytes());
+        buf[2..4].copy_from_slice(&(!len).to_le_bytes());
+        buf[4..8].copy_from_slice(&self.seq.to_le_bytes());
+        let crc = crc32_le(crc32_le(0, &buf[..8]), record);
+        buf[8..12].copy_from_slice(&crc.to_le_bytes());
+        buf[RECORD_HEADER_LEN..RECORD_HEADER_LEN + record.len()].copy_from_slice(record);
+
+        let at = self.address(self.active) + self.position;
+        self.flash.write(at, &buf[..span as usize])?;
+        self.position += span;
+        let seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        Ok(seq)
+    }
+
+    /// Every record still in the log, oldest first.
+    pub fn iter(&mut self) -> Records<'_, 'd> {
+        Records {
+            log: self,
+            visited: 0,
+            position: 0,
+        }
+    }
+
+    /// Erase every record. Sequence numbers carry on from the last.
+    pub fn clear(&mut self) -> Result<(), EventLogError> {
+        for sector in 0..self.sectors {
+            if self.sector_start(sector)?.is_some() {
+                let at = self.address(sector);
+                self.flash.erase(at, at + SECTOR)?;
+            }
+        }
+        // Start sector 0 straight away, to keep the sequence number.
+        self.active = self.sectors - 1;
+        self.advance()
+    }
+
+    fn address(&self, sector: u32) -> u32 {
+        self.offset + sector * SECTOR
+    }
+
+    /// Sequence number of the first record in `sector`, or `None` if it
+    /// hasn't been started.
+    fn sector_start(&mut self, sector: u32) -> Result<Option<u32>, EventLogError> {
+        let mut header = [0; SECTOR_HEADER_LEN as usize];
+        let at = self.address(sector);
+        ReadStorage::read(&mut self.flash, at, &mut header)?;
+        let [m0, m1, m2, m3, s0, s1, s2, s3] = header;
+        Ok(([m0, m1, m2, m3] == MAGIC).then_some(u32::from_le_bytes([s0, s1, s2, s3])))
+    }
+
+    /// The header of the record at `position` in `sector`.
+    fn slot(&mut self, sector: u32, position: u32) -> Result<Slot, EventLogError> {
+        if position + RECORD_HEADER_LEN as u32 > SECTOR {
+            return Ok(Slot::Garbage);
+        }
+        let mut header = [0; RECORD_HEADER_LEN];
+        let at = self.address(sector) + position;
+        ReadStorage::read(&mut self.flash, at, &mut header)?;
+        Ok(Slot::parse(&header))
+    }
+
+    /// Find the newest sector and the end of the records in it.
+    fn mount(&mut self) -> Result<(), EventLogError> {
+        let mut newest = None;
+        for sector in 0..self.sectors {
+            if let Some(start) = self.sector_start(sector)?
+                && newest.is_none_or(|(_, newest)| start > newest)
+            {
+                newest = Some((sector, start));
+            }
+        }
+        let Some((active, start)) = newest else {
+            return Ok(());
+        };
+        self.active = active;
+        self.seq = start;
+        self.position = SECTOR_HEADER_LEN;
+        loop {
+            match self.slot(active, self.position)? {
+                Slot::Free => break,
+                Slot::Garbage => {
+                    self.position = SECTOR;
+                    break;
+                }
+                Slot::Record { len } => {
+                    // A torn record's number may be torn too, and it's
+                    // never read back, so only count intact ones.
+                    if let Some(record) = self.record(active, self.position, len)? {
+                        self.seq = self.seq.max(record.seq.wrapping_add(1));
+                    }
+                    self.position += span(len);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The record of `len` bytes at `position` in `sector`, or `None` if
+    /// its CRC doesn't match.
+    fn record(
+        &mut self,
+        sector: u32,
+        position: u32,
+        len: usize,
+    ) -> Result<Option<Record>, EventLogError> {
+        let mut buf = [0; RECORD_HEADER_LEN + MAX_RECORD_LEN];
+        let at = self.address(sector) + position;
+        ReadStorage::read(&mut self.flash, at, &mut buf[..RECORD_HEADER_LEN + len])?;
+        let crc = u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]);
+        if crc32_le(
+            crc32_le(0, &buf[..8]),
+            &buf[RECORD_HEADER_LEN..RECORD_HEADER_LEN + len],
+        ) != crc
+        {
+            return Ok(None);
+        }
+        let mut record = Record {
+            seq: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            bytes: [0; MAX_RECORD_LEN],
+            len,
+        };
+        record.bytes[..len].copy_from_slice(&buf[RECORD_HEADER_LEN..RECORD_HEADER_LEN + len]);
+        Ok(Some(record))
+    }
+
+    /// Erase the sector after the active one, dropping its records, and
+    /// start appending to it.
+    fn advance(&mut self) -> Result<(), EventLogError> {
+        let next = (self.active + 1) % self.sectors;
+        let at = self.address(next);
+        self.flash.erase(at, at + SECTOR)?;
+        let mut header = [0; SECTOR_HEADER_LEN as usize];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4..].copy_from_slice(&self.seq.to_le_bytes());
+        self.flash.write(at, &header)?;
+        self.active = next;
+        self.position = SECTOR_HEADER_LEN;
+        Ok(())
+    }
+}
+
+/// Records from [`EventLog::iter`], oldest first. Each reads the flash; a
+/// flash error ends the iteration after it's returned.
+pub struct Records<'a, 'd> {
+    log: &'a mut EventLog<'d>,
+    /// Sectors done, counting from the one after the active sector.
+    visited: u32,
+    /// Where in the current sector the next record is, or 0 before its
+    /// header has been read.
+    position: u32,
+}
+
+impl Records<'_, '_> {
+    fn step(&mut self) -> Result<Option<Record>, EventLogError> {
+        while self.visited < self.log.sectors {
+            let sector = (self.log.active + 1 + self.visited) % self.log.sectors;
+            if self.position == 0 {
+                if self.log.sector_start(sector)?.is_none() {
+                    self.visited += 1;
+                    continue;
+                }
+                self.position = SECTOR_HEADER_LEN;
+            }
+            let end = if sector == self.log.active {
+                self.log.position
+            } else {
+                SECTOR
+            };
+            let slot = if self.position < end {
+                self.log.slot(sector, self.position)?
+            } else {
+                Slot::Free
+            };
+            let Slot::Record { len } = slot else {
+                self.visited += 1;
+                self.position = 0;
+                continue;
+            };
+            let record = self.log.record(sector, self.position, len)?;
+            self.position += span(len);
+            if record.is_some() {
+                return Ok(record);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for Records<'_, '_> {
+    type Item = Result<Record, EventLogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step() {
+            Ok(record) => record.map(Ok),
+            Err(err) => {
+                self.visited = self.log.sectors;
+                Some(Err(err))
+            }
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Files in their own flash partition, behind the `fs` feature: for
+//! recordings, images, logs and anything else too big for a
+//! [`Store`](crate::storage::Store) value.
+//!
+//! ```rust,ignore
+//! let mut fs = Fs::new(resources.flash.into())?;
+//! fs.write("hello.txt", b"Hello, Disobey!")?;
+//!
+//! let mut recording = fs.create("rec/001.pcm")?;
+//! while let Some(samples) = mic.next().await {
+//!     recording.write(samples)?;
+//! }
+//! recording.finish()?;
+//!
+//! for file in fs.files()? {
+//!     info!("{} {} bytes", file.name.as_str(), file.size);
+//! }
+//! let mut buf = [0; 64];
+//! let len = fs.read("hello.txt", 0, &mut buf)?;
+//! ```
+//!
+//! The partition is the one labelled [`PARTITION`] in the partition table,
+//! like `partitions.csv` in the repository root, formatted as
+//! [littlefs](https://github.com/littlefs-project/littlefs) through the
+//! `littlefs2` crate:
+//!
+//! - Small files are kept inline in their directory's blocks, so a short
+//!   log or setting costs bytes rather than a 4 KB sector each.
+//! - A `/` in a name is a directory, made as needed; [`Fs::files`] lists
+//!   the files in all of them.
+//! - A file from [`Fs::create`] is written to a scratch file and renamed
+//!   over the old one by [`FileWriter::finish`]. A reset before that
+//!   leaves the old contents; replacing a file needs room for both copies
+//!   until then.
+//! - Erases are spread across the partition, files that never change
+//!   included.
+//! - Every call blocks while the flash works; erasing a sector takes about
+//!   50 ms. Put an `Fs` behind an async mutex to share it between tasks.
+//!
+//! littlefs itself is C, built by `littlefs2-sys` with the C compiler and
+//! libclang `espup install` sets up alongside the Rust toolchain.
+
+use alloc::{
+    boxed::Box,
+    string::String,
+    vec::Vec,
+};
+
+use defmt::{
+    info,
+    warn,
+};
+use embedded_storage::nor_flash::{
+    NorFlash,
+    ReadNorFlash,
+};
+use esp_bootloader_esp_idf::partitions::{
+    self,
+    PARTITION_TABLE_MAX_LEN,
+};
+use littlefs2::{
+    consts::{
+        U1,
+        U256,
+    },
+    driver::Storage,
+    fs::{
+        Allocation,
+        File,
+        FileAllocation,
+        Filesystem,
+        OpenOptions,
+    },
+    io::{
+        self,
+        SeekFrom,
+    },
+    path,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::FlashStorage;
+#[cfg(feature = "serde")]
+use crate::storage::{
+    self,
+    EncodingError,
+    TypedError,
+    Versioned,
+};
+
+/// Label of the partition files are kept in.
+pub const PARTITION: &str = "files";
+/// Longest file name, in bytes, directories included.
+pub const MAX_NAME_LEN: usize = 32;
+/// Bytes of the partition in use. `littlefs2` takes the block count as a
+/// constant, so this matches `partitions.csv`; a larger partition is used
+/// only this far.
+const PARTITION_LEN: u32 = 0x2_0000;
+/// Where [`FileWriter`] writes until it's finished.
+const SCRATCH: &Path = path!(".partial");
+
+/// Errors from [`Fs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum FsError {
+    /// The partition table has no partition labelled [`PARTITION`], or it's
+    /// smaller than the 128 KB in `partitions.csv`.
+    Partition(partitions::Error),
+    NotFound,
+    /// No block is left for the data written.
+    Full,
+    /// The name is empty, longer than [`MAX_NAME_LEN`] or not ASCII.
+    BadName,
+    /// Any other littlefs error, by its code: -5 for a flash read or write
+    /// that failed, -84 for a corrupt block.
+    Io(i32),
+}
+
+impl From<partitions::Error> for FsError {
+    fn from(err: partitions::Error) -> Self {
+        Self::Partition(err)
+    }
+}
+
+impl From<io::Error> for FsError {
+    fn from(err: io::Error) -> Self {
+        match err {
+            io::Error::NO_SUCH_ENTRY => Self::NotFound,
+            io::Error::NO_SPACE => Self::Full,
+            io::Error::FILENAME_TOO_LONG | io::Error::INVALID => Self::BadName,
+            err => Self::Io(err.code()),
+        }
+    }
+}
+
+/// A file as [`Fs::files`] lists it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileInfo {
+    /// Full name, directories included.
+    pub name: String,
+    /// Length in bytes.
+    pub size: u32,
+}
+
+/// The [`PARTITION`] partition, as littlefs reads and writes it.
+struct Partition<'d> {
+    flash: FlashStorage<'d>,
+    /// Start of the partition.
+    offset: u32,
+}
+
+impl Storage for Partition<'_> {
+    const READ_SIZE: usize = FlashStorage::WORD_SIZE as usize;
+    const WRITE_SIZE: usize = FlashStorage::WORD_SIZE as usize;
+    const BLOCK_SIZE: usize = FlashStorage::SECTOR_SIZE as usize;
+    const BLOCK_COUNT: usize = (PARTITION_LEN / FlashStorage::SECTOR_SIZE) as usize;
+    /// Erases of a metadata block before littlefs moves it, to spread wear.
+    const BLOCK_CYCLES: isize = 500;
+    type CACHE_SIZE = U256;
+    /// 8 bytes, a bit for each of up to 64 blocks.
+    type LOOKAHEAD_SIZE = U1;
+
+    fn read(&mut self, off: usize, buf: &mut [u8]) -> io::Result<usize> {
+        ReadNorFlash::read(&mut self.flash, self.offset + off as u32, buf)
+            .map_err(|_| io::Error::IO)?;
+        Ok(buf.len())
+    }
+
+    fn write(&mut self, off: usize, data: &[u8]) -> io::Result<usize> {
+        NorFlash::write(&mut self.flash, self.offset + off as u32, data)
+            .map_err(|_| io::Error::IO)?;
+        Ok(data.len())
+    }
+
+    fn erase(&mut self, off: usize, len: usize) -> io::Result<usize> {
+        let from = self.offset + off as u32;
+        NorFlash::erase(&mut self.flash, from, from + len as u32).map_err(|_| io::Error::IO)?;
+        Ok(len)
+    }
+}
+
+/// A filesystem in the [`PARTITION`] partition. See the [module docs](self).
+///
+/// littlefs keeps pointers into its buffers and the partition for as long
+/// as it's mounted, so [`Fs::new`] leaks them, about 1 KB: make one `Fs`
+/// at boot and share it.
+pub struct Fs<'d> {
+    fs: Filesystem<'d, Partition<'d>>,
+}
+
+// SAFETY: the pointers littlefs holds are into the buffers and partition
+// leaked for this `Fs` alone, so they move between tasks with it.
+unsafe impl Send for Fs<'_> {}
+
+impl<'d> Fs<'d> {
+    /// Find the [`PARTITION`] partition and mount it.
+    ///
+    /// A partition that doesn't hold littlefs, erased or written by other
+    /// firmware, is formatted and comes up empty.
+    pub fn new(mut flash: FlashStorage<'d>) -> Result<Self, FsError> {
+        let mut table = [0; PARTITION_TABLE_MAX_LEN];
+        let table = partitions::read_partition_table(&mut flash, &mut table)?;
+        let partition = table
+            .iter()
+            .find(|partition| partition.label_as_str() == PARTITION)
+            .ok_or(partitions::Error::Invalid)?;
+        let (offset, len) = (partition.offset(), partition.len());
+        if len < PARTITION_LEN {
+            return Err(partitions::Error::Invalid.into());
+        }
+
+        let storage = Box::leak(Box::new(Partition { flash, offset }));
+        if !Filesystem::is_mountable(storage) {
+            warn!("fs: formatting the {} partition", PARTITION);
+            Filesystem::format(storage)?;
+        }
+        let alloc = Box::leak(Box::new(Allocation::new()));
+        let fs = Self {
+            fs: Filesystem::mount(alloc, storage)?,
+        };
+        // Left by a writer a reset cut off.
+        match fs.fs.remove(SCRATCH) {
+            Ok(()) | Err(io::Error::NO_SUCH_ENTRY) => {}
+            Err(err) => return Err(err.into()),
+        }
+        info!("fs: {} of {} bytes free", fs.free()?, fs.capacity());
+        Ok(fs)
+    }
+
+    /// Bytes the partition holds.
+    pub fn capacity(&self) -> u32 {
+        self.fs.total_space() as u32
+    }
+
+    /// Bytes left for new files, at least: small files share blocks, so
+    /// more may fit.
+    pub fn free(&self) -> Result<u32, FsError> {
+        Ok(self.fs.available_space()? as u32)
+    }
+
+    /// Every file, in every directory.
+    pub fn files(&self) -> Result<Vec<FileInfo>, FsError> {
+        let mut files = Vec::new();
+        let mut dirs = alloc::vec![PathBuf::from(path!("/"))];
+        while let Some(dir) = dirs.pop() {
+            self.fs.read_dir_and_then(&dir, |entries| {
+                for entry in entries {
+                    let entry = entry?;
+                    let name = entry.path().as_str().trim_start_matches('/');
+                    if matches!(entry.file_name().as_str(), "." | "..") || name == SCRATCH.as_str()
+                    {
+                        continue;
+                    }
+                    if entry.metadata().is_dir() {
+                        dirs.push(entry.path().into());
+                    } else {
+                        files.push(FileInfo {
+                            name: name.into(),
+                            size: entry.metadata().len() as u32,
+                        });
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Ok(files)
+    }
+
+    /// Length of `name` in bytes, if it exists.
+    pub fn size(&self, name: &str) -> Option<u32> {
+        let metadata = self.fs.metadata(&file_path(name).ok()?).ok()?;
+        metadata.is_file().then_some(metadata.len() as u32)
+    }
+
+    /// Read from `name` at `offset` into `buf`, returning the bytes read:
+    /// fewer than `buf` holds only at the end of the file.
+    pub fn read(&self, name: &str, offset: u32, buf: &mut [u8]) -> Result<usize, FsError> {
+        let path = file_path(name)?;
+        let read = self.fs.open_file_and_then(&path, |file| {
+            file.seek(SeekFrom::Start(offset))?;
+            let mut read = 0;
+            while read < buf.len() {
+                match file.read(&mut buf[read..])? {
+                    0 => break,
+                    count => read += count,
+                }
+            }
+            Ok(read)
+        })?;
+        Ok(read)
+    }
+
+    /// Write `data` as `name`, replacing any file of that name.
+    pub fn write(&mut self, name: &str, data: &[u8]) -> Result<(), FsError> {
+        let mut writer = self.create(name)?;
+        writer.write(data)?;
+        writer.finish()
+    }
+
+    /// Start writing `name`. It appears, replacing any file of that name,
+    /// once [`FileWriter::finish`] is called; dropping the writer before
+    /// then leaves things as they were.
+    pub fn create(&mut self, name: &str) -> Result<FileWriter<'_, 'd>, FsError> {
+        let name = file_path(name)?;
+        let fs = &self.fs;
+        // Freed only once the file is closed, so it outlives littlefs's
+        // pointer to it even if the writer is forgotten.
+        let alloc = Box::into_raw(Box::new(FileAllocation::new()));
+        // SAFETY: `alloc` is the writer's own and stays put until
+        // `FileWriter::close` closes the file.
+        let file = unsafe {
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(fs, &mut *alloc, SCRATCH)
+        };
+        match file {
+            Ok(file) => Ok(FileWriter {
+                fs,
+                file: Some(file),
+                alloc,
+                name,
+                size: 0,
+            }),
+            Err(err) => {
+                // SAFETY: the file didn't open, so littlefs holds no pointer
+                // to `alloc`.
+                drop(unsafe { Box::from_raw(alloc) });
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Delete `name`, or a directory `name` if it's empty.
+    pub fn remove(&mut self, name: &str) -> Result<(), FsError> {
+        Ok(self.fs.remove(&file_path(name)?)?)
+    }
+
+    /// Read `name` as a [`Versioned`] `serde` type, or `None` if it doesn't
+    /// exist or is an older version [`Versioned::upgrade`] doesn't take.
+    #[cfg(feature = "serde")]
+    pub fn load<T: Versioned>(&self, name: &str) -> Result<Option<T>, TypedError<FsError>> {
+        let Some(size) = self.size(name) else {
+            return Ok(None);
+        };
+        let mut bytes = alloc::vec![0; size as usize];
+        self.read(name, 0, &mut bytes)?;
+        storage::decode(&bytes)
+    }
+
+    /// Write `value` as `name`, with its version, replacing any file of
+    /// that name. Unlike a [`Store`](storage::Store) value, it can be as
+    /// long as the free space allows.
+    #[cfg(feature = "serde")]
+    pub fn save<T: Versioned>(&mut self, name: &str, value: &T) -> Result<(), TypedError<FsError>> {
+        let mut buf = alloc::vec![0; storage::MAX_ENCODED_LEN];
+        loop {
+            let len = buf.len();
+            match storage::encode(value, &mut buf) {
+                Ok(bytes) => return Ok(self.write(name, bytes)?),
+                Err(EncodingError::SerializeBufferFull) if len < self.capacity() as usize => {
+                    buf.resize(len * 2, 0);
+                }
+                Err(err) => return Err(TypedError::Encoding(err)),
+            }
+        }
+    }
+}
+
+/// A file being written, from [`Fs::create`].
+pub struct FileWriter<'f, 'd> {
+    fs: &'f Filesystem<'d, Partition<'d>>,
+    /// The scratch file, until it's closed.
+    file: Option<File<'d, 'f, Partition<'d>>>,
+    alloc: *mut FileAllocation<Partition<'d>>,
+    name: PathBuf,
+    size: u32,
+}
+
+impl FileWriter<'_, '_> {
+    /// Add `data` to the end of the file.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), FsError> {
+        let file = self.file.as_ref().expect("closed only by finish or drop");
+        let mut rest = data;
+        while !rest.is_empty() {
+            let count = file.write(rest)?;
+            rest = &rest[count..];
+        }
+        self.size += data.len() as u32;
+        Ok(())
+    }
+
+    /// Bytes written so far.
+    pub const fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Close the file and rename it over any older file of the same name,
+    /// which makes it appear.
+    pub fn finish(mut self) -> Result<(), FsError> {
+        self.close()?;
+        if let Some(dir) = self.name.parent() {
+            self.fs.create_dir_all(&dir)?;
+        }
+        Ok(self.fs.rename(SCRATCH, &self.name)?)
+    }
+
+    fn close(&mut self) -> Result<(), FsError> {
+        let Some(file) = self.file.take() else {
+            return Ok(());
+        };
+        // SAFETY: `file` is taken, so it's closed only once.
+        let closed = unsafe { file.close() };
+        // SAFETY: closing drops the file from littlefs's list, even when
+        // writing its last data fails, so nothing points to `alloc` now.
+        drop(unsafe { Box::from_raw(self.alloc) });
+        Ok(closed?)
+    }
+}
+
+impl Drop for FileWriter<'_, '_> {
+    /// An unfinished file is closed and its scratch copy removed.
+    fn drop(&mut self) {
+        if self.file.is_some() {
+            let _ = self.close();
+            let _ = self.fs.remove(SCRATCH);
+        }
+    }
+}
+
+/// `name` as a path from the root, if it's a name [`Fs`] takes.
+fn file_path(name: &str) -> Result<PathBuf, FsError> {
+    let name = name.trim_start_matches('/');
+    if name.is_empty() || name.len() > MAX_NAME_LEN || name == SCRATCH.as_str() {
+        return Err(FsError::BadName);
+    }
+    PathBuf::try_from(name).map_err(|_| FsError::BadName)
+}
@@ -0,0 +1,602 @@
+//! Two-player game link over ESP-NOW, behind the `esp-now` feature.
+//!
+//! Badges running the same game find each other in a lobby, pair when both
+//! players hold A, then exchange messages over a channel that acknowledges,
+//! retries and de-duplicates, and notices when the other badge goes away.
+//!
+//! ```rust,ignore
+//! const PONG: GameId = 0x504f;
+//! static LINK: GameLink = GameLink::new(PONG);
+//!
+//! #[embassy_executor::task]
+//! async fn link_task(esp_now: EspNow<'static>) {
+//!     LINK.run(esp_now).await
+//! }
+//!
+//! // main: the radio must be on for ESP-NOW
+//! let (mut wifi, _device, esp_now) = Wifi::with_esp_now(resources.wifi)?;
+//! wifi.start().await?;
+//! spawner.must_spawn(link_task(esp_now));
+//!
+//! // game task
+//! LINK.set_pairing(buttons.a.is_low());
+//! match LINK.next().await {
+//!     LinkEvent::Linked { peer, host } => info!("playing {}", peer),
+//!     LinkEvent::Message(bytes) => apply(&bytes),
+//!     LinkEvent::Unlinked => show_lobby(),
+//! }
+//! LINK.send(&[SCORE, 3]).await?; // waits for the ack
+//! LINK.post(&paddle.to_le_bytes()); // fire and forget, for state sent every frame
+//! ```
+//!
+//! Both badges must be on the same Wi-Fi channel: the default one, or that
+//! of an access point they have both joined.
+
+use core::{
+    cell::RefCell,
+    ops::Deref,
+    sync::atomic::{
+        AtomicBool,
+        Ordering,
+    },
+};
+
+use embassy_futures::select::{
+    Either3,
+    select3,
+};
+use embassy_sync::{
+    blocking_mutex::{
+        self,
+        raw::CriticalSectionRawMutex,
+    },
+    channel::Channel,
+    mutex::Mutex,
+    signal::Signal,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+pub use esp_radio::esp_now::EspNow;
+use esp_radio::esp_now::{
+    BROADCAST_ADDRESS,
+    ESP_NOW_MAX_DATA_LEN,
+    EspNowManager,
+    EspNowSender,
+    EspNowWifiInterface,
+    PeerInfo,
+};
+
+use crate::BadgeId;
+pub use crate::games::GameId;
+
+/// Longest message, what ESP-NOW carries after the link's header.
+pub const MAX_PAYLOAD: usize = ESP_NOW_MAX_DATA_LEN - HEADER_LEN;
+/// How many badges the [`lobby`](GameLink::lobby) lists.
+pub const MAX_LOBBY: usize = 8;
+/// Events queued for the game before new ones are dropped.
+pub const LINK_QUEUE: usize = 8;
+/// How often a badge in the lobby announces itself.
+pub const HELLO_INTERVAL: Duration = Duration::from_millis(250);
+/// How often a linked badge sends something, even with nothing to say.
+pub const KEEPALIVE: Duration = Duration::from_millis(250);
+/// A peer not heard from for this long has left: the link drops, and a
+/// badge in the lobby disappears from it.
+pub const LINK_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long [`GameLink::send`] waits for an ack before sending again.
+pub const ACK_TIMEOUT: Duration = Duration::from_millis(50);
+/// Tries before [`GameLink::send`] gives up with [`LinkError::Timeout`].
+pub const MAX_ATTEMPTS: u8 = 10;
+
+/// Magic, kind, game and sequence number.
+const HEADER_LEN: usize = 7;
+const MAGIC: [u8; 2] = *b"DL";
+
+// Frame kinds.
+/// Lobby announcement, broadcast. Payload: 1 if the player holds A.
+const HELLO: u8 = 0;
+/// "Let's play", sent to a pairing peer until it answers. Payload: that
+/// peer's address, since it's broadcast until the two are paired.
+const PAIR: u8 = 1;
+/// Reliable message, acked with its sequence number.
+const DATA: u8 = 2;
+const ACK: u8 = 3;
+/// Unreliable message.
+const POST: u8 = 4;
+const PING: u8 = 5;
+/// Leaving the game.
+const BYE: u8 = 6;
+
+/// A message as sent or received, at most [`MAX_PAYLOAD`] bytes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Payload {
+    len: u8,
+    bytes: [u8; MAX_PAYLOAD],
+}
+
+impl Payload {
+    /// `data` as a payload, or `None` if it's longer than [`MAX_PAYLOAD`].
+    pub fn new(data: &[u8]) -> Option<Self> {
+        let mut bytes = [0; MAX_PAYLOAD];
+        bytes.get_mut(..data.len())?.copy_from_slice(data);
+        Some(Self {
+            len: data.len() as u8,
+            bytes,
+        })
+    }
+}
+
+impl Deref for Payload {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+}
+
+impl core::fmt::Debug for Payload {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl defmt::Format for Payload {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "{=[u8]}", &**self);
+    }
+}
+
+/// What happened to the link.
+// Messages are carried inline: no heap, and events stay `Copy`.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum LinkEvent {
+    /// Paired with `peer`. Exactly one of the two badges is the `host`, for
+    /// whatever the game needs decided once, like who serves first.
+    Linked { peer: BadgeId, host: bool },
+    /// The peer left or went quiet for [`LINK_TIMEOUT`]; back in the lobby.
+    Unlinked,
+    /// A message from the peer, from [`send`](GameLink::send) or
+    /// [`post`](GameLink::post).
+    Message(Payload),
+}
+
+/// Why a message wasn't delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum LinkError {
+    /// Not paired with anyone.
+    NotLinked,
+    /// No ack after [`MAX_ATTEMPTS`].
+    Timeout,
+    /// Longer than [`MAX_PAYLOAD`].
+    TooLong,
+}
+
+/// A badge seen in the lobby.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct LobbyPeer {
+    pub id: BadgeId,
+    /// Whether its player is holding A to pair.
+    pub pairing: bool,
+    /// Signal strength in dBm.
+    pub rssi: i8,
+    /// When it last announced itself.
+    pub seen: Instant,
+}
+
+enum Request {
+    Send(Payload),
+    Post(Payload),
+    Leave,
+}
+
+/// One side of a two-player game. See the [module docs](self).
+pub struct GameLink {
+    game: GameId,
+    pairing: AtomicBool,
+    lobby: blocking_mutex::Mutex<CriticalSectionRawMutex, RefCell<[Option<LobbyPeer>; MAX_LOBBY]>>,
+    events: Channel<CriticalSectionRawMutex, LinkEvent, LINK_QUEUE>,
+    requests: Channel<CriticalSectionRawMutex, Request, 1>,
+    /// Result of the [`Request::Send`] in flight.
+    delivered: Signal<CriticalSectionRawMutex, Result<(), LinkError>>,
+    /// One [`send`](Self::send) at a time.
+    sending: Mutex<CriticalSectionRawMutex, ()>,
+}
+
+impl GameLink {
+    pub const fn new(game: GameId) -> Self {
+        Self {
+            game,
+            pairing: AtomicBool::new(false),
+            lobby: blocking_mutex::Mutex::new(RefCell::new([None; MAX_LOBBY])),
+            events: Channel::new(),
+            requests: Channel::new(),
+            delivered: Signal::new(),
+            sending: Mutex::new(()),
+        }
+    }
+
+    /// Run the link over `esp_now` forever: announce this badge in the
+    /// lobby, pair, and carry messages once paired.
+    pub async fn run(&self, esp_now: EspNow<'static>) -> ! {
+        let (manager, mut sender, mut receiver) = esp_now.split();
+        let mut session = Session {
+            link: self,
+            me: crate::id(),
+            manager,
+            peer: None,
+            next_hello: Instant::now(),
+        };
+        loop {
+            let deadline = session.deadline();
+            match select3(
+                receiver.receive_async(),
+                self.requests.receive(),
+                Timer::at(deadline),
+            )
+            .await
+            {
+                Either3::First(received) => {
+                    let rssi = received.info.rx_control.rssi.clamp(-128, 0) as i8;
+                    session
+                        .on_frame(
+                            &mut sender,
+                            received.info.src_address,
+                            received.data(),
+                            rssi,
+                        )
+                        .await;
+                }
+                Either3::Second(request) => session.on_request(&mut sender, request).await,
+                Either3::Third(()) => session.on_tick(&mut sender).await,
+            }
+        }
+    }
+
+    /// Whether this badge's player wants to pair: call it with whether A is
+    /// held. Two badges pair once both are.
+    pub fn set_pairing(&self, pairing: bool) {
+        self.pairing.store(pairing, Ordering::Relaxed);
+    }
+
+    /// Badges running the same game heard within [`LINK_TIMEOUT`], strongest
+    /// signal first.
+    pub fn lobby(&self) -> [Option<LobbyPeer>; MAX_LOBBY] {
+        let now = Instant::now();
+        let mut lobby = self.lobby.lock(|lobby| *lobby.borrow());
+        for slot in &mut lobby {
+            if slot.is_some_and(|peer| now.saturating_duration_since(peer.seen) > LINK_TIMEOUT) {
+                *slot = None;
+            }
+        }
+        lobby.sort_unstable_by_key(|slot| core::cmp::Reverse(slot.map(|peer| peer.rssi)));
+        lobby
+    }
+
+    /// Wait for the next event.
+    pub async fn next(&self) -> LinkEvent {
+        self.events.receive().await
+    }
+
+    /// The next event if one is already waiting.
+    pub fn try_next(&self) -> Option<LinkEvent> {
+        self.events.try_receive().ok()
+    }
+
+    /// Deliver `data` to the peer, waiting until it's acknowledged. Messages
+    /// arrive once each, in the order sent.
+    pub async fn send(&self, data: &[u8]) -> Result<(), LinkError> {
+        let payload = Payload::new(data).ok_or(LinkError::TooLong)?;
+        let _sending = self.sending.lock().await;
+        self.delivered.reset();
+        self.requests.send(Request::Send(payload)).await;
+        self.delivered.wait().await
+    }
+
+    /// Send `data` once, without waiting or retrying: for state sent every
+    /// frame, where the next one replaces a lost one anyway.
+    pub fn post(&self, data: &[u8]) -> Result<(), LinkError> {
+        let payload = Payload::new(data).ok_or(LinkError::TooLong)?;
+        // A full queue means the radio is behind; dropping is what a lost
+        // frame would do.
+        let _ = self.requests.try_send(Request::Post(payload));
+        Ok(())
+    }
+
+    /// Tell the peer this badge is leaving, and go back to the lobby.
+    pub async fn leave(&self) {
+        self.requests.send(Request::Leave).await;
+    }
+
+    fn event(&self, event: LinkEvent) {
+        if self.events.try_send(event).is_err() {
+            defmt::warn!("gamelink: event queue full, dropped {}", event);
+        }
+    }
+
+    fn heard(&self, id: BadgeId, pairing: bool, rssi: i8) {
+        let seen = Instant::now();
+        self.lobby.lock(|lobby| {
+            let mut lobby = lobby.borrow_mut();
+            let slot = match lobby
+                .iter()
+                .position(|slot| slot.is_some_and(|peer| peer.id == id))
+            {
+                Some(index) => index,
+                None => lobby
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, slot)| slot.map(|peer| peer.seen))
+                    .map_or(0, |(index, _)| index),
+            };
+            lobby[slot] = Some(LobbyPeer {
+                id,
+                pairing,
+                rssi,
+                seen,
+            });
+        });
+    }
+}
+
+/// The badge on the other end.
+struct Peer {
+    address: [u8; 6],
+    /// Last time anything came from it.
+    heard: Instant,
+    /// Last time anything went to it.
+    told: Instant,
+    /// Whether it has sent anything since pairing. Until then it may not
+    /// know it's paired, so keepalives are [`PAIR`]s.
+    confirmed: bool,
+    next_seq: u16,
+    /// Sequence number of the last [`DATA`] received, to drop repeats.
+    last_received: Option<u16>,
+    /// The [`DATA`] waiting for an ack.
+    unacked: Option<Unacked>,
+}
+
+struct Unacked {
+    seq: u16,
+    payload: Payload,
+    attempts: u8,
+    retry_at: Instant,
+}
+
+/// State of [`GameLink::run`].
+struct Session<'a> {
+    link: &'a GameLink,
+    me: BadgeId,
+    manager: EspNowManager<'static>,
+    peer: Option<Peer>,
+    next_hello: Instant,
+}
+
+impl Session<'_> {
+    /// When [`on_tick`](Self::on_tick) next has something to do.
+    fn deadline(&self) -> Instant {
+        match &self.peer {
+            None => self.next_hello,
+            Some(peer) => {
+                let quiet = (peer.heard + LINK_TIMEOUT).min(peer.told + KEEPALIVE);
+                peer.unacked
+                    .as_ref()
+                    .map_or(quiet, |unacked| quiet.min(unacked.retry_at))
+            }
+        }
+    }
+
+    async fn on_tick(&mut self, sender: &mut EspNowSender<'static>) {
+        let now = Instant::now();
+        let Some(peer) = &mut self.peer else {
+            if now >= self.next_hello {
+                let pairing = self.link.pairing.load(Ordering::Relaxed);
+                self.transmit(sender, BROADCAST_ADDRESS, HELLO, 0, &[u8::from(pairing)])
+                    .await;
+                self.next_hello = now + HELLO_INTERVAL;
+            }
+            return;
+        };
+        if now >= peer.heard + LINK_TIMEOUT {
+            defmt::info!("gamelink: peer timed out");
+            self.unlink();
+            return;
+        }
+        let address = peer.address;
+        if let Some(unacked) = &mut peer.unacked
+            && now >= unacked.retry_at
+        {
+            if unacked.attempts >= MAX_ATTEMPTS {
+                peer.unacked = None;
+                self.link.delivered.signal(Err(LinkError::Timeout));
+            } else {
+                unacked.attempts += 1;
+                unacked.retry_at = now + ACK_TIMEOUT;
+                let (seq, payload) = (unacked.seq, unacked.payload);
+                self.transmit(sender, address, DATA, seq, &payload).await;
+            }
+            return;
+        }
+        if now >= peer.told + KEEPALIVE {
+            if peer.confirmed {
+                self.transmit(sender, address, PING, 0, &[]).await;
+            } else {
+                self.transmit(sender, address, PAIR, 0, &address).await;
+            }
+        }
+    }
+
+    async fn on_request(&mut self, sender: &mut EspNowSender<'static>, request: Request) {
+        let Some(peer) = &mut self.peer else {
+            if let Request::Send(_) = request {
+                self.link.delivered.signal(Err(LinkError::NotLinked));
+            }
+            return;
+        };
+        let address = peer.address;
+        match request {
+            Request::Send(payload) => {
+                let seq = peer.next_seq;
+                peer.next_seq = seq.wrapping_add(1);
+                peer.unacked = Some(Unacked {
+                    seq,
+                    payload,
+                    attempts: 1,
+                    retry_at: Instant::now() + ACK_TIMEOUT,
+                });
+                self.transmit(sender, address, DATA, seq, &payload).await;
+            }
+            Request::Post(payload) => self.transmit(sender, address, POST, 0, &payload).await,
+            Request::Leave => {
+                self.transmit(sender, address, BYE, 0, &[]).await;
+                self.unlink();
+            }
+        }
+    }
+
+    async fn on_frame(
+        &mut self,
+        sender: &mut EspNowSender<'static>,
+        from: [u8; 6],
+        frame: &[u8],
+        rssi: i8,
+    ) {
+        let Some((kind, seq, payload)) = self.parse(frame) else {
+            return;
+        };
+        let pairing = self.link.pairing.load(Ordering::Relaxed);
+        let Some(peer) = self.peer.as_mut().filter(|peer| peer.address == from) else {
+            // From a badge in the lobby.
+            match kind {
+                HELLO => {
+                    let their_pairing = payload.first() == Some(&1);
+                    self.link
+                        .heard(BadgeId::from_mac(from), their_pairing, rssi);
+                    if self.peer.is_none() && pairing && their_pairing {
+                        self.transmit(sender, BROADCAST_ADDRESS, PAIR, 0, &from)
+                            .await;
+                    }
+                }
+                PAIR if self.peer.is_none() && pairing && payload == self.me.mac() => {
+                    self.link_to(from);
+                    if self.peer.is_some() {
+                        self.transmit(sender, from, PAIR, 0, &from).await;
+                    }
+                }
+                _ => {}
+            }
+            return;
+        };
+        peer.heard = Instant::now();
+        peer.confirmed = true;
+        match kind {
+            DATA => {
+                self.transmit(sender, from, ACK, seq, &[]).await;
+                let Some(peer) = &mut self.peer else { return };
+                if peer.last_received != Some(seq) {
+                    peer.last_received = Some(seq);
+                    if let Some(payload) = Payload::new(payload) {
+                        self.link.event(LinkEvent::Message(payload));
+                    }
+                }
+            }
+            ACK if peer
+                .unacked
+                .as_ref()
+                .is_some_and(|unacked| unacked.seq == seq) =>
+            {
+                peer.unacked = None;
+                self.link.delivered.signal(Ok(()));
+            }
+            POST => {
+                if let Some(payload) = Payload::new(payload) {
+                    self.link.event(LinkEvent::Message(payload));
+                }
+            }
+            BYE => {
+                defmt::info!("gamelink: peer left");
+                self.unlink();
+            }
+            // The peer is still waiting to hear back after pairing.
+            PAIR => self.transmit(sender, from, PING, 0, &[]).await,
+            _ => {}
+        }
+    }
+
+    /// Kind, sequence number and payload of a frame for this game.
+    fn parse<'f>(&self, frame: &'f [u8]) -> Option<(u8, u16, &'f [u8])> {
+        let (header, payload) = frame.split_first_chunk::<HEADER_LEN>()?;
+        let [m0, m1, kind, g0, g1, s0, s1] = *header;
+        (m0 == MAGIC[0] && m1 == MAGIC[1] && u16::from_le_bytes([g0, g1]) == self.link.game)
+            .then_some((kind, u16::from_le_bytes([s0, s1]), payload))
+    }
+
+    fn link_to(&mut self, address: [u8; 6]) {
+        let peer = PeerInfo {
+            interface: EspNowWifiInterface::Sta,
+            peer_address: address,
+            lmk: None,
+            channel: None,
+            encrypt: false,
+        };
+        if let Err(err) = self.manager.add_peer(peer) {
+            defmt::warn!("gamelink: adding peer failed: {}", err);
+            return;
+        }
+        let now = Instant::now();
+        self.peer = Some(Peer {
+            address,
+            heard: now,
+            told: now,
+            confirmed: false,
+            next_seq: 0,
+            last_received: None,
+            unacked: None,
+        });
+        let id = BadgeId::from_mac(address);
+        let host = self.me < id;
+        defmt::info!("gamelink: linked with {}", id);
+        self.link.event(LinkEvent::Linked { peer: id, host });
+    }
+
+    fn unlink(&mut self) {
+        let Some(peer) = self.peer.take() else {
+            return;
+        };
+        let _ = self.manager.remove_peer(&peer.address);
+        if peer.unacked.is_some() {
+            self.link.delivered.signal(Err(LinkError::NotLinked));
+        }
+        self.next_hello = Instant::now();
+        self.link.event(LinkEvent::Unlinked);
+    }
+
+    /// Send one frame; a lost one is the protocol's problem, not an error.
+    async fn transmit(
+        &mut self,
+        sender: &mut EspNowSender<'static>,
+        to: [u8; 6],
+        kind: u8,
+        seq: u16,
+        payload: &[u8],
+    ) {
+        let mut frame = [0; ESP_NOW_MAX_DATA_LEN];
+        let [g0, g1] = self.link.game.to_le_bytes();
+        let [s0, s1] = seq.to_le_bytes();
+        frame[..HEADER_LEN].copy_from_slice(&[MAGIC[0], MAGIC[1], kind, g0, g1, s0, s1]);
+        frame[HEADER_LEN..][..payload.len()].copy_from_slice(payload);
+        if let Err(err) = sender
+            .send_async(&to, &frame[..HEADER_LEN + payload.len()])
+            .await
+        {
+            defmt::debug!("gamelink: send failed: {}", err);
+        }
+        if let Some(peer) = &mut self.peer
+            && peer.address == to
+        {
+            peer.told = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Small built-in games as pure logic plus a reference renderer.
+//!
+//! Each game is a plain state machine: feed it input, call `tick` at a
+//! fixed rate, then draw it with its `render` function (or your own). No
+//! hardware access happens inside, so the logic is easy to read, port and
+//! reuse.
+
+pub mod high_scores;
+pub mod pong;
+pub mod snake;
+
+/// Tells games apart, so badges only meet others playing the same one and
+/// each keeps its own [high scores](high_scores). Pick any constant, such
+/// as two letters of the name.
+pub type GameId = u16;
+
+/// A D-pad direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// The direction pointing the other way.
+    pub const fn opposite(self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
+/// Xorshift32 generator, good enough for food placement and serve angles.
+#[derive(Clone, Copy, Debug)]
+pub struct Rng(u32);
+
+impl Rng {
+    /// A generator from `seed`; zero is replaced since xorshift would stick.
+    pub const fn new(seed: u32) -> Self {
+        Self(if seed == 0 { 0xDEAD_BEEF } else { seed })
+    }
+
+    pub const fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// A value in `0..max`.
+    pub const fn below(&mut self, max: u32) -> u32 {
+        self.next_u32() % max
+    }
+}
+
+/// Format `n` as decimal into `buf` without allocating.
+fn format_number(mut n: u16, buf: &mut [u8; 5]) -> &str {
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    core::str::from_utf8(&buf[i..]).unwrap_or("")
+}
@@ -0,0 +1,428 @@
+//! High-score tables kept in a [`Store`], one per game, so every game
+//! saves its scores the same way and keeps them across resets.
+//!
+//! ```rust,ignore
+//! const BREAKOUT: GameId = u16::from_le_bytes(*b"BO");
+//!
+//! // game over
+//! let mut scores = HighScores::new(&mut *settings.lock().await);
+//! if let Some(rank) = scores.submit(BREAKOUT, config::NAME, score)? {
+//!     info!("new high score, #{}", rank);
+//! }
+//! let top = scores.top(BREAKOUT, 5)?;
+//! high_scores::render("Breakout", Some(score), top.iter(), &mut display)?;
+//! ```
+//!
+//! A table keeps the best [`MAX_ENTRIES`] scores. Higher is better, and of
+//! two equal scores the earlier one ranks first. A score that doesn't make
+//! the table writes nothing, and each table is one small value, so flash
+//! wears with new high scores rather than with games played.
+//!
+//! ## Storage format
+//!
+//! Tables are under [`storage::HIGH_SCORES`] to `+` [`MAX_GAMES`] `- 1`,
+//! in the order games first submitted a score. Each is the game's ID as a
+//! little-endian `u16`, then every entry, best first: the score as a
+//! little-endian `u32`, then the name as a length byte and UTF-8.
+
+use core::fmt::{
+    self,
+    Write as _,
+};
+
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyle,
+        ascii::FONT_6X10,
+        iso_8859_1::FONT_10X20,
+    },
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        Point,
+        RgbColor,
+        WebColors,
+    },
+    text::{
+        Alignment,
+        Text,
+    },
+};
+
+use super::GameId;
+use crate::{
+    config,
+    display::DISPLAY_WIDTH,
+    storage::{
+        self,
+        Key,
+        Store,
+    },
+};
+
+/// Scores kept per game.
+pub const MAX_ENTRIES: usize = 10;
+/// Longest player name, in bytes.
+pub const MAX_NAME_LEN: usize = 24;
+/// Games that can keep a table.
+pub const MAX_GAMES: usize = 16;
+
+/// Longest encoded [`Table`].
+const TABLE_LEN: usize = 2 + MAX_ENTRIES * (4 + 1 + MAX_NAME_LEN);
+
+/// Errors from [`HighScores::submit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum HighScoreError<E> {
+    /// The underlying store failed.
+    Store(E),
+    /// [`MAX_GAMES`] other games already have a table.
+    Full,
+}
+
+impl<E> From<E> for HighScoreError<E> {
+    fn from(err: E) -> Self {
+        Self::Store(err)
+    }
+}
+
+/// One line of a high-score list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Entry<'b> {
+    /// 1 for the best.
+    pub rank: u16,
+    pub score: u32,
+    pub name: &'b str,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Record {
+    score: u32,
+    name: [u8; MAX_NAME_LEN],
+    name_len: u8,
+}
+
+impl Record {
+    const EMPTY: Self = Self {
+        score: 0,
+        name: [0; MAX_NAME_LEN],
+        name_len: 0,
+    };
+
+    fn name(&self) -> &str {
+        // Only ever filled from a `&str` or checked on load.
+        core::str::from_utf8(&self.name[..usize::from(self.name_len)]).unwrap_or_default()
+    }
+}
+
+/// One game's best scores, best first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Table {
+    game: GameId,
+    records: [Record; MAX_ENTRIES],
+    len: usize,
+}
+
+impl Table {
+    /// An empty table for `game`.
+    pub const fn new(game: GameId) -> Self {
+        Self {
+            game,
+            records: [Record::EMPTY; MAX_ENTRIES],
+            len: 0,
+        }
+    }
+
+    pub const fn game(&self) -> GameId {
+        self.game
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The best score, if there is one.
+    pub fn best(&self) -> Option<Entry<'_>> {
+        self.iter().next()
+    }
+
+    /// Every entry, best first.
+    pub fn iter(&self) -> impl Iterator<Item = Entry<'_>> + Clone {
+        self.records[..self.len]
+            .iter()
+            .zip(1..)
+            .map(|(record, rank)| Entry {
+                rank,
+                score: record.score,
+                name: record.name(),
+            })
+    }
+
+    /// The rank `score` would get, 1 for the best, or `None` if it doesn't
+    /// make the table.
+    pub fn rank(&self, score: u32) -> Option<u16> {
+        let index = self.records[..self.len]
+            .iter()
+            .position(|record| score > record.score)
+            .unwrap_or(self.len);
+        (index < MAX_ENTRIES).then_some(index as u16 + 1)
+    }
+
+    /// Add `score` by `name` if it makes the table, dropping the lowest if
+    /// the table is full, and return its rank. Names are cut at a character
+    /// boundary to fit [`MAX_NAME_LEN`].
+    pub fn insert(&mut self, name: &str, score: u32) -> Option<u16> {
+        let rank = self.rank(score)?;
+        let index = usize::from(rank - 1);
+        let mut record = Record {
+            score,
+            ..Record::EMPTY
+        };
+        record.name_len = fit(name, &mut record.name);
+        self.len = (self.len + 1).min(MAX_ENTRIES);
+        self.records[index..self.len].rotate_right(1);
+        self.records[index] = record;
+        Some(rank)
+    }
+
+    /// Keep only the best `n`.
+    pub fn truncate(&mut self, n: usize) {
+        self.len = self.len.min(n);
+    }
+
+    /// Write the table into `buf` as described in the
+    /// [module docs](self#storage-format), returning the bytes used.
+    fn encode<'b>(&self, buf: &'b mut [u8; TABLE_LEN]) -> &'b [u8] {
+        buf[..2].copy_from_slice(&self.game.to_le_bytes());
+        let mut len = 2;
+        for record in &self.records[..self.len] {
+            let name = &record.name[..usize::from(record.name_len)];
+            buf[len..len + 4].copy_from_slice(&record.score.to_le_bytes());
+            buf[len + 4] = record.name_len;
+            buf[len + 5..len + 5 + name.len()].copy_from_slice(name);
+            len += 5 + name.len();
+        }
+        &buf[..len]
+    }
+
+    /// Read a table written by [`encode`](Self::encode). Entries that
+    /// don't decode end it.
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (game, mut rest) = bytes.split_first_chunk::<2>()?;
+        let mut table = Self::new(u16::from_le_bytes(*game));
+        while table.len < MAX_ENTRIES {
+            let Some((score, after)) = rest.split_first_chunk::<4>() else {
+                break;
+            };
+            let Some((&name_len, after)) = after.split_first() else {
+                break;
+            };
+            let Some((name, after)) = after.split_at_checked(usize::from(name_len)) else {
+                break;
+            };
+            let Ok(name) = core::str::from_utf8(name) else {
+                break;
+            };
+            let record = &mut table.records[table.len];
+            record.score = u32::from_le_bytes(*score);
+            record.name_len = fit(name, &mut record.name);
+            table.len += 1;
+            rest = after;
+        }
+        Some(table)
+    }
+}
+
+/// High-score tables in a [`Store`]. See the [module docs](self).
+pub struct HighScores<'s, S: Store + ?Sized> {
+    store: &'s mut S,
+}
+
+impl<'s, S: Store + ?Sized> HighScores<'s, S> {
+    pub const fn new(store: &'s mut S) -> Self {
+        Self { store }
+    }
+
+    /// The best `n` scores of `game`, best first.
+    pub fn top(&mut self, game: GameId, n: usize) -> Result<Table, S::Error> {
+        let mut table = self
+            .find(game)?
+            .0
+            .map_or(Table::new(game), |(_, table)| table);
+        table.truncate(n);
+        Ok(table)
+    }
+
+    /// The rank `score` would get in `game`, or `None` if it doesn't make
+    /// the table; ask for a name only if it does.
+    pub fn rank(&mut self, game: GameId, score: u32) -> Result<Option<u16>, S::Error> {
+        Ok(self.top(game, MAX_ENTRIES)?.rank(score))
+    }
+
+    /// Record `score` in `game` by `name`, and return its rank if it made
+    /// the table. Nothing is written if it didn't.
+    pub fn submit(
+        &mut self,
+        game: GameId,
+        name: &str,
+        score: u32,
+    ) -> Result<Option<u16>, HighScoreError<S::Error>> {
+        let (found, free) = self.find(game)?;
+        let (slot, mut table) = match (found, free) {
+            (Some(found), _) => found,
+            (None, Some(slot)) => (slot, Table::new(game)),
+            (None, None) => return Err(HighScoreError::Full),
+        };
+        let Some(rank) = table.insert(name, score) else {
+            return Ok(None);
+        };
+        let mut buf = [0; TABLE_LEN];
+        self.store.write(key(slot), table.encode(&mut buf))?;
+        Ok(Some(rank))
+    }
+
+    /// Forget every score of `game`.
+    pub fn clear(&mut self, game: GameId) -> Result<(), S::Error> {
+        match self.find(game)?.0 {
+            Some((slot, _)) => self.store.remove(key(slot)),
+            None => Ok(()),
+        }
+    }
+
+    /// The slot and table of `game` if it has one, and the first free slot.
+    #[allow(clippy::type_complexity)]
+    fn find(&mut self, game: GameId) -> Result<(Option<(usize, Table)>, Option<usize>), S::Error> {
+        let mut free = None;
+        let mut buf = [0; TABLE_LEN];
+        for slot in 0..MAX_GAMES {
+            match self.store.read(key(slot), &mut buf)? {
+                Some(len) => match Table::decode(&buf[..len]) {
+                    Some(table) if table.game == game => return Ok((Some((slot, table)), free)),
+                    Some(_) => {}
+                    None => {
+                        free.get_or_insert(slot);
+                    }
+                },
+                None => {
+                    free.get_or_insert(slot);
+                }
+            }
+        }
+        Ok((None, free))
+    }
+}
+
+/// Draw a game-over screen: `game`, the player's `score` if given, and the
+/// `top` list, from a [`Table`] or a leaderboard server. Entries with this
+/// badge's name and the player's score are highlighted.
+pub fn render<'b, D>(
+    game: &str,
+    score: Option<u32>,
+    top: impl IntoIterator<Item = Entry<'b>>,
+    target: &mut D,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    target.clear(Rgb565::BLACK)?;
+    let center = i32::from(DISPLAY_WIDTH) / 2;
+    let title = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_GOLD);
+    let mut heading = Line::new();
+    let _ = match score {
+        Some(score) => write!(heading, "{game}: {score}"),
+        None => write!(heading, "{game} high scores"),
+    };
+    Text::with_alignment(
+        heading.as_str(),
+        Point::new(center, 22),
+        title,
+        Alignment::Center,
+    )
+    .draw(target)?;
+
+    let row = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let mine = MonoTextStyle::new(&FONT_10X20, Rgb565::CSS_DEEP_SKY_BLUE);
+    let mut rows = 0;
+    for (index, entry) in top.into_iter().take(6).enumerate() {
+        let y = 50 + 20 * index as i32;
+        let style = if entry.name == config::NAME && Some(entry.score) == score {
+            mine
+        } else {
+            row
+        };
+        let mut name = Line::new();
+        let _ = write!(name, "{}. {}", entry.rank, entry.name);
+        Text::new(name.as_str(), Point::new(12, y), style).draw(target)?;
+        let mut score = Line::new();
+        let _ = write!(score, "{}", entry.score);
+        Text::with_alignment(
+            score.as_str(),
+            Point::new(i32::from(DISPLAY_WIDTH) - 12, y),
+            style,
+            Alignment::Right,
+        )
+        .draw(target)?;
+        rows += 1;
+    }
+    if rows == 0 {
+        let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+        Text::with_alignment(
+            "No scores yet",
+            Point::new(center, 80),
+            small,
+            Alignment::Center,
+        )
+        .draw(target)?;
+    }
+    Ok(())
+}
+
+/// A line of text formatted on the stack; what doesn't fit is dropped.
+struct Line {
+    buf: [u8; 48],
+    len: usize,
+}
+
+impl Line {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 48],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for Line {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(self.buf.len() - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+fn key(slot: usize) -> Key {
+    storage::HIGH_SCORES + slot as Key
+}
+
+/// Copy as much of `text` as fits `buf`, cut at a character boundary, and
+/// return its length.
+fn fit(text: &str, buf: &mut [u8; MAX_NAME_LEN]) -> u8 {
+    let mut len = text.len().min(MAX_NAME_LEN);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    buf[..len].copy_from_slice(&text.as_bytes()[..len]);
+    len as u8
+}
@@ -0,0 +1,267 @@
+//! Pong: the player's paddle on the left against a CPU paddle on the right.
+//!
+//! Positions use 1/16 px fixed point so the ball can move at fractional
+//! speeds without floats.
+
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyle,
+        ascii::FONT_6X10,
+    },
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        Point,
+        Primitive,
+        RgbColor,
+        Size,
+    },
+    primitives::{
+        PrimitiveStyle,
+        Rectangle,
+    },
+    text::{
+        Alignment,
+        Text,
+    },
+};
+
+use super::{
+    Rng,
+    format_number,
+};
+
+/// Court width in pixels.
+pub const WIDTH: i32 = 320;
+/// Court height in pixels.
+pub const HEIGHT: i32 = 170;
+pub const PADDLE_W: i32 = 4;
+pub const PADDLE_H: i32 = 30;
+pub const BALL: i32 = 5;
+/// Points needed to win.
+pub const WIN_SCORE: u8 = 7;
+
+/// Fixed-point scale: positions are in 1/16 px.
+const FP: i32 = 16;
+const PADDLE_SPEED: i32 = 3 * FP;
+const CPU_SPEED: i32 = 2 * FP;
+const SERVE_SPEED: i32 = 2 * FP;
+const MAX_SPEED: i32 = 6 * FP;
+const PADDLE_MARGIN: i32 = 6;
+
+/// Player paddle movement for one tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub enum Move {
+    #[default]
+    Stay,
+    Up,
+    Down,
+}
+
+/// What happened during a [`Pong::tick`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Event {
+    None,
+    /// The ball bounced off a paddle.
+    Hit,
+    /// The ball bounced off the top or bottom wall.
+    Wall,
+    PlayerScored,
+    CpuScored,
+    /// Someone reached [`WIN_SCORE`]; `player_won` says who.
+    GameOver {
+        player_won: bool,
+    },
+}
+
+/// Pong game state.
+pub struct Pong {
+    player_y: i32,
+    cpu_y: i32,
+    ball: (i32, i32),
+    velocity: (i32, i32),
+    player_score: u8,
+    cpu_score: u8,
+    rng: Rng,
+}
+
+impl Pong {
+    /// A fresh game, with serve angles drawn from `seed`.
+    pub fn new(seed: u32) -> Self {
+        let mut game = Self {
+            player_y: (HEIGHT - PADDLE_H) / 2 * FP,
+            cpu_y: (HEIGHT - PADDLE_H) / 2 * FP,
+            ball: (0, 0),
+            velocity: (0, 0),
+            player_score: 0,
+            cpu_score: 0,
+            rng: Rng::new(seed),
+        };
+        game.serve(true);
+        game
+    }
+
+    /// Advance one step with the player's paddle moving as `input` says.
+    pub fn tick(&mut self, input: Move) -> Event {
+        if self.is_over() {
+            return Event::GameOver {
+                player_won: self.player_score > self.cpu_score,
+            };
+        }
+
+        let max_y = (HEIGHT - PADDLE_H) * FP;
+        self.player_y = match input {
+            Move::Stay => self.player_y,
+            Move::Up => self.player_y - PADDLE_SPEED,
+            Move::Down => self.player_y + PADDLE_SPEED,
+        }
+        .clamp(0, max_y);
+
+        // The CPU tracks the ball, but only at a limited speed.
+        let target = self.ball.1 + BALL * FP / 2 - PADDLE_H * FP / 2;
+        self.cpu_y =
+            (self.cpu_y + (target - self.cpu_y).clamp(-CPU_SPEED, CPU_SPEED)).clamp(0, max_y);
+
+        self.ball.0 += self.velocity.0;
+        self.ball.1 += self.velocity.1;
+
+        let mut event = Event::None;
+        let max_ball_y = (HEIGHT - BALL) * FP;
+        if self.ball.1 < 0 || self.ball.1 > max_ball_y {
+            self.ball.1 = self.ball.1.clamp(0, max_ball_y);
+            self.velocity.1 = -self.velocity.1;
+            event = Event::Wall;
+        }
+
+        if self.velocity.0 < 0 && self.hits(self.player_paddle()) {
+            self.bounce(self.player_y);
+            self.ball.0 = (PADDLE_MARGIN + PADDLE_W) * FP;
+            event = Event::Hit;
+        } else if self.velocity.0 > 0 && self.hits(self.cpu_paddle()) {
+            self.bounce(self.cpu_y);
+            self.ball.0 = (WIDTH - PADDLE_MARGIN - PADDLE_W - BALL) * FP;
+            event = Event::Hit;
+        }
+
+        if self.ball.0 < -BALL * FP {
+            self.cpu_score += 1;
+            self.serve(true);
+            event = Event::CpuScored;
+        } else if self.ball.0 > WIDTH * FP {
+            self.player_score += 1;
+            self.serve(false);
+            event = Event::PlayerScored;
+        }
+
+        if self.is_over() {
+            return Event::GameOver {
+                player_won: self.player_score > self.cpu_score,
+            };
+        }
+        event
+    }
+
+    pub const fn scores(&self) -> (u8, u8) {
+        (self.player_score, self.cpu_score)
+    }
+
+    pub const fn is_over(&self) -> bool {
+        self.player_score >= WIN_SCORE || self.cpu_score >= WIN_SCORE
+    }
+
+    pub fn player_paddle(&self) -> Rectangle {
+        paddle_rect(PADDLE_MARGIN, self.player_y)
+    }
+
+    pub fn cpu_paddle(&self) -> Rectangle {
+        paddle_rect(WIDTH - PADDLE_MARGIN - PADDLE_W, self.cpu_y)
+    }
+
+    pub fn ball(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(self.ball.0 / FP, self.ball.1 / FP),
+            Size::new(BALL as u32, BALL as u32),
+        )
+    }
+
+    fn hits(&self, paddle: Rectangle) -> bool {
+        self.ball().intersection(&paddle).size != Size::zero()
+    }
+
+    /// Reverse horizontally, speed up a little and angle the ball by where
+    /// it struck the paddle.
+    fn bounce(&mut self, paddle_y: i32) {
+        let speed = (self.velocity.0.abs() + FP / 4).min(MAX_SPEED);
+        self.velocity.0 = if self.velocity.0 < 0 { speed } else { -speed };
+        let offset = (self.ball.1 + BALL * FP / 2) - (paddle_y + PADDLE_H * FP / 2);
+        self.velocity.1 = offset * 2 * FP / (PADDLE_H * FP);
+    }
+
+    /// Put the ball in the middle, heading towards the player or the CPU.
+    fn serve(&mut self, towards_player: bool) {
+        self.ball = ((WIDTH - BALL) / 2 * FP, (HEIGHT - BALL) / 2 * FP);
+        let dy = self.rng.below(2 * FP as u32 + 1) as i32 - FP;
+        self.velocity = (
+            if towards_player {
+                -SERVE_SPEED
+            } else {
+                SERVE_SPEED
+            },
+            dy,
+        );
+    }
+}
+
+fn paddle_rect(x: i32, y: i32) -> Rectangle {
+    Rectangle::new(
+        Point::new(x, y / FP),
+        Size::new(PADDLE_W as u32, PADDLE_H as u32),
+    )
+}
+
+/// Draw the whole game: net, paddles, ball, scores and a result message.
+pub fn render<D>(game: &Pong, target: &mut D) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    target.clear(Rgb565::BLACK)?;
+    let white = PrimitiveStyle::with_fill(Rgb565::WHITE);
+    let grey = PrimitiveStyle::with_fill(Rgb565::new(8, 16, 8));
+
+    for y in (0..HEIGHT).step_by(10) {
+        Rectangle::new(Point::new(WIDTH / 2 - 1, y), Size::new(2, 5))
+            .into_styled(grey)
+            .draw(target)?;
+    }
+    game.player_paddle().into_styled(white).draw(target)?;
+    game.cpu_paddle().into_styled(white).draw(target)?;
+    game.ball().into_styled(white).draw(target)?;
+
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let (player, cpu) = game.scores();
+    let mut buf = [0; 5];
+    let text = format_number(u16::from(player), &mut buf);
+    Text::with_alignment(text, Point::new(WIDTH / 4, 12), style, Alignment::Center).draw(target)?;
+    let text = format_number(u16::from(cpu), &mut buf);
+    Text::with_alignment(
+        text,
+        Point::new(WIDTH * 3 / 4, 12),
+        style,
+        Alignment::Center,
+    )
+    .draw(target)?;
+
+    if game.is_over() {
+        let message = if player > cpu { "YOU WIN" } else { "YOU LOSE" };
+        let style = MonoTextStyle::new(&FONT_6X10, Rgb565::YELLOW);
+        Text::with_alignment(
+            message,
+            Point::new(WIDTH / 2, HEIGHT / 2),
+            style,
+            Alignment::Center,
+        )
+        .draw(target)?;
+    }
+    Ok(())
+}
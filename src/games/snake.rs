@@ -0,0 +1,227 @@
+//! Snake: steer with the D-pad, eat food, don't bite yourself or the walls.
+
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyle,
+        ascii::FONT_6X10,
+    },
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        Point,
+        Primitive,
+        RgbColor,
+        Size,
+    },
+    primitives::{
+        PrimitiveStyle,
+        Rectangle,
+    },
+    text::{
+        Alignment,
+        Text,
+    },
+};
+
+use super::{
+    Direction,
+    Rng,
+    format_number,
+};
+
+/// Size of one grid cell in pixels.
+pub const CELL: u32 = 10;
+/// Playfield width in cells (fills the 320 px screen).
+pub const GRID_W: u8 = 32;
+/// Playfield height in cells (fills 170 px).
+pub const GRID_H: u8 = 17;
+
+const MAX_LEN: usize = GRID_W as usize * GRID_H as usize;
+const START_LEN: usize = 4;
+
+/// A grid cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct Cell {
+    pub x: u8,
+    pub y: u8,
+}
+
+/// What happened during a [`Snake::tick`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Event {
+    /// The snake moved one cell.
+    Moved,
+    /// The snake ate food and grew.
+    Ate,
+    /// The snake hit a wall or itself; the game is over.
+    Died,
+}
+
+/// Snake game state.
+pub struct Snake {
+    /// Ring buffer of body cells; `head` indexes the head.
+    body: [Cell; MAX_LEN],
+    head: usize,
+    len: usize,
+    direction: Direction,
+    next_direction: Direction,
+    food: Cell,
+    score: u16,
+    over: bool,
+    rng: Rng,
+}
+
+impl Snake {
+    /// A fresh game, with food placed using `seed`.
+    pub fn new(seed: u32) -> Self {
+        let mut game = Self {
+            body: [Cell::default(); MAX_LEN],
+            head: START_LEN - 1,
+            len: START_LEN,
+            direction: Direction::Right,
+            next_direction: Direction::Right,
+            food: Cell::default(),
+            score: 0,
+            over: false,
+            rng: Rng::new(seed),
+        };
+        let (x, y) = (GRID_W / 2, GRID_H / 2);
+        for (i, cell) in game.body[..START_LEN].iter_mut().enumerate() {
+            *cell = Cell {
+                x: x + i as u8 - START_LEN as u8 + 1,
+                y,
+            };
+        }
+        game.spawn_food();
+        game
+    }
+
+    /// Turn on the next tick. Reversing straight into the neck is ignored.
+    pub fn steer(&mut self, direction: Direction) {
+        if direction != self.direction.opposite() {
+            self.next_direction = direction;
+        }
+    }
+
+    /// Advance one step.
+    pub fn tick(&mut self) -> Event {
+        if self.over {
+            return Event::Died;
+        }
+        self.direction = self.next_direction;
+
+        let head = self.body[self.head];
+        let next = match self.direction {
+            Direction::Up => head.y.checked_sub(1).map(|y| Cell { y, ..head }),
+            Direction::Down => (head.y + 1 < GRID_H).then(|| Cell {
+                y: head.y + 1,
+                ..head
+            }),
+            Direction::Left => head.x.checked_sub(1).map(|x| Cell { x, ..head }),
+            Direction::Right => (head.x + 1 < GRID_W).then(|| Cell {
+                x: head.x + 1,
+                ..head
+            }),
+        };
+        let ate = next == Some(self.food);
+        // The tail moves out of the way this tick unless we grow.
+        let hits_self = |cell| self.segments().skip(usize::from(!ate)).any(|c| c == cell);
+        let Some(next) = next.filter(|&cell| !hits_self(cell)) else {
+            self.over = true;
+            return Event::Died;
+        };
+
+        self.head = (self.head + 1) % MAX_LEN;
+        self.body[self.head] = next;
+        if ate {
+            self.len += 1;
+            self.score += 1;
+            if self.len == MAX_LEN {
+                // The board is full: nothing left to eat.
+                self.over = true;
+                return Event::Ate;
+            }
+            self.spawn_food();
+            Event::Ate
+        } else {
+            Event::Moved
+        }
+    }
+
+    /// Body cells from tail to head.
+    pub fn segments(&self) -> impl Iterator<Item = Cell> + '_ {
+        (0..self.len).map(move |i| self.body[(self.head + MAX_LEN + 1 - self.len + i) % MAX_LEN])
+    }
+
+    pub const fn head(&self) -> Cell {
+        self.body[self.head]
+    }
+
+    pub const fn food(&self) -> Cell {
+        self.food
+    }
+
+    pub const fn score(&self) -> u16 {
+        self.score
+    }
+
+    pub const fn is_over(&self) -> bool {
+        self.over
+    }
+
+    fn spawn_food(&mut self) {
+        loop {
+            let cell = Cell {
+                x: self.rng.below(u32::from(GRID_W)) as u8,
+                y: self.rng.below(u32::from(GRID_H)) as u8,
+            };
+            if !self.segments().any(|c| c == cell) {
+                self.food = cell;
+                return;
+            }
+        }
+    }
+}
+
+/// Screen rectangle covered by `cell`, leaving a 1 px gap.
+pub fn cell_rect(cell: Cell) -> Rectangle {
+    Rectangle::new(
+        Point::new(
+            i32::from(cell.x) * CELL as i32,
+            i32::from(cell.y) * CELL as i32,
+        ),
+        Size::new(CELL - 1, CELL - 1),
+    )
+}
+
+/// Draw the whole game: board, snake, food, score and a game-over message.
+pub fn render<D>(game: &Snake, target: &mut D) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    target.clear(Rgb565::BLACK)?;
+    let body = PrimitiveStyle::with_fill(Rgb565::GREEN);
+    for cell in game.segments() {
+        cell_rect(cell).into_styled(body).draw(target)?;
+    }
+    cell_rect(game.food())
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::RED))
+        .draw(target)?;
+
+    let mut buf = [0; 5];
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    Text::new(
+        format_number(game.score(), &mut buf),
+        Point::new(4, 10),
+        style,
+    )
+    .draw(target)?;
+
+    if game.is_over() {
+        let style = MonoTextStyle::new(&FONT_6X10, Rgb565::RED);
+        let center = target.bounding_box().center();
+        Text::with_alignment("GAME OVER", center, style, Alignment::Center).draw(target)?;
+    }
+    Ok(())
+}
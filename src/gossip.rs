@@ -0,0 +1,537 @@
+//! Tokens spread from badge to badge over ESP-NOW, behind the `gossip`
+//! feature: the machinery for conference-wide "virus" games.
+//!
+//! A badge [spreads](Gossip::spread) a small signed token; every badge that
+//! hears it checks the signature, hands it to the game once, and passes it
+//! on with one hop less to go. Badges also keep re-sending the tokens they
+//! carry for a while, so a badge that walks into the room later still
+//! catches them.
+//!
+//! ```rust,ignore
+//! const VIRUS: GameId = u16::from_le_bytes(*b"VX");
+//! static GOSSIP: Gossip = Gossip::new(VIRUS);
+//!
+//! #[embassy_executor::task]
+//! async fn gossip_task(esp_now: EspNow<'static>) {
+//!     GOSSIP.run(esp_now, gossip::generate_key()).await
+//! }
+//!
+//! // patient zero
+//! GOSSIP.spread(b"flu", 8)?;
+//!
+//! // everyone else
+//! let token = GOSSIP.next().await;
+//! info!("caught {=[u8]} from {}, {} hops out", token.data(), token.from, token.hops);
+//! ```
+//!
+//! Each token is signed with the key of the badge that first spread it, so
+//! it can't be altered on the way; [`Token::origin`] says whose it is. A game
+//! run by organisers can accept only their tokens with
+//! [`with_origin`](Gossip::with_origin), given `config::CONFERENCE_KEY`.
+//!
+//! Nothing floods the air: tokens go at most [`MAX_TTL`] hops, each badge
+//! sends at most [`SEND_RATE`] frames a second, in bursts of [`SEND_BURST`],
+//! and checks at most [`VERIFY_RATE`] signatures a second. Like the
+//! [game link](crate::gamelink), all badges must be on the same Wi-Fi
+//! channel.
+
+pub use ed25519_compact::{
+    KeyPair,
+    PublicKey,
+};
+use ed25519_compact::{
+    Seed,
+    Signature,
+};
+use embassy_futures::select::{
+    Either3,
+    select3,
+};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    channel::Channel,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use esp_radio::esp_now::{
+    BROADCAST_ADDRESS,
+    ESP_NOW_MAX_DATA_LEN,
+};
+
+use crate::BadgeId;
+pub use crate::gamelink::{
+    EspNow,
+    GameId,
+};
+
+/// Longest token data.
+pub const MAX_TOKEN: usize = 64;
+/// Most hops a token goes; longer TTLs are cut to this.
+pub const MAX_TTL: u8 = 16;
+/// Frames sent per second, counting both new tokens and passed-on ones.
+pub const SEND_RATE: u32 = 10;
+/// Frames that may go back to back after a quiet spell.
+pub const SEND_BURST: u32 = 4;
+/// Signatures checked per second; new tokens beyond that are ignored.
+pub const VERIFY_RATE: u32 = 20;
+/// How long a badge keeps re-sending a token it has heard.
+pub const CARRY_TIME: Duration = Duration::from_secs(600);
+/// Time between re-sends of each carried token.
+pub const RESEND_INTERVAL: Duration = Duration::from_secs(10);
+/// Tokens queued for the game before new ones are dropped.
+pub const GOSSIP_QUEUE: usize = 8;
+
+/// Tokens remembered as seen, so each reaches the game once.
+const SEEN: usize = 64;
+/// Tokens carried and re-sent at once; a new one replaces the oldest.
+const CARRY: usize = 8;
+/// Frames waiting to be sent; more are dropped.
+const OUTBOX: usize = 8;
+
+const MAGIC: [u8; 2] = *b"DG";
+/// Magic, game, TTL, hops, origin and serial.
+const HEADER_LEN: usize = 10 + PublicKey::BYTES;
+const FRAME_LEN: usize = HEADER_LEN + MAX_TOKEN + Signature::BYTES;
+const _: () = assert!(FRAME_LEN <= ESP_NOW_MAX_DATA_LEN);
+
+/// Why [`Gossip::spread`] didn't take a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum GossipError {
+    /// Longer than [`MAX_TOKEN`].
+    TooLong,
+    /// A TTL of 0 goes nowhere.
+    NoTtl,
+    /// Tokens are being spread faster than [`Gossip::run`] sends them.
+    Busy,
+}
+
+/// A token heard from another badge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    /// Key of the badge that first spread it, which signed it.
+    pub origin: PublicKey,
+    /// Tells the origin's tokens apart.
+    pub serial: u32,
+    /// Badges it went through to get here; 1 straight from the origin.
+    pub hops: u8,
+    /// Hops it may still go from here.
+    pub ttl: u8,
+    /// The badge it was heard from.
+    pub from: BadgeId,
+    /// Signal strength from that badge, in dBm.
+    pub rssi: i8,
+    len: u8,
+    data: [u8; MAX_TOKEN],
+}
+
+impl Token {
+    /// What the origin spread.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..usize::from(self.len)]
+    }
+}
+
+impl defmt::Format for Token {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "Token {{ origin: {=[u8]:02x}, serial: {}, hops: {}, from: {}, data: {=[u8]} }}",
+            self.origin[..4],
+            self.serial,
+            self.hops,
+            self.from,
+            self.data()
+        );
+    }
+}
+
+/// A new, random key to sign tokens with. Call it once the radio is on:
+/// only then is the hardware random number generator truly random.
+pub fn generate_key() -> KeyPair {
+    let mut seed = [0; Seed::BYTES];
+    esp_hal::rng::Rng::new().read(&mut seed);
+    KeyPair::from_seed(Seed::new(seed))
+}
+
+struct Spread {
+    len: u8,
+    data: [u8; MAX_TOKEN],
+    ttl: u8,
+}
+
+/// One game's tokens. See the [module docs](self).
+pub struct Gossip {
+    game: GameId,
+    origin: Option<[u8; PublicKey::BYTES]>,
+    tokens: Channel<CriticalSectionRawMutex, Token, GOSSIP_QUEUE>,
+    requests: Channel<CriticalSectionRawMutex, Spread, 2>,
+}
+
+impl Gossip {
+    pub const fn new(game: GameId) -> Self {
+        Self {
+            game,
+            origin: None,
+            tokens: Channel::new(),
+            requests: Channel::new(),
+        }
+    }
+
+    /// Only accept, and pass on, tokens signed with `key`, such as one the
+    /// organisers hold. The badge can still spread its own.
+    #[must_use]
+    pub const fn with_origin(mut self, key: [u8; PublicKey::BYTES]) -> Self {
+        self.origin = Some(key);
+        self
+    }
+
+    /// Run the gossip over `esp_now` forever, signing this badge's tokens
+    /// with `key`.
+    pub async fn run(&self, mut esp_now: EspNow<'static>, key: KeyPair) -> ! {
+        let mut state = State {
+            gossip: self,
+            key,
+            serial: esp_hal::rng::Rng::new().random(),
+            seen: [None; SEEN],
+            next_seen: 0,
+            carried: [None; CARRY],
+            outbox: [None; OUTBOX],
+            send: Limiter::new(SEND_RATE, SEND_BURST),
+            verify: Limiter::new(VERIFY_RATE, VERIFY_RATE),
+        };
+        loop {
+            let deadline = state.deadline();
+            match select3(
+                esp_now.receive_async(),
+                self.requests.receive(),
+                Timer::at(deadline),
+            )
+            .await
+            {
+                Either3::First(received) => {
+                    let rssi = received.info.rx_control.rssi.clamp(-128, 0) as i8;
+                    state.on_frame(received.info.src_address, received.data(), rssi);
+                }
+                Either3::Second(spread) => state.on_spread(&spread),
+                Either3::Third(()) => state.on_tick(&mut esp_now).await,
+            }
+        }
+    }
+
+    /// Start spreading `data`, to go `ttl` hops: 1 reaches only the badges
+    /// in range.
+    pub fn spread(&self, data: &[u8], ttl: u8) -> Result<(), GossipError> {
+        if ttl == 0 {
+            return Err(GossipError::NoTtl);
+        }
+        let mut spread = Spread {
+            len: data.len() as u8,
+            data: [0; MAX_TOKEN],
+            ttl: ttl.min(MAX_TTL),
+        };
+        spread
+            .data
+            .get_mut(..data.len())
+            .ok_or(GossipError::TooLong)?
+            .copy_from_slice(data);
+        self.requests
+            .try_send(spread)
+            .map_err(|_| GossipError::Busy)
+    }
+
+    /// Wait for the next token heard.
+    pub async fn next(&self) -> Token {
+        self.tokens.receive().await
+    }
+
+    /// The next token heard, if one is already waiting.
+    pub fn try_next(&self) -> Option<Token> {
+        self.tokens.try_receive().ok()
+    }
+}
+
+/// A frame as it goes on the air.
+#[derive(Clone, Copy)]
+struct Frame {
+    len: u8,
+    bytes: [u8; FRAME_LEN],
+}
+
+impl Frame {
+    fn new(bytes: &[u8]) -> Option<Self> {
+        let mut frame = Self {
+            len: bytes.len() as u8,
+            bytes: [0; FRAME_LEN],
+        };
+        frame.bytes.get_mut(..bytes.len())?.copy_from_slice(bytes);
+        Some(frame)
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+
+    /// Which token this is: the start of the origin's key and the serial.
+    fn id(&self) -> (u64, u32) {
+        let word = |at: usize| {
+            u32::from_le_bytes([
+                self.bytes[at],
+                self.bytes[at + 1],
+                self.bytes[at + 2],
+                self.bytes[at + 3],
+            ])
+        };
+        let origin = u64::from(word(6)) | u64::from(word(10)) << 32;
+        (origin, word(HEADER_LEN - 4))
+    }
+}
+
+/// A token this badge keeps re-sending.
+#[derive(Clone, Copy)]
+struct Carried {
+    frame: Frame,
+    until: Instant,
+    resend_at: Instant,
+}
+
+/// Allows `rate` events a second, in bursts of up to `burst`.
+struct Limiter {
+    interval: Duration,
+    burst: u32,
+    /// When the bucket is empty again.
+    empty_at: Instant,
+}
+
+impl Limiter {
+    fn new(rate: u32, burst: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / rate,
+            burst,
+            empty_at: Instant::now(),
+        }
+    }
+
+    /// When the next event is allowed.
+    fn ready_at(&self) -> Instant {
+        let window = self.interval * (self.burst - 1);
+        Instant::from_ticks(self.empty_at.as_ticks().saturating_sub(window.as_ticks()))
+    }
+
+    /// Count an event now, if one is allowed.
+    fn allow(&mut self) -> bool {
+        let now = Instant::now();
+        if self.ready_at() > now {
+            return false;
+        }
+        self.empty_at = self.empty_at.max(now) + self.interval;
+        true
+    }
+}
+
+/// State of [`Gossip::run`].
+struct State<'a> {
+    gossip: &'a Gossip,
+    key: KeyPair,
+    serial: u32,
+    seen: [Option<(u64, u32)>; SEEN],
+    next_seen: usize,
+    carried: [Option<Carried>; CARRY],
+    outbox: [Option<Frame>; OUTBOX],
+    send: Limiter,
+    verify: Limiter,
+}
+
+impl State<'_> {
+    /// When [`on_tick`](Self::on_tick) next has something to do.
+    fn deadline(&self) -> Instant {
+        let resend = self
+            .carried
+            .iter()
+            .flatten()
+            .map(|carried| carried.resend_at.min(carried.until))
+            .min()
+            .unwrap_or(Instant::MAX);
+        if self.outbox.iter().any(Option::is_some) {
+            resend.min(self.send.ready_at())
+        } else {
+            resend
+        }
+    }
+
+    async fn on_tick(&mut self, esp_now: &mut EspNow<'static>) {
+        let now = Instant::now();
+        for slot in &mut self.carried {
+            if slot.is_some_and(|carried| now >= carried.until) {
+                *slot = None;
+            }
+        }
+        for index in 0..CARRY {
+            if let Some(carried) = &mut self.carried[index]
+                && now >= carried.resend_at
+            {
+                carried.resend_at = now + RESEND_INTERVAL;
+                let frame = carried.frame;
+                self.queue(frame);
+            }
+        }
+        if self.outbox.iter().any(Option::is_some) && self.send.allow() {
+            let frame = self.outbox[0].take();
+            self.outbox.rotate_left(1);
+            if let Some(frame) = frame
+                && let Err(err) = esp_now
+                    .send_async(&BROADCAST_ADDRESS, frame.as_bytes())
+                    .await
+            {
+                defmt::debug!("gossip: send failed: {}", err);
+            }
+        }
+    }
+
+    fn on_spread(&mut self, spread: &Spread) {
+        let data = &spread.data[..usize::from(spread.len)];
+        let game = self.gossip.game.to_le_bytes();
+        let serial = self.serial.to_le_bytes();
+        self.serial = self.serial.wrapping_add(1);
+        let mut signed = [0; 6 + MAX_TOKEN];
+        let signature = self
+            .key
+            .sk
+            .sign(message(&mut signed, game, serial, data), None);
+
+        let mut bytes = [0; FRAME_LEN];
+        bytes[..2].copy_from_slice(&MAGIC);
+        bytes[2..4].copy_from_slice(&game);
+        bytes[4] = spread.ttl;
+        bytes[5] = 0;
+        bytes[6..HEADER_LEN - 4].copy_from_slice(&*self.key.pk);
+        bytes[HEADER_LEN - 4..HEADER_LEN].copy_from_slice(&serial);
+        bytes[HEADER_LEN..][..data.len()].copy_from_slice(data);
+        let len = HEADER_LEN + data.len();
+        bytes[len..][..Signature::BYTES].copy_from_slice(&*signature);
+        let Some(frame) = Frame::new(&bytes[..len + Signature::BYTES]) else {
+            return;
+        };
+        defmt::info!("gossip: spreading {=[u8]}", data);
+        self.remember(frame.id());
+        self.carry(frame);
+    }
+
+    fn on_frame(&mut self, from: [u8; 6], bytes: &[u8], rssi: i8) {
+        let Some(frame) = Frame::new(bytes) else {
+            return;
+        };
+        let Some((header, rest)) = bytes.split_first_chunk::<HEADER_LEN>() else {
+            return;
+        };
+        let Some((data, signature)) = rest.split_last_chunk::<{ Signature::BYTES }>() else {
+            return;
+        };
+        let game = [header[2], header[3]];
+        if header[..2] != MAGIC || u16::from_le_bytes(game) != self.gossip.game {
+            return;
+        }
+        let origin = &header[6..HEADER_LEN - 4];
+        if self.gossip.origin.is_some_and(|trusted| trusted != origin) {
+            return;
+        }
+        let id = frame.id();
+        if self.seen.contains(&Some(id)) {
+            return;
+        }
+        if !self.verify.allow() {
+            defmt::debug!("gossip: too many new tokens, ignoring one");
+            return;
+        }
+        let Some(&serial) = header[HEADER_LEN - 4..].first_chunk::<4>() else {
+            return;
+        };
+        let Ok(key) = PublicKey::from_slice(origin) else {
+            return;
+        };
+        let mut signed = [0; 6 + MAX_TOKEN];
+        if key
+            .verify(
+                message(&mut signed, game, serial, data),
+                &Signature::new(*signature),
+            )
+            .is_err()
+        {
+            defmt::debug!("gossip: bad signature from {}", BadgeId::from_mac(from));
+            return;
+        }
+        self.remember(id);
+
+        let ttl = header[4].min(MAX_TTL).saturating_sub(1);
+        let hops = header[5].saturating_add(1);
+        let mut token = Token {
+            origin: key,
+            serial: u32::from_le_bytes(serial),
+            hops,
+            ttl,
+            from: BadgeId::from_mac(from),
+            rssi,
+            len: data.len() as u8,
+            data: [0; MAX_TOKEN],
+        };
+        let Some(dest) = token.data.get_mut(..data.len()) else {
+            return;
+        };
+        dest.copy_from_slice(data);
+        if self.gossip.tokens.try_send(token).is_err() {
+            defmt::warn!("gossip: token queue full, dropped {}", token);
+        }
+
+        if ttl > 0 {
+            let mut frame = frame;
+            frame.bytes[4] = ttl;
+            frame.bytes[5] = hops;
+            self.carry(frame);
+        }
+    }
+
+    fn remember(&mut self, id: (u64, u32)) {
+        self.seen[self.next_seen] = Some(id);
+        self.next_seen = (self.next_seen + 1) % SEEN;
+    }
+
+    /// Send `frame` now and again every [`RESEND_INTERVAL`] for
+    /// [`CARRY_TIME`].
+    fn carry(&mut self, frame: Frame) {
+        let now = Instant::now();
+        let slot = self
+            .carried
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, slot)| slot.map(|carried| carried.until))
+            .map_or(0, |(index, _)| index);
+        self.carried[slot] = Some(Carried {
+            frame,
+            until: now + CARRY_TIME,
+            resend_at: now + RESEND_INTERVAL,
+        });
+        self.queue(frame);
+    }
+
+    fn queue(&mut self, frame: Frame) {
+        match self.outbox.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(frame),
+            None => defmt::debug!("gossip: outbox full, dropped a frame"),
+        }
+    }
+}
+
+/// What a token's signature covers, in `buf`: game, serial and data.
+fn message<'b>(
+    buf: &'b mut [u8; 6 + MAX_TOKEN],
+    game: [u8; 2],
+    serial: [u8; 4],
+    data: &[u8],
+) -> &'b [u8] {
+    buf[..2].copy_from_slice(&game);
+    buf[2..6].copy_from_slice(&serial);
+    buf[6..][..data.len()].copy_from_slice(data);
+    &buf[..6 + data.len()]
+}
@@ -0,0 +1,316 @@
+//! Small HTTP client over the Wi-Fi station, behind the `http` feature;
+//! `https` adds TLS.
+//!
+//! [`stack`] gets an address over DHCP on the network [`Wifi`] joined, and
+//! [`HttpClient`] makes one request at a time on it with buffers the caller
+//! provides, so nothing is allocated per request:
+//!
+//! ```rust,ignore
+//! #[embassy_executor::task]
+//! async fn net_task(mut runner: http::Runner<'static, WifiDevice<'static>>) {
+//!     runner.run().await
+//! }
+//!
+//! let (mut wifi, device) = Wifi::new(resources.wifi)?;
+//! wifi.connect("hacklab", "hunter22").await?;
+//! let resources = mk_static!(StackResources<SOCKETS>, StackResources::new());
+//! let (stack, runner) = http::stack(device, resources);
+//! spawner.must_spawn(net_task(runner));
+//! stack.wait_config_up().await;
+//!
+//! let mut client = HttpClient::new(stack);
+//! let mut buf = [0; 2048];
+//! let response = client.get("http://example.com/scores", &mut buf).await?;
+//! info!("{}: {}", response.status, response.text());
+//! ```
+//!
+//! `buf` holds the response headers and body, so size it for the biggest
+//! reply expected; [`HttpClient::download`] streams bodies of any size.
+//! Redirects are not followed.
+//!
+//! For `https://` URLs, give the client room for TLS records with
+//! [`HttpClient::with_tls`]. The server certificate is only checked against
+//! a CA given with [`HttpClient::with_ca`]; without one the connection is
+//! encrypted but the server could be anyone, which is fine for data that is
+//! checked some other way, such as signed firmware.
+
+use defmt::debug;
+use embassy_net::{
+    Config,
+    dns::DnsSocket,
+    tcp::client::{
+        TcpClient,
+        TcpClientState,
+    },
+};
+pub use embassy_net::{
+    Runner,
+    Stack,
+    StackResources,
+};
+use embedded_io_async::Read as _;
+pub use reqwless::headers::ContentType;
+use reqwless::{
+    client,
+    request::{
+        Method,
+        RequestBuilder as _,
+    },
+};
+
+#[cfg(doc)]
+use crate::wifi::Wifi;
+use crate::wifi::WifiDevice;
+
+/// Sockets [`stack`] needs room for: DHCP, DNS and one TCP connection.
+pub const SOCKETS: usize = 3;
+/// Size of each TCP buffer, one for sending and one for receiving.
+pub const TCP_BUF: usize = 1024;
+
+/// Errors from [`HttpClient`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum HttpError {
+    /// The host name didn't resolve.
+    Dns,
+    /// The connection couldn't be made or broke.
+    Network,
+    /// The URL isn't `http://` or `https://`, or is `https://` without
+    /// [`HttpClient::with_tls`].
+    InvalidUrl,
+    /// The TLS handshake or a TLS record failed.
+    Tls,
+    /// The response headers, or the body for [`HttpClient::get`] and
+    /// [`HttpClient::post`], don't fit the buffer.
+    BufferTooSmall,
+    /// The server's reply isn't valid HTTP.
+    BadResponse,
+    /// [`HttpClient::download`] got a status other than 2xx.
+    Status(u16),
+}
+
+impl From<reqwless::Error> for HttpError {
+    fn from(err: reqwless::Error) -> Self {
+        debug!("http: {}", err);
+        match err {
+            reqwless::Error::Dns => Self::Dns,
+            reqwless::Error::Network(_) | reqwless::Error::ConnectionAborted => Self::Network,
+            reqwless::Error::InvalidUrl(_) => Self::InvalidUrl,
+            #[cfg(feature = "https")]
+            reqwless::Error::Tls(_) => Self::Tls,
+            reqwless::Error::BufferTooSmall => Self::BufferTooSmall,
+            reqwless::Error::Codec
+            | reqwless::Error::AlreadySent
+            | reqwless::Error::IncorrectBodyWritten => Self::BadResponse,
+        }
+    }
+}
+
+/// A response read whole into the caller's buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Response<'b> {
+    pub status: u16,
+    pub body: &'b [u8],
+}
+
+impl<'b> Response<'b> {
+    /// Whether the status is 2xx.
+    pub const fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+
+    /// The body as text; empty if it isn't UTF-8.
+    pub fn text(&self) -> &'b str {
+        core::str::from_utf8(self.body).unwrap_or_default()
+    }
+}
+
+/// Buffers for TLS records, for [`HttpClient::with_tls`]. About 21 KiB, so
+/// keep it in a static:
+///
+/// ```rust,ignore
+/// let tls = mk_static!(TlsBuffers, TlsBuffers::new());
+/// let mut client = HttpClient::new(stack).with_tls(tls);
+/// ```
+#[cfg(feature = "https")]
+pub struct TlsBuffers {
+    read: [u8; 16_640],
+    write: [u8; 4096],
+}
+
+#[cfg(feature = "https")]
+impl TlsBuffers {
+    pub const fn new() -> Self {
+        Self {
+            read: [0; 16_640],
+            write: [0; 4096],
+        }
+    }
+}
+
+#[cfg(feature = "https")]
+impl Default for TlsBuffers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Start a network stack on the station `device` from [`Wifi::new`],
+/// configured over DHCP. Run the [`Runner`] in a task of its own, and wait
+/// for [`Stack::wait_config_up`] before making requests.
+pub fn stack(
+    device: WifiDevice<'static>,
+    resources: &'static mut StackResources<SOCKETS>,
+) -> (Stack<'static>, Runner<'static, WifiDevice<'static>>) {
+    let mut rng = crate::rng();
+    let seed = u64::from(rng.next_u32()) << 32 | u64::from(rng.next_u32());
+    embassy_net::new(device, Config::dhcpv4(Default::default()), resources, seed)
+}
+
+/// HTTP requests on a network [`Stack`], one at a time. See the
+/// [module docs](self).
+pub struct HttpClient<'a> {
+    stack: Stack<'a>,
+    #[cfg(feature = "https")]
+    tls: Option<&'a mut TlsBuffers>,
+    #[cfg(feature = "https")]
+    ca: Option<&'a [u8]>,
+}
+
+impl<'a> HttpClient<'a> {
+    pub fn new(stack: Stack<'a>) -> Self {
+        Self {
+            stack,
+            #[cfg(feature = "https")]
+            tls: None,
+            #[cfg(feature = "https")]
+            ca: None,
+        }
+    }
+
+    /// Allow `https://` URLs, with `buffers` for the TLS records.
+    #[cfg(feature = "https")]
+    #[must_use]
+    pub fn with_tls(mut self, buffers: &'a mut TlsBuffers) -> Self {
+        self.tls = Some(buffers);
+        self
+    }
+
+    /// Only accept `https://` servers whose certificate is signed by `ca`, a
+    /// DER-encoded ECDSA P-256 certificate. Without one, any server is
+    /// accepted.
+    #[cfg(feature = "https")]
+    #[must_use]
+    pub fn with_ca(mut self, ca: &'a [u8]) -> Self {
+        self.ca = Some(ca);
+        self
+    }
+
+    /// GET `url`, reading the reply into `buf`.
+    pub async fn get<'b>(
+        &mut self,
+        url: &str,
+        buf: &'b mut [u8],
+    ) -> Result<Response<'b>, HttpError> {
+        self.request(Method::GET, url, None, buf).await
+    }
+
+    /// POST `body` to `url`, reading the reply into `buf`.
+    pub async fn post<'b>(
+        &mut self,
+        url: &str,
+        content_type: ContentType,
+        body: &[u8],
+        buf: &'b mut [u8],
+    ) -> Result<Response<'b>, HttpError> {
+        self.request(Method::POST, url, Some((content_type, body)), buf)
+            .await
+    }
+
+    /// GET `url` and pass the body to `sink` a piece at a time, for bodies
+    /// too big for any buffer, such as firmware. `buf` is split between the
+    /// response headers and the pieces.
+    ///
+    /// Fails with [`HttpError::Status`] unless the status is 2xx, and stops
+    /// at the first error from `sink`.
+    pub async fn download<E: From<HttpError>>(
+        &mut self,
+        url: &str,
+        buf: &mut [u8],
+        mut sink: impl FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let (headers, piece) = buf.split_at_mut(buf.len() / 2);
+        let state = TcpClientState::new();
+        let tcp = TcpClient::new(self.stack, &state);
+        let dns = DnsSocket::new(self.stack);
+        let mut client = self.client(&tcp, &dns);
+        let mut request = client
+            .request(Method::GET, url)
+            .await
+            .map_err(HttpError::from)?;
+        let response = request.send(headers).await.map_err(HttpError::from)?;
+        if !response.status.is_successful() {
+            return Err(HttpError::Status(response.status.0).into());
+        }
+        let mut reader = response.body().reader();
+        loop {
+            let len = reader.read(piece).await.map_err(HttpError::from)?;
+            if len == 0 {
+                return Ok(());
+            }
+            sink(&piece[..len])?;
+        }
+    }
+
+    async fn request<'b>(
+        &mut self,
+        method: Method,
+        url: &str,
+        body: Option<(ContentType, &[u8])>,
+        buf: &'b mut [u8],
+    ) -> Result<Response<'b>, HttpError> {
+        let state = TcpClientState::new();
+        let tcp = TcpClient::new(self.stack, &state);
+        let dns = DnsSocket::new(self.stack);
+        let mut client = self.client(&tcp, &dns);
+        let request = client.request(method, url).await?;
+        let response = match body {
+            Some((content_type, body)) => {
+                let mut request = request.content_type(content_type).body(body);
+                let response = request.send(buf).await?;
+                (response.status.0, response.body().read_to_end().await?)
+            }
+            None => {
+                let mut request = request;
+                let response = request.send(buf).await?;
+                (response.status.0, response.body().read_to_end().await?)
+            }
+        };
+        Ok(Response {
+            status: response.0,
+            body: response.1,
+        })
+    }
+
+    fn client<'c, 'd>(
+        &'c mut self,
+        tcp: &'c TcpClient<'d, 1, TCP_BUF, TCP_BUF>,
+        dns: &'c DnsSocket<'d>,
+    ) -> client::HttpClient<'c, TcpClient<'d, 1, TCP_BUF, TCP_BUF>, DnsSocket<'d>> {
+        #[cfg(feature = "https")]
+        if let Some(tls) = self.tls.as_deref_mut() {
+            let verify = match self.ca {
+                Some(ca) => client::TlsVerify::Certificate {
+                    ca,
+                    cert: None,
+                    key: None,
+                },
+                None => client::TlsVerify::None,
+            };
+            let rng = esp_hal::rng::Rng::new();
+            let seed = u64::from(rng.random()) << 32 | u64::from(rng.random());
+            let config = client::TlsConfig::new(seed, &mut tls.read, &mut tls.write, verify);
+            return client::HttpClient::new_with_tls(tcp, dns, config);
+        }
+        client::HttpClient::new(tcp, dns)
+    }
+}
@@ -0,0 +1,99 @@
+//! Who this badge is: a stable ID from the factory MAC address in eFuse.
+//!
+//! The MAC never changes, so neither does the ID: it survives reflashing
+//! and is the default identity for radio protocols and leaderboards. For
+//! people, [`BadgeId`] also prints as a word pair and number such as
+//! `brave-otter-42`.
+
+use core::fmt;
+
+use esp_hal::efuse::Efuse;
+
+use crate::entropy::mix;
+
+const ADJECTIVES: [&str; 64] = [
+    "amber", "bold", "brave", "bright", "calm", "clever", "cosmic", "crimson", "curious", "dapper",
+    "daring", "eager", "electric", "fancy", "fearless", "fizzy", "fluffy", "frosty", "fuzzy",
+    "gentle", "giddy", "glowing", "golden", "grumpy", "happy", "hidden", "hungry", "icy", "jolly",
+    "lucky", "lunar", "magic", "mellow", "mighty", "misty", "neon", "nimble", "noisy", "odd",
+    "plucky", "polite", "proud", "quick", "quiet", "rapid", "rusty", "salty", "shiny", "silent",
+    "silver", "sleepy", "sly", "smooth", "solar", "sparkly", "speedy", "spicy", "steady", "stormy",
+    "sunny", "swift", "tiny", "witty", "zesty",
+];
+
+const ANIMALS: [&str; 64] = [
+    "alpaca", "badger", "bat", "bear", "beaver", "bison", "cat", "cobra", "crab", "crow", "deer",
+    "dingo", "dolphin", "duck", "eagle", "eel", "elk", "falcon", "ferret", "finch", "fox", "frog",
+    "gecko", "goat", "goose", "hare", "hawk", "hedgehog", "heron", "ibex", "koala", "lemur",
+    "lynx", "marten", "mole", "moose", "moth", "newt", "octopus", "otter", "owl", "panda",
+    "parrot", "puffin", "quokka", "rabbit", "raccoon", "raven", "reindeer", "seal", "shark",
+    "sloth", "squid", "stoat", "swan", "tapir", "tiger", "toad", "turtle", "viper", "walrus",
+    "wolf", "wombat", "yak",
+];
+
+/// A badge's identity: its 48-bit factory MAC address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BadgeId(u64);
+
+impl BadgeId {
+    pub const fn from_mac(mac: [u8; 6]) -> Self {
+        let [a, b, c, d, e, f] = mac;
+        Self(u64::from_be_bytes([0, 0, a, b, c, d, e, f]))
+    }
+
+    pub const fn mac(self) -> [u8; 6] {
+        let [_, _, a, b, c, d, e, f] = self.0.to_be_bytes();
+        [a, b, c, d, e, f]
+    }
+
+    /// The MAC as a number, only the low 48 bits set.
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// The ID hashed down to 32 bits, for protocols short on room. Badges
+    /// from one batch have near-identical MACs, so this mixes every bit
+    /// rather than cutting some off.
+    pub const fn as_u32(self) -> u32 {
+        let hash = mix(self.0);
+        (hash ^ (hash >> 32)) as u32
+    }
+
+    /// Adjective and animal naming this badge, e.g. `["brave", "otter"]`.
+    ///
+    /// Only 4096 pairs exist, so two badges can share one; [`Display`]
+    /// adds a number to make that unlikely among the people in one room.
+    ///
+    /// [`Display`]: fmt::Display
+    pub const fn words(self) -> [&'static str; 2] {
+        let hash = mix(self.0);
+        [
+            ADJECTIVES[(hash % 64) as usize],
+            ANIMALS[((hash >> 6) % 64) as usize],
+        ]
+    }
+
+    /// Number after the [`words`](Self::words), 0 to 99.
+    pub const fn number(self) -> u8 {
+        ((mix(self.0) >> 12) % 100) as u8
+    }
+}
+
+impl fmt::Display for BadgeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [adjective, animal] = self.words();
+        write!(f, "{adjective}-{animal}-{}", self.number())
+    }
+}
+
+impl defmt::Format for BadgeId {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        let [adjective, animal] = self.words();
+        defmt::write!(f, "{=str}-{=str}-{}", adjective, animal, self.number());
+    }
+}
+
+/// This badge's identity, read from eFuse.
+pub fn id() -> BadgeId {
+    BadgeId::from_mac(Efuse::read_base_mac_address())
+}
@@ -0,0 +1,320 @@
+//! High scores shared on a server, behind the `leaderboard` feature.
+//!
+//! A game signs its [`Score`] with the badge's key and [submits](Leaderboard::submit)
+//! it over the [HTTP client](crate::http); [`Leaderboard::top`] fetches the
+//! best ones back for the game-over screen, and [`render`] draws them:
+//!
+//! ```rust,ignore
+//! let key = leaderboard::load_key(&mut store)?;
+//! let board = Leaderboard::from_config().unwrap();
+//!
+//! // game over
+//! let score = Score::new("snake", game.score().into()).sign(&key);
+//! board.submit(&mut client, &score).await?;
+//! let mut buf = [0; 512];
+//! let top = board.top(&mut client, "snake", 5, &mut buf).await?;
+//! leaderboard::render("snake", Some(score.score().score), top, &mut display)?;
+//! ```
+//!
+//! Badges that aren't on Wi-Fi can hand their scores to one that is with
+//! [`relay`] (with the `esp-now` feature).
+//!
+//! ## Server
+//!
+//! The server lives at `leaderboard.url` in `badge.toml` and answers two
+//! requests:
+//!
+//! - `POST {url}/scores` with a [`SignedScore`] as the body: version 1, the
+//!   game and the player's name, each as a length byte and UTF-8, the score
+//!   as a little-endian `u32`, the badge's MAC, then the Ed25519 public key
+//!   and a signature over everything before the key. Any 2xx status means
+//!   the score was taken.
+//! - `GET {url}/top/{game}?n={n}`: the best `n` scores as plain text, best
+//!   first, one `{score} {name}` per line.
+//!
+//! The key stays the same for as long as the badge keeps its storage, so a
+//! server can tie scores to a badge the first time it sees the key and
+//! refuse the same badge ID signed with another one.
+
+#[cfg(feature = "esp-now")]
+pub mod relay;
+
+use alloc::format;
+
+use ed25519_compact::Signature;
+
+use crate::{
+    BadgeId,
+    config,
+    http::{
+        ContentType,
+        HttpClient,
+        HttpError,
+    },
+};
+pub use crate::{
+    games::high_scores::{
+        Entry,
+        render,
+    },
+    signed::{
+        KeyPair,
+        PublicKey,
+        load_key,
+    },
+};
+
+/// Longest game name, in bytes.
+pub const MAX_GAME_LEN: usize = 16;
+/// Longest player name, in bytes.
+pub const MAX_NAME_LEN: usize = 24;
+/// Longest encoded [`SignedScore`].
+pub const SIGNED_LEN: usize = RECORD_LEN + PublicKey::BYTES + Signature::BYTES;
+
+/// Version, game, name, score and MAC.
+const RECORD_LEN: usize = 1 + (1 + MAX_GAME_LEN) + (1 + MAX_NAME_LEN) + 4 + 6;
+const VERSION: u8 = 1;
+
+/// A score as a game reports it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Score {
+    game: [u8; MAX_GAME_LEN],
+    game_len: u8,
+    name: [u8; MAX_NAME_LEN],
+    name_len: u8,
+    pub score: u32,
+    /// The badge it was played on.
+    pub badge: BadgeId,
+}
+
+impl Score {
+    /// `score` in `game`, played on this badge by `badge.name` from
+    /// `badge.toml`. Names are cut at a character boundary to fit.
+    pub fn new(game: &str, score: u32) -> Self {
+        Self::with_name(game, config::NAME, score)
+    }
+
+    /// `score` in `game`, played on this badge by `name`.
+    pub fn with_name(game: &str, name: &str, score: u32) -> Self {
+        let mut this = Self {
+            game: [0; MAX_GAME_LEN],
+            game_len: 0,
+            name: [0; MAX_NAME_LEN],
+            name_len: 0,
+            score,
+            badge: crate::id(),
+        };
+        this.game_len = fit(game, &mut this.game);
+        this.name_len = fit(name, &mut this.name);
+        this
+    }
+
+    pub fn game(&self) -> &str {
+        core::str::from_utf8(&self.game[..usize::from(self.game_len)]).unwrap_or_default()
+    }
+
+    /// Who played.
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..usize::from(self.name_len)]).unwrap_or_default()
+    }
+
+    /// Sign the score with `key`, from [`load_key`].
+    pub fn sign(&self, key: &KeyPair) -> SignedScore {
+        let mut bytes = [0; SIGNED_LEN];
+        let mut len = 0;
+        let mut put = |part: &[u8]| {
+            bytes[len..][..part.len()].copy_from_slice(part);
+            len += part.len();
+        };
+        put(&[VERSION, self.game_len]);
+        put(self.game().as_bytes());
+        put(&[self.name_len]);
+        put(self.name().as_bytes());
+        put(&self.score.to_le_bytes());
+        put(&self.badge.mac());
+        let signature = key.sk.sign(&bytes[..len], None);
+        bytes[len..][..PublicKey::BYTES].copy_from_slice(&*key.pk);
+        bytes[len + PublicKey::BYTES..][..Signature::BYTES].copy_from_slice(&*signature);
+        SignedScore {
+            score: *self,
+            key: key.pk,
+            len: (len + PublicKey::BYTES + Signature::BYTES) as u8,
+            bytes,
+        }
+    }
+}
+
+/// A [`Score`] with the badge's signature, as sent to the server. The
+/// [module docs](self#server) have the format.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SignedScore {
+    score: Score,
+    key: PublicKey,
+    len: u8,
+    bytes: [u8; SIGNED_LEN],
+}
+
+impl SignedScore {
+    /// A signed score from its bytes, if they decode and the signature
+    /// checks out.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (score, rest) = decode(bytes)?;
+        let record = &bytes[..bytes.len() - rest.len()];
+        let (key, rest) = rest.split_first_chunk::<{ PublicKey::BYTES }>()?;
+        let (signature, []) = rest.split_first_chunk::<{ Signature::BYTES }>()? else {
+            return None;
+        };
+        let key = PublicKey::new(*key);
+        key.verify(record, &Signature::new(*signature)).ok()?;
+        let mut signed = Self {
+            score,
+            key,
+            len: bytes.len() as u8,
+            bytes: [0; SIGNED_LEN],
+        };
+        signed.bytes.get_mut(..bytes.len())?.copy_from_slice(bytes);
+        Some(signed)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+
+    /// The score that was signed.
+    pub const fn score(&self) -> &Score {
+        &self.score
+    }
+
+    /// The key it was signed with.
+    pub const fn key(&self) -> PublicKey {
+        self.key
+    }
+}
+
+impl core::fmt::Debug for SignedScore {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("SignedScore").field(&self.score).finish()
+    }
+}
+
+/// The best scores, best first, read from a [`Leaderboard::top`] reply.
+#[derive(Clone)]
+pub struct Top<'b> {
+    lines: core::str::Lines<'b>,
+    rank: u16,
+}
+
+impl<'b> Iterator for Top<'b> {
+    type Item = Entry<'b>;
+
+    fn next(&mut self) -> Option<Entry<'b>> {
+        loop {
+            let line = self.lines.next()?;
+            let (score, name) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+            // Skip whatever isn't a score, rather than stopping at it.
+            if let Ok(score) = score.parse() {
+                self.rank += 1;
+                return Some(Entry {
+                    rank: self.rank,
+                    score,
+                    name: name.trim(),
+                });
+            }
+        }
+    }
+}
+
+/// A leaderboard server. See the [module docs](self#server) for what it
+/// must answer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Leaderboard<'u> {
+    url: &'u str,
+}
+
+impl<'u> Leaderboard<'u> {
+    /// The server at `url`, without a trailing slash.
+    pub const fn new(url: &'u str) -> Self {
+        Self { url }
+    }
+
+    /// The server at `leaderboard.url` in `badge.toml`, if set.
+    pub const fn from_config() -> Option<Leaderboard<'static>> {
+        match config::LEADERBOARD_URL {
+            Some(url) => Some(Leaderboard::new(url)),
+            None => None,
+        }
+    }
+
+    /// Send `score` to the server.
+    pub async fn submit(
+        &self,
+        client: &mut HttpClient<'_>,
+        score: &SignedScore,
+    ) -> Result<(), HttpError> {
+        let url = format!("{}/scores", self.url);
+        let mut buf = [0; 512];
+        let response = client
+            .post(
+                &url,
+                ContentType::ApplicationOctetStream,
+                score.as_bytes(),
+                &mut buf,
+            )
+            .await?;
+        if !response.is_success() {
+            return Err(HttpError::Status(response.status));
+        }
+        Ok(())
+    }
+
+    /// Fetch the best `n` scores in `game`, reading the reply into `buf`.
+    pub async fn top<'b>(
+        &self,
+        client: &mut HttpClient<'_>,
+        game: &str,
+        n: u8,
+        buf: &'b mut [u8],
+    ) -> Result<Top<'b>, HttpError> {
+        let url = format!("{}/top/{game}?n={n}", self.url);
+        let response = client.get(&url, buf).await?;
+        if !response.is_success() {
+            return Err(HttpError::Status(response.status));
+        }
+        Ok(Top {
+            lines: response.text().lines(),
+            rank: 0,
+        })
+    }
+}
+
+/// Copy as much of `text` as fits `buf`, cut at a character boundary, and
+/// return its length.
+fn fit(text: &str, buf: &mut [u8]) -> u8 {
+    let mut len = text.len().min(buf.len());
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    buf[..len].copy_from_slice(&text.as_bytes()[..len]);
+    len as u8
+}
+
+/// The score in a signed record, and the key and signature after it.
+fn decode(bytes: &[u8]) -> Option<(Score, &[u8])> {
+    let Some((&VERSION, rest)) = bytes.split_first() else {
+        return None;
+    };
+    let (game, rest) = text(rest)?;
+    let (name, rest) = text(rest)?;
+    let (score, rest) = rest.split_first_chunk::<4>()?;
+    let (mac, rest) = rest.split_first_chunk::<6>()?;
+    let mut decoded = Score::with_name(game, name, u32::from_le_bytes(*score));
+    decoded.badge = BadgeId::from_mac(*mac);
+    Some((decoded, rest))
+}
+
+/// A length byte and that much UTF-8, and the bytes after it.
+fn text(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let (&len, rest) = bytes.split_first()?;
+    let (text, rest) = rest.split_at_checked(usize::from(len))?;
+    Some((core::str::from_utf8(text).ok()?, rest))
+}
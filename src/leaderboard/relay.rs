@@ -0,0 +1,150 @@
+//! Scores passed over ESP-NOW to a badge that can reach the server.
+//!
+//! A badge without Wi-Fi [offers](offer) its signed score to everyone in
+//! range until a relay acknowledges it. The relay, a badge on Wi-Fi, hears
+//! offers with a [`Collector`] and submits them for it:
+//!
+//! ```rust,ignore
+//! // player's badge
+//! if !relay::offer(&mut esp_now, &score).await {
+//!     warn!("no relay in range");
+//! }
+//!
+//! // relay
+//! let mut collector = Collector::new();
+//! loop {
+//!     let score = collector.next(&mut esp_now).await;
+//!     board.submit(&mut client, &score).await?;
+//! }
+//! ```
+//!
+//! Scores keep their signature, so the relay can't change them on the way.
+//! Like the [game link](crate::gamelink), both badges must be on the same
+//! Wi-Fi channel: the relay's access point's.
+
+use embassy_futures::select::{
+    Either,
+    select,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use esp_radio::esp_now::{
+    BROADCAST_ADDRESS,
+    ESP_NOW_MAX_DATA_LEN,
+    EspNow,
+};
+
+use super::{
+    SIGNED_LEN,
+    SignedScore,
+};
+
+/// Time between offers of a score.
+pub const OFFER_INTERVAL: Duration = Duration::from_millis(200);
+/// Offers of a score before giving up on finding a relay.
+pub const MAX_OFFERS: u8 = 25;
+
+/// First bytes of every frame, telling these from other ESP-NOW traffic.
+const MAGIC: [u8; 2] = *b"DS";
+/// A [`SignedScore`] follows.
+const SCORE: u8 = 0;
+/// The [`Tag`] of a score taken follows.
+const ACK: u8 = 1;
+const _: () = assert!(3 + SIGNED_LEN <= ESP_NOW_MAX_DATA_LEN);
+
+/// Scores a [`Collector`] remembers, so each is returned once.
+const SEEN: usize = 16;
+
+/// Tells scores apart: the end of the signature.
+type Tag = [u8; 8];
+
+/// Broadcast `score` until a relay acknowledges it, for up to
+/// [`MAX_OFFERS`] tries. Returns whether one did.
+pub async fn offer(esp_now: &mut EspNow<'_>, score: &SignedScore) -> bool {
+    let tag = tag(score);
+    for _ in 0..MAX_OFFERS {
+        send(esp_now, SCORE, score.as_bytes()).await;
+        let deadline = Instant::now() + OFFER_INTERVAL;
+        while let Either::First(received) =
+            select(esp_now.receive_async(), Timer::at(deadline)).await
+        {
+            if parse(received.data()) == Some((ACK, &tag[..])) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Hears the scores [offered](offer) nearby. See the [module docs](self).
+pub struct Collector {
+    seen: [Option<Tag>; SEEN],
+    next_seen: usize,
+}
+
+impl Collector {
+    pub const fn new() -> Self {
+        Self {
+            seen: [None; SEEN],
+            next_seen: 0,
+        }
+    }
+
+    /// Wait for a score not heard before, acknowledging every valid offer.
+    pub async fn next(&mut self, esp_now: &mut EspNow<'_>) -> SignedScore {
+        loop {
+            let received = esp_now.receive_async().await;
+            let Some((SCORE, bytes)) = parse(received.data()) else {
+                continue;
+            };
+            let Some(score) = SignedScore::from_bytes(bytes) else {
+                defmt::debug!("leaderboard: bad score offered");
+                continue;
+            };
+            let tag = tag(&score);
+            // Acked even when seen: the last ack may have been lost.
+            send(esp_now, ACK, &tag).await;
+            if self.seen.contains(&Some(tag)) {
+                continue;
+            }
+            self.seen[self.next_seen] = Some(tag);
+            self.next_seen = (self.next_seen + 1) % SEEN;
+            return score;
+        }
+    }
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn tag(score: &SignedScore) -> Tag {
+    let bytes = score.as_bytes();
+    let mut tag = [0; 8];
+    tag.copy_from_slice(&bytes[bytes.len() - 8..]);
+    tag
+}
+
+async fn send(esp_now: &mut EspNow<'_>, kind: u8, data: &[u8]) {
+    let mut frame = [0; 3 + SIGNED_LEN];
+    frame[..2].copy_from_slice(&MAGIC);
+    frame[2] = kind;
+    frame[3..][..data.len()].copy_from_slice(data);
+    if let Err(err) = esp_now
+        .send_async(&BROADCAST_ADDRESS, &frame[..3 + data.len()])
+        .await
+    {
+        defmt::debug!("leaderboard: send failed: {}", err);
+    }
+}
+
+/// Kind and data of a relay frame.
+fn parse(frame: &[u8]) -> Option<(u8, &[u8])> {
+    let ([m0, m1, kind], data) = frame.split_first_chunk::<3>()?;
+    ([*m0, *m1] == MAGIC).then_some((*kind, data))
+}
@@ -2,86 +2,504 @@
 //!
 //! The badge has 10 RGB LEDs arranged in a strip.
 
-use defmt::error;
+pub mod anim;
+pub mod audio;
+mod compositor;
+pub mod effects;
+pub mod layers;
+pub mod layout;
+mod meter;
+mod mirror;
+mod notify;
+mod profile;
+
+pub use compositor::{
+    LedCompositor,
+    LedLayer,
+};
+use defmt::{
+    error,
+    warn,
+};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    signal::Signal,
+};
 use embassy_time::{
     Duration,
+    Instant,
     Timer,
 };
 use esp_hal::{
     Blocking,
-    gpio::Level,
+    delay::Delay,
+    gpio::{
+        Level,
+        Output,
+        OutputConfig,
+    },
     rmt::{
         PulseCode,
+        Rmt,
         Tx,
+        TxChannelConfig,
+        TxChannelCreator as _,
+    },
+    time::Rate,
+};
+pub use meter::{
+    BarMeter,
+    VU_RAMP,
+};
+pub use mirror::LedMirror;
+pub use notify::{
+    LedNotification,
+    LedNotifier,
+};
+use palette::{
+    FromColor,
+    Hsv,
+    ShiftHue,
+    Srgb,
+};
+pub use profile::LedProfile;
+
+use crate::{
+    LedResources,
+    power::{
+        Allowance,
+        Subsystem,
     },
 };
-use palette::Srgb;
 
 /// Number of WS2812 LEDs on the badge.
 /// There are two led bars with 5 leds each. Left and right. Indexing is counter clockwise starting from the bottom right.
 /// Index 0 is bottom right. Index 4 is top right. Index 5 is top left. Index 9 is bottom left.
+/// See [`layout`] for mapping helpers.
 pub const LED_COUNT: usize = 10;
 
 /// Number of LEDs per bar (left or right).
 pub const BAR_COUNT: usize = 5;
 
+/// Default cap on the strip's estimated current draw, in milliamps.
+///
+/// Ten LEDs at full white draw around 600 mA, enough to brown out the badge
+/// on battery; this leaves headroom for the rest of the board.
+pub const DEFAULT_CURRENT_LIMIT_MA: u16 = 300;
+
+/// Default for [`Leds::set_white_point`]: what the badge's WS2812s need to
+/// show for white to look like the LCD's, rather than distinctly blue.
+pub const DEFAULT_WHITE_POINT: Srgb<u8> = Srgb::new(255, 215, 165);
+
+/// Default for [`Leds::set_max_frame_rate`], in frames per second.
+pub const DEFAULT_MAX_FRAME_RATE: u32 = 200;
+
+/// Line held low after a frame so the WS2812s latch it. Newer parts need
+/// 280 µs; earlier frames sent inside it run into the last one.
+const LATCH_TIME: Duration = Duration::from_micros(300);
+
+/// Time between steps of [`Leds::fade_to`].
+const FADE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Extra attempts at a frame whose transfer failed.
+const TRANSMIT_RETRIES: u8 = 2;
+
+/// Time the LEDs need after their supply comes on before they take data.
+const POWER_ON_DELAY: Duration = Duration::from_millis(1);
+
+/// Current drawn by one colour channel at full duty.
+const MA_PER_CHANNEL: u32 = 20;
+
+/// One frame of GRB bytes, ready to send.
+type Frame = [[u8; 3]; LED_COUNT];
+
+/// Output level for each channel value, following the CIE 1931 lightness
+/// curve so dim colours keep their hue instead of collapsing into whichever
+/// channel the LED renders brightest.
+const GAMMA: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let lightness = i as f32 * 100.0 / 255.0;
+        let luminance = if lightness <= 8.0 {
+            lightness / 903.3
+        } else {
+            let t = (lightness + 16.0) / 116.0;
+            t * t * t
+        };
+        table[i] = (luminance * 255.0 + 0.5) as u8;
+        i += 1;
+    }
+    table
+};
+
 /// WS2812 LED strip driver.
 ///
 /// Maintains an in-memory framebuffer that is flushed to hardware
-/// with [`update`](Leds::update).
+/// with [`update`](Leds::update). Colours are gamma corrected on the way
+/// out unless turned off with [`set_gamma_correction`](Leds::set_gamma_correction).
+///
+/// Frames are also limited on the way out: every channel is scaled by
+/// [`max_brightness`](Leds::max_brightness), and whole frames that would
+/// draw more than the [current limit](Leds::set_current_limit) are dimmed
+/// evenly to fit.
+///
+/// By default [`update`](Leds::update) waits for the transfer; see
+/// [`queued`](Leds::queued) to hand that to a background task. Firmware
+/// without an executor can set the LEDs up the same way and call
+/// [`update_blocking`](Leds::update_blocking) instead.
+///
+/// With a [power pin](Leds::with_power) the LED supply is switched off
+/// while every LED is dark, since each WS2812 draws about 1 mA even when
+/// showing black.
 pub struct Leds<'a> {
     channel: Option<esp_hal::rmt::Channel<'a, Blocking, Tx>>,
+    queue: Option<&'a LedQueue>,
+    mirror: Option<&'a LedMirror>,
+    power: Option<Output<'a>>,
+    power_state: PowerState,
+    auto_power_off: bool,
+    /// The supply just came on; wait [`POWER_ON_DELAY`] before sending.
+    settling: bool,
     framebuffer: [Srgb<u8>; LED_COUNT],
+    /// Framebuffer as of the last [`update`](Leds::update).
+    shown: [Srgb<u8>; LED_COUNT],
+    gamma: bool,
+    white_point: Srgb<u8>,
+    max_brightness: u8,
+    current_limit: Option<u16>,
+    max_frame_rate: u32,
+    /// Earliest time the next frame may be sent.
+    next_frame_at: Instant,
+    /// The [power budget](crate::power) followed, from [`Leds::follow`].
+    budget: Option<Subsystem<'static>>,
+}
+
+/// Errors from LED setup and updates.
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum LedError {
+    /// The RMT peripheral or channel rejected its configuration.
+    Config(esp_hal::rmt::Error),
+    /// A frame failed to send, even after retrying.
+    Transmit(esp_hal::rmt::Error),
+    /// An earlier failure lost the RMT channel.
+    ChannelLost,
+}
+
+impl<'a> TryFrom<LedResources<'a>> for Leds<'a> {
+    type Error = LedError;
+
+    fn try_from(res: LedResources<'a>) -> Result<Self, LedError> {
+        Self::try_new(res)
+    }
 }
 
 impl<'a> Leds<'a> {
+    /// Set up the RMT channel and LED supply. `resources.leds.try_into()`
+    /// does the same.
+    ///
+    /// To be able to [`reinit`](Self::reinit) later, pass
+    /// `resources.leds.reborrow()`.
+    pub fn try_new(res: LedResources<'a>) -> Result<Self, LedError> {
+        let power = Output::new(res.power, Level::High, OutputConfig::default());
+        let rmt = Rmt::new(res.rmt, Rate::from_mhz(40)).map_err(LedError::Config)?;
+        let tx_config = TxChannelConfig::default().with_clk_divider(1);
+        let channel = rmt
+            .channel0
+            .configure_tx(res.io, tx_config)
+            .map_err(LedError::Config)?;
+        Ok(Self::new(channel).with_power(power))
+    }
+
+    /// Set the RMT channel and supply up again from `res`, e.g. after
+    /// [`LedError::ChannelLost`].
+    ///
+    /// The framebuffer and settings are kept. A [`queued`](Self::queued)
+    /// strip goes back to direct updates, so stop its [`LedWriter`] first.
+    pub fn reinit(&mut self, res: LedResources<'a>) -> Result<(), LedError> {
+        let fresh = Self::try_new(res)?;
+        self.channel = fresh.channel;
+        self.queue = None;
+        self.power = fresh.power;
+        self.power_state = PowerState::On;
+        self.settling = true;
+        Ok(())
+    }
+
     pub const fn new(channel: esp_hal::rmt::Channel<'a, Blocking, Tx>) -> Self {
         Self {
             channel: Some(channel),
+            queue: None,
+            mirror: None,
+            power: None,
+            power_state: PowerState::On,
+            auto_power_off: true,
+            settling: false,
             framebuffer: [Srgb::new(0, 0, 0); LED_COUNT],
+            shown: [Srgb::new(0, 0, 0); LED_COUNT],
+            gamma: true,
+            white_point: DEFAULT_WHITE_POINT,
+            max_brightness: u8::MAX,
+            current_limit: Some(DEFAULT_CURRENT_LIMIT_MA),
+            max_frame_rate: DEFAULT_MAX_FRAME_RATE,
+            next_frame_at: Instant::MIN,
+            budget: None,
         }
     }
 
-    /// Flush the framebuffer to the physical LEDs.
+    /// Switch the LED supply with `power`, which should be driving it on.
+    #[must_use]
+    pub fn with_power(mut self, power: Output<'a>) -> Self {
+        self.power = Some(power);
+        self
+    }
+
+    /// Send every frame shown to `mirror` as well, to draw on the display.
+    #[must_use]
+    pub const fn with_mirror(mut self, mirror: &'a LedMirror) -> Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    /// Whether the LED supply is on. Always `true` without a power pin.
+    pub fn is_powered(&self) -> bool {
+        self.power_state == PowerState::On
+    }
+
+    /// Cut the LED supply until [`power_on`](Self::power_on). The
+    /// framebuffer is kept, and [`update`](Self::update) does nothing
+    /// meanwhile.
+    pub fn power_off(&mut self) {
+        if let Some(power) = &mut self.power {
+            power.set_low();
+            self.power_state = PowerState::Off;
+        }
+    }
+
+    /// Restore the LED supply. The LEDs come up dark; call
+    /// [`update`](Self::update) to show the framebuffer again.
+    pub fn power_on(&mut self) {
+        if let Some(power) = &mut self.power
+            && self.power_state != PowerState::On
+        {
+            power.set_high();
+            self.power_state = PowerState::On;
+            self.settling = true;
+        }
+    }
+
+    pub const fn auto_power_off(&self) -> bool {
+        self.auto_power_off
+    }
+
+    /// Whether [`update`](Self::update) switches the supply off for frames
+    /// with every LED dark, and back on for the next lit one. On by
+    /// default.
+    pub fn set_auto_power_off(&mut self, enabled: bool) {
+        self.auto_power_off = enabled;
+        if !enabled && self.power_state == PowerState::Idle {
+            self.power_on();
+        }
+    }
+
+    /// Whether [`update`](Self::update) applies gamma correction.
+    pub const fn gamma_correction(&self) -> bool {
+        self.gamma
+    }
+
+    /// Turn gamma correction off to send framebuffer values to the LEDs
+    /// unchanged, e.g. for colours that are already linear.
+    pub const fn set_gamma_correction(&mut self, enabled: bool) {
+        self.gamma = enabled;
+    }
+
+    pub const fn white_point(&self) -> Srgb<u8> {
+        self.white_point
+    }
+
+    /// Scale each channel so full white comes out as `white_point`,
+    /// calibrating the LEDs' colour temperature. White (255, 255, 255)
+    /// sends colours unchanged. See [`DEFAULT_WHITE_POINT`].
+    pub const fn set_white_point(&mut self, white_point: Srgb<u8>) {
+        self.white_point = white_point;
+    }
+
+    /// Upper limit for every channel, e.g. from
+    /// [`Allowance::led_brightness`].
+    pub const fn max_brightness(&self) -> u8 {
+        self.max_brightness
+    }
+
+    /// Scale all output so a full channel becomes `max`.
+    pub const fn set_max_brightness(&mut self, max: u8) {
+        self.max_brightness = max;
+    }
+
+    pub const fn current_limit(&self) -> Option<u16> {
+        self.current_limit
+    }
+
+    /// Cap the estimated draw of a frame at `limit_ma` milliamps, or lift
+    /// the cap with `None`. See [`DEFAULT_CURRENT_LIMIT_MA`].
+    pub const fn set_current_limit(&mut self, limit_ma: Option<u16>) {
+        self.current_limit = limit_ma;
+    }
+
+    pub const fn max_frame_rate(&self) -> u32 {
+        self.max_frame_rate
+    }
+
+    /// Send at most `fps` frames per second; [`update`](Self::update)
+    /// waits out the rest of the frame time. Rates too fast for the LEDs
+    /// to latch each frame are capped. See [`DEFAULT_MAX_FRAME_RATE`].
+    ///
+    /// A [`queued`](Self::queued) strip keeps the rate it had when it was
+    /// handed over.
+    pub const fn set_max_frame_rate(&mut self, fps: u32) {
+        self.max_frame_rate = fps;
+    }
+
+    /// Follow a [power budget](crate::power) allowance: cap every channel
+    /// at its [`led_brightness`](Allowance::led_brightness) and the frame
+    /// rate at one frame per [`frame_interval`](Allowance::frame_interval).
+    pub fn apply(&mut self, allowance: &Allowance) {
+        self.max_brightness = allowance.led_brightness;
+        let fps = Duration::from_secs(1).as_ticks() / allowance.frame_interval.as_ticks().max(1);
+        self.max_frame_rate = fps.max(1) as u32;
+    }
+
+    /// Keep following `budget`, from [`power::BUDGET`](crate::power::BUDGET):
+    /// its allowance is [applied](Self::apply) now and again whenever it
+    /// changes, before the next frame goes out.
+    ///
+    /// ```rust,ignore
+    /// if let Some(budget) = power::BUDGET.register() {
+    ///     leds.follow(budget);
+    /// }
+    /// ```
+    pub fn follow(&mut self, mut budget: Subsystem<'static>) {
+        self.apply(&budget.allowance());
+        self.budget = Some(budget);
+    }
+
+    /// Hand the RMT channel to a [`LedWriter`] so that
+    /// [`update`](Self::update) only queues the frame and returns at once.
+    ///
+    /// Run [`LedWriter::run`] in its own task. Returns `None` if the
+    /// channel was lost or already handed over.
+    ///
+    /// ```rust,ignore
+    /// static QUEUE: LedQueue = LedQueue::new();
+    ///
+    /// #[embassy_executor::task]
+    /// async fn led_writer_task(writer: LedWriter<'static>) {
+    ///     writer.run().await
+    /// }
+    ///
+    /// let writer = leds.queued(&QUEUE).unwrap();
+    /// spawner.must_spawn(led_writer_task(writer));
+    /// ```
+    pub fn queued(&mut self, queue: &'a LedQueue) -> Option<LedWriter<'a>> {
+        let channel = self.channel.take()?;
+        self.queue = Some(queue);
+        Some(LedWriter {
+            channel,
+            queue,
+            frame_interval: self.frame_interval(),
+        })
+    }
+
+    /// Flush the framebuffer to the physical LEDs, logging any failure.
+    ///
+    /// When [`queued`](Self::queued), this returns immediately and the
+    /// frame replaces any that the writer hasn't started sending yet.
     pub async fn update(&mut self) {
-        let Some(channel) = self.channel.take() else {
-            error!("RMT channel lost during previous transmission");
-            return;
+        if let Err(err) = self.try_update().await {
+            error!("LED update failed: {}", err);
+        }
+    }
+
+    /// [`update`](Self::update), returning failures instead of logging
+    /// them.
+    ///
+    /// A failed transfer is retried before giving up. After
+    /// [`LedError::ChannelLost`] every update fails until
+    /// [`reinit`](Self::reinit).
+    pub async fn try_update(&mut self) -> Result<(), LedError> {
+        let Some(frame) = self.prepare_frame() else {
+            return Ok(());
         };
+        if core::mem::take(&mut self.settling) {
+            Timer::after(POWER_ON_DELAY).await;
+        }
+        if let Some(queue) = self.queue {
+            queue.pending.signal(frame);
+            return Ok(());
+        }
+        let channel = self.channel.take().ok_or(LedError::ChannelLost)?;
+        Timer::at(self.next_frame_at).await;
+        let (channel, result) = Self::transmit(channel, &frame);
+        self.next_frame_at = Instant::now() + self.frame_interval();
+        self.channel = channel;
+        result
+    }
 
-        // 10 LEDs × 3 bytes × 8 bits + 1 end marker = 241 pulse codes
-        const PULSE_COUNT: usize = LED_COUNT * 24 + 1;
-        let mut pulses = [PulseCode::default(); PULSE_COUNT];
-        let mut idx = 0;
-        for color in &self.framebuffer {
-            let c: palette::rgb::Rgb<palette::encoding::Srgb, u8> = color.into_format::<u8>();
-            // WS2812 expects GRB byte order
-            for byte in [c.green, c.red, c.blue] {
-                let bp = Self::byte_to_pulses(byte);
-                pulses[idx..idx + 8].copy_from_slice(&bp);
-                idx += 8;
-            }
+    /// [`update`](Self::update) for firmware without an async executor,
+    /// e.g. factory tests: busy-waits instead of awaiting, and needs no
+    /// time driver.
+    ///
+    /// The frame-rate limit is kept by waiting after each frame rather
+    /// than before the next.
+    pub fn update_blocking(&mut self) {
+        if let Err(err) = self.try_update_blocking() {
+            error!("LED update failed: {}", err);
         }
-        pulses[idx] = PulseCode::end_marker();
+    }
 
-        let transaction = match channel.transmit(&pulses) {
-            Ok(t) => t,
-            Err(e) => {
-                error!("RMT transmit failed: {}", e);
-                return;
-            }
+    /// [`update_blocking`](Self::update_blocking), returning failures
+    /// instead of logging them.
+    pub fn try_update_blocking(&mut self) -> Result<(), LedError> {
+        let Some(frame) = self.prepare_frame() else {
+            return Ok(());
         };
+        let delay = Delay::new();
+        if core::mem::take(&mut self.settling) {
+            delay.delay_micros(POWER_ON_DELAY.as_micros() as u32);
+        }
+        if let Some(queue) = self.queue {
+            queue.pending.signal(frame);
+            return Ok(());
+        }
+        let channel = self.channel.take().ok_or(LedError::ChannelLost)?;
+        let (channel, result) = Self::transmit(channel, &frame);
+        delay.delay_micros(self.frame_interval().as_micros() as u32);
+        self.channel = channel;
+        result
+    }
 
-        self.channel = Some(match transaction.wait() {
-            Ok(ch) => ch,
-            Err((err, ch)) => {
-                error!("RMT transaction failed: {}", err);
-                ch
+    /// Fade from the colours last shown by [`update`](Self::update) to
+    /// `frame` over `duration`, updating the LEDs every 10 ms on the way.
+    ///
+    /// Steps follow the clock, so a busy executor makes the fade coarser
+    /// rather than longer. `frame` is left in the framebuffer.
+    pub async fn fade_to(&mut self, frame: &[Srgb<u8>; LED_COUNT], duration: Duration) {
+        let from = self.shown;
+        let start = Instant::now();
+        let total = duration.as_ticks() as f32;
+        loop {
+            let elapsed = start.elapsed();
+            if elapsed >= duration {
+                break;
             }
-        });
-
-        // WS2812 reset time
-        Timer::after(Duration::from_micros(50)).await;
+            let t = elapsed.as_ticks() as f32 / total;
+            for ((led, &a), &b) in self.framebuffer.iter_mut().zip(&from).zip(frame) {
+                *led = mix(a, b, t);
+            }
+            self.update().await;
+            Timer::after(FADE_INTERVAL).await;
+        }
+        self.framebuffer = *frame;
+        self.update().await;
     }
 
     /// Set a single LED by index.
@@ -94,6 +512,29 @@ impl<'a> Leds<'a> {
         self.framebuffer.fill(color);
     }
 
+    /// Set a single LED from `hue` in degrees and `saturation` and `value`
+    /// in 0.0–1.0.
+    pub fn set_hsv(&mut self, index: usize, hue: f32, saturation: f32, value: f32) {
+        self.set(index, hsv_to_srgb(hue, saturation, value));
+    }
+
+    /// Fill all LEDs with one HSV colour; see [`set_hsv`](Self::set_hsv).
+    pub fn fill_hsv(&mut self, hue: f32, saturation: f32, value: f32) {
+        self.fill(hsv_to_srgb(hue, saturation, value));
+    }
+
+    /// Shift the hue of every LED by `degrees`, keeping saturation and value.
+    ///
+    /// Each call round-trips through 8-bit RGB, so very dim colours drift
+    /// over many small rotations; regenerate them with
+    /// [`set_hsv`](Self::set_hsv) for long animations.
+    pub fn rotate_hue(&mut self, degrees: f32) {
+        for led in &mut self.framebuffer {
+            let hsv = Hsv::from_color(led.into_format::<f32>());
+            *led = Srgb::from_color(hsv.shift_hue(degrees)).into_format();
+        }
+    }
+
     /// Turn all LEDs off.
     pub fn clear(&mut self) {
         self.fill(Srgb::new(0, 0, 0));
@@ -113,8 +554,9 @@ impl<'a> Leds<'a> {
     /// [`set_left_bar`], so passing the same array to both produces
     /// a symmetrical display.
     pub fn set_right_bar(&mut self, colors: &[Srgb<u8>; BAR_COUNT]) {
-        // Right bar: hardware indices 0 (bottom) .. 4 (top) — already bottom-to-top.
-        self.framebuffer[..BAR_COUNT].copy_from_slice(colors);
+        for (&index, &color) in layout::RIGHT_BAR.iter().zip(colors) {
+            self.framebuffer[index] = color;
+        }
     }
 
     /// Set the left LED bar (5 LEDs).
@@ -123,10 +565,8 @@ impl<'a> Leds<'a> {
     /// index 4 is the top LED. Hardware indices 5–9 run top-to-bottom,
     /// so the slice is reversed internally.
     pub fn set_left_bar(&mut self, colors: &[Srgb<u8>; BAR_COUNT]) {
-        // Left bar: hardware index 5 = top, 9 = bottom.
-        // Reverse so that colors[0] = bottom, colors[4] = top.
-        for i in 0..BAR_COUNT {
-            self.framebuffer[BAR_COUNT + i] = colors[BAR_COUNT - 1 - i];
+        for (&index, &color) in layout::LEFT_BAR.iter().zip(colors) {
+            self.framebuffer[index] = color;
         }
     }
 
@@ -146,6 +586,119 @@ impl<'a> Leds<'a> {
 
     // ── Internal helpers ────────────────────────────────────────────────
 
+    /// Note the framebuffer as shown and switch the supply for it. Returns
+    /// the frame to send, or `None` if the LEDs are off.
+    fn prepare_frame(&mut self) -> Option<Frame> {
+        if let Some(allowance) = self.budget.as_mut().and_then(Subsystem::try_changed) {
+            self.apply(&allowance);
+        }
+        self.shown = self.framebuffer;
+        if let Some(mirror) = self.mirror {
+            mirror.publish(if self.power_state == PowerState::Off {
+                [Srgb::new(0, 0, 0); LED_COUNT]
+            } else {
+                self.shown
+            });
+        }
+        let frame = self.output_frame();
+        if self.power.is_some() {
+            let dark = frame.iter().flatten().all(|&v| v == 0);
+            match self.power_state {
+                PowerState::Off => return None,
+                PowerState::On if dark && self.auto_power_off => {
+                    self.power_off();
+                    self.power_state = PowerState::Idle;
+                    return None;
+                }
+                PowerState::Idle if dark => return None,
+                PowerState::Idle => self.power_on(),
+                PowerState::On => {}
+            }
+        }
+        Some(frame)
+    }
+
+    /// The framebuffer as GRB bytes, the order WS2812 expects, after
+    /// white balance, brightness, gamma and current limiting.
+    fn output_frame(&self) -> Frame {
+        let max = u32::from(self.max_brightness);
+        let white = self.white_point;
+        let scale = [white.green, white.red, white.blue].map(|w| u32::from(w) * max);
+        let mut frame = self.framebuffer.map(|c| {
+            let mut grb = [c.green, c.red, c.blue];
+            for (v, scale) in grb.iter_mut().zip(scale) {
+                let scaled = (u32::from(*v) * scale / (255 * 255)) as u8;
+                *v = if self.gamma {
+                    GAMMA[usize::from(scaled)]
+                } else {
+                    scaled
+                };
+            }
+            grb
+        });
+
+        if let Some(limit) = self.current_limit {
+            let total: u32 = frame.iter().flatten().map(|&v| u32::from(v)).sum();
+            let draw_ma = total * MA_PER_CHANNEL / 255;
+            let limit = u32::from(limit);
+            if draw_ma > limit {
+                for v in frame.iter_mut().flatten() {
+                    *v = (u32::from(*v) * limit / draw_ma) as u8;
+                }
+            }
+        }
+        frame
+    }
+
+    /// Shortest time from one frame to the next.
+    fn frame_interval(&self) -> Duration {
+        Duration::from_hz(u64::from(self.max_frame_rate.max(1))).max(LATCH_TIME)
+    }
+
+    /// Send one frame, retrying failed transfers. The channel comes back
+    /// unless it was lost.
+    ///
+    /// Callers keep [`LATCH_TIME`] between frames. The transfer blocks for
+    /// about 0.3 ms, too short to be worth yielding for.
+    fn transmit(
+        channel: esp_hal::rmt::Channel<'a, Blocking, Tx>,
+        frame: &Frame,
+    ) -> (
+        Option<esp_hal::rmt::Channel<'a, Blocking, Tx>>,
+        Result<(), LedError>,
+    ) {
+        // 10 LEDs × 3 bytes × 8 bits + 1 end marker = 241 pulse codes
+        const PULSE_COUNT: usize = LED_COUNT * 24 + 1;
+        let mut pulses = [PulseCode::default(); PULSE_COUNT];
+        let mut idx = 0;
+        for byte in frame.iter().flatten() {
+            let bp = Self::byte_to_pulses(*byte);
+            pulses[idx..idx + 8].copy_from_slice(&bp);
+            idx += 8;
+        }
+        pulses[idx] = PulseCode::end_marker();
+
+        let mut channel = channel;
+        let mut retries = TRANSMIT_RETRIES;
+        loop {
+            let transaction = match channel.transmit(&pulses) {
+                Ok(t) => t,
+                Err(e) => return (None, Err(LedError::Transmit(e))),
+            };
+
+            match transaction.wait() {
+                Ok(ch) => return (Some(ch), Ok(())),
+                Err((err, ch)) if retries == 0 => return (Some(ch), Err(LedError::Transmit(err))),
+                Err((err, ch)) => {
+                    warn!("RMT transaction failed, retrying: {}", err);
+                    retries -= 1;
+                    channel = ch;
+                    Delay::new().delay_micros(LATCH_TIME.as_micros() as u32);
+                }
+            }
+        }
+    }
+
     /// WS2812 bit timing at 40 MHz RMT clock.
     const fn bit_to_pulse(bit: bool) -> PulseCode {
         if bit {
@@ -165,3 +718,89 @@ impl<'a> Leds<'a> {
         pulses
     }
 }
+
+/// Blend `a` → `b` by `t` in 0.0–1.0.
+fn mix(a: Srgb<u8>, b: Srgb<u8>, t: f32) -> Srgb<u8> {
+    let mix = |x: u8, y: u8| {
+        let (x, y) = (f32::from(x), f32::from(y));
+        (x + (y - x) * t + 0.5) as u8
+    };
+    Srgb::new(
+        mix(a.red, b.red),
+        mix(a.green, b.green),
+        mix(a.blue, b.blue),
+    )
+}
+
+fn hsv_to_srgb(hue: f32, saturation: f32, value: f32) -> Srgb<u8> {
+    let hsv: Hsv = Hsv::new(hue, saturation, value);
+    Srgb::from_color(hsv).into_format()
+}
+
+/// State of the LED supply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PowerState {
+    On,
+    /// Switched off by [`Leds::power_off`].
+    Off,
+    /// Switched off by [`Leds::update`] for a dark frame.
+    Idle,
+}
+
+// ── Background output ──────────────────────────────────────────────────
+
+/// The frame waiting for a [`LedWriter`]. With the one being sent, that
+/// makes the double buffer.
+pub struct LedQueue {
+    pending: Signal<CriticalSectionRawMutex, Frame>,
+}
+
+impl Default for LedQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LedQueue {
+    pub const fn new() -> Self {
+        Self {
+            pending: Signal::new(),
+        }
+    }
+}
+
+/// Owns the RMT channel after [`Leds::queued`] and sends queued frames.
+pub struct LedWriter<'a> {
+    channel: esp_hal::rmt::Channel<'a, Blocking, Tx>,
+    queue: &'a LedQueue,
+    frame_interval: Duration,
+}
+
+impl LedWriter<'_> {
+    /// Send each queued frame as it arrives.
+    ///
+    /// Frames queued faster than the [maximum frame
+    /// rate](Leds::set_max_frame_rate) are dropped so only the newest is
+    /// shown. Returns if the channel is lost; [`Leds::reinit`] then takes
+    /// back direct updates.
+    pub async fn run(self) {
+        let Self {
+            mut channel,
+            queue,
+            frame_interval,
+        } = self;
+        loop {
+            let frame = queue.pending.wait().await;
+            let (ch, result) = Leds::transmit(channel, &frame);
+            // Whatever is queued meanwhile replaces the frame before it.
+            Timer::after(frame_interval).await;
+            if let Err(err) = result {
+                error!("LED update failed: {}", err);
+            }
+            match ch {
+                Some(ch) => channel = ch,
+                None => return,
+            }
+        }
+    }
+}
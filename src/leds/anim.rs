@@ -0,0 +1,401 @@
+//! Compact keyframe format for shareable LED idle animations.
+//!
+//! An animation is a small byte blob that can be stored in flash or passed
+//! between badges, and played back by a sandboxed interpreter. Untrusted
+//! blobs are fully validated by [`Animation::parse`], and playback clamps
+//! brightness and frame rate to [`Limits`] whatever the blob asks for.
+//!
+//! # Format (version 1)
+//!
+//! All integers are little-endian.
+//!
+//! | Bytes | Field |
+//! |---|---|
+//! | 2 | magic `"LA"` |
+//! | 1 | version, `1` |
+//! | 1 | flags: bit 0 = loop |
+//! | 1 | frame count, 1–[`MAX_FRAMES`] |
+//! | … | frames |
+//!
+//! Each frame is a `u16` duration in milliseconds, an opcode byte and its
+//! colours (3 bytes RGB each). Bit 7 of the opcode fades from the previous
+//! frame over the duration instead of cutting.
+//!
+//! | Opcode | Pattern | Colours |
+//! |---|---|---|
+//! | `0` | [`Pattern::Fill`] | 1 |
+//! | `1` | [`Pattern::Gradient`] | 2: bottom, top |
+//! | `2` | [`Pattern::Each`] | [`LED_COUNT`], hardware order |
+
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use palette::Srgb;
+
+use super::{
+    BAR_COUNT,
+    LED_COUNT,
+    Leds,
+    mix,
+};
+
+/// First two bytes of every animation blob.
+pub const MAGIC: [u8; 2] = *b"LA";
+/// Format version written by [`encode`] and accepted by [`Animation::parse`].
+pub const VERSION: u8 = 1;
+/// Largest blob accepted, so a share fits in a single radio packet batch.
+pub const MAX_BLOB_LEN: usize = 1024;
+/// Most frames one animation may contain.
+pub const MAX_FRAMES: usize = 64;
+
+const FLAG_LOOP: u8 = 1;
+const OP_FADE: u8 = 0x80;
+const OP_FILL: u8 = 0;
+const OP_GRADIENT: u8 = 1;
+const OP_EACH: u8 = 2;
+
+/// Why a blob was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum AnimError {
+    /// Longer than [`MAX_BLOB_LEN`].
+    TooLong,
+    /// Does not start with [`MAGIC`].
+    BadMagic,
+    /// Written for a format version this firmware can't play.
+    UnsupportedVersion(u8),
+    /// No frames, or more than [`MAX_FRAMES`].
+    BadFrameCount(u8),
+    /// A frame uses an opcode this version doesn't define.
+    UnknownPattern(u8),
+    /// The blob ends in the middle of a frame.
+    Truncated,
+    /// Bytes left over after the last frame.
+    TrailingData,
+    /// The output buffer passed to [`encode`] is too small.
+    BufferTooSmall,
+}
+
+/// What the LEDs show during one keyframe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Every LED the same colour.
+    Fill(Srgb<u8>),
+    /// Both bars blend from `bottom` to `top`.
+    Gradient { bottom: Srgb<u8>, top: Srgb<u8> },
+    /// One colour per LED, in hardware order.
+    Each([Srgb<u8>; LED_COUNT]),
+}
+
+impl Pattern {
+    /// The colour of every LED, in hardware order.
+    pub fn colors(&self) -> [Srgb<u8>; LED_COUNT] {
+        match *self {
+            Self::Fill(color) => [color; LED_COUNT],
+            Self::Gradient { bottom, top } => {
+                let mut out = [bottom; LED_COUNT];
+                for i in 0..BAR_COUNT {
+                    let color = mix(bottom, top, i as f32 / (BAR_COUNT - 1) as f32);
+                    // Right bar runs bottom-to-top, left bar top-to-bottom.
+                    out[i] = color;
+                    out[LED_COUNT - 1 - i] = color;
+                }
+                out
+            }
+            Self::Each(colors) => colors,
+        }
+    }
+
+    const fn opcode(&self) -> u8 {
+        match self {
+            Self::Fill(_) => OP_FILL,
+            Self::Gradient { .. } => OP_GRADIENT,
+            Self::Each(_) => OP_EACH,
+        }
+    }
+}
+
+/// How a keyframe moves from the previous colours to its own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub enum Easing {
+    /// Jump to the new colours at once and hold them.
+    Step,
+    #[default]
+    Linear,
+    /// Start slowly, finish quickly.
+    EaseIn,
+    /// Start quickly, settle slowly.
+    EaseOut,
+    /// Slow at both ends.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map progress `t` in 0.0–1.0 through the curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Step => 1.0,
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut if t < 0.5 => 2.0 * t * t,
+            Self::EaseInOut => 1.0 - 2.0 * (1.0 - t) * (1.0 - t),
+        }
+    }
+}
+
+/// One step of an animation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Keyframe {
+    /// How long the frame lasts, in milliseconds.
+    pub duration_ms: u16,
+    /// Fade from the previous frame rather than cutting to this one.
+    pub fade: bool,
+    pub pattern: Pattern,
+}
+
+impl Keyframe {
+    /// [`Easing::Linear`] for a fade, [`Easing::Step`] for a cut.
+    pub const fn easing(&self) -> Easing {
+        if self.fade {
+            Easing::Linear
+        } else {
+            Easing::Step
+        }
+    }
+}
+
+/// Caps applied during playback, whatever the blob asks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Every channel is scaled so 255 maps to this.
+    pub max_brightness: u8,
+    /// Shortest frame, and shortest interval between LED updates.
+    pub min_frame: Duration,
+}
+
+impl Default for Limits {
+    /// A quarter brightness and at most 50 updates per second.
+    fn default() -> Self {
+        Self {
+            max_brightness: 64,
+            min_frame: Duration::from_millis(20),
+        }
+    }
+}
+
+/// A validated animation blob.
+#[derive(Clone, Copy, Debug)]
+pub struct Animation<'a> {
+    frames: &'a [u8],
+    count: u8,
+    looping: bool,
+}
+
+impl<'a> Animation<'a> {
+    /// Validate `blob` and wrap it for playback.
+    pub fn parse(blob: &'a [u8]) -> Result<Self, AnimError> {
+        if blob.len() > MAX_BLOB_LEN {
+            return Err(AnimError::TooLong);
+        }
+        let [m0, m1, version, flags, count, ref frames @ ..] = *blob else {
+            return Err(AnimError::Truncated);
+        };
+        if [m0, m1] != MAGIC {
+            return Err(AnimError::BadMagic);
+        }
+        if version != VERSION {
+            return Err(AnimError::UnsupportedVersion(version));
+        }
+        if count == 0 || usize::from(count) > MAX_FRAMES {
+            return Err(AnimError::BadFrameCount(count));
+        }
+
+        let mut rest = frames;
+        for _ in 0..count {
+            let (_, tail) = decode_frame(rest)?;
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(AnimError::TrailingData);
+        }
+
+        Ok(Self {
+            frames,
+            count,
+            looping: flags & FLAG_LOOP != 0,
+        })
+    }
+
+    /// Whether playback restarts after the last frame.
+    pub const fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    pub const fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The decoded keyframes.
+    pub fn keyframes(&self) -> impl Iterator<Item = Keyframe> + Clone + 'a {
+        let mut rest = self.frames;
+        (0..self.count).filter_map(move |_| {
+            // Already validated by `parse`.
+            let (frame, tail) = decode_frame(rest).ok()?;
+            rest = tail;
+            Some(frame)
+        })
+    }
+
+    /// Play on `leds` within `limits`.
+    ///
+    /// Returns after the last frame, or never for a looping animation —
+    /// drop the future (e.g. with `select`) to stop it. Frames blend like
+    /// [`LedAnimation::sample`](super::layers::LedAnimation::sample), redrawn every
+    /// [`Limits::min_frame`].
+    pub async fn play(&self, leds: &mut Leds<'_>, limits: Limits) {
+        let keys = self.keyframes().map(move |frame| {
+            let duration = Duration::from_millis(frame.duration_ms.into()).max(limits.min_frame);
+            (frame.pattern, duration.as_ticks(), frame.easing())
+        });
+        let start = Instant::now();
+        while let Some(colors) = interpolate(keys.clone(), self.looping, start.elapsed().as_ticks())
+        {
+            for (i, color) in colors.into_iter().enumerate() {
+                leds.set(i, scale(color, limits.max_brightness));
+            }
+            leds.update().await;
+            Timer::after(limits.min_frame).await;
+        }
+    }
+}
+
+/// Serialise `frames` into `buf`, returning the blob length.
+pub fn encode(frames: &[Keyframe], looping: bool, buf: &mut [u8]) -> Result<usize, AnimError> {
+    let count = u8::try_from(frames.len())
+        .ok()
+        .filter(|&n| n > 0 && usize::from(n) <= MAX_FRAMES)
+        .ok_or(AnimError::BadFrameCount(frames.len().min(255) as u8))?;
+
+    let mut out = Writer { buf, len: 0 };
+    out.put(&MAGIC)?;
+    out.put(&[VERSION, if looping { FLAG_LOOP } else { 0 }, count])?;
+    for frame in frames {
+        out.put(&frame.duration_ms.to_le_bytes())?;
+        let fade = if frame.fade { OP_FADE } else { 0 };
+        out.put(&[frame.pattern.opcode() | fade])?;
+        match frame.pattern {
+            Pattern::Fill(color) => out.put_color(color)?,
+            Pattern::Gradient { bottom, top } => {
+                out.put_color(bottom)?;
+                out.put_color(top)?;
+            }
+            Pattern::Each(colors) => {
+                for color in colors {
+                    out.put_color(color)?;
+                }
+            }
+        }
+    }
+    if out.len > MAX_BLOB_LEN {
+        return Err(AnimError::TooLong);
+    }
+    Ok(out.len)
+}
+
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), AnimError> {
+        let end = self.len + bytes.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(AnimError::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    fn put_color(&mut self, color: Srgb<u8>) -> Result<(), AnimError> {
+        self.put(&[color.red, color.green, color.blue])
+    }
+}
+
+fn decode_frame(data: &[u8]) -> Result<(Keyframe, &[u8]), AnimError> {
+    let [d0, d1, op, ref rest @ ..] = *data else {
+        return Err(AnimError::Truncated);
+    };
+    let colors = match op & !OP_FADE {
+        OP_FILL => 1,
+        OP_GRADIENT => 2,
+        OP_EACH => LED_COUNT,
+        other => return Err(AnimError::UnknownPattern(other)),
+    };
+    let (payload, rest) = rest
+        .split_at_checked(colors * 3)
+        .ok_or(AnimError::Truncated)?;
+    let color = |i: usize| Srgb::new(payload[i * 3], payload[i * 3 + 1], payload[i * 3 + 2]);
+    let pattern = match op & !OP_FADE {
+        OP_FILL => Pattern::Fill(color(0)),
+        OP_GRADIENT => Pattern::Gradient {
+            bottom: color(0),
+            top: color(1),
+        },
+        _ => Pattern::Each(core::array::from_fn(color)),
+    };
+    Ok((
+        Keyframe {
+            duration_ms: u16::from_le_bytes([d0, d1]),
+            fade: op & OP_FADE != 0,
+            pattern,
+        },
+        rest,
+    ))
+}
+
+/// The colours `t` into `keys` of `(pattern, length, easing)`, or `None`
+/// past the end unless `looping`.
+///
+/// The first key blends from black, or from the last key when looping.
+pub(super) fn interpolate(
+    keys: impl Iterator<Item = (Pattern, u64, Easing)> + Clone,
+    looping: bool,
+    mut t: u64,
+) -> Option<[Srgb<u8>; LED_COUNT]> {
+    let total: u64 = keys.clone().map(|(_, len, _)| len).sum();
+    if t >= total {
+        if !looping || total == 0 {
+            return None;
+        }
+        t %= total;
+    }
+
+    let mut from = match keys.clone().last() {
+        Some((pattern, _, _)) if looping => pattern.colors(),
+        _ => [Srgb::new(0, 0, 0); LED_COUNT],
+    };
+    for (pattern, len, easing) in keys {
+        let to = pattern.colors();
+        if t < len {
+            let progress = easing.apply(t as f32 / len as f32);
+            return Some(core::array::from_fn(|i| mix(from[i], to[i], progress)));
+        }
+        t -= len;
+        from = to;
+    }
+    Some(from)
+}
+
+fn scale(color: Srgb<u8>, max: u8) -> Srgb<u8> {
+    let s = |c: u8| (u16::from(c) * u16::from(max) / 255) as u8;
+    Srgb::new(s(color.red), s(color.green), s(color.blue))
+}
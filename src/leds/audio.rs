@@ -0,0 +1,193 @@
+//! The microphone's level or spectrum on the LED bars.
+//!
+//! [`run`] waits for blocks of samples from a [`MicStream`] and draws each
+//! one, so the display keeps pace with the audio and other tasks run while
+//! the block is recorded:
+//!
+//! ```rust,ignore
+//! #[embassy_executor::task]
+//! async fn vu_task(
+//!     mut stream: MicStream<'static, 'static, BLOCK_SIZE>,
+//!     leds: &'static mut Leds<'static>,
+//! ) {
+//!     let meter = AudioMeter::new(AudioDisplay::Spectrum).with_smoothing(0.6, 0.15);
+//!     led_audio::run(&mut stream, leds, meter).await
+//! }
+//!
+//! let ring = mk_static!([u32; STREAM_RING_WORDS], [0; STREAM_RING_WORDS]);
+//! spawner.must_spawn(vu_task(mic.stream(ring)?, leds));
+//! ```
+
+use core::f32::consts::TAU;
+
+use embassy_time::{
+    Duration,
+    Timer,
+};
+use palette::Srgb;
+
+use super::{
+    BAR_COUNT,
+    BarMeter,
+    Leds,
+    VU_RAMP,
+};
+use crate::microphone::{
+    MicStream,
+    StreamError,
+    dsp::Goertzel,
+};
+
+/// Samples read and analysed per frame.
+pub const BLOCK_SIZE: usize = 512;
+
+/// Centre frequencies of the spectrum bands, bottom LED to top.
+pub const BAND_HZ: [u32; BAR_COUNT] = [125, 350, 900, 2200, 5000];
+
+/// Amplitude shown as a full bar unless changed with
+/// [`AudioMeter::with_max_amplitude`].
+pub const DEFAULT_MAX_AMPLITUDE: u16 = 4000;
+
+/// Pause after a DMA error before trying again.
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// What the bars show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub enum AudioDisplay {
+    /// Both bars fill from the bottom with the loudness.
+    #[default]
+    Level,
+    /// Each LED from the bottom shows the loudness of one of [`BAND_HZ`],
+    /// low to high, in the same place on both bars.
+    Spectrum,
+}
+
+/// Settings for [`run`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioMeter {
+    display: AudioDisplay,
+    max_amplitude: u16,
+    attack: f32,
+    release: f32,
+    ramp: [Srgb<u8>; BAR_COUNT],
+    peak_hold: Option<Duration>,
+}
+
+impl Default for AudioMeter {
+    fn default() -> Self {
+        Self::new(AudioDisplay::Level)
+    }
+}
+
+impl AudioMeter {
+    /// [`VU_RAMP`] colours, a quick rise and a slower fall, no peak hold.
+    pub const fn new(display: AudioDisplay) -> Self {
+        Self {
+            display,
+            max_amplitude: DEFAULT_MAX_AMPLITUDE,
+            attack: 0.7,
+            release: 0.2,
+            ramp: VU_RAMP,
+            peak_hold: None,
+        }
+    }
+
+    /// Show `max_amplitude` and above as full. Lower it for quiet rooms.
+    #[must_use]
+    pub const fn with_max_amplitude(mut self, max_amplitude: u16) -> Self {
+        self.max_amplitude = max_amplitude;
+        self
+    }
+
+    /// How far the display moves towards a louder (`attack`) or quieter
+    /// (`release`) reading each frame, from 0.0 (never) to 1.0 (at once).
+    #[must_use]
+    pub const fn with_smoothing(mut self, attack: f32, release: f32) -> Self {
+        self.attack = attack;
+        self.release = release;
+        self
+    }
+
+    /// Colour of each LED from the bottom when fully lit.
+    #[must_use]
+    pub const fn with_ramp(mut self, ramp: [Srgb<u8>; BAR_COUNT]) -> Self {
+        self.ramp = ramp;
+        self
+    }
+
+    /// Keep the highest recent level marked for `hold`. Only used by
+    /// [`AudioDisplay::Level`].
+    #[must_use]
+    pub const fn with_peak_hold(mut self, hold: Duration) -> Self {
+        self.peak_hold = Some(hold);
+        self
+    }
+}
+
+/// Show what `stream` hears on `leds` as set up by `meter`, forever.
+pub async fn run(
+    stream: &mut MicStream<'_, '_, BLOCK_SIZE>,
+    leds: &mut Leds<'_>,
+    meter: AudioMeter,
+) -> ! {
+    let full = f32::from(meter.max_amplitude.max(1));
+    let mut levels = [0.0f32; BAR_COUNT];
+
+    let mut bar = BarMeter::new(meter.ramp);
+    if let Some(hold) = meter.peak_hold {
+        bar = bar.with_peak_hold(hold);
+    }
+    let window: [f32; BLOCK_SIZE] =
+        core::array::from_fn(|i| 0.5 - 0.5 * libm::cosf(TAU * i as f32 / BLOCK_SIZE as f32));
+    let filters = BAND_HZ.map(|hz| Goertzel::new(hz, stream.sample_rate()));
+
+    loop {
+        let buf = match stream.next().await {
+            Ok(buf) => buf,
+            // Dropped samples: carry on with the next block.
+            Err(StreamError::Overrun) => continue,
+            Err(StreamError::Dma(_)) => {
+                Timer::after(RETRY_DELAY).await;
+                continue;
+            }
+        };
+        match meter.display {
+            AudioDisplay::Level => {
+                let peak = buf.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+                smooth(&mut levels[0], f32::from(peak) / full, &meter);
+                bar.show(leds, (levels[0] * full) as u32, full as u32);
+            }
+            AudioDisplay::Spectrum => {
+                // A full-scale sine comes out of the windowed filter at a
+                // quarter of the block length.
+                let scale = full * BLOCK_SIZE as f32 / 4.0;
+                for (level, filter) in levels.iter_mut().zip(&filters) {
+                    smooth(
+                        level,
+                        filter.magnitude_windowed(buf, &window) / scale,
+                        &meter,
+                    );
+                }
+                let colors = core::array::from_fn(|i| dim(meter.ramp[i], levels[i]));
+                leds.set_both_bars(&colors);
+            }
+        }
+        leds.update().await;
+    }
+}
+
+/// Move `level` towards `target`, clamped to 0.0–1.0.
+fn smooth(level: &mut f32, target: f32, meter: &AudioMeter) {
+    let target = target.clamp(0.0, 1.0);
+    let rate = if target > *level {
+        meter.attack
+    } else {
+        meter.release
+    };
+    *level += (target - *level) * rate.clamp(0.0, 1.0);
+}
+
+fn dim(color: Srgb<u8>, level: f32) -> Srgb<u8> {
+    let s = |c: u8| (f32::from(c) * level + 0.5) as u8;
+    Srgb::new(s(color.red), s(color.green), s(color.blue))
+}
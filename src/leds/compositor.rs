@@ -0,0 +1,177 @@
+//! Frames from several tasks, stacked by priority with per-LED alpha.
+
+use core::cell::RefCell;
+
+use embassy_sync::{
+    blocking_mutex::{
+        Mutex,
+        raw::CriticalSectionRawMutex,
+    },
+    signal::Signal,
+};
+use palette::Srgb;
+
+use super::{
+    LED_COUNT,
+    Leds,
+};
+
+const BLACK: Srgb<u8> = Srgb::new(0, 0, 0);
+const LAYER_COUNT: usize = 8;
+
+/// Colours and coverage for one [`LedCompositor`] layer.
+///
+/// Alpha 0 leaves an LED to the layers below, 255 covers it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LedLayer {
+    colors: [Srgb<u8>; LED_COUNT],
+    alpha: [u8; LED_COUNT],
+}
+
+impl Default for LedLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LedLayer {
+    /// A layer that covers nothing.
+    pub const fn new() -> Self {
+        Self {
+            colors: [BLACK; LED_COUNT],
+            alpha: [0; LED_COUNT],
+        }
+    }
+
+    /// `colors` over every LED at `alpha`.
+    pub const fn from_colors(colors: [Srgb<u8>; LED_COUNT], alpha: u8) -> Self {
+        Self {
+            colors,
+            alpha: [alpha; LED_COUNT],
+        }
+    }
+
+    /// Set LED `index`, with how much of the layers below it hides.
+    pub const fn set(&mut self, index: usize, color: Srgb<u8>, alpha: u8) {
+        self.colors[index] = color;
+        self.alpha[index] = alpha;
+    }
+
+    pub fn fill(&mut self, color: Srgb<u8>, alpha: u8) {
+        self.colors.fill(color);
+        self.alpha.fill(alpha);
+    }
+
+    /// Uncover LED `index`.
+    pub const fn clear(&mut self, index: usize) {
+        self.alpha[index] = 0;
+    }
+
+    /// Scale every LED's alpha by `opacity`, e.g. to fade the whole layer.
+    #[must_use]
+    pub fn with_opacity(mut self, opacity: u8) -> Self {
+        for alpha in &mut self.alpha {
+            *alpha = (u16::from(*alpha) * u16::from(opacity) / 255) as u8;
+        }
+        self
+    }
+
+    /// Draw this layer over `below`.
+    fn blend_over(&self, below: &mut [Srgb<u8>; LED_COUNT]) {
+        for ((out, color), &alpha) in below.iter_mut().zip(self.colors).zip(&self.alpha) {
+            let a = u16::from(alpha);
+            let blend = |under: u8, over: u8| {
+                ((u16::from(under) * (255 - a) + u16::from(over) * a + 127) / 255) as u8
+            };
+            *out = Srgb::new(
+                blend(out.red, color.red),
+                blend(out.green, color.green),
+                blend(out.blue, color.blue),
+            );
+        }
+    }
+}
+
+/// Combines [`LedLayer`]s from any number of tasks into one frame.
+///
+/// Each source owns a priority, from 0 at the bottom to
+/// [`LAYERS`](Self::LAYERS)` - 1` on top, and replaces its layer whenever
+/// it likes; one task owns the [`Leds`] and shows the result with
+/// [`run`](Self::run).
+///
+/// ```rust,ignore
+/// static LED_COMPOSITOR: LedCompositor = LedCompositor::new();
+///
+/// // Ambient colour at the bottom…
+/// LED_COMPOSITOR.set(0, LedLayer::from_colors([Srgb::new(0, 0, 10); LED_COUNT], 255));
+/// // …game effects over it…
+/// LED_COMPOSITOR.set(1, hit_flash.with_opacity(fade));
+/// // …and a battery warning on one LED above everything.
+/// let mut battery = LedLayer::new();
+/// battery.set(0, Srgb::new(30, 0, 0), 255);
+/// LED_COMPOSITOR.set(LedCompositor::LAYERS - 1, battery);
+/// ```
+pub struct LedCompositor {
+    layers: Mutex<CriticalSectionRawMutex, RefCell<[Option<LedLayer>; LAYER_COUNT]>>,
+    changed: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl Default for LedCompositor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LedCompositor {
+    /// Number of priorities.
+    pub const LAYERS: usize = LAYER_COUNT;
+
+    pub const fn new() -> Self {
+        Self {
+            layers: Mutex::new(RefCell::new([None; Self::LAYERS])),
+            changed: Signal::new(),
+        }
+    }
+
+    /// Replace the layer at `priority`.
+    ///
+    /// # Panics
+    ///
+    /// If `priority` is not below [`LAYERS`](Self::LAYERS).
+    pub fn set(&self, priority: usize, layer: LedLayer) {
+        self.layers
+            .lock(|layers| layers.borrow_mut()[priority] = Some(layer));
+        self.changed.signal(());
+    }
+
+    /// Remove the layer at `priority`, uncovering the ones below.
+    ///
+    /// # Panics
+    ///
+    /// If `priority` is not below [`LAYERS`](Self::LAYERS).
+    pub fn remove(&self, priority: usize) {
+        self.layers
+            .lock(|layers| layers.borrow_mut()[priority] = None);
+        self.changed.signal(());
+    }
+
+    /// The layers stacked over black, lowest priority first.
+    pub fn compose(&self) -> [Srgb<u8>; LED_COUNT] {
+        self.layers.lock(|layers| {
+            let mut frame = [BLACK; LED_COUNT];
+            for layer in layers.borrow().iter().flatten() {
+                layer.blend_over(&mut frame);
+            }
+            frame
+        })
+    }
+
+    /// Show the composed frame on `leds` each time a layer changes.
+    pub async fn run(&self, leds: &mut Leds<'_>) -> ! {
+        loop {
+            leds.fill_from_iter(self.compose());
+            leds.update().await;
+            self.changed.wait().await;
+        }
+    }
+}
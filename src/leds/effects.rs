@@ -0,0 +1,569 @@
+//! Ready-made, parameterised LED effects.
+//!
+//! Each effect is an [`Effect`] that draws one frame from the time since it
+//! started; [`run`] redraws it forever.
+//!
+//! ```rust,ignore
+//! #[embassy_executor::task]
+//! async fn led_task(leds: &'static mut Leds<'static>) {
+//!     led_effects::run(leds, &mut Rainbow::new(), DEFAULT_FRAME_INTERVAL).await
+//! }
+//! ```
+
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use palette::Srgb;
+
+use super::{
+    BAR_COUNT,
+    LED_COUNT,
+    Leds,
+    anim::Easing,
+};
+
+const BLACK: Srgb<u8> = Srgb::new(0, 0, 0);
+
+/// Something that draws onto the LEDs as time passes.
+pub trait Effect {
+    /// Draw the frame for `elapsed` since the effect started. Calls come
+    /// in increasing `elapsed` order.
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration);
+}
+
+/// Draw `effect` on `leds` every `interval`, forever.
+pub async fn run(leds: &mut Leds<'_>, effect: &mut impl Effect, interval: Duration) -> ! {
+    let start = Instant::now();
+    loop {
+        effect.draw(leds, start.elapsed());
+        leds.update().await;
+        Timer::after(interval).await;
+    }
+}
+
+// ── Rainbow cycle ───────────────────────────────────────────────────────
+
+/// Hues spread around the strip, rotating over time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rainbow {
+    /// Degrees per second.
+    pub speed: f32,
+    /// Hue difference between neighbouring LEDs, in degrees.
+    pub spread: f32,
+    pub saturation: f32,
+    pub value: f32,
+}
+
+impl Default for Rainbow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rainbow {
+    /// One turn every six seconds at a dim 8 % value.
+    pub const fn new() -> Self {
+        Self {
+            speed: 60.0,
+            spread: 25.0,
+            saturation: 1.0,
+            value: 0.08,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_speed(mut self, degrees_per_second: f32) -> Self {
+        self.speed = degrees_per_second;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_spread(mut self, degrees: f32) -> Self {
+        self.spread = degrees;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_value(mut self, value: f32) -> Self {
+        self.value = value;
+        self
+    }
+}
+
+impl Effect for Rainbow {
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration) {
+        let offset = seconds(elapsed) * self.speed;
+        for i in 0..LED_COUNT {
+            let hue = (offset + i as f32 * self.spread) % 360.0;
+            leds.set_hsv(i, hue, self.saturation, self.value);
+        }
+    }
+}
+
+// ── Breathing ───────────────────────────────────────────────────────────
+
+/// All LEDs fading smoothly in and out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breathing {
+    pub color: Srgb<u8>,
+    /// One full in-and-out cycle.
+    pub period: Duration,
+}
+
+impl Breathing {
+    pub const fn new(color: Srgb<u8>) -> Self {
+        Self {
+            color,
+            period: Duration::from_secs(4),
+        }
+    }
+
+    #[must_use]
+    pub const fn with_period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+}
+
+impl Effect for Breathing {
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration) {
+        let phase = cycle(elapsed, self.period);
+        let triangle = 1.0 - (2.0 * phase - 1.0).abs();
+        leds.fill(scale(self.color, Easing::EaseInOut.apply(triangle)));
+    }
+}
+
+// ── Heartbeat ───────────────────────────────────────────────────────────
+
+/// Double beat, lub-dub, then a pause.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// Colour at the peak of each beat.
+    pub color: Srgb<u8>,
+}
+
+/// Brightness out of 30 and how long it lasts, in milliseconds.
+const HEARTBEAT: [(u8, u64); 5] = [(30, 80), (10, 100), (30, 80), (5, 120), (0, 600)];
+
+impl Heartbeat {
+    pub const fn new(color: Srgb<u8>) -> Self {
+        Self { color }
+    }
+}
+
+impl Effect for Heartbeat {
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration) {
+        let period: u64 = HEARTBEAT.iter().map(|&(_, ms)| ms).sum();
+        let mut t = elapsed.as_millis() % period;
+        for (level, ms) in HEARTBEAT {
+            if t < ms {
+                leds.fill(scale(self.color, f32::from(level) / 30.0));
+                return;
+            }
+            t -= ms;
+        }
+    }
+}
+
+// ── Theater chase ───────────────────────────────────────────────────────
+
+/// Every `spacing`-th LED lit, stepping around the strip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TheaterChase {
+    pub color: Srgb<u8>,
+    pub background: Srgb<u8>,
+    pub spacing: usize,
+    /// Time between steps.
+    pub step: Duration,
+}
+
+impl TheaterChase {
+    pub const fn new(color: Srgb<u8>) -> Self {
+        Self {
+            color,
+            background: BLACK,
+            spacing: 3,
+            step: Duration::from_millis(100),
+        }
+    }
+
+    #[must_use]
+    pub const fn with_background(mut self, background: Srgb<u8>) -> Self {
+        self.background = background;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_spacing(mut self, spacing: usize) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_step(mut self, step: Duration) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+impl Effect for TheaterChase {
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration) {
+        let spacing = self.spacing.max(1);
+        let offset = (elapsed.as_ticks() / self.step.as_ticks().max(1)) as usize % spacing;
+        for i in 0..LED_COUNT {
+            let lit = i % spacing == offset;
+            leds.set(i, if lit { self.color } else { self.background });
+        }
+    }
+}
+
+// ── Sparkle ─────────────────────────────────────────────────────────────
+
+/// Random LEDs flashing and fading out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sparkle {
+    pub color: Srgb<u8>,
+    /// Average new sparkles per second.
+    pub rate: f32,
+    /// How long a sparkle takes to fade.
+    pub fade: Duration,
+    levels: [f32; LED_COUNT],
+    last: Duration,
+    rng: Rng,
+}
+
+impl Sparkle {
+    /// Sparkles in `color`, placed differently on every badge and boot.
+    pub fn new(color: Srgb<u8>) -> Self {
+        Self {
+            color,
+            rate: 8.0,
+            fade: Duration::from_millis(400),
+            levels: [0.0; LED_COUNT],
+            last: Duration::from_ticks(0),
+            rng: Rng::new(crate::entropy::rng().next_u32()),
+        }
+    }
+
+    #[must_use]
+    pub const fn with_rate(mut self, per_second: f32) -> Self {
+        self.rate = per_second;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_fade(mut self, fade: Duration) -> Self {
+        self.fade = fade;
+        self
+    }
+
+    /// Use a fixed `seed` instead, so the pattern repeats exactly.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u32) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+}
+
+impl Effect for Sparkle {
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration) {
+        let dt = seconds(elapsed - self.last.min(elapsed));
+        self.last = elapsed;
+
+        let decay = dt / seconds(self.fade).max(f32::EPSILON);
+        for level in &mut self.levels {
+            *level = (*level - decay).max(0.0);
+        }
+        if self.rng.chance(self.rate * dt) {
+            self.levels[self.rng.below(LED_COUNT)] = 1.0;
+        }
+        for (i, level) in self.levels.iter().enumerate() {
+            leds.set(i, scale(self.color, *level));
+        }
+    }
+}
+
+// ── Fire ────────────────────────────────────────────────────────────────
+
+/// Flickering flames rising up both bars.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fire {
+    /// Brightness of the hottest flame, 0.0–1.0.
+    pub intensity: f32,
+    /// How fast flames cool as they rise; higher gives shorter flames.
+    pub cooling: f32,
+    /// Heat per bar position, bottom to top.
+    heat: [[f32; BAR_COUNT]; 2],
+    last: Duration,
+    rng: Rng,
+}
+
+impl Default for Fire {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fire {
+    /// Flames that flicker differently on every badge and boot.
+    pub fn new() -> Self {
+        Self {
+            intensity: 0.3,
+            cooling: 0.35,
+            heat: [[0.0; BAR_COUNT]; 2],
+            last: Duration::from_ticks(0),
+            rng: Rng::new(crate::entropy::rng().next_u32()),
+        }
+    }
+
+    #[must_use]
+    pub const fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_cooling(mut self, cooling: f32) -> Self {
+        self.cooling = cooling;
+        self
+    }
+
+    /// Use a fixed `seed` instead, so the pattern repeats exactly.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u32) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+}
+
+impl Effect for Fire {
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration) {
+        // Step at a fixed 30 ms so the look doesn't depend on the redraw rate.
+        const STEP: Duration = Duration::from_millis(30);
+        while self.last + STEP <= elapsed {
+            self.last += STEP;
+            for bar in &mut self.heat {
+                for cell in bar.iter_mut() {
+                    *cell = (*cell - self.rng.unit() * self.cooling).max(0.0);
+                }
+                for i in (1..BAR_COUNT).rev() {
+                    bar[i] = (bar[i] + bar[i - 1] * 2.0) / 3.0;
+                }
+                if self.rng.chance(0.6) {
+                    bar[0] = (bar[0] + 0.5 + self.rng.unit() * 0.5).min(1.0);
+                }
+            }
+        }
+
+        let [right, left] = self.heat;
+        let mut colors = [BLACK; BAR_COUNT];
+        for (color, heat) in colors.iter_mut().zip(right) {
+            *color = heat_color(heat * self.intensity);
+        }
+        leds.set_right_bar(&colors);
+        for (color, heat) in colors.iter_mut().zip(left) {
+            *color = heat_color(heat * self.intensity);
+        }
+        leds.set_left_bar(&colors);
+    }
+}
+
+/// Black through red and orange to yellow as `heat` rises to 1.0, scaled
+/// so `heat` also sets the brightness.
+fn heat_color(heat: f32) -> Srgb<u8> {
+    let heat = heat.clamp(0.0, 1.0);
+    let green = (heat * heat * 0.6).min(1.0);
+    Srgb::new((heat * 255.0) as u8, (green * 255.0) as u8, 0)
+}
+
+// ── Comet ───────────────────────────────────────────────────────────────
+
+/// A bright head circling the strip with a fading tail.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Comet {
+    pub color: Srgb<u8>,
+    /// LEDs per second.
+    pub speed: f32,
+    /// Tail length in LEDs.
+    pub tail: f32,
+}
+
+impl Comet {
+    pub const fn new(color: Srgb<u8>) -> Self {
+        Self {
+            color,
+            speed: 10.0,
+            tail: 4.0,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_speed(mut self, leds_per_second: f32) -> Self {
+        self.speed = leds_per_second;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_tail(mut self, leds: f32) -> Self {
+        self.tail = leds;
+        self
+    }
+}
+
+impl Effect for Comet {
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration) {
+        let head = (seconds(elapsed) * self.speed) % LED_COUNT as f32;
+        for i in 0..LED_COUNT {
+            // Distance behind the head, wrapping around the strip.
+            let behind = (head - i as f32 + LED_COUNT as f32) % LED_COUNT as f32;
+            let level = (1.0 - behind / self.tail.max(f32::EPSILON)).max(0.0);
+            leds.set(i, scale(self.color, level * level));
+        }
+    }
+}
+
+// ── VU bounce ───────────────────────────────────────────────────────────
+
+/// Both bars bouncing like a level meter, green through yellow to red,
+/// with a falling peak marker.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VuBounce {
+    /// Brightness of a fully lit segment, 0.0–1.0.
+    pub brightness: f32,
+    level: [f32; 2],
+    target: [f32; 2],
+    peak: [f32; 2],
+    last: Duration,
+    rng: Rng,
+}
+
+impl Default for VuBounce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VuBounce {
+    /// Levels that bounce differently on every badge and boot.
+    pub fn new() -> Self {
+        Self {
+            brightness: 0.15,
+            level: [0.0; 2],
+            target: [0.0; 2],
+            peak: [0.0; 2],
+            last: Duration::from_ticks(0),
+            rng: Rng::new(crate::entropy::rng().next_u32()),
+        }
+    }
+
+    #[must_use]
+    pub const fn with_brightness(mut self, brightness: f32) -> Self {
+        self.brightness = brightness;
+        self
+    }
+
+    /// Use a fixed `seed` instead, so the pattern repeats exactly.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u32) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+}
+
+impl Effect for VuBounce {
+    fn draw(&mut self, leds: &mut Leds<'_>, elapsed: Duration) {
+        let dt = seconds(elapsed - self.last.min(elapsed));
+        self.last = elapsed;
+
+        for bar in 0..2 {
+            if self.rng.chance(dt * 6.0) {
+                self.target[bar] = self.rng.unit();
+            }
+            // Jump up quickly, fall back slowly; the peak falls slower still.
+            let rate = if self.target[bar] > self.level[bar] {
+                12.0
+            } else {
+                3.0
+            };
+            self.level[bar] += (self.target[bar] - self.level[bar]) * (rate * dt).min(1.0);
+            self.peak[bar] = (self.peak[bar] - dt * 0.5).max(self.level[bar]);
+        }
+
+        leds.set_right_bar(&self.bar_colors(0));
+        leds.set_left_bar(&self.bar_colors(1));
+    }
+}
+
+impl VuBounce {
+    fn bar_colors(&self, bar: usize) -> [Srgb<u8>; BAR_COUNT] {
+        let lit = self.level[bar] * BAR_COUNT as f32;
+        let peak = ((self.peak[bar] * BAR_COUNT as f32) as usize).min(BAR_COUNT - 1);
+        core::array::from_fn(|i| {
+            let level = (lit - i as f32).clamp(0.0, 1.0);
+            let level = if i == peak { level.max(0.5) } else { level };
+            scale(vu_color(i), level * self.brightness)
+        })
+    }
+}
+
+/// Green at the bottom of a bar, yellow in the middle, red at the top.
+const fn vu_color(index: usize) -> Srgb<u8> {
+    match index {
+        0..=1 => Srgb::new(0, 255, 0),
+        2..=3 => Srgb::new(255, 160, 0),
+        _ => Srgb::new(255, 0, 0),
+    }
+}
+
+// ── Helpers ─────────────────────────────────────────────────────────────
+
+fn seconds(duration: Duration) -> f32 {
+    duration.as_micros() as f32 / 1_000_000.0
+}
+
+/// Position in 0.0–1.0 through the current repeat of `period`.
+fn cycle(elapsed: Duration, period: Duration) -> f32 {
+    let period = period.as_ticks().max(1);
+    (elapsed.as_ticks() % period) as f32 / period as f32
+}
+
+fn scale(color: Srgb<u8>, level: f32) -> Srgb<u8> {
+    let level = level.clamp(0.0, 1.0);
+    let s = |c: u8| (f32::from(c) * level + 0.5) as u8;
+    Srgb::new(s(color.red), s(color.green), s(color.blue))
+}
+
+/// Small xorshift generator; effects only need something that looks random.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Rng(u32);
+
+impl Rng {
+    /// Zero is replaced, since xorshift would stay at zero.
+    const fn new(seed: u32) -> Self {
+        Self(if seed == 0 { 0x5eed_1e55 } else { seed })
+    }
+
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    /// Uniform in 0.0–1.0.
+    fn unit(&mut self) -> f32 {
+        (self.next() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn chance(&mut self, probability: f32) -> bool {
+        self.unit() < probability
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.next() as usize % n
+    }
+}
@@ -0,0 +1,492 @@
+//! Keyframe animations played in layers by a background task.
+//!
+//! Keyframes blend between [`Pattern`]s with the same [`Easing`] curves as
+//! the [blob interpreter](super::anim).
+//!
+//! ```rust,ignore
+//! static LED_ANIMATOR: LedAnimator = LedAnimator::new();
+//!
+//! static FLASH: [Keyframe; 2] = [
+//!     Keyframe::new(Pattern::Fill(Srgb::new(255, 255, 255)), Duration::from_millis(60))
+//!         .with_easing(Easing::Step),
+//!     Keyframe::new(Pattern::Fill(Srgb::new(0, 0, 0)), Duration::from_millis(300))
+//!         .with_easing(Easing::EaseOut),
+//! ];
+//!
+//! #[embassy_executor::task]
+//! async fn led_task(leds: &'static mut Leds<'static>) {
+//!     LED_ANIMATOR.run(leds, DEFAULT_FRAME_INTERVAL).await
+//! }
+//!
+//! LED_ANIMATOR.play(0, LedAnimation::new(&RAINBOW).with_loop(true));
+//! // later, over the rainbow until it ends:
+//! LED_ANIMATOR.play(1, LedAnimation::new(&FLASH));
+//! ```
+
+use core::cell::RefCell;
+
+use embassy_futures::select::select;
+use embassy_sync::{
+    blocking_mutex::{
+        Mutex,
+        raw::CriticalSectionRawMutex,
+    },
+    signal::Signal,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use palette::Srgb;
+
+use super::{
+    LED_COUNT,
+    Leds,
+    anim::{
+        Easing,
+        Pattern,
+        interpolate,
+    },
+};
+use crate::{
+    HapticPattern,
+    HapticQueue,
+};
+
+/// Number of layers an [`LedAnimator`] can play at once.
+pub const MAX_LAYERS: usize = 4;
+/// Redraw rate for [`LedAnimator::run`]: 50 frames per second.
+pub const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
+const BLACK: Srgb<u8> = Srgb::new(0, 0, 0);
+
+/// One step of an [`LedAnimation`]: blend from the previous keyframe's
+/// colours to `pattern` over `duration`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Keyframe {
+    pub pattern: Pattern,
+    pub duration: Duration,
+    pub easing: Easing,
+    /// Played as the keyframe starts; see [`LedAnimator::with_haptics`].
+    pub haptic: Option<HapticPattern>,
+}
+
+impl Keyframe {
+    /// A keyframe with [`Easing::Linear`].
+    pub const fn new(pattern: Pattern, duration: Duration) -> Self {
+        Self {
+            pattern,
+            duration,
+            easing: Easing::Linear,
+            haptic: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Buzz `haptic` when this keyframe starts.
+    #[must_use]
+    pub const fn with_haptic(mut self, haptic: HapticPattern) -> Self {
+        self.haptic = Some(haptic);
+        self
+    }
+}
+
+/// How a layer combines with the layers below it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub enum Blend {
+    /// Hide everything below while playing.
+    #[default]
+    Replace,
+    /// Add to the colours below, saturating at full brightness.
+    Add,
+}
+
+/// A sequence of [`Keyframe`]s, played once or looped.
+#[derive(Clone, Copy, Debug)]
+pub struct LedAnimation {
+    keyframes: &'static [Keyframe],
+    looping: bool,
+    blend: Blend,
+}
+
+impl LedAnimation {
+    /// Play `keyframes` once, replacing the layers below.
+    pub const fn new(keyframes: &'static [Keyframe]) -> Self {
+        Self {
+            keyframes,
+            looping: false,
+            blend: Blend::Replace,
+        }
+    }
+
+    /// Restart from the first keyframe after the last, until stopped.
+    #[must_use]
+    pub const fn with_loop(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_blend(mut self, blend: Blend) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Length of one pass through the keyframes.
+    pub fn duration(&self) -> Duration {
+        self.keyframes
+            .iter()
+            .fold(Duration::from_ticks(0), |total, key| total + key.duration)
+    }
+
+    /// The colours `elapsed` after the start, or `None` once an animation
+    /// that doesn't loop has finished.
+    ///
+    /// The first keyframe blends from black, or from the last keyframe when
+    /// looping.
+    pub fn sample(&self, elapsed: Duration) -> Option<[Srgb<u8>; LED_COUNT]> {
+        let keys = self
+            .keyframes
+            .iter()
+            .map(|key| (key.pattern, key.duration.as_ticks(), key.easing));
+        interpolate(keys, self.looping, elapsed.as_ticks())
+    }
+
+    /// Which pass and keyframe `elapsed` falls in, or `None` once an
+    /// animation that doesn't loop has finished.
+    fn keyframe_at(&self, elapsed: Duration) -> Option<(u64, usize)> {
+        let lens = self.keyframes.iter().map(|key| key.duration.as_ticks());
+        locate(lens, self.looping, elapsed.as_ticks())
+    }
+}
+
+// ── Frame timeline ──────────────────────────────────────────────────────
+
+/// One step of a [`Timeline`], like a [`Keyframe`] but measured in display
+/// frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimelineKey {
+    pub pattern: Pattern,
+    pub frames: u32,
+    pub easing: Easing,
+    /// Returned by [`Timeline::cue`] on the key's first frame.
+    pub haptic: Option<HapticPattern>,
+}
+
+impl TimelineKey {
+    /// A key with [`Easing::Linear`].
+    pub const fn new(pattern: Pattern, frames: u32) -> Self {
+        Self {
+            pattern,
+            frames,
+            easing: Easing::Linear,
+            haptic: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Cue `haptic` on this key's first frame.
+    #[must_use]
+    pub const fn with_haptic(mut self, haptic: HapticPattern) -> Self {
+        self.haptic = Some(haptic);
+        self
+    }
+}
+
+/// LED keyframes stepped by the render loop rather than the clock, so
+/// flashes land on exactly the frame they were written for however long
+/// each frame takes.
+///
+/// ```rust,ignore
+/// // A flash on every 24th frame, fading out before the next beat.
+/// static BEAT: [TimelineKey; 2] = [
+///     TimelineKey::new(Pattern::Fill(Srgb::new(40, 40, 40)), 1).with_easing(Easing::Step),
+///     TimelineKey::new(Pattern::Fill(Srgb::new(0, 0, 0)), 23).with_easing(Easing::EaseOut),
+/// ];
+///
+/// let mut timeline = Timeline::new(&BEAT).with_loop(true);
+/// loop {
+///     draw_scene(&mut display, timeline.frame());
+///     if let Some(colors) = timeline.advance() {
+///         leds.fill_from_iter(colors);
+///         leds.update().await;
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Timeline {
+    keys: &'static [TimelineKey],
+    looping: bool,
+    frame: u32,
+}
+
+impl Timeline {
+    /// Play `keys` once from frame 0.
+    pub const fn new(keys: &'static [TimelineKey]) -> Self {
+        Self {
+            keys,
+            looping: false,
+            frame: 0,
+        }
+    }
+
+    /// Restart from the first key after the last, forever.
+    #[must_use]
+    pub const fn with_loop(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Frames in one pass through the keys.
+    pub fn len(&self) -> u32 {
+        self.keys.iter().map(|key| key.frames).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The frame [`advance`](Self::advance) shows next.
+    pub const fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Jump to `frame`, e.g. to follow the music after a dropped frame.
+    pub const fn seek(&mut self, frame: u32) {
+        self.frame = frame;
+    }
+
+    /// The colours at `frame`, or `None` past the end of a timeline that
+    /// doesn't loop. Blends like [`LedAnimation::sample`].
+    pub fn sample(&self, frame: u32) -> Option<[Srgb<u8>; LED_COUNT]> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| (key.pattern, u64::from(key.frames), key.easing));
+        interpolate(keys, self.looping, u64::from(frame))
+    }
+
+    /// The haptic pattern to start on `frame`, if a key with one begins
+    /// there.
+    pub fn cue(&self, frame: u32) -> Option<HapticPattern> {
+        let total = self.len();
+        if total == 0 || (!self.looping && frame >= total) {
+            return None;
+        }
+        let mut t = frame % total;
+        for key in self.keys {
+            if t == 0 {
+                return key.haptic;
+            }
+            if t < key.frames {
+                return None;
+            }
+            t -= key.frames;
+        }
+        None
+    }
+
+    /// The colours for the current frame; then move on to the next.
+    pub fn advance(&mut self) -> Option<[Srgb<u8>; LED_COUNT]> {
+        let colors = self.sample(self.frame);
+        self.frame = self.frame.saturating_add(1);
+        colors
+    }
+}
+
+/// Which pass through `lens` and which entry `t` falls in, or `None` past
+/// the end unless `looping`.
+fn locate(
+    lens: impl Iterator<Item = u64> + Clone,
+    looping: bool,
+    mut t: u64,
+) -> Option<(u64, usize)> {
+    let total: u64 = lens.clone().sum();
+    let mut pass = 0;
+    if t >= total {
+        if !looping || total == 0 {
+            return None;
+        }
+        pass = t / total;
+        t %= total;
+    }
+    let mut start = 0;
+    for (i, len) in lens.enumerate() {
+        if t < start + len {
+            return Some((pass, i));
+        }
+        start += len;
+    }
+    None
+}
+
+#[derive(Clone, Copy)]
+struct Playing {
+    animation: LedAnimation,
+    started: Instant,
+    /// The pass and keyframe drawn last, to spot the start of the next.
+    keyframe: Option<(u64, usize)>,
+}
+
+/// Plays [`LedAnimation`]s on up to [`MAX_LAYERS`] layers.
+///
+/// Any task can [`play`](Self::play) or [`stop`](Self::stop) a layer; one
+/// task owns the [`Leds`] and draws the result with [`run`](Self::run).
+/// Higher layers draw over lower ones, and a layer empties itself when an
+/// animation that doesn't loop ends.
+pub struct LedAnimator {
+    layers: Mutex<CriticalSectionRawMutex, RefCell<[Option<Playing>; MAX_LAYERS]>>,
+    changed: Signal<CriticalSectionRawMutex, ()>,
+    haptics: Option<&'static HapticQueue>,
+}
+
+impl Default for LedAnimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LedAnimator {
+    pub const fn new() -> Self {
+        Self {
+            layers: Mutex::new(RefCell::new([None; MAX_LAYERS])),
+            changed: Signal::new(),
+            haptics: None,
+        }
+    }
+
+    /// Request each keyframe's [haptic](Keyframe::with_haptic) from
+    /// `haptics` as the keyframe starts, at the layer number as priority.
+    ///
+    /// Cues follow the drawn frames, so a keyframe shorter than the frame
+    /// interval can be passed over without its cue.
+    ///
+    /// ```rust,ignore
+    /// static HAPTICS: HapticQueue = HapticQueue::new();
+    /// static LED_ANIMATOR: LedAnimator = LedAnimator::new().with_haptics(&HAPTICS);
+    /// ```
+    #[must_use]
+    pub const fn with_haptics(mut self, haptics: &'static HapticQueue) -> Self {
+        self.haptics = Some(haptics);
+        self
+    }
+
+    /// Start `animation` from its first keyframe on `layer`, replacing
+    /// whatever that layer was playing.
+    ///
+    /// # Panics
+    ///
+    /// If `layer` is not below [`MAX_LAYERS`].
+    pub fn play(&self, layer: usize, animation: LedAnimation) {
+        let playing = Playing {
+            animation,
+            started: Instant::now(),
+            keyframe: None,
+        };
+        self.layers
+            .lock(|layers| layers.borrow_mut()[layer] = Some(playing));
+        self.changed.signal(());
+    }
+
+    /// Empty `layer`, uncovering the layers below it.
+    ///
+    /// # Panics
+    ///
+    /// If `layer` is not below [`MAX_LAYERS`].
+    pub fn stop(&self, layer: usize) {
+        self.layers.lock(|layers| layers.borrow_mut()[layer] = None);
+        self.changed.signal(());
+    }
+
+    pub fn stop_all(&self) {
+        self.layers
+            .lock(|layers| *layers.borrow_mut() = [None; MAX_LAYERS]);
+        self.changed.signal(());
+    }
+
+    pub fn is_playing(&self, layer: usize) -> bool {
+        self.layers
+            .lock(|layers| layers.borrow().get(layer).is_some_and(Option::is_some))
+    }
+
+    /// Draw the layers on `leds` every `interval` while any is playing.
+    ///
+    /// When all layers are empty the LEDs are turned off and the task
+    /// sleeps until the next [`play`](Self::play).
+    pub async fn run(&self, leds: &mut Leds<'_>, interval: Duration) -> ! {
+        loop {
+            let (frame, active, cues) = self.compose(Instant::now());
+            if let Some(haptics) = self.haptics {
+                for (layer, cue) in cues.iter().enumerate() {
+                    if let Some(pattern) = cue {
+                        haptics.request(*pattern, layer as u8);
+                    }
+                }
+            }
+            leds.fill_from_iter(frame);
+            leds.update().await;
+            if active {
+                select(Timer::after(interval), self.changed.wait()).await;
+            } else {
+                self.changed.wait().await;
+            }
+        }
+    }
+
+    /// The combined frame at `now`, whether any layer is still playing,
+    /// and each layer's haptic cue for a keyframe that just started.
+    fn compose(
+        &self,
+        now: Instant,
+    ) -> (
+        [Srgb<u8>; LED_COUNT],
+        bool,
+        [Option<HapticPattern>; MAX_LAYERS],
+    ) {
+        self.layers.lock(|layers| {
+            let mut frame = [BLACK; LED_COUNT];
+            let mut active = false;
+            let mut cues = [None; MAX_LAYERS];
+            for (slot, cue) in layers.borrow_mut().iter_mut().zip(&mut cues) {
+                let Some(playing) = slot else {
+                    continue;
+                };
+                let elapsed = now.saturating_duration_since(playing.started);
+                let Some(colors) = playing.animation.sample(elapsed) else {
+                    *slot = None;
+                    continue;
+                };
+                active = true;
+                let keyframe = playing.animation.keyframe_at(elapsed);
+                if keyframe != playing.keyframe {
+                    playing.keyframe = keyframe;
+                    *cue = keyframe.and_then(|(_, i)| playing.animation.keyframes[i].haptic);
+                }
+                match playing.animation.blend {
+                    Blend::Replace => frame = colors,
+                    Blend::Add => {
+                        for (out, color) in frame.iter_mut().zip(colors) {
+                            *out = Srgb::new(
+                                out.red.saturating_add(color.red),
+                                out.green.saturating_add(color.green),
+                                out.blue.saturating_add(color.blue),
+                            );
+                        }
+                    }
+                }
+            }
+            (frame, active, cues)
+        })
+    }
+}
@@ -0,0 +1,159 @@
+//! Where each LED sits on the badge.
+//!
+//! Hardware indices run counter-clockwise from the bottom of the right bar:
+//! 0–4 up the right bar, then 5–9 down the left bar. The tables here map
+//! between those indices and bar positions or coordinates, so effects can
+//! be written in terms of the badge's geometry.
+//!
+//! [`PCB_POSITIONS_MM`] holds where each LED sits on the board, in mm. The
+//! normalised coordinates effects usually want are derived from it: `x` is
+//! −1.0 at the leftmost LED and 1.0 at the rightmost, `y` runs from 0.0 at
+//! the lowest LED to 1.0 at the highest.
+
+use palette::Srgb;
+
+use super::{
+    BAR_COUNT,
+    LED_COUNT,
+    Leds,
+};
+
+/// Which bar an LED is on, as seen from the front.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, defmt::Format)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Physical placement of one LED.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct LedPosition {
+    pub side: Side,
+    /// 0 for the bottom LED of the bar up to `BAR_COUNT - 1` for the top.
+    pub height: usize,
+    /// −1.0 on the left bar, 1.0 on the right.
+    pub x: f32,
+    /// 0.0 at the bottom, 1.0 at the top.
+    pub y: f32,
+    /// Millimetres right of the board's centre line.
+    pub x_mm: f32,
+    /// Millimetres above the board's centre line.
+    pub y_mm: f32,
+}
+
+/// Hardware indices of the left bar, bottom to top.
+pub const LEFT_BAR: [usize; BAR_COUNT] = [9, 8, 7, 6, 5];
+/// Hardware indices of the right bar, bottom to top.
+pub const RIGHT_BAR: [usize; BAR_COUNT] = [0, 1, 2, 3, 4];
+
+/// Centre of each LED on the PCB, in hardware order: millimetres right of
+/// and above the middle of the board, seen from the front.
+pub const PCB_POSITIONS_MM: [(f32, f32); LED_COUNT] = [
+    (45.0, -16.0),
+    (45.0, -8.0),
+    (45.0, 0.0),
+    (45.0, 8.0),
+    (45.0, 16.0),
+    (-45.0, 16.0),
+    (-45.0, 8.0),
+    (-45.0, 0.0),
+    (-45.0, -8.0),
+    (-45.0, -16.0),
+];
+
+/// Every LED's position, in hardware order.
+pub const POSITIONS: [LedPosition; LED_COUNT] = {
+    // Bounds of the LEDs on the board, to normalise against.
+    let (mut left, mut right) = (f32::MAX, f32::MIN);
+    let (mut bottom, mut top) = (f32::MAX, f32::MIN);
+    let mut i = 0;
+    while i < LED_COUNT {
+        let (x, y) = PCB_POSITIONS_MM[i];
+        left = left.min(x);
+        right = right.max(x);
+        bottom = bottom.min(y);
+        top = top.max(y);
+        i += 1;
+    }
+    let mut positions = [LedPosition {
+        side: Side::Right,
+        height: 0,
+        x: 0.0,
+        y: 0.0,
+        x_mm: 0.0,
+        y_mm: 0.0,
+    }; LED_COUNT];
+    let mut height = 0;
+    while height < BAR_COUNT {
+        let sides = [
+            (Side::Left, LEFT_BAR[height]),
+            (Side::Right, RIGHT_BAR[height]),
+        ];
+        let mut s = 0;
+        while s < sides.len() {
+            let (side, index) = sides[s];
+            let (x_mm, y_mm) = PCB_POSITIONS_MM[index];
+            positions[index] = LedPosition {
+                side,
+                height,
+                x: (x_mm - left) / (right - left) * 2.0 - 1.0,
+                y: (y_mm - bottom) / (top - bottom),
+                x_mm,
+                y_mm,
+            };
+            s += 1;
+        }
+        height += 1;
+    }
+    positions
+};
+
+impl Side {
+    /// Hardware indices of this bar, bottom to top.
+    pub const fn bar(self) -> [usize; BAR_COUNT] {
+        match self {
+            Self::Left => LEFT_BAR,
+            Self::Right => RIGHT_BAR,
+        }
+    }
+}
+
+/// Hardware index of the LED `height` steps up the `side` bar.
+///
+/// # Panics
+///
+/// If `height` is not below [`BAR_COUNT`].
+pub const fn index(side: Side, height: usize) -> usize {
+    side.bar()[height]
+}
+
+/// Position of the LED at hardware `index`.
+///
+/// # Panics
+///
+/// If `index` is not below `LED_COUNT`.
+pub const fn position(index: usize) -> LedPosition {
+    POSITIONS[index]
+}
+
+impl Leds<'_> {
+    /// Set the LED `height` steps up the `side` bar.
+    pub const fn set_at(&mut self, side: Side, height: usize, color: Srgb<u8>) {
+        self.set(index(side, height), color);
+    }
+
+    /// Colour every LED by its position.
+    ///
+    /// ```rust,ignore
+    /// // A band sweeping diagonally from bottom left to top right.
+    /// leds.fill_by_position(|p| {
+    ///     let d = ((p.x + 1.0) / 2.0 + p.y) / 2.0 - t;
+    ///     if d.abs() < 0.15 { Srgb::new(0, 20, 20) } else { Srgb::new(0, 0, 0) }
+    /// });
+    /// ```
+    pub fn fill_by_position(&mut self, mut color: impl FnMut(LedPosition) -> Srgb<u8>) {
+        for (i, position) in POSITIONS.iter().enumerate() {
+            self.set(i, color(*position));
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Bar-graph levels on the two LED bars.
+
+use embassy_time::{
+    Duration,
+    Instant,
+};
+use palette::Srgb;
+
+use super::{
+    BAR_COUNT,
+    Leds,
+};
+
+/// Green through yellow to red, bottom to top, at a dim level.
+pub const VU_RAMP: [Srgb<u8>; BAR_COUNT] = [
+    Srgb::new(0, 20, 0),
+    Srgb::new(0, 20, 0),
+    Srgb::new(20, 20, 0),
+    Srgb::new(20, 10, 0),
+    Srgb::new(20, 0, 0),
+];
+
+impl Leds<'_> {
+    /// Fill both bars from the bottom in proportion to `value / max`.
+    ///
+    /// LED `i` from the bottom shows `ramp[i]`. Partial steps round up, so
+    /// any value above zero lights at least one LED.
+    pub fn bar_meter(&mut self, value: u32, max: u32, ramp: &[Srgb<u8>; BAR_COUNT]) {
+        self.set_both_bars(&meter_colors(leds_for(value, max), 0, ramp));
+    }
+}
+
+/// [`Leds::bar_meter`] with a peak marker that holds the highest recent
+/// level.
+///
+/// ```rust,ignore
+/// let mut meter = BarMeter::new(VU_RAMP).with_peak_hold(Duration::from_millis(800));
+/// loop {
+///     meter.show(&mut leds, mic_peak, MAX_AMPLITUDE);
+///     leds.update().await;
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarMeter {
+    ramp: [Srgb<u8>; BAR_COUNT],
+    hold: Option<Duration>,
+    /// LEDs lit at the held peak.
+    peak: usize,
+    peak_at: Instant,
+}
+
+impl BarMeter {
+    /// A meter without peak hold.
+    pub const fn new(ramp: [Srgb<u8>; BAR_COUNT]) -> Self {
+        Self {
+            ramp,
+            hold: None,
+            peak: 0,
+            peak_at: Instant::MIN,
+        }
+    }
+
+    /// Keep the LED at the highest level lit for `hold` after it was
+    /// reached.
+    #[must_use]
+    pub const fn with_peak_hold(mut self, hold: Duration) -> Self {
+        self.hold = Some(hold);
+        self
+    }
+
+    /// Show `value / max` on both bars of `leds`.
+    pub fn show(&mut self, leds: &mut Leds<'_>, value: u32, max: u32) {
+        let lit = leds_for(value, max);
+        if let Some(hold) = self.hold {
+            let now = Instant::now();
+            if lit >= self.peak || now.saturating_duration_since(self.peak_at) > hold {
+                self.peak = lit;
+                self.peak_at = now;
+            }
+        }
+        leds.set_both_bars(&meter_colors(lit, self.peak, &self.ramp));
+    }
+}
+
+/// LEDs needed to show `value / max`, rounding up.
+fn leds_for(value: u32, max: u32) -> usize {
+    if max == 0 {
+        return 0;
+    }
+    let value = u64::from(value.min(max));
+    (value * BAR_COUNT as u64).div_ceil(u64::from(max)) as usize
+}
+
+/// The bottom `lit` LEDs from `ramp`, plus the `peak`-th one.
+fn meter_colors(lit: usize, peak: usize, ramp: &[Srgb<u8>; BAR_COUNT]) -> [Srgb<u8>; BAR_COUNT] {
+    core::array::from_fn(|i| {
+        if i < lit || i + 1 == peak {
+            ramp[i]
+        } else {
+            Srgb::new(0, 0, 0)
+        }
+    })
+}
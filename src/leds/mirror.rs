@@ -0,0 +1,150 @@
+//! The LED colours drawn on the display, for working at a desk and for
+//! screenshots and screen recordings.
+
+use defmt::error;
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    signal::Signal,
+};
+use embedded_graphics::{
+    Drawable,
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        Point,
+        Primitive,
+        RgbColor,
+        Size,
+    },
+    primitives::{
+        Circle,
+        PrimitiveStyle,
+        Rectangle,
+    },
+};
+use palette::Srgb;
+
+use super::{
+    BAR_COUNT,
+    LED_COUNT,
+    layout::{
+        POSITIONS,
+        Side,
+    },
+};
+use crate::SharedDisplay;
+
+/// Distance between neighbouring circles.
+const PITCH: u32 = 12;
+/// Circle size, leaving a gap inside the pitch.
+const DIAMETER: u32 = 10;
+/// Outline of an LED that is off, so it stays visible.
+const OFF_OUTLINE: Rgb565 = Rgb565::new(6, 12, 6);
+
+/// Receives each frame [`Leds`](super::Leds) shows and draws it as ten
+/// circles, laid out like the two bars.
+///
+/// ```rust,ignore
+/// static LED_MIRROR: LedMirror = LedMirror::new();
+///
+/// #[embassy_executor::task]
+/// async fn mirror_task(display: &'static SharedDisplay<'static>) {
+///     let corner = Point::new(i32::from(DISPLAY_WIDTH) - LedMirror::SIZE.width as i32, 0);
+///     LED_MIRROR.run(display, corner).await
+/// }
+///
+/// let leds = Leds::from(resources.leds).with_mirror(&LED_MIRROR);
+/// ```
+///
+/// The circles show the framebuffer colours, before brightness, white
+/// balance and gamma, scaled up so the brightest channel in the frame is
+/// at full. Dim effects stay readable and keep their relative levels.
+pub struct LedMirror {
+    frame: Signal<CriticalSectionRawMutex, [Srgb<u8>; LED_COUNT]>,
+}
+
+impl Default for LedMirror {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LedMirror {
+    /// Area covered by [`draw`](Self::draw).
+    pub const SIZE: Size = Size::new(PITCH * 2, PITCH * BAR_COUNT as u32);
+
+    pub const fn new() -> Self {
+        Self {
+            frame: Signal::new(),
+        }
+    }
+
+    /// Hand `frame` to [`run`](Self::run), replacing one not drawn yet.
+    pub(super) fn publish(&self, frame: [Srgb<u8>; LED_COUNT]) {
+        self.frame.signal(frame);
+    }
+
+    /// Draw each frame as it arrives on `display`, with the top left corner
+    /// at `top_left`. Unchanged frames are skipped.
+    pub async fn run(&self, display: &SharedDisplay<'_>, top_left: Point) -> ! {
+        let area = Rectangle::new(top_left, Self::SIZE);
+        let mut last = None;
+        loop {
+            let frame = self.frame.wait().await;
+            if last == Some(frame) {
+                continue;
+            }
+            last = Some(frame);
+            let mut region = display.region(area).await;
+            if let Err(err) = Self::draw(&frame, Point::zero(), &mut region) {
+                error!("LED mirror draw failed: {}", err);
+            }
+        }
+    }
+
+    /// Draw `frame` in the [`SIZE`](Self::SIZE) area from `top_left`: the
+    /// left bar on the left, bottom LEDs at the bottom.
+    pub fn draw<D>(
+        frame: &[Srgb<u8>; LED_COUNT],
+        top_left: Point,
+        target: &mut D,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        target.fill_solid(&Rectangle::new(top_left, Self::SIZE), Rgb565::BLACK)?;
+        let brightest = frame
+            .iter()
+            .flat_map(|c| [c.red, c.green, c.blue])
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let boost = |v: u8| (u32::from(v) * 255 / u32::from(brightest)) as u8;
+        for (color, position) in frame.iter().zip(POSITIONS) {
+            let column = match position.side {
+                Side::Left => 0,
+                Side::Right => 1,
+            };
+            let row = (BAR_COUNT - 1 - position.height) as u32;
+            let offset = (PITCH - DIAMETER) / 2;
+            let corner = top_left
+                + Point::new(
+                    (column * PITCH + offset) as i32,
+                    (row * PITCH + offset) as i32,
+                );
+            let style = if *color == Srgb::new(0, 0, 0) {
+                PrimitiveStyle::with_stroke(OFF_OUTLINE, 1)
+            } else {
+                PrimitiveStyle::with_fill(Rgb565::new(
+                    boost(color.red) >> 3,
+                    boost(color.green) >> 2,
+                    boost(color.blue) >> 3,
+                ))
+            };
+            Circle::new(corner, DIAMETER)
+                .into_styled(style)
+                .draw(target)?;
+        }
+        Ok(())
+    }
+}
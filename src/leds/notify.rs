@@ -0,0 +1,233 @@
+//! Prioritised LED notifications played over an [`LedAnimator`] layer.
+
+use core::{
+    cell::RefCell,
+    cmp::Reverse,
+};
+
+use embassy_futures::select::{
+    Either,
+    select,
+};
+use embassy_sync::{
+    blocking_mutex::{
+        Mutex,
+        raw::CriticalSectionRawMutex,
+    },
+    signal::Signal,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+
+use super::layers::{
+    LedAnimation,
+    LedAnimator,
+};
+
+/// Notifications that can wait at once, besides the one playing.
+pub const NOTIFY_CAPACITY: usize = 8;
+
+/// An animation to show for a while, and how much it matters.
+#[derive(Clone, Copy, Debug)]
+pub struct LedNotification {
+    animation: LedAnimation,
+    priority: u8,
+    duration: Duration,
+    max_wait: Option<Duration>,
+}
+
+impl LedNotification {
+    /// Play `animation` once through. Higher `priority` plays first.
+    pub fn new(animation: LedAnimation, priority: u8) -> Self {
+        Self {
+            animation,
+            priority,
+            duration: animation.duration(),
+            max_wait: None,
+        }
+    }
+
+    /// Show it for `duration` instead, e.g. for a looping animation.
+    /// [`Duration::MAX`] shows it until something more important comes.
+    #[must_use]
+    pub const fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Drop it unless it starts within `max_wait` of being queued.
+    #[must_use]
+    pub const fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = Some(max_wait);
+        self
+    }
+
+    pub const fn priority(&self) -> u8 {
+        self.priority
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Queued {
+    notification: LedNotification,
+    queued_at: Instant,
+}
+
+impl Queued {
+    fn expired(&self, now: Instant) -> bool {
+        self.notification
+            .max_wait
+            .is_some_and(|wait| now.saturating_duration_since(self.queued_at) > wait)
+    }
+}
+
+type Queue = [Option<Queued>; NOTIFY_CAPACITY];
+
+/// Plays one [`LedNotification`] at a time, most important first.
+///
+/// Any task can [`notify`](Self::notify); one task plays them with
+/// [`run`](Self::run) on a layer of an [`LedAnimator`], above whatever
+/// ambient animation the lower layers show.
+///
+/// ```rust,ignore
+/// static LED_ANIMATOR: LedAnimator = LedAnimator::new();
+/// static LED_NOTIFIER: LedNotifier = LedNotifier::new();
+///
+/// #[embassy_executor::task]
+/// async fn led_task(leds: &'static mut Leds<'static>) {
+///     join(
+///         LED_ANIMATOR.run(leds, DEFAULT_FRAME_INTERVAL),
+///         LED_NOTIFIER.run(&LED_ANIMATOR, MAX_LAYERS - 1),
+///     )
+///     .await;
+/// }
+///
+/// LED_NOTIFIER.notify(LedNotification::new(LedAnimation::new(&LOW_BATTERY), 200));
+/// ```
+pub struct LedNotifier {
+    queue: Mutex<CriticalSectionRawMutex, RefCell<Queue>>,
+    changed: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl Default for LedNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LedNotifier {
+    pub const fn new() -> Self {
+        Self {
+            queue: Mutex::new(RefCell::new([None; NOTIFY_CAPACITY])),
+            changed: Signal::new(),
+        }
+    }
+
+    /// Queue `notification`.
+    ///
+    /// When the queue is full the least important waiting notification
+    /// makes room, if it matters less than this one. Returns `false` if
+    /// `notification` was dropped instead.
+    pub fn notify(&self, notification: LedNotification) -> bool {
+        let queued = Queued {
+            notification,
+            queued_at: Instant::now(),
+        };
+        let accepted = self
+            .queue
+            .lock(|queue| insert(&mut queue.borrow_mut(), queued));
+        if accepted {
+            self.changed.signal(());
+        }
+        accepted
+    }
+
+    /// Drop every waiting notification. The one playing finishes.
+    pub fn clear(&self) {
+        self.queue
+            .lock(|queue| *queue.borrow_mut() = [None; NOTIFY_CAPACITY]);
+    }
+
+    /// Play queued notifications on `layer` of `animator`, forever.
+    ///
+    /// A more important notification interrupts the one playing, which
+    /// goes back in the queue and starts over when its turn comes again.
+    /// Notifications of equal priority play in the order they came.
+    pub async fn run(&self, animator: &LedAnimator, layer: usize) -> ! {
+        loop {
+            let Some(current) = self.take_next() else {
+                self.changed.wait().await;
+                continue;
+            };
+            animator.play(layer, current.notification.animation);
+            let ends = Instant::now()
+                .checked_add(current.notification.duration)
+                .unwrap_or(Instant::MAX);
+            while let Either::Second(()) = select(Timer::at(ends), self.changed.wait()).await {
+                let preempted = self.queue.lock(|queue| {
+                    let mut queue = queue.borrow_mut();
+                    let outranked = best(&mut queue, Instant::now()).is_some_and(|i| {
+                        queue[i].is_some_and(|next| {
+                            next.notification.priority > current.notification.priority
+                        })
+                    });
+                    if outranked {
+                        insert(&mut queue, current);
+                    }
+                    outranked
+                });
+                if preempted {
+                    break;
+                }
+            }
+            animator.stop(layer);
+        }
+    }
+
+    fn take_next(&self) -> Option<Queued> {
+        self.queue.lock(|queue| {
+            let mut queue = queue.borrow_mut();
+            let i = best(&mut queue, Instant::now())?;
+            queue[i].take()
+        })
+    }
+}
+
+/// Index of the notification to play next, dropping any that waited too
+/// long.
+fn best(queue: &mut Queue, now: Instant) -> Option<usize> {
+    for slot in queue.iter_mut() {
+        if slot.is_some_and(|queued| queued.expired(now)) {
+            *slot = None;
+        }
+    }
+    queue
+        .iter()
+        .enumerate()
+        .filter_map(|(i, slot)| slot.map(|queued| (i, queued)))
+        .max_by_key(|(_, queued)| (queued.notification.priority, Reverse(queued.queued_at)))
+        .map(|(i, _)| i)
+}
+
+/// Put `queued` in a free slot, or in place of a less important
+/// notification. Returns whether it went in.
+fn insert(queue: &mut Queue, queued: Queued) -> bool {
+    let slot = queue.iter().position(Option::is_none).or_else(|| {
+        // The least important, newest first, if it's below `queued`.
+        queue
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.map(|other| (i, other)))
+            .min_by_key(|(_, other)| (other.notification.priority, Reverse(other.queued_at)))
+            .filter(|(_, other)| other.notification.priority < queued.notification.priority)
+            .map(|(i, _)| i)
+    });
+    let Some(slot) = slot else {
+        return false;
+    };
+    queue[slot] = Some(queued);
+    true
+}
@@ -0,0 +1,165 @@
+//! The user's ambient LED lighting, kept in a [`Store`].
+
+use palette::Srgb;
+
+use super::{
+    Leds,
+    effects,
+    layers::DEFAULT_FRAME_INTERVAL,
+};
+use crate::{
+    config::{
+        self,
+        LedEffect,
+    },
+    storage::{
+        self,
+        Store,
+    },
+};
+
+/// Longest profile name kept, in bytes.
+pub const PROFILE_NAME_LEN: usize = 16;
+
+/// Peak colour of [`LedEffect::Heartbeat`].
+const HEARTBEAT_COLOR: Srgb<u8> = Srgb::new(30, 0, 0);
+
+/// Effect tag, colour and brightness ahead of the name.
+const HEADER_LEN: usize = 5;
+
+/// A named choice of idle effect and brightness.
+///
+/// A settings app [`save`](Self::save)s it once; any firmware can then
+/// pick it up at boot:
+///
+/// ```rust,ignore
+/// let profile = LedProfile::restore(&mut store);
+/// spawner.must_spawn(led_task(leds, profile));
+///
+/// #[embassy_executor::task]
+/// async fn led_task(leds: &'static mut Leds<'static>, profile: LedProfile) {
+///     profile.run(leds).await
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LedProfile {
+    name: [u8; PROFILE_NAME_LEN],
+    name_len: u8,
+    pub effect: LedEffect,
+    /// Applied with [`Leds::set_max_brightness`].
+    pub brightness: u8,
+}
+
+impl LedProfile {
+    /// An unnamed profile.
+    pub const fn new(effect: LedEffect, brightness: u8) -> Self {
+        Self {
+            name: [0; PROFILE_NAME_LEN],
+            name_len: 0,
+            effect,
+            brightness,
+        }
+    }
+
+    /// The effect and brightness from `badge.toml`.
+    pub const fn from_config() -> Self {
+        Self::new(config::LED_EFFECT, config::LED_BRIGHTNESS)
+    }
+
+    /// Name the profile, cut to [`PROFILE_NAME_LEN`] bytes at a character
+    /// boundary.
+    #[must_use]
+    pub fn with_name(mut self, name: &str) -> Self {
+        let mut len = name.len().min(PROFILE_NAME_LEN);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.name = [0; PROFILE_NAME_LEN];
+        self.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+        self.name_len = len as u8;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        // Only ever filled from a `&str` or checked on load.
+        core::str::from_utf8(&self.name[..usize::from(self.name_len)]).unwrap_or_default()
+    }
+
+    /// Persist the profile so [`load`](Self::load) can pick it up after a
+    /// reboot.
+    pub fn save<S: Store + ?Sized>(&self, store: &mut S) -> Result<(), S::Error> {
+        let (tag, color) = match self.effect {
+            LedEffect::Off => (0, Srgb::new(0, 0, 0)),
+            LedEffect::Heartbeat => (1, Srgb::new(0, 0, 0)),
+            LedEffect::Rainbow => (2, Srgb::new(0, 0, 0)),
+            LedEffect::Solid(color) => (3, color),
+        };
+        let len = usize::from(self.name_len);
+        let mut buf = [0; HEADER_LEN + PROFILE_NAME_LEN];
+        buf[..HEADER_LEN].copy_from_slice(&[
+            tag,
+            color.red,
+            color.green,
+            color.blue,
+            self.brightness,
+        ]);
+        buf[HEADER_LEN..HEADER_LEN + len].copy_from_slice(&self.name[..len]);
+        store.write(storage::LED_PROFILE, &buf[..HEADER_LEN + len])
+    }
+
+    /// The profile stored by [`save`](Self::save), if any.
+    ///
+    /// Anything that doesn't decode reads as `None`.
+    pub fn load<S: Store + ?Sized>(store: &mut S) -> Result<Option<Self>, S::Error> {
+        let mut buf = [0; HEADER_LEN + PROFILE_NAME_LEN];
+        let Some(len) = store.read(storage::LED_PROFILE, &mut buf)? else {
+            return Ok(None);
+        };
+        let Some(name) = buf.get(HEADER_LEN..len) else {
+            return Ok(None);
+        };
+        let Ok(name) = core::str::from_utf8(name) else {
+            return Ok(None);
+        };
+        let [tag, red, green, blue, brightness] = [buf[0], buf[1], buf[2], buf[3], buf[4]];
+        let effect = match tag {
+            0 => LedEffect::Off,
+            1 => LedEffect::Heartbeat,
+            2 => LedEffect::Rainbow,
+            3 => LedEffect::Solid(Srgb::new(red, green, blue)),
+            _ => return Ok(None),
+        };
+        Ok(Some(Self::new(effect, brightness).with_name(name)))
+    }
+
+    /// The stored profile, or [`from_config`](Self::from_config) if there
+    /// is none or it can't be read.
+    pub fn restore<S: Store + ?Sized>(store: &mut S) -> Self {
+        Self::load(store)
+            .ok()
+            .flatten()
+            .unwrap_or(Self::from_config())
+    }
+
+    /// Set the brightness and show the effect on `leds`, forever.
+    ///
+    /// [`LedEffect::Off`] cuts the LED supply and then just waits.
+    pub async fn run(&self, leds: &mut Leds<'_>) -> ! {
+        leds.set_max_brightness(self.brightness);
+        match self.effect {
+            LedEffect::Heartbeat => {
+                let mut heartbeat = effects::Heartbeat::new(HEARTBEAT_COLOR);
+                effects::run(leds, &mut heartbeat, DEFAULT_FRAME_INTERVAL).await
+            }
+            LedEffect::Rainbow => {
+                effects::run(leds, &mut effects::Rainbow::new(), DEFAULT_FRAME_INTERVAL).await
+            }
+            LedEffect::Solid(color) => {
+                leds.fill(color);
+                leds.update().await;
+            }
+            LedEffect::Off => leds.power_off(),
+        }
+        core::future::pending().await
+    }
+}
@@ -4,11 +4,37 @@
 //!
 //! Provides clean abstractions for all onboard peripherals:
 //! - **Display**: 320×170 ST7789 LCD over SPI with DMA
-//! - **Buttons**: 9-button input (D-pad, A/B, Start/Select, joystick click) with debouncing
-//! - **LEDs**: 10× WS2812 addressable RGB LEDs via RMT
-//! - **Backlight**: Display backlight control
+//! - **Buttons**: 9-button input (D-pad, A/B, Start/Select, joystick click) with debouncing, plus the BOOT button
+//! - **LEDs**: 10× WS2812 addressable RGB LEDs via RMT, with built-in effects, layered keyframe animations and shareable idle animations
+//! - **Backlight**: Display backlight dimming and fades over PWM
 //! - **Vibration motor**: Haptic feedback
 //! - **Microphone**: I2S MEMS microphone input
+//! - **Identity**: stable badge ID and `brave-otter-42` style name from the factory MAC
+//! - **Config**: compile-time settings from `badge.toml`, with the nametag overridable from storage
+//! - **UI**: frame and dialog-box drawing helpers
+//! - **Sprites**: size-checked sprite and tileset assets
+//! - **Tweak**: on-screen live tuning of game parameters
+//! - **Storage**: key-value persistence with schema migrations, typed per-app settings shared between tasks, a wear-levelled store in the `kv` flash partition (`settings` feature), and versioned `serde` structs in postcard's compact binary format (`serde` feature)
+//! - **Assets**: images and other data flashed as a bundle to an `assets` partition and read in place, without rebuilding (`assets` feature)
+//! - **Files**: named files in littlefs on their own `files` flash partition, replaced atomically, for recordings, images and logs (`fs` feature)
+//! - **Contacts**: cards swapped by handshake over ESP-NOW (`esp-now` feature) and kept in storage
+//! - **Stats**: boots, button presses, time on and time in each app, counted in RAM and saved to storage now and then
+//! - **Event log**: small records appended to a ring of sectors in an `events` flash partition, the oldest dropped as it fills, for histories that survive a reset (`event-log` feature)
+//! - **Panic log**: the last panic's message and backtrace kept in a `panic` flash partition across the reset, for the next boot to show (`panic-log` feature)
+//! - **Clock**: wall-clock time once something has set it
+//! - **Proximity**: smoothed RSSI sorted into near/close/touching zones with hysteresis, for badges heard over any radio
+//! - **Power**: one battery-saver switch shared by LEDs, backlight, frame rate and radio
+//! - **Games**: snake and pong as pure logic with reference renderers, and high-score tables kept in storage
+//! - **Wi-Fi**: station scan/connect and per-channel surveys on `esp-radio` (`wifi` feature), and a promiscuous-mode packet stream with channel hopping (`sniffer` feature)
+//! - **Game link**: lobby, pairing and reliable messages for two-player games over ESP-NOW (`esp-now` feature)
+//! - **Gossip**: signed tokens flooded badge to badge with TTL and rate limits, for "virus" games (`gossip` feature)
+//! - **HTTP**: small GET/POST client with fixed buffers over the Wi-Fi station, TLS optional (`http`/`https` features)
+//! - **Leaderboard**: signed high scores submitted to a server and the top list fetched back, relayed over ESP-NOW for badges off Wi-Fi (`leaderboard` feature)
+//! - **OTA**: signed firmware updates into the spare app partition, also passed badge to badge over ESP-NOW (`ota` feature)
+//! - **Signed messages**: Ed25519 envelopes with replay protection for ESP-NOW or BLE payloads, checkable against the organisers' key (`signed` feature)
+//! - **Remote display**: frames streamed over TCP from a laptop, raw or run-length encoded, with flow control (`remote-display` feature)
+//! - **Portal**: soft-AP captive portal for editing the nametag from a phone (`portal` feature)
+//! - **Bluetooth**: "Disobey badge" GATT service for phones (`ble` feature)
 //!
 //! ## Quick start
 //!
@@ -18,21 +44,121 @@
 //!
 //! let display: disobey2026badge::Display = resources.display.into();
 //! let buttons: disobey2026badge::Buttons = resources.buttons.into();
-//! let leds: disobey2026badge::Leds = resources.leds.into();
+//! let leds: disobey2026badge::Leds = resources.leds.try_into().expect("LED setup failed");
 //! ```
 
 #![no_std]
 
+#[cfg(any(feature = "wifi", feature = "settings", feature = "fs"))]
+extern crate alloc;
+
+#[cfg(feature = "assets")]
+pub mod assets;
 mod backlight;
+#[cfg(feature = "ble")]
+pub mod ble;
 mod buttons;
+pub mod clock;
+pub mod config;
+pub mod contacts;
 mod display;
+mod entropy;
+#[cfg(feature = "event-log")]
+pub mod event_log;
+#[cfg(feature = "fs")]
+pub mod fs;
+#[cfg(feature = "esp-now")]
+pub mod gamelink;
+pub mod games;
+#[cfg(feature = "gossip")]
+pub mod gossip;
+#[cfg(feature = "http")]
+pub mod http;
+mod identity;
+#[cfg(feature = "leaderboard")]
+pub mod leaderboard;
 mod leds;
 pub mod microphone;
+mod nametag;
+#[cfg(feature = "ota")]
+pub mod ota;
+#[cfg(feature = "panic-log")]
+pub mod panic_log;
+#[cfg(feature = "portal")]
+pub mod portal;
+pub mod power;
+pub mod proximity;
+#[cfg(any(feature = "wifi", feature = "ble"))]
+mod radio;
+#[cfg(feature = "remote-display")]
+pub mod remote_display;
+#[cfg(feature = "signed")]
+pub mod signed;
+pub mod sprite;
+pub mod stats;
+pub mod storage;
+pub mod tweak;
+pub mod ui;
 mod vibration;
+#[cfg(feature = "wifi")]
+pub mod wifi;
 
-pub use backlight::Backlight;
-pub use buttons::Buttons;
-pub use display::Display;
+pub use backlight::{
+    Activity,
+    Backlight,
+    DEFAULT_DIM_AFTER,
+    DEFAULT_OFF_AFTER,
+    IdleDimmer,
+};
+#[cfg(feature = "input-injection")]
+pub use buttons::inject;
+pub use buttons::{
+    Action,
+    BootButton,
+    Button,
+    ButtonEvent,
+    ButtonEvents,
+    ButtonInput,
+    ButtonMonitor,
+    ButtonSet,
+    ButtonState,
+    ButtonWake,
+    Buttons,
+    Chord,
+    ChordDetector,
+    Chords,
+    ClickDetector,
+    ClickKind,
+    DEFAULT_CLICK_WINDOW,
+    DEFAULT_DEBOUNCE,
+    DEFAULT_LONG_PRESS,
+    EventConfig,
+    EventTracker,
+    InputHub,
+    InputSubscriber,
+    Keymap,
+    MIN_REPEAT_INTERVAL,
+    WakeError,
+};
+pub use display::{
+    DISPLAY_HEIGHT,
+    DISPLAY_WIDTH,
+    Display,
+    DisplayError,
+    GLANCE_BAND_WIDTH,
+    GlanceTicker,
+    PanelId,
+    PanelInterface,
+    PanelStatus,
+    PowerMode,
+    RawDisplay,
+    Region,
+    SharedDisplay,
+};
+pub use entropy::{
+    add_entropy,
+    rng,
+};
 use esp_hal::{
     Async,
     Blocking,
@@ -55,12 +181,53 @@ use esp_hal::{
     rom,
     time::Rate,
 };
+pub use esp_storage::FlashStorage;
+pub use identity::{
+    BadgeId,
+    id,
+};
 pub use leds::{
     BAR_COUNT,
+    BarMeter,
+    DEFAULT_CURRENT_LIMIT_MA,
+    DEFAULT_MAX_FRAME_RATE,
+    DEFAULT_WHITE_POINT,
+    LED_COUNT,
+    LedCompositor,
+    LedError,
+    LedLayer,
+    LedMirror,
+    LedNotification,
+    LedNotifier,
+    LedProfile,
+    LedQueue,
+    LedWriter,
     Leds,
+    VU_RAMP,
+    anim as led_anim,
+    audio as led_audio,
+    effects as led_effects,
+    layers::{
+        self as led_layers,
+        LedAnimation,
+        LedAnimator,
+    },
+    layout as led_layout,
 };
 pub use microphone::Microphone;
-pub use vibration::Vibration;
+pub use nametag::{
+    NAMETAG_NAME_LEN,
+    Nametag,
+};
+pub use vibration::{
+    DutyLimit,
+    HapticEffect,
+    HapticPattern,
+    HapticQueue,
+    HapticSegment,
+    Metronome,
+    Vibration,
+};
 
 /// StaticCell helper — allocates a value into a `static` exactly once.
 #[macro_export]
@@ -89,6 +256,7 @@ assign_resources! {
         },
         backlight: BacklightResources<'d> {
             led: GPIO19,
+            ledc: LEDC,
         },
         buttons: ButtonResources<'d> {
             up: GPIO11,
@@ -108,6 +276,7 @@ assign_resources! {
         },
         vibra: VibrationResources<'d> {
             motor: GPIO20,
+            timer: TIMG1,
         },
         mic: MicResources<'d> {
             ws: GPIO8,
@@ -118,6 +287,18 @@ assign_resources! {
         },
         boot: BootResources<'d> {
             pin: GPIO0,
+        },
+        usb: UsbResources<'d> {
+            usb: USB_DEVICE,
+        },
+        wifi: WifiResources<'d> {
+            wifi: WIFI,
+        },
+        bt: BtResources<'d> {
+            bt: BT,
+        },
+        flash: FlashResources<'d> {
+            flash: FLASH,
         }
     }
 }
@@ -166,26 +347,52 @@ impl From<esp_hal::peripherals::Peripherals> for Resources<'_> {
     }
 }
 
-impl<'a> From<LedResources<'a>> for esp_hal::rmt::Channel<'a, Blocking, Tx> {
-    fn from(res: LedResources<'a>) -> Self {
-        let _ws_power = Output::new(res.power, Level::High, OutputConfig::default());
-        let rmt = Rmt::new(res.rmt, Rate::from_mhz(40)).unwrap();
-        let tx_config = TxChannelConfig::default().with_clk_divider(1);
-        rmt.channel0.configure_tx(res.io, tx_config).unwrap()
+/// Raw access to the whole SPI flash, for firmware updates (`ota` feature)
+/// and other code that writes partitions.
+///
+/// Flash can't be written while the second core runs code from it, so that
+/// core is parked for the duration of each erase and write.
+impl<'a> From<FlashResources<'a>> for FlashStorage<'a> {
+    fn from(res: FlashResources<'a>) -> Self {
+        FlashStorage::new(res.flash).multicore_auto_park()
     }
 }
 
-impl<'a> From<LedResources<'a>> for esp_hal::rmt::Channel<'a, Async, Tx> {
-    fn from(res: LedResources<'a>) -> Self {
-        let _ws_power = Output::new(res.power, Level::High, OutputConfig::default());
-        let rmt = Rmt::new(res.rmt, Rate::from_mhz(40)).unwrap().into_async();
+/// The bare RMT channel, for driving the LEDs with another crate. The LED
+/// supply is switched on for good, since the channel can't hold the pin;
+/// use [`Leds`] to have it switched off while the LEDs are dark.
+impl<'a> TryFrom<LedResources<'a>> for esp_hal::rmt::Channel<'a, Blocking, Tx> {
+    type Error = LedError;
+
+    fn try_from(res: LedResources<'a>) -> Result<Self, LedError> {
+        let rmt = Rmt::new(res.rmt, Rate::from_mhz(40)).map_err(LedError::Config)?;
         let tx_config = TxChannelConfig::default().with_clk_divider(1);
-        rmt.channel0.configure_tx(res.io, tx_config).unwrap()
+        let channel = rmt
+            .channel0
+            .configure_tx(res.io, tx_config)
+            .map_err(LedError::Config)?;
+        // The driver has no `Drop`: the pin stays driven high once it goes.
+        Output::new(res.power, Level::High, OutputConfig::default());
+        Ok(channel)
     }
 }
 
-impl<'a> From<LedResources<'a>> for Leds<'a> {
-    fn from(res: LedResources<'a>) -> Self {
-        Leds::new(res.into())
+/// The bare RMT channel in async mode, with the LED supply switched on for
+/// good as for the blocking one.
+impl<'a> TryFrom<LedResources<'a>> for esp_hal::rmt::Channel<'a, Async, Tx> {
+    type Error = LedError;
+
+    fn try_from(res: LedResources<'a>) -> Result<Self, LedError> {
+        let rmt = Rmt::new(res.rmt, Rate::from_mhz(40))
+            .map_err(LedError::Config)?
+            .into_async();
+        let tx_config = TxChannelConfig::default().with_clk_divider(1);
+        let channel = rmt
+            .channel0
+            .configure_tx(res.io, tx_config)
+            .map_err(LedError::Config)?;
+        // The driver has no `Drop`: the pin stays driven high once it goes.
+        Output::new(res.power, Level::High, OutputConfig::default());
+        Ok(channel)
     }
 }
@@ -5,29 +5,66 @@
 //! - SD (serial data / DIN) on GPIO3
 //! - DIO (bit clock / BCLK) on GPIO46
 //!
-//! Uses DMA for efficient sample capture.
+//! Uses DMA for efficient sample capture. Read blocks of samples directly
+//! from [`Microphone::rx`], or [`stream`](Microphone::stream) them without
+//! gaps.
 
+pub mod dsp;
+pub mod host;
+pub mod reactive;
+pub mod spectrogram;
+
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
 use esp_hal::{
     Blocking,
-    dma::DmaDescriptor,
+    dma::{
+        DmaDescriptor,
+        DmaError,
+        DmaTransferRxCircular,
+    },
     i2s::master::{
         Channels,
         Config,
         DataFormat,
+        Error,
         I2s,
         I2sRx,
     },
     time::Rate,
 };
 
+use self::dsp::NoiseFloor;
 use crate::MicResources;
 
 /// Default sample rate for the microphone (16 kHz).
 pub const DEFAULT_SAMPLE_RATE: u32 = 16_000;
 
+/// Size of the ring buffer [`Microphone::stream`] records into, in 32-bit
+/// words: 2040 samples, about 128 ms at [`DEFAULT_SAMPLE_RATE`].
+///
+/// Splits into three equal DMA descriptors, so the microphone needs at
+/// least three.
+pub const STREAM_RING_WORDS: usize = 1020;
+
+/// Room in a [`MicStream`] for a ring's worth of new samples on top of
+/// almost a whole chunk left over.
+const STAGED_SAMPLES: usize = STREAM_RING_WORDS * 4;
+
+/// Pole of the DC blocker turned on by [`MicProcessing::with_dc_block`]:
+/// cuts below about 12 Hz at [`DEFAULT_SAMPLE_RATE`].
+pub const DC_BLOCK_POLE: f32 = 0.995;
+
+/// How often a [`MicStream`] checks for new samples while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
 /// I2S microphone, ready for DMA reads.
 pub struct Microphone<'a> {
     pub rx: I2sRx<'a, Blocking>,
+    sample_rate: u32,
 }
 
 impl<'a> Microphone<'a> {
@@ -58,6 +95,373 @@ impl<'a> Microphone<'a> {
             .with_din(res.dio)
             .build(descriptors);
 
-        Self { rx }
+        Self { rx, sample_rate }
+    }
+
+    /// Sample rate in Hz, as passed to [`new`](Self::new).
+    pub const fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Read one block and stir it into the [`rng`](crate::rng) pool, e.g. at
+    /// startup for firmware that doesn't otherwise use the microphone.
+    pub fn gather_entropy(&mut self) -> Result<(), Error> {
+        let mut block = [0i16; 256];
+        self.rx.read_words(&mut block)?;
+        crate::add_entropy(&block);
+        Ok(())
+    }
+
+    /// Record continuously into `ring` and read it back in chunks of `N`
+    /// samples with [`MicStream::next`].
+    ///
+    /// `N` can be up to a ring's worth, 2040 samples.
+    ///
+    /// ```rust,ignore
+    /// let ring = mk_static!([u32; STREAM_RING_WORDS], [0; STREAM_RING_WORDS]);
+    /// let mut stream = mic.stream::<256>(ring)?;
+    /// loop {
+    ///     match stream.next().await {
+    ///         Ok(chunk) => process(chunk),
+    ///         Err(StreamError::Overrun) => warn!("dropped audio"),
+    ///         Err(err) => error!("mic failed: {}", err),
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// Recording stops when the stream is dropped.
+    pub fn stream<'s, const N: usize>(
+        &'s mut self,
+        ring: &'s mut [u32; STREAM_RING_WORDS],
+    ) -> Result<MicStream<'s, 'a, N>, Error> {
+        const { assert!(N > 0 && N <= STREAM_RING_WORDS * 2, "N must be 1 to 2040") };
+        Ok(MicStream {
+            transfer: self.rx.read_dma_circular(ring)?,
+            staged: [0; STAGED_SAMPLES],
+            start: 0,
+            end: 0,
+            processing: None,
+            sample_rate: self.sample_rate,
+            started: Instant::now(),
+            next_sample: 0,
+        })
+    }
+}
+
+/// Why [`MicStream::next`] returned no chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum StreamError {
+    /// The ring filled up before it was read, and was thrown away. Recording
+    /// carries on; the next chunk starts with fresh samples.
+    Overrun,
+    /// The DMA transfer failed.
+    Dma(DmaError),
+}
+
+/// When the first sample of a chunk from [`MicStream::next_timed`] was
+/// recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct ChunkTime {
+    /// Samples recorded before it since the stream started, counting any
+    /// lost to an [`Overrun`](StreamError::Overrun).
+    pub sample: u64,
+    /// Derived from `sample`, so successive chunks are exactly `N` samples
+    /// apart rather than jittering with when they were read.
+    pub at: Instant,
+}
+
+/// Samples from [`Microphone::stream`], `N` at a time.
+///
+/// Chunks are lent straight out of the buffer the DMA ring is emptied into,
+/// and processed there, so each sample is copied once on its way in and
+/// never again.
+pub struct MicStream<'s, 'a, const N: usize> {
+    transfer: DmaTransferRxCircular<'s, I2sRx<'a, Blocking>>,
+    /// Samples taken off the ring, not yet handed out.
+    staged: [i16; STAGED_SAMPLES],
+    /// Byte offsets into `staged`. `start` is always even, but a sample can
+    /// arrive half at a time.
+    start: usize,
+    end: usize,
+    processing: Option<MicProcessing>,
+    sample_rate: u32,
+    /// When the first sample was recorded.
+    started: Instant,
+    /// Index of the next sample to go into `chunk`.
+    next_sample: u64,
+}
+
+impl<const N: usize> MicStream<'_, '_, N> {
+    /// Run each chunk through `processing` before handing it out.
+    #[must_use]
+    pub fn with_processing(mut self, processing: MicProcessing) -> Self {
+        self.processing = Some(processing);
+        self
+    }
+
+    /// Listen for `duration` and measure how loud the room is, e.g. at
+    /// startup. The samples heard meanwhile are used up.
+    ///
+    /// Best with [`MicProcessing::with_dc_block`], so the microphone's bias
+    /// doesn't count as noise.
+    pub async fn calibrate_noise_floor(
+        &mut self,
+        duration: Duration,
+    ) -> Result<NoiseFloor, StreamError> {
+        let end = Instant::now() + duration;
+        let mut floor = NoiseFloor::new();
+        while Instant::now() < end {
+            match self.next().await {
+                Ok(chunk) => floor.update(chunk),
+                Err(StreamError::Overrun) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(floor)
+    }
+
+    /// The next `N` samples, waiting until they have been recorded.
+    ///
+    /// Call it at least every 128 ms or so (see [`STREAM_RING_WORDS`]) to
+    /// keep up.
+    ///
+    /// The chunk is borrowed from the stream, so copy it out to keep it past
+    /// the next call.
+    pub async fn next(&mut self) -> Result<&[i16; N], StreamError> {
+        self.next_timed().await.map(|(chunk, _)| chunk)
+    }
+
+    /// [`next`](Self::next), along with when the chunk was recorded, e.g. to
+    /// line a beat up with the display frame or LED animation it belongs
+    /// to.
+    ///
+    /// Times follow the microphone's sample clock from when the stream
+    /// started, so they are as exact as the sample rate; after an
+    /// [`Overrun`](StreamError::Overrun) they pick up from the system time.
+    pub async fn next_timed(&mut self) -> Result<(&[i16; N], ChunkTime), StreamError> {
+        while self.end - self.start < N * 2 {
+            let staged = staged_bytes(&mut self.staged);
+            if self.start > 0 {
+                // Move what's left of the last read, even an odd byte, to
+                // the front to make room.
+                staged.copy_within(self.start..self.end, 0);
+                self.end -= self.start;
+                self.start = 0;
+            }
+            match self.transfer.pop(&mut staged[self.end..]) {
+                Ok(0) => Timer::after(POLL_INTERVAL).await,
+                Ok(read) => self.end += read,
+                Err(DmaError::Late) => {
+                    // Give every descriptor back to the DMA and start over.
+                    let _ = self.transfer.pop(staged);
+                    self.end = 0;
+                    // The next sample is the one being recorded now.
+                    let elapsed = self.started.elapsed().as_micros();
+                    self.next_sample = elapsed * u64::from(self.sample_rate) / 1_000_000;
+                    return Err(StreamError::Overrun);
+                }
+                Err(err) => return Err(StreamError::Dma(err)),
+            }
+        }
+
+        let first = self.start / 2;
+        self.start += N * 2;
+        let time = self.time_of(self.next_sample);
+        self.next_sample += N as u64;
+        let chunk = &mut self.staged[first..first + N];
+        crate::add_entropy(chunk);
+        if let Some(processing) = &mut self.processing {
+            processing.process(chunk);
+        }
+        let chunk = self.staged[first..]
+            .first_chunk()
+            .expect("a whole chunk is staged");
+        Ok((chunk, time))
+    }
+
+    /// Sample rate in Hz of the [`Microphone`] being streamed.
+    pub const fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn time_of(&self, sample: u64) -> ChunkTime {
+        let micros = sample * 1_000_000 / u64::from(self.sample_rate.max(1));
+        ChunkTime {
+            sample,
+            at: self.started + Duration::from_micros(micros),
+        }
     }
 }
+
+/// `staged` as the bytes the DMA delivers, little-endian like the CPU.
+fn staged_bytes(staged: &mut [i16; STAGED_SAMPLES]) -> &mut [u8] {
+    let len = core::mem::size_of_val(staged);
+    // SAFETY: the bytes cover exactly the samples, `u8` needs no alignment
+    // and any bytes make valid `i16`s, so both views stay sound; the
+    // returned slice borrows `staged` mutably, so they are never live at
+    // once.
+    unsafe { core::slice::from_raw_parts_mut(staged.as_mut_ptr().cast::<u8>(), len) }
+}
+
+/// Clean-up for raw microphone samples: removes the DC bias, then applies
+/// gain, clipping at full scale.
+///
+/// Hand it to [`MicStream::with_processing`], or call
+/// [`process`](Self::process) on blocks read from [`Microphone::rx`]:
+///
+/// ```rust,ignore
+/// let mut processing = MicProcessing::new().with_dc_block().with_gain(4.0);
+/// mic.rx.read_words(&mut buf)?;
+/// processing.process(&mut buf);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MicProcessing {
+    dc_pole: Option<f32>,
+    gain: f32,
+    last_in: f32,
+    last_out: f32,
+}
+
+impl Default for MicProcessing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MicProcessing {
+    /// Samples pass through unchanged.
+    pub const fn new() -> Self {
+        Self {
+            dc_pole: None,
+            gain: 1.0,
+            last_in: 0.0,
+            last_out: 0.0,
+        }
+    }
+
+    /// Remove the DC bias with a one-pole high-pass filter at
+    /// [`DC_BLOCK_POLE`].
+    #[must_use]
+    pub const fn with_dc_block(mut self) -> Self {
+        self.dc_pole = Some(DC_BLOCK_POLE);
+        self
+    }
+
+    /// Multiply samples by `gain` after the DC blocker. Results beyond the
+    /// `i16` range clip.
+    #[must_use]
+    pub const fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Process `samples` in place. The filter carries over from one call to
+    /// the next, so pass consecutive blocks in order.
+    pub fn process(&mut self, samples: &mut [i16]) {
+        for sample in samples {
+            let mut x = f32::from(*sample);
+            if let Some(pole) = self.dc_pole {
+                let y = x - self.last_in + pole * self.last_out;
+                self.last_in = x;
+                self.last_out = y;
+                x = y;
+            }
+            *sample = (x * self.gain).clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16;
+        }
+    }
+}
+
+// ── Level meter ─────────────────────────────────────────────────────────
+
+/// Lowest level [`LevelMeter`] reports, for silence.
+pub const SILENCE_DBFS: f32 = -96.0;
+
+/// Loudness of the audio seen by a [`LevelMeter`], relative to a full-scale
+/// sample (0 dBFS).
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Level {
+    /// Smoothed RMS, as in how loud it sounds.
+    pub rms_dbfs: f32,
+    /// Smoothed highest sample, to check for clipping.
+    pub peak_dbfs: f32,
+}
+
+impl Level {
+    /// How far [`rms_dbfs`](Self::rms_dbfs) is from `floor_dbfs` towards
+    /// 0 dBFS, from 0.0 to 1.0, e.g. for a bar:
+    ///
+    /// ```rust,ignore
+    /// let fill = meter.update(&chunk).fraction(-60.0);
+    /// bar.show(leds, (fill * 100.0) as u32, 100);
+    /// ```
+    pub fn fraction(self, floor_dbfs: f32) -> f32 {
+        ((self.rms_dbfs - floor_dbfs) / -floor_dbfs).clamp(0.0, 1.0)
+    }
+}
+
+/// RMS and peak levels of successive blocks of samples, smoothed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelMeter {
+    attack: f32,
+    release: f32,
+    /// Mean square, as a fraction of full scale squared.
+    power: f32,
+    /// As a fraction of full scale.
+    peak: f32,
+}
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LevelMeter {
+    /// A quick rise and a slower fall, starting from silence.
+    pub const fn new() -> Self {
+        Self {
+            attack: 0.7,
+            release: 0.2,
+            power: 0.0,
+            peak: 0.0,
+        }
+    }
+
+    /// How far the levels move towards a louder (`attack`) or quieter
+    /// (`release`) block each update, from 0.0 (never) to 1.0 (at once).
+    #[must_use]
+    pub const fn with_smoothing(mut self, attack: f32, release: f32) -> Self {
+        self.attack = attack;
+        self.release = release;
+        self
+    }
+
+    /// Take in the next block of samples and return the new level.
+    pub fn update(&mut self, samples: &[i16]) -> Level {
+        if !samples.is_empty() {
+            let full = f32::from(i16::MAX);
+            let (sum, peak) = samples.iter().fold((0.0f32, 0u16), |(sum, peak), &s| {
+                let x = f32::from(s) / full;
+                (sum + x * x, peak.max(s.unsigned_abs()))
+            });
+            let power = sum / samples.len() as f32;
+            let peak = (f32::from(peak) / full).min(1.0);
+            smooth(&mut self.power, power, self.attack, self.release);
+            smooth(&mut self.peak, peak, self.attack, self.release);
+        }
+        self.level()
+    }
+
+    /// The level as of the last [`update`](Self::update).
+    pub fn level(&self) -> Level {
+        Level {
+            rms_dbfs: (10.0 * libm::log10f(self.power)).max(SILENCE_DBFS),
+            peak_dbfs: (20.0 * libm::log10f(self.peak)).max(SILENCE_DBFS),
+        }
+    }
+}
+
+fn smooth(value: &mut f32, target: f32, attack: f32, release: f32) {
+    let rate = if target > *value { attack } else { release };
+    *value += (target - *value) * rate.clamp(0.0, 1.0);
+}
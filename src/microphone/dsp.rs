@@ -0,0 +1,1296 @@
+//! Spectrum analysis for microphone samples, in fixed point and without
+//! allocation.
+//!
+//! [`Fft`] windows a block of samples and transforms it; the [`Spectrum`]
+//! it returns gives the power per bin or summed over frequency bands:
+//!
+//! ```rust,ignore
+//! let fft = Fft::<256>::new();
+//! let mut bands = [0; 5];
+//! loop {
+//!     let chunk = stream.next().await?;
+//!     fft.process(chunk).bands(&[60, 250, 500, 2000, 4000, 8000], DEFAULT_SAMPLE_RATE, &mut bands);
+//!     // …
+//! }
+//! ```
+//!
+//! For a few known frequencies, [`Goertzel`] filters are cheaper than an
+//! FFT; [`ToneDetector`] and [`DtmfDecoder`] build on them.
+//!
+//! [`BeatDetector`] picks onsets out of the block energies,
+//! [`ClapDetector`] hand claps and [`VoiceDetector`] speech. A measured
+//! [`NoiseFloor`] lets them adapt to the room. [`WhistleControl`] turns a
+//! whistled note into a steering input.
+//!
+//! [`Biquad`] filters and [`MovingAverage`]s shape the signal first, and a
+//! [`Decimator`] lowers the sample rate so they have less to do.
+
+use core::f32::consts::TAU;
+
+use embassy_time::Duration;
+
+/// Full scale of the Q15 window and twiddle factors.
+const Q15: f32 = 32767.0;
+
+/// A radix-2 FFT of `N` samples with a Hann window.
+///
+/// `N` must be a power of two from 8 to 4096; 128, 256 and 512 suit
+/// [`DEFAULT_SAMPLE_RATE`](super::DEFAULT_SAMPLE_RATE) well. Each stage
+/// halves its results so nothing overflows, which leaves a full-scale sine
+/// at about a quarter of full scale in its bin.
+pub struct Fft<const N: usize> {
+    /// `sin(2πk/N)` for each `k`, in Q15.
+    sines: [i16; N],
+    /// In Q15.
+    window: [i16; N],
+}
+
+impl<const N: usize> Default for Fft<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Fft<N> {
+    pub fn new() -> Self {
+        const { assert!(N.is_power_of_two() && N >= 8 && N <= 4096) };
+        Self {
+            sines: core::array::from_fn(|k| (libm::sinf(TAU * k as f32 / N as f32) * Q15) as i16),
+            window: core::array::from_fn(|i| {
+                ((0.5 - 0.5 * libm::cosf(TAU * i as f32 / N as f32)) * Q15) as i16
+            }),
+        }
+    }
+
+    /// Window `samples` and transform them.
+    pub fn process(&self, samples: &[i16; N]) -> Spectrum<N> {
+        let shift = usize::BITS - N.trailing_zeros();
+        let mut re: [i32; N] = core::array::from_fn(|i| {
+            let j = i.reverse_bits() >> shift;
+            (i32::from(samples[j]) * i32::from(self.window[j])) >> 15
+        });
+        let mut im = [0i32; N];
+
+        let mut len = 2;
+        while len <= N {
+            let half = len / 2;
+            let step = N / len;
+            for start in (0..N).step_by(len) {
+                for k in 0..half {
+                    // e^(−2πik/len), with cos(x) = sin(x + π/2).
+                    let w_re = i32::from(self.sines[(k * step + N / 4) % N]);
+                    let w_im = -i32::from(self.sines[k * step]);
+                    let (a, b) = (start + k, start + k + half);
+                    let t_re = (re[b] * w_re - im[b] * w_im) >> 15;
+                    let t_im = (re[b] * w_im + im[b] * w_re) >> 15;
+                    (re[a], re[b]) = ((re[a] + t_re) >> 1, (re[a] - t_re) >> 1);
+                    (im[a], im[b]) = ((im[a] + t_im) >> 1, (im[a] - t_im) >> 1);
+                }
+            }
+            len *= 2;
+        }
+
+        Spectrum {
+            re: re.map(|v| v as i16),
+            im: im.map(|v| v as i16),
+        }
+    }
+}
+
+/// The output of [`Fft::process`]. Bins `0..N / 2` run from DC up to just
+/// below half the sample rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spectrum<const N: usize> {
+    re: [i16; N],
+    im: [i16; N],
+}
+
+impl<const N: usize> Spectrum<N> {
+    /// Number of useful bins, `N / 2`.
+    pub const BINS: usize = N / 2;
+
+    /// The complex value of `bin`.
+    pub const fn bin(&self, bin: usize) -> (i16, i16) {
+        (self.re[bin], self.im[bin])
+    }
+
+    /// Squared magnitude of `bin`.
+    pub const fn power(&self, bin: usize) -> u32 {
+        let (re, im) = (self.re[bin] as i32, self.im[bin] as i32);
+        (re * re) as u32 + (im * im) as u32
+    }
+
+    pub const fn magnitude(&self, bin: usize) -> u16 {
+        self.power(bin).isqrt() as u16
+    }
+
+    /// Centre frequency of `bin` when sampled at `sample_rate` Hz.
+    pub const fn bin_hz(bin: usize, sample_rate: u32) -> u32 {
+        (bin as u64 * sample_rate as u64 / N as u64) as u32
+    }
+
+    /// Total power of the bins from `low_hz` up to, not including,
+    /// `high_hz`.
+    pub fn band_energy(&self, low_hz: u32, high_hz: u32, sample_rate: u32) -> u32 {
+        let bin_of = |hz: u32| {
+            ((u64::from(hz) * N as u64).div_ceil(u64::from(sample_rate.max(1))) as usize)
+                .min(Self::BINS)
+        };
+        (bin_of(low_hz)..bin_of(high_hz)).fold(0u32, |sum, bin| sum.saturating_add(self.power(bin)))
+    }
+
+    /// [`band_energy`](Self::band_energy) of each band between neighbouring
+    /// `edges_hz`, into `out`. Stops at whichever runs out first.
+    pub fn bands(&self, edges_hz: &[u32], sample_rate: u32, out: &mut [u32]) {
+        for (energy, edges) in out.iter_mut().zip(edges_hz.windows(2)) {
+            *energy = self.band_energy(edges[0], edges[1], sample_rate);
+        }
+    }
+}
+
+// ── Tones ───────────────────────────────────────────────────────────────
+
+/// DTMF row frequencies, top row of the keypad first.
+pub const DTMF_ROWS_HZ: [u32; 4] = [697, 770, 852, 941];
+/// DTMF column frequencies, left column first.
+pub const DTMF_COLUMNS_HZ: [u32; 4] = [1209, 1336, 1477, 1633];
+const DTMF_KEYS: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+/// Measures one frequency in a block of samples: one bin of a DFT, for a
+/// handful of multiplies per sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Goertzel {
+    coeff: f32,
+}
+
+impl Goertzel {
+    /// A filter for `hz` in samples taken at `sample_rate` Hz.
+    ///
+    /// It picks out roughly `sample_rate / block length` Hz around `hz`, so
+    /// telling close tones apart takes long blocks.
+    pub fn new(hz: u32, sample_rate: u32) -> Self {
+        Self {
+            coeff: 2.0 * libm::cosf(TAU * hz as f32 / sample_rate.max(1) as f32),
+        }
+    }
+
+    /// Magnitude of the frequency in `samples`; a sine of amplitude `a`
+    /// comes out at `a · samples.len() / 2`.
+    pub fn magnitude(&self, samples: &[i16]) -> f32 {
+        self.run(samples.iter().map(|&s| f32::from(s)))
+    }
+
+    /// [`magnitude`](Self::magnitude) with each sample first scaled by the
+    /// matching `window` value, to keep neighbouring frequencies out.
+    pub fn magnitude_windowed(&self, samples: &[i16], window: &[f32]) -> f32 {
+        self.run(samples.iter().zip(window).map(|(&s, &w)| f32::from(s) * w))
+    }
+
+    /// Amplitude of the frequency in `samples`, as a fraction of full scale.
+    pub fn amplitude(&self, samples: &[i16]) -> f32 {
+        2.0 * self.magnitude(samples) / (samples.len().max(1) as f32 * f32::from(i16::MAX))
+    }
+
+    fn run(&self, samples: impl Iterator<Item = f32>) -> f32 {
+        let (mut s1, mut s2) = (0.0f32, 0.0f32);
+        for x in samples {
+            let s0 = x + self.coeff * s1 - s2;
+            s2 = s1;
+            s1 = s0;
+        }
+        // Rounding can take the power a hair below zero.
+        libm::sqrtf((s1 * s1 + s2 * s2 - self.coeff * s1 * s2).max(0.0))
+    }
+}
+
+/// Listens for any of `K` tones, e.g. pilot tones played from a phone.
+///
+/// ```rust,ignore
+/// let tones = ToneDetector::new([1000, 1500, 2000], DEFAULT_SAMPLE_RATE);
+/// if let Some(tone) = tones.detect(stream.next().await?) {
+///     show_page(tone);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneDetector<const K: usize> {
+    filters: [Goertzel; K],
+    min_amplitude: f32,
+    purity: f32,
+}
+
+impl<const K: usize> ToneDetector<K> {
+    /// Tones `tones_hz` in samples taken at `sample_rate` Hz.
+    pub fn new(tones_hz: [u32; K], sample_rate: u32) -> Self {
+        Self {
+            filters: tones_hz.map(|hz| Goertzel::new(hz, sample_rate)),
+            min_amplitude: 0.01,
+            purity: 0.5,
+        }
+    }
+
+    /// Quietest tone heard, as a fraction of full scale. 0.01 by default.
+    #[must_use]
+    pub const fn with_min_amplitude(mut self, min_amplitude: f32) -> Self {
+        self.min_amplitude = min_amplitude;
+        self
+    }
+
+    /// Share of the block's energy the tone must carry, from 0.0 to 1.0, so
+    /// loud music or speech doesn't set it off. 0.5 by default.
+    #[must_use]
+    pub const fn with_purity(mut self, purity: f32) -> Self {
+        self.purity = purity;
+        self
+    }
+
+    /// Amplitude of each tone in `samples`, as a fraction of full scale.
+    pub fn levels(&self, samples: &[i16]) -> [f32; K] {
+        self.filters.map(|filter| filter.amplitude(samples))
+    }
+
+    /// Index of the loudest tone in `samples`, if it's loud and clean
+    /// enough.
+    pub fn detect(&self, samples: &[i16]) -> Option<usize> {
+        let (index, amplitude) = self
+            .levels(samples)
+            .into_iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        (amplitude >= self.min_amplitude
+            && tone_share(amplitude, mean_square(samples)) >= self.purity)
+            .then_some(index)
+    }
+}
+
+/// Decodes touch-tone keypad presses.
+///
+/// Feed it blocks of at least 256 samples at 16 kHz, so the tones can be
+/// told apart. A key is reported once when it has been heard in two blocks
+/// in a row, and again only after it stops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DtmfDecoder {
+    rows: ToneDetector<4>,
+    columns: ToneDetector<4>,
+    /// Key heard in the last block, and whether it was reported yet.
+    last: Option<(char, bool)>,
+}
+
+impl DtmfDecoder {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            rows: ToneDetector::new(DTMF_ROWS_HZ, sample_rate),
+            columns: ToneDetector::new(DTMF_COLUMNS_HZ, sample_rate),
+            last: None,
+        }
+    }
+
+    /// Take in the next block of samples; returns `'0'`–`'9'`, `'*'`, `'#'`
+    /// or `'A'`–`'D'` as a key is first heard.
+    pub fn update(&mut self, samples: &[i16]) -> Option<char> {
+        let key = self.key(samples);
+        match (self.last, key) {
+            (Some((last, reported)), Some(key)) if last == key => {
+                self.last = Some((key, true));
+                (!reported).then_some(key)
+            }
+            _ => {
+                self.last = key.map(|key| (key, false));
+                None
+            }
+        }
+    }
+
+    fn key(&self, samples: &[i16]) -> Option<char> {
+        let strongest = |levels: [f32; 4]| {
+            levels
+                .into_iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap_or((0, 0.0))
+        };
+        let (row, row_level) = strongest(self.rows.levels(samples));
+        let (column, column_level) = strongest(self.columns.levels(samples));
+        let min = self.rows.min_amplitude;
+        // The two tones may differ by up to "twist" of 8 dB, about 2.5×.
+        let balanced = row_level < column_level * 2.5 && column_level < row_level * 2.5;
+        let energy = mean_square(samples);
+        let clean =
+            tone_share(row_level, energy) + tone_share(column_level, energy) >= self.rows.purity;
+        (row_level >= min && column_level >= min && balanced && clean)
+            .then(|| DTMF_KEYS[row][column])
+    }
+}
+
+/// Mean square of `samples`, as a fraction of full scale squared.
+fn mean_square(samples: &[i16]) -> f32 {
+    let full = f32::from(i16::MAX);
+    samples.iter().fold(0.0f32, |sum, &s| {
+        let x = f32::from(s) / full;
+        sum + x * x
+    }) / samples.len().max(1) as f32
+}
+
+/// Share of `energy` (a mean square) in a sine of `amplitude`.
+fn tone_share(amplitude: f32, energy: f32) -> f32 {
+    amplitude * amplitude / 2.0 / energy.max(f32::MIN_POSITIVE)
+}
+
+// ── Noise floor ─────────────────────────────────────────────────────────
+
+/// How loud a room is when nothing in particular is happening, e.g. from
+/// [`MicStream::calibrate_noise_floor`](super::MicStream::calibrate_noise_floor).
+///
+/// Hand it to the detectors so they work the same in a quiet room and a
+/// loud hall, or use [`dbfs`](Self::dbfs) as the bottom of a level meter.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct NoiseFloor {
+    mean_square: f32,
+    blocks: u32,
+}
+
+impl Default for NoiseFloor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoiseFloor {
+    /// Nothing measured yet; reads as silence.
+    pub const fn new() -> Self {
+        Self {
+            mean_square: 0.0,
+            blocks: 0,
+        }
+    }
+
+    /// Take in another block of ambient sound.
+    ///
+    /// Blocks far louder than the estimate so far, like a door slamming,
+    /// are left out.
+    pub fn update(&mut self, samples: &[i16]) {
+        if samples.is_empty() {
+            return;
+        }
+        let energy = mean_square(samples);
+        if self.blocks == 0 {
+            self.mean_square = energy;
+        } else if energy < self.mean_square * 4.0 {
+            self.mean_square += (energy - self.mean_square) * 0.2;
+        }
+        self.blocks = self.blocks.saturating_add(1);
+    }
+
+    /// Mean square energy, as a fraction of full scale squared.
+    pub const fn mean_square(&self) -> f32 {
+        self.mean_square
+    }
+
+    pub fn dbfs(&self) -> f32 {
+        (10.0 * libm::log10f(self.mean_square)).max(super::SILENCE_DBFS)
+    }
+}
+
+// ── Beat detection ──────────────────────────────────────────────────────
+
+/// Blocks of energy [`BeatDetector`] compares each new block against:
+/// about half a second of 256-sample chunks at
+/// [`DEFAULT_SAMPLE_RATE`](super::DEFAULT_SAMPLE_RATE).
+pub const BEAT_HISTORY: usize = 32;
+
+/// An onset found by [`BeatDetector`].
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Beat {
+    /// The block's energy over the recent average; 1.0 would be no louder.
+    pub strength: f32,
+}
+
+/// Finds onsets, such as drum hits, as blocks much louder than the ones
+/// before.
+///
+/// A block counts as a beat when its energy is more than
+/// [`with_sensitivity`](Self::with_sensitivity) standard deviations above
+/// the mean of the last [`BEAT_HISTORY`] blocks, so the threshold follows
+/// the music's loudness. Feed it every block in order:
+///
+/// ```rust,ignore
+/// let mut beats = BeatDetector::new();
+/// loop {
+///     let chunk = stream.next().await?;
+///     // Kick drums stand out best on their own.
+///     let bass = fft.process(chunk).band_energy(40, 150, DEFAULT_SAMPLE_RATE);
+///     if let Some(beat) = beats.update_energy(bass as f32) {
+///         LED_NOTIFIER.notify(pulse(beat.strength));
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BeatDetector {
+    history: [f32; BEAT_HISTORY],
+    /// Blocks seen, up to [`BEAT_HISTORY`].
+    seen: usize,
+    next: usize,
+    since_beat: u16,
+    sensitivity: f32,
+    min_ratio: f32,
+    refractory: u16,
+    noise_floor: f32,
+}
+
+impl Default for BeatDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BeatDetector {
+    pub const fn new() -> Self {
+        Self {
+            history: [0.0; BEAT_HISTORY],
+            seen: 0,
+            next: 0,
+            since_beat: u16::MAX,
+            sensitivity: 1.5,
+            min_ratio: 1.3,
+            refractory: 4,
+            noise_floor: 1e-5,
+        }
+    }
+
+    /// Standard deviations above the recent mean a block must reach. Lower
+    /// finds more beats.
+    #[must_use]
+    pub const fn with_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    /// Blocks after a beat before another can be found, so one hit doesn't
+    /// count twice.
+    #[must_use]
+    pub const fn with_refractory(mut self, blocks: u16) -> Self {
+        self.refractory = blocks;
+        self
+    }
+
+    /// Ignore blocks in [`update`](Self::update) less than twice as loud as
+    /// `floor`, so room noise finds nothing.
+    #[must_use]
+    pub const fn with_noise_floor(mut self, floor: NoiseFloor) -> Self {
+        self.noise_floor = floor.mean_square * 2.0;
+        self
+    }
+
+    /// Take in the next block of samples.
+    pub fn update(&mut self, samples: &[i16]) -> Option<Beat> {
+        if samples.is_empty() {
+            return None;
+        }
+        let full = f32::from(i16::MAX);
+        let sum = samples.iter().fold(0.0f32, |sum, &s| {
+            let x = f32::from(s) / full;
+            sum + x * x
+        });
+        let energy = sum / samples.len() as f32;
+        if energy < self.noise_floor {
+            self.since_beat = self.since_beat.saturating_add(1);
+            self.push(energy);
+            return None;
+        }
+        self.update_energy(energy)
+    }
+
+    /// Take in the energy of the next block, in any unit as long as it
+    /// stays the same, e.g. a [`Spectrum::band_energy`].
+    pub fn update_energy(&mut self, energy: f32) -> Option<Beat> {
+        let history = &self.history[..self.seen];
+        let beat = if self.seen == BEAT_HISTORY {
+            let n = BEAT_HISTORY as f32;
+            let mean = history.iter().sum::<f32>() / n;
+            let variance = history.iter().map(|e| (e - mean) * (e - mean)).sum::<f32>() / n;
+            let threshold =
+                (mean + self.sensitivity * libm::sqrtf(variance)).max(mean * self.min_ratio);
+            (energy > threshold && self.since_beat >= self.refractory).then(|| Beat {
+                strength: energy / mean.max(f32::MIN_POSITIVE),
+            })
+        } else {
+            None
+        };
+        self.since_beat = if beat.is_some() {
+            0
+        } else {
+            self.since_beat.saturating_add(1)
+        };
+        self.push(energy);
+        beat
+    }
+
+    fn push(&mut self, energy: f32) {
+        self.history[self.next] = energy;
+        self.next = (self.next + 1) % BEAT_HISTORY;
+        self.seen = (self.seen + 1).min(BEAT_HISTORY);
+    }
+}
+
+// ── Clap detection ──────────────────────────────────────────────────────
+
+/// Longest a sound can take to die away and still count as a clap.
+const CLAP_MAX_LENGTH: Duration = Duration::from_millis(150);
+
+/// What [`ClapDetector`] heard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ClapEvent {
+    Clap,
+    /// Two claps in quick succession, reported instead of two
+    /// [`Clap`](Self::Clap)s.
+    DoubleClap,
+}
+
+/// Picks out claps: sudden, loud sounds that die away quickly. Speech and
+/// music build up and hang around, so they don't count.
+///
+/// Feed it every block in order:
+///
+/// ```rust,ignore
+/// let mut claps = ClapDetector::new();
+/// loop {
+///     match claps.update(stream.next().await?) {
+///         Some(ClapEvent::Clap) => next_background(),
+///         Some(ClapEvent::DoubleClap) => toggle_lights(),
+///         None => {}
+///     }
+/// }
+/// ```
+///
+/// A single clap is only reported once the double-clap window has passed
+/// without a second one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClapDetector {
+    sample_rate: u32,
+    sensitivity: f32,
+    min_peak: u16,
+    double_window: Duration,
+    /// Smoothed energy of the room between claps.
+    background: f32,
+    /// Samples seen so far.
+    clock: u64,
+    /// Start and loudest energy of the sound being heard.
+    onset: Option<(u64, f32)>,
+    /// Waiting for a loud sound that was too long to end.
+    sustained: bool,
+    /// Start of a clap that may yet be the first of two.
+    pending: Option<u64>,
+}
+
+impl Default for ClapDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClapDetector {
+    pub const fn new() -> Self {
+        Self {
+            sample_rate: super::DEFAULT_SAMPLE_RATE,
+            sensitivity: 20.0,
+            min_peak: 4000,
+            double_window: Duration::from_millis(600),
+            background: 0.0,
+            clock: 0,
+            onset: None,
+            sustained: false,
+            pending: None,
+        }
+    }
+
+    /// The rate the samples were recorded at, if not
+    /// [`DEFAULT_SAMPLE_RATE`](super::DEFAULT_SAMPLE_RATE).
+    #[must_use]
+    pub const fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// How many times the room's background energy a clap must reach, 20 by
+    /// default. Lower catches quieter claps, and more false ones.
+    #[must_use]
+    pub const fn with_sensitivity(mut self, ratio: f32) -> Self {
+        self.sensitivity = ratio;
+        self
+    }
+
+    /// Quietest peak sample a clap can have, so a silent room doesn't make
+    /// every click a clap.
+    #[must_use]
+    pub const fn with_min_peak(mut self, min_peak: u16) -> Self {
+        self.min_peak = min_peak;
+        self
+    }
+
+    /// Start from the measured `floor` rather than learning the room from
+    /// the first few blocks.
+    #[must_use]
+    pub const fn with_noise_floor(mut self, floor: NoiseFloor) -> Self {
+        self.background = floor.mean_square;
+        self
+    }
+
+    /// Longest time from one clap to the next that makes a
+    /// [`ClapEvent::DoubleClap`].
+    #[must_use]
+    pub const fn with_double_clap_window(mut self, window: Duration) -> Self {
+        self.double_window = window;
+        self
+    }
+
+    /// Take in the next block of samples.
+    pub fn update(&mut self, samples: &[i16]) -> Option<ClapEvent> {
+        let full = f32::from(i16::MAX);
+        let (sum, peak) = samples.iter().fold((0.0f32, 0u16), |(sum, peak), &s| {
+            let x = f32::from(s) / full;
+            (sum + x * x, peak.max(s.unsigned_abs()))
+        });
+        let energy = sum / samples.len().max(1) as f32;
+        let now = self.clock;
+        self.clock += samples.len() as u64;
+        let loud = energy > self.background * self.sensitivity && peak >= self.min_peak;
+
+        let mut clap = false;
+        match self.onset {
+            Some((start, loudest)) => {
+                if energy < loudest / 10.0 {
+                    // Died away; a clap if it was quick about it.
+                    self.onset = None;
+                    clap = now - start <= self.samples(CLAP_MAX_LENGTH);
+                    self.sustained = !clap && loud;
+                } else if now - start > self.samples(CLAP_MAX_LENGTH) {
+                    self.onset = None;
+                    self.sustained = true;
+                } else {
+                    self.onset = Some((start, loudest.max(energy)));
+                }
+            }
+            None if self.sustained => self.sustained = loud,
+            None if loud => self.onset = Some((now, energy)),
+            None => self.background += (energy - self.background) * 0.05,
+        }
+
+        let window = self.samples(self.double_window);
+        match self.pending {
+            Some(first) if clap => {
+                self.pending = None;
+                if now - first <= window {
+                    return Some(ClapEvent::DoubleClap);
+                }
+                self.pending = Some(now);
+                Some(ClapEvent::Clap)
+            }
+            None if clap => {
+                self.pending = Some(now);
+                None
+            }
+            Some(first) if now - first > window && self.onset.is_none() => {
+                self.pending = None;
+                Some(ClapEvent::Clap)
+            }
+            _ => None,
+        }
+    }
+
+    /// `duration` in samples.
+    fn samples(&self, duration: Duration) -> u64 {
+        duration.as_micros() * u64::from(self.sample_rate) / 1_000_000
+    }
+}
+
+// ── Voice activity ──────────────────────────────────────────────────────
+
+/// How long speech-like sound must last to start speech, so a cough or a
+/// knock doesn't.
+const SPEECH_MIN_LENGTH: Duration = Duration::from_millis(60);
+
+/// Zero crossings per sample, around the block's mean, that speech falls
+/// between: hum crosses less, hiss more.
+const SPEECH_ZCR: (f32, f32) = (0.01, 0.3);
+
+/// A change reported by [`VoiceDetector`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum VoiceEvent {
+    SpeechStarted,
+    SpeechEnded,
+}
+
+/// Tells when someone is talking, from how loud each block is against the
+/// background and how often it crosses zero.
+///
+/// ```rust,ignore
+/// let mut voice = VoiceDetector::new();
+/// loop {
+///     match voice.update(stream.next().await?) {
+///         Some(VoiceEvent::SpeechStarted) => backlight.on(),
+///         Some(VoiceEvent::SpeechEnded) => backlight.off(),
+///         None => {}
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoiceDetector {
+    sample_rate: u32,
+    sensitivity: f32,
+    hangover: Duration,
+    /// Smoothed energy while nobody talks; `None` until the first block.
+    background: Option<f32>,
+    /// Samples seen so far.
+    clock: u64,
+    speaking: bool,
+    /// When the current run of speech-like (or, while speaking, quiet)
+    /// blocks began.
+    run_start: Option<u64>,
+}
+
+impl Default for VoiceDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VoiceDetector {
+    pub const fn new() -> Self {
+        Self {
+            sample_rate: super::DEFAULT_SAMPLE_RATE,
+            sensitivity: 4.0,
+            hangover: Duration::from_millis(400),
+            background: None,
+            clock: 0,
+            speaking: false,
+            run_start: None,
+        }
+    }
+
+    /// The rate the samples were recorded at, if not
+    /// [`DEFAULT_SAMPLE_RATE`](super::DEFAULT_SAMPLE_RATE).
+    #[must_use]
+    pub const fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// How many times the background energy speech must reach, 4 by
+    /// default. Lower hears quieter voices, and more noise.
+    #[must_use]
+    pub const fn with_sensitivity(mut self, ratio: f32) -> Self {
+        self.sensitivity = ratio;
+        self
+    }
+
+    /// Start from the measured `floor` rather than taking the first block as
+    /// the background.
+    #[must_use]
+    pub const fn with_noise_floor(mut self, floor: NoiseFloor) -> Self {
+        self.background = Some(floor.mean_square);
+        self
+    }
+
+    /// How long it must be quiet before speech ends, so pauses between
+    /// words don't end it.
+    #[must_use]
+    pub const fn with_hangover(mut self, hangover: Duration) -> Self {
+        self.hangover = hangover;
+        self
+    }
+
+    /// Whether someone is talking, as of the last block.
+    pub const fn is_speaking(&self) -> bool {
+        self.speaking
+    }
+
+    /// Take in the next block of samples.
+    pub fn update(&mut self, samples: &[i16]) -> Option<VoiceEvent> {
+        if samples.is_empty() {
+            return None;
+        }
+        let full = f32::from(i16::MAX);
+        let n = samples.len() as f32;
+        // Measured around the mean so a DC bias doesn't hide crossings.
+        let mean = samples.iter().map(|&s| f32::from(s)).sum::<f32>() / n;
+        let (sum, crossings, _) =
+            samples
+                .iter()
+                .fold((0.0f32, 0u32, false), |(sum, crossings, was_above), &s| {
+                    let x = (f32::from(s) - mean) / full;
+                    let above = x > 0.0;
+                    (
+                        sum + x * x,
+                        crossings + u32::from(above != was_above),
+                        above,
+                    )
+                });
+        let energy = sum / n;
+        let zcr = crossings as f32 / n;
+        let background = *self.background.get_or_insert(energy);
+
+        let voice_like =
+            energy > background * self.sensitivity && (SPEECH_ZCR.0..SPEECH_ZCR.1).contains(&zcr);
+        let now = self.clock;
+        self.clock += samples.len() as u64;
+
+        if !self.speaking && !voice_like {
+            self.background = Some(background + (energy - background) * 0.05);
+        }
+        // While quiet, time runs of speech; while speaking, runs of quiet.
+        if voice_like == self.speaking {
+            self.run_start = None;
+            return None;
+        }
+        let start = *self.run_start.get_or_insert(now);
+        let needed = if self.speaking {
+            self.hangover
+        } else {
+            SPEECH_MIN_LENGTH
+        };
+        if self.clock - start < self.samples(needed) {
+            return None;
+        }
+        self.run_start = None;
+        self.speaking = !self.speaking;
+        Some(if self.speaking {
+            VoiceEvent::SpeechStarted
+        } else {
+            VoiceEvent::SpeechEnded
+        })
+    }
+
+    /// `duration` in samples.
+    fn samples(&self, duration: Duration) -> u64 {
+        duration.as_micros() * u64::from(self.sample_rate) / 1_000_000
+    }
+}
+
+// ── Whistle control ─────────────────────────────────────────────────────
+
+/// Turns a whistled pitch into a control value, like a slider you sing
+/// into: e.g. 500 Hz steers hard left, 2000 Hz hard right.
+///
+/// Pitch maps on a log scale, so each octave covers the same share of the
+/// range, the way it feels to whistle. The value only moves when the pitch
+/// has moved by more than the hysteresis, and whistling has to start or
+/// stop for a few blocks in a row before it counts, so a wavering note
+/// doesn't make the value jitter.
+///
+/// ```rust,ignore
+/// let fft = Fft::<256>::new();
+/// let mut whistle = WhistleControl::new(500.0, 2000.0);
+/// loop {
+///     let spectrum = fft.process(stream.next().await?);
+///     if let Some(steer) = whistle.update(&spectrum, DEFAULT_SAMPLE_RATE) {
+///         ship.steer_to(steer);
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct WhistleControl {
+    low_hz: f32,
+    high_hz: f32,
+    min: f32,
+    max: f32,
+    hysteresis: f32,
+    min_amplitude: f32,
+    purity: f32,
+    sustain: u8,
+    /// Blocks in a row that disagreed with whether `value` is set.
+    run: u8,
+    value: Option<f32>,
+}
+
+impl WhistleControl {
+    /// Listen for whistles from `low_hz` to `high_hz`; below and above
+    /// clamp to the ends of the range.
+    pub const fn new(low_hz: f32, high_hz: f32) -> Self {
+        Self {
+            low_hz,
+            high_hz,
+            min: 0.0,
+            max: 100.0,
+            hysteresis: 2.0,
+            min_amplitude: 0.01,
+            purity: 0.5,
+            sustain: 3,
+            run: 0,
+            value: None,
+        }
+    }
+
+    /// Values `low_hz` and `high_hz` map to. 0 to 100 by default.
+    #[must_use]
+    pub const fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// How far, in output units, the pitch has to move before the value
+    /// follows. 2 by default.
+    #[must_use]
+    pub const fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Quietest whistle heard, as a fraction of full scale. 0.01 by
+    /// default.
+    #[must_use]
+    pub const fn with_min_amplitude(mut self, min_amplitude: f32) -> Self {
+        self.min_amplitude = min_amplitude;
+        self
+    }
+
+    /// Share of the energy between `low_hz` and `high_hz` the whistle must
+    /// carry, from 0.0 to 1.0, so speech and music don't steer. 0.5 by
+    /// default.
+    #[must_use]
+    pub const fn with_purity(mut self, purity: f32) -> Self {
+        self.purity = purity;
+        self
+    }
+
+    /// Blocks in a row a whistle has to be heard, or not heard, before it
+    /// starts or stops counting. 3 by default, about 50 ms of 256-sample
+    /// blocks at [`DEFAULT_SAMPLE_RATE`](super::DEFAULT_SAMPLE_RATE).
+    #[must_use]
+    pub const fn with_sustain(mut self, blocks: u8) -> Self {
+        self.sustain = blocks;
+        self
+    }
+
+    /// The control value while someone is whistling.
+    pub const fn value(&self) -> Option<f32> {
+        self.value
+    }
+
+    /// Take in the spectrum of the next block, returning the control
+    /// value if someone is whistling.
+    pub fn update<const N: usize>(
+        &mut self,
+        spectrum: &Spectrum<N>,
+        sample_rate: u32,
+    ) -> Option<f32> {
+        let heard = Self::pitch(spectrum, sample_rate, self.low_hz, self.high_hz)
+            .filter(|&(_, amplitude, share)| {
+                amplitude >= self.min_amplitude && share >= self.purity
+            })
+            .map(|(hz, _, _)| self.scale(hz));
+        if heard.is_some() == self.value.is_some() {
+            self.run = 0;
+        } else {
+            self.run = self.run.saturating_add(1);
+            if self.run < self.sustain {
+                return self.value;
+            }
+            self.run = 0;
+        }
+        self.value = match (self.value, heard) {
+            (Some(value), Some(target)) if (target - value).abs() <= self.hysteresis => Some(value),
+            (_, heard) => heard,
+        };
+        self.value
+    }
+
+    /// Pitch, amplitude and share of the energy of the loudest frequency
+    /// between `low_hz` and `high_hz`.
+    fn pitch<const N: usize>(
+        spectrum: &Spectrum<N>,
+        sample_rate: u32,
+        low_hz: f32,
+        high_hz: f32,
+    ) -> Option<(f32, f32, f32)> {
+        let bin_width = sample_rate as f32 / N as f32;
+        let first = ((low_hz / bin_width) as usize).max(1);
+        let last = ((high_hz / bin_width) as usize + 1).min(Spectrum::<N>::BINS - 1);
+        if first >= last {
+            return None;
+        }
+        let power = |bin: usize| spectrum.power(bin) as f32;
+        let (peak, peak_power) = (first..=last)
+            .map(|bin| (bin, power(bin)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        let total: f32 = (first..=last).map(power).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        // A windowed sine spreads over its bin and the two beside it.
+        let (before, after) = (power(peak - 1), power(peak + 1));
+        let share = (before + peak_power + after) / total;
+        // Fit a parabola through the three magnitudes to place the peak
+        // between bins.
+        let (a, b, c) = (
+            libm::sqrtf(before),
+            libm::sqrtf(peak_power),
+            libm::sqrtf(after),
+        );
+        let curve = a - 2.0 * b + c;
+        let offset = if curve < 0.0 {
+            0.5 * (a - c) / curve
+        } else {
+            0.0
+        };
+        let hz = (peak as f32 + offset) * bin_width;
+        let amplitude = libm::sqrtf(peak_power) / (Q15 / 4.0);
+        Some((hz, amplitude, share))
+    }
+
+    /// `hz` on the output scale, clamped to it.
+    fn scale(&self, hz: f32) -> f32 {
+        let octaves = |hz: f32| libm::log10f(hz.max(1.0)) / core::f32::consts::LOG10_2;
+        let span = octaves(self.high_hz) - octaves(self.low_hz);
+        let t = if span > 0.0 {
+            ((octaves(hz) - octaves(self.low_hz)) / span).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.min + (self.max - self.min) * t
+    }
+}
+
+// ── Filters and decimation ──────────────────────────────────────────────
+
+/// Q of a second-order Butterworth filter: as flat as possible up to the
+/// cutoff.
+pub const BUTTERWORTH_Q: f32 = core::f32::consts::FRAC_1_SQRT_2;
+
+/// A second-order IIR filter ("biquad"), designed as in the Audio EQ
+/// Cookbook.
+///
+/// ```rust,ignore
+/// // Keep only the bass before looking for beats.
+/// let mut bass = Biquad::low_pass(150.0, BUTTERWORTH_Q, DEFAULT_SAMPLE_RATE);
+/// bass.process(&mut chunk);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Pass below `cutoff_hz`. Higher `q` peaks at the cutoff.
+    pub fn low_pass(cutoff_hz: f32, q: f32, sample_rate: u32) -> Self {
+        let (cos_w, alpha) = Self::angle(cutoff_hz, q, sample_rate);
+        let b1 = 1.0 - cos_w;
+        Self::normalized(
+            [b1 / 2.0, b1, b1 / 2.0],
+            [1.0 + alpha, -2.0 * cos_w, 1.0 - alpha],
+        )
+    }
+
+    /// Pass above `cutoff_hz`, e.g. to cut hum and handling noise.
+    pub fn high_pass(cutoff_hz: f32, q: f32, sample_rate: u32) -> Self {
+        let (cos_w, alpha) = Self::angle(cutoff_hz, q, sample_rate);
+        let b1 = -(1.0 + cos_w);
+        Self::normalized(
+            [-b1 / 2.0, b1, -b1 / 2.0],
+            [1.0 + alpha, -2.0 * cos_w, 1.0 - alpha],
+        )
+    }
+
+    /// Pass around `center_hz` at full level; higher `q` is narrower.
+    pub fn band_pass(center_hz: f32, q: f32, sample_rate: u32) -> Self {
+        let (cos_w, alpha) = Self::angle(center_hz, q, sample_rate);
+        Self::normalized(
+            [alpha, 0.0, -alpha],
+            [1.0 + alpha, -2.0 * cos_w, 1.0 - alpha],
+        )
+    }
+
+    /// Filter one sample.
+    pub fn step(&mut self, x: f32) -> f32 {
+        // Transposed direct form II: two state variables, good with floats.
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    /// Filter `samples` in place, clipping at full scale.
+    pub fn process(&mut self, samples: &mut [i16]) {
+        for sample in samples {
+            *sample = clip(self.step(f32::from(*sample)));
+        }
+    }
+
+    /// Forget past samples, e.g. after a gap in the audio.
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    /// `cos ω` and `α` for a frequency and Q.
+    fn angle(hz: f32, q: f32, sample_rate: u32) -> (f32, f32) {
+        let w = TAU * hz / sample_rate.max(1) as f32;
+        (libm::cosf(w), libm::sinf(w) / (2.0 * q.max(0.01)))
+    }
+
+    fn normalized(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+}
+
+/// The mean of the last `N` samples, updated one sample at a time.
+///
+/// A cheap low-pass filter, or fed with squared levels, a smoothed
+/// envelope.
+#[derive(Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct MovingAverage<const N: usize> {
+    window: [i16; N],
+    next: usize,
+    len: usize,
+    sum: i32,
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> MovingAverage<N> {
+    pub const fn new() -> Self {
+        const { assert!(N > 0 && N <= 65536, "N must be 1 to 65536") };
+        Self {
+            window: [0; N],
+            next: 0,
+            len: 0,
+            sum: 0,
+        }
+    }
+
+    /// Add `sample` and return the new mean. Until `N` samples have been
+    /// seen, it is the mean of those there are.
+    pub fn update(&mut self, sample: i16) -> i16 {
+        if self.len == N {
+            self.sum -= i32::from(self.window[self.next]);
+        } else {
+            self.len += 1;
+        }
+        self.window[self.next] = sample;
+        self.sum += i32::from(sample);
+        self.next = (self.next + 1) % N;
+        self.average()
+    }
+
+    /// Replace each of `samples` by the mean up to it.
+    pub fn process(&mut self, samples: &mut [i16]) {
+        for sample in samples {
+            *sample = self.update(*sample);
+        }
+    }
+
+    /// The current mean; 0 before any samples.
+    pub fn average(&self) -> i16 {
+        if self.len == 0 {
+            0
+        } else {
+            (self.sum / self.len as i32) as i16
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Lowers the sample rate by a whole factor, e.g. 48 kHz capture to 8 kHz
+/// for the level meter or [`VoiceDetector`], which then only see a sixth
+/// of the samples.
+///
+/// A fourth-order Butterworth low-pass at 40% of the new rate keeps what
+/// can't be represented any more from folding back in as noise.
+///
+/// ```rust,ignore
+/// let mut decimator = Decimator::new(6, 48_000);
+/// let mut low = [0; 256 / 6 + 1];
+/// let chunk = stream.next().await?;
+/// let n = decimator.process(chunk, &mut low);
+/// voice.update(&low[..n]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Decimator {
+    filters: [Biquad; 2],
+    factor: usize,
+    /// Input samples until the next one kept.
+    skip: usize,
+}
+
+impl Decimator {
+    /// Divide `sample_rate` by `factor`; a factor of 0 counts as 1.
+    pub fn new(factor: usize, sample_rate: u32) -> Self {
+        let factor = factor.max(1);
+        let cutoff = 0.4 * sample_rate as f32 / factor as f32;
+        // Q of the two stages of a fourth-order Butterworth filter.
+        let filters = [0.541_196_1, 1.306_563].map(|q| Biquad::low_pass(cutoff, q, sample_rate));
+        Self {
+            filters,
+            factor,
+            skip: 0,
+        }
+    }
+
+    pub const fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Filter `input` and write every `factor`th sample to `output`,
+    /// returning how many were written.
+    ///
+    /// The phase carries over between calls, so blocks of any length give
+    /// the same result as one long one. `output` needs room for
+    /// `input.len() / factor + 1` samples; input past that is dropped.
+    pub fn process(&mut self, input: &[i16], output: &mut [i16]) -> usize {
+        let mut written = 0;
+        for &sample in input {
+            if written == output.len() && self.skip == 0 {
+                break;
+            }
+            let mut y = f32::from(sample);
+            for filter in &mut self.filters {
+                y = filter.step(y);
+            }
+            if self.skip == 0 {
+                output[written] = clip(y);
+                written += 1;
+                self.skip = self.factor;
+            }
+            self.skip -= 1;
+        }
+        written
+    }
+
+    /// Forget past samples, e.g. after a gap in the audio.
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+        self.skip = 0;
+    }
+}
+
+/// A filter output as a sample, clipping at full scale.
+fn clip(x: f32) -> i16 {
+    x.clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16
+}
@@ -0,0 +1,133 @@
+//! Microphone audio streamed to a computer over the USB serial port.
+//!
+//! Each block of samples goes out as one frame, all fields little-endian:
+//!
+//! | Bytes | Field                                               |
+//! |-------|-----------------------------------------------------|
+//! | 4     | [`FRAME_MAGIC`], `b"MIC1"`                          |
+//! | 2     | sequence number, +1 per frame, wrapping             |
+//! | 2     | sample count `n`                                    |
+//! | 4     | sample rate in Hz                                   |
+//! | 2·n   | samples, `i16`                                      |
+//! | 2     | wrapping sum of the samples as `u16`                |
+//!
+//! The same port carries the defmt log, so the host scans for the magic
+//! and drops frames whose checksum doesn't match. A gap in the sequence
+//! numbers means frames were lost. To record a WAV file on a laptop:
+//!
+//! ```text
+//! import serial, struct, sys, wave
+//!
+//! port = serial.Serial(sys.argv[1])
+//! out = None
+//! buf = b""
+//! while True:
+//!     buf += port.read(port.in_waiting or 1)
+//!     start = buf.find(b"MIC1")
+//!     if start < 0 or len(buf) < start + 12:
+//!         continue
+//!     seq, n, rate = struct.unpack_from("<HHI", buf, start + 4)
+//!     end = start + 12 + 2 * n + 2
+//!     if len(buf) < end:
+//!         continue
+//!     data = buf[start + 12 : end - 2]
+//!     (checksum,) = struct.unpack_from("<H", buf, end - 2)
+//!     if sum(struct.unpack(f"<{n}h", data)) & 0xFFFF == checksum:
+//!         if out is None:
+//!             out = wave.open(sys.argv[2], "wb")
+//!             out.setparams((1, 2, rate, 0, "NONE", None))
+//!         out.writeframes(data)
+//!         buf = buf[end:]
+//!     else:
+//!         buf = buf[start + 4 :]
+//! ```
+
+use defmt::{
+    error,
+    warn,
+};
+use embedded_io_async::Write;
+use esp_hal::{
+    Async,
+    usb_serial_jtag::UsbSerialJtag,
+};
+
+use super::{
+    MicStream,
+    StreamError,
+};
+use crate::UsbResources;
+
+/// Start of every frame.
+pub const FRAME_MAGIC: [u8; 4] = *b"MIC1";
+
+/// Samples converted and written at a time.
+const WRITE_CHUNK: usize = 32;
+
+/// Sends frames of audio over the USB serial/JTAG port.
+///
+/// ```rust,ignore
+/// let mut host = HostAudio::new(resources.usb, DEFAULT_SAMPLE_RATE);
+/// let ring = mk_static!([u32; STREAM_RING_WORDS], [0; STREAM_RING_WORDS]);
+/// let mut stream = mic.stream::<256>(ring).unwrap();
+/// host.run(&mut stream).await
+/// ```
+pub struct HostAudio<'d> {
+    usb: UsbSerialJtag<'d, Async>,
+    sample_rate: u32,
+    sequence: u16,
+}
+
+impl<'d> HostAudio<'d> {
+    /// `sample_rate` is only passed on to the host, for its benefit.
+    pub fn new(res: UsbResources<'d>, sample_rate: u32) -> Self {
+        Self {
+            usb: UsbSerialJtag::new(res.usb).into_async(),
+            sample_rate,
+            sequence: 0,
+        }
+    }
+
+    /// Send `samples` as one frame.
+    ///
+    /// Waits while the host isn't reading, e.g. with nothing connected.
+    ///
+    /// # Panics
+    ///
+    /// If there are more than 65535 samples.
+    pub async fn send(&mut self, samples: &[i16]) {
+        let count = u16::try_from(samples.len()).expect("too many samples for one frame");
+        let mut header = [0; 12];
+        header[..4].copy_from_slice(&FRAME_MAGIC);
+        header[4..6].copy_from_slice(&self.sequence.to_le_bytes());
+        header[6..8].copy_from_slice(&count.to_le_bytes());
+        header[8..].copy_from_slice(&self.sample_rate.to_le_bytes());
+        let Ok(()) = self.usb.write_all(&header).await;
+
+        let mut checksum = 0u16;
+        let mut bytes = [0; WRITE_CHUNK * 2];
+        for chunk in samples.chunks(WRITE_CHUNK) {
+            for (out, &sample) in bytes.chunks_exact_mut(2).zip(chunk) {
+                out.copy_from_slice(&sample.to_le_bytes());
+                checksum = checksum.wrapping_add(sample as u16);
+            }
+            let Ok(()) = self.usb.write_all(&bytes[..chunk.len() * 2]).await;
+        }
+        let Ok(()) = self.usb.write_all(&checksum.to_le_bytes()).await;
+        self.sequence = self.sequence.wrapping_add(1);
+    }
+
+    /// Send every chunk from `stream`, forever.
+    ///
+    /// Audio recorded while the host wasn't reading is lost, leaving a gap
+    /// in the sequence numbers.
+    pub async fn run<const N: usize>(&mut self, stream: &mut MicStream<'_, '_, N>) -> ! {
+        loop {
+            match stream.next().await {
+                Ok(chunk) => self.send(chunk).await,
+                Err(StreamError::Overrun) => warn!("host audio: dropped samples"),
+                Err(StreamError::Dma(err)) => error!("host audio: {}", err),
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! One task analyses the microphone; any number of tasks react to it.
+
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    pubsub::{
+        PubSubChannel,
+        Subscriber,
+        WaitResult,
+    },
+    watch::{
+        Receiver,
+        Watch,
+    },
+};
+
+use super::{
+    ChunkTime,
+    Level,
+    LevelMeter,
+    MicStream,
+    StreamError,
+    dsp::{
+        Beat,
+        BeatDetector,
+        Fft,
+    },
+};
+
+/// Edges of the [`AudioFrame::bands`], in Hz: bass, low mids, mids, upper
+/// mids and treble, one per LED up a bar.
+pub const REACTIVE_BAND_EDGES_HZ: [u32; REACTIVE_BANDS + 1] = [60, 250, 500, 2000, 4000, 8000];
+/// Number of [`AudioFrame::bands`].
+pub const REACTIVE_BANDS: usize = 5;
+/// How many tasks can [`watch`](AudioReactive::watch) at once.
+pub const MAX_AUDIO_WATCHERS: usize = 4;
+/// How many tasks can take [`beats`](AudioReactive::beats) at once.
+pub const MAX_BEAT_SUBSCRIBERS: usize = 4;
+/// Beats buffered per subscriber before the slowest one starts missing
+/// some.
+const BEAT_CAPACITY: usize = 4;
+
+/// Band level shown as empty; full scale is full.
+const BAND_FLOOR_DBFS: f32 = -70.0;
+
+/// Power of a full-scale sine in its FFT bin; see [`Fft`].
+const FULL_SCALE_POWER: f32 = (32767.0 / 4.0) * (32767.0 / 4.0);
+
+type BeatChannel = PubSubChannel<
+    CriticalSectionRawMutex,
+    (Beat, ChunkTime),
+    BEAT_CAPACITY,
+    MAX_BEAT_SUBSCRIBERS,
+    1,
+>;
+
+/// What the microphone heard in one chunk.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct AudioFrame {
+    pub level: Level,
+    /// Loudness of each of [`REACTIVE_BAND_EDGES_HZ`], low to high, from 0.0
+    /// (-70 dBFS or below) to 1.0 (full scale).
+    pub bands: [f32; REACTIVE_BANDS],
+    /// Set if this chunk starts a beat in the bass.
+    pub beat: Option<Beat>,
+    /// When the chunk was recorded.
+    pub time: ChunkTime,
+}
+
+/// Level meter, spectrum bands and beat detection over one [`MicStream`],
+/// shared with every task that wants them.
+///
+/// ```rust,ignore
+/// static AUDIO: AudioReactive = AudioReactive::new();
+///
+/// #[embassy_executor::task]
+/// async fn audio_task(mut stream: MicStream<'static, 'static, 256>) {
+///     AUDIO.run(&mut stream).await
+/// }
+///
+/// #[embassy_executor::task]
+/// async fn led_task(leds: &'static mut Leds<'static>) {
+///     let mut frames = AUDIO.watch().unwrap();
+///     loop {
+///         let frame = frames.changed().await;
+///         let bar = frame.bands.map(|level| Srgb::new((level * 60.0) as u8, 0, 0));
+///         leds.set_both_bars(&bar);
+///         leds.update().await;
+///     }
+/// }
+///
+/// #[embassy_executor::task]
+/// async fn beat_task() {
+///     let mut beats = AUDIO.beats().unwrap();
+///     loop {
+///         let (beat, time) = beats.next().await;
+///         defmt::info!("beat {} at {}", beat.strength, time.at);
+///     }
+/// }
+/// ```
+pub struct AudioReactive {
+    frames: Watch<CriticalSectionRawMutex, AudioFrame, MAX_AUDIO_WATCHERS>,
+    beats: BeatChannel,
+}
+
+impl Default for AudioReactive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioReactive {
+    pub const fn new() -> Self {
+        Self {
+            frames: Watch::new(),
+            beats: PubSubChannel::new(),
+        }
+    }
+
+    /// Analyse `stream` forever, publishing an [`AudioFrame`] per chunk.
+    pub async fn run<const N: usize>(&self, stream: &mut MicStream<'_, '_, N>) -> ! {
+        self.run_with(stream, LevelMeter::new(), BeatDetector::new())
+            .await
+    }
+
+    /// [`run`](Self::run) with a configured meter and beat detector, e.g.
+    /// gated by a measured noise floor.
+    pub async fn run_with<const N: usize>(
+        &self,
+        stream: &mut MicStream<'_, '_, N>,
+        mut meter: LevelMeter,
+        mut beats: BeatDetector,
+    ) -> ! {
+        let sample_rate = stream.sample_rate();
+        let fft = Fft::<N>::new();
+        let sender = self.frames.sender();
+        let publisher = self.beats.immediate_publisher();
+        let mut energies = [0; REACTIVE_BANDS];
+        loop {
+            let (chunk, time) = match stream.next_timed().await {
+                Ok(timed) => timed,
+                Err(StreamError::Overrun) => continue,
+                Err(err) => {
+                    defmt::error!("audio reactive: {}", err);
+                    continue;
+                }
+            };
+            let level = meter.update(chunk);
+            fft.process(chunk)
+                .bands(&REACTIVE_BAND_EDGES_HZ, sample_rate, &mut energies);
+            let beat = beats.update_energy(energies[0] as f32);
+            let frame = AudioFrame {
+                level,
+                bands: energies.map(|energy| {
+                    let dbfs = 10.0 * libm::log10f(energy as f32 / FULL_SCALE_POWER);
+                    ((dbfs - BAND_FLOOR_DBFS) / -BAND_FLOOR_DBFS).clamp(0.0, 1.0)
+                }),
+                beat,
+                time,
+            };
+            sender.send(frame);
+            if let Some(beat) = beat {
+                publisher.publish_immediate((beat, time));
+            }
+        }
+    }
+
+    /// The most recent frame, if any.
+    pub fn latest(&self) -> Option<AudioFrame> {
+        self.frames.try_get()
+    }
+
+    /// A receiver whose `changed().await` wakes on every frame, or `None` if
+    /// [`MAX_AUDIO_WATCHERS`] are already registered. Frames that arrive
+    /// while a task is busy replace each other; use [`beats`](Self::beats)
+    /// not to miss any beat.
+    pub fn watch(
+        &self,
+    ) -> Option<Receiver<'_, CriticalSectionRawMutex, AudioFrame, MAX_AUDIO_WATCHERS>> {
+        self.frames.receiver()
+    }
+
+    /// Start receiving beats, or `None` if [`MAX_BEAT_SUBSCRIBERS`] already
+    /// are.
+    pub fn beats(&self) -> Option<BeatSubscriber<'_>> {
+        self.beats
+            .subscriber()
+            .ok()
+            .map(|subscriber| BeatSubscriber { subscriber })
+    }
+}
+
+/// A task's view of the beats from an [`AudioReactive`]. Dropping it frees
+/// the slot.
+pub struct BeatSubscriber<'a> {
+    subscriber: Subscriber<
+        'a,
+        CriticalSectionRawMutex,
+        (Beat, ChunkTime),
+        BEAT_CAPACITY,
+        MAX_BEAT_SUBSCRIBERS,
+        1,
+    >,
+}
+
+impl BeatSubscriber<'_> {
+    /// Wait for the next beat, and when the chunk it was heard in was
+    /// recorded.
+    pub async fn next(&mut self) -> (Beat, ChunkTime) {
+        loop {
+            match self.subscriber.next_message().await {
+                WaitResult::Message(beat) => return beat,
+                WaitResult::Lagged(missed) => {
+                    defmt::warn!("audio reactive: subscriber missed {} beats", missed)
+                }
+            }
+        }
+    }
+
+    /// The next beat if one is already waiting.
+    pub fn try_next(&mut self) -> Option<(Beat, ChunkTime)> {
+        loop {
+            match self.subscriber.try_next_message()? {
+                WaitResult::Message(beat) => return Some(beat),
+                WaitResult::Lagged(missed) => {
+                    defmt::warn!("audio reactive: subscriber missed {} beats", missed)
+                }
+            }
+        }
+    }
+}
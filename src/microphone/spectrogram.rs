@@ -0,0 +1,188 @@
+//! A scrolling spectrogram ("waterfall") of the microphone on the display.
+//!
+//! Each [`Spectrum`] becomes one column of pixels, low frequencies at the
+//! bottom, loudness as colour. New columns go in on the right and the
+//! panel's hardware scroll moves the rest left, so only one column is drawn
+//! per spectrum.
+//!
+//! The ST7789 scrolls whole columns of the screen: everything in the
+//! region's columns moves, above and below the region too.
+
+use defmt::warn;
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::{
+        Point,
+        RgbColor,
+        Size,
+    },
+    primitives::Rectangle,
+};
+
+use super::{
+    MicStream,
+    dsp::{
+        Fft,
+        Spectrum,
+    },
+};
+use crate::{
+    DISPLAY_HEIGHT,
+    DISPLAY_WIDTH,
+    Display,
+    DisplayError,
+};
+
+/// Power of a full-scale sine in its [`Spectrum`] bin.
+const FULL_SCALE_POWER: f32 = (32767.0 / 4.0) * (32767.0 / 4.0);
+
+/// Colours from quietest to loudest.
+const GRADIENT: [[u8; 3]; 5] = [
+    [0, 0, 0],
+    [40, 0, 120],
+    [200, 0, 80],
+    [255, 160, 0],
+    [255, 255, 255],
+];
+
+/// Draws spectra into a region of the [`Display`], scrolling it along.
+pub struct Spectrogram {
+    area: Rectangle,
+    /// Column of the region the next spectrum goes into.
+    next: u16,
+    floor_dbfs: f32,
+}
+
+impl Spectrogram {
+    /// Draw into `area` of the screen, cut to fit.
+    pub fn new(area: Rectangle) -> Self {
+        let screen = Rectangle::new(
+            Point::zero(),
+            Size::new(u32::from(DISPLAY_WIDTH), u32::from(DISPLAY_HEIGHT)),
+        );
+        Self {
+            area: area.intersection(&screen),
+            next: 0,
+            floor_dbfs: -70.0,
+        }
+    }
+
+    /// Show levels from `floor_dbfs` (black) up to full scale (white).
+    /// -70 by default.
+    #[must_use]
+    pub const fn with_floor(mut self, floor_dbfs: f32) -> Self {
+        self.floor_dbfs = floor_dbfs;
+        self
+    }
+
+    /// Set up hardware scrolling over the region's columns and clear it.
+    pub fn begin(&mut self, display: &mut Display<'_>) -> Result<(), DisplayError> {
+        let (left, width) = self.columns();
+        display.set_vertical_scroll_region(left, DISPLAY_WIDTH - left - width)?;
+        display.set_vertical_scroll_offset(left)?;
+        self.next = 0;
+        let (top, height) = self.rows();
+        if width > 0 && height > 0 {
+            let pixels = usize::from(width) * usize::from(height);
+            display.set_pixels(
+                left,
+                top,
+                left + width - 1,
+                top + height - 1,
+                core::iter::repeat_n(Rgb565::BLACK, pixels),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Add `spectrum` as the newest column.
+    pub fn push<const N: usize>(
+        &mut self,
+        display: &mut Display<'_>,
+        spectrum: &Spectrum<N>,
+    ) -> Result<(), DisplayError> {
+        let (left, width) = self.columns();
+        let (top, height) = self.rows();
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let x = left + self.next;
+        let colors = (0..height)
+            .rev()
+            .map(|row| self.color(spectrum, row, height));
+        display.set_pixels(x, top, x, top + height - 1, colors)?;
+        // Show the column just drawn at the region's right edge.
+        self.next = (self.next + 1) % width;
+        display.set_vertical_scroll_offset(left + self.next)
+    }
+
+    /// Stop scrolling and put the screen back as it was.
+    pub fn end(&self, display: &mut Display<'_>) -> Result<(), DisplayError> {
+        display.set_vertical_scroll_region(0, 0)?;
+        display.set_vertical_scroll_offset(0)
+    }
+
+    /// Colour of pixel `row`, counted up from the bottom of `height`.
+    fn color<const N: usize>(&self, spectrum: &Spectrum<N>, row: u16, height: u16) -> Rgb565 {
+        // Skip the DC bin; share the rest out between the rows.
+        let bins = Spectrum::<N>::BINS - 1;
+        let (row, height) = (usize::from(row), usize::from(height));
+        let first = 1 + row * bins / height;
+        let last = (1 + (row + 1) * bins / height).max(first + 1);
+        let power = (first..last.min(Spectrum::<N>::BINS))
+            .map(|bin| spectrum.power(bin))
+            .max()
+            .unwrap_or(0);
+        let dbfs = 10.0 * libm::log10f(power as f32 / FULL_SCALE_POWER);
+        let t = ((dbfs - self.floor_dbfs) / -self.floor_dbfs).clamp(0.0, 1.0);
+        gradient(t)
+    }
+
+    /// Left edge and width, in the panel's scroll axis.
+    fn columns(&self) -> (u16, u16) {
+        (self.area.top_left.x as u16, self.area.size.width as u16)
+    }
+
+    fn rows(&self) -> (u16, u16) {
+        (self.area.top_left.y as u16, self.area.size.height as u16)
+    }
+}
+
+/// Position `t`, 0.0 to 1.0, along [`GRADIENT`].
+fn gradient(t: f32) -> Rgb565 {
+    let scaled = t * (GRADIENT.len() - 1) as f32;
+    let i = (scaled as usize).min(GRADIENT.len() - 2);
+    let f = scaled - i as f32;
+    let mix = |c: usize| {
+        let (a, b) = (f32::from(GRADIENT[i][c]), f32::from(GRADIENT[i + 1][c]));
+        (a + (b - a) * f) as u8
+    };
+    Rgb565::new(mix(0) >> 3, mix(1) >> 2, mix(2) >> 3)
+}
+
+/// Show `stream` as a spectrogram in `area` of `display`, forever.
+///
+/// ```rust,ignore
+/// let mut stream = mic.stream::<256>(ring).unwrap();
+/// let area = Rectangle::new(Point::zero(), Size::new(320, 128));
+/// spectrogram::draw_spectrogram(display, area, &mut stream).await
+/// ```
+pub async fn draw_spectrogram<const N: usize>(
+    display: &mut Display<'_>,
+    area: Rectangle,
+    stream: &mut MicStream<'_, '_, N>,
+) -> ! {
+    let fft = Fft::<N>::new();
+    let mut spectrogram = Spectrogram::new(area);
+    if let Err(err) = spectrogram.begin(display) {
+        warn!("spectrogram: {}", err);
+    }
+    loop {
+        let Ok(chunk) = stream.next().await else {
+            continue;
+        };
+        if let Err(err) = spectrogram.push(display, &fft.process(chunk)) {
+            warn!("spectrogram: {}", err);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! The owner's name and colours, kept in a [`Store`] so they can change
+//! without a rebuild.
+
+use palette::Srgb;
+
+use crate::{
+    config::{
+        self,
+        Background,
+        Theme,
+    },
+    storage::{
+        self,
+        Store,
+    },
+};
+
+/// Longest name kept, in bytes.
+pub const NAMETAG_NAME_LEN: usize = 32;
+
+/// Background tag and colour, then the foreground colour, ahead of the name.
+const HEADER_LEN: usize = 7;
+
+/// What the nametag shows: a name and a [`Theme`].
+///
+/// [`from_config`](Self::from_config) is what `badge.toml` says; an editor
+/// such as the captive portal (`portal` feature) [`save`](Self::save)s
+/// changes, and the nametag picks them up at boot:
+///
+/// ```rust,ignore
+/// let nametag = Nametag::restore(&mut store);
+/// info!("Name tag: {}", nametag.name());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Nametag {
+    name: [u8; NAMETAG_NAME_LEN],
+    name_len: u8,
+    pub theme: Theme,
+}
+
+impl Nametag {
+    /// `name` in `theme`, cut to [`NAMETAG_NAME_LEN`] bytes at a character
+    /// boundary.
+    pub fn new(name: &str, theme: Theme) -> Self {
+        Self {
+            name: [0; NAMETAG_NAME_LEN],
+            name_len: 0,
+            theme,
+        }
+        .with_name(name)
+    }
+
+    /// The name and theme from `badge.toml`.
+    pub fn from_config() -> Self {
+        Self::new(config::NAME, config::THEME)
+    }
+
+    /// Change the name, cut to [`NAMETAG_NAME_LEN`] bytes at a character
+    /// boundary.
+    #[must_use]
+    pub fn with_name(mut self, name: &str) -> Self {
+        let mut len = name.len().min(NAMETAG_NAME_LEN);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.name = [0; NAMETAG_NAME_LEN];
+        self.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+        self.name_len = len as u8;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        // Only ever filled from a `&str` or checked on load.
+        core::str::from_utf8(&self.name[..usize::from(self.name_len)]).unwrap_or_default()
+    }
+
+    /// Persist the nametag so [`load`](Self::load) can pick it up after a
+    /// reboot.
+    pub fn save<S: Store + ?Sized>(&self, store: &mut S) -> Result<(), S::Error> {
+        let (tag, background) = match self.theme.background {
+            Background::Solid(color) => (0, color),
+            Background::Rainbow => (1, Srgb::new(0, 0, 0)),
+            Background::Retrofuture => (2, Srgb::new(0, 0, 0)),
+            Background::Hearts => (3, Srgb::new(0, 0, 0)),
+        };
+        let foreground = self.theme.foreground;
+        let len = usize::from(self.name_len);
+        let mut buf = [0; HEADER_LEN + NAMETAG_NAME_LEN];
+        buf[..HEADER_LEN].copy_from_slice(&[
+            tag,
+            background.red,
+            background.green,
+            background.blue,
+            foreground.red,
+            foreground.green,
+            foreground.blue,
+        ]);
+        buf[HEADER_LEN..HEADER_LEN + len].copy_from_slice(&self.name[..len]);
+        store.write(storage::NAMETAG, &buf[..HEADER_LEN + len])
+    }
+
+    /// The nametag stored by [`save`](Self::save), if any.
+    ///
+    /// Anything that doesn't decode reads as `None`.
+    pub fn load<S: Store + ?Sized>(store: &mut S) -> Result<Option<Self>, S::Error> {
+        let mut buf = [0; HEADER_LEN + NAMETAG_NAME_LEN];
+        let Some(len) = store.read(storage::NAMETAG, &mut buf)? else {
+            return Ok(None);
+        };
+        let Some(name) = buf.get(HEADER_LEN..len) else {
+            return Ok(None);
+        };
+        let Ok(name) = core::str::from_utf8(name) else {
+            return Ok(None);
+        };
+        let background = match buf[0] {
+            0 => Background::Solid(Srgb::new(buf[1], buf[2], buf[3])),
+            1 => Background::Rainbow,
+            2 => Background::Retrofuture,
+            3 => Background::Hearts,
+            _ => return Ok(None),
+        };
+        let theme = Theme {
+            background,
+            foreground: Srgb::new(buf[4], buf[5], buf[6]),
+        };
+        Ok(Some(Self::new(name, theme)))
+    }
+
+    /// The stored nametag, or [`from_config`](Self::from_config) if there
+    /// is none or it can't be read.
+    pub fn restore<S: Store + ?Sized>(store: &mut S) -> Self {
+        Self::load(store)
+            .ok()
+            .flatten()
+            .unwrap_or_else(Self::from_config)
+    }
+}
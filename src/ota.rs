@@ -0,0 +1,436 @@
+//! Signed firmware updates, behind the `ota` feature.
+//!
+//! New firmware is written to the app partition that isn't running, checked
+//! against an Ed25519 signature and booted on the next reset. How the bytes
+//! arrive is up to the caller: a download with [`Ota::download`] (with the
+//! `http` feature), the USB port, or another badge through [`relay`] (with
+//! the `esp-now` feature).
+//!
+//! ```rust,ignore
+//! let mut flash: FlashStorage = resources.flash.into();
+//! let mut ota = Ota::new(&mut flash, config::OTA_PUBLIC_KEY.unwrap());
+//! // New firmware made it this far: keep it.
+//! ota.mark_valid()?;
+//!
+//! let mut update = ota.begin()?;
+//! while let Some(chunk) = download.next().await {
+//!     update.write(&chunk)?;
+//! }
+//! update.finish()?;
+//! ota::reboot();
+//! ```
+//!
+//! The partition table needs an OTA data partition and two OTA app slots,
+//! like `partitions.csv` in the repository root. Flash it once over USB
+//! with `espflash flash --partition-table partitions.csv`.
+//!
+//! ## Signed images
+//!
+//! An update is the app image from `espflash save-image`, followed by a
+//! [`TRAILER_LEN`]-byte trailer, all fields little-endian:
+//!
+//! | Bytes | Field                                               |
+//! |-------|-----------------------------------------------------|
+//! | 64    | Ed25519 signature of the image followed by `version` |
+//! | 4     | `version`, `u32`; higher is newer                   |
+//! | 4     | image length without the trailer                    |
+//! | 4     | `b"DOTA"`                                           |
+//!
+//! [`Update::finish`] reads the image back from flash before checking it,
+//! so a bad write is caught as well as a bad signature, and refuses a
+//! version no newer than the running firmware's, so an old signed release
+//! can't roll a badge back to its bugs. The trailer stays
+//! in flash after the image, which is how a badge knows its own version and
+//! can pass it on. Make a key once, put the public half in `badge.toml` as
+//! `ota.public_key`, and sign each release with a bumped version:
+//!
+//! ```text
+//! # pip install cryptography
+//! # python sign.py keygen ota-key.pem
+//! # python sign.py sign ota-key.pem app.bin 7 app.ota
+//! import struct, sys
+//! from cryptography.hazmat.primitives import serialization
+//! from cryptography.hazmat.primitives.asymmetric.ed25519 import Ed25519PrivateKey
+//!
+//! if sys.argv[1] == "keygen":
+//!     key = Ed25519PrivateKey.generate()
+//!     open(sys.argv[2], "wb").write(key.private_bytes(
+//!         serialization.Encoding.PEM, serialization.PrivateFormat.PKCS8,
+//!         serialization.NoEncryption()))
+//! else:
+//!     key = serialization.load_pem_private_key(open(sys.argv[2], "rb").read(), None)
+//!     image, version = open(sys.argv[3], "rb").read(), int(sys.argv[4])
+//!     signature = key.sign(image + struct.pack("<I", version))
+//!     trailer = signature + struct.pack("<II", version, len(image)) + b"DOTA"
+//!     open(sys.argv[5], "wb").write(image + trailer)
+//! print("public_key =", key.public_key().public_bytes_raw().hex())
+//! ```
+
+#[cfg(feature = "esp-now")]
+pub mod relay;
+
+use defmt::info;
+use ed25519_compact::{
+    PublicKey,
+    Signature,
+};
+use embedded_storage::{
+    ReadStorage,
+    nor_flash::NorFlash,
+};
+use esp_bootloader_esp_idf::{
+    ota::OtaImageState,
+    ota_updater::OtaUpdater,
+    partitions::{
+        self,
+        AppPartitionSubType,
+        PARTITION_TABLE_MAX_LEN,
+        PartitionType,
+    },
+};
+use esp_storage::FlashStorageError;
+
+use crate::FlashStorage;
+#[cfg(feature = "http")]
+use crate::http::{
+    HttpClient,
+    HttpError,
+};
+
+/// Length of the trailer after the image.
+pub const TRAILER_LEN: usize = 76;
+
+/// Last bytes of the trailer.
+const TRAILER_MAGIC: [u8; 4] = *b"DOTA";
+/// First byte of every ESP app image.
+const IMAGE_MAGIC: u8 = 0xe9;
+/// Length of the ESP app image header.
+const IMAGE_HEADER_LEN: u32 = 24;
+/// Erase unit, and how much [`Update`] buffers before writing.
+const SECTOR: usize = FlashStorage::SECTOR_SIZE as usize;
+
+/// Errors from installing an update.
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum OtaError {
+    /// The partition table can't be read or lacks the OTA data partition
+    /// or two OTA app slots.
+    Partition(partitions::Error),
+    Flash(FlashStorageError),
+    /// The update is bigger than the app partition.
+    TooLarge,
+    /// The data isn't an ESP app image followed by a trailer.
+    NotAnImage,
+    /// The signature doesn't match the image or the key.
+    BadSignature,
+    /// The update's version isn't newer than the running firmware's, so
+    /// installing it would roll the badge back.
+    NotNewer {
+        version: u32,
+        running: u32,
+    },
+    /// The download failed.
+    #[cfg(feature = "http")]
+    Http(HttpError),
+}
+
+impl From<partitions::Error> for OtaError {
+    fn from(err: partitions::Error) -> Self {
+        Self::Partition(err)
+    }
+}
+
+impl From<FlashStorageError> for OtaError {
+    fn from(err: FlashStorageError) -> Self {
+        Self::Flash(err)
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<HttpError> for OtaError {
+    fn from(err: HttpError) -> Self {
+        Self::Http(err)
+    }
+}
+
+/// A signed image in flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct SignedImage {
+    /// Flash address of the first byte.
+    pub offset: u32,
+    /// Length of the image and its trailer together.
+    pub len: u32,
+    pub version: u32,
+}
+
+/// Firmware updates, checked against one public key. See the
+/// [module docs](self).
+pub struct Ota<'a, 'd> {
+    flash: &'a mut FlashStorage<'d>,
+    key: PublicKey,
+    table: [u8; PARTITION_TABLE_MAX_LEN],
+}
+
+impl<'a, 'd> Ota<'a, 'd> {
+    /// Accept updates signed with the Ed25519 key whose public half is
+    /// `public_key`, usually [`OTA_PUBLIC_KEY`](crate::config::OTA_PUBLIC_KEY).
+    pub fn new(flash: &'a mut FlashStorage<'d>, public_key: [u8; 32]) -> Self {
+        Self {
+            flash,
+            key: PublicKey::new(public_key),
+            table: [0; PARTITION_TABLE_MAX_LEN],
+        }
+    }
+
+    /// The running firmware, if an update installed it. Firmware flashed
+    /// over USB has no trailer and reads as `None`.
+    pub fn running(&mut self) -> Result<Option<SignedImage>, OtaError> {
+        let table = partitions::read_partition_table(self.flash, &mut self.table)?;
+        let Some(booted) = table.booted_partition()? else {
+            return Ok(None);
+        };
+        let (offset, capacity) = (booted.offset(), booted.len());
+        let Some(image_len) = self.image_len(offset, capacity)? else {
+            return Ok(None);
+        };
+        if image_len + TRAILER_LEN as u32 > capacity {
+            return Ok(None);
+        }
+        let mut trailer = [0; TRAILER_LEN];
+        self.read(offset + image_len, &mut trailer)?;
+        Ok(parse_trailer(&trailer)
+            .filter(|&(_, len)| len == image_len)
+            .map(|(version, _)| SignedImage {
+                offset,
+                len: image_len + TRAILER_LEN as u32,
+                version,
+            }))
+    }
+
+    /// Version of the running firmware; 0 if it wasn't installed by an
+    /// update.
+    pub fn running_version(&mut self) -> Result<u32, OtaError> {
+        Ok(self.running()?.map_or(0, |image| image.version))
+    }
+
+    /// Read `buf.len()` bytes of flash from `offset`, e.g. to pass a
+    /// [`SignedImage`] on.
+    pub fn read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), OtaError> {
+        Ok(ReadStorage::read(self.flash, offset, buf)?)
+    }
+
+    /// Start writing an update to the partition that isn't running.
+    pub fn begin(&mut self) -> Result<Update<'_, 'a, 'd>, OtaError> {
+        let next = self.next_slot()?;
+        let table = partitions::read_partition_table(self.flash, &mut self.table)?;
+        let slot = table
+            .find_partition(PartitionType::App(next))?
+            .ok_or(partitions::Error::Invalid)?;
+        let (offset, capacity) = (slot.offset(), slot.len());
+        info!("ota: writing {} at {=u32:#x}", next, offset);
+        Ok(Update {
+            ota: self,
+            offset,
+            capacity,
+            written: 0,
+            sector: [0xff; SECTOR],
+        })
+    }
+
+    /// Download a signed image from `url` and install it like
+    /// [`begin`](Self::begin) … [`Update::finish`]. Returns its version;
+    /// [`reboot`] to run it.
+    #[cfg(feature = "http")]
+    pub async fn download(
+        &mut self,
+        client: &mut HttpClient<'_>,
+        url: &str,
+    ) -> Result<u32, OtaError> {
+        let mut update = self.begin()?;
+        let mut buf = [0; 2048];
+        client
+            .download(url, &mut buf, |piece| update.write(piece))
+            .await?;
+        update.finish()
+    }
+
+    /// Tell the bootloader the running firmware works, so one that rolls
+    /// back untested updates keeps it. Does nothing for firmware flashed
+    /// over USB.
+    pub fn mark_valid(&mut self) -> Result<(), OtaError> {
+        let mut updater = OtaUpdater::new(self.flash, &mut self.table)?;
+        match updater.current_ota_state() {
+            Ok(OtaImageState::New | OtaImageState::PendingVerify) => {
+                updater.set_current_ota_state(OtaImageState::Valid)?;
+                info!("ota: running firmware marked valid");
+            }
+            // No slot selected yet: booted from what was flashed over USB.
+            Ok(_) | Err(partitions::Error::InvalidState) => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(())
+    }
+
+    fn next_slot(&mut self) -> Result<AppPartitionSubType, OtaError> {
+        let mut updater = OtaUpdater::new(self.flash, &mut self.table)?;
+        Ok(updater.next_partition()?.1)
+    }
+
+    /// Length of the ESP app image at `offset`, without the trailer, by
+    /// walking its segment headers.
+    fn image_len(&mut self, offset: u32, capacity: u32) -> Result<Option<u32>, OtaError> {
+        let mut header = [0; IMAGE_HEADER_LEN as usize];
+        self.read(offset, &mut header)?;
+        if header[0] != IMAGE_MAGIC {
+            return Ok(None);
+        }
+        let mut len = IMAGE_HEADER_LEN;
+        for _ in 0..header[1] {
+            let mut segment = [0; 8];
+            self.read(offset + len, &mut segment)?;
+            let data = u32::from_le_bytes([segment[4], segment[5], segment[6], segment[7]]);
+            match len.checked_add(8 + data) {
+                Some(end) if end <= capacity => len = end,
+                _ => return Ok(None),
+            }
+        }
+        // A checksum byte ends the image on a 16-byte boundary, then an
+        // optional SHA-256 of it all.
+        len = (len + 16) & !15;
+        if header[23] == 1 {
+            len += 32;
+        }
+        Ok(Some(len))
+    }
+
+    /// Check the signature in `trailer` over the `image_len` bytes at
+    /// `offset` as they are now in flash.
+    fn verify(
+        &mut self,
+        offset: u32,
+        image_len: u32,
+        trailer: &[u8; TRAILER_LEN],
+        version: u32,
+    ) -> Result<(), OtaError> {
+        let signature =
+            Signature::from_slice(&trailer[..64]).map_err(|_| OtaError::BadSignature)?;
+        let mut state = self
+            .key
+            .verify_incremental(&signature)
+            .map_err(|_| OtaError::BadSignature)?;
+        let mut buf = [0; 1024];
+        let mut done = 0;
+        while done < image_len {
+            let len = (image_len - done).min(buf.len() as u32) as usize;
+            self.read(offset + done, &mut buf[..len])?;
+            state.absorb(&buf[..len]);
+            done += len as u32;
+        }
+        state.absorb(version.to_le_bytes());
+        state.verify().map_err(|_| OtaError::BadSignature)
+    }
+}
+
+/// An update being written. Feed it the signed image in order with
+/// [`write`](Self::write), then [`finish`](Self::finish) it; dropping it
+/// before that leaves the running firmware selected.
+pub struct Update<'o, 'a, 'd> {
+    ota: &'o mut Ota<'a, 'd>,
+    /// Flash address of the partition being written.
+    offset: u32,
+    capacity: u32,
+    written: u32,
+    /// The sector being filled; written out when full.
+    sector: [u8; SECTOR],
+}
+
+impl Update<'_, '_, '_> {
+    /// Append `data` to the update.
+    pub fn write(&mut self, mut data: &[u8]) -> Result<(), OtaError> {
+        if self.written == 0 && data.first().is_some_and(|&byte| byte != IMAGE_MAGIC) {
+            return Err(OtaError::NotAnImage);
+        }
+        if self.written as usize + data.len() > self.capacity as usize {
+            return Err(OtaError::TooLarge);
+        }
+        while !data.is_empty() {
+            let filled = self.written as usize % SECTOR;
+            let len = data.len().min(SECTOR - filled);
+            self.sector[filled..filled + len].copy_from_slice(&data[..len]);
+            self.written += len as u32;
+            data = &data[len..];
+            if (self.written as usize).is_multiple_of(SECTOR) {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes written so far.
+    pub fn written(&self) -> u32 {
+        self.written
+    }
+
+    /// Write out what's left, check the signature and that the update is
+    /// newer than the running firmware, and select it for the next boot. Returns its version; [`reboot`] to run it.
+    pub fn finish(mut self) -> Result<u32, OtaError> {
+        if !(self.written as usize).is_multiple_of(SECTOR) {
+            self.flush()?;
+        }
+        let Some(image_len) = self.written.checked_sub(TRAILER_LEN as u32) else {
+            return Err(OtaError::NotAnImage);
+        };
+        let mut trailer = [0; TRAILER_LEN];
+        self.ota.read(self.offset + image_len, &mut trailer)?;
+        let Some((version, len)) = parse_trailer(&trailer) else {
+            return Err(OtaError::NotAnImage);
+        };
+        if len != image_len {
+            return Err(OtaError::NotAnImage);
+        }
+        let running = self.ota.running_version()?;
+        if version <= running {
+            return Err(OtaError::NotNewer { version, running });
+        }
+        self.ota.verify(self.offset, image_len, &trailer, version)?;
+
+        let mut updater = OtaUpdater::new(self.ota.flash, &mut self.ota.table)?;
+        updater.activate_next_partition()?;
+        updater.set_current_ota_state(OtaImageState::New)?;
+        info!("ota: version {} installed, boots on reset", version);
+        Ok(version)
+    }
+
+    /// Erase the sector being filled and write it, padded to whole words.
+    fn flush(&mut self) -> Result<(), OtaError> {
+        let start = self.offset + (self.written - 1) / SECTOR as u32 * SECTOR as u32;
+        let filled = match self.written as usize % SECTOR {
+            0 => SECTOR,
+            filled => filled,
+        };
+        let len = filled.next_multiple_of(FlashStorage::WORD_SIZE as usize);
+        self.sector[filled..len].fill(0xff);
+        let flash = &mut *self.ota.flash;
+        flash.erase(start, start + SECTOR as u32)?;
+        NorFlash::write(flash, start, &self.sector[..len])?;
+        Ok(())
+    }
+}
+
+/// Restart into whatever the bootloader selects: the update, once
+/// [`Update::finish`] has succeeded.
+pub fn reboot() -> ! {
+    esp_hal::system::software_reset()
+}
+
+/// The version and image length from a trailer, if it has the magic.
+fn parse_trailer(trailer: &[u8; TRAILER_LEN]) -> Option<(u32, u32)> {
+    let word = |at: usize| {
+        u32::from_le_bytes([
+            trailer[at],
+            trailer[at + 1],
+            trailer[at + 2],
+            trailer[at + 3],
+        ])
+    };
+    (trailer[72..] == TRAILER_MAGIC).then(|| (word(64), word(68)))
+}
@@ -0,0 +1,193 @@
+//! Firmware updates passed from badge to badge over ESP-NOW.
+//!
+//! A badge running an update offers it to everyone in range once a second
+//! and sends any piece of it asked for. When [`relay`] hears of a newer
+//! version it asks for the pieces in order, installs the update and
+//! returns, ready to [`reboot`](super::reboot); after that it offers the
+//! new version in turn. Flash one badge with a signed image and it spreads
+//! across the room.
+//!
+//! ```rust,ignore
+//! #[embassy_executor::task]
+//! async fn update_task(mut esp_now: EspNow<'static>, flash: &'static mut FlashStorage<'static>) {
+//!     let mut ota = Ota::new(flash, config::OTA_PUBLIC_KEY.unwrap());
+//!     relay(&mut esp_now, &mut ota).await;
+//!     ota::reboot()
+//! }
+//! ```
+//!
+//! The whole update is checked by [`Update::finish`](super::Update::finish)
+//! like any other, so a badge offering junk only wastes time; a version that
+//! fails the check is not fetched again. Like the
+//! [game link](crate::gamelink), both badges must be on the same Wi-Fi
+//! channel.
+
+use embassy_futures::select::{
+    Either,
+    select,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use esp_radio::esp_now::{
+    BROADCAST_ADDRESS,
+    ESP_NOW_MAX_DATA_LEN,
+    EspNow,
+};
+
+use super::{
+    Ota,
+    OtaError,
+    SignedImage,
+};
+
+/// Time between offers of the running update.
+pub const OFFER_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to wait for a piece before asking again.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_millis(100);
+/// How many times a piece is asked for before giving up on the update.
+pub const MAX_ATTEMPTS: u8 = 20;
+
+/// First bytes of every frame, telling relay frames from other ESP-NOW
+/// traffic.
+const MAGIC: [u8; 2] = *b"DU";
+/// Magic, kind, version and offset (or length).
+const HEADER_LEN: usize = 11;
+/// Image bytes per [`DATA`] frame.
+const CHUNK: usize = 224;
+const _: () = assert!(HEADER_LEN + CHUNK <= ESP_NOW_MAX_DATA_LEN);
+
+/// "I have `version`, `len` bytes with the trailer."
+const OFFER: u8 = 0;
+/// "Send me `version` from `offset`."
+const REQUEST: u8 = 1;
+/// "`version` from `offset`:" followed by up to [`CHUNK`] bytes.
+const DATA: u8 = 2;
+
+/// Offer the running update, if any, until a newer one turns up; fetch and
+/// install that. Returns the installed version.
+///
+/// Failed downloads are logged and the newest offer is tried again.
+pub async fn relay(esp_now: &mut EspNow<'_>, ota: &mut Ota<'_, '_>) -> u32 {
+    let own = ota.running().unwrap_or_else(|err| {
+        defmt::warn!("ota: can't read the running image: {}", err);
+        None
+    });
+    let mut rejected = 0;
+    loop {
+        let (version, len) = seed(esp_now, ota, own, rejected).await;
+        defmt::info!("ota: fetching version {} ({} bytes)", version, len);
+        match fetch(esp_now, ota, version, len).await {
+            Ok(()) => return version,
+            Err(Some(err)) => {
+                defmt::warn!("ota: version {} rejected: {}", version, err);
+                rejected = rejected.max(version);
+            }
+            Err(None) => defmt::warn!("ota: lost the badge offering version {}", version),
+        }
+    }
+}
+
+/// Offer and send `own` until someone offers a version newer than it and
+/// than `rejected`; return that version and its length.
+async fn seed(
+    esp_now: &mut EspNow<'_>,
+    ota: &mut Ota<'_, '_>,
+    own: Option<SignedImage>,
+    rejected: u32,
+) -> (u32, u32) {
+    let own_version = own.map_or(0, |image| image.version);
+    let mut next_offer = Instant::now();
+    loop {
+        let received = match select(esp_now.receive_async(), Timer::at(next_offer)).await {
+            Either::First(received) => received,
+            Either::Second(()) => {
+                next_offer += OFFER_INTERVAL;
+                if let Some(own) = own {
+                    send(esp_now, OFFER, own.version, own.len, &[]).await;
+                }
+                continue;
+            }
+        };
+        let Some((kind, version, offset, _)) = parse(received.data()) else {
+            continue;
+        };
+        match (kind, own) {
+            (OFFER, _) if version > own_version && version > rejected => return (version, offset),
+            (REQUEST, Some(own)) if version == own.version && offset < own.len => {
+                let mut chunk = [0; CHUNK];
+                let len = (own.len - offset).min(CHUNK as u32) as usize;
+                if ota.read(own.offset + offset, &mut chunk[..len]).is_ok() {
+                    send(esp_now, DATA, version, offset, &chunk[..len]).await;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Ask for `version` piece by piece from whoever has it, and install it.
+///
+/// `Err(None)` means nobody answered.
+async fn fetch(
+    esp_now: &mut EspNow<'_>,
+    ota: &mut Ota<'_, '_>,
+    version: u32,
+    len: u32,
+) -> Result<(), Option<OtaError>> {
+    let mut update = ota.begin()?;
+    let mut attempts = 0;
+    while update.written() < len {
+        let offset = update.written();
+        if attempts == MAX_ATTEMPTS {
+            return Err(None);
+        }
+        attempts += 1;
+        send(esp_now, REQUEST, version, offset, &[]).await;
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        while let Either::First(received) =
+            select(esp_now.receive_async(), Timer::at(deadline)).await
+        {
+            if let Some((DATA, v, at, data)) = parse(received.data())
+                && v == version
+                && at == offset
+            {
+                let data = &data[..data.len().min((len - offset) as usize)];
+                update.write(data)?;
+                attempts = 0;
+                break;
+            }
+        }
+    }
+    update.finish()?;
+    Ok(())
+}
+
+async fn send(esp_now: &mut EspNow<'_>, kind: u8, version: u32, offset: u32, data: &[u8]) {
+    let mut frame = [0; HEADER_LEN + CHUNK];
+    frame[..2].copy_from_slice(&MAGIC);
+    frame[2] = kind;
+    frame[3..7].copy_from_slice(&version.to_le_bytes());
+    frame[7..11].copy_from_slice(&offset.to_le_bytes());
+    frame[HEADER_LEN..][..data.len()].copy_from_slice(data);
+    if let Err(err) = esp_now
+        .send_async(&BROADCAST_ADDRESS, &frame[..HEADER_LEN + data.len()])
+        .await
+    {
+        defmt::debug!("ota: send failed: {}", err);
+    }
+}
+
+/// Kind, version, offset and data of a relay frame.
+fn parse(frame: &[u8]) -> Option<(u8, u32, u32, &[u8])> {
+    let (header, data) = frame.split_at_checked(HEADER_LEN)?;
+    if header[..2] != MAGIC {
+        return None;
+    }
+    let word = |at: usize| {
+        u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+    };
+    Some((header[2], word(3), word(7), data))
+}
@@ -0,0 +1,277 @@
+//! A panic handler that keeps the last panic in flash across the reset,
+//! behind the `panic-log` feature, so the next boot can tell the player
+//! what happened instead of the badge silently restarting.
+//!
+//! ```rust,ignore
+//! let mut flash: FlashStorage = resources.flash.into();
+//! if let Some(panic) = panic_log::last_panic(&mut flash)? {
+//!     show_dialog(&mut display, "Previous app crashed", panic.message());
+//!     panic_log::clear(&mut flash)?;
+//! }
+//! ```
+//!
+//! On a panic the handler logs the message and a backtrace over defmt, as
+//! `esp-backtrace`'s does, writes them to the partition labelled
+//! [`PARTITION`] (like in `partitions.csv` in the repository root) and
+//! resets. Messages longer than [`MAX_MESSAGE_LEN`] bytes are cut short.
+//!
+//! It replaces `esp-backtrace`'s handler, which the crate's default
+//! `panic-handler` feature turns on, so build without it:
+//!
+//! ```sh
+//! cargo run --release --no-default-features --features panic-log --example panic_log
+//! ```
+//!
+//! Call [`last_panic`] early at boot: besides reading the log, it notes
+//! where the partition is, so the panic handler doesn't have to read the
+//! 3 KB partition table on whatever stack panicked.
+
+use core::{
+    fmt::{
+        self,
+        Write,
+    },
+    panic::PanicInfo,
+    sync::atomic::{
+        AtomicBool,
+        AtomicU32,
+        Ordering,
+    },
+};
+
+use defmt::error;
+use embedded_storage::{
+    ReadStorage,
+    nor_flash::NorFlash,
+};
+use esp_bootloader_esp_idf::partitions::{
+    self,
+    PARTITION_TABLE_MAX_LEN,
+};
+use esp_hal::rom::crc::crc32_le;
+use esp_storage::FlashStorageError;
+
+use crate::{
+    FlashResources,
+    FlashStorage,
+};
+
+#[cfg(feature = "panic-handler")]
+compile_error!(
+    "`panic-log` replaces the `panic-handler` feature: build with `--no-default-features`"
+);
+
+/// Label of the partition the last panic is kept in.
+pub const PARTITION: &str = "panic";
+/// Longest message kept, in bytes.
+pub const MAX_MESSAGE_LEN: usize = 256;
+/// Most backtrace addresses kept.
+pub const MAX_FRAMES: usize = 8;
+
+const MAGIC: [u8; 4] = *b"BPNC";
+/// Magic, CRC-32 of the rest, message length, frame count and a spare
+/// byte.
+const HEADER_LEN: usize = 12;
+const RECORD_LEN: usize = HEADER_LEN + 4 * MAX_FRAMES + MAX_MESSAGE_LEN;
+
+/// Start of the partition once known, or 0.
+static OFFSET: AtomicU32 = AtomicU32::new(0);
+/// Set by the first panic, so one during saving doesn't start over.
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+/// Errors from [`last_panic`] and [`clear`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum PanicLogError {
+    /// The partition table has no partition labelled [`PARTITION`].
+    Partition(partitions::Error),
+    Flash(FlashStorageError),
+}
+
+impl From<partitions::Error> for PanicLogError {
+    fn from(err: partitions::Error) -> Self {
+        Self::Partition(err)
+    }
+}
+
+impl From<FlashStorageError> for PanicLogError {
+    fn from(err: FlashStorageError) -> Self {
+        Self::Flash(err)
+    }
+}
+
+/// A panic saved by the handler.
+#[derive(Clone, Debug)]
+pub struct PanicLog {
+    message: [u8; MAX_MESSAGE_LEN],
+    message_len: usize,
+    frames: [u32; MAX_FRAMES],
+    frame_count: usize,
+}
+
+impl PanicLog {
+    /// Where it panicked and why, as `panicked at src/main.rs:12:5:
+    /// message`.
+    pub fn message(&self) -> &str {
+        let bytes = &self.message[..self.message_len];
+        match core::str::from_utf8(bytes) {
+            Ok(message) => message,
+            Err(err) => core::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default(),
+        }
+    }
+
+    /// Return addresses on the stack when it panicked, innermost first.
+    /// Look them up with `addr2line -e <elf>`.
+    pub fn backtrace(&self) -> &[u32] {
+        &self.frames[..self.frame_count]
+    }
+
+    /// The innermost backtrace address.
+    pub fn pc(&self) -> Option<u32> {
+        self.backtrace().first().copied()
+    }
+}
+
+impl defmt::Format for PanicLog {
+    fn format(&self, fmt: defmt::Formatter<'_>) {
+        defmt::write!(
+            fmt,
+            "{=str} (backtrace {:#x})",
+            self.message(),
+            self.backtrace()
+        );
+    }
+}
+
+/// The panic saved before the last reset, if there is one and it hasn't
+/// been [`clear`]ed.
+pub fn last_panic(flash: &mut FlashStorage<'_>) -> Result<Option<PanicLog>, PanicLogError> {
+    let offset = partition(flash)?;
+    let mut record = [0; RECORD_LEN];
+    ReadStorage::read(flash, offset, &mut record)?;
+    let [m0, m1, m2, m3, c0, c1, c2, c3, l0, l1, count, _] = *record.first_chunk().unwrap();
+    let message_len = usize::from(u16::from_le_bytes([l0, l1]));
+    let frame_count = usize::from(count);
+    if [m0, m1, m2, m3] != MAGIC
+        || message_len > MAX_MESSAGE_LEN
+        || frame_count > MAX_FRAMES
+        || crc32_le(0, &record[8..]) != u32::from_le_bytes([c0, c1, c2, c3])
+    {
+        return Ok(None);
+    }
+
+    let mut log = PanicLog {
+        message: [0; MAX_MESSAGE_LEN],
+        message_len,
+        frames: [0; MAX_FRAMES],
+        frame_count,
+    };
+    for (frame, bytes) in log
+        .frames
+        .iter_mut()
+        .zip(record[HEADER_LEN..].chunks_exact(4))
+    {
+        *frame = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    let message = HEADER_LEN + 4 * MAX_FRAMES;
+    log.message
+        .copy_from_slice(&record[message..message + MAX_MESSAGE_LEN]);
+    Ok(Some(log))
+}
+
+/// Forget the saved panic, once it has been shown.
+pub fn clear(flash: &mut FlashStorage<'_>) -> Result<(), PanicLogError> {
+    let offset = partition(flash)?;
+    flash.erase(offset, offset + FlashStorage::SECTOR_SIZE)?;
+    Ok(())
+}
+
+/// Start of the [`PARTITION`] partition, read from the partition table
+/// the first time.
+fn partition(flash: &mut FlashStorage<'_>) -> Result<u32, PanicLogError> {
+    let offset = OFFSET.load(Ordering::Relaxed);
+    if offset != 0 {
+        return Ok(offset);
+    }
+    let mut table = [0; PARTITION_TABLE_MAX_LEN];
+    let table = partitions::read_partition_table(flash, &mut table)?;
+    let offset = table
+        .iter()
+        .find(|partition| partition.label_as_str() == PARTITION)
+        .ok_or(partitions::Error::Invalid)?
+        .offset();
+    OFFSET.store(offset, Ordering::Relaxed);
+    Ok(offset)
+}
+
+/// Writes as much of a message as fits, in whole characters.
+struct Message<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for Message<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let end = self.len + c.len_utf8();
+            let Some(dest) = self.buf.get_mut(self.len..end) else {
+                break;
+            };
+            c.encode_utf8(dest);
+            self.len = end;
+        }
+        Ok(())
+    }
+}
+
+fn save(info: &PanicInfo<'_>, frames: &[u32]) -> Result<(), PanicLogError> {
+    // SAFETY: the panicking code is never resumed, so whatever owned the
+    // flash won't use it again before the reset.
+    let flash = unsafe { esp_hal::peripherals::FLASH::steal() };
+    let mut flash: FlashStorage = FlashResources { flash }.into();
+    let offset = partition(&mut flash)?;
+
+    let mut record = [0xff; RECORD_LEN];
+    let (header, rest) = record.split_at_mut(HEADER_LEN);
+    let (frame_bytes, message) = rest.split_at_mut(4 * MAX_FRAMES);
+    for (bytes, frame) in frame_bytes.chunks_exact_mut(4).zip(frames) {
+        bytes.copy_from_slice(&frame.to_le_bytes());
+    }
+    let mut writer = Message {
+        buf: message,
+        len: 0,
+    };
+    let _ = write!(writer, "{info}");
+    let message_len = writer.len as u16;
+    header[..4].copy_from_slice(&MAGIC);
+    header[8..10].copy_from_slice(&message_len.to_le_bytes());
+    header[10] = frames.len() as u8;
+    let crc = crc32_le(0, &record[8..]);
+    record[4..8].copy_from_slice(&crc.to_le_bytes());
+
+    flash.erase(offset, offset + FlashStorage::SECTOR_SIZE)?;
+    flash.write(offset, &record)?;
+    Ok(())
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo<'_>) -> ! {
+    if PANICKED.swap(true, Ordering::Relaxed) {
+        esp_hal::system::software_reset();
+    }
+    error!("====================== PANIC ======================");
+    error!("{}", defmt::Display2Format(info));
+
+    let backtrace = esp_backtrace::Backtrace::capture();
+    let mut frames = [0; MAX_FRAMES];
+    let mut count = 0;
+    for (slot, frame) in frames.iter_mut().zip(backtrace.frames()) {
+        *slot = frame.program_counter() as u32;
+        error!("0x{:x}", *slot);
+        count += 1;
+    }
+
+    if let Err(err) = save(info, &frames[..count]) {
+        error!("panic_log: not saved: {}", err);
+    }
+    esp_hal::system::software_reset()
+}
@@ -0,0 +1,592 @@
+//! Captive portal for editing the nametag from a phone, behind the `portal`
+//! feature.
+//!
+//! The badge opens an access point of its own. Joining it pops up the
+//! sign-in page phones show for hotel Wi-Fi, which here is a form for the
+//! name, colours and LED effect. Submitting it saves a [`Nametag`] and a
+//! [`LedProfile`] to the store and hands them to the app through
+//! [`Portal::changed`], so nothing needs rebuilding with a new `badge.toml`.
+//!
+//! ```rust,ignore
+//! static PORTAL: Portal = Portal::new();
+//!
+//! #[embassy_executor::task]
+//! async fn portal_task(wifi: WifiResources<'static>, store: &'static mut MyStore) {
+//!     let (wifi, device) = Wifi::access_point(wifi).unwrap();
+//!     PORTAL.run(wifi, device, store, "disobey-badge").await
+//! }
+//!
+//! // in the nametag task
+//! let mut nametag = Nametag::restore(&mut store);
+//! loop {
+//!     draw(&nametag);
+//!     nametag = PORTAL.changed().await.nametag;
+//! }
+//! ```
+//!
+//! Under the hood the badge takes [`PORTAL_IP`] and runs a DHCP server that
+//! names itself router and DNS server, a DNS server answering every name with
+//! [`PORTAL_IP`], and plain HTTP on port 80 that redirects any page but the
+//! form back to it. The access point is open: anyone in range can change the
+//! nametag while the portal runs.
+
+use alloc::string::String;
+use core::{
+    fmt::Write as _,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+    },
+};
+
+use defmt::{
+    Debug2Format,
+    error,
+    info,
+    warn,
+};
+use edge_dhcp::{
+    io::{
+        DEFAULT_SERVER_PORT,
+        server,
+    },
+    server::{
+        Server,
+        ServerOptions,
+    },
+};
+use edge_nal::UdpBind as _;
+use edge_nal_embassy::{
+    Udp,
+    UdpBuffers,
+};
+use embassy_futures::join::join4;
+use embassy_net::{
+    Config,
+    Ipv4Cidr,
+    StackResources,
+    StaticConfigV4,
+    tcp::{
+        self,
+        TcpSocket,
+    },
+};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    signal::Signal,
+};
+use embassy_time::{
+    Duration,
+    Timer,
+};
+use palette::Srgb;
+
+use crate::{
+    LedProfile,
+    NAMETAG_NAME_LEN,
+    Nametag,
+    config::{
+        Background,
+        LedEffect,
+        Theme,
+    },
+    storage::Store,
+    wifi::{
+        Wifi,
+        WifiDevice,
+    },
+};
+
+/// The badge's address on its own network.
+pub const PORTAL_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 4, 1);
+/// Where phones are sent to sign in, announced over DHCP.
+pub const PORTAL_URL: &str = "http://192.168.4.1/";
+/// How long a client may take to send a request or read the reply.
+pub const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a failed DHCP or DNS server waits before starting again.
+pub const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// DHCP and DNS over UDP, HTTP over TCP.
+const SOCKETS: usize = 3;
+/// Clients that can hold an address at once.
+const LEASES: usize = 8;
+/// Largest request read: headers and form body together.
+const REQUEST_LEN: usize = 1536;
+/// How long phones may cache the DNS answers.
+const DNS_TTL: core::time::Duration = core::time::Duration::from_secs(60);
+
+/// What the form was last submitted with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortalEdit {
+    pub nametag: Nametag,
+    pub leds: LedProfile,
+    /// `false` if the store failed to write; the edit then only lasts until
+    /// reboot.
+    pub saved: bool,
+}
+
+/// The portal and the latest edit made through it. See the
+/// [module docs](self).
+pub struct Portal {
+    changed: Signal<CriticalSectionRawMutex, PortalEdit>,
+}
+
+impl Default for Portal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Portal {
+    pub const fn new() -> Self {
+        Self {
+            changed: Signal::new(),
+        }
+    }
+
+    /// Open the access point `ssid` and serve the form forever, starting
+    /// from what `store` holds.
+    ///
+    /// An access point that fails to start is logged and this never
+    /// returns; the DHCP and DNS servers restart after [`RETRY_DELAY`] if
+    /// they fail.
+    pub async fn run<S: Store + ?Sized>(
+        &self,
+        mut wifi: Wifi,
+        device: WifiDevice<'static>,
+        store: &mut S,
+        ssid: &str,
+    ) -> ! {
+        if let Err(err) = wifi.start_access_point(ssid, "").await {
+            error!("portal: access point failed: {}", err);
+            core::future::pending().await
+        }
+
+        let config = Config::ipv4_static(StaticConfigV4 {
+            address: Ipv4Cidr::new(PORTAL_IP, 24),
+            gateway: Some(PORTAL_IP),
+            dns_servers: Default::default(),
+        });
+        let mut rng = crate::rng();
+        let seed = u64::from(rng.next_u32()) << 32 | u64::from(rng.next_u32());
+        let mut resources = StackResources::<SOCKETS>::new();
+        let (stack, mut runner) = embassy_net::new(device, config, &mut resources, seed);
+        let buffers = UdpBuffers::<2, 600, 600>::new();
+        let udp = Udp::new(stack, &buffers);
+
+        let dhcp = async {
+            let mut buf = [0; 600];
+            loop {
+                match udp
+                    .bind(SocketAddr::from((
+                        Ipv4Addr::UNSPECIFIED,
+                        DEFAULT_SERVER_PORT,
+                    )))
+                    .await
+                {
+                    Ok(mut socket) => {
+                        let mut server = Server::<_, LEASES>::new_with_et(PORTAL_IP);
+                        let mut gateways = [PORTAL_IP];
+                        let mut options = ServerOptions::new(PORTAL_IP, Some(&mut gateways));
+                        let dns = [PORTAL_IP];
+                        options.dns = &dns;
+                        options.captive_url = Some(PORTAL_URL);
+                        if let Err(err) =
+                            server::run(&mut server, &options, &mut socket, &mut buf).await
+                        {
+                            warn!("portal: dhcp: {}", Debug2Format(&err));
+                        }
+                    }
+                    Err(err) => warn!("portal: dhcp: {}", err),
+                }
+                Timer::after(RETRY_DELAY).await;
+            }
+        };
+        let dns = async {
+            let mut tx = [0; 512];
+            let mut rx = [0; 512];
+            loop {
+                let local = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 53));
+                if let Err(err) =
+                    edge_captive::io::run(&udp, local, &mut tx, &mut rx, PORTAL_IP, DNS_TTL).await
+                {
+                    warn!("portal: dns: {}", Debug2Format(&err));
+                }
+                Timer::after(RETRY_DELAY).await;
+            }
+        };
+        let http = async {
+            let mut edit = PortalEdit {
+                nametag: Nametag::restore(store),
+                leds: LedProfile::restore(store),
+                saved: true,
+            };
+            let mut rx = [0; 1024];
+            let mut tx = [0; 1024];
+            let mut request = [0; REQUEST_LEN];
+            info!("portal: serving {}", PORTAL_URL);
+            loop {
+                let mut socket = TcpSocket::new(stack, &mut rx, &mut tx);
+                socket.set_timeout(Some(HTTP_TIMEOUT));
+                if let Err(err) = socket.accept(80).await {
+                    warn!("portal: accept: {}", err);
+                    continue;
+                }
+                if let Err(err) = self
+                    .serve(&mut socket, &mut request, store, &mut edit)
+                    .await
+                {
+                    warn!("portal: http: {}", err);
+                }
+                socket.close();
+                socket.flush().await.ok();
+            }
+        };
+        match join4(runner.run(), dhcp, dns, http).await {}
+    }
+
+    /// Wait for the form to be submitted.
+    pub async fn changed(&self) -> PortalEdit {
+        self.changed.wait().await
+    }
+
+    /// Answer one request: the form, a submission of it, or a redirect to it
+    /// for anything else, which is what makes phones show it on joining.
+    async fn serve<S: Store + ?Sized>(
+        &self,
+        socket: &mut TcpSocket<'_>,
+        buf: &mut [u8],
+        store: &mut S,
+        edit: &mut PortalEdit,
+    ) -> Result<(), tcp::Error> {
+        let len = read_request(socket, buf).await?;
+        let request = &buf[..len];
+        let mut line = request
+            .split(|&b| b == b'\r' || b == b'\n')
+            .next()
+            .unwrap_or_default()
+            .split(|&b| b == b' ');
+        let (method, path) = (
+            line.next().unwrap_or_default(),
+            line.next().unwrap_or_default(),
+        );
+        let path = path.split(|&b| b == b'?').next().unwrap_or_default();
+
+        let response = match method {
+            b"POST" => {
+                let body = find(request, b"\r\n\r\n").map_or(&[][..], |end| &request[end + 4..]);
+                *edit = apply_form(body, edit);
+                edit.saved = edit.nametag.save(store).is_ok() && edit.leds.save(store).is_ok();
+                info!("portal: nametag is now {}", edit.nametag.name());
+                self.changed.signal(*edit);
+                let message = if edit.saved {
+                    "Saved."
+                } else {
+                    "Applied, but couldn't be saved; it lasts until reboot."
+                };
+                page(edit, Some(message))
+            }
+            _ if path == b"/" => page(edit, None),
+            _ => {
+                let mut response = String::new();
+                write!(
+                    response,
+                    "HTTP/1.1 302 Found\r\nLocation: {PORTAL_URL}\r\n\
+                     Content-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .ok();
+                response
+            }
+        };
+        write_all(socket, response.as_bytes()).await
+    }
+}
+
+/// Read a request's headers and as much of its body as `Content-Length`
+/// promises, or until `buf` is full.
+async fn read_request(socket: &mut TcpSocket<'_>, buf: &mut [u8]) -> Result<usize, tcp::Error> {
+    let mut len = 0;
+    while len < buf.len() {
+        let read = socket.read(&mut buf[len..]).await?;
+        if read == 0 {
+            break;
+        }
+        len += read;
+        if let Some(end) = find(&buf[..len], b"\r\n\r\n") {
+            let headers = &buf[..end];
+            let body = headers
+                .split(|&b| b == b'\n')
+                .filter_map(|line| {
+                    let (name, value) = line.split_at(line.iter().position(|&b| b == b':')?);
+                    name.eq_ignore_ascii_case(b"content-length")
+                        .then_some(&value[1..])
+                })
+                .find_map(|value| {
+                    core::str::from_utf8(value)
+                        .ok()?
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                })
+                .unwrap_or(0);
+            if len >= end + 4 + body {
+                break;
+            }
+        }
+    }
+    Ok(len)
+}
+
+async fn write_all(socket: &mut TcpSocket<'_>, mut data: &[u8]) -> Result<(), tcp::Error> {
+    while !data.is_empty() {
+        let written = socket.write(data).await?;
+        if written == 0 {
+            return Err(tcp::Error::ConnectionReset);
+        }
+        data = &data[written..];
+    }
+    socket.flush().await
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// ── Form ────────────────────────────────────────────────────────────────────
+
+/// `edit` with the fields of an `application/x-www-form-urlencoded` `body`
+/// applied. Missing or malformed fields keep their old value.
+fn apply_form(body: &[u8], edit: &PortalEdit) -> PortalEdit {
+    let mut name = None;
+    let Theme {
+        mut background,
+        mut foreground,
+    } = edit.nametag.theme;
+    let mut background_color = None;
+    let mut effect = edit.leds.effect;
+    let mut led_color = None;
+    let mut brightness = edit.leds.brightness;
+
+    for field in body.split(|&b| b == b'&') {
+        let Some(split) = field.iter().position(|&b| b == b'=') else {
+            continue;
+        };
+        let (key, value) = (&field[..split], &field[split + 1..]);
+        let mut value_buf = [0; 16];
+        match key {
+            b"name" => name = Some(value),
+            b"background" => {
+                background = match value {
+                    b"solid" => Background::Solid(Srgb::new(0, 0, 0)),
+                    b"rainbow" => Background::Rainbow,
+                    b"retrofuture" => Background::Retrofuture,
+                    b"hearts" => Background::Hearts,
+                    _ => background,
+                };
+            }
+            b"bg" => background_color = parse_color(decode(value, &mut value_buf)),
+            b"fg" => {
+                foreground = parse_color(decode(value, &mut value_buf)).unwrap_or(foreground);
+            }
+            b"leds" => {
+                effect = match value {
+                    b"off" => LedEffect::Off,
+                    b"heartbeat" => LedEffect::Heartbeat,
+                    b"rainbow" => LedEffect::Rainbow,
+                    b"solid" => LedEffect::Solid(Srgb::new(0, 0, 0)),
+                    _ => effect,
+                };
+            }
+            b"led" => led_color = parse_color(decode(value, &mut value_buf)),
+            b"brightness" => {
+                brightness = decode(value, &mut value_buf).parse().unwrap_or(brightness);
+            }
+            _ => {}
+        }
+    }
+
+    // The colour pickers always submit, whichever style is chosen; only
+    // solid styles use them.
+    if let Background::Solid(color) = &mut background {
+        *color = background_color.unwrap_or(match edit.nametag.theme.background {
+            Background::Solid(old) => old,
+            _ => *color,
+        });
+    }
+    if let LedEffect::Solid(color) = &mut effect {
+        *color = led_color.unwrap_or(match edit.leds.effect {
+            LedEffect::Solid(old) => old,
+            _ => *color,
+        });
+    }
+
+    let mut name_buf = [0; NAMETAG_NAME_LEN];
+    let name = name.map_or(edit.nametag.name(), |name| decode(name, &mut name_buf));
+    PortalEdit {
+        nametag: Nametag::new(
+            name,
+            Theme {
+                background,
+                foreground,
+            },
+        ),
+        leds: LedProfile::new(effect, brightness).with_name("portal"),
+        saved: edit.saved,
+    }
+}
+
+/// Undo URL encoding of `value` into `buf`, keeping the longest whole-UTF-8
+/// prefix that fits.
+fn decode<'a>(value: &[u8], buf: &'a mut [u8]) -> &'a str {
+    let mut len = 0;
+    let mut bytes = value.iter();
+    while len < buf.len()
+        && let Some(&byte) = bytes.next()
+    {
+        buf[len] = match byte {
+            b'+' => b' ',
+            b'%' => {
+                let hex = [
+                    *bytes.next().unwrap_or(&b'0'),
+                    *bytes.next().unwrap_or(&b'0'),
+                ];
+                core::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .unwrap_or(b'?')
+            }
+            byte => byte,
+        };
+        len += 1;
+    }
+    match core::str::from_utf8(&buf[..len]) {
+        Ok(text) => text,
+        Err(err) => core::str::from_utf8(&buf[..err.valid_up_to()]).unwrap_or_default(),
+    }
+}
+
+/// A `#rrggbb` colour as `<input type="color">` sends it.
+fn parse_color(text: &str) -> Option<Srgb<u8>> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some(Srgb::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+/// The form filled in with `edit`, as a whole HTTP response.
+fn page(edit: &PortalEdit, message: Option<&str>) -> String {
+    let Theme {
+        background,
+        foreground,
+    } = edit.nametag.theme;
+    let background_color = match background {
+        Background::Solid(color) => color,
+        _ => Srgb::new(0, 0, 0),
+    };
+    let led_color = match edit.leds.effect {
+        LedEffect::Solid(color) => color,
+        _ => Srgb::new(255, 255, 255),
+    };
+
+    let mut body = String::new();
+    body.push_str(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width\"><title>Nametag</title>\
+         <style>body{font-family:sans-serif;max-width:24em;margin:auto;padding:1em}\
+         label{display:block;margin-top:1em}</style></head><body><h1>Nametag</h1>",
+    );
+    if let Some(message) = message {
+        write!(body, "<p><b>{message}</b></p>").ok();
+    }
+    body.push_str("<form method=\"post\" action=\"/\"><label>Name <input name=\"name\" value=\"");
+    escape(&mut body, edit.nametag.name());
+    write!(body, "\" maxlength=\"{NAMETAG_NAME_LEN}\"></label>").ok();
+
+    body.push_str("<label>Background <select name=\"background\">");
+    for (value, label, selected) in [
+        ("solid", "Solid", matches!(background, Background::Solid(_))),
+        ("rainbow", "Rainbow", background == Background::Rainbow),
+        (
+            "retrofuture",
+            "Retrofuture",
+            background == Background::Retrofuture,
+        ),
+        ("hearts", "Hearts", background == Background::Hearts),
+    ] {
+        option(&mut body, value, label, selected);
+    }
+    body.push_str("</select> ");
+    color_input(&mut body, "bg", background_color);
+    body.push_str("</label><label>Text ");
+    color_input(&mut body, "fg", foreground);
+
+    body.push_str("</label><label>LEDs <select name=\"leds\">");
+    for (value, label, selected) in [
+        ("off", "Off", edit.leds.effect == LedEffect::Off),
+        (
+            "heartbeat",
+            "Heartbeat",
+            edit.leds.effect == LedEffect::Heartbeat,
+        ),
+        ("rainbow", "Rainbow", edit.leds.effect == LedEffect::Rainbow),
+        (
+            "solid",
+            "Solid",
+            matches!(edit.leds.effect, LedEffect::Solid(_)),
+        ),
+    ] {
+        option(&mut body, value, label, selected);
+    }
+    body.push_str("</select> ");
+    color_input(&mut body, "led", led_color);
+    write!(
+        body,
+        "</label><label>LED brightness <input type=\"range\" name=\"brightness\" \
+         min=\"0\" max=\"255\" value=\"{}\"></label>\
+         <p><button>Save</button></p></form></body></html>",
+        edit.leds.brightness
+    )
+    .ok();
+
+    let mut response = String::new();
+    write!(
+        response,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+         Content-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .ok();
+    response
+}
+
+fn option(body: &mut String, value: &str, label: &str, selected: bool) {
+    let selected = if selected { " selected" } else { "" };
+    write!(body, "<option value=\"{value}\"{selected}>{label}</option>").ok();
+}
+
+fn color_input(body: &mut String, name: &str, color: Srgb<u8>) {
+    write!(
+        body,
+        "<input type=\"color\" name=\"{name}\" value=\"#{:02x}{:02x}{:02x}\">",
+        color.red, color.green, color.blue
+    )
+    .ok();
+}
+
+/// Append `text` with the characters HTML gives meaning to escaped.
+fn escape(body: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '&' => body.push_str("&amp;"),
+            '<' => body.push_str("&lt;"),
+            '>' => body.push_str("&gt;"),
+            '"' => body.push_str("&quot;"),
+            '\'' => body.push_str("&#39;"),
+            ch => body.push(ch),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Badge-wide power budget.
+//!
+//! Subsystems that can trade quality for battery life (LED brightness,
+//! backlight level and idle timeout, frame rate, radio duty cycle) register
+//! with [`BUDGET`] and follow the [`Allowance`] it hands out. Flipping the
+//! single battery-saver switch then adjusts all of them together.
+//!
+//! The crate's own drivers follow it themselves: [`IdleDimmer`] caps the
+//! backlight and picks how soon and how far it dims, [`Leds::follow`]
+//! applies each change before the next frame, and
+//! `Wifi::stay_connected` sets the radio's power saving. Anything else
+//! registers and applies the allowance its own way:
+//!
+//! ```rust,ignore
+//! // In the LED task:
+//! leds.follow(power::BUDGET.register().unwrap());
+//! let limits = led_anim::Limits::from(power::BUDGET.allowance());
+//!
+//! // In the settings menu:
+//! power::BUDGET.set_mode(power::Mode::Saver);
+//! ```
+//!
+//! [`IdleDimmer`]: crate::IdleDimmer
+//! [`Leds::follow`]: crate::Leds::follow
+
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    watch::{
+        Receiver,
+        Watch,
+    },
+};
+use embassy_time::Duration;
+
+use crate::{
+    DEFAULT_DIM_AFTER,
+    led_anim,
+};
+
+/// How many subsystems can [`register`](Budget::register) at once.
+pub const MAX_SUBSYSTEMS: usize = 8;
+
+/// The shared budget every subsystem follows.
+pub static BUDGET: Budget = Budget::new();
+
+/// What each subsystem may spend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Allowance {
+    /// Brightest any LED channel may be driven (0–255).
+    pub led_brightness: u8,
+    /// Brightest the display backlight may be (0–255).
+    pub backlight_brightness: u8,
+    /// Shortest time between rendered frames.
+    pub frame_interval: Duration,
+    /// Share of time the radio may be listening, in percent.
+    pub radio_duty_pct: u8,
+    /// Longest the backlight stays fully bright without input.
+    pub dim_after: Duration,
+    /// Where the display goes once the badge is left alone.
+    pub idle_display: IdleDisplay,
+}
+
+/// What the display does once the badge is left alone, after the backlight
+/// has dimmed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum IdleDisplay {
+    /// A band of the panel keeps showing the time, with the backlight off;
+    /// see [`IdleDimmer::run_glance`](crate::IdleDimmer::run_glance).
+    Glance,
+    /// The backlight goes off and the panel shows nothing.
+    Dark,
+}
+
+/// Preset allowances behind the user-facing battery-saver toggle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Mode {
+    Normal,
+    /// Dimmer LEDs and backlight, 20 fps, radio mostly asleep, and a
+    /// display that dims sooner and goes dark rather than to glance mode.
+    Saver,
+}
+
+impl Mode {
+    pub const fn allowance(self) -> Allowance {
+        match self {
+            Self::Normal => Allowance {
+                led_brightness: 255,
+                backlight_brightness: 255,
+                frame_interval: Duration::from_millis(16),
+                radio_duty_pct: 100,
+                dim_after: DEFAULT_DIM_AFTER,
+                idle_display: IdleDisplay::Glance,
+            },
+            Self::Saver => Allowance {
+                led_brightness: 32,
+                backlight_brightness: 96,
+                frame_interval: Duration::from_millis(50),
+                radio_duty_pct: 10,
+                dim_after: Duration::from_secs(10),
+                idle_display: IdleDisplay::Dark,
+            },
+        }
+    }
+}
+
+/// Central power budget, broadcasting the current [`Allowance`].
+pub struct Budget {
+    watch: Watch<CriticalSectionRawMutex, Allowance, MAX_SUBSYSTEMS>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Budget {
+    /// A budget starting in [`Mode::Normal`].
+    pub const fn new() -> Self {
+        Self {
+            watch: Watch::new_with(Mode::Normal.allowance()),
+        }
+    }
+
+    /// Switch every registered subsystem to a preset.
+    pub fn set_mode(&self, mode: Mode) {
+        self.set(mode.allowance());
+    }
+
+    /// Switch every registered subsystem to a custom allowance.
+    pub fn set(&self, allowance: Allowance) {
+        defmt::info!("power: {}", allowance);
+        self.watch.sender().send(allowance);
+    }
+
+    /// The allowance in force right now.
+    pub fn allowance(&self) -> Allowance {
+        self.watch
+            .try_get()
+            .unwrap_or_else(|| Mode::Normal.allowance())
+    }
+
+    /// Register a subsystem, or `None` if [`MAX_SUBSYSTEMS`] already have.
+    ///
+    /// Dropping the returned handle frees its slot.
+    pub fn register(&self) -> Option<Subsystem<'_>> {
+        self.watch.receiver().map(|receiver| Subsystem { receiver })
+    }
+}
+
+/// A registered subsystem's view of the [`Budget`].
+pub struct Subsystem<'a> {
+    receiver: Receiver<'a, CriticalSectionRawMutex, Allowance, MAX_SUBSYSTEMS>,
+}
+
+impl Subsystem<'_> {
+    /// The allowance in force right now.
+    pub fn allowance(&mut self) -> Allowance {
+        self.receiver
+            .try_get()
+            .unwrap_or_else(|| Mode::Normal.allowance())
+    }
+
+    /// Wait until the allowance changes, then return the new one.
+    pub async fn changed(&mut self) -> Allowance {
+        self.receiver.changed().await
+    }
+
+    /// The new allowance if it changed since last asked, without waiting.
+    pub fn try_changed(&mut self) -> Option<Allowance> {
+        self.receiver.try_changed()
+    }
+}
+
+impl From<Allowance> for led_anim::Limits {
+    fn from(allowance: Allowance) -> Self {
+        Self {
+            max_brightness: allowance.led_brightness,
+            min_frame: allowance.frame_interval,
+        }
+    }
+}
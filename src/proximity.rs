@@ -0,0 +1,313 @@
+//! How close other badges are, from the signal strength they're heard at.
+//!
+//! RSSI jumps around by several dB from one packet to the next, so
+//! [`Proximity`] smooths it and sorts it into a [`Zone`], with some
+//! hysteresis so a badge right on a boundary doesn't flicker between two.
+//! [`Tracker`] does that for every badge in range and says when one changes
+//! zone, which is what a "light up when a friend is close" effect needs:
+//!
+//! ```rust,ignore
+//! let mut tracker: Tracker<BadgeId, 8> = Tracker::new(Calibration::ESP_NOW);
+//! loop {
+//!     for peer in LINK.lobby().into_iter().flatten() {
+//!         if let Some(change) = tracker.update(peer.id, peer.rssi, peer.seen) {
+//!             info!("{} is now {}", change.id, change.to);
+//!         }
+//!     }
+//!     while let Some(change) = tracker.expire(Instant::now()) {
+//!         info!("{} went away", change.id);
+//!     }
+//!     Timer::after_millis(100).await;
+//! }
+//! ```
+//!
+//! Readings come from anything that reports RSSI: the game link lobby
+//! (`gamelink`), BLE beacons (`ble::beacon`) or raw ESP-NOW frames. Radios differ in transmit power, hence a [`Calibration`]
+//! for each. Distances are rough: bodies, walls and how the badge hangs
+//! all change the signal more than a metre or two does.
+
+use embassy_time::{
+    Duration,
+    Instant,
+};
+
+/// How much of each new reading goes into the smoothed RSSI; the rest is
+/// the old value.
+pub const SMOOTHING: f32 = 0.3;
+/// How much the signal weakens with distance: 2 in open space, more with
+/// people in the way.
+pub const PATH_LOSS_EXPONENT: f32 = 2.5;
+/// A badge not heard for this long is [`Zone::Far`] in a [`Tracker`].
+pub const LOST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How close a badge is, from farthest to closest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, defmt::Format)]
+pub enum Zone {
+    /// Out of range, or farther than [`Near`](Self::Near).
+    Far,
+    /// In the same room, within a few metres.
+    Near,
+    /// Within arm's reach.
+    Close,
+    /// Badges held together.
+    Touching,
+}
+
+/// Where the [`Zone`] boundaries lie for one radio, in dBm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Calibration {
+    /// Weakest smoothed RSSI that counts as [`Zone::Near`].
+    pub near: i8,
+    /// Weakest smoothed RSSI that counts as [`Zone::Close`].
+    pub close: i8,
+    /// Weakest smoothed RSSI that counts as [`Zone::Touching`].
+    pub touching: i8,
+    /// How far below a boundary the RSSI must drop to leave a zone.
+    pub hysteresis: u8,
+    /// RSSI at one metre, for [`Proximity::distance`].
+    pub one_metre: i8,
+}
+
+impl Calibration {
+    /// For badges heard over ESP-NOW, such as in the game link lobby.
+    pub const ESP_NOW: Self = Self {
+        near: -75,
+        close: -55,
+        touching: -40,
+        hysteresis: 4,
+        one_metre: -45,
+    };
+    /// For badges heard as BLE beacons, which transmit more quietly.
+    pub const BLE: Self = Self {
+        near: -85,
+        close: -65,
+        touching: -50,
+        hysteresis: 4,
+        one_metre: -60,
+    };
+
+    /// The zone `rssi` falls in, ignoring hysteresis.
+    pub fn zone(&self, rssi: f32) -> Zone {
+        if rssi >= f32::from(self.touching) {
+            Zone::Touching
+        } else if rssi >= f32::from(self.close) {
+            Zone::Close
+        } else if rssi >= f32::from(self.near) {
+            Zone::Near
+        } else {
+            Zone::Far
+        }
+    }
+
+    /// The RSSI where `zone` starts.
+    const fn threshold(&self, zone: Zone) -> i8 {
+        match zone {
+            Zone::Far => i8::MIN,
+            Zone::Near => self.near,
+            Zone::Close => self.close,
+            Zone::Touching => self.touching,
+        }
+    }
+}
+
+/// Smoothed signal strength and zone of one badge.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Proximity {
+    calibration: Calibration,
+    rssi: Option<f32>,
+    zone: Zone,
+}
+
+impl Proximity {
+    /// Nothing heard yet: [`Zone::Far`].
+    pub const fn new(calibration: Calibration) -> Self {
+        Self {
+            calibration,
+            rssi: None,
+            zone: Zone::Far,
+        }
+    }
+
+    /// Add a reading of `rssi` dBm. Returns the new zone if it changed.
+    ///
+    /// A badge moves into a closer zone as soon as the smoothed RSSI
+    /// reaches its boundary, but only leaves once it's
+    /// [`hysteresis`](Calibration::hysteresis) dB below.
+    pub fn update(&mut self, rssi: i8) -> Option<Zone> {
+        let rssi = match self.rssi {
+            Some(smoothed) => smoothed + SMOOTHING * (f32::from(rssi) - smoothed),
+            None => f32::from(rssi),
+        };
+        self.rssi = Some(rssi);
+        let calibration = &self.calibration;
+        let mut zone = calibration.zone(rssi);
+        if zone < self.zone {
+            let leave =
+                f32::from(calibration.threshold(self.zone)) - f32::from(calibration.hysteresis);
+            if rssi >= leave {
+                return None;
+            }
+            zone = calibration.zone(rssi + f32::from(calibration.hysteresis));
+        }
+        (zone != self.zone).then(|| {
+            self.zone = zone;
+            zone
+        })
+    }
+
+    /// Forget the readings, as when the badge goes away.
+    pub fn reset(&mut self) {
+        self.rssi = None;
+        self.zone = Zone::Far;
+    }
+
+    pub const fn zone(&self) -> Zone {
+        self.zone
+    }
+
+    /// Smoothed RSSI in dBm, if anything has been heard.
+    pub const fn rssi(&self) -> Option<f32> {
+        self.rssi
+    }
+
+    /// Rough distance in metres, if anything has been heard.
+    pub fn distance(&self) -> Option<f32> {
+        let loss = f32::from(self.calibration.one_metre) - self.rssi?;
+        Some(libm::powf(10.0, loss / (10.0 * PATH_LOSS_EXPONENT)))
+    }
+}
+
+/// A badge that moved to another [`Zone`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Change<K> {
+    pub id: K,
+    pub from: Zone,
+    pub to: Zone,
+}
+
+#[derive(Clone, Copy)]
+struct Peer<K> {
+    id: K,
+    proximity: Proximity,
+    heard: Instant,
+}
+
+/// [`Proximity`] of up to `N` badges, told apart by `K`: a
+/// [`BadgeId`](crate::BadgeId), a Bluetooth address or the like. See the
+/// [module docs](self).
+pub struct Tracker<K, const N: usize> {
+    calibration: Calibration,
+    timeout: Duration,
+    peers: [Option<Peer<K>>; N],
+}
+
+impl<K: Copy + PartialEq, const N: usize> Tracker<K, N> {
+    pub const fn new(calibration: Calibration) -> Self {
+        Self {
+            calibration,
+            timeout: LOST_TIMEOUT,
+            peers: [const { None }; N],
+        }
+    }
+
+    /// Count a badge as gone after `timeout` without a reading, instead of
+    /// [`LOST_TIMEOUT`].
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Add a reading of `rssi` dBm from `id`, taken at `heard`. Returns
+    /// the change if the badge moved to another zone.
+    ///
+    /// A reading with the same `heard` as the last one is skipped, so a
+    /// list that is polled faster than it's updated, like
+    /// `GameLink::lobby`, can be fed in whole each time. When all `N` slots are taken, a new badge
+    /// replaces the one heard longest ago.
+    pub fn update(&mut self, id: K, rssi: i8, heard: Instant) -> Option<Change<K>> {
+        let slot = match self
+            .peers
+            .iter()
+            .position(|slot| slot.is_some_and(|peer| peer.id == id))
+        {
+            Some(index) => index,
+            None => self
+                .peers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, slot)| slot.map(|peer| peer.heard))
+                .map(|(index, _)| index)?,
+        };
+        let peer = match &mut self.peers[slot] {
+            Some(peer) if peer.id == id => {
+                if peer.heard == heard {
+                    return None;
+                }
+                peer
+            }
+            other => other.insert(Peer {
+                id,
+                proximity: Proximity::new(self.calibration),
+                heard,
+            }),
+        };
+        peer.heard = heard;
+        let from = peer.proximity.zone();
+        let to = peer.proximity.update(rssi)?;
+        Some(Change { id, from, to })
+    }
+
+    /// Drop one badge not heard from since `now` minus the timeout.
+    /// Returns its change to [`Zone::Far`] if it was closer; call it until
+    /// it returns `None`.
+    pub fn expire(&mut self, now: Instant) -> Option<Change<K>> {
+        loop {
+            let slot = self.peers.iter_mut().find(|slot| {
+                slot.is_some_and(|peer| now.saturating_duration_since(peer.heard) > self.timeout)
+            })?;
+            let peer = slot.take()?;
+            let from = peer.proximity.zone();
+            if from != Zone::Far {
+                return Some(Change {
+                    id: peer.id,
+                    from,
+                    to: Zone::Far,
+                });
+            }
+        }
+    }
+
+    /// Zone of `id`; [`Zone::Far`] if it isn't tracked.
+    pub fn zone(&self, id: K) -> Zone {
+        self.get(id).map_or(Zone::Far, Proximity::zone)
+    }
+
+    /// Proximity of `id`, if it's tracked.
+    pub fn get(&self, id: K) -> Option<&Proximity> {
+        self.peers
+            .iter()
+            .flatten()
+            .find(|peer| peer.id == id)
+            .map(|peer| &peer.proximity)
+    }
+
+    /// The closest badge tracked, if any is nearer than [`Zone::Far`].
+    pub fn closest(&self) -> Option<(K, &Proximity)> {
+        self.iter()
+            .filter(|(_, proximity)| proximity.zone() > Zone::Far)
+            .max_by(|a, b| {
+                a.1.rssi()
+                    .partial_cmp(&b.1.rssi())
+                    .unwrap_or(core::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Every badge tracked, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (K, &Proximity)> {
+        self.peers
+            .iter()
+            .flatten()
+            .map(|peer| (peer.id, &peer.proximity))
+    }
+}
@@ -0,0 +1,21 @@
+//! The one `esp-radio` controller that Wi-Fi and Bluetooth share.
+
+use embassy_sync::once_lock::OnceLock;
+use esp_radio::{
+    Controller,
+    InitializationError,
+};
+
+static CONTROLLER: OnceLock<Controller<'static>> = OnceLock::new();
+
+/// Start the radio driver on first use.
+///
+/// Needs the heap and `esp_rtos::start` to be set up already, and
+/// interrupts enabled, so it can't run inside a critical section.
+pub(crate) fn controller() -> Result<&'static Controller<'static>, InitializationError> {
+    if let Some(controller) = CONTROLLER.try_get() {
+        return Ok(controller);
+    }
+    let controller = esp_radio::init()?;
+    Ok(CONTROLLER.get_or_init(|| controller))
+}
@@ -0,0 +1,302 @@
+//! The badge as a small wireless monitor, behind the `remote-display`
+//! feature: a laptop on the same network streams frames over TCP and the
+//! badge draws them.
+//!
+//! ```rust,ignore
+//! static SCREEN: RemoteDisplay = RemoteDisplay::new();
+//!
+//! let (stack, runner) = http::stack(device, resources);
+//! spawner.must_spawn(net_task(runner));
+//! stack.wait_config_up().await;
+//! SCREEN.run(stack, display).await
+//! ```
+//!
+//! A frame is sent as horizontal strips of up to [`MAX_ROWS`] rows, so the
+//! badge never holds more than one strip. On connecting, the badge sends a
+//! hello, all fields little-endian:
+//!
+//! | Bytes | Field                                               |
+//! |-------|-----------------------------------------------------|
+//! | 2     | [`MAGIC`], `b"RD"`                                  |
+//! | 1     | [`VERSION`]                                         |
+//! | 1     | strips the sender may have in flight, [`CREDITS`]   |
+//! | 1     | most rows in a strip, [`MAX_ROWS`]                  |
+//! | 2     | width, 320                                          |
+//! | 2     | height, 170                                         |
+//!
+//! Then each strip is a header and its pixels:
+//!
+//! | Bytes | Field                                               |
+//! |-------|-----------------------------------------------------|
+//! | 1     | [`RLE`] if run-length encoded, plus [`END_OF_FRAME`] on the last strip of a frame |
+//! | 1     | first row                                           |
+//! | 1     | rows, 1 to [`MAX_ROWS`]                             |
+//! | 2     | length of the pixel data in bytes                   |
+//! | len   | pixel data                                          |
+//!
+//! Raw pixel data is every pixel of the strip as RGB565, row by row. RLE
+//! data is runs of a count (1 to 255) and an RGB565 colour, 3 bytes each,
+//! that add up to every pixel of the strip; either way the data must fit in
+//! a raw strip of [`MAX_ROWS`] rows, so send noisy strips raw.
+//!
+//! Flow control: the badge answers each strip with one [`ACK`] byte once
+//! it's on the panel, and a sender keeps at most [`CREDITS`] strips
+//! unacknowledged. A sender that ignores this only fills the TCP window,
+//! since the badge reads no faster than it draws. A strip that breaks the
+//! rules closes the connection. To stream a test pattern from a laptop:
+//!
+//! ```text
+//! import socket, struct, sys
+//!
+//! sock = socket.create_connection((sys.argv[1], 5320))
+//! f = sock.makefile("rb")
+//! magic, version, credits, max_rows, width, height = struct.unpack("<2sBBBHH", f.read(9))
+//! in_flight = t = 0
+//! while True:
+//!     for y in range(0, height, max_rows):
+//!         rows = min(max_rows, height - y)
+//!         data = b"".join(
+//!             struct.pack("<H", ((x + t) >> 3 & 31) << 11 | (row + t) & 63)
+//!             for row in range(y, y + rows)
+//!             for x in range(width)
+//!         )
+//!         if in_flight == credits:
+//!             f.read(1)
+//!             in_flight -= 1
+//!         last = 0x80 if y + rows == height else 0
+//!         sock.sendall(struct.pack("<BBBH", last, y, rows, len(data)) + data)
+//!         in_flight += 1
+//!     t += 4
+//! ```
+//!
+//! Only one sender is served at a time; the next connects once it leaves.
+//! TCP is used rather than UDP so a lost packet can't tear a frame.
+
+use core::{
+    iter,
+    sync::atomic::{
+        AtomicBool,
+        AtomicU32,
+        Ordering,
+    },
+};
+
+use defmt::{
+    info,
+    warn,
+};
+use embassy_net::{
+    Stack,
+    tcp::TcpSocket,
+};
+use embassy_time::Duration;
+use embedded_graphics::pixelcolor::{
+    Rgb565,
+    raw::RawU16,
+};
+use embedded_io_async::{
+    Read as _,
+    Write as _,
+};
+
+use crate::{
+    DISPLAY_HEIGHT,
+    DISPLAY_WIDTH,
+    Display,
+};
+
+/// TCP port senders connect to.
+pub const PORT: u16 = 5320;
+/// First bytes of the hello.
+pub const MAGIC: [u8; 2] = *b"RD";
+/// Version of the protocol in the hello.
+pub const VERSION: u8 = 1;
+/// Most rows in one strip.
+pub const MAX_ROWS: u8 = 16;
+/// Strips a sender may have sent but not had acknowledged.
+pub const CREDITS: u8 = 2;
+/// Strip flag: the pixel data is run-length encoded.
+pub const RLE: u8 = 0x01;
+/// Strip flag: the strip ends a frame.
+pub const END_OF_FRAME: u8 = 0x80;
+/// Sent back for each strip drawn.
+pub const ACK: u8 = 0x06;
+/// How long a connected sender may go quiet before it's dropped.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bytes in a raw strip of [`MAX_ROWS`] rows, the most a strip may carry.
+const STRIP_LEN: usize = DISPLAY_WIDTH as usize * MAX_ROWS as usize * 2;
+/// The TCP receive window: about one strip queued while another is drawn.
+const RX_BUF: usize = 8192;
+const HEADER_LEN: usize = 5;
+
+/// Why a connection was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+enum StreamError {
+    /// The sender left or the connection broke.
+    Network,
+    /// Unknown flags, or rows outside the screen.
+    Header,
+    /// The pixel data doesn't cover the strip exactly.
+    Data,
+    /// The panel didn't take the pixels.
+    Display,
+}
+
+/// Frames received over TCP and drawn on the display. See the
+/// [module docs](self).
+pub struct RemoteDisplay {
+    port: u16,
+    connected: AtomicBool,
+    frames: AtomicU32,
+}
+
+impl Default for RemoteDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RemoteDisplay {
+    /// Listen on [`PORT`].
+    pub const fn new() -> Self {
+        Self {
+            port: PORT,
+            connected: AtomicBool::new(false),
+            frames: AtomicU32::new(0),
+        }
+    }
+
+    /// Listen on `port` instead of [`PORT`].
+    #[must_use]
+    pub const fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Whether a sender is connected.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Frames completed since boot, counted by [`END_OF_FRAME`].
+    pub fn frames(&self) -> u32 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    /// Accept senders on `stack` one after another and draw what they send
+    /// on `display`, forever.
+    ///
+    /// Whatever was last drawn stays on the screen between senders.
+    pub async fn run(&self, stack: Stack<'_>, display: &mut Display<'_>) -> ! {
+        let mut rx = [0; RX_BUF];
+        let mut tx = [0; 64];
+        let mut strip = [0; STRIP_LEN];
+        stack.wait_config_up().await;
+        loop {
+            let mut socket = TcpSocket::new(stack, &mut rx, &mut tx);
+            socket.set_timeout(Some(IDLE_TIMEOUT));
+            if let Err(err) = socket.accept(self.port).await {
+                warn!("remote display: accept: {}", err);
+                continue;
+            }
+            info!("remote display: {} connected", socket.remote_endpoint());
+            self.connected.store(true, Ordering::Relaxed);
+            let err = self.serve(&mut socket, &mut strip, display).await;
+            self.connected.store(false, Ordering::Relaxed);
+            match err {
+                StreamError::Network => info!("remote display: sender left"),
+                err => warn!("remote display: {}", err),
+            }
+            socket.close();
+            socket.flush().await.ok();
+        }
+    }
+
+    /// Draw strips from `socket` until something goes wrong.
+    async fn serve(
+        &self,
+        socket: &mut TcpSocket<'_>,
+        strip: &mut [u8; STRIP_LEN],
+        display: &mut Display<'_>,
+    ) -> StreamError {
+        let [w0, w1] = DISPLAY_WIDTH.to_le_bytes();
+        let [h0, h1] = DISPLAY_HEIGHT.to_le_bytes();
+        let [m0, m1] = MAGIC;
+        let hello = [m0, m1, VERSION, CREDITS, MAX_ROWS, w0, w1, h0, h1];
+        if socket.write_all(&hello).await.is_err() {
+            return StreamError::Network;
+        }
+        loop {
+            let mut header = [0; HEADER_LEN];
+            if socket.read_exact(&mut header).await.is_err() {
+                return StreamError::Network;
+            }
+            let [flags, y, rows, l0, l1] = header;
+            let len = usize::from(u16::from_le_bytes([l0, l1]));
+            if flags & !(RLE | END_OF_FRAME) != 0
+                || !(1..=MAX_ROWS).contains(&rows)
+                || u16::from(y) + u16::from(rows) > DISPLAY_HEIGHT
+            {
+                return StreamError::Header;
+            }
+            let Some(data) = strip.get_mut(..len) else {
+                return StreamError::Data;
+            };
+            if socket.read_exact(data).await.is_err() {
+                return StreamError::Network;
+            }
+            if let Err(err) = blit(display, flags & RLE != 0, y, rows, data) {
+                return err;
+            }
+            if socket.write_all(&[ACK]).await.is_err() {
+                return StreamError::Network;
+            }
+            if flags & END_OF_FRAME != 0 {
+                self.frames.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Draw one strip of `rows` rows from `y`, checking first that `data`
+/// covers it exactly.
+fn blit(
+    display: &mut Display<'_>,
+    rle: bool,
+    y: u8,
+    rows: u8,
+    data: &[u8],
+) -> Result<(), StreamError> {
+    let pixels = usize::from(DISPLAY_WIDTH) * usize::from(rows);
+    let color = |lo, hi| Rgb565::from(RawU16::new(u16::from_le_bytes([lo, hi])));
+    let (sy, ey) = (u16::from(y), u16::from(y) + u16::from(rows) - 1);
+    let drawn = if rle {
+        let runs = data.chunks_exact(3);
+        if !runs.remainder().is_empty()
+            || runs.clone().any(|run| run[0] == 0)
+            || runs.clone().map(|run| usize::from(run[0])).sum::<usize>() != pixels
+        {
+            return Err(StreamError::Data);
+        }
+        display.set_pixels(
+            0,
+            sy,
+            DISPLAY_WIDTH - 1,
+            ey,
+            runs.flat_map(|run| iter::repeat_n(color(run[1], run[2]), usize::from(run[0]))),
+        )
+    } else {
+        if data.len() != pixels * 2 {
+            return Err(StreamError::Data);
+        }
+        display.set_pixels(
+            0,
+            sy,
+            DISPLAY_WIDTH - 1,
+            ey,
+            data.chunks_exact(2).map(|pixel| color(pixel[0], pixel[1])),
+        )
+    };
+    drawn.map_err(|_| StreamError::Display)
+}
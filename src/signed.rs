@@ -0,0 +1,251 @@
+//! Ed25519-signed messages for any radio, behind the `signed` feature, so
+//! a dev kit in the crowd can't pass itself off as a badge or as the
+//! organisers.
+//!
+//! A [`Signer`] wraps a payload in an envelope with the sender's public key
+//! and a serial number and signs the lot; a [`Verifier`] checks it and hands
+//! back the payload. Envelopes are plain bytes, so they go in ESP-NOW frames
+//! or BLE writes alike:
+//!
+//! ```rust,ignore
+//! const NEWS: Context = u16::from_le_bytes(*b"NW");
+//!
+//! // organiser's badge
+//! let mut signer = Signer::new(key, NEWS);
+//! let mut buf = [0; 200];
+//! esp_now.send_async(&BROADCAST_ADDRESS, signer.sign(b"Doors open", &mut buf)?).await?;
+//!
+//! // everyone else
+//! let mut verifier = Verifier::organisers(NEWS).unwrap();
+//! let received = esp_now.receive_async().await;
+//! if let Ok(message) = verifier.open(received.data()) {
+//!     info!("{=[u8]}", message.payload);
+//! }
+//! ```
+//!
+//! Every envelope is [`OVERHEAD`] bytes on top of its payload, all fields
+//! little-endian:
+//!
+//! | Bytes | Field                                               |
+//! |-------|-----------------------------------------------------|
+//! | 1     | [`VERSION`]                                         |
+//! | 2     | [`Context`]                                         |
+//! | 32    | signer's Ed25519 public key                         |
+//! | 4     | serial number, +1 per message                       |
+//! | n     | payload                                             |
+//! | 64    | signature over everything before it                 |
+//!
+//! The context tells uses apart, so a message signed for one game can't be
+//! replayed into another. A verifier remembers the last [`SEEN`] messages
+//! and refuses them a second time; a recording played back much later
+//! still gets through, so put a time or round number in payloads where
+//! that matters.
+//!
+//! Without [`Verifier::with_signer`] any key is accepted and a message
+//! only proves it came from whoever holds that key, which is enough to tell
+//! badges apart. With it, only the holder of that one key can send: set
+//! `conference.public_key` in `badge.toml` and [`Verifier::organisers`]
+//! takes only what the organisers signed. Signatures are asymmetric on
+//! purpose: an HMAC key shared by every badge could be read out of any
+//! one of them.
+//!
+//! Checking a signature takes a few milliseconds. Messages from the wrong
+//! signer or seen before are turned away without one, but a verifier that
+//! accepts any key should be fed at a rate the app can afford.
+
+use ed25519_compact::Signature;
+pub use ed25519_compact::{
+    KeyPair,
+    PublicKey,
+    Seed,
+};
+
+use crate::{
+    config,
+    storage::{
+        self,
+        Store,
+    },
+};
+
+/// Format version at the start of every envelope.
+pub const VERSION: u8 = 1;
+/// Bytes an envelope adds to its payload.
+pub const OVERHEAD: usize = HEADER_LEN + Signature::BYTES;
+/// Messages a [`Verifier`] remembers, to refuse replays.
+pub const SEEN: usize = 32;
+/// Context of conference-wide announcements.
+pub const ANNOUNCEMENTS: Context = u16::from_le_bytes(*b"AN");
+
+const HEADER_LEN: usize = 7 + PublicKey::BYTES;
+
+/// What a message is for, such as a game's `GameId`; signed along with it.
+pub type Context = u16;
+
+/// Errors from signing or checking an envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum SignedError {
+    /// The buffer can't hold the payload plus [`OVERHEAD`].
+    TooLong,
+    /// Too short, or another [`VERSION`].
+    Malformed,
+    /// Signed for another [`Context`].
+    WrongContext,
+    /// Not from the key given to [`Verifier::with_signer`].
+    Untrusted,
+    /// Seen before.
+    Replayed,
+    /// The signature doesn't match.
+    BadSignature,
+}
+
+/// The badge's signing key, made on first use and kept under
+/// [`storage::BADGE_KEY`]. Call it once the radio is on: only then is the
+/// hardware random number generator truly random.
+pub fn load_key<S: Store + ?Sized>(store: &mut S) -> Result<KeyPair, S::Error> {
+    let mut seed = [0; Seed::BYTES];
+    if store.read(storage::BADGE_KEY, &mut seed)? != Some(Seed::BYTES) {
+        esp_hal::rng::Rng::new().read(&mut seed);
+        store.write(storage::BADGE_KEY, &seed)?;
+    }
+    Ok(KeyPair::from_seed(Seed::new(seed)))
+}
+
+/// Signs messages for one [`Context`]. See the [module docs](self).
+pub struct Signer {
+    key: KeyPair,
+    context: Context,
+    serial: u32,
+}
+
+impl Signer {
+    /// Sign with `key`, from [`load_key`] or an organiser's seed. Serial
+    /// numbers start at random, so they don't repeat after a reboot.
+    pub fn new(key: KeyPair, context: Context) -> Self {
+        Self {
+            key,
+            context,
+            serial: esp_hal::rng::Rng::new().random(),
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.key.pk
+    }
+
+    /// Wrap `payload` in a signed envelope in `buf` and return it.
+    pub fn sign<'b>(&mut self, payload: &[u8], buf: &'b mut [u8]) -> Result<&'b [u8], SignedError> {
+        let len = HEADER_LEN + payload.len();
+        let envelope = buf
+            .get_mut(..len + Signature::BYTES)
+            .ok_or(SignedError::TooLong)?;
+        envelope[0] = VERSION;
+        envelope[1..3].copy_from_slice(&self.context.to_le_bytes());
+        envelope[3..HEADER_LEN - 4].copy_from_slice(&*self.key.pk);
+        envelope[HEADER_LEN - 4..HEADER_LEN].copy_from_slice(&self.serial.to_le_bytes());
+        envelope[HEADER_LEN..len].copy_from_slice(payload);
+        self.serial = self.serial.wrapping_add(1);
+        let signature = self.key.sk.sign(&envelope[..len], None);
+        envelope[len..].copy_from_slice(&*signature);
+        Ok(envelope)
+    }
+}
+
+/// A message that passed a [`Verifier`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Message<'a> {
+    /// Key it was signed with.
+    pub signer: PublicKey,
+    pub serial: u32,
+    pub payload: &'a [u8],
+}
+
+impl defmt::Format for Message<'_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "Message {{ signer: {=[u8]:02x}, serial: {}, payload: {=[u8]} }}",
+            self.signer[..4],
+            self.serial,
+            self.payload
+        );
+    }
+}
+
+/// Checks envelopes for one [`Context`] and refuses replays. See the
+/// [module docs](self).
+pub struct Verifier {
+    context: Context,
+    signer: Option<[u8; PublicKey::BYTES]>,
+    seen: [Option<(u64, u32)>; SEEN],
+    next_seen: usize,
+}
+
+impl Verifier {
+    /// Accept messages signed with any key.
+    pub const fn new(context: Context) -> Self {
+        Self {
+            context,
+            signer: None,
+            seen: [None; SEEN],
+            next_seen: 0,
+        }
+    }
+
+    /// Accept only messages signed with `key`.
+    #[must_use]
+    pub const fn with_signer(mut self, key: [u8; PublicKey::BYTES]) -> Self {
+        self.signer = Some(key);
+        self
+    }
+
+    /// Accept only messages signed with `conference.public_key` from
+    /// `badge.toml`; `None` if it isn't set.
+    pub const fn organisers(context: Context) -> Option<Self> {
+        match config::CONFERENCE_KEY {
+            Some(key) => Some(Self::new(context).with_signer(key)),
+            None => None,
+        }
+    }
+
+    /// Check `envelope` and return its payload, if it's signed for this
+    /// context by an accepted key and hasn't been seen before.
+    pub fn open<'a>(&mut self, envelope: &'a [u8]) -> Result<Message<'a>, SignedError> {
+        let (header, rest) = envelope
+            .split_first_chunk::<HEADER_LEN>()
+            .ok_or(SignedError::Malformed)?;
+        let (payload, signature) = rest
+            .split_last_chunk::<{ Signature::BYTES }>()
+            .ok_or(SignedError::Malformed)?;
+        if header[0] != VERSION {
+            return Err(SignedError::Malformed);
+        }
+        if u16::from_le_bytes([header[1], header[2]]) != self.context {
+            return Err(SignedError::WrongContext);
+        }
+        let signer = &header[3..HEADER_LEN - 4];
+        if self.signer.is_some_and(|trusted| trusted != signer) {
+            return Err(SignedError::Untrusted);
+        }
+        let serial = u32::from_le_bytes([header[35], header[36], header[37], header[38]]);
+        let mut prefix = [0; 8];
+        prefix.copy_from_slice(&signer[..8]);
+        let id = (u64::from_le_bytes(prefix), serial);
+        if self.seen.contains(&Some(id)) {
+            return Err(SignedError::Replayed);
+        }
+        let key = PublicKey::from_slice(signer).map_err(|_| SignedError::Malformed)?;
+        key.verify(
+            &envelope[..HEADER_LEN + payload.len()],
+            &Signature::new(*signature),
+        )
+        .map_err(|_| SignedError::BadSignature)?;
+        self.seen[self.next_seen] = Some(id);
+        self.next_seen = (self.next_seen + 1) % SEEN;
+        Ok(Message {
+            signer: key,
+            serial,
+            payload,
+        })
+    }
+}
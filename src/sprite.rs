@@ -0,0 +1,148 @@
+//! Fixed-size sprites and tiles with compile-time size checks.
+//!
+//! Sprite data is raw big-endian Rgb565 (2 bytes per pixel, row-major), the
+//! same layout `embedded-graphics`' `ImageRawBE` uses. The dimensions are part
+//! of the type, and [`Sprite::from_raw`] is a `const fn` that asserts the data
+//! length, so declaring the asset as a `const` turns a wrong-sized file into a
+//! build error instead of a garbled image on the badge:
+//!
+//! ```rust,ignore
+//! use disobey2026badge::sprite::{Sprite, Tileset};
+//!
+//! const SHIP: Sprite<16, 12> = Sprite::from_raw(include_bytes!("assets/ship.bin"));
+//! const TILES: Tileset<8, 8> = Tileset::from_raw(include_bytes!("assets/tiles.bin"));
+//!
+//! Image::new(&SHIP, Point::new(10, 20)).draw(display)?;
+//! Image::new(&TILES.tile(3), Point::new(0, 0)).draw(display)?;
+//! ```
+
+use embedded_graphics::{
+    image::{
+        ImageDrawable,
+        ImageRawBE,
+    },
+    pixelcolor::{
+        Rgb565,
+        raw::RawU16,
+    },
+    prelude::{
+        DrawTarget,
+        OriginDimensions,
+        Size,
+    },
+    primitives::Rectangle,
+};
+
+/// A `W × H` Rgb565 image whose size is checked when it is declared.
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite<const W: usize, const H: usize> {
+    data: &'static [u8],
+}
+
+/// A single tile — a [`Sprite`] cut from a [`Tileset`].
+pub type Tile<const W: usize, const H: usize> = Sprite<W, H>;
+
+impl<const W: usize, const H: usize> Sprite<W, H> {
+    /// Number of bytes of Rgb565 data a sprite of this size needs.
+    pub const BYTES: usize = W * H * 2;
+
+    /// Wrap raw big-endian Rgb565 data.
+    ///
+    /// # Panics
+    ///
+    /// If `data` is not exactly `W * H * 2` bytes. In a `const` this is a
+    /// compile error.
+    pub const fn from_raw(data: &'static [u8]) -> Self {
+        assert!(
+            data.len() == Self::BYTES,
+            "sprite data length does not match its declared width × height"
+        );
+        Self { data }
+    }
+
+    /// Colour of the pixel at `(x, y)`, or `None` if out of bounds.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Rgb565> {
+        if x >= W || y >= H {
+            return None;
+        }
+        let i = (y * W + x) * 2;
+        Some(RawU16::new(u16::from_be_bytes([self.data[i], self.data[i + 1]])).into())
+    }
+
+    /// The raw bytes backing this sprite.
+    pub const fn as_bytes(&self) -> &'static [u8] {
+        self.data
+    }
+
+    fn raw(&self) -> ImageRawBE<'static, Rgb565> {
+        ImageRawBE::new(self.data, W as u32)
+    }
+}
+
+impl<const W: usize, const H: usize> OriginDimensions for Sprite<W, H> {
+    fn size(&self) -> Size {
+        Size::new(W as u32, H as u32)
+    }
+}
+
+impl<const W: usize, const H: usize> ImageDrawable for Sprite<W, H> {
+    type Color = Rgb565;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.raw().draw(target)
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.raw().draw_sub_image(target, area)
+    }
+}
+
+/// A strip of equally sized `W × H` tiles stored back to back.
+#[derive(Clone, Copy, Debug)]
+pub struct Tileset<const W: usize, const H: usize> {
+    data: &'static [u8],
+}
+
+impl<const W: usize, const H: usize> Tileset<W, H> {
+    /// Wrap raw big-endian Rgb565 data holding whole tiles.
+    ///
+    /// # Panics
+    ///
+    /// If `data` is empty or not a whole number of `W × H` tiles. In a
+    /// `const` this is a compile error.
+    pub const fn from_raw(data: &'static [u8]) -> Self {
+        assert!(
+            !data.is_empty() && data.len().is_multiple_of(Tile::<W, H>::BYTES),
+            "tileset data is not a whole number of tiles of its declared size"
+        );
+        Self { data }
+    }
+
+    /// Number of tiles in the set.
+    pub const fn len(&self) -> usize {
+        self.data.len() / Tile::<W, H>::BYTES
+    }
+
+    /// Always `false`; [`from_raw`](Self::from_raw) rejects empty data.
+    pub const fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The tile at `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index >= self.len()`.
+    pub const fn tile(&self, index: usize) -> Tile<W, H> {
+        let start = index * Tile::<W, H>::BYTES;
+        let (_, rest) = self.data.split_at(start);
+        let (tile, _) = rest.split_at(Tile::<W, H>::BYTES);
+        Tile::from_raw(tile)
+    }
+}
@@ -0,0 +1,254 @@
+//! Usage counters kept in a [`Store`]: boots, button presses, time on and
+//! time spent in each app, for "badge stats" screens and for organisers
+//! curious which apps get used.
+//!
+//! ```rust,ignore
+//! // once at boot, from the launcher
+//! let mut stats = Stats::boot(&mut *settings.lock().await)?;
+//!
+//! // on starting an app; its time counts from here until the next one
+//! stats.enter(SNAKE);
+//!
+//! // every few minutes, and on leaving an app
+//! stats.save(&mut *settings.lock().await)?;
+//!
+//! info!("boot #{}, {} presses", stats.boots(), stats.button_presses());
+//! ```
+//!
+//! Counting costs nothing between saves: presses are counted as
+//! [`Buttons::events`](crate::Buttons::events) reports them and time is
+//! read off the clock, both in RAM. [`Stats::save`] writes one small value,
+//! so flash wears with how often it's called, not with use; time and
+//! presses since the last save are lost on a reset.
+//!
+//! ## Storage format
+//!
+//! The counters are under [`storage::STATS`]: boots, button presses and
+//! seconds on, then per app, most used first, its ID as a little-endian
+//! `u16` and its seconds. The rest are little-endian `u32`s.
+
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering,
+};
+
+use embassy_time::{
+    Duration,
+    Instant,
+};
+
+use crate::storage::{
+    self,
+    Store,
+};
+
+/// Tells apps apart in the counters. A game's
+/// [`GameId`](crate::games::GameId) works; otherwise pick any constant,
+/// such as two letters of the name.
+pub type AppId = u16;
+
+/// Apps whose time is counted. Time in any more still counts towards
+/// [`Stats::uptime`].
+pub const MAX_APPS: usize = 16;
+
+/// Longest encoded [`Stats`].
+const STATS_LEN: usize = 12 + MAX_APPS * 6;
+
+/// Presses reported since the last [`Stats::save`].
+static PRESSES: AtomicU32 = AtomicU32::new(0);
+
+/// Count a press for [`Stats::button_presses`].
+pub(crate) fn count_press() {
+    PRESSES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The counters, as saved plus what's been counted since. See the
+/// [module docs](self).
+#[derive(Clone, Copy, Debug)]
+pub struct Stats {
+    boots: u32,
+    presses: u32,
+    seconds: u32,
+    apps: [(AppId, u32); MAX_APPS],
+    app_count: usize,
+    /// The app running, if any.
+    app: Option<AppId>,
+    /// Time up to which seconds have been added.
+    counted: Instant,
+}
+
+impl Stats {
+    /// Read the counters from `store`, counting time from now.
+    pub fn load<S: Store + ?Sized>(store: &mut S) -> Result<Self, S::Error> {
+        let mut stats = Self {
+            boots: 0,
+            presses: 0,
+            seconds: 0,
+            apps: [(0, 0); MAX_APPS],
+            app_count: 0,
+            app: None,
+            counted: Instant::now(),
+        };
+        let mut buf = [0; STATS_LEN];
+        if let Some(len) = store.read(storage::STATS, &mut buf)? {
+            stats.decode(&buf[..len]);
+        }
+        Ok(stats)
+    }
+
+    /// [`load`](Self::load) the counters, count this boot and save them.
+    /// Call once, early at boot, where the time since reset isn't worth
+    /// counting.
+    pub fn boot<S: Store + ?Sized>(store: &mut S) -> Result<Self, S::Error> {
+        let mut stats = Self::load(store)?;
+        stats.boots = stats.boots.wrapping_add(1);
+        stats.save(store)?;
+        Ok(stats)
+    }
+
+    /// Times the badge has booted, counting this one.
+    pub const fn boots(&self) -> u32 {
+        self.boots
+    }
+
+    /// Presses reported by [`Buttons::events`](crate::Buttons::events) in
+    /// every boot so far.
+    pub fn button_presses(&self) -> u32 {
+        self.presses.wrapping_add(PRESSES.load(Ordering::Relaxed))
+    }
+
+    /// Time the badge has been on over every boot, each counted from when
+    /// the counters were loaded.
+    pub fn uptime(&self) -> Duration {
+        Duration::from_secs(u64::from(self.seconds)) + self.pending()
+    }
+
+    /// Time spent in `app` over every boot.
+    pub fn app_time(&self, app: AppId) -> Duration {
+        let saved = self.apps[..self.app_count]
+            .iter()
+            .find(|&&(id, _)| id == app)
+            .map_or(0, |&(_, seconds)| seconds);
+        let running = if self.app == Some(app) {
+            self.pending()
+        } else {
+            Duration::from_secs(0)
+        };
+        Duration::from_secs(u64::from(saved)) + running
+    }
+
+    /// Every app with time counted, most used first, apart from the
+    /// running app's time since it was last added up.
+    pub fn apps(&self) -> impl Iterator<Item = (AppId, Duration)> + '_ {
+        self.apps[..self.app_count]
+            .iter()
+            .map(|&(id, seconds)| (id, Duration::from_secs(u64::from(seconds))))
+    }
+
+    /// The app whose time is being counted.
+    pub const fn app(&self) -> Option<AppId> {
+        self.app
+    }
+
+    /// Count time from now towards `app`, ending the previous one's.
+    pub fn enter(&mut self, app: AppId) {
+        self.tally();
+        self.app = Some(app);
+    }
+
+    /// Stop counting time towards the running app, e.g. back in the
+    /// launcher.
+    pub fn leave(&mut self) {
+        self.tally();
+        self.app = None;
+    }
+
+    /// Add up what's been counted since the last save and write the
+    /// counters to `store`.
+    pub fn save<S: Store + ?Sized>(&mut self, store: &mut S) -> Result<(), S::Error> {
+        self.tally();
+        self.presses = self
+            .presses
+            .wrapping_add(PRESSES.swap(0, Ordering::Relaxed));
+        let mut buf = [0; STATS_LEN];
+        store.write(storage::STATS, self.encode(&mut buf))
+    }
+
+    /// Time since seconds were last added, less than a second after a
+    /// [`tally`](Self::tally).
+    fn pending(&self) -> Duration {
+        Instant::now().saturating_duration_since(self.counted)
+    }
+
+    /// Add the whole seconds since the last tally to the uptime and the
+    /// running app, carrying the rest.
+    fn tally(&mut self) {
+        let seconds = self.pending().as_secs();
+        self.counted += Duration::from_secs(seconds);
+        let seconds = seconds.min(u64::from(u32::MAX)) as u32;
+        self.seconds = self.seconds.saturating_add(seconds);
+        let Some(app) = self.app else {
+            return;
+        };
+        let slot = match self.apps[..self.app_count]
+            .iter()
+            .position(|&(id, _)| id == app)
+        {
+            Some(slot) => slot,
+            None if self.app_count < MAX_APPS => {
+                self.apps[self.app_count] = (app, 0);
+                self.app_count += 1;
+                self.app_count - 1
+            }
+            None => return,
+        };
+        self.apps[slot].1 = self.apps[slot].1.saturating_add(seconds);
+        // Keep the most used first.
+        let mut slot = slot;
+        while slot > 0 && self.apps[slot - 1].1 < self.apps[slot].1 {
+            self.apps.swap(slot - 1, slot);
+            slot -= 1;
+        }
+    }
+
+    /// Write the counters into `buf` as described in the
+    /// [module docs](self#storage-format), returning the bytes used.
+    fn encode<'b>(&self, buf: &'b mut [u8; STATS_LEN]) -> &'b [u8] {
+        buf[..4].copy_from_slice(&self.boots.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.presses.to_le_bytes());
+        buf[8..12].copy_from_slice(&self.seconds.to_le_bytes());
+        let mut len = 12;
+        for &(id, seconds) in &self.apps[..self.app_count] {
+            buf[len..len + 2].copy_from_slice(&id.to_le_bytes());
+            buf[len + 2..len + 6].copy_from_slice(&seconds.to_le_bytes());
+            len += 6;
+        }
+        &buf[..len]
+    }
+
+    /// Read counters written by [`encode`](Self::encode). A value too
+    /// short for the totals leaves them at zero.
+    fn decode(&mut self, bytes: &[u8]) {
+        let Some((totals, mut rest)) = bytes.split_first_chunk::<12>() else {
+            return;
+        };
+        let word = |at: usize| {
+            u32::from_le_bytes([totals[at], totals[at + 1], totals[at + 2], totals[at + 3]])
+        };
+        self.boots = word(0);
+        self.presses = word(4);
+        self.seconds = word(8);
+        while self.app_count < MAX_APPS {
+            let Some((app, after)) = rest.split_first_chunk::<6>() else {
+                break;
+            };
+            let [i0, i1, s0, s1, s2, s3] = *app;
+            self.apps[self.app_count] = (
+                u16::from_le_bytes([i0, i1]),
+                u32::from_le_bytes([s0, s1, s2, s3]),
+            );
+            self.app_count += 1;
+            rest = after;
+        }
+    }
+}
@@ -0,0 +1,526 @@
+//! Persistent key-value storage and schema migrations.
+//!
+//! [`Store`] is the small blocking interface everything that persists data
+//! (settings, high scores) is written against. Values are raw bytes; callers
+//! own their encoding.
+//!
+//! Because those encodings change between firmware versions, [`migrate`]
+//! runs at startup: it reads the schema version stored under
+//! [`SCHEMA_VERSION`], applies every registered [`Migration`] newer than it
+//! in order, and records the new version after each step.
+//!
+//! ```rust,ignore
+//! const MIGRATIONS: &[Migration<MyStore>] = &[
+//!     // v1: high scores grew from u16 to u32
+//!     Migration { to: 1, run: widen_high_scores },
+//!     // v2: settings moved to a new key
+//!     Migration { to: 2, run: move_settings },
+//! ];
+//!
+//! storage::migrate(&mut store, MIGRATIONS)?;
+//! ```
+//!
+//! With the `settings` feature, [`FlashStore`] keeps values in the `kv`
+//! flash partition. [`Settings`] shares a store between tasks and gives
+//! each app typed values under its own namespace:
+//!
+//! ```rust,ignore
+//! let store = FlashStore::new(resources.flash.into())?;
+//! let settings = mk_static!(Settings<FlashStore<'static>>, Settings::new(store));
+//!
+//! const SNAKE: u8 = 0x10;
+//! const SPEED: u8 = 0;
+//! let snake = settings.app(SNAKE);
+//! let speed = snake.get_or(SPEED, 150u16).await?;
+//! snake.set(SPEED, speed + 10).await?;
+//!
+//! // crate-level values, such as the backlight level, take the store itself
+//! backlight.save(&mut *settings.lock().await)?;
+//! ```
+//!
+//! With the `serde` feature, any `#[derive(Serialize, Deserialize)]` type
+//! that implements [`Versioned`] is stored in [`postcard`]'s compact binary
+//! format along with its layout version:
+//!
+//! ```rust,ignore
+//! let mut config: Config = snake.load_or_default(CONFIG).await?;
+//! snake.save(CONFIG, &config).await?;
+//! ```
+
+#[cfg(feature = "settings")]
+mod flash;
+
+use core::str;
+
+use embassy_sync::{
+    blocking_mutex::raw::{
+        CriticalSectionRawMutex,
+        RawMutex,
+    },
+    mutex::{
+        Mutex,
+        MutexGuard,
+    },
+};
+#[cfg(feature = "settings")]
+pub use flash::{
+    FlashStore,
+    FlashStoreError,
+    MAX_VALUE_LEN,
+    PARTITION,
+};
+/// Errors encoding and decoding [`Versioned`] values.
+#[cfg(feature = "serde")]
+pub use postcard::Error as EncodingError;
+#[cfg(feature = "serde")]
+use serde::{
+    Serialize,
+    de::DeserializeOwned,
+};
+
+/// Identifies a stored value.
+pub type Key = u16;
+
+/// Key holding the schema version as a little-endian `u16`.
+///
+/// Keys below `0x0100` are reserved for the crate.
+pub const SCHEMA_VERSION: Key = 0x0000;
+
+/// Key holding the backlight level as one byte; see [`Backlight::save`](crate::Backlight::save).
+pub const BACKLIGHT_BRIGHTNESS: Key = 0x0001;
+
+/// Key holding the button mapping as nine bytes; see [`Keymap::save`](crate::Keymap::save).
+pub const KEYMAP: Key = 0x0002;
+
+/// Key holding the ambient LED profile; see [`LedProfile::save`](crate::LedProfile::save).
+pub const LED_PROFILE: Key = 0x0003;
+
+/// Key holding the nametag name and theme; see [`Nametag::save`](crate::Nametag::save).
+pub const NAMETAG: Key = 0x0004;
+
+/// Key holding the number of contacts; see [`contacts`](crate::contacts).
+pub const CONTACTS: Key = 0x0005;
+
+/// Key holding the seed of the badge's signing key; see
+/// `signed::load_key`.
+pub const BADGE_KEY: Key = 0x0006;
+
+/// Key holding the usage counters; see [`stats`](crate::stats).
+pub const STATS: Key = 0x0007;
+
+/// Key holding the first high-score table. The next
+/// [`MAX_GAMES`](crate::games::high_scores::MAX_GAMES) - 1 keys hold the
+/// rest; see [`high_scores`](crate::games::high_scores).
+pub const HIGH_SCORES: Key = 0x0040;
+
+/// Key holding the first contact. The next
+/// [`MAX_CONTACTS`](crate::contacts::MAX_CONTACTS) - 1 keys hold the rest.
+pub const CONTACT_LIST: Key = 0x0080;
+
+/// Blocking key-value storage.
+pub trait Store {
+    type Error;
+
+    /// Read the value for `key` into `buf`, returning its length, or `None`
+    /// if the key is absent. Values longer than `buf` are an error.
+    fn read(&mut self, key: Key, buf: &mut [u8]) -> Result<Option<usize>, Self::Error>;
+
+    /// Store `value` under `key`, replacing any previous value.
+    fn write(&mut self, key: Key, value: &[u8]) -> Result<(), Self::Error>;
+
+    /// Delete `key`. Removing an absent key is not an error.
+    fn remove(&mut self, key: Key) -> Result<(), Self::Error>;
+}
+
+/// One step in the schema history.
+pub struct Migration<S: Store + ?Sized> {
+    /// The schema version after this step has run.
+    pub to: u16,
+    /// Rewrite stored data from version `to - 1` (or the previous step's
+    /// `to`) into the new layout.
+    ///
+    /// Should be idempotent: if power is lost mid-run, it runs again on the
+    /// next boot.
+    pub run: fn(&mut S) -> Result<(), S::Error>,
+}
+
+/// Errors from [`migrate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum MigrationError<E> {
+    /// The underlying store failed.
+    Store(E),
+    /// The stored data was written by newer firmware than this one knows.
+    FromFuture { stored: u16, supported: u16 },
+}
+
+impl<E> From<E> for MigrationError<E> {
+    fn from(err: E) -> Self {
+        Self::Store(err)
+    }
+}
+
+/// Read the stored schema version; a store without one is version 0.
+pub fn schema_version<S: Store + ?Sized>(store: &mut S) -> Result<u16, S::Error> {
+    let mut buf = [0; 2];
+    Ok(match store.read(SCHEMA_VERSION, &mut buf)? {
+        Some(2) => u16::from_le_bytes(buf),
+        _ => 0,
+    })
+}
+
+/// Bring stored data up to the latest version in `migrations`.
+///
+/// `migrations` must be sorted by [`Migration::to`], ascending. Returns the
+/// version the store is at afterwards.
+///
+/// # Panics
+///
+/// If `migrations` is not sorted.
+pub fn migrate<S: Store + ?Sized>(
+    store: &mut S,
+    migrations: &[Migration<S>],
+) -> Result<u16, MigrationError<S::Error>> {
+    assert!(
+        migrations.is_sorted_by(|a, b| a.to < b.to),
+        "migrations must be sorted by version"
+    );
+
+    let supported = migrations.last().map_or(0, |m| m.to);
+    let mut version = schema_version(store)?;
+    if version > supported {
+        return Err(MigrationError::FromFuture {
+            stored: version,
+            supported,
+        });
+    }
+
+    let stored = version;
+    for migration in migrations.iter().filter(|m| m.to > stored) {
+        defmt::info!("storage: migrating schema {} -> {}", version, migration.to);
+        (migration.run)(store)?;
+        version = migration.to;
+        store.write(SCHEMA_VERSION, &version.to_le_bytes())?;
+    }
+    Ok(version)
+}
+
+// ── Typed values ────────────────────────────────────────────────────────────
+
+/// Longest encoding [`load`], [`save`] and their [`AppSettings`]
+/// counterparts handle, version included.
+#[cfg(feature = "serde")]
+pub const MAX_ENCODED_LEN: usize = 256;
+
+/// A `serde` type stored with a schema version of its own, so a later
+/// firmware can tell which layout it's reading.
+///
+/// ```rust,ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Config {
+///     volume: u8,
+///     name: heapless::String<16>,
+///     difficulty: Difficulty,
+/// }
+///
+/// impl Versioned for Config {
+///     const VERSION: u16 = 2;
+///
+///     fn upgrade(version: u16, bytes: &[u8]) -> Option<Self> {
+///         match version {
+///             // v1 had no difficulty
+///             1 => {
+///                 let (volume, name) = postcard::from_bytes(bytes).ok()?;
+///                 Some(Self { volume, name, difficulty: Difficulty::Normal })
+///             }
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let mut config = app.load_or_default::<Config>(CONFIG).await?;
+/// config.volume += 1;
+/// app.save(CONFIG, &config).await?;
+/// ```
+#[cfg(feature = "serde")]
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// The layout version. Bump it whenever fields are added, removed,
+    /// reordered or change type.
+    const VERSION: u16;
+
+    /// Read a value that was stored as the older layout `version`:
+    /// decode `bytes` as that layout with [`postcard::from_bytes`] and
+    /// convert it. `None` treats it as absent, which is what the default
+    /// does for every older version.
+    fn upgrade(version: u16, bytes: &[u8]) -> Option<Self> {
+        let _ = (version, bytes);
+        None
+    }
+}
+
+/// Errors from [`load`] and [`save`] and their counterparts on
+/// [`AppSettings`] and `Fs`.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum TypedError<E> {
+    /// The underlying store failed.
+    Store(E),
+    /// The value didn't fit in [`MAX_ENCODED_LEN`] bytes, or the stored
+    /// bytes aren't a value of the type.
+    Encoding(EncodingError),
+    /// The value was written by newer firmware, with a layout this one
+    /// doesn't know.
+    FromFuture { stored: u16, supported: u16 },
+}
+
+#[cfg(feature = "serde")]
+impl<E> From<E> for TypedError<E> {
+    fn from(err: E) -> Self {
+        Self::Store(err)
+    }
+}
+
+/// Encode `value` and its [`Versioned::VERSION`] into the start of `buf`,
+/// returning the bytes written.
+#[cfg(feature = "serde")]
+pub fn encode<'b, T: Versioned>(value: &T, buf: &'b mut [u8]) -> Result<&'b [u8], EncodingError> {
+    postcard::to_slice(&(T::VERSION, value), buf).map(|bytes| &*bytes)
+}
+
+/// Decode bytes from [`encode`], upgrading older versions with
+/// [`Versioned::upgrade`].
+#[cfg(feature = "serde")]
+pub fn decode<T: Versioned, E>(bytes: &[u8]) -> Result<Option<T>, TypedError<E>> {
+    let (version, rest) = postcard::take_from_bytes::<u16>(bytes).map_err(TypedError::Encoding)?;
+    match version {
+        version if version == T::VERSION => postcard::from_bytes(rest)
+            .map(Some)
+            .map_err(TypedError::Encoding),
+        version if version > T::VERSION => Err(TypedError::FromFuture {
+            stored: version,
+            supported: T::VERSION,
+        }),
+        version => Ok(T::upgrade(version, rest)),
+    }
+}
+
+/// Read the [`Versioned`] value under `key`, or `None` if it's absent.
+#[cfg(feature = "serde")]
+pub fn load<T: Versioned, S: Store + ?Sized>(
+    store: &mut S,
+    key: Key,
+) -> Result<Option<T>, TypedError<S::Error>> {
+    let mut buf = [0; MAX_ENCODED_LEN];
+    match store.read(key, &mut buf)? {
+        Some(len) => decode(&buf[..len]),
+        None => Ok(None),
+    }
+}
+
+/// Store `value` under `key`, with its version.
+#[cfg(feature = "serde")]
+pub fn save<T: Versioned, S: Store + ?Sized>(
+    store: &mut S,
+    key: Key,
+    value: &T,
+) -> Result<(), TypedError<S::Error>> {
+    let mut buf = [0; MAX_ENCODED_LEN];
+    let bytes = encode(value, &mut buf).map_err(TypedError::Encoding)?;
+    Ok(store.write(key, bytes)?)
+}
+
+// ── Settings ────────────────────────────────────────────────────────────────
+
+/// A value [`AppSettings`] can store, encoded little-endian.
+pub trait Value: Sized {
+    /// The encoded value; its length is the stored length.
+    type Bytes: AsRef<[u8]> + AsMut<[u8]> + Default;
+
+    fn to_bytes(&self) -> Self::Bytes;
+
+    /// Decode `bytes`, or `None` if they aren't a valid value.
+    fn from_bytes(bytes: Self::Bytes) -> Option<Self>;
+}
+
+macro_rules! impl_value {
+    ($($ty:ty),*) => {$(
+        impl Value for $ty {
+            type Bytes = [u8; size_of::<$ty>()];
+
+            fn to_bytes(&self) -> Self::Bytes {
+                self.to_le_bytes()
+            }
+
+            fn from_bytes(bytes: Self::Bytes) -> Option<Self> {
+                Some(<$ty>::from_le_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+impl_value!(u8, u16, u32, u64, i8, i16, i32, i64, f32);
+
+impl Value for bool {
+    type Bytes = [u8; 1];
+
+    fn to_bytes(&self) -> Self::Bytes {
+        [u8::from(*self)]
+    }
+
+    fn from_bytes(bytes: Self::Bytes) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// A [`Store`] behind an async mutex, so every task can read and write
+/// settings without handing a single `&mut` around. Each app gets its own
+/// namespace of keys from [`app`](Self::app).
+///
+/// # Performance
+///
+/// - Store calls are blocking. A read from flash takes microseconds, a
+///   write a fraction of a millisecond, but a write that has to reclaim a
+///   sector erases it and stalls every task on the core for about 50 ms.
+///   Save when the player won't notice (on leaving a menu or at game
+///   over), not every frame.
+/// - Each call locks the store for that call only. Hold a guard from
+///   [`lock`](Self::lock) for several calls in a row, but never across
+///   other awaits.
+pub struct Settings<S, M: RawMutex = CriticalSectionRawMutex> {
+    store: Mutex<M, S>,
+}
+
+impl<S: Store, M: RawMutex> Settings<S, M> {
+    pub const fn new(store: S) -> Self {
+        Self {
+            store: Mutex::new(store),
+        }
+    }
+
+    /// Lock the whole store, for crate-level keys such as
+    /// [`BACKLIGHT_BRIGHTNESS`] and for [`migrate`].
+    pub async fn lock(&self) -> MutexGuard<'_, M, S> {
+        self.store.lock().await
+    }
+
+    /// Settings of one app, kept under keys `namespace << 8` to
+    /// `namespace << 8 | 0xff`. Pick a namespace no other app on the badge
+    /// uses.
+    ///
+    /// # Panics
+    ///
+    /// If `namespace` is 0, which holds the crate's own keys.
+    pub const fn app(&self, namespace: u8) -> AppSettings<'_, S, M> {
+        assert!(namespace != 0, "namespace 0 is reserved for the crate");
+        AppSettings {
+            settings: self,
+            namespace,
+        }
+    }
+}
+
+/// One app's settings, from [`Settings::app`]. Each takes an item number,
+/// 0 to 255, that the app picks.
+pub struct AppSettings<'s, S, M: RawMutex = CriticalSectionRawMutex> {
+    settings: &'s Settings<S, M>,
+    namespace: u8,
+}
+
+impl<S: Store, M: RawMutex> AppSettings<'_, S, M> {
+    /// The store key of `item`.
+    pub const fn key(&self, item: u8) -> Key {
+        u16::from_be_bytes([self.namespace, item])
+    }
+
+    /// Read `item`, or `None` if it's absent or stored as a shorter type.
+    /// One stored as a longer type is the store's error.
+    pub async fn get<T: Value>(&self, item: u8) -> Result<Option<T>, S::Error> {
+        let mut bytes = T::Bytes::default();
+        let len = bytes.as_ref().len();
+        let read = self
+            .settings
+            .lock()
+            .await
+            .read(self.key(item), bytes.as_mut())?;
+        Ok(read
+            .filter(|&read| read == len)
+            .and_then(|_| T::from_bytes(bytes)))
+    }
+
+    /// Read `item`, or `default` if it's absent.
+    pub async fn get_or<T: Value>(&self, item: u8, default: T) -> Result<T, S::Error> {
+        Ok(self.get(item).await?.unwrap_or(default))
+    }
+
+    pub async fn set<T: Value>(&self, item: u8, value: T) -> Result<(), S::Error> {
+        self.settings
+            .lock()
+            .await
+            .write(self.key(item), value.to_bytes().as_ref())
+    }
+
+    /// Read `item` into `buf` as raw bytes, for values such as a high score
+    /// table that the app encodes itself.
+    pub async fn get_bytes<'b>(
+        &self,
+        item: u8,
+        buf: &'b mut [u8],
+    ) -> Result<Option<&'b [u8]>, S::Error> {
+        let len = self.settings.lock().await.read(self.key(item), buf)?;
+        Ok(len.map(|len| &buf[..len]))
+    }
+
+    pub async fn set_bytes(&self, item: u8, value: &[u8]) -> Result<(), S::Error> {
+        self.settings.lock().await.write(self.key(item), value)
+    }
+
+    /// Read `item` into `buf` as text, or `None` if it's absent or not
+    /// UTF-8.
+    pub async fn get_str<'b>(
+        &self,
+        item: u8,
+        buf: &'b mut [u8],
+    ) -> Result<Option<&'b str>, S::Error> {
+        let bytes = self.get_bytes(item, buf).await?;
+        Ok(bytes.and_then(|bytes| str::from_utf8(bytes).ok()))
+    }
+
+    pub async fn set_str(&self, item: u8, value: &str) -> Result<(), S::Error> {
+        self.set_bytes(item, value.as_bytes()).await
+    }
+
+    /// Delete `item`. Removing an absent item is not an error.
+    pub async fn remove(&self, item: u8) -> Result<(), S::Error> {
+        self.settings.lock().await.remove(self.key(item))
+    }
+
+    /// Read `item` as a [`Versioned`] `serde` type, or `None` if it's
+    /// absent or an older version [`Versioned::upgrade`] doesn't take.
+    #[cfg(feature = "serde")]
+    pub async fn load<T: Versioned>(&self, item: u8) -> Result<Option<T>, TypedError<S::Error>> {
+        load(&mut *self.settings.lock().await, self.key(item))
+    }
+
+    /// Read `item` as a [`Versioned`] `serde` type, or its default if it's
+    /// absent.
+    #[cfg(feature = "serde")]
+    pub async fn load_or_default<T: Versioned + Default>(
+        &self,
+        item: u8,
+    ) -> Result<T, TypedError<S::Error>> {
+        Ok(self.load(item).await?.unwrap_or_default())
+    }
+
+    /// Store `value` as `item`, with its version.
+    #[cfg(feature = "serde")]
+    pub async fn save<T: Versioned>(
+        &self,
+        item: u8,
+        value: &T,
+    ) -> Result<(), TypedError<S::Error>> {
+        save(&mut *self.settings.lock().await, self.key(item), value)
+    }
+}
@@ -0,0 +1,489 @@
+//! [`FlashStore`]: a [`Store`] in the `kv` partition of the SPI flash.
+//!
+//! The partition is used as a log of sectors. Every write appends a record
+//! (key, length, CRC-32 and value) to the newest sector, and removing a key
+//! appends a tombstone; the newest record for a key wins. An index in RAM
+//! maps each key to its newest record, so reads don't scan the flash.
+//!
+//! One sector is always kept erased. When the log reaches it, the oldest
+//! sector's values that are still current are copied into it and the
+//! oldest sector is erased in turn, so sectors are written round-robin and
+//! wear evenly. That compaction takes one sector erase (about 50 ms) plus
+//! the copy; [`FlashStore::compact`] runs it ahead of time.
+//!
+//! A write cut short by a reset fails its CRC and is skipped on the next
+//! boot, leaving the value it would have replaced.
+//!
+//! The layout is the badge's own, not ESP-IDF's NVS, so it lives in its
+//! own [`PARTITION`] with an `undefined` subtype rather than in an
+//! `nvs`-typed one. Tools that read or reset NVS leave it alone, and the
+//! `nvs` name stays free for firmware that wants the real thing.
+
+use alloc::{
+    collections::BTreeMap,
+    vec::Vec,
+};
+
+use defmt::{
+    info,
+    warn,
+};
+use embedded_storage::{
+    ReadStorage,
+    nor_flash::NorFlash,
+};
+use esp_bootloader_esp_idf::partitions::{
+    self,
+    PARTITION_TABLE_MAX_LEN,
+};
+use esp_hal::rom::crc::crc32_le;
+use esp_storage::FlashStorageError;
+
+use super::{
+    Key,
+    Store,
+};
+use crate::FlashStorage;
+
+/// Label of the partition values are kept in.
+pub const PARTITION: &str = "kv";
+/// Longest value [`FlashStore`] takes.
+pub const MAX_VALUE_LEN: usize = 1024;
+
+/// Erase unit of the flash.
+const SECTOR: u32 = FlashStorage::SECTOR_SIZE;
+/// First bytes of a sector in use, before its sequence number.
+const SECTOR_MAGIC: [u8; 4] = *b"BKV1";
+const SECTOR_HEADER_LEN: u32 = 8;
+/// Key, length and CRC-32 before each value.
+const RECORD_HEADER_LEN: u32 = 8;
+/// Length of a record that removes its key.
+const TOMBSTONE: u16 = 0xffff;
+/// What erased flash reads as; never a valid key.
+const ERASED_KEY: Key = 0xffff;
+/// Bytes moved per flash access when checking or copying records.
+const CHUNK: usize = 64;
+
+/// Errors from [`FlashStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum FlashStoreError {
+    /// The partition table has no [`PARTITION`] partition, or it's smaller
+    /// than two sectors.
+    Partition(partitions::Error),
+    Flash(FlashStorageError),
+    /// The value is longer than [`MAX_VALUE_LEN`], or than the buffer given
+    /// to read it into.
+    TooLong,
+    /// Every sector is taken up by current values.
+    Full,
+    /// The stored value fails its CRC.
+    Corrupt,
+    /// Key `0xFFFF` can't be stored: it marks free space.
+    ReservedKey,
+}
+
+impl From<partitions::Error> for FlashStoreError {
+    fn from(err: partitions::Error) -> Self {
+        Self::Partition(err)
+    }
+}
+
+impl From<FlashStorageError> for FlashStoreError {
+    fn from(err: FlashStorageError) -> Self {
+        Self::Flash(err)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct RecordHeader {
+    key: Key,
+    len: u16,
+    crc: u32,
+}
+
+impl RecordHeader {
+    const fn from_bytes(bytes: [u8; RECORD_HEADER_LEN as usize]) -> Self {
+        let [k0, k1, l0, l1, c0, c1, c2, c3] = bytes;
+        Self {
+            key: u16::from_le_bytes([k0, k1]),
+            len: u16::from_le_bytes([l0, l1]),
+            crc: u32::from_le_bytes([c0, c1, c2, c3]),
+        }
+    }
+
+    /// The header for `value` under `key`, or a tombstone for `None`.
+    fn new(key: Key, value: Option<&[u8]>) -> Self {
+        let len = value.map_or(TOMBSTONE, |value| value.len() as u16);
+        let mut header = Self { key, len, crc: 0 };
+        header.crc = crc32_le(header.crc_prefix(), value.unwrap_or_default());
+        header
+    }
+
+    fn to_bytes(self) -> [u8; RECORD_HEADER_LEN as usize] {
+        let mut bytes = [0; RECORD_HEADER_LEN as usize];
+        bytes[..2].copy_from_slice(&self.key.to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.len.to_le_bytes());
+        bytes[4..].copy_from_slice(&self.crc.to_le_bytes());
+        bytes
+    }
+
+    /// CRC of the key and length, which the value's CRC carries on from.
+    fn crc_prefix(self) -> u32 {
+        crc32_le(0, &self.to_bytes()[..4])
+    }
+
+    const fn is_erased(self) -> bool {
+        self.key == ERASED_KEY && self.len == TOMBSTONE && self.crc == u32::MAX
+    }
+
+    /// Bytes of value that follow the header.
+    const fn value_len(self) -> u32 {
+        if self.len == TOMBSTONE {
+            0
+        } else {
+            self.len as u32
+        }
+    }
+
+    /// Bytes from this header to the next, padded to whole flash words.
+    const fn span(self) -> u32 {
+        RECORD_HEADER_LEN + self.value_len().next_multiple_of(FlashStorage::WORD_SIZE)
+    }
+}
+
+/// Key-value storage in the [`PARTITION`] partition. See the
+/// [module docs](self).
+///
+/// Every call blocks while the flash is read or written; share one through
+/// [`Settings`](super::Settings) so tasks take turns.
+pub struct FlashStore<'d> {
+    flash: FlashStorage<'d>,
+    /// Start of the partition.
+    offset: u32,
+    /// Sequence number of each sector in use, `None` for erased ones.
+    sectors: Vec<Option<u32>>,
+    /// Sector being written and where its free space starts.
+    active: Option<(usize, u32)>,
+    /// Where the newest record of each key that has a value starts.
+    index: BTreeMap<Key, u32>,
+}
+
+impl<'d> FlashStore<'d> {
+    /// Find the [`PARTITION`] partition and index what's stored in it.
+    ///
+    /// An erased partition, or one written by other firmware, comes up
+    /// empty.
+    pub fn new(mut flash: FlashStorage<'d>) -> Result<Self, FlashStoreError> {
+        let mut table = [0; PARTITION_TABLE_MAX_LEN];
+        let table = partitions::read_partition_table(&mut flash, &mut table)?;
+        let partition = table
+            .iter()
+            .find(|partition| partition.label_as_str() == PARTITION)
+            .ok_or(partitions::Error::Invalid)?;
+        let (offset, len) = (partition.offset(), partition.len());
+        if len / SECTOR < 2 {
+            return Err(partitions::Error::Invalid.into());
+        }
+        let mut store = Self {
+            flash,
+            offset,
+            sectors: Vec::new(),
+            active: None,
+            index: BTreeMap::new(),
+        };
+        store.mount(len / SECTOR)?;
+        info!(
+            "storage: {} keys in {} sectors at {=u32:#x}",
+            store.index.len(),
+            store.sectors.len(),
+            offset
+        );
+        Ok(store)
+    }
+
+    /// Keys that have a value.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// If the store is down to its erased sector and the sector being
+    /// written is over half full, move the oldest sector's current values
+    /// forward and erase it now, rather than in the middle of a later
+    /// write. Call it when a pause of tens of milliseconds won't be
+    /// noticed, such as between levels.
+    pub fn compact(&mut self) -> Result<(), FlashStoreError> {
+        if self.erased() < 2 && self.active.is_none_or(|(_, pos)| pos > SECTOR / 2) {
+            self.compact_oldest()?;
+        }
+        Ok(())
+    }
+
+    /// Read the sector headers and index every record, oldest first.
+    fn mount(&mut self, sectors: u32) -> Result<(), FlashStoreError> {
+        for sector in 0..sectors {
+            let mut header = [0; SECTOR_HEADER_LEN as usize];
+            ReadStorage::read(&mut self.flash, self.offset + sector * SECTOR, &mut header)?;
+            let [m0, m1, m2, m3, s0, s1, s2, s3] = header;
+            let sequence =
+                ([m0, m1, m2, m3] == SECTOR_MAGIC).then(|| u32::from_le_bytes([s0, s1, s2, s3]));
+            self.sectors.push(sequence);
+        }
+        let mut order: Vec<usize> = (0..self.sectors.len())
+            .filter(|&sector| self.sectors[sector].is_some())
+            .collect();
+        order.sort_by_key(|&sector| self.sectors[sector]);
+        for &sector in &order {
+            let mut pos = SECTOR_HEADER_LEN;
+            while pos + RECORD_HEADER_LEN <= SECTOR {
+                let at = self.address(sector, pos);
+                let header = self.header(at)?;
+                if header.is_erased() {
+                    break;
+                }
+                if header.value_len() as usize > MAX_VALUE_LEN || pos + header.span() > SECTOR {
+                    warn!(
+                        "storage: bad record at {=u32:#x}, skipping the rest of the sector",
+                        at
+                    );
+                    pos = SECTOR;
+                    break;
+                }
+                if !self.check(at, header)? {
+                    warn!(
+                        "storage: torn record for key {=u16:#x} at {=u32:#x}",
+                        header.key, at
+                    );
+                } else if header.len == TOMBSTONE {
+                    self.index.remove(&header.key);
+                } else {
+                    self.index.insert(header.key, at);
+                }
+                pos += header.span();
+            }
+            self.active = Some((sector, pos));
+        }
+        // A write cut short before its header leaves programmed bytes past
+        // the end; start a new sector rather than write over them.
+        if let Some((sector, pos)) = self.active
+            && !self.is_blank(self.address(sector, pos), SECTOR - pos)?
+        {
+            self.active = Some((sector, SECTOR));
+        }
+        Ok(())
+    }
+
+    fn address(&self, sector: usize, pos: u32) -> u32 {
+        self.offset + sector as u32 * SECTOR + pos
+    }
+
+    fn header(&mut self, at: u32) -> Result<RecordHeader, FlashStoreError> {
+        let mut bytes = [0; RECORD_HEADER_LEN as usize];
+        ReadStorage::read(&mut self.flash, at, &mut bytes)?;
+        Ok(RecordHeader::from_bytes(bytes))
+    }
+
+    /// Whether the value after the header at `at` matches its CRC.
+    fn check(&mut self, at: u32, header: RecordHeader) -> Result<bool, FlashStoreError> {
+        let mut crc = header.crc_prefix();
+        let mut chunk = [0; CHUNK];
+        let mut pos = at + RECORD_HEADER_LEN;
+        let end = pos + header.value_len();
+        while pos < end {
+            let chunk = &mut chunk[..(end - pos).min(CHUNK as u32) as usize];
+            ReadStorage::read(&mut self.flash, pos, chunk)?;
+            crc = crc32_le(crc, chunk);
+            pos += chunk.len() as u32;
+        }
+        Ok(crc == header.crc)
+    }
+
+    /// Whether the stored value at `at` is `value`.
+    fn matches(&mut self, at: u32, value: &[u8]) -> Result<bool, FlashStoreError> {
+        let header = self.header(at)?;
+        if header.len == TOMBSTONE || usize::from(header.len) != value.len() {
+            return Ok(false);
+        }
+        let mut chunk = [0; CHUNK];
+        for (index, expected) in value.chunks(CHUNK).enumerate() {
+            let chunk = &mut chunk[..expected.len()];
+            let pos = at + RECORD_HEADER_LEN + (index * CHUNK) as u32;
+            ReadStorage::read(&mut self.flash, pos, chunk)?;
+            if chunk != expected {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn is_blank(&mut self, mut at: u32, len: u32) -> Result<bool, FlashStoreError> {
+        let end = at + len;
+        let mut chunk = [0; CHUNK];
+        while at < end {
+            let chunk = &mut chunk[..(end - at).min(CHUNK as u32) as usize];
+            ReadStorage::read(&mut self.flash, at, chunk)?;
+            if chunk.iter().any(|&byte| byte != 0xff) {
+                return Ok(false);
+            }
+            at += chunk.len() as u32;
+        }
+        Ok(true)
+    }
+
+    fn erased(&self) -> usize {
+        self.sectors
+            .iter()
+            .filter(|sector| sector.is_none())
+            .count()
+    }
+
+    /// Append a record for `value` under `key`, or a tombstone for `None`,
+    /// and return where it starts.
+    fn append(&mut self, key: Key, value: Option<&[u8]>) -> Result<u32, FlashStoreError> {
+        let header = RecordHeader::new(key, value);
+        let value = value.unwrap_or_default();
+        for _ in 0..=self.sectors.len() {
+            let Some((sector, pos)) = self
+                .active
+                .filter(|&(_, pos)| pos + header.span() <= SECTOR)
+            else {
+                self.advance()?;
+                continue;
+            };
+            let at = self.address(sector, pos);
+            self.flash.write(at, &header.to_bytes())?;
+            let whole = value.len() & !(FlashStorage::WORD_SIZE as usize - 1);
+            self.flash.write(at + RECORD_HEADER_LEN, &value[..whole])?;
+            if whole < value.len() {
+                let mut tail = [0xff; FlashStorage::WORD_SIZE as usize];
+                tail[..value.len() - whole].copy_from_slice(&value[whole..]);
+                self.flash
+                    .write(at + RECORD_HEADER_LEN + whole as u32, &tail)?;
+            }
+            self.active = Some((sector, pos + header.span()));
+            return Ok(at);
+        }
+        Err(FlashStoreError::Full)
+    }
+
+    /// Move on to a new sector, compacting the oldest if the erased one
+    /// would be the last.
+    fn advance(&mut self) -> Result<(), FlashStoreError> {
+        if self.erased() < 2 {
+            self.compact_oldest()
+        } else {
+            self.open().map(drop)
+        }
+    }
+
+    /// Start writing the erased sector after the active one.
+    fn open(&mut self) -> Result<usize, FlashStoreError> {
+        let count = self.sectors.len();
+        let start = self.active.map_or(0, |(sector, _)| sector + 1);
+        let sector = (start..start + count)
+            .map(|sector| sector % count)
+            .find(|&sector| self.sectors[sector].is_none())
+            .ok_or(FlashStoreError::Full)?;
+        let sequence = self.sectors.iter().flatten().max().map_or(0, |max| max + 1);
+        let at = self.address(sector, 0);
+        // Erased sectors may hold a half-finished erase or another
+        // format's data, so erase again.
+        self.flash.erase(at, at + SECTOR)?;
+        let mut header = [0; SECTOR_HEADER_LEN as usize];
+        header[..4].copy_from_slice(&SECTOR_MAGIC);
+        header[4..].copy_from_slice(&sequence.to_le_bytes());
+        self.flash.write(at, &header)?;
+        self.sectors[sector] = Some(sequence);
+        self.active = Some((sector, SECTOR_HEADER_LEN));
+        Ok(sector)
+    }
+
+    /// Copy the current records of the oldest sector into a new one and
+    /// erase it.
+    fn compact_oldest(&mut self) -> Result<(), FlashStoreError> {
+        let Some(oldest) = (0..self.sectors.len())
+            .filter(|&sector| self.sectors[sector].is_some())
+            .min_by_key(|&sector| self.sectors[sector])
+        else {
+            return self.open().map(drop);
+        };
+        let target = self.open()?;
+        let mut pos = SECTOR_HEADER_LEN;
+        let mut end = SECTOR_HEADER_LEN;
+        while pos + RECORD_HEADER_LEN <= SECTOR {
+            let at = self.address(oldest, pos);
+            let header = self.header(at)?;
+            if header.is_erased() || pos + header.span() > SECTOR {
+                break;
+            }
+            if self.index.get(&header.key) == Some(&at) {
+                let to = self.address(target, end);
+                let mut chunk = [0; CHUNK];
+                let mut copied = 0;
+                while copied < header.span() {
+                    let chunk = &mut chunk[..(header.span() - copied).min(CHUNK as u32) as usize];
+                    ReadStorage::read(&mut self.flash, at + copied, chunk)?;
+                    self.flash.write(to + copied, chunk)?;
+                    copied += chunk.len() as u32;
+                }
+                self.index.insert(header.key, to);
+                end += header.span();
+            }
+            pos += header.span();
+        }
+        self.active = Some((target, end));
+        let at = self.address(oldest, 0);
+        self.flash.erase(at, at + SECTOR)?;
+        self.sectors[oldest] = None;
+        info!("storage: compacted sector {} into {}", oldest, target);
+        Ok(())
+    }
+}
+
+impl Store for FlashStore<'_> {
+    type Error = FlashStoreError;
+
+    fn read(&mut self, key: Key, buf: &mut [u8]) -> Result<Option<usize>, FlashStoreError> {
+        let Some(&at) = self.index.get(&key) else {
+            return Ok(None);
+        };
+        let header = self.header(at)?;
+        let len = usize::from(header.len);
+        let value = buf.get_mut(..len).ok_or(FlashStoreError::TooLong)?;
+        ReadStorage::read(&mut self.flash, at + RECORD_HEADER_LEN, value)?;
+        if crc32_le(header.crc_prefix(), value) != header.crc {
+            return Err(FlashStoreError::Corrupt);
+        }
+        Ok(Some(len))
+    }
+
+    /// Store `value` under `key`. Writing the value already stored is
+    /// skipped, so settings can be saved freely without wearing the flash.
+    fn write(&mut self, key: Key, value: &[u8]) -> Result<(), FlashStoreError> {
+        if key == ERASED_KEY {
+            return Err(FlashStoreError::ReservedKey);
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(FlashStoreError::TooLong);
+        }
+        if let Some(&at) = self.index.get(&key)
+            && self.matches(at, value)?
+        {
+            return Ok(());
+        }
+        let at = self.append(key, Some(value))?;
+        self.index.insert(key, at);
+        Ok(())
+    }
+
+    fn remove(&mut self, key: Key) -> Result<(), FlashStoreError> {
+        if self.index.contains_key(&key) {
+            self.append(key, None)?;
+            self.index.remove(&key);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,429 @@
+//! Live parameter tweaking for tuning games on hardware.
+//!
+//! Register tunables (gravity, speeds, colours) in a [`Tweaker`], feed it the
+//! buttons once per frame and read the values back in your game loop. With
+//! the overlay shown, up/down select a parameter and A/B raise/lower it;
+//! left/right take ten steps at a time, or pick the channel of a colour.
+//! Start dumps every value over defmt so it can be pasted back into code,
+//! and Select hides or shows the overlay.
+//!
+//! ```rust,ignore
+//! const GRAVITY: usize = 0;
+//! let mut tweaker = Tweaker::new([Param::float("gravity", 0.3, 0.0..=2.0, 0.05)]);
+//! loop {
+//!     tweaker.poll(&buttons);
+//!     ball.vy += tweaker.float(GRAVITY);
+//!     tweaker.draw(&mut display)?;
+//! }
+//! ```
+
+use core::{
+    fmt::Write as _,
+    ops::RangeInclusive,
+};
+
+use embedded_graphics::{
+    Drawable,
+    mono_font::{
+        MonoTextStyle,
+        ascii::FONT_6X10,
+    },
+    pixelcolor::Rgb565,
+    prelude::{
+        DrawTarget,
+        Point,
+        RgbColor,
+        Size,
+    },
+    primitives::Rectangle,
+    text::{
+        Baseline,
+        Text,
+    },
+};
+
+use crate::{
+    Buttons,
+    ui::{
+        Frame,
+        FrameStyle,
+    },
+};
+
+const LINE_HEIGHT: i32 = 10;
+const PANEL_WIDTH: u32 = 150;
+
+/// A tunable value and its limits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Int {
+        value: i32,
+        min: i32,
+        max: i32,
+        step: i32,
+    },
+    Float {
+        value: f32,
+        min: f32,
+        max: f32,
+        step: f32,
+    },
+    Color(Rgb565),
+}
+
+/// A named tunable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Param {
+    name: &'static str,
+    value: Value,
+}
+
+impl Param {
+    /// An integer clamped to `range`, adjusted by `step`.
+    pub const fn int(
+        name: &'static str,
+        value: i32,
+        range: RangeInclusive<i32>,
+        step: i32,
+    ) -> Self {
+        Self {
+            name,
+            value: Value::Int {
+                value,
+                min: *range.start(),
+                max: *range.end(),
+                step,
+            },
+        }
+    }
+
+    /// A float clamped to `range`, adjusted by `step`.
+    pub const fn float(
+        name: &'static str,
+        value: f32,
+        range: RangeInclusive<f32>,
+        step: f32,
+    ) -> Self {
+        Self {
+            name,
+            value: Value::Float {
+                value,
+                min: *range.start(),
+                max: *range.end(),
+                step,
+            },
+        }
+    }
+
+    /// An RGB565 colour, adjusted one channel at a time.
+    pub const fn color(name: &'static str, value: Rgb565) -> Self {
+        Self {
+            name,
+            value: Value::Color(value),
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub const fn value(&self) -> Value {
+        self.value
+    }
+
+    fn nudge(&mut self, steps: i32, channel: usize) {
+        match &mut self.value {
+            Value::Int {
+                value,
+                min,
+                max,
+                step,
+            } => *value = value.saturating_add(steps * *step).clamp(*min, *max),
+            Value::Float {
+                value,
+                min,
+                max,
+                step,
+            } => *value = (*value + steps as f32 * *step).clamp(*min, *max),
+            Value::Color(color) => {
+                let mut rgb = [color.r(), color.g(), color.b()];
+                let max = [Rgb565::MAX_R, Rgb565::MAX_G, Rgb565::MAX_B][channel];
+                rgb[channel] = (i32::from(rgb[channel]) + steps).clamp(0, i32::from(max)) as u8;
+                *color = Rgb565::new(rgb[0], rgb[1], rgb[2]);
+            }
+        }
+    }
+}
+
+/// One step of tweaker navigation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum TweakInput {
+    /// Select the previous parameter.
+    Prev,
+    /// Select the next parameter.
+    Next,
+    /// Raise the selected value by one step.
+    Increase,
+    /// Lower the selected value by one step.
+    Decrease,
+    /// Lower by ten steps, or select the previous colour channel.
+    Left,
+    /// Raise by ten steps, or select the next colour channel.
+    Right,
+    /// Log all values over defmt.
+    Dump,
+    /// Show or hide the overlay.
+    Toggle,
+}
+
+/// A fixed set of `N` tunable parameters with an on-screen editor.
+pub struct Tweaker<const N: usize> {
+    params: [Param; N],
+    selected: usize,
+    channel: usize,
+    visible: bool,
+    held: u8,
+}
+
+impl<const N: usize> Tweaker<N> {
+    /// A visible tweaker over `params`, with the first one selected.
+    pub const fn new(params: [Param; N]) -> Self {
+        Self {
+            params,
+            selected: 0,
+            channel: 0,
+            visible: true,
+            held: 0,
+        }
+    }
+
+    pub const fn params(&self) -> &[Param; N] {
+        &self.params
+    }
+
+    pub const fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// The current value of integer parameter `index`.
+    ///
+    /// # Panics
+    ///
+    /// If the parameter is not an [`Value::Int`].
+    pub fn int(&self, index: usize) -> i32 {
+        match self.params[index].value {
+            Value::Int { value, .. } => value,
+            _ => panic!("tweak parameter is not an int"),
+        }
+    }
+
+    /// The current value of float parameter `index`.
+    ///
+    /// # Panics
+    ///
+    /// If the parameter is not a [`Value::Float`].
+    pub fn float(&self, index: usize) -> f32 {
+        match self.params[index].value {
+            Value::Float { value, .. } => value,
+            _ => panic!("tweak parameter is not a float"),
+        }
+    }
+
+    /// The current value of colour parameter `index`.
+    ///
+    /// # Panics
+    ///
+    /// If the parameter is not a [`Value::Color`].
+    pub fn color(&self, index: usize) -> Rgb565 {
+        match self.params[index].value {
+            Value::Color(color) => color,
+            _ => panic!("tweak parameter is not a color"),
+        }
+    }
+
+    /// Apply one input. Returns `true` if a value changed.
+    ///
+    /// Adjustments are ignored while the overlay is hidden, so the same
+    /// buttons stay usable by the game.
+    pub fn handle(&mut self, input: TweakInput) -> bool {
+        if N == 0 || !self.visible && input != TweakInput::Toggle && input != TweakInput::Dump {
+            return false;
+        }
+        let is_color = matches!(self.params[self.selected].value, Value::Color(_));
+        let steps = match input {
+            TweakInput::Prev => {
+                self.selected = (self.selected + N - 1) % N;
+                self.channel = 0;
+                return false;
+            }
+            TweakInput::Next => {
+                self.selected = (self.selected + 1) % N;
+                self.channel = 0;
+                return false;
+            }
+            TweakInput::Left if is_color => {
+                self.channel = (self.channel + 2) % 3;
+                return false;
+            }
+            TweakInput::Right if is_color => {
+                self.channel = (self.channel + 1) % 3;
+                return false;
+            }
+            TweakInput::Dump => {
+                self.dump();
+                return false;
+            }
+            TweakInput::Toggle => {
+                self.visible = !self.visible;
+                return false;
+            }
+            TweakInput::Increase => 1,
+            TweakInput::Decrease => -1,
+            TweakInput::Right => 10,
+            TweakInput::Left => -10,
+        };
+        let param = &mut self.params[self.selected];
+        let before = param.value;
+        param.nudge(steps, self.channel);
+        param.value != before
+    }
+
+    /// Sample the buttons and apply any newly pressed ones.
+    ///
+    /// Call once per frame from a polling game loop. Returns `true` if a
+    /// value changed.
+    pub fn poll(&mut self, buttons: &Buttons) -> bool {
+        // Select is wired active-high; everything else is active-low.
+        let state = [
+            (buttons.up.is_low(), TweakInput::Prev),
+            (buttons.down.is_low(), TweakInput::Next),
+            (buttons.a.is_low(), TweakInput::Increase),
+            (buttons.b.is_low(), TweakInput::Decrease),
+            (buttons.left.is_low(), TweakInput::Left),
+            (buttons.right.is_low(), TweakInput::Right),
+            (buttons.start.is_low(), TweakInput::Dump),
+            (buttons.select.is_high(), TweakInput::Toggle),
+        ];
+        let mut changed = false;
+        for (bit, (pressed, input)) in state.into_iter().enumerate() {
+            let mask = 1 << bit;
+            if pressed && self.held & mask == 0 {
+                changed |= self.handle(input);
+            }
+            if pressed {
+                self.held |= mask;
+            } else {
+                self.held &= !mask;
+            }
+        }
+        changed
+    }
+
+    /// Log every parameter over defmt.
+    pub fn dump(&self) {
+        for param in &self.params {
+            match param.value {
+                Value::Int { value, .. } => defmt::info!("tweak {} = {}", param.name, value),
+                Value::Float { value, .. } => defmt::info!("tweak {} = {}", param.name, value),
+                Value::Color(c) => {
+                    defmt::info!(
+                        "tweak {} = Rgb565::new({}, {}, {})",
+                        param.name,
+                        c.r(),
+                        c.g(),
+                        c.b()
+                    );
+                }
+            }
+        }
+    }
+
+    /// The screen area the overlay covers.
+    pub fn area(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(2, 2),
+            Size::new(PANEL_WIDTH, N as u32 * LINE_HEIGHT as u32 + 6),
+        )
+    }
+
+    /// Draw the overlay in the top-left corner, if visible.
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if !self.visible {
+            return Ok(());
+        }
+        let frame = FrameStyle::new(Rgb565::WHITE, Rgb565::BLACK);
+        let area = self.area();
+        frame.draw_frame(area, target)?;
+        let origin = frame.content_area(area).top_left + Point::new(2, 1);
+
+        for (i, param) in self.params.iter().enumerate() {
+            let selected = i == self.selected;
+            let color = if selected {
+                Rgb565::YELLOW
+            } else {
+                Rgb565::WHITE
+            };
+            let style = MonoTextStyle::new(&FONT_6X10, color);
+            let mut line = LineBuf::new();
+            let marker = if selected { '>' } else { ' ' };
+            let _ = match param.value {
+                Value::Int { value, .. } => write!(line, "{marker}{}: {value}", param.name),
+                Value::Float { value, .. } => write!(line, "{marker}{}: {value:.2}", param.name),
+                Value::Color(c) => {
+                    let ch = if selected {
+                        ["R", "G", "B"][self.channel]
+                    } else {
+                        ""
+                    };
+                    write!(
+                        line,
+                        "{marker}{}: {}/{}/{} {ch}",
+                        param.name,
+                        c.r(),
+                        c.g(),
+                        c.b()
+                    )
+                }
+            };
+            let position = origin + Point::new(0, i as i32 * LINE_HEIGHT);
+            Text::with_baseline(line.as_str(), position, style, Baseline::Top).draw(target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fixed-size line buffer for formatting without an allocator.
+struct LineBuf {
+    bytes: [u8; 32],
+    len: usize,
+}
+
+impl LineBuf {
+    const fn new() -> Self {
+        Self {
+            bytes: [0; 32],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl core::fmt::Write for LineBuf {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        // Truncate at a char boundary rather than failing the whole line.
+        let room = self.bytes.len() - self.len;
+        let mut take = s.len().min(room);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.bytes[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+//! UI drawing helpers: dialog boxes and HUD frames.
+//!
+//! A [`Frame`] draws a border around an arbitrary rectangle. Two kinds ship
+//! with the crate:
+//! - [`FrameStyle`]: a procedural box (border, optional inner bevel, fill)
+//! - [`NinePatch`]: a small bitmap sliced into nine regions whose corners stay
+//!   fixed while the edges and centre stretch to fit
+//!
+//! Menus and widgets report what the user did through [`HapticFeedback`], so
+//! every screen buzzes the same way for the same action.
+
+use embedded_graphics::{
+    Drawable,
+    Pixel,
+    prelude::{
+        DrawTarget,
+        PixelColor,
+        Point,
+        Primitive,
+        Size,
+    },
+    primitives::{
+        PrimitiveStyle,
+        PrimitiveStyleBuilder,
+        Rectangle,
+    },
+};
+
+use crate::{
+    HapticEffect,
+    HapticQueue,
+};
+
+/// Something that can draw itself as a box around `area`.
+pub trait Frame<C: PixelColor> {
+    /// Draw the frame (and its fill, if any) covering `area`.
+    fn draw_frame<D>(&self, area: Rectangle, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>;
+
+    /// The area left for content once the border is drawn.
+    fn content_area(&self, area: Rectangle) -> Rectangle;
+}
+
+/// Border thickness on each side of a [`NinePatch`] or [`FrameStyle`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Insets {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl Insets {
+    /// The same inset on all four sides.
+    pub const fn uniform(width: u32) -> Self {
+        Self {
+            left: width,
+            top: width,
+            right: width,
+            bottom: width,
+        }
+    }
+
+    fn shrink(self, area: Rectangle) -> Rectangle {
+        Rectangle::new(
+            area.top_left + Point::new(self.left as i32, self.top as i32),
+            Size::new(
+                area.size.width.saturating_sub(self.left + self.right),
+                area.size.height.saturating_sub(self.top + self.bottom),
+            ),
+        )
+    }
+}
+
+// ── Procedural frames ───────────────────────────────────────────────────────
+
+/// A procedurally drawn box: outer border, optional inner bevel line, fill.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameStyle<C> {
+    border: C,
+    border_width: u32,
+    bevel: Option<C>,
+    fill: Option<C>,
+}
+
+impl<C: PixelColor> FrameStyle<C> {
+    /// A 1 px `border` around a solid `fill`.
+    pub const fn new(border: C, fill: C) -> Self {
+        Self {
+            border,
+            border_width: 1,
+            bevel: None,
+            fill: Some(fill),
+        }
+    }
+
+    /// A border only; the inside is left untouched.
+    pub const fn outline(border: C) -> Self {
+        Self {
+            border,
+            border_width: 1,
+            bevel: None,
+            fill: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_border_width(mut self, width: u32) -> Self {
+        self.border_width = width;
+        self
+    }
+
+    /// Add a 1 px line just inside the border, for a raised look.
+    #[must_use]
+    pub const fn with_bevel(mut self, color: C) -> Self {
+        self.bevel = Some(color);
+        self
+    }
+
+    const fn insets(&self) -> Insets {
+        let bevel = if self.bevel.is_some() { 1 } else { 0 };
+        Insets::uniform(self.border_width + bevel)
+    }
+}
+
+impl<C: PixelColor> Frame<C> for FrameStyle<C> {
+    fn draw_frame<D>(&self, area: Rectangle, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let mut style = PrimitiveStyleBuilder::new()
+            .stroke_color(self.border)
+            .stroke_width(self.border_width)
+            .stroke_alignment(embedded_graphics::primitives::StrokeAlignment::Inside);
+        if let Some(fill) = self.fill {
+            style = style.fill_color(fill);
+        }
+        area.into_styled(style.build()).draw(target)?;
+
+        if let Some(bevel) = self.bevel {
+            Insets::uniform(self.border_width)
+                .shrink(area)
+                .into_styled(PrimitiveStyle::with_stroke(bevel, 1))
+                .draw(target)?;
+        }
+        Ok(())
+    }
+
+    fn content_area(&self, area: Rectangle) -> Rectangle {
+        self.insets().shrink(area)
+    }
+}
+
+// ── Bitmap nine-patch ───────────────────────────────────────────────────────
+
+/// A bitmap border sliced into nine regions.
+///
+/// Corners are copied 1:1, edges stretch along one axis and the centre
+/// stretches along both (nearest neighbour). Pixels equal to the optional
+/// transparent key are skipped, so rounded corners can show the background.
+#[derive(Clone, Copy, Debug)]
+pub struct NinePatch<'a, C> {
+    pixels: &'a [C],
+    width: u32,
+    height: u32,
+    insets: Insets,
+    transparent: Option<C>,
+}
+
+impl<'a, C: PixelColor> NinePatch<'a, C> {
+    /// Wrap a row-major `width × height` bitmap with fixed-size `insets`.
+    ///
+    /// # Panics
+    ///
+    /// If `pixels` is shorter than `width * height`, or the insets leave no
+    /// stretchable middle row/column.
+    pub const fn new(pixels: &'a [C], width: u32, height: u32, insets: Insets) -> Self {
+        assert!(pixels.len() >= (width * height) as usize);
+        assert!(insets.left + insets.right < width && insets.top + insets.bottom < height);
+        Self {
+            pixels,
+            width,
+            height,
+            insets,
+            transparent: None,
+        }
+    }
+
+    /// Treat pixels of `color` as transparent.
+    #[must_use]
+    pub const fn with_transparent(mut self, color: C) -> Self {
+        self.transparent = Some(color);
+        self
+    }
+
+    fn sample(&self, x: u32, y: u32, size: Size) -> C {
+        let sx = map_axis(
+            x,
+            size.width,
+            self.width,
+            self.insets.left,
+            self.insets.right,
+        );
+        let sy = map_axis(
+            y,
+            size.height,
+            self.height,
+            self.insets.top,
+            self.insets.bottom,
+        );
+        self.pixels[(sy * self.width + sx) as usize]
+    }
+}
+
+/// Map a destination coordinate onto the source bitmap along one axis.
+fn map_axis(d: u32, dst_len: u32, src_len: u32, start: u32, end: u32) -> u32 {
+    if d < start {
+        d
+    } else if d + end >= dst_len {
+        src_len - (dst_len - d)
+    } else {
+        let src_mid = src_len - start - end;
+        let dst_mid = dst_len.saturating_sub(start + end).max(1);
+        start + (d - start) * src_mid / dst_mid
+    }
+}
+
+impl<C: PixelColor> Frame<C> for NinePatch<'_, C> {
+    fn draw_frame<D>(&self, area: Rectangle, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let size = area.size;
+        let pixels = (0..size.height).flat_map(move |y| (0..size.width).map(move |x| (x, y)));
+        match self.transparent {
+            None => target.fill_contiguous(&area, pixels.map(|(x, y)| self.sample(x, y, size))),
+            Some(key) => target.draw_iter(pixels.filter_map(|(x, y)| {
+                let color = self.sample(x, y, size);
+                (color != key).then(|| Pixel(area.top_left + Point::new(x as i32, y as i32), color))
+            })),
+        }
+    }
+
+    fn content_area(&self, area: Rectangle) -> Rectangle {
+        self.insets.shrink(area)
+    }
+}
+
+// ── Haptic feedback ─────────────────────────────────────────────────────
+
+/// Priority of UI feedback on a [`HapticQueue`]: above ambient patterns,
+/// below game events.
+pub const FEEDBACK_PRIORITY: u8 = 1;
+
+/// Where widgets send the user's actions to be felt.
+///
+/// The hooks default to [`HapticEffect`] presets, so an implementation only
+/// needs [`play`](Self::play); override a hook to change how that action
+/// feels everywhere. `()` turns feedback off.
+///
+/// ```rust,ignore
+/// static HAPTICS: HapticQueue = HapticQueue::new();
+///
+/// fn move_selection(menu: &mut Menu, feedback: &impl HapticFeedback, down: bool) {
+///     if menu.step(down) {
+///         feedback.focus_changed();
+///     } else {
+///         feedback.error();
+///     }
+/// }
+/// ```
+pub trait HapticFeedback {
+    fn play(&self, effect: HapticEffect);
+
+    /// Focus moved to another item.
+    fn focus_changed(&self) {
+        self.play(HapticEffect::Tick);
+    }
+
+    /// The focused item was chosen.
+    fn accepted(&self) {
+        self.play(HapticEffect::Click);
+    }
+
+    /// The action was refused, or failed.
+    fn error(&self) {
+        self.play(HapticEffect::Error);
+    }
+}
+
+impl HapticFeedback for () {
+    fn play(&self, _effect: HapticEffect) {}
+}
+
+/// Queues each effect at [`FEEDBACK_PRIORITY`], without waiting for it.
+impl HapticFeedback for HapticQueue {
+    fn play(&self, effect: HapticEffect) {
+        self.request(effect.pattern(), FEEDBACK_PRIORITY);
+    }
+}
+
+impl<T: HapticFeedback + ?Sized> HapticFeedback for &T {
+    fn play(&self, effect: HapticEffect) {
+        (**self).play(effect);
+    }
+
+    fn focus_changed(&self) {
+        (**self).focus_changed();
+    }
+
+    fn accepted(&self) {
+        (**self).accepted();
+    }
+
+    fn error(&self) {
+        (**self).error();
+    }
+}
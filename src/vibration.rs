@@ -1,43 +1,697 @@
 //! Vibration motor control for haptic feedback.
 
+use core::{
+    cell::RefCell,
+    cmp::Reverse,
+};
+
+use embassy_futures::select::select;
+use embassy_sync::{
+    blocking_mutex::{
+        Mutex,
+        raw::CriticalSectionRawMutex,
+    },
+    signal::Signal,
+};
 use embassy_time::{
     Duration,
+    Instant,
     Timer,
 };
-use esp_hal::gpio::{
-    Level,
-    Output,
-    OutputConfig,
+use esp_hal::{
+    Blocking,
+    gpio::{
+        Level,
+        Output,
+        OutputConfig,
+    },
+    handler,
+    timer::{
+        OneShotTimer,
+        timg::TimerGroup,
+    },
 };
 
 use crate::VibrationResources;
 
+/// Patterns that can wait in a [`HapticQueue`] at once.
+pub const HAPTIC_QUEUE_CAPACITY: usize = 4;
+
+/// Length of one on/off cycle when driving the motor below full intensity.
+/// The motor's inertia smooths it into a weaker buzz.
+const PWM_PERIOD: Duration = Duration::from_millis(10);
+
+/// One step of a [`HapticPattern`]: intensity moving from `start` to `end`
+/// over `duration`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct HapticSegment {
+    pub start: u8,
+    pub end: u8,
+    pub duration: Duration,
+}
+
+impl HapticSegment {
+    /// Buzz at `intensity`, 0–255, for `duration`.
+    pub const fn hold(intensity: u8, duration: Duration) -> Self {
+        Self {
+            start: intensity,
+            end: intensity,
+            duration,
+        }
+    }
+
+    /// Motor off for `duration`.
+    pub const fn pause(duration: Duration) -> Self {
+        Self::hold(0, duration)
+    }
+
+    /// Sweep evenly from `start` to `end` over `duration`.
+    pub const fn ramp(start: u8, end: u8, duration: Duration) -> Self {
+        Self {
+            start,
+            end,
+            duration,
+        }
+    }
+
+    /// Intensity `elapsed` into the segment.
+    fn intensity_at(&self, elapsed: Duration) -> u8 {
+        let total = self.duration.as_ticks().max(1);
+        let t = elapsed.as_ticks().min(total);
+        let (start, end) = (i64::from(self.start), i64::from(self.end));
+        (start + (end - start) * t as i64 / total as i64) as u8
+    }
+}
+
+/// A sequence of [`HapticSegment`]s, played once or looped.
+///
+/// ```rust,ignore
+/// static LUB_DUB: [HapticSegment; 3] = [
+///     HapticSegment::hold(255, Duration::from_millis(80)),
+///     HapticSegment::pause(Duration::from_millis(120)),
+///     HapticSegment::hold(160, Duration::from_millis(80)),
+/// ];
+///
+/// vibration.play(HapticPattern::new(&LUB_DUB)).await;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct HapticPattern {
+    segments: &'static [HapticSegment],
+    looping: bool,
+}
+
+impl HapticPattern {
+    /// Play `segments` once.
+    pub const fn new(segments: &'static [HapticSegment]) -> Self {
+        Self {
+            segments,
+            looping: false,
+        }
+    }
+
+    /// Start over after the last segment, until the future is dropped.
+    #[must_use]
+    pub const fn with_loop(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub const fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Length of one pass through the segments.
+    pub fn duration(&self) -> Duration {
+        self.segments
+            .iter()
+            .fold(Duration::from_ticks(0), |total, segment| {
+                total + segment.duration
+            })
+    }
+}
+
+// ── Presets ─────────────────────────────────────────────────────────────
+
+/// Named feedback patterns, so every app feels the same.
+///
+/// Tuned for the badge's ERM motor, which needs about 10 ms at full power
+/// to spin up noticeably and keeps spinning briefly once cut off, so short
+/// effects are full-power bursts rather than low intensities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum HapticEffect {
+    /// Barely there; for scrolling through lists.
+    Tick,
+    /// A button press or selection.
+    Click,
+    /// Two clicks, e.g. for toggling something on.
+    DoubleClick,
+    /// Short, then stronger and longer; an action worked.
+    Success,
+    /// Three sharp buzzes; an action failed.
+    Error,
+    /// One long, strong thud, e.g. for a long press or a hit.
+    Heavy,
+}
+
+static TICK: [HapticSegment; 1] = [HapticSegment::hold(255, Duration::from_millis(8))];
+static CLICK: [HapticSegment; 1] = [HapticSegment::hold(255, Duration::from_millis(18))];
+static DOUBLE_CLICK: [HapticSegment; 3] = [
+    HapticSegment::hold(255, Duration::from_millis(18)),
+    HapticSegment::pause(Duration::from_millis(70)),
+    HapticSegment::hold(255, Duration::from_millis(18)),
+];
+static SUCCESS: [HapticSegment; 3] = [
+    HapticSegment::hold(200, Duration::from_millis(25)),
+    HapticSegment::pause(Duration::from_millis(60)),
+    HapticSegment::ramp(255, 140, Duration::from_millis(90)),
+];
+static ERROR: [HapticSegment; 5] = [
+    HapticSegment::hold(255, Duration::from_millis(40)),
+    HapticSegment::pause(Duration::from_millis(50)),
+    HapticSegment::hold(255, Duration::from_millis(40)),
+    HapticSegment::pause(Duration::from_millis(50)),
+    HapticSegment::hold(255, Duration::from_millis(40)),
+];
+static HEAVY: [HapticSegment; 1] = [HapticSegment::hold(255, Duration::from_millis(80))];
+
+impl HapticEffect {
+    pub const fn pattern(self) -> HapticPattern {
+        HapticPattern::new(match self {
+            Self::Tick => &TICK,
+            Self::Click => &CLICK,
+            Self::DoubleClick => &DOUBLE_CLICK,
+            Self::Success => &SUCCESS,
+            Self::Error => &ERROR,
+            Self::Heavy => &HEAVY,
+        })
+    }
+}
+
+// ── Metronome ───────────────────────────────────────────────────────────
+
+/// How late a [`Metronome`] beat may still be played.
+const BEAT_TOLERANCE: Duration = Duration::from_millis(10);
+
+static DOWNBEAT: [HapticSegment; 1] = [HapticSegment::hold(255, Duration::from_millis(45))];
+
+/// Haptic beats at a steady tempo, with the first beat of each bar
+/// accented.
+///
+/// Every beat is scheduled from the start time rather than from the beat
+/// before, so the tempo doesn't drift however long the patterns or the
+/// executor take. A beat more than 10 ms late is skipped.
+///
+/// ```rust,ignore
+/// let metronome = Metronome::new(120).with_beats_per_bar(3);
+/// let start = Instant::now();
+/// // Elsewhere, score the player against metronome.beat_at(start, n).
+/// metronome.run(vibra, start).await
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Metronome {
+    bpm: u16,
+    beats_per_bar: u8,
+    accent: HapticPattern,
+    beat: HapticPattern,
+}
+
+impl Metronome {
+    /// `bpm` beats a minute in 4/4, a long buzz on the downbeat and a
+    /// [`Click`](HapticEffect::Click) on the others.
+    pub const fn new(bpm: u16) -> Self {
+        Self {
+            bpm,
+            beats_per_bar: 4,
+            accent: HapticPattern::new(&DOWNBEAT),
+            beat: HapticEffect::Click.pattern(),
+        }
+    }
+
+    /// Accent every `beats`th beat; 1 accents them all.
+    #[must_use]
+    pub const fn with_beats_per_bar(mut self, beats: u8) -> Self {
+        self.beats_per_bar = beats;
+        self
+    }
+
+    /// Play `accent` on downbeats and `beat` on the rest. Both should end
+    /// well within a beat.
+    #[must_use]
+    pub const fn with_patterns(mut self, accent: HapticPattern, beat: HapticPattern) -> Self {
+        self.accent = accent;
+        self.beat = beat;
+        self
+    }
+
+    pub const fn bpm(&self) -> u16 {
+        self.bpm
+    }
+
+    /// When beat `n` falls, counting from 0 at `start`.
+    pub fn beat_at(&self, start: Instant, n: u32) -> Instant {
+        start + Duration::from_micros(u64::from(n) * 60_000_000 / u64::from(self.bpm.max(1)))
+    }
+
+    /// The last beat at or before `now`, counting from 0 at `start`.
+    pub fn beat_index(&self, start: Instant, now: Instant) -> u32 {
+        let elapsed = now.saturating_duration_since(start).as_micros();
+        (elapsed * u64::from(self.bpm.max(1)) / 60_000_000) as u32
+    }
+
+    /// Buzz on every beat from `start`, forever.
+    pub async fn run(&self, vibra: &mut Vibration, start: Instant) -> ! {
+        let mut n = 0;
+        loop {
+            let now = Instant::now();
+            if now.saturating_duration_since(self.beat_at(start, n)) > BEAT_TOLERANCE {
+                // Behind, e.g. `start` was in the past; pick up at the next beat.
+                n = self.beat_index(start, now) + 1;
+            }
+            Timer::at(self.beat_at(start, n)).await;
+            let pattern = if n % u32::from(self.beats_per_bar.max(1)) == 0 {
+                self.accent
+            } else {
+                self.beat
+            };
+            vibra.play(pattern).await;
+            n += 1;
+        }
+    }
+}
+
+/// Cap on how hard the motor may be driven over time, so a stuck or buggy
+/// app can't overheat it or drain the battery.
+///
+/// At most `max_duty_percent` of any `window` with the motor on, counted
+/// as a leaky bucket: after a rest the motor may run for
+/// `max_duty_percent` of `window` in one go, and over longer stretches it
+/// averages no more than `max_duty_percent`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct DutyLimit {
+    pub max_duty_percent: u8,
+    pub window: Duration,
+}
+
+impl DutyLimit {
+    /// Half of any two seconds.
+    pub const DEFAULT: Self = Self {
+        max_duty_percent: 50,
+        window: Duration::from_secs(2),
+    };
+
+    /// On-time the bucket holds, in microseconds.
+    fn capacity_us(self) -> u64 {
+        let duty = u64::from(self.max_duty_percent.min(100));
+        self.window.as_micros() * duty * (100 - duty) / (100 * 100)
+    }
+}
+
+impl Default for DutyLimit {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The motor pin and the timer that cuts it off, shared with the timer's
+/// interrupt.
+struct Motor {
+    pin: Output<'static>,
+    timer: OneShotTimer<'static, Blocking>,
+}
+
+static MOTOR: Mutex<CriticalSectionRawMutex, RefCell<Option<Motor>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Switch the motor off when the cut-off timer fires.
+#[handler]
+fn cut_off() {
+    MOTOR.lock(|motor| {
+        if let Some(motor) = motor.borrow_mut().as_mut() {
+            motor.timer.clear_interrupt();
+            motor.pin.set_low();
+        }
+    });
+}
+
 /// Controls the onboard vibration motor.
+///
+/// Every way of switching the motor on respects a [`DutyLimit`], on by
+/// default; the motor stays off, or stops early, once it is used up. The
+/// stop is made by a hardware timer interrupt, so it happens on time even
+/// if the app never calls [`poll`](Self::poll) or drops a future midway.
 pub struct Vibration {
-    pin: Output<'static>,
+    /// Whether the motor was last switched on rather than off; it may
+    /// have been cut off since, at `off_at`.
+    on: bool,
+    /// When the motor has to stop: the end of a
+    /// [`pulse_nb`](Vibration::pulse_nb), or of the duty allowance.
+    off_at: Option<Instant>,
+    limit: Option<DutyLimit>,
+    /// On-time above the allowed average, in microseconds.
+    excess_us: u64,
+    accounted_at: Instant,
 }
 
 impl From<VibrationResources<'static>> for Vibration {
     fn from(res: VibrationResources<'static>) -> Self {
+        let timg = TimerGroup::new(res.timer);
+        let mut timer = OneShotTimer::new(timg.timer0);
+        timer.set_interrupt_handler(cut_off);
+        timer.listen();
+        let pin = Output::new(res.motor, Level::Low, OutputConfig::default());
+        MOTOR.lock(|motor| *motor.borrow_mut() = Some(Motor { pin, timer }));
         Self {
-            pin: Output::new(res.motor, Level::Low, OutputConfig::default()),
+            on: false,
+            off_at: None,
+            limit: Some(DutyLimit::DEFAULT),
+            excess_us: 0,
+            accounted_at: Instant::MIN,
         }
     }
 }
 
 impl Vibration {
+    /// Switch the motor on until [`off`](Self::off), or until the duty
+    /// allowance runs out.
     pub fn on(&mut self) {
-        self.pin.set_high();
+        let until = self.start();
+        self.off_at = self.limit.map(|_| until);
     }
 
     pub fn off(&mut self) {
-        self.pin.set_low();
+        self.account(Instant::now());
+        self.off_at = None;
+        self.switch(false);
+    }
+
+    pub const fn duty_limit(&self) -> Option<DutyLimit> {
+        self.limit
+    }
+
+    /// Change the duty cap, or lift it with `None`, from the next time the
+    /// motor is switched on. The on-time already counted is kept.
+    pub fn set_duty_limit(&mut self, limit: Option<DutyLimit>) {
+        self.account(Instant::now());
+        self.limit = limit;
+    }
+
+    /// Start a pulse of `duration` and return at once; the cut-off timer
+    /// stops the motor when it's over. A pulse already running is extended
+    /// if this one ends later.
+    ///
+    /// ```rust,ignore
+    /// loop {
+    ///     if hit {
+    ///         vibra.pulse_nb(Duration::from_millis(40));
+    ///     }
+    ///     ticker.next().await;
+    /// }
+    /// ```
+    pub fn pulse_nb(&mut self, duration: Duration) {
+        let pending = self
+            .off_at
+            .filter(|&off_at| self.on && Instant::now() < off_at);
+        let ends = Instant::now() + duration;
+        let ends = pending.map_or(ends, |off_at| off_at.max(ends));
+        let ends = ends.min(self.start());
+        self.off_at = Some(ends);
+        self.arm(ends);
+    }
+
+    /// Catch up with a pulse or duty allowance the cut-off timer has
+    /// ended. Optional: the motor is already off by then.
+    pub fn poll(&mut self) {
+        if self.off_at.is_some_and(|off_at| Instant::now() >= off_at) {
+            self.off();
+        }
+    }
+
+    /// Whether the motor is on with an end time still to come.
+    pub fn is_pulsing(&self) -> bool {
+        self.on && self.off_at.is_some_and(|off_at| Instant::now() < off_at)
     }
 
     /// Buzz for the given duration, then stop.
     pub async fn pulse(&mut self, duration: Duration) {
-        self.on();
-        Timer::after(duration).await;
+        let until = self.start();
+        Timer::at((Instant::now() + duration).min(until)).await;
         self.off();
     }
+
+    /// Play a preset, returning when it ends.
+    pub async fn effect(&mut self, effect: HapticEffect) {
+        self.play(effect.pattern()).await;
+    }
+
+    /// Play `pattern`, returning when it ends; a looping pattern never
+    /// does.
+    ///
+    /// Intensities between 0 and 255 are made by switching the motor on
+    /// for part of every 10 ms. Dropping the future can leave the motor
+    /// running, so call [`off`](Self::off) after cancelling it.
+    pub async fn play(&mut self, pattern: HapticPattern) {
+        loop {
+            for segment in pattern.segments {
+                self.play_segment(segment).await;
+            }
+            if !pattern.looping || pattern.segments.is_empty() {
+                break;
+            }
+        }
+        self.off();
+    }
+
+    async fn play_segment(&mut self, segment: &HapticSegment) {
+        let start = Instant::now();
+        let ends = start + segment.duration;
+        loop {
+            let now = Instant::now();
+            if now >= ends {
+                return;
+            }
+            let period_end = (now + PWM_PERIOD).min(ends);
+            let on_ticks = PWM_PERIOD.as_ticks() * u64::from(segment.intensity_at(now - start))
+                / u64::from(u8::MAX);
+            if on_ticks == 0 {
+                self.off();
+            } else {
+                let until = self.start();
+                let on_until = (now + Duration::from_ticks(on_ticks)).min(until);
+                if on_until < period_end {
+                    Timer::at(on_until).await;
+                    self.off();
+                }
+            }
+            Timer::at(period_end).await;
+        }
+    }
+
+    /// Switch the motor on if the duty allowance has any left, with the
+    /// cut-off timer set for when it runs out. Returns when it has to go
+    /// off again, which is now if it was refused.
+    fn start(&mut self) -> Instant {
+        let now = Instant::now();
+        self.account(now);
+        let until = match self.limit {
+            Some(limit) if limit.max_duty_percent < 100 => {
+                let duty = u64::from(limit.max_duty_percent);
+                let left_us = limit.capacity_us().saturating_sub(self.excess_us);
+                if left_us == 0 {
+                    self.switch(false);
+                    return now;
+                }
+                // The excess grows at the rate the motor runs above the duty
+                // cap.
+                now + Duration::from_micros(left_us * 100 / (100 - duty))
+            }
+            _ => Instant::MAX,
+        };
+        self.arm(until);
+        self.switch(true);
+        until
+    }
+
+    fn switch(&mut self, on: bool) {
+        self.on = on;
+        MOTOR.lock(|motor| {
+            if let Some(motor) = motor.borrow_mut().as_mut() {
+                motor.pin.set_level(Level::from(on));
+            }
+        });
+    }
+
+    /// Have the cut-off timer switch the motor off at `at`, or never for
+    /// [`Instant::MAX`].
+    fn arm(&mut self, at: Instant) {
+        MOTOR.lock(|motor| {
+            let mut motor = motor.borrow_mut();
+            let Some(motor) = motor.as_mut() else {
+                return;
+            };
+            motor.timer.stop();
+            if at == Instant::MAX {
+                return;
+            }
+            let micros = at.saturating_duration_since(Instant::now()).as_micros();
+            let timeout = esp_hal::time::Duration::from_micros(micros.max(1));
+            if motor.timer.schedule(timeout).is_err() {
+                // Too far off to time: cut off now rather than never.
+                motor.pin.set_low();
+            }
+        });
+    }
+
+    /// Bring the on-time count up to `now`. While switched on, the motor
+    /// ran until `now` or until the cut-off timer stopped it at `off_at`.
+    fn account(&mut self, now: Instant) {
+        let since = self.accounted_at;
+        self.accounted_at = now;
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let duty = u64::from(limit.max_duty_percent.min(100));
+        let on_until = match (self.on, self.off_at) {
+            (false, _) => since,
+            (true, Some(off_at)) => off_at.clamp(since, now),
+            (true, None) => now,
+        };
+        let on = on_until.saturating_duration_since(since).as_micros();
+        let off = now.saturating_duration_since(on_until).as_micros();
+        self.excess_us += on * (100 - duty) / 100;
+        self.excess_us = self.excess_us.saturating_sub(off * duty / 100);
+        self.excess_us = self.excess_us.min(limit.capacity_us());
+    }
+}
+
+// ── Background queue ────────────────────────────────────────────────────
+
+#[derive(Clone, Copy)]
+struct Queued {
+    pattern: HapticPattern,
+    priority: u8,
+    queued_at: Instant,
+}
+
+/// Lets any task ask for haptic feedback while one task owns the
+/// [`Vibration`] and plays the requests with [`run`](Self::run).
+///
+/// A more important request cuts off the pattern playing, which is
+/// dropped: feedback that arrives late is worse than none. Requests of
+/// equal priority play in the order they came.
+///
+/// ```rust,ignore
+/// static HAPTICS: HapticQueue = HapticQueue::new();
+///
+/// #[embassy_executor::task]
+/// async fn haptics_task(vibra: &'static mut Vibration) {
+///     HAPTICS.run(vibra).await
+/// }
+///
+/// HAPTICS.request(HapticPattern::new(&TICK), 0);
+/// HAPTICS.request(HapticPattern::new(&ALARM).with_loop(true), 200);
+/// ```
+pub struct HapticQueue {
+    queue: Mutex<CriticalSectionRawMutex, RefCell<[Option<Queued>; HAPTIC_QUEUE_CAPACITY]>>,
+    changed: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl Default for HapticQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HapticQueue {
+    pub const fn new() -> Self {
+        Self {
+            queue: Mutex::new(RefCell::new([None; HAPTIC_QUEUE_CAPACITY])),
+            changed: Signal::new(),
+        }
+    }
+
+    /// Queue `pattern`. Higher `priority` plays first.
+    ///
+    /// When the queue is full the least important waiting request makes
+    /// room, if it matters less than this one. Returns `false` if
+    /// `pattern` was dropped instead.
+    pub fn request(&self, pattern: HapticPattern, priority: u8) -> bool {
+        let queued = Queued {
+            pattern,
+            priority,
+            queued_at: Instant::now(),
+        };
+        let accepted = self.queue.lock(|queue| {
+            let mut queue = queue.borrow_mut();
+            let slot = queue.iter().position(Option::is_none).or_else(|| {
+                queue
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, slot)| slot.map(|other| (i, other)))
+                    .min_by_key(|(_, other)| (other.priority, Reverse(other.queued_at)))
+                    .filter(|(_, other)| other.priority < priority)
+                    .map(|(i, _)| i)
+            });
+            let Some(i) = slot else {
+                return false;
+            };
+            queue[i] = Some(queued);
+            true
+        });
+        if accepted {
+            self.changed.signal(());
+        }
+        accepted
+    }
+
+    /// Drop every waiting request. The one playing finishes.
+    pub fn clear(&self) {
+        self.queue
+            .lock(|queue| *queue.borrow_mut() = [None; HAPTIC_QUEUE_CAPACITY]);
+    }
+
+    /// Play queued requests on `vibra`, forever.
+    pub async fn run(&self, vibra: &mut Vibration) -> ! {
+        loop {
+            let Some(current) = self.take_next() else {
+                self.changed.wait().await;
+                continue;
+            };
+            select(
+                vibra.play(current.pattern),
+                self.outranked(current.priority),
+            )
+            .await;
+            vibra.off();
+        }
+    }
+
+    /// Wait until something more important than `priority` is queued.
+    async fn outranked(&self, priority: u8) {
+        loop {
+            self.changed.wait().await;
+            let outranked = self.queue.lock(|queue| {
+                queue
+                    .borrow()
+                    .iter()
+                    .flatten()
+                    .any(|queued| queued.priority > priority)
+            });
+            if outranked {
+                return;
+            }
+        }
+    }
+
+    fn take_next(&self) -> Option<Queued> {
+        self.queue.lock(|queue| {
+            let mut queue = queue.borrow_mut();
+            let i = queue
+                .iter()
+                .enumerate()
+                .filter_map(|(i, slot)| slot.map(|queued| (i, queued)))
+                .max_by_key(|(_, queued)| (queued.priority, Reverse(queued.queued_at)))
+                .map(|(i, _)| i)?;
+            queue[i].take()
+        })
+    }
 }
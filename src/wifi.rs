@@ -0,0 +1,317 @@
+//! Wi-Fi station and access point modes on top of `esp-radio`, behind the
+//! `wifi` feature.
+//!
+//! The radio driver runs on the `esp-rtos` scheduler and allocates its
+//! buffers on the heap, so bring things up in this order:
+//!
+//! ```rust,ignore
+//! let peripherals = disobey2026badge::init();
+//! let resources = split_resources!(peripherals);
+//!
+//! // Wi-Fi needs about 100 KiB of heap on top of what the app uses.
+//! esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+//! esp_alloc::heap_allocator!(size: 64 * 1024);
+//!
+//! let timg0 = TimerGroup::new(peripherals.TIMG0);
+//! esp_rtos::start(timg0.timer0);
+//!
+//! let (mut wifi, device) = Wifi::new(resources.wifi)?;
+//! for ap in wifi.scan(10).await? {
+//!     info!("{} {} dBm", ap.ssid.as_str(), ap.signal_strength);
+//! }
+//! wifi.connect("hacklab", "hunter22").await?;
+//! // Hand `device` to `embassy-net` for TCP/IP.
+//! ```
+//!
+//! [`Wifi::access_point`] and [`Wifi::start_access_point`] do the same for
+//! an access point of the badge's own; the captive portal (`portal` feature)
+//! is built on them. With the `sniffer` feature, [`Wifi::sniffer`] puts the
+//! radio in promiscuous mode instead; see `sniffer`. [`Wifi::survey`] sums
+//! up a scan per channel, see [`survey`].
+//!
+//! [`Wifi::new`] must run on the core that called `esp_rtos::start`, with
+//! interrupts enabled, i.e. not inside a critical section. The radio wants
+//! optimised code even in debug builds:
+//!
+//! ```toml
+//! [profile.dev.package.esp-radio]
+//! opt-level = 3
+//! ```
+
+#[cfg(feature = "sniffer")]
+pub mod sniffer;
+pub mod survey;
+
+use alloc::vec::Vec;
+
+use defmt::{
+    info,
+    warn,
+};
+use embassy_futures::select::{
+    Either,
+    select,
+};
+use embassy_time::{
+    Duration,
+    Timer,
+};
+#[cfg(feature = "esp-now")]
+use esp_radio::esp_now::EspNow;
+pub use esp_radio::wifi::{
+    AccessPointInfo,
+    WifiController,
+    WifiDevice,
+};
+use esp_radio::{
+    InitializationError,
+    wifi::{
+        self,
+        AccessPointConfig,
+        AuthMethod,
+        ClientConfig,
+        ModeConfig,
+        PowerSaveMode,
+        ScanConfig,
+        WifiEvent,
+        WifiStaState,
+    },
+};
+
+use self::survey::Survey;
+use crate::{
+    WifiResources,
+    power::{
+        self,
+        Allowance,
+    },
+    radio,
+};
+
+/// How long [`Wifi::stay_connected`] waits before trying again.
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Errors from bringing Wi-Fi up or using it.
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum WifiError {
+    /// The radio driver failed to start, e.g. because `esp_rtos::start`
+    /// hasn't been called yet.
+    Init(InitializationError),
+    /// The Wi-Fi driver rejected a request, or the connection failed.
+    Driver(wifi::WifiError),
+    /// The radio couldn't be moved to this channel.
+    #[cfg(feature = "sniffer")]
+    Channel(u8),
+}
+
+impl From<InitializationError> for WifiError {
+    fn from(err: InitializationError) -> Self {
+        Self::Init(err)
+    }
+}
+
+impl From<wifi::WifiError> for WifiError {
+    fn from(err: wifi::WifiError) -> Self {
+        Self::Driver(err)
+    }
+}
+
+/// The badge's Wi-Fi, as a station joining an access point or as an access
+/// point of its own.
+pub struct Wifi {
+    controller: WifiController<'static>,
+}
+
+impl Wifi {
+    /// Start the radio and return the controller along with the station
+    /// network device, for `embassy-net`.
+    pub fn new(res: WifiResources<'static>) -> Result<(Self, WifiDevice<'static>), WifiError> {
+        let (controller, interfaces) =
+            wifi::new(radio::controller()?, res.wifi, Default::default())?;
+        Ok((Self { controller }, interfaces.sta))
+    }
+
+    /// [`new`](Self::new), also returning ESP-NOW for
+    /// [`GameLink`](crate::gamelink::GameLink). ESP-NOW only works once the
+    /// radio is [`start`](Self::start)ed.
+    #[cfg(feature = "esp-now")]
+    pub fn with_esp_now(
+        res: WifiResources<'static>,
+    ) -> Result<(Self, WifiDevice<'static>, EspNow<'static>), WifiError> {
+        let (controller, interfaces) =
+            wifi::new(radio::controller()?, res.wifi, Default::default())?;
+        Ok((Self { controller }, interfaces.sta, interfaces.esp_now))
+    }
+
+    /// [`new`](Self::new), but returning the access point network device
+    /// instead, for serving clients with `embassy-net`. Open the access point
+    /// with [`start_access_point`](Self::start_access_point).
+    pub fn access_point(
+        res: WifiResources<'static>,
+    ) -> Result<(Self, WifiDevice<'static>), WifiError> {
+        let (controller, interfaces) =
+            wifi::new(radio::controller()?, res.wifi, Default::default())?;
+        Ok((Self { controller }, interfaces.ap))
+    }
+
+    /// [`new`](Self::new), but returning a [`Sniffer`](sniffer::Sniffer)
+    /// that hears every frame in the air instead of a network device.
+    /// Capture starts once the radio is [`start`](Self::start)ed and
+    /// [`Sniffer::start`](sniffer::Sniffer::start) is called.
+    #[cfg(feature = "sniffer")]
+    pub fn sniffer(res: WifiResources<'static>) -> Result<(Self, sniffer::Sniffer), WifiError> {
+        let (controller, interfaces) =
+            wifi::new(radio::controller()?, res.wifi, Default::default())?;
+        let sniffer = sniffer::Sniffer::new(interfaces.sniffer, interfaces.esp_now);
+        Ok((Self { controller }, sniffer))
+    }
+
+    /// Open an access point named `ssid`, replacing whatever the radio was
+    /// doing. Pass an empty `password` for an open network; otherwise it
+    /// needs at least 8 characters for WPA2.
+    pub async fn start_access_point(
+        &mut self,
+        ssid: &str,
+        password: &str,
+    ) -> Result<(), WifiError> {
+        let auth_method = if password.is_empty() {
+            AuthMethod::None
+        } else {
+            AuthMethod::Wpa2Personal
+        };
+        let config = AccessPointConfig::default()
+            .with_ssid(ssid.into())
+            .with_password(password.into())
+            .with_auth_method(auth_method);
+        if self.controller.is_started()? {
+            self.controller.stop_async().await?;
+        }
+        self.controller
+            .set_config(&ModeConfig::AccessPoint(config))?;
+        self.controller.start_async().await?;
+        info!("wifi: access point {} up", ssid);
+        Ok(())
+    }
+
+    /// Turn the radio on in station mode without joining anything, if it
+    /// isn't on already. [`scan`](Self::scan) and [`connect`](Self::connect)
+    /// do this themselves.
+    pub async fn start(&mut self) -> Result<(), WifiError> {
+        if !self.controller.is_started()? {
+            self.controller
+                .set_config(&ModeConfig::Client(ClientConfig::default()))?;
+            self.controller.start_async().await?;
+        }
+        Ok(())
+    }
+
+    /// Look for access points, strongest first, at most `max` of them.
+    pub async fn scan(&mut self, max: usize) -> Result<Vec<AccessPointInfo>, WifiError> {
+        self.start().await?;
+        let mut found = self
+            .controller
+            .scan_with_config_async(ScanConfig::default().with_max(max))
+            .await?;
+        found.sort_unstable_by_key(|ap| core::cmp::Reverse(ap.signal_strength));
+        Ok(found)
+    }
+
+    /// Scan for every network, hidden ones too, and sum them up per channel.
+    /// Networks come strongest first, as from [`scan`](Self::scan).
+    pub async fn survey(&mut self) -> Result<(Vec<AccessPointInfo>, Survey), WifiError> {
+        self.start().await?;
+        let mut found = self
+            .controller
+            .scan_with_config_async(ScanConfig::default().with_show_hidden(true))
+            .await?;
+        found.sort_unstable_by_key(|ap| core::cmp::Reverse(ap.signal_strength));
+        let survey = Survey::new(&found);
+        Ok((found, survey))
+    }
+
+    /// Join `ssid`, waiting until connected. Pass an empty `password` for
+    /// an open network.
+    pub async fn connect(&mut self, ssid: &str, password: &str) -> Result<(), WifiError> {
+        let config = ClientConfig::default()
+            .with_ssid(ssid.into())
+            .with_password(password.into());
+        if self.controller.is_started()? {
+            self.controller.disconnect_async().await.ok();
+        }
+        self.controller.set_config(&ModeConfig::Client(config))?;
+        if !self.controller.is_started()? {
+            self.controller.start_async().await?;
+        }
+        self.controller.connect_async().await?;
+        info!("wifi: connected to {}", ssid);
+        Ok(())
+    }
+
+    pub async fn disconnect(&mut self) -> Result<(), WifiError> {
+        Ok(self.controller.disconnect_async().await?)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        wifi::sta_state() == WifiStaState::Connected
+    }
+
+    /// Signal strength of the access point in dBm, while connected.
+    pub fn rssi(&self) -> Option<i32> {
+        self.controller.rssi().ok()
+    }
+
+    /// Join `ssid` and rejoin whenever the connection drops, forever.
+    ///
+    /// Failed attempts are retried every [`RECONNECT_DELAY`]. The radio's
+    /// power saving follows the [power budget](crate::power), through
+    /// [`apply`](Self::apply), as it changes.
+    pub async fn stay_connected(&mut self, ssid: &str, password: &str) -> ! {
+        let mut budget = power::BUDGET.register();
+        loop {
+            if self.is_connected() {
+                let disconnected = self.controller.wait_for_event(WifiEvent::StaDisconnected);
+                let changed = async {
+                    match &mut budget {
+                        Some(budget) => budget.changed().await,
+                        None => core::future::pending().await,
+                    }
+                };
+                if let Either::Second(allowance) = select(disconnected, changed).await {
+                    if let Err(err) = self.apply(&allowance) {
+                        warn!("wifi: power saving failed: {}", err);
+                    }
+                    continue;
+                }
+                warn!("wifi: disconnected");
+                Timer::after(RECONNECT_DELAY).await;
+            }
+            match self.connect(ssid, password).await {
+                Ok(()) => {
+                    if let Err(err) = self.apply(&power::BUDGET.allowance()) {
+                        warn!("wifi: power saving failed: {}", err);
+                    }
+                }
+                Err(err) => {
+                    warn!("wifi: connecting to {} failed: {}", ssid, err);
+                    Timer::after(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    }
+
+    /// Sleep the radio between beacons according to the power budget's
+    /// [`radio_duty_pct`](Allowance::radio_duty_pct).
+    pub fn apply(&mut self, allowance: &Allowance) -> Result<(), WifiError> {
+        let mode = match allowance.radio_duty_pct {
+            100.. => PowerSaveMode::None,
+            50..=99 => PowerSaveMode::Minimum,
+            _ => PowerSaveMode::Maximum,
+        };
+        Ok(self.controller.set_power_saving(mode)?)
+    }
+
+    /// The underlying driver, for anything not covered here.
+    pub fn controller(&mut self) -> &mut WifiController<'static> {
+        &mut self.controller
+    }
+}
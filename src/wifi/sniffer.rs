@@ -0,0 +1,335 @@
+//! Every Wi-Fi frame in the air, behind the `sniffer` feature: the radio in
+//! promiscuous mode, for "packets in the air" visualisers and deauth
+//! detectors.
+//!
+//! ```rust,ignore
+//! let (mut wifi, mut sniffer) = Wifi::sniffer(resources.wifi)?;
+//! wifi.start().await?;
+//! sniffer.start()?;
+//! sniffer.hop(&CHANNELS, Duration::from_millis(250));
+//! loop {
+//!     let packet = sniffer.next().await;
+//!     if packet.management() == Some(Management::Deauthentication) {
+//!         warn!("deauth from {=[u8]:02x} on {}", packet.addr2().unwrap_or_default(), packet.channel);
+//!     }
+//! }
+//! ```
+//!
+//! The driver hands over frames from its own task, where they are copied,
+//! up to [`CAPTURE_LEN`] bytes each, into a queue of [`PACKET_QUEUE`]. When
+//! the queue is full new frames are [dropped](Sniffer::dropped) rather
+//! than holding up the radio, so read them promptly; a busy channel brings
+//! thousands a second. Frames that failed their checksum are left out.
+//!
+//! The radio listens on one channel at a time. [`Sniffer::set_channel`]
+//! stays on one; [`Sniffer::hop`] cycles through a list while
+//! [`next`](Sniffer::next) waits. The channel is set through ESP-NOW, the
+//! only way `esp-radio` offers, which is why `sniffer` includes `esp-now`.
+//! Don't connect or scan while sniffing, since either moves the radio.
+
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering,
+};
+
+use embassy_futures::select::{
+    Either,
+    select,
+};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    channel::Channel,
+};
+use embassy_time::{
+    Duration,
+    Instant,
+    Timer,
+};
+use esp_radio::{
+    esp_now::EspNow,
+    wifi::{
+        self,
+        PromiscuousPkt,
+    },
+};
+
+use super::WifiError;
+
+/// Bytes kept of each frame, from the start of its MAC header: the header
+/// and the start of the body, enough for a beacon's SSID.
+pub const CAPTURE_LEN: usize = 128;
+/// Frames waiting to be read before new ones are dropped.
+pub const PACKET_QUEUE: usize = 16;
+/// The 2.4 GHz channels used in Finland and the rest of Europe.
+pub const CHANNELS: [u8; 13] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13];
+
+/// Length of the MAC header of management and data frames without QoS.
+const HEADER_LEN: usize = 24;
+
+static PACKETS: Channel<CriticalSectionRawMutex, Packet, PACKET_QUEUE> = Channel::new();
+static DROPPED: AtomicU32 = AtomicU32::new(0);
+
+/// What sort of frame the driver says a [`Packet`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Kind {
+    /// Beacons, probes, (de)authentication and the like; see
+    /// [`Packet::management`].
+    Management,
+    /// Acknowledgements, RTS/CTS and block acks.
+    Control,
+    Data,
+    /// Anything the driver couldn't make out, such as HT frames it doesn't
+    /// decode.
+    Misc,
+}
+
+/// Subtype of a [`Kind::Management`] frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Management {
+    AssociationRequest,
+    AssociationResponse,
+    ReassociationRequest,
+    ReassociationResponse,
+    ProbeRequest,
+    ProbeResponse,
+    Beacon,
+    Disassociation,
+    Authentication,
+    Deauthentication,
+    Action,
+    Other(u8),
+}
+
+impl Management {
+    const fn from_subtype(subtype: u8) -> Self {
+        match subtype {
+            0 => Self::AssociationRequest,
+            1 => Self::AssociationResponse,
+            2 => Self::ReassociationRequest,
+            3 => Self::ReassociationResponse,
+            4 => Self::ProbeRequest,
+            5 => Self::ProbeResponse,
+            8 => Self::Beacon,
+            10 => Self::Disassociation,
+            11 => Self::Authentication,
+            12 => Self::Deauthentication,
+            13 => Self::Action,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// A frame heard in promiscuous mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Packet {
+    pub kind: Kind,
+    /// Signal strength in dBm.
+    pub rssi: i8,
+    /// Channel it was heard on.
+    pub channel: u8,
+    /// Length on the air, including the checksum; [`data`](Self::data)
+    /// may hold less.
+    pub len: u16,
+    captured: u8,
+    data: [u8; CAPTURE_LEN],
+}
+
+impl Packet {
+    /// The first [`CAPTURE_LEN`] bytes of the frame, from its MAC header.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..usize::from(self.captured)]
+    }
+
+    /// The frame control field: protocol version, type, subtype and flags.
+    pub fn frame_control(&self) -> Option<u16> {
+        let (bytes, _) = self.data().split_first_chunk()?;
+        Some(u16::from_le_bytes(*bytes))
+    }
+
+    /// Subtype, if this is a management frame.
+    pub fn management(&self) -> Option<Management> {
+        if self.kind != Kind::Management {
+            return None;
+        }
+        let subtype = (self.frame_control()? >> 4) & 0xf;
+        Some(Management::from_subtype(subtype as u8))
+    }
+
+    /// Receiver address.
+    pub fn addr1(&self) -> Option<[u8; 6]> {
+        self.address(4)
+    }
+
+    /// Transmitter address. Acknowledgements and CTS frames have none.
+    pub fn addr2(&self) -> Option<[u8; 6]> {
+        self.address(10)
+    }
+
+    /// BSSID of a management frame; for data frames it depends on the
+    /// direction bits. Control frames have none.
+    pub fn addr3(&self) -> Option<[u8; 6]> {
+        if self.kind == Kind::Control {
+            return None;
+        }
+        self.address(16)
+    }
+
+    /// Reason code of a deauthentication or disassociation.
+    pub fn reason(&self) -> Option<u16> {
+        match self.management()? {
+            Management::Deauthentication | Management::Disassociation => {
+                let (bytes, _) = self.data().get(HEADER_LEN..)?.split_first_chunk()?;
+                Some(u16::from_le_bytes(*bytes))
+            }
+            _ => None,
+        }
+    }
+
+    fn address(&self, offset: usize) -> Option<[u8; 6]> {
+        let (mac, _) = self.data().get(offset..)?.split_first_chunk()?;
+        Some(*mac)
+    }
+}
+
+impl defmt::Format for Packet {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "Packet {{ kind: {}, rssi: {}, channel: {}, len: {} }}",
+            self.kind,
+            self.rssi,
+            self.channel,
+            self.len
+        );
+    }
+}
+
+struct Hopping {
+    channels: &'static [u8],
+    dwell: Duration,
+    next: usize,
+    at: Instant,
+}
+
+/// The radio in promiscuous mode, from [`Wifi::sniffer`](super::Wifi::sniffer).
+/// See the [module docs](self).
+pub struct Sniffer {
+    sniffer: wifi::Sniffer<'static>,
+    esp_now: EspNow<'static>,
+    channel: Option<u8>,
+    hopping: Option<Hopping>,
+}
+
+impl Sniffer {
+    pub(super) const fn new(sniffer: wifi::Sniffer<'static>, esp_now: EspNow<'static>) -> Self {
+        Self {
+            sniffer,
+            esp_now,
+            channel: None,
+            hopping: None,
+        }
+    }
+
+    /// Start capturing frames. The radio must be
+    /// [`start`](super::Wifi::start)ed.
+    pub fn start(&mut self) -> Result<(), WifiError> {
+        self.sniffer.set_receive_cb(received);
+        Ok(self.sniffer.set_promiscuous_mode(true)?)
+    }
+
+    /// Stop capturing; frames already queued can still be read.
+    pub fn stop(&mut self) -> Result<(), WifiError> {
+        Ok(self.sniffer.set_promiscuous_mode(false)?)
+    }
+
+    /// Listen on `channel` only, stopping any [`hop`](Self::hop).
+    pub fn set_channel(&mut self, channel: u8) -> Result<(), WifiError> {
+        self.hopping = None;
+        self.tune(channel)
+    }
+
+    /// Move through `channels` in turn, `dwell` on each, while
+    /// [`next`](Self::next) waits. The first comes on the next call.
+    pub fn hop(&mut self, channels: &'static [u8], dwell: Duration) {
+        self.hopping = (!channels.is_empty()).then(|| Hopping {
+            channels,
+            dwell,
+            next: 0,
+            at: Instant::now(),
+        });
+    }
+
+    /// Channel set last, if any.
+    pub const fn channel(&self) -> Option<u8> {
+        self.channel
+    }
+
+    /// Frames dropped since boot because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        DROPPED.load(Ordering::Relaxed)
+    }
+
+    /// Wait for the next frame, hopping channel on the way if asked to.
+    ///
+    /// A channel that can't be set is logged and skipped.
+    pub async fn next(&mut self) -> Packet {
+        loop {
+            let Some(hopping) = &mut self.hopping else {
+                return PACKETS.receive().await;
+            };
+            if Instant::now() >= hopping.at {
+                let channel = hopping.channels[hopping.next];
+                hopping.next = (hopping.next + 1) % hopping.channels.len();
+                hopping.at = Instant::now() + hopping.dwell;
+                if let Err(err) = self.tune(channel) {
+                    defmt::warn!("sniffer: {}", err);
+                }
+                continue;
+            }
+            if let Either::First(packet) = select(PACKETS.receive(), Timer::at(hopping.at)).await {
+                return packet;
+            }
+        }
+    }
+
+    /// The next frame, if one is queued.
+    pub fn try_next(&self) -> Option<Packet> {
+        PACKETS.try_receive().ok()
+    }
+
+    fn tune(&mut self, channel: u8) -> Result<(), WifiError> {
+        self.esp_now
+            .set_channel(channel)
+            .map_err(|_| WifiError::Channel(channel))?;
+        self.channel = Some(channel);
+        Ok(())
+    }
+}
+
+/// Runs in the Wi-Fi driver's task for every frame heard.
+fn received(packet: PromiscuousPkt<'_>) {
+    let info = &packet.rx_cntl;
+    if info.rx_state != 0 {
+        return;
+    }
+    let kind = match packet.frame_type {
+        0 => Kind::Management,
+        1 => Kind::Control,
+        2 => Kind::Data,
+        _ => Kind::Misc,
+    };
+    let captured = packet.data.len().min(CAPTURE_LEN);
+    let mut data = [0; CAPTURE_LEN];
+    data[..captured].copy_from_slice(&packet.data[..captured]);
+    let packet = Packet {
+        kind,
+        rssi: i8::try_from(info.rssi).unwrap_or(i8::MIN),
+        channel: info.channel as u8,
+        len: u16::try_from(packet.len).unwrap_or(u16::MAX),
+        captured: captured as u8,
+        data,
+    };
+    if PACKETS.try_send(packet).is_err() {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,247 @@
+//! How crowded each 2.4 GHz channel is, from a scan of the networks
+//! around: a site survey without promiscuous capture.
+//!
+//! ```rust,ignore
+//! let (found, survey) = wifi.survey().await?;
+//! for ap in &found {
+//!     info!("{} ch {} {} dBm {}", ap.ssid.as_str(), ap.channel, ap.signal_strength, survey::security(ap.auth_method));
+//! }
+//! info!("quietest: {}", survey.quietest().channel);
+//! survey::render(&found, &survey, display)?;
+//! ```
+//!
+//! A network on one channel spills [`OVERLAP`] channels to each side, so
+//! [`ChannelStats::utilization`] adds up every network near a channel,
+//! weighted by how strong it is and how far off it sits. It's an estimate
+//! from beacons, not measured airtime: an idle access point counts as much
+//! as a busy one. The `sniffer` feature counts actual frames.
+
+use alloc::{
+    format,
+    string::String,
+};
+
+use embedded_graphics::{
+    mono_font::{
+        MonoTextStyle,
+        ascii::FONT_6X10,
+        iso_8859_1::FONT_10X20,
+    },
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{
+        PrimitiveStyle,
+        Rectangle,
+    },
+    text::{
+        Alignment,
+        Text,
+    },
+};
+use esp_radio::wifi::{
+    AccessPointInfo,
+    AuthMethod,
+};
+
+use crate::{
+    DISPLAY_HEIGHT,
+    DISPLAY_WIDTH,
+};
+
+/// Channels surveyed, 1 to 13 as used in Europe.
+pub const CHANNELS: usize = 13;
+/// Channels a 20 MHz network reaches on each side of its own.
+pub const OVERLAP: u8 = 4;
+/// Signal, in dBm, at or below which a network doesn't count.
+pub const NOISE_FLOOR: i8 = -95;
+/// Signal, in dBm, at or above which a network counts in full.
+pub const STRONG: i8 = -35;
+/// Full-strength networks on one channel that make it count as full.
+pub const SATURATION: f32 = 4.0;
+
+/// Channels that don't overlap each other; [`Survey::quietest`] picks one.
+const CLEAR_CHANNELS: [u8; 3] = [1, 6, 11];
+
+/// What a [`Survey`] found on one channel.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct ChannelStats {
+    pub channel: u8,
+    /// Networks on this channel.
+    pub networks: u8,
+    /// Networks on neighbouring channels close enough to interfere.
+    pub overlapping: u8,
+    /// Strongest signal of the networks on this channel, in dBm.
+    pub strongest: Option<i8>,
+    /// How crowded the channel is, from 0 (empty) to 1 (full).
+    pub utilization: f32,
+}
+
+impl ChannelStats {
+    const fn new(channel: u8) -> Self {
+        Self {
+            channel,
+            networks: 0,
+            overlapping: 0,
+            strongest: None,
+            utilization: 0.0,
+        }
+    }
+}
+
+/// Per-channel statistics from one scan. See the [module docs](self).
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Survey {
+    channels: [ChannelStats; CHANNELS],
+}
+
+impl Survey {
+    /// Sum up `networks`, as [`Wifi::scan`](super::Wifi::scan) returns
+    /// them. Networks on channels 14 and up are left out.
+    pub fn new(networks: &[AccessPointInfo]) -> Self {
+        let mut channels: [ChannelStats; CHANNELS] =
+            core::array::from_fn(|index| ChannelStats::new(index as u8 + 1));
+        let mut load = [0.0; CHANNELS];
+        for ap in networks {
+            if !(1..=CHANNELS as u8).contains(&ap.channel) {
+                continue;
+            }
+            let weight = weight(ap.signal_strength);
+            for (stats, load) in channels.iter_mut().zip(&mut load) {
+                let distance = stats.channel.abs_diff(ap.channel);
+                if distance == 0 {
+                    stats.networks = stats.networks.saturating_add(1);
+                    stats.strongest = stats.strongest.max(Some(ap.signal_strength));
+                } else if distance <= OVERLAP {
+                    stats.overlapping = stats.overlapping.saturating_add(1);
+                } else {
+                    continue;
+                }
+                *load += weight * (1.0 - f32::from(distance) / f32::from(OVERLAP + 1));
+            }
+        }
+        for (stats, load) in channels.iter_mut().zip(load) {
+            stats.utilization = (load / SATURATION).min(1.0);
+        }
+        Self { channels }
+    }
+
+    /// Statistics for `channel`, 1 to [`CHANNELS`].
+    pub fn channel(&self, channel: u8) -> Option<&ChannelStats> {
+        self.channels.get(usize::from(channel).checked_sub(1)?)
+    }
+
+    /// Every channel, from 1 up.
+    pub fn iter(&self) -> impl Iterator<Item = &ChannelStats> {
+        self.channels.iter()
+    }
+
+    /// The least crowded of channels 1, 6 and 11, the ones that don't
+    /// overlap: the best pick for a new access point.
+    pub fn quietest(&self) -> &ChannelStats {
+        CLEAR_CHANNELS
+            .iter()
+            .filter_map(|&channel| self.channel(channel))
+            .min_by(|a, b| a.utilization.total_cmp(&b.utilization))
+            .unwrap_or(&self.channels[0])
+    }
+}
+
+/// How much a network heard at `rssi` dBm counts, from 0 to 1.
+fn weight(rssi: i8) -> f32 {
+    let span = f32::from(STRONG) - f32::from(NOISE_FLOOR);
+    ((f32::from(rssi) - f32::from(NOISE_FLOOR)) / span).clamp(0.0, 1.0)
+}
+
+/// Short name of the encryption a network uses, for lists.
+pub const fn security(auth: Option<AuthMethod>) -> &'static str {
+    match auth {
+        Some(AuthMethod::None) => "open",
+        Some(AuthMethod::Wep) => "WEP",
+        Some(AuthMethod::Wpa) => "WPA",
+        Some(AuthMethod::Wpa2Personal) => "WPA2",
+        Some(AuthMethod::WpaWpa2Personal) => "WPA/2",
+        Some(AuthMethod::Wpa2Enterprise) => "WPA2-E",
+        Some(AuthMethod::Wpa3Personal) => "WPA3",
+        Some(AuthMethod::Wpa2Wpa3Personal) => "WPA2/3",
+        Some(AuthMethod::WapiPersonal) => "WAPI",
+        _ => "?",
+    }
+}
+
+/// Draw the strongest `networks` with their channel, signal and
+/// encryption above a bar per channel of the `survey`, green to red with
+/// utilization and the [quietest](Survey::quietest) one marked.
+pub fn render<D>(
+    networks: &[AccessPointInfo],
+    survey: &Survey,
+    target: &mut D,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    target.clear(Rgb565::BLACK)?;
+    let title = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let heading = format!("{} networks", networks.len());
+    Text::new(&heading, Point::new(8, 18), title).draw(target)?;
+    let quietest = survey.quietest().channel;
+    let small = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_GRAY);
+    Text::with_alignment(
+        &format!("best ch {quietest}"),
+        Point::new(i32::from(DISPLAY_WIDTH) - 8, 16),
+        small,
+        Alignment::Right,
+    )
+    .draw(target)?;
+
+    let row = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let open = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_ORANGE);
+    for (index, ap) in networks.iter().take(6).enumerate() {
+        let y = 34 + 11 * index as i32;
+        let ssid = match ap.ssid.as_str() {
+            "" => "(hidden)",
+            ssid => ssid,
+        };
+        let style = if ap.auth_method == Some(AuthMethod::None) {
+            open
+        } else {
+            row
+        };
+        let name: String = ssid.chars().take(24).collect();
+        Text::new(&name, Point::new(8, y), style).draw(target)?;
+        let details = format!(
+            "ch{:<2} {:>4} {}",
+            ap.channel,
+            ap.signal_strength,
+            security(ap.auth_method)
+        );
+        Text::new(&details, Point::new(168, y), style).draw(target)?;
+    }
+
+    let bottom = i32::from(DISPLAY_HEIGHT) - 12;
+    let width = (i32::from(DISPLAY_WIDTH) - 16) / CHANNELS as i32;
+    for (index, stats) in survey.iter().enumerate() {
+        let x = 8 + width * index as i32;
+        let height = 4 + (stats.utilization * 40.0) as u32;
+        let red = (stats.utilization * 31.0) as u8;
+        let color = Rgb565::new(red, 63 - red * 2, 0);
+        Rectangle::new(
+            Point::new(x + 2, bottom - 2 - height as i32),
+            Size::new(width as u32 - 4, height),
+        )
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(target)?;
+        let label = if stats.channel == quietest {
+            MonoTextStyle::new(&FONT_6X10, Rgb565::CYAN)
+        } else {
+            small
+        };
+        Text::with_alignment(
+            &format!("{}", stats.channel),
+            Point::new(x + width / 2, bottom + 9),
+            label,
+            Alignment::Center,
+        )
+        .draw(target)?;
+    }
+    Ok(())
+}